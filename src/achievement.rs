@@ -0,0 +1,248 @@
+use crate::character::class::Class;
+use crate::faction::Faction;
+use crate::game::Game;
+use crate::hook;
+use crate::item::ring::Ring;
+use crate::log;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Long-term goals of the player, tracked across heroes and games.
+/// Unlike quests, achievements are never locked, and they're only rewarded
+/// with a cosmetic title.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct Achievements {
+    bosses_beaten: i32,
+    chests_opened: i32,
+    deaths: i32,
+    goblins_killed: i32,
+    tombs_raided: i32,
+
+    /// Deaths of heroes carrying no gold.
+    penniless_deaths: i32,
+
+    /// Rings ever added to the inventory, upgraded ones count as the base ring.
+    rings_found: HashSet<Ring>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Achievement {
+    FirstBoss,
+    ChestHunter,
+    Persistent,
+    RingCollector,
+    GoblinSlayer,
+    TombRaider,
+    Penniless,
+}
+
+impl Achievement {
+    fn all() -> [Self; 7] {
+        [
+            Achievement::FirstBoss,
+            Achievement::ChestHunter,
+            Achievement::Persistent,
+            Achievement::RingCollector,
+            Achievement::GoblinSlayer,
+            Achievement::TombRaider,
+            Achievement::Penniless,
+        ]
+    }
+
+    pub fn description(&self) -> String {
+        let goal = self.goal();
+        match self {
+            Achievement::FirstBoss => String::from("beat a boss"),
+            Achievement::ChestHunter => format!("open {} chests", goal),
+            Achievement::Persistent => format!("die {} times", goal),
+            Achievement::RingCollector => String::from("collect all rings"),
+            Achievement::GoblinSlayer => format!("kill {} goblins", goal),
+            Achievement::TombRaider => format!("raid {} tombstones", goal),
+            Achievement::Penniless => String::from("die without a coin"),
+        }
+    }
+
+    /// The cosmetic title earned by unlocking the achievement.
+    pub fn title(&self) -> &'static str {
+        match self {
+            Achievement::FirstBoss => "Boss Hunter",
+            Achievement::ChestHunter => "Treasure Hunter",
+            Achievement::Persistent => "Undying",
+            Achievement::RingCollector => "Ring Lord",
+            Achievement::GoblinSlayer => "Goblin Slayer",
+            Achievement::TombRaider => "Tomb Raider",
+            Achievement::Penniless => "Penniless",
+        }
+    }
+
+    fn goal(&self) -> i32 {
+        match self {
+            Achievement::FirstBoss => 1,
+            Achievement::ChestHunter => 100,
+            Achievement::Persistent => 10,
+            Achievement::RingCollector => Ring::found_set().len() as i32,
+            Achievement::GoblinSlayer => 50,
+            Achievement::TombRaider => 10,
+            Achievement::Penniless => 1,
+        }
+    }
+}
+
+// EVENT TRIGGERING FUNCTIONS
+
+pub fn boss_beaten(game: &mut Game) {
+    update(game, |achievements| achievements.bosses_beaten += 1);
+}
+
+pub fn chest_opened(game: &mut Game) {
+    update(game, |achievements| achievements.chests_opened += 1);
+}
+
+/// Account for the death of the hero, given whether it carried no gold.
+pub fn died(game: &mut Game, penniless: bool) {
+    update(game, |achievements| {
+        achievements.deaths += 1;
+        if penniless {
+            achievements.penniless_deaths += 1;
+        }
+    });
+}
+
+pub fn enemy_killed(game: &mut Game, class: &Class) {
+    if Faction::of(class) == Some(Faction::Goblins) {
+        update(game, |achievements| achievements.goblins_killed += 1);
+    }
+}
+
+pub fn tomb_raided(game: &mut Game) {
+    update(game, |achievements| achievements.tombs_raided += 1);
+}
+
+pub fn ring_found(game: &mut Game, ring: &Ring) {
+    update(game, |achievements| {
+        // bought rings aren't collected
+        if !ring.is_bought() {
+            achievements.rings_found.insert(ring.base());
+        }
+    });
+}
+
+/// Apply the change to the game achievements, logging the ones it unlocks.
+fn update(game: &mut Game, change: impl FnOnce(&mut Achievements)) {
+    let before = game.achievements.unlocked();
+    change(&mut game.achievements);
+    for achievement in game.achievements.unlocked() {
+        if !before.contains(&achievement) {
+            log::achievement_unlocked(&achievement);
+            log::title_earned(achievement.title());
+            hook::achievement(&achievement);
+        }
+    }
+}
+
+impl Achievements {
+    /// The current count towards the goal of the given achievement,
+    /// capped at the goal.
+    fn progress(&self, achievement: &Achievement) -> i32 {
+        let count = match achievement {
+            Achievement::FirstBoss => self.bosses_beaten,
+            Achievement::ChestHunter => self.chests_opened,
+            Achievement::Persistent => self.deaths,
+            Achievement::RingCollector => self.rings_found.len() as i32,
+            Achievement::GoblinSlayer => self.goblins_killed,
+            Achievement::TombRaider => self.tombs_raided,
+            Achievement::Penniless => self.penniless_deaths,
+        };
+        std::cmp::min(count, achievement.goal())
+    }
+
+    pub fn unlocked(&self) -> Vec<Achievement> {
+        Achievement::all()
+            .iter()
+            .filter(|achievement| self.progress(achievement) == achievement.goal())
+            .copied()
+            .collect()
+    }
+
+    /// Each achievement paired with its progress and goal.
+    pub fn list(&self) -> Vec<(Achievement, i32, i32)> {
+        Achievement::all()
+            .iter()
+            .map(|achievement| (*achievement, self.progress(achievement), achievement.goal()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress() {
+        let mut game = Game::new();
+        for _ in 0..99 {
+            chest_opened(&mut game);
+        }
+        died(&mut game, false);
+        assert!(game.achievements.unlocked().is_empty());
+
+        chest_opened(&mut game);
+        chest_opened(&mut game);
+        boss_beaten(&mut game);
+        assert_eq!(
+            vec![Achievement::FirstBoss, Achievement::ChestHunter],
+            game.achievements.unlocked()
+        );
+
+        let list = game.achievements.list();
+        assert_eq!((Achievement::ChestHunter, 100, 100), list[1]);
+        assert_eq!((Achievement::Persistent, 1, 10), list[2]);
+
+        // upgraded rings don't count twice
+        ring_found(&mut game, &Ring::Attack);
+        ring_found(&mut game, &Ring::AttackPlus);
+        let (_, found, total) = game.achievements.list()[3];
+        assert_eq!(1, found);
+        assert_eq!(Ring::found_set().len() as i32, total);
+        ring_found(&mut game, &Ring::Vampire);
+        assert_eq!(1, game.achievements.list()[3].1);
+        for ring in Ring::found_set() {
+            ring_found(&mut game, &ring);
+        }
+        assert!(game
+            .achievements
+            .unlocked()
+            .contains(&Achievement::RingCollector));
+
+        // achievements are kept across heroes
+        game.reset();
+        assert_eq!(3, game.achievements.unlocked().len());
+    }
+
+    #[test]
+    fn test_titles() {
+        let mut game = Game::new();
+        let orc = Class::enemy_by_name("orc").unwrap();
+        let rat = Class::enemy_by_name("rat").unwrap();
+        for _ in 0..49 {
+            enemy_killed(&mut game, orc);
+            enemy_killed(&mut game, rat);
+        }
+        for _ in 0..10 {
+            tomb_raided(&mut game);
+        }
+        died(&mut game, false);
+        assert_eq!(vec![Achievement::TombRaider], game.achievements.unlocked());
+
+        enemy_killed(&mut game, orc);
+        died(&mut game, true);
+        let titles: Vec<&str> = game
+            .achievements
+            .unlocked()
+            .iter()
+            .map(Achievement::title)
+            .collect();
+        assert_eq!(vec!["Goblin Slayer", "Tomb Raider", "Penniless"], titles);
+    }
+}
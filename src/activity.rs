@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+#[cfg(not(feature = "wasm"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+// the std clock isn't available in the browser
+#[cfg(feature = "wasm")]
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// Minimum seconds between two tracked invocations, so a burst of shell
+/// commands counts as a single one.
+const TICK_INTERVAL: u64 = 60;
+
+/// Maximum amount of tracked invocations that accrue xp within an hour.
+const MAX_TICKS_PER_HOUR: i32 = 20;
+
+/// A potion reward is queued every time this many invocations are tracked.
+const TICKS_PER_REWARD: i32 = 50;
+
+/// Accounting of the player's shell activity for the opt-in passive training
+/// mode. When enabled, the shell hook invocations accrue a small amount of xp
+/// and occasional rewards, which are kept pending until the player claims them.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Activity {
+    pub enabled: bool,
+
+    /// Timestamp of the last invocation that accrued xp.
+    last_tick: u64,
+
+    /// Start of the current hour window and the ticks counted within it.
+    hour_start: u64,
+    hour_ticks: i32,
+
+    /// Total ticks counted since the last reward was queued.
+    ticks: i32,
+
+    pub pending_xp: i32,
+    pub pending_potions: i32,
+}
+
+impl Activity {
+    /// Track a shell hook invocation at the given time, accruing the given xp
+    /// if the activity mode is enabled and the anti-abuse caps allow it.
+    /// Return whether the invocation was counted.
+    pub fn tick(&mut self, now: u64, xp: i32) -> bool {
+        if !self.enabled || now < self.last_tick + TICK_INTERVAL {
+            return false;
+        }
+
+        if now >= self.hour_start + 3600 {
+            self.hour_start = now;
+            self.hour_ticks = 0;
+        }
+        if self.hour_ticks >= MAX_TICKS_PER_HOUR {
+            return false;
+        }
+
+        self.last_tick = now;
+        self.hour_ticks += 1;
+        self.pending_xp += xp;
+
+        self.ticks += 1;
+        if self.ticks >= TICKS_PER_REWARD {
+            self.ticks = 0;
+            self.pending_potions += 1;
+        }
+        true
+    }
+
+    /// Take the accrued (xp, potions) rewards, leaving nothing pending.
+    pub fn claim(&mut self) -> (i32, i32) {
+        let rewards = (self.pending_xp, self.pending_potions);
+        self.pending_xp = 0;
+        self.pending_potions = 0;
+        rewards
+    }
+}
+
+/// Seconds in a real-time day, for the features that reset daily.
+pub const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Current unix timestamp in seconds.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disabled_by_default() {
+        let mut activity = Activity::default();
+        assert!(!activity.tick(1000, 5));
+        assert_eq!((0, 0), activity.claim());
+    }
+
+    #[test]
+    fn tick_interval_and_hourly_cap() {
+        let mut activity = Activity {
+            enabled: true,
+            ..Activity::default()
+        };
+
+        let mut now = 10_000;
+        assert!(activity.tick(now, 2));
+        // too soon, not counted
+        assert!(!activity.tick(now + 10, 2));
+        assert_eq!(2, activity.pending_xp);
+
+        for _ in 1..MAX_TICKS_PER_HOUR {
+            now += TICK_INTERVAL;
+            assert!(activity.tick(now, 2));
+        }
+        now += TICK_INTERVAL;
+        assert!(!activity.tick(now, 2));
+        assert_eq!(2 * MAX_TICKS_PER_HOUR, activity.pending_xp);
+
+        // next hour counts again
+        assert!(activity.tick(10_000 + 3600, 2));
+        assert_eq!((2 * MAX_TICKS_PER_HOUR + 2, 0), activity.claim());
+        assert_eq!((0, 0), activity.claim());
+    }
+
+    #[test]
+    fn queue_potions() {
+        let mut activity = Activity {
+            enabled: true,
+            ..Activity::default()
+        };
+
+        let mut now = 0;
+        for _ in 0..TICKS_PER_REWARD {
+            now += 3600;
+            activity.tick(now, 1);
+        }
+        assert_eq!(1, activity.pending_potions);
+    }
+}
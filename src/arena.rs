@@ -0,0 +1,186 @@
+use crate::character::enemy;
+use crate::game::Game;
+use crate::item::equipment::Equipment;
+use crate::item::key::Key;
+use crate::item::ring::Ring;
+use crate::log;
+use anyhow::{anyhow, bail, Result};
+use core::fmt;
+
+/// Arena tokens exchanged for the vampire ring.
+const RING_TOKENS: i32 = 150;
+
+/// Arena tokens exchanged for each piece of arena equipment.
+const EQUIPMENT_TOKENS: i32 = 40;
+
+/// Levels above the hero's of the arena equipment.
+const EQUIPMENT_BONUS: i32 = 15;
+
+/// The exclusive items exchanged for arena tokens.
+#[derive(Debug, Clone)]
+pub enum Reward {
+    Ring(Ring),
+    Equipment(Equipment),
+}
+
+impl Reward {
+    pub fn key(&self) -> Key {
+        match self {
+            Reward::Ring(ring) => Key::Ring(ring.clone()),
+            Reward::Equipment(piece) => piece.key(),
+        }
+    }
+}
+
+impl fmt::Display for Reward {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reward::Ring(ring) => write!(f, "{}", ring),
+            Reward::Equipment(piece) => write!(f, "{}", piece),
+        }
+    }
+}
+
+/// Fight waves of enemies at home, each one stronger than the last, until
+/// the hero is beaten. There's no running away, but losing isn't deadly.
+/// Beating the nth wave earns n tokens.
+pub fn fight(game: &mut Game) -> Result<()> {
+    open(game)?;
+    let mut waves = 0;
+    let mut tokens = 0;
+    loop {
+        log::arena_wave(waves + 1);
+        let mut enemies = enemy::wave(&game.location, &game.player, waves + 1, game.difficulty);
+        if !game.arena_battle(&mut enemies) {
+            break;
+        }
+        waves += 1;
+        tokens += waves;
+    }
+    game.arena_tokens += tokens;
+    let record = game.stats.arena(waves);
+    log::arena_end(waves, tokens, record);
+    Ok(())
+}
+
+/// The rewards the hero can currently exchange tokens for, with their
+/// price. The vampire ring is left out once the hero has it, and the
+/// equipment is always some levels above the hero's.
+pub fn rewards(game: &Game) -> Vec<(Reward, i32)> {
+    let mut rewards = Vec::new();
+    let ring = Ring::Vampire;
+    let worn = [&game.player.left_ring, &game.player.right_ring]
+        .iter()
+        .any(|worn| worn.as_ref() == Some(&ring));
+    if !worn && !game.inventory().contains_key(&Key::Ring(ring.clone())) {
+        rewards.push((Reward::Ring(ring), RING_TOKENS));
+    }
+
+    let level = game.player.rounded_level() + EQUIPMENT_BONUS;
+    for piece in [
+        Equipment::sword(level),
+        Equipment::shield(level),
+        Equipment::helmet(level),
+        Equipment::armor(level),
+        Equipment::boots(level),
+    ] {
+        rewards.push((Reward::Equipment(piece), EQUIPMENT_TOKENS));
+    }
+    rewards
+}
+
+/// Exchange arena tokens for the reward of the given key.
+pub fn exchange(game: &mut Game, key: &Key) -> Result<()> {
+    open(game)?;
+    let (reward, price) = rewards(game)
+        .into_iter()
+        .find(|(reward, _)| reward.key() == *key)
+        .ok_or_else(|| anyhow!("There's no {} to exchange at the arena.", key))?;
+    if game.arena_tokens < price {
+        bail!("Not enough arena tokens.");
+    }
+    match &reward {
+        Reward::Ring(_) if !game.has_room_for(key) => bail!("Not enough room in the inventory."),
+        Reward::Equipment(piece) => {
+            if let Some(worn) = game.player.equipment(key) {
+                if worn.is_cursed() {
+                    bail!("The cursed {} can't be replaced.", worn);
+                }
+                if worn.level() >= piece.level() {
+                    bail!("The {} worn is already as good.", worn);
+                }
+            }
+        }
+        _ => {}
+    }
+
+    game.arena_tokens -= price;
+    log::arena_exchange(&reward.to_string(), price);
+    match reward {
+        Reward::Ring(ring) => game.add_item(Box::new(ring)),
+        Reward::Equipment(piece) => game.player.equip(piece),
+    }
+    Ok(())
+}
+
+fn open(game: &Game) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("The arena is only open at home.");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arena_waves() {
+        let mut game = Game::new();
+        for _ in 1..10 {
+            game.player.add_experience(game.player.xp_for_next());
+        }
+        game.player.sword = Some(Equipment::sword(10));
+        game.player.restore();
+        game.add_item(Box::new(crate::item::PhoenixDown::new()));
+        let gold = game.gold;
+
+        fight(&mut game).unwrap();
+        let waves = game.stats.best_wave;
+        assert!(waves > 0);
+        assert_eq!(waves * (waves + 1) / 2, game.arena_tokens);
+        // the hero is only knocked out
+        assert_eq!(1, game.player.current_hp);
+        assert!(game.tombstones.is_empty());
+        assert!(game.gold > gold);
+        // and doesn't need to be revived
+        assert!(game.inventory().contains_key(&Key::PhoenixDown));
+    }
+
+    #[test]
+    fn exchange_rewards() {
+        let mut game = Game::new();
+        let ring = Key::Ring(Ring::Vampire);
+        assert!(!game.ring_pool.contains(&Ring::Vampire));
+        assert_eq!(6, rewards(&game).len());
+
+        assert!(exchange(&mut game, &ring).is_err());
+        game.arena_tokens = RING_TOKENS + EQUIPMENT_TOKENS;
+        assert!(exchange(&mut game, &Key::Potion).is_err());
+        exchange(&mut game, &ring).unwrap();
+        assert_eq!(1, *game.inventory().get(&ring).unwrap());
+        // only one vampire ring per hero
+        assert!(exchange(&mut game, &ring).is_err());
+        assert_eq!(5, rewards(&game).len());
+
+        exchange(&mut game, &Key::Sword).unwrap();
+        assert_eq!(0, game.arena_tokens);
+        let level = game.player.rounded_level() + EQUIPMENT_BONUS;
+        assert_eq!(level, game.player.sword.as_ref().unwrap().level());
+
+        // tokens aren't spent on equipment as good as the worn one
+        game.arena_tokens = EQUIPMENT_TOKENS;
+        assert!(exchange(&mut game, &Key::Sword).is_err());
+        assert_eq!(EQUIPMENT_TOKENS, game.arena_tokens);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::activity;
+use crate::game::Game;
+use crate::log;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// Percentage of the balance paid as interest every real-time day.
+const DAILY_INTEREST: i32 = 1;
+
+/// Days of interest paid at most, however long the gold was left untouched.
+const MAX_INTEREST_DAYS: u64 = 30;
+
+/// Gold paid per hero level for the insurance of the next death.
+const INSURANCE_PREMIUM: i32 = 50;
+
+/// Gold stored at home, which isn't lost when the hero dies.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct Bank {
+    pub balance: i32,
+
+    /// Real-time day, counted since the unix epoch, up to which the interest
+    /// was already paid. Zero until the bank is first used.
+    paid_day: u64,
+}
+
+impl Bank {
+    /// Pay the interest of the days passed since the last payment, compounded
+    /// daily, and return the gold earned.
+    pub fn accrue(&mut self, today: u64) -> i32 {
+        let days = if self.paid_day == 0 {
+            0
+        } else {
+            std::cmp::min(today.saturating_sub(self.paid_day), MAX_INTEREST_DAYS)
+        };
+        self.paid_day = today;
+
+        let before = self.balance;
+        for _ in 0..days {
+            self.balance += self.balance * DAILY_INTEREST / 100;
+        }
+        self.balance - before
+    }
+}
+
+/// Print the bank balance, after paying the pending interest.
+pub fn balance(game: &mut Game) -> Result<()> {
+    open(game)?;
+    log::bank(0, game.bank.balance);
+    Ok(())
+}
+
+/// Move the given amount of gold, or all of it, from the hero to the bank.
+pub fn deposit(game: &mut Game, amount: Option<i32>) -> Result<()> {
+    open(game)?;
+    let amount = amount.unwrap_or(game.gold);
+    if amount <= 0 {
+        bail!("Nothing to deposit.");
+    }
+    if amount > game.gold {
+        bail!("Not enough gold.");
+    }
+
+    game.gold -= amount;
+    game.bank.balance += amount;
+    log::bank(-amount, game.bank.balance);
+    Ok(())
+}
+
+/// Move the given amount of gold, or all of it, from the bank to the hero.
+pub fn withdraw(game: &mut Game, amount: Option<i32>) -> Result<()> {
+    open(game)?;
+    let amount = amount.unwrap_or(game.bank.balance);
+    if amount <= 0 {
+        bail!("Nothing to withdraw.");
+    }
+    if amount > game.bank.balance {
+        bail!("Not enough gold in the bank.");
+    }
+
+    game.bank.balance -= amount;
+    game.gold += amount;
+    log::bank(amount, game.bank.balance);
+    Ok(())
+}
+
+/// Pay the premium of the insurance that covers the next death of the hero,
+/// leaving part of what they carry at home instead of in their tomb.
+pub fn insure(game: &mut Game) -> Result<()> {
+    open(game)?;
+    if game.hardcore {
+        bail!("Hardcore heroes can't be insured.");
+    }
+    if game.insured {
+        bail!("The hero is already insured.");
+    }
+    let premium = INSURANCE_PREMIUM * game.player.level;
+    if game.gold < premium {
+        bail!("Not enough gold.");
+    }
+
+    game.gold -= premium;
+    game.stats.spend(premium);
+    game.insured = true;
+    log::insured(premium);
+    Ok(())
+}
+
+/// The bank is only available at home, and pays the pending interest every
+/// time it's used.
+fn open(game: &mut Game) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Bank is only allowed at home.");
+    }
+    let interest = game
+        .bank
+        .accrue(activity::now() / activity::SECONDS_PER_DAY);
+    if interest > 0 {
+        game.stats.earn(interest);
+        log::bank_interest(interest);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interest() {
+        let mut bank = Bank {
+            balance: 1000,
+            ..Bank::default()
+        };
+        // no interest before the bank is first used
+        assert_eq!(0, bank.accrue(100));
+        assert_eq!(0, bank.accrue(100));
+
+        assert_eq!(10 + 10, bank.accrue(102));
+        assert_eq!(1020, bank.balance);
+
+        // capped after a long time away
+        let mut capped = bank.clone();
+        bank.accrue(102 + MAX_INTEREST_DAYS);
+        capped.accrue(102 + 2 * MAX_INTEREST_DAYS);
+        assert_eq!(bank.balance, capped.balance);
+    }
+
+    #[test]
+    fn test_deposit_withdraw() {
+        let mut game = Game::new();
+        game.gold = 500;
+
+        assert!(deposit(&mut game, Some(600)).is_err());
+        deposit(&mut game, Some(200)).unwrap();
+        assert_eq!(300, game.gold);
+        deposit(&mut game, None).unwrap();
+        assert_eq!(0, game.gold);
+        assert_eq!(500, game.bank.balance);
+        assert!(deposit(&mut game, None).is_err());
+
+        withdraw(&mut game, Some(100)).unwrap();
+        assert_eq!(100, game.gold);
+        assert!(withdraw(&mut game, Some(1000)).is_err());
+
+        // the bank is kept when the hero dies
+        game.reset();
+        assert_eq!(0, game.gold);
+        assert_eq!(400, game.bank.balance);
+
+        game.location = crate::location::Location::from("/").unwrap();
+        assert!(withdraw(&mut game, None).is_err());
+    }
+
+    #[test]
+    fn test_insure() {
+        let mut game = Game::new();
+        game.gold = INSURANCE_PREMIUM + 10;
+        insure(&mut game).unwrap();
+        assert!(game.insured);
+        assert_eq!(10, game.gold);
+        assert!(insure(&mut game).is_err());
+
+        // the insurance only covers the current hero
+        game.reset();
+        assert!(!game.insured);
+
+        game.hardcore = true;
+        game.gold = INSURANCE_PREMIUM;
+        assert!(insure(&mut game).is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use crate::character::Character;
+use crate::item::key::Key;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Record of the enemy classes faced by the player, kept across heroes.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct Bestiary {
+    /// Entries by enemy class name, sorted so they're listed alphabetically.
+    entries: BTreeMap<String, Entry>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Entry {
+    pub encountered: i32,
+    pub killed: i32,
+
+    /// Highest level of the enemies of this class that were killed.
+    pub max_level: i32,
+
+    /// Items dropped by enemies of this class, in the order they were seen.
+    pub drops: Vec<Key>,
+}
+
+impl Bestiary {
+    pub fn encounter(&mut self, enemy: &Character) {
+        self.entry(enemy).encountered += 1;
+    }
+
+    /// Record the kill of the given enemy and return whether it was the
+    /// first time an enemy of its class was killed.
+    pub fn kill(&mut self, enemy: &Character) -> bool {
+        let entry = self.entry(enemy);
+        entry.killed += 1;
+        entry.max_level = std::cmp::max(entry.max_level, enemy.level);
+        entry.killed == 1
+    }
+
+    pub fn drop_seen(&mut self, enemy: &Character, item: Key) {
+        let entry = self.entry(enemy);
+        if !entry.drops.contains(&item) {
+            entry.drops.push(item);
+        }
+    }
+
+    /// Whether an enemy of the given class was ever encountered.
+    pub fn seen(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    pub fn list(&self) -> Vec<(&String, &Entry)> {
+        self.entries.iter().collect()
+    }
+
+    fn entry(&mut self, enemy: &Character) -> &mut Entry {
+        self.entries.entry(enemy.name()).or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::class::{Category, Class};
+
+    #[test]
+    fn test_entries() {
+        let mut bestiary = Bestiary::default();
+        let class = Class::random(Category::Common);
+        let weak = Character::new(class.clone(), 2);
+        let strong = Character::new(class.clone(), 5);
+
+        bestiary.encounter(&strong);
+        bestiary.encounter(&weak);
+        assert!(bestiary.kill(&weak));
+        assert!(!bestiary.kill(&strong));
+        bestiary.encounter(&weak);
+        assert!(!bestiary.kill(&weak));
+
+        let fang = Key::from("fang").unwrap();
+        bestiary.drop_seen(&weak, fang.clone());
+        bestiary.drop_seen(&strong, fang.clone());
+
+        let expected = Entry {
+            encountered: 3,
+            killed: 3,
+            max_level: 5,
+            drops: vec![fang],
+        };
+        assert_eq!(vec![(&class.name, &expected)], bestiary.list());
+    }
+}
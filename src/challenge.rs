@@ -0,0 +1,341 @@
+use crate::activity::{self, SECONDS_PER_DAY};
+use crate::game::Game;
+use crate::item::chest::Chest;
+use crate::log;
+use serde::{Deserialize, Serialize};
+
+/// Streaks longer than this don't improve the reward chests any further.
+const MAX_STREAK_BONUS: i32 = 7;
+
+/// The daily challenges, one per real-time day in turn.
+const DAILY: [Goal; 5] = [
+    Goal::WinBattles { count: 5, depth: 0 },
+    Goal::OpenChests {
+        count: 3,
+        depth: 10,
+    },
+    Goal::GainLevels(2),
+    Goal::WinBattles {
+        count: 3,
+        depth: 15,
+    },
+    Goal::OpenChests { count: 5, depth: 0 },
+];
+
+/// The weekly challenges, one per real-time week in turn.
+const WEEKLY: [Goal; 4] = [
+    Goal::WinBattles {
+        count: 50,
+        depth: 0,
+    },
+    Goal::OpenChests {
+        count: 20,
+        depth: 10,
+    },
+    Goal::GainLevels(10),
+    Goal::WinBattles {
+        count: 20,
+        depth: 25,
+    },
+];
+
+/// Goals that rotate with the real-time days and weeks, rewarded with a
+/// bonus chest. Completing them in consecutive days or weeks builds up a
+/// streak that makes the chests better. Tracked across heroes.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct Challenges {
+    daily: Track,
+    weekly: Track,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Daily,
+    Weekly,
+}
+
+impl Period {
+    /// The day or week, counted since the unix epoch, of the given time.
+    /// Weeks start on monday.
+    pub fn at(&self, now: u64) -> u64 {
+        let day = now / SECONDS_PER_DAY;
+        match self {
+            Period::Daily => day,
+            // the epoch was a thursday
+            Period::Weekly => (day + 3) / 7,
+        }
+    }
+
+    fn goal(&self, period: u64) -> Goal {
+        match self {
+            Period::Daily => DAILY[period as usize % DAILY.len()],
+            Period::Weekly => WEEKLY[period as usize % WEEKLY.len()],
+        }
+    }
+
+    /// How much farther from home the reward chest is generated as if found.
+    fn boost(&self) -> i32 {
+        match self {
+            Period::Daily => 5,
+            Period::Weekly => 15,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Goal {
+    WinBattles { count: i32, depth: i32 },
+    OpenChests { count: i32, depth: i32 },
+    GainLevels(i32),
+}
+
+impl Goal {
+    pub fn description(&self, period: Period) -> String {
+        let goal = match self {
+            Goal::WinBattles { count, depth } => format!("win {} battles{}", count, deeper(*depth)),
+            Goal::OpenChests { count, depth } => format!("open {} chests{}", count, deeper(*depth)),
+            Goal::GainLevels(count) => format!("gain {} levels", count),
+        };
+        match period {
+            Period::Daily => format!("{} today", goal),
+            Period::Weekly => format!("{} this week", goal),
+        }
+    }
+
+    fn count(&self) -> i32 {
+        match self {
+            Goal::WinBattles { count, .. } => *count,
+            Goal::OpenChests { count, .. } => *count,
+            Goal::GainLevels(count) => *count,
+        }
+    }
+
+    /// How much the event counts towards this goal.
+    fn progress(&self, event: &Event) -> i32 {
+        match (self, event) {
+            (Goal::WinBattles { depth, .. }, Event::BattleWon { distance })
+            | (Goal::OpenChests { depth, .. }, Event::ChestOpened { distance })
+                if distance > depth =>
+            {
+                1
+            }
+            (Goal::GainLevels(_), Event::LevelUp { count }) => *count,
+            _ => 0,
+        }
+    }
+}
+
+fn deeper(depth: i32) -> String {
+    if depth > 0 {
+        format!(" deeper than {}", depth)
+    } else {
+        String::new()
+    }
+}
+
+enum Event {
+    BattleWon { distance: i32 },
+    ChestOpened { distance: i32 },
+    LevelUp { count: i32 },
+}
+
+/// The progress towards the challenge of a period.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+struct Track {
+    /// Day or week the progress belongs to.
+    period: u64,
+    progress: i32,
+
+    /// Consecutive periods the challenge was completed, up to the last one.
+    streak: i32,
+
+    /// The period the challenge was last completed.
+    completed: Option<u64>,
+}
+
+impl Track {
+    /// Move the track to the given period, which discards the progress of
+    /// the previous one.
+    fn at(&mut self, period: u64) {
+        if self.period != period {
+            self.period = period;
+            self.progress = 0;
+        }
+    }
+
+    /// Count progress towards the goal, and return whether it completed it.
+    fn advance(&mut self, goal: &Goal, amount: i32) -> bool {
+        if amount == 0 || self.completed == Some(self.period) {
+            return false;
+        }
+        self.progress = std::cmp::min(self.progress + amount, goal.count());
+        if self.progress < goal.count() {
+            return false;
+        }
+        self.streak = match self.completed {
+            Some(last) if last + 1 == self.period => self.streak + 1,
+            _ => 1,
+        };
+        self.completed = Some(self.period);
+        true
+    }
+
+    /// The current streak, which is lost if the challenge of the previous
+    /// period wasn't completed.
+    fn streak(&self, period: u64) -> i32 {
+        match self.completed {
+            Some(last) if last == period || last + 1 == period => self.streak,
+            _ => 0,
+        }
+    }
+
+    fn progress(&self, period: u64) -> i32 {
+        if self.period == period {
+            self.progress
+        } else {
+            0
+        }
+    }
+}
+
+// EVENT TRIGGERING FUNCTIONS
+
+pub fn battle_won(game: &mut Game) {
+    let distance = game.location.distance_from_home().len();
+    handle(game, activity::now(), Event::BattleWon { distance });
+}
+
+pub fn chest_opened(game: &mut Game) {
+    let distance = game.location.distance_from_home().len();
+    handle(game, activity::now(), Event::ChestOpened { distance });
+}
+
+pub fn level_up(game: &mut Game, count: i32) {
+    handle(game, activity::now(), Event::LevelUp { count });
+}
+
+/// Count the event towards the current challenges, rewarding the ones it
+/// completes with a bonus chest.
+fn handle(game: &mut Game, now: u64, event: Event) {
+    for period in [Period::Daily, Period::Weekly] {
+        let current = period.at(now);
+        let goal = period.goal(current);
+        let track = game.challenges.track(period);
+        track.at(current);
+        if track.advance(&goal, goal.progress(&event)) {
+            let streak = track.streak;
+            let boost = period.boost() + std::cmp::min(streak, MAX_STREAK_BONUS);
+            let mut chest = Chest::generate_boosted(game, boost);
+            let (items, gold) = chest.pick_up(game);
+            game.stats.earn(gold);
+            log::challenge_completed(&goal.description(period), streak, &items, gold);
+            game.leave_behind(chest);
+        }
+    }
+}
+
+impl Challenges {
+    fn track(&mut self, period: Period) -> &mut Track {
+        match period {
+            Period::Daily => &mut self.daily,
+            Period::Weekly => &mut self.weekly,
+        }
+    }
+
+    /// The challenges of the given time, each with its description,
+    /// progress, goal and streak.
+    pub fn list(&self, now: u64) -> Vec<(String, i32, i32, i32)> {
+        [(Period::Daily, &self.daily), (Period::Weekly, &self.weekly)]
+            .iter()
+            .map(|(period, track)| {
+                let current = period.at(now);
+                let goal = period.goal(current);
+                (
+                    goal.description(*period),
+                    track.progress(current),
+                    goal.count(),
+                    track.streak(current),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation() {
+        let monday = 4 * SECONDS_PER_DAY;
+        assert_eq!(Period::Weekly.at(monday - 1) + 1, Period::Weekly.at(monday));
+        assert_eq!(
+            Period::Weekly.at(monday),
+            Period::Weekly.at(monday + 7 * SECONDS_PER_DAY - 1)
+        );
+        assert_ne!(
+            Period::Daily.goal(Period::Daily.at(monday)),
+            Period::Daily.goal(Period::Daily.at(monday + SECONDS_PER_DAY))
+        );
+        assert_eq!(
+            "open 3 chests deeper than 10 today",
+            Goal::OpenChests {
+                count: 3,
+                depth: 10
+            }
+            .description(Period::Daily)
+        );
+    }
+
+    #[test]
+    fn test_streak() {
+        let goal = Goal::WinBattles { count: 2, depth: 0 };
+        let mut track = Track::default();
+        track.at(10);
+        assert!(!track.advance(&goal, 1));
+        assert!(track.advance(&goal, 1));
+        assert_eq!(1, track.streak(10));
+        // no more rewards for the same period
+        assert!(!track.advance(&goal, 1));
+
+        track.at(11);
+        assert_eq!(0, track.progress(11));
+        assert_eq!(1, track.streak(11));
+        track.advance(&goal, 2);
+        assert_eq!(2, track.streak(11));
+
+        // skipping a period loses the streak
+        assert_eq!(0, track.streak(13));
+        track.at(13);
+        track.advance(&goal, 2);
+        assert_eq!(1, track.streak(13));
+    }
+
+    #[test]
+    fn test_progress() {
+        let deep = Goal::OpenChests {
+            count: 3,
+            depth: 10,
+        };
+        assert_eq!(0, deep.progress(&Event::ChestOpened { distance: 10 }));
+        assert_eq!(1, deep.progress(&Event::ChestOpened { distance: 11 }));
+        assert_eq!(0, deep.progress(&Event::BattleWon { distance: 11 }));
+        assert_eq!(
+            3,
+            Goal::GainLevels(5).progress(&Event::LevelUp { count: 3 })
+        );
+
+        let mut game = Game::new();
+        let now = 0;
+        let goal = Period::Daily.goal(Period::Daily.at(now));
+        assert_eq!(Goal::WinBattles { count: 5, depth: 0 }, goal);
+        for _ in 0..5 {
+            handle(&mut game, now, Event::BattleWon { distance: 1 });
+        }
+        assert!(game.gold > 0);
+        let (_, progress, count, streak) = &game.challenges.list(now)[0];
+        assert_eq!((5, 5, 1), (*progress, *count, *streak));
+    }
+}
@@ -1,6 +1,9 @@
-use crate::randomizer::{random, Randomizer};
+use crate::item::key::Key;
+use crate::item::material::Material;
+use crate::plugin;
+use crate::randomizer::{choose, random};
+use anyhow::{anyhow, bail, Result};
 use once_cell::sync::OnceCell;
-use rand::prelude::SliceRandom;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
@@ -42,6 +45,55 @@ pub struct Class {
     pub category: Category,
 
     pub inflicts: Option<(super::StatusEffect, u32)>,
+
+    /// Crafting material that enemies of this class can drop when beaten.
+    #[serde(default)]
+    pub drops: Option<Material>,
+
+    /// Elemental affinity of the class, which determines the element of its
+    /// spells and unarmed attacks, and the elements it's weak against or resists.
+    #[serde(default)]
+    pub element: Option<super::element::Element>,
+
+    /// Spells that characters of this class learn, with the level at which
+    /// each one is unlocked.
+    #[serde(default)]
+    pub spells: Vec<(super::spell::Spell, i32)>,
+
+    /// Abilities that characters of this class have outside of battle.
+    #[serde(default)]
+    pub skills: Vec<Skill>,
+
+    /// Equipment pieces that heroes of this class start with, at level 1.
+    #[serde(default)]
+    pub equipment: Vec<Key>,
+
+    /// Bonus that heroes of this class always have, unlike skills it's
+    /// not tied to a skill tree.
+    #[serde(default)]
+    pub passive: Option<Passive>,
+
+    /// Spell known from level 1 and cast at half the mp cost.
+    #[serde(default)]
+    pub signature: Option<super::spell::Spell>,
+}
+
+/// Class abilities that aren't tied to stats.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Skill {
+    /// Better chance of avoiding and disarming chest traps.
+    Disarm,
+}
+
+/// Unique bonuses that player classes can have.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Passive {
+    /// Ethers restore twice the mp.
+    DoubleEther,
+    /// Potions restore twice the hp.
+    DoublePotion,
 }
 
 /// Determines whether the class is intended for a Player or, if it's for an enemy,
@@ -54,6 +106,8 @@ pub enum Category {
     Common,
     Rare,
     Legendary,
+    /// Bosses don't appear randomly, each one guards a given distance from home.
+    Boss,
 }
 
 static CLASSES: OnceCell<HashMap<Category, Vec<Class>>> = OnceCell::new();
@@ -66,8 +120,40 @@ impl Class {
     }
 
     /// Customize the classes definitions based on an input yaml byte array.
-    pub fn load(bytes: &[u8]) {
-        CLASSES.set(from_bytes(bytes)).unwrap();
+    pub fn load(bytes: &[u8]) -> Result<()> {
+        let classes = from_bytes(bytes).map_err(|e| anyhow!("Invalid classes file: {}", e))?;
+        CLASSES.set(classes).unwrap();
+        Ok(())
+    }
+
+    pub fn has_passive(&self, passive: Passive) -> bool {
+        self.passive == Some(passive)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let player = self.category == Category::Player;
+        for key in &self.equipment {
+            match key {
+                Key::Sword | Key::Shield | Key::Helmet | Key::Armor | Key::Boots => {}
+                _ => bail!("class {}: {} is not a piece of equipment", self.name, key),
+            }
+            if self.equipment.iter().filter(|other| *other == key).count() > 1 {
+                bail!("class {}: {} is listed more than once", self.name, key);
+            }
+        }
+        if !player && (!self.equipment.is_empty() || self.passive.is_some()) {
+            bail!(
+                "class {}: only player classes can have equipment and passives",
+                self.name
+            );
+        }
+        if self.signature.is_some() && !self.is_magic() {
+            bail!("class {}: a signature spell requires mp", self.name);
+        }
+        if !self.spells.is_empty() && !self.is_magic() {
+            bail!("class {}: spells require mp", self.name);
+        }
+        Ok(())
     }
 
     /// The default player class, exposed for initialization and parameterization of
@@ -85,9 +171,32 @@ impl Class {
             .copied()
     }
 
+    /// The enemy class of the given name, excluding player classes and bosses.
+    pub fn enemy_by_name(name: &str) -> Option<&'static Self> {
+        [Category::Common, Category::Rare, Category::Legendary]
+            .iter()
+            .flat_map(|category| Self::of(category.clone()))
+            .find(|class| class.name == name)
+    }
+
     pub fn random(category: Category) -> &'static Self {
-        let mut rng = rand::thread_rng();
-        Self::of(category).choose(&mut rng).unwrap()
+        choose(Self::of(category)).unwrap()
+    }
+
+    /// The boss guarding the given distance from home, if any. There's a boss
+    /// every ten steps, and they repeat once the deepest one is reached.
+    pub fn boss(distance: i32) -> Option<&'static Self> {
+        if distance <= 0 || distance % 10 != 0 {
+            return None;
+        }
+        let bosses = CLASSES.get_or_init(default_classes).get(&Category::Boss)?;
+        let index = (distance / 10 - 1) as usize % bosses.len();
+        bosses.get(index)
+    }
+
+    /// All the boss classes, in the order they guard the distances.
+    pub fn bosses() -> &'static [Self] {
+        Self::of(Category::Boss)
     }
 
     pub fn names(category: Category) -> HashSet<String> {
@@ -103,21 +212,80 @@ impl Class {
 }
 
 fn default_classes() -> HashMap<Category, Vec<Class>> {
-    from_bytes(include_bytes!("classes.yaml"))
+    from_bytes(include_bytes!("classes.yaml")).unwrap()
 }
 
-fn from_bytes(bytes: &[u8]) -> HashMap<Category, Vec<Class>> {
+fn from_bytes(bytes: &[u8]) -> Result<HashMap<Category, Vec<Class>>> {
     // it would arguably be better for these module not to deal with deserialization
     // and yaml, but at this stage it's easier allow it to pick up defaults from
     // the local file when it hasn't been customized (especially for tests)
-    let mut classes: Vec<Class> = serde_yaml::from_slice(bytes).unwrap();
+    let mut classes: Vec<Class> = serde_yaml::from_slice(bytes)?;
+    classes.extend(plugin::enemies());
 
     let mut class_groups = HashMap::new();
     for class in classes.drain(..) {
+        class.validate()?;
         let entry = class_groups
             .entry(class.category.clone())
             .or_insert_with(Vec::new);
+        if entry.iter().any(|other: &Class| other.name == class.name) {
+            bail!("class {} is defined more than once", class.name);
+        }
         entry.push(class);
     }
-    class_groups
+
+    let required = [
+        Category::Player,
+        Category::Common,
+        Category::Rare,
+        Category::Legendary,
+    ];
+    for category in &required {
+        if !class_groups.contains_key(category) {
+            let name = format!("{:?}", category).to_lowercase();
+            bail!("at least one {} class is required", name);
+        }
+    }
+    Ok(class_groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_classes() {
+        let defaults = include_str!("classes.yaml");
+        assert!(from_bytes(defaults.as_bytes()).is_ok());
+
+        let custom = defaults.replace(
+            "  category: player\n  skills: [disarm]",
+            "  category: player\n  skills: [disarm]\n  equipment: [sword, boots]\n  passive: double_potion",
+        );
+        let classes = from_bytes(custom.as_bytes()).unwrap();
+        let thief = classes[&Category::Player]
+            .iter()
+            .find(|class| class.name == "thief")
+            .unwrap();
+        assert_eq!(vec![Key::Sword, Key::Boots], thief.equipment);
+        assert!(thief.has_passive(Passive::DoublePotion));
+
+        let item = defaults.replace("  skills: [disarm]", "  equipment: [potion]");
+        assert!(from_bytes(item.as_bytes()).is_err());
+
+        let repeated = defaults.replace("  skills: [disarm]", "  equipment: [sword, sword]");
+        assert!(from_bytes(repeated.as_bytes()).is_err());
+
+        let signature = defaults.replace("  skills: [disarm]", "  signature: fireball");
+        assert!(from_bytes(signature.as_bytes()).is_err());
+
+        let passive = defaults.replace("  skills: [disarm]", "  passive: triple_ether");
+        assert!(from_bytes(passive.as_bytes()).is_err());
+
+        let duplicate = defaults.replace("- name: thief", "- name: mage");
+        assert!(from_bytes(duplicate.as_bytes()).is_err());
+
+        let no_players = defaults.replace("category: player", "category: common");
+        assert!(from_bytes(no_players.as_bytes()).is_err());
+    }
 }
@@ -0,0 +1,64 @@
+use super::class::Category;
+use super::Character;
+use crate::item::Item;
+use serde::{Deserialize, Serialize};
+
+/// A tamed creature that fights alongside the hero, gaining its own
+/// experience. Only common enemies can be tamed. A companion can hold an
+/// item, which is dropped with the hero's belongings when the hero dies.
+#[derive(Serialize, Deserialize)]
+pub struct Companion {
+    pub character: Character,
+
+    #[serde(default)]
+    pub item: Option<Box<dyn Item>>,
+}
+
+impl Companion {
+    /// Tame a new creature of the same class and level as the given enemy,
+    /// or None if it can't be tamed. Companions join holding the crafting
+    /// material of their class, if any.
+    pub fn tame(enemy: &Character) -> Option<Self> {
+        if enemy.class.category != Category::Common {
+            return None;
+        }
+
+        let item = enemy
+            .class
+            .drops
+            .clone()
+            .map(|material| Box::new(material) as Box<dyn Item>);
+        Some(Self {
+            character: Character::new(enemy.class.clone(), enemy.level),
+            item,
+        })
+    }
+
+    pub fn name(&self) -> String {
+        self.character.name()
+    }
+
+    /// Knocked out companions don't fight until they rest at home.
+    pub fn is_knocked_out(&self) -> bool {
+        self.character.current_hp == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::character::class::Class;
+
+    #[test]
+    fn tame() {
+        let mut rat = Character::new(Class::random(Category::Common).clone(), 3);
+        rat.current_hp = 0;
+        let companion = Companion::tame(&rat).unwrap();
+        assert_eq!(rat.name(), companion.name());
+        assert_eq!(3, companion.character.level);
+        assert!(!companion.is_knocked_out());
+
+        let dragon = Character::new(Class::random(Category::Rare).clone(), 3);
+        assert!(Companion::tame(&dragon).is_none());
+    }
+}
@@ -0,0 +1,69 @@
+use core::fmt;
+use serde::{Deserialize, Serialize};
+
+/// Elemental affinity of attacks and characters. Each element is strong
+/// against the next one in the cycle fire -> ice -> thunder -> fire.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Element {
+    Fire,
+    Ice,
+    Thunder,
+}
+
+impl Element {
+    /// The element this one is strong against.
+    fn beats(&self) -> Element {
+        match self {
+            Element::Fire => Element::Ice,
+            Element::Ice => Element::Thunder,
+            Element::Thunder => Element::Fire,
+        }
+    }
+
+    /// The factor to apply to the damage of an attack of the given element
+    /// on a receiver of the given element. Receivers are weak against the
+    /// element that beats their own, and resist both their own element and
+    /// the one they beat.
+    pub fn multiplier(attack: Option<Element>, receiver: Option<Element>) -> f64 {
+        match (attack, receiver) {
+            (Some(attack), Some(receiver)) if attack.beats() == receiver => 2.0,
+            (Some(attack), Some(receiver)) if attack == receiver || receiver.beats() == attack => {
+                0.5
+            }
+            _ => 1.0,
+        }
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Element::Fire => "fire",
+            Element::Ice => "ice",
+            Element::Thunder => "thunder",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multiplier() {
+        let multiplier = Element::multiplier;
+        assert_eq!(1.0, multiplier(None, None));
+        assert_eq!(1.0, multiplier(Some(Element::Fire), None));
+        assert_eq!(1.0, multiplier(None, Some(Element::Fire)));
+
+        assert_eq!(2.0, multiplier(Some(Element::Fire), Some(Element::Ice)));
+        assert_eq!(2.0, multiplier(Some(Element::Ice), Some(Element::Thunder)));
+        assert_eq!(2.0, multiplier(Some(Element::Thunder), Some(Element::Fire)));
+
+        assert_eq!(0.5, multiplier(Some(Element::Fire), Some(Element::Fire)));
+        assert_eq!(0.5, multiplier(Some(Element::Ice), Some(Element::Fire)));
+        assert_eq!(0.5, multiplier(Some(Element::Fire), Some(Element::Thunder)));
+    }
+}
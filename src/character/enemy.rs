@@ -1,36 +1,222 @@
-use super::{class::Category, class::Class, Character};
+use super::{class::Category, class::Class, class::Stat, Character};
+use crate::clock::Clock;
+use crate::config::Config;
+use crate::difficulty::Difficulty;
+use crate::faction::Reputation;
 use crate::item::ring::Ring;
 use crate::location;
 use crate::log;
-use crate::randomizer::{random, Randomizer};
-use rand::prelude::SliceRandom;
-use rand::Rng;
+use crate::randomizer::{choose, choose_weighted, random};
+use std::collections::HashSet;
 
-/// Randomly spawn an enemy character at the given location, based on the
+/// Randomly spawn enemy characters at the given location, based on the
 /// current character stats.
-/// The distance from home will influence the enemy frequency and level.
+/// The distance from home will influence the enemy frequency and level,
+/// and far away from home enemies may appear in groups.
 /// Under certain conditions, special (quest-related) enemies may be spawned.
-pub fn spawn(location: &location::Location, player: &Character) -> Option<Character> {
-    if player.enemies_evaded() {
-        return None;
+/// The weather makes enemies more or less likely to appear.
+/// No enemies appear where the game is disabled by an ignore marker or pattern.
+/// Returns an empty vector if no enemies appear.
+pub fn spawn(
+    location: &location::Location,
+    player: &Character,
+    tier: i32,
+    difficulty: Difficulty,
+    clock: &Clock,
+    reputation: &Reputation,
+) -> Vec<Character> {
+    if player.enemies_evaded() || location.is_ignored() {
+        return Vec::new();
     }
 
     let distance = location.distance_from_home();
-    if random().should_enemy_appear(&distance) {
-        // try spawning "special" enemies if conditions are met, otherwise
-        // a random one for the current location
-        let (class, level) = spawn_gorthaur(player, location)
-            .or_else(|| spawn_shadow(player, location))
-            .or_else(|| spawn_dev(player, location))
-            .unwrap_or_else(|| spawn_random(player, &distance));
-
-        let level = random().enemy_level(level);
-        let enemy = Character::new(class, level);
-        log::enemy_appears(&enemy, location);
-        Some(enemy)
+    let mut appears = random().should_enemy_appear(&distance);
+    // storms bring out enemies that wouldn't have appeared, and fog hides
+    // the hero from some that would
+    let shift = clock.encounter_shift();
+    let flips = (shift > 0 && !appears) || (shift < 0 && appears);
+    if flips && random().weather_encounter(shift.unsigned_abs()) {
+        appears = !appears;
+    }
+    if !appears {
+        return Vec::new();
+    }
+
+    // try spawning "special" enemies if conditions are met, they always
+    // come alone. Otherwise random ones for the current location
+    let special = spawn_gorthaur(player, location)
+        .or_else(|| spawn_shadow(player, location))
+        .or_else(|| spawn_dev(player, location));
+    if let Some((class, level)) = special {
+        vec![appear(location, player, class, level, difficulty)]
     } else {
-        None
+        spawn_group(
+            location, player, &distance, tier, difficulty, clock, reputation,
+        )
+    }
+}
+
+/// Spawn a random group of enemies as they would appear at the given
+/// distance from home, regardless of the actual location.
+/// Higher tiers, unlocked by New Game+, make stronger enemies appear
+/// closer to home. Enemy stats are scaled by the game difficulty.
+/// At night, enemies are about twice as likely to be undead. Dangerous
+/// directories make the enemies stronger. Members of the factions friendly
+/// to the hero leave it alone.
+pub fn spawn_group(
+    location: &location::Location,
+    player: &Character,
+    distance: &location::Distance,
+    tier: i32,
+    difficulty: Difficulty,
+    clock: &Clock,
+    reputation: &Reputation,
+) -> Vec<Character> {
+    let lair = Lair::of(location);
+    let danger = location.danger();
+    (0..random().enemy_group_size(distance))
+        .filter_map(|_| {
+            let (mut class, level) = spawn_random(player, distance, tier);
+            let level = level + danger;
+            if clock.is_night() && !is_undead(&class) {
+                // a second pick gives the undead another chance to show up
+                class = Class::random(class.category.clone()).clone();
+            }
+            let class = lair.and_then(|lair| lair.dweller()).unwrap_or(class);
+            if reputation.spares(&class) {
+                log::enemy_spared(&class.name);
+                return None;
+            }
+            Some(appear(location, player, class, level, difficulty))
+        })
+        .collect()
+}
+
+/// Most enemies in an arena wave.
+const MAX_WAVE_SIZE: i32 = 4;
+
+/// Spawn the enemies of the given arena wave, counting from 1. Each wave
+/// is a level stronger than the previous one, starting below the hero
+/// level, and every third one brings an extra enemy. Enemy classes are
+/// picked as if the hero walked farther from home with each wave.
+pub fn wave(
+    location: &location::Location,
+    player: &Character,
+    wave: i32,
+    difficulty: Difficulty,
+) -> Vec<Character> {
+    let size = std::cmp::min(1 + (wave - 1) / 3, MAX_WAVE_SIZE);
+    let distance = location::Distance::from(wave * 2);
+    (0..size)
+        .map(|_| {
+            let (class, _) = spawn_random(player, &distance, 0);
+            let level = std::cmp::max(1, player.level + wave - 3);
+            appear(location, player, class, level, difficulty)
+        })
+        .collect()
+}
+
+/// Kinds of directories where some enemy classes are more likely to appear,
+/// to make different parts of the filesystem feel distinct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lair {
+    /// Directories of code repositories.
+    Repo,
+    /// Hidden directories, e.g. `~/.config`.
+    Hidden,
+    /// The temporary files directory, e.g. `/tmp`.
+    Temp,
+}
+
+impl Lair {
+    /// The lair kind of the given location, if any. When more than one
+    /// applies, repositories take precedence over hidden and temp dirs.
+    pub fn of(location: &location::Location) -> Option<Self> {
+        if location.is_repo() {
+            Some(Lair::Repo)
+        } else if location.is_hidden() {
+            Some(Lair::Hidden)
+        } else if location.is_temp() {
+            Some(Lair::Temp)
+        } else {
+            None
+        }
+    }
+
+    /// The names of the enemy classes dwelling in this kind of lair, as
+    /// set in the config file.
+    pub fn classes(&self) -> &'static [String] {
+        let lairs = &Config::get().lairs;
+        match self {
+            Lair::Repo => &lairs.repo,
+            Lair::Hidden => &lairs.hidden,
+            Lair::Temp => &lairs.temp,
+        }
+    }
+
+    /// Randomly pick one of the dwellers of the lair to replace a regular
+    /// enemy. Classes missing from a customized classes file, or unknown
+    /// names in the config file, are skipped.
+    fn dweller(&self) -> Option<Class> {
+        if !random().lair_enemy() {
+            return None;
+        }
+        let dwellers: Vec<&Class> = self
+            .classes()
+            .iter()
+            .filter_map(|name| Class::enemy_by_name(name))
+            .collect();
+        choose(&dwellers).map(|class| (*class).clone())
+    }
+}
+
+/// The names of the enemy classes that rise at night.
+const UNDEAD: &[&str] = &["zombie", "skeleton", "wraith", "vampire", "lich"];
+
+pub fn is_undead(class: &Class) -> bool {
+    UNDEAD.contains(&class.name.as_str())
+}
+
+/// Enemies share the prestige rank of the player, so ascended heroes face
+/// equally strengthened foes.
+fn appear(
+    location: &location::Location,
+    player: &Character,
+    class: Class,
+    level: i32,
+    difficulty: Difficulty,
+) -> Character {
+    let level = random().enemy_level(level);
+    let mut enemy = Character::new(class, level)
+        .scaled(difficulty.enemy_stats())
+        .with_prestige(player.prestige);
+    if random().gilded_enemy() {
+        enemy = enemy.gild();
+    }
+    log::enemy_appears(&enemy, location);
+    enemy
+}
+
+/// Spawn the boss guarding the given location, if there's one at its
+/// distance from home and it wasn't beaten yet. Unlike other enemies,
+/// bosses always appear and their level is fixed by the distance.
+pub fn spawn_boss(
+    location: &location::Location,
+    player: &Character,
+    beaten: &HashSet<i32>,
+    difficulty: Difficulty,
+) -> Option<Character> {
+    let distance = location.distance_from_home().len();
+    if beaten.contains(&distance) {
+        return None;
     }
+
+    let class = Class::boss(distance)?;
+    let enemy = Character::new(class.clone(), distance)
+        .scaled(difficulty.enemy_stats())
+        .with_prestige(player.prestige);
+    log::enemy_appears(&enemy, location);
+    Some(enemy)
 }
 
 /// Final boss, only appears at level +100 when wearing the ruling ring
@@ -52,8 +238,7 @@ fn spawn_gorthaur(player: &Character, location: &location::Location) -> Option<(
 
 /// Player shadow, appears at home directory
 fn spawn_shadow(player: &Character, location: &location::Location) -> Option<(Class, i32)> {
-    let mut rng = rand::thread_rng();
-    if location.is_home() && rng.gen_ratio(1, 10) {
+    if location.is_home() && random().special_enemy() {
         let mut class = player.class.clone();
         class.name = String::from("shadow");
         class.category = Category::Rare;
@@ -65,9 +250,7 @@ fn spawn_shadow(player: &Character, location: &location::Location) -> Option<(Cl
 
 /// Easter egg, appears at rpg data dir
 fn spawn_dev(player: &Character, location: &location::Location) -> Option<(Class, i32)> {
-    let mut rng = rand::thread_rng();
-
-    if location.is_rpg_dir() && rng.gen_ratio(1, 10) {
+    if location.is_rpg_dir() && random().special_enemy() {
         let mut class = Class::player_first().clone();
         class.name = String::from("dev");
         class.hp.0 /= 2;
@@ -80,17 +263,56 @@ fn spawn_dev(player: &Character, location: &location::Location) -> Option<(Class
     }
 }
 
+/// A monster disguised as a treasure chest, appears when trying to pick one up.
+/// Its level is expected to be based on the value of the chest contents.
+pub fn mimic(location: &location::Location, level: i32) -> Character {
+    let class = Class {
+        name: String::from("mimic"),
+        hp: Stat(60, 8),
+        mp: None,
+        strength: Stat(14, 3),
+        speed: Stat(8, 2),
+        category: Category::Rare,
+        inflicts: None,
+        drops: None,
+        element: None,
+        spells: Vec::new(),
+        skills: Vec::new(),
+        equipment: Vec::new(),
+        passive: None,
+        signature: None,
+    };
+    let enemy = Character::new(class, level);
+    log::enemy_appears(&enemy, location);
+    enemy
+}
+
+/// A rare enemy protecting a treasure chest, appears when trying to pick it up.
+/// Its level is expected to be based on the value of the chest contents.
+pub fn guard(location: &location::Location, level: i32) -> Character {
+    let class = Class::random(Category::Rare).clone();
+    let enemy = Character::new(class, level);
+    log::enemy_appears(&enemy, location);
+    enemy
+}
+
 /// Choose an enemy randomly, with higher chance to difficult enemies the further from home.
-fn spawn_random(player: &Character, distance: &location::Distance) -> (Class, i32) {
+fn spawn_random(player: &Character, distance: &location::Distance, tier: i32) -> (Class, i32) {
     // the weights for each group of enemies are different depending on the distance
-    // the further from home, the bigger the chance to find difficult enemies
-    let (w_common, w_rare, w_legendary) = match distance {
-        location::Distance::Near(_) => (10, 2, 0),
-        location::Distance::Mid(_) => (8, 10, 1),
-        location::Distance::Far(_) => (0, 8, 2),
+    // the further from home, the bigger the chance to find difficult enemies.
+    // Each tier moves the weights one step further, the last step can only
+    // be reached in New Game+
+    let band = match distance {
+        location::Distance::Near(_) => 0,
+        location::Distance::Mid(_) => 1,
+        location::Distance::Far(_) => 2,
+    };
+    let (w_common, w_rare, w_legendary) = match std::cmp::min(band + tier, 3) {
+        0 => (10, 2, 0),
+        1 => (8, 10, 1),
+        2 => (0, 8, 2),
+        _ => (0, 4, 4),
     };
-
-    let mut rng = rand::thread_rng();
 
     // assign weights to each group and select one
     let weights = vec![
@@ -99,9 +321,7 @@ fn spawn_random(player: &Character, distance: &location::Distance) -> (Class, i3
         (Category::Legendary, w_legendary),
     ];
 
-    let category = weights
-        .as_slice()
-        .choose_weighted(&mut rng, |(_c, weight)| *weight)
+    let category = choose_weighted(&weights, |(_c, weight)| *weight)
         .unwrap()
         .0
         .clone();
@@ -122,37 +342,157 @@ mod tests {
         let d3 = location::Distance::from(3);
         let d10 = location::Distance::from(10);
 
-        assert_eq!(1, spawn_random(&player, &d1).1);
-        assert_eq!(1, spawn_random(&player, &d2).1);
-        assert_eq!(2, spawn_random(&player, &d3).1);
-        assert_eq!(9, spawn_random(&player, &d10).1);
+        assert_eq!(1, spawn_random(&player, &d1, 0).1);
+        assert_eq!(1, spawn_random(&player, &d2, 0).1);
+        assert_eq!(2, spawn_random(&player, &d3, 0).1);
+        assert_eq!(9, spawn_random(&player, &d10, 0).1);
 
         player.level = 5;
-        assert_eq!(1, spawn_random(&player, &d1).1);
-        assert_eq!(1, spawn_random(&player, &d2).1);
-        assert_eq!(2, spawn_random(&player, &d3).1);
-        assert_eq!(9, spawn_random(&player, &d10).1);
+        assert_eq!(1, spawn_random(&player, &d1, 0).1);
+        assert_eq!(1, spawn_random(&player, &d2, 0).1);
+        assert_eq!(2, spawn_random(&player, &d3, 0).1);
+        assert_eq!(9, spawn_random(&player, &d10, 0).1);
+
+        player.level = 10;
+        assert_eq!(1, spawn_random(&player, &d1, 0).1);
+        assert_eq!(2, spawn_random(&player, &d2, 0).1);
+        assert_eq!(3, spawn_random(&player, &d3, 0).1);
+        assert_eq!(10, spawn_random(&player, &d10, 0).1);
+    }
 
+    #[test]
+    fn test_arena_waves() {
+        let mut player = Character::player();
         player.level = 10;
-        assert_eq!(1, spawn_random(&player, &d1).1);
-        assert_eq!(2, spawn_random(&player, &d2).1);
-        assert_eq!(3, spawn_random(&player, &d3).1);
-        assert_eq!(10, spawn_random(&player, &d10).1);
+        let home = location::Location::home();
+
+        let first = wave(&home, &player, 1, Difficulty::default());
+        assert_eq!(1, first.len());
+        assert_eq!(8, first[0].level);
+
+        let seventh = wave(&home, &player, 7, Difficulty::default());
+        assert_eq!(3, seventh.len());
+        assert!(seventh.iter().all(|enemy| enemy.level == 14));
+
+        let last = wave(&home, &player, 50, Difficulty::default());
+        assert_eq!(MAX_WAVE_SIZE as usize, last.len());
+    }
+
+    #[test]
+    fn test_enemy_tier() {
+        let player = Character::player();
+        let d1 = location::Distance::from(1);
+
+        // no common enemies near home after a few new game+
+        for _ in 0..20 {
+            let (class, _) = spawn_random(&player, &d1, 3);
+            assert_ne!(Category::Common, class.category);
+        }
+    }
+
+    #[test]
+    fn test_lairs() {
+        let root = std::env::temp_dir().join("rpg-lairs");
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in &["repo/.git", "repo/src", ".hidden", "plain"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        let lair = |dir: &str| {
+            let path = root.join(dir).to_string_lossy().to_string();
+            Lair::of(&location::Location::from(&path).unwrap())
+        };
+
+        assert_eq!(Some(Lair::Repo), lair("repo"));
+        assert_eq!(Some(Lair::Repo), lair("repo/src"));
+        assert_eq!(Some(Lair::Hidden), lair(".hidden"));
+        assert_eq!(Some(Lair::Temp), lair("plain"));
+        assert_eq!(None, Lair::of(&location::Location::home()));
+
+        // all the dwellers are defined by the default classes
+        for lair in &[Lair::Repo, Lair::Hidden, Lair::Temp] {
+            for name in lair.classes() {
+                assert!(Class::enemy_by_name(name).is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn test_boss() {
+        let mut player = Character::player();
+        let mut beaten = HashSet::new();
+        let home = location::Location::home().path_string();
+        let near = location::tests::location_from(&format!("{}/1/2/3/4/5/6/7/8/9", home));
+        assert!(spawn_boss(&near, &player, &beaten, Difficulty::Normal).is_none());
+
+        let guarded = location::tests::location_from(&format!("{}/1/2/3/4/5/6/7/8/9/10", home));
+        let boss = spawn_boss(&guarded, &player, &beaten, Difficulty::Normal).unwrap();
+        assert!(boss.is_boss());
+        assert_eq!(10, boss.level);
+
+        // bosses keep up with ascended heroes
+        player.prestige = 2;
+        let ascended = spawn_boss(&guarded, &player, &beaten, Difficulty::Normal).unwrap();
+        assert_eq!(2, ascended.prestige);
+        assert_eq!(ascended.max_hp(), ascended.current_hp);
+        assert!(ascended.max_hp() > boss.max_hp());
+
+        // and so do they at harder difficulties
+        player.prestige = 0;
+        let hard = spawn_boss(&guarded, &player, &beaten, Difficulty::Hard).unwrap();
+        assert_eq!(boss.max_hp() * 125 / 100, hard.max_hp());
+        assert_eq!(hard.max_hp(), hard.current_hp);
+
+        beaten.insert(10);
+        assert!(spawn_boss(&guarded, &player, &beaten, Difficulty::Normal).is_none());
     }
 
     #[test]
     fn test_run_ring() {
         let mut player = Character::player();
         let location = location::tests::location_from("~/1/");
-        assert!(spawn(&location, &player).is_some());
+        let clock = Clock::default();
+        let reputation = Reputation::default();
+        assert!(!spawn(
+            &location,
+            &player,
+            0,
+            Difficulty::Normal,
+            &clock,
+            &reputation
+        )
+        .is_empty());
 
         player.equip_ring(Ring::Evade);
-        assert!(spawn(&location, &player).is_none());
+        assert!(spawn(
+            &location,
+            &player,
+            0,
+            Difficulty::Normal,
+            &clock,
+            &reputation
+        )
+        .is_empty());
 
         player.equip_ring(Ring::Void);
-        assert!(spawn(&location, &player).is_none());
+        assert!(spawn(
+            &location,
+            &player,
+            0,
+            Difficulty::Normal,
+            &clock,
+            &reputation
+        )
+        .is_empty());
 
         player.equip_ring(Ring::Void);
-        assert!(spawn(&location, &player).is_some());
+        assert!(!spawn(
+            &location,
+            &player,
+            0,
+            Difficulty::Normal,
+            &clock,
+            &reputation
+        )
+        .is_empty());
     }
 }
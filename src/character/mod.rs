@@ -1,17 +1,61 @@
 use crate::item::equipment;
 use crate::item::key::Key;
-use crate::item::ring::Ring;
+use crate::item::ring::{Ring, SetBonus};
+use crate::item::throwable::Throwable;
 use crate::item::Item;
 use crate::log;
-use crate::randomizer::{random, Randomizer};
-use class::Class;
+use crate::randomizer::random;
+use anyhow::{bail, Result};
+use class::{Class, Stat};
+use element::Element;
 use serde::{Deserialize, Serialize};
+use skill::Skill;
+use spell::Spell;
+use status::Statuses;
 use std::cmp::{max, min};
+use std::collections::{HashMap, HashSet};
 
 pub mod class;
+pub mod companion;
+pub mod element;
 pub mod enemy;
+pub mod skill;
+pub mod spell;
+pub mod status;
 
-#[derive(Serialize, Deserialize)]
+pub use status::StatusEffect;
+
+/// Percentage of the accumulated stats kept when changing class above level 1.
+/// The rest is recomputed from the stat growth of the new class.
+const RESPEC_KEPT: i32 = 50;
+
+/// Level at which heroes can ascend, restarting at level 1 with a higher
+/// prestige rank.
+pub const PRESTIGE_LEVEL: i32 = 100;
+
+/// Stat increase given by each prestige rank, as a fraction of the base stat.
+const PRESTIGE_BONUS: f64 = 0.1;
+
+/// Percentage by which the stats of gilded enemies are raised.
+const GILDED_BONUS: i32 = 50;
+
+/// Percentage of the damage dealt recovered as hp by wearers of the
+/// vampire ring.
+const VAMPIRE_DRAIN: i32 = 20;
+
+/// Times the strength of a character once its enrage timer runs out.
+const ENRAGE_STRENGTH: i32 = 2;
+
+/// Inventory slots of a level 1 hero, one more is added every 5 levels.
+const BASE_SLOTS: i32 = 15;
+
+/// Inventory slots added by each bag used by the hero.
+pub const BAG_SLOTS: i32 = 5;
+
+/// How many bags a hero can use.
+pub const MAX_BAGS: i32 = 4;
+
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(default)]
 pub struct Character {
     pub class: Class,
@@ -29,17 +73,58 @@ pub struct Character {
 
     pub sword: Option<equipment::Equipment>,
     pub shield: Option<equipment::Equipment>,
+    pub helmet: Option<equipment::Equipment>,
+    pub armor: Option<equipment::Equipment>,
+    pub boots: Option<equipment::Equipment>,
     pub left_ring: Option<Ring>,
     pub right_ring: Option<Ring>,
 
-    pub status_effect: Option<StatusEffect>,
-}
+    /// Times each ring was upgraded at the shop. There's one instance of each
+    /// ring, so the tier is kept while it's unequipped, but not if it's dropped.
+    pub ring_tiers: HashMap<Ring, i32>,
+
+    /// Cursed rings are stronger, but drain gold after battles and can't be
+    /// unequipped until the curse is lifted. Like tiers, this is kept by type.
+    pub cursed_rings: HashSet<Ring>,
+
+    pub status_effects: Statuses,
+
+    /// Spells learned from scrolls, in addition to the ones of the class.
+    pub learned_spells: Vec<Spell>,
+
+    /// Skills unlocked with skill points, in the order they were learned.
+    /// Only the ones in the tree of the current class take effect.
+    pub skills: Vec<Skill>,
+
+    /// Times the hero ascended, each rank permanently raises all stats.
+    /// Enemies facing a hero get the same rank to keep up with it.
+    pub prestige: i32,
+
+    /// Gilded enemies are rare variants with higher stats, that always drop
+    /// loot and double gold.
+    pub gilded: bool,
+
+    /// Turns left before a raid boss flies into a rage, if it's one.
+    pub enrage_timer: Option<i32>,
+
+    /// Bags used by the hero, each one adds inventory slots.
+    pub bags: i32,
+
+    /// Stat raises gained from stones, as (stones used, points raised) by
+    /// stat name, so they can be taken back with a stone of forgetting.
+    pub stone_raises: HashMap<String, (i32, i32)>,
+
+    /// Stones taken back by a stone of forgetting, still to be spent on
+    /// raising stats.
+    pub stone_points: i32,
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
-#[serde(rename_all = "snake_case")]
-pub enum StatusEffect {
-    Burn,
-    Poison,
+    /// Whether level ups grant points to assign to the stats, instead of
+    /// raising them along the class growth.
+    pub manual_growth: bool,
+
+    /// Points granted on level up when growing manually, still to be
+    /// assigned.
+    pub growth_points: i32,
 }
 
 /// Outcome of an attack attempt.
@@ -74,6 +159,10 @@ impl Character {
         self.class.category == class::Category::Player
     }
 
+    pub fn is_boss(&self) -> bool {
+        self.class.category == class::Category::Boss
+    }
+
     pub fn new(class: Class, level: i32) -> Self {
         let max_hp = class.hp.base();
         let strength = class.strength.base();
@@ -84,8 +173,13 @@ impl Character {
             class,
             sword: None,
             shield: None,
+            helmet: None,
+            armor: None,
+            boots: None,
             left_ring: None,
             right_ring: None,
+            ring_tiers: HashMap::new(),
+            cursed_rings: HashSet::new(),
             level: 1,
             xp: 0,
             max_hp,
@@ -94,13 +188,35 @@ impl Character {
             current_mp: max_mp,
             strength,
             speed,
-            status_effect: None,
+            status_effects: Statuses::default(),
+            learned_spells: Vec::new(),
+            skills: Vec::new(),
+            prestige: 0,
+            gilded: false,
+            enrage_timer: None,
+            bags: 0,
+            stone_raises: HashMap::new(),
+            stone_points: 0,
+            manual_growth: false,
+            growth_points: 0,
         };
 
         for _ in 1..level {
             character.raise_level();
         }
 
+        // custom player classes can start with some equipment
+        for key in character.class.equipment.clone() {
+            match key {
+                Key::Sword => character.sword = Some(equipment::Equipment::sword(1)),
+                Key::Shield => character.shield = Some(equipment::Equipment::shield(1)),
+                Key::Helmet => character.helmet = Some(equipment::Equipment::helmet(1)),
+                Key::Armor => character.armor = Some(equipment::Equipment::armor(1)),
+                Key::Boots => character.boots = Some(equipment::Equipment::boots(1)),
+                _ => {}
+            }
+        }
+
         character
     }
 
@@ -116,28 +232,36 @@ impl Character {
             if self.level == 1 {
                 // if class change is done at level 1, it works as a game reset
                 // the player stats are regenerated with the new class
-                // if equipment was already set, it is preserved
+                // if equipment was already set, it is preserved over the
+                // one the new class starts with
                 let sword = self.sword.take();
                 let shield = self.shield.take();
+                let helmet = self.helmet.take();
+                let armor = self.armor.take();
+                let boots = self.boots.take();
                 let left_ring = self.left_ring.take();
                 let right_ring = self.right_ring.take();
+                let bags = self.bags;
+                let stone_points = self.stone_points;
+                let manual_growth = self.manual_growth;
 
                 *self = Self::new(class.clone(), 1);
-                self.sword = sword;
-                self.shield = shield;
+                self.bags = bags;
+                self.stone_points = stone_points;
+                self.manual_growth = manual_growth;
+                self.sword = sword.or(self.sword.take());
+                self.shield = shield.or(self.shield.take());
+                self.helmet = helmet.or(self.helmet.take());
+                self.armor = armor.or(self.armor.take());
+                self.boots = boots.or(self.boots.take());
                 self.left_ring = left_ring;
                 self.right_ring = right_ring;
             } else {
                 self.class = class.clone();
+                self.respec();
 
-                // if switching to a magic class on a higher level, we need to
-                // force the base mp so it can attack like a level 1 char
-                // rather than having no magic at all
-                if class.is_magic() && self.max_mp == 0 {
-                    let base_mp = class.mp.as_ref().map(|mp| mp.base()).unwrap();
-                    self.max_mp = base_mp;
-                    self.current_mp = base_mp;
-                }
+                // the skill points are refunded to learn the skills of the new class
+                self.skills.clear();
             }
 
             self.xp = 0;
@@ -148,9 +272,166 @@ impl Character {
         }
     }
 
-    /// Raise the level and all the character stats.
+    /// Recompute the stats based on the growth of the current class at the
+    /// current level, keeping part of the accumulated ones, then restore
+    /// the character. Only magic classes recompute the mp, so switching to
+    /// one from a non magic class gives some mp to start with.
+    fn respec(&mut self) {
+        let level = self.level;
+        let blend = |current: i32, stat: &Stat| {
+            (current * RESPEC_KEPT + stat.at(level) * (100 - RESPEC_KEPT)) / 100
+        };
+
+        self.max_hp = blend(self.max_hp, &self.class.hp);
+        self.strength = blend(self.strength, &self.class.strength);
+        self.speed = blend(self.speed, &self.class.speed);
+        if let Some(mp) = &self.class.mp {
+            self.max_mp = blend(self.max_mp, mp);
+        }
+        // only the kept part of the stone raises can be forgotten later
+        for (_, points) in self.stone_raises.values_mut() {
+            *points = *points * RESPEC_KEPT / 100;
+        }
+        self.restore();
+    }
+
+    /// Restart the character at level 1 with the next prestige rank.
+    /// Equipment, rings, bags and learned spells are kept.
+    pub fn ascend(&mut self) -> Result<()> {
+        if self.level < PRESTIGE_LEVEL {
+            bail!("Ascension is unlocked at level {}.", PRESTIGE_LEVEL);
+        }
+
+        let mut hero = Self::new(self.class.clone(), 1);
+        hero.sword = self.sword.take().or(hero.sword);
+        hero.shield = self.shield.take().or(hero.shield);
+        hero.helmet = self.helmet.take().or(hero.helmet);
+        hero.armor = self.armor.take().or(hero.armor);
+        hero.boots = self.boots.take().or(hero.boots);
+        hero.left_ring = self.left_ring.take();
+        hero.right_ring = self.right_ring.take();
+        std::mem::swap(&mut hero.ring_tiers, &mut self.ring_tiers);
+        std::mem::swap(&mut hero.cursed_rings, &mut self.cursed_rings);
+        std::mem::swap(&mut hero.learned_spells, &mut self.learned_spells);
+        hero.bags = self.bags;
+        hero.stone_points = self.stone_points;
+        hero.manual_growth = self.manual_growth;
+        *self = hero.with_prestige(self.prestige + 1);
+        Ok(())
+    }
+
+    /// Set the prestige rank of the character, restoring it to the
+    /// resulting max hp and mp.
+    pub fn with_prestige(mut self, rank: i32) -> Self {
+        self.prestige = rank;
+        self.restore();
+        self
+    }
+
+    /// Turn the character into its gilded variant, raising its stats.
+    pub fn gild(mut self) -> Self {
+        self.gilded = true;
+        self.scaled(100 + GILDED_BONUS)
+    }
+
+    /// Scale the hp, strength and speed of the character by the given
+    /// percentage, e.g. to adjust enemies to the game difficulty.
+    pub fn scaled(mut self, percent: i32) -> Self {
+        self.max_hp = self.max_hp * percent / 100;
+        self.strength = self.strength * percent / 100;
+        self.speed = self.speed * percent / 100;
+        self.restore();
+        self
+    }
+
+    /// Scale the hp and the strength of the character separately, e.g. to
+    /// make battles against it last longer than its class would.
+    pub fn reshaped(mut self, hp_percent: i32, strength_percent: i32) -> Self {
+        self.max_hp = self.max_hp * hp_percent / 100;
+        self.strength = self.strength * strength_percent / 100;
+        self.restore();
+        self
+    }
+
+    /// The cosmetic title given by the prestige rank, if any.
+    pub fn title(&self) -> Option<&'static str> {
+        match self.prestige {
+            0 => None,
+            1 => Some("ascended"),
+            2 => Some("exalted"),
+            3 => Some("paragon"),
+            _ => Some("mythic"),
+        }
+    }
+
+    fn prestige_factor(&self) -> f64 {
+        self.prestige as f64 * PRESTIGE_BONUS
+    }
+
+    /// Skill points earned by leveling up and not spent yet.
+    pub fn skill_points(&self) -> i32 {
+        self.level / skill::SKILL_POINT_LEVELS - self.skills.len() as i32
+    }
+
+    pub fn has_skill(&self, skill: Skill) -> bool {
+        self.skills.contains(&skill) && Skill::tree(&self.class.name).contains(&skill)
+    }
+
+    /// Spend a skill point to unlock the given skill of the character class
+    /// tree, once the ones before it are unlocked.
+    pub fn learn_skill(&mut self, skill: Skill) -> Result<()> {
+        let tree = Skill::tree(&self.class.name);
+        let position = match tree.iter().position(|s| *s == skill) {
+            Some(position) => position,
+            None => bail!("{} is not a {} skill.", skill, self.class.name),
+        };
+        if self.skills.contains(&skill) {
+            bail!("{} already learned.", skill);
+        }
+        if let Some(previous) = tree[..position].iter().find(|s| !self.skills.contains(s)) {
+            bail!("{} needs to be learned first.", previous);
+        }
+        if self.skill_points() <= 0 {
+            bail!("No skill points left.");
+        }
+        self.skills.push(skill);
+        Ok(())
+    }
+
+    /// The active skill the character would use on its turn, if any of the
+    /// learned ones is useful in the current situation.
+    pub fn ready_skill(&self) -> Option<Skill> {
+        let near_death = self.current_hp < self.max_hp() / 4;
+        let low_mp = self.class.is_magic() && self.current_mp < self.max_mp() / 4;
+        [Skill::SecondWind, Skill::SmokeBomb, Skill::ManaSurge]
+            .iter()
+            .copied()
+            .filter(|skill| self.has_skill(*skill))
+            .find(|skill| match skill {
+                Skill::ManaSurge => low_mp,
+                _ => near_death,
+            })
+    }
+
+    /// Apply the effect of the given active skill on the character.
+    /// Escaping with a smoke bomb is up to the caller.
+    pub fn use_skill(&mut self, skill: Skill) {
+        let (hp, mp) = match skill {
+            Skill::SecondWind => (self.update_hp(self.max_hp() / 3).unwrap_or_default(), 0),
+            Skill::ManaSurge => (0, self.update_mp(self.max_mp() / 2)),
+            _ => (0, 0),
+        };
+        log::skill_used(self, skill, hp, mp);
+    }
+
+    /// Raise the level and all the character stats, or when growing
+    /// manually grant a point per stat to assign instead.
     pub fn raise_level(&mut self) {
         self.level += 1;
+        if self.manual_growth {
+            self.growth_points += self.stone_stats().len() as i32;
+            return;
+        }
         self.raise_strength();
         self.raise_speed();
         self.raise_hp();
@@ -189,6 +470,107 @@ impl Character {
         inc
     }
 
+    /// Raise the given stat, one of hp, mp, str or spd, as a stone does,
+    /// remembering it so it can be taken back with a stone of forgetting.
+    pub fn raise_with_stone(&mut self, stat: &str) -> i32 {
+        let inc = self.raise_stat(stat);
+        let (stones, points) = self.stone_raises.entry(stat.to_string()).or_default();
+        *stones += 1;
+        *points += inc;
+        inc
+    }
+
+    /// Raise the given stat, one of hp, mp, str or spd, as much as a level
+    /// up of the class would.
+    pub fn raise_stat(&mut self, stat: &str) -> i32 {
+        match stat {
+            "hp" => self.raise_hp(),
+            "mp" => self.raise_mp(),
+            "str" => self.raise_strength(),
+            _ => self.raise_speed(),
+        }
+    }
+
+    /// Lower the given stat, one of hp, mp, str or spd, as much as a level
+    /// up of the class would raise it, without going below 1.
+    pub fn lower_stat(&mut self, stat: &str) -> i32 {
+        let (value, growth) = match stat {
+            "hp" => (&mut self.max_hp, &self.class.hp),
+            "mp" => match &self.class.mp {
+                Some(mp) => (&mut self.max_mp, mp),
+                None => return 0,
+            },
+            "str" => (&mut self.strength, &self.class.strength),
+            _ => (&mut self.speed, &self.class.speed),
+        };
+        let dec = max(0, min(growth.increase(), *value - 1));
+        *value -= dec;
+        self.current_hp = min(self.current_hp, self.max_hp());
+        self.current_mp = min(self.current_mp, self.max_mp());
+        dec
+    }
+
+    /// The stats that stones can raise, mp only for the classes that have it.
+    pub fn stone_stats(&self) -> Vec<&'static str> {
+        let mut stats = vec!["hp", "mp", "str", "spd"];
+        if self.class.mp.is_none() {
+            stats.retain(|stat| *stat != "mp");
+        }
+        stats
+    }
+
+    /// Take back the stat raises gained from stones, returning the stones
+    /// refunded as points to spend again.
+    pub fn forget_stones(&mut self) -> i32 {
+        let mut refunded = 0;
+        for (stat, (stones, points)) in std::mem::take(&mut self.stone_raises) {
+            match stat.as_str() {
+                "hp" => self.max_hp -= points,
+                "mp" => self.max_mp -= points,
+                "str" => self.strength -= points,
+                _ => self.speed -= points,
+            }
+            refunded += stones;
+        }
+        self.current_hp = max(1, min(self.current_hp, self.max_hp()));
+        self.current_mp = min(self.current_mp, self.max_mp());
+        self.stone_points += refunded;
+        refunded
+    }
+
+    /// Spend one of the points refunded by a stone of forgetting to raise
+    /// the given stat, as if a stone for it was used.
+    pub fn spend_stone_point(&mut self, stat: &str) -> Result<i32> {
+        if self.stone_points <= 0 {
+            bail!("No stone points left.");
+        }
+        self.check_stat(stat)?;
+        self.stone_points -= 1;
+        Ok(self.raise_with_stone(stat))
+    }
+
+    /// Spend one of the points granted on level up when growing manually,
+    /// to raise the given stat.
+    pub fn spend_growth_point(&mut self, stat: &str) -> Result<i32> {
+        if self.growth_points <= 0 {
+            bail!("No level up points left.");
+        }
+        self.check_stat(stat)?;
+        self.growth_points -= 1;
+        Ok(self.raise_stat(stat))
+    }
+
+    /// Fail unless the given name is a stat the character can raise.
+    fn check_stat(&self, stat: &str) -> Result<()> {
+        if !["hp", "mp", "str", "spd"].contains(&stat) {
+            bail!("{} is not a stat, choose hp, mp, str or spd.", stat);
+        }
+        if stat == "mp" && self.class.mp.is_none() {
+            bail!("The {} class has no mp.", self.class.name);
+        }
+        Ok(())
+    }
+
     /// Add to the accumulated experience points, possibly increasing the level.
     pub fn add_experience(&mut self, xp: i32) -> i32 {
         self.xp += xp;
@@ -228,8 +610,7 @@ impl Character {
 
     /// Restore all health and magic points to their max and remove status effects
     pub fn restore(&mut self) -> (i32, i32, bool) {
-        let healed = self.status_effect.is_some();
-        self.status_effect = None;
+        let healed = self.status_effects.cure();
         (
             self.update_hp(self.max_hp()).unwrap(),
             self.update_mp(self.max_mp()),
@@ -239,9 +620,13 @@ impl Character {
 
     /// How many experience points are required to move to the next level.
     pub fn xp_for_next(&self) -> i32 {
-        let exp = 1.5;
-        let base_xp = 30.0;
-        (base_xp * (self.level as f64).powf(exp)) as i32
+        xp_for_level(self.level)
+    }
+
+    /// The experience points gained since level 1, counting the ones
+    /// spent on level ups.
+    pub fn total_xp(&self) -> i32 {
+        (1..self.level).map(xp_for_level).sum::<i32>() + self.xp
     }
 
     pub fn max_hp(&self) -> i32 {
@@ -253,7 +638,8 @@ impl Character {
     }
 
     pub fn speed(&self) -> i32 {
-        self.modify_stat(self.speed, Ring::Speed)
+        let speed = self.modify_stat(self.speed, Ring::Speed) as f64;
+        (speed * self.status_effects.boost_factor(StatusEffect::Haste)) as i32
     }
 
     /// Generate and log an attack of this character and apply its effects to
@@ -279,14 +665,35 @@ impl Character {
         // the caller to handle that scenario.
         let result = receiver.update_hp(-damage).map(|_| ());
         if let AttackType::Effect(status) = attack_type {
-            receiver.status_effect = Some(status);
+            receiver.status_effects.inflict(status);
+        }
+        if damage > 0 && self.vampire_ring() {
+            let _ = self.update_hp(max(1, damage * VAMPIRE_DRAIN / 100));
         }
 
-        log::attack(receiver, &attack_type, damage, mp_cost);
+        log::attack(self, receiver, &attack_type, damage, mp_cost);
+
+        // both the attacker's weapon and the receiver's armor are used in the turn
+        self.wear_equipment(|key| *key == Key::Sword);
+        receiver.wear_equipment(|key| *key != Key::Sword);
 
         (xp, result)
     }
 
+    /// Reduce the durability of the equipped pieces matching the given
+    /// filter, logging the ones that break.
+    fn wear_equipment(&mut self, filter: impl Fn(&Key) -> bool) {
+        let mut broken = Vec::new();
+        for piece in self.equipment_slots().iter_mut().filter_map(|p| p.as_mut()) {
+            if filter(&piece.key()) && piece.wear() {
+                broken.push(piece.to_string());
+            }
+        }
+        for piece in broken {
+            log::equipment_broken(self, &piece);
+        }
+    }
+
     /// If the double beat ring is equipped, attack the receiver.
     pub fn maybe_double_beat(&mut self, receiver: &mut Self) {
         if receiver.current_hp > 0
@@ -297,6 +704,35 @@ impl Character {
         }
     }
 
+    /// Bosses enter their second phase when their hp falls below half,
+    /// staying hasted and shielded for the rest of the battle.
+    /// Returns true when the second phase begins.
+    pub fn maybe_enrage(&mut self) -> bool {
+        if !self.is_boss() || self.current_hp * 2 > self.max_hp {
+            return false;
+        }
+        let entering = !self.status_effects.has(StatusEffect::Haste);
+        self.status_effects.inflict(StatusEffect::Haste);
+        self.status_effects.inflict(StatusEffect::Shield);
+        entering
+    }
+
+    /// Count down the turns of the enrage timer, if any. Once it runs out
+    /// the character's strength is multiplied for the rest of the battle.
+    /// Returns true on the turn it runs out.
+    pub fn tick_enrage_timer(&mut self) -> bool {
+        match self.enrage_timer {
+            Some(turns) if turns > 0 => {
+                self.enrage_timer = Some(turns - 1);
+                if turns == 1 {
+                    self.strength *= ENRAGE_STRENGTH;
+                }
+                turns == 1
+            }
+            _ => false,
+        }
+    }
+
     /// If the counter attack ring is equipped randomly counter attack the receiver.
     pub fn maybe_counter_attack(&mut self, receiver: &mut Self) {
         let wearing_counter =
@@ -356,7 +792,21 @@ impl Character {
             (self.physical_attack(), 0)
         };
 
-        (max(1, damage - receiver.deffense()), mp_cost)
+        let damage = max(1, damage - receiver.deffense()) as f64;
+        let multiplier = Element::multiplier(self.attack_element(), receiver.class.element);
+        (max(1, (damage * multiplier).round() as i32), mp_cost)
+    }
+
+    /// The element of the character attacks. Spells take the element of the
+    /// class and physical attacks the one of the sword, falling back to
+    /// each other if missing.
+    pub fn attack_element(&self) -> Option<Element> {
+        let sword_element = self.sword.as_ref().and_then(|s| s.element());
+        if self.can_magic_attack() {
+            self.class.element.or(sword_element)
+        } else {
+            sword_element.or(self.class.element)
+        }
     }
 
     /// The character's class enables magic and there's enough mp left
@@ -374,6 +824,7 @@ impl Character {
     pub fn physical_attack(&self) -> i32 {
         let sword_str = self.sword.as_ref().map_or(0, |s| s.strength());
         let attack = self.modify_stat(self.strength, Ring::Attack) + sword_str;
+        let attack = (attack as f64 * self.status_effects.attack_factor()) as i32;
         if self.class.is_magic() {
             attack / 3
         } else {
@@ -386,16 +837,183 @@ impl Character {
     pub fn magic_attack(&self) -> i32 {
         if self.class.is_magic() {
             let base = self.strength * 3;
-            self.modify_stat(base, Ring::Magic)
+            let attack = self.modify_stat(base, Ring::Magic);
+            (attack as f64 * self.status_effects.attack_factor()) as i32
         } else {
             0
         }
     }
 
     pub fn deffense(&self) -> i32 {
-        let shield_str = self.shield.as_ref().map_or(0, |s| s.strength());
+        let armor_str: i32 = [&self.shield, &self.helmet, &self.armor, &self.boots]
+            .iter()
+            .map(|piece| piece.as_ref().map_or(0, |p| p.strength()))
+            .sum();
         // base strength should be zero, subtract it from ring calculation
-        shield_str + self.modify_stat(self.strength, Ring::Deffense) - self.strength
+        let deffense = armor_str + self.modify_stat(self.strength, Ring::Deffense) - self.strength;
+        (deffense as f64 * self.status_effects.boost_factor(StatusEffect::Shield)) as i32
+    }
+
+    /// The spells this character knows: the ones unlocked by its class up
+    /// to the current level, plus the ones learned from scrolls.
+    pub fn spells(&self) -> Vec<Spell> {
+        let mut spells: Vec<Spell> = self
+            .class
+            .spells
+            .iter()
+            .filter(|(_, level)| *level <= self.level)
+            .map(|(spell, _)| *spell)
+            .collect();
+        if let Some(spell) = self.class.signature {
+            if !spells.contains(&spell) {
+                spells.insert(0, spell);
+            }
+        }
+        for spell in &self.learned_spells {
+            if !spells.contains(spell) {
+                spells.push(*spell);
+            }
+        }
+        spells
+    }
+
+    /// Add the spell to the ones known by the character.
+    /// Returns false if it was already known.
+    pub fn learn_spell(&mut self, spell: Spell) -> bool {
+        if self.spells().contains(&spell) {
+            return false;
+        }
+        self.learned_spells.push(spell);
+        true
+    }
+
+    /// Whether the character knows the spell and has enough mp to cast it,
+    /// and casting it would make a difference in the current situation.
+    pub fn should_cast(&self, spell: Spell) -> bool {
+        let useful = match spell {
+            Spell::Heal => self.current_hp < self.max_hp() / 2,
+            Spell::Fireball => true,
+            Spell::Shield => !self.status_effects.has(StatusEffect::Shield),
+            Spell::Haste => !self.status_effects.has(StatusEffect::Haste),
+        };
+        useful && self.can_cast(spell)
+    }
+
+    /// Whether the character knows the spell and has enough mp to cast it.
+    pub fn can_cast(&self, spell: Spell) -> bool {
+        self.class.is_magic()
+            && self.spells().contains(&spell)
+            && self.current_mp >= self.spell_mp_cost(spell)
+    }
+
+    /// The mp it takes this character to cast the spell, signature spells
+    /// of the class cost half.
+    pub fn spell_mp_cost(&self, spell: Spell) -> i32 {
+        let cost = spell.mp_cost(self.max_mp());
+        if self.class.signature == Some(spell) {
+            max(1, cost / 2)
+        } else {
+            cost
+        }
+    }
+
+    /// Cast the given spell and log its effects, either on the character
+    /// itself or on the receiver.
+    /// Returns a tuple with the gained experience and a Err(Dead) result if
+    /// the receiver died from the inflicted damage.
+    pub fn cast(&mut self, spell: Spell, receiver: &mut Self) -> (i32, Result<(), Dead>) {
+        let mp_cost = self.spell_mp_cost(spell);
+        self.update_mp(-mp_cost);
+        log::cast(self, spell, mp_cost);
+
+        match spell {
+            Spell::Heal => {
+                let recovered = self.update_hp(self.max_hp() / 2).unwrap_or_default();
+                log::heal_item(self, "heal", recovered, 0, false);
+            }
+            Spell::Fireball => {
+                let damage = max(1, self.magic_attack() * 3 / 2 - receiver.deffense()) as f64;
+                let multiplier = Element::multiplier(Some(Element::Fire), receiver.class.element);
+                let damage = random().damage((damage * multiplier).round() as i32);
+                let xp = self.xp_gained(receiver, damage);
+                let result = receiver.update_hp(-damage).map(|_| ());
+                log::attack(self, receiver, &AttackType::Regular, damage, 0);
+                return (xp, result);
+            }
+            Spell::Shield => self.status_effects.inflict(StatusEffect::Shield),
+            Spell::Haste => self.status_effects.inflict(StatusEffect::Haste),
+        }
+        (0, Ok(()))
+    }
+
+    /// Throw an item at the receiver: bombs burn it, shuriken hit it
+    /// regardless of its defense and holy water hurts it if undead or
+    /// stuns it otherwise. Returns the xp gained and whether the receiver
+    /// died, as attacks do.
+    pub fn throw(&self, item: Throwable, receiver: &mut Self) -> (i32, Result<(), Dead>) {
+        let (damage, status) = match item {
+            Throwable::Bomb => {
+                let damage = random().damage(10 * self.level) as f64;
+                let multiplier = Element::multiplier(Some(Element::Fire), receiver.class.element);
+                (
+                    (damage * multiplier).round() as i32,
+                    Some(StatusEffect::Burn),
+                )
+            }
+            Throwable::Shuriken => (random().damage(self.physical_attack()), None),
+            Throwable::HolyWater if enemy::is_undead(&receiver.class) => {
+                (max(1, receiver.max_hp() / 3), None)
+            }
+            Throwable::HolyWater => (0, Some(StatusEffect::Stun)),
+        };
+        let xp = self.xp_gained(receiver, damage);
+        let result = receiver.update_hp(-damage).map(|_| ());
+
+        let attack_type = if let Some(status) = status {
+            receiver.status_effects.inflict(status);
+            AttackType::Effect(status)
+        } else {
+            AttackType::Regular
+        };
+        log::attack(self, receiver, &attack_type, damage, 0);
+        (xp, result)
+    }
+
+    /// The equipment slots of the character, in display order.
+    pub fn equipment_slots(&mut self) -> [&mut Option<equipment::Equipment>; 5] {
+        [
+            &mut self.sword,
+            &mut self.shield,
+            &mut self.helmet,
+            &mut self.armor,
+            &mut self.boots,
+        ]
+    }
+
+    /// Return the equipped piece for the given key, if any.
+    pub fn equipment(&self, key: &Key) -> Option<&equipment::Equipment> {
+        [
+            &self.sword,
+            &self.shield,
+            &self.helmet,
+            &self.armor,
+            &self.boots,
+        ]
+        .iter()
+        .filter_map(|piece| piece.as_ref())
+        .find(|piece| piece.key() == *key)
+    }
+
+    /// Wear the given piece in its slot, replacing the one worn there.
+    pub fn equip(&mut self, piece: equipment::Equipment) {
+        let slot = match piece.key() {
+            Key::Sword => &mut self.sword,
+            Key::Shield => &mut self.shield,
+            Key::Helmet => &mut self.helmet,
+            Key::Armor => &mut self.armor,
+            _ => &mut self.boots,
+        };
+        slot.replace(piece);
     }
 
     /// How many experience points are gained by inflicting damage to an enemy.
@@ -403,6 +1021,7 @@ impl Character {
         let class_multiplier = match receiver.class.category {
             class::Category::Rare => 3,
             class::Category::Legendary => 5,
+            class::Category::Boss => 10,
             _ => 1,
         };
 
@@ -434,16 +1053,17 @@ impl Character {
 
         let result = self.class.inflicts.or(ring_status);
         if let Some((status, _)) = result {
-            // don't double-inflict if already has the same status
-            if receiver.status_effect == Some(status) {
+            // don't inflict if it can't be stacked any further
+            if receiver.status_effects.is_maxed(status) {
                 return None;
             }
         }
         result
     }
 
-    /// If the character has a status condition (e.g. poison) or an equipped
-    /// ring that produces one (e.g. regen hp), apply its effects.
+    /// If the character has status effects (e.g. poison) or an equipped
+    /// ring that produces one (e.g. regen hp), apply them and count down
+    /// a turn of the status effects duration.
     pub fn apply_status_effects(&mut self) -> Result<(), Dead> {
         let mut hp_effect = 0;
         let mut mp_effect = 0;
@@ -466,9 +1086,7 @@ impl Character {
             hp_effect -= hp_unit();
         }
 
-        if self.status_effect == Some(StatusEffect::Burn)
-            || self.status_effect == Some(StatusEffect::Poison)
-        {
+        for _ in 0..self.status_effects.damage_units() {
             hp_effect -= hp_unit();
         }
 
@@ -477,6 +1095,10 @@ impl Character {
 
         log::status_effect(self, hp_effect, mp_effect);
 
+        for expired in self.status_effects.tick() {
+            log::status_expired(self, expired);
+        }
+
         result
     }
 
@@ -489,8 +1111,17 @@ impl Character {
 
     /// Equip the given ring and apply its side-effects.
     /// If already carrying two rings, the least recently equipped one is
-    /// removed, undoing its side-effects.
+    /// removed, undoing its side-effects. Cursed rings are never removed,
+    /// so a cursed least recent ring stays and the other one is replaced.
     pub fn equip_ring(&mut self, ring: Ring) -> Option<Ring> {
+        if self.right_ring.as_ref().is_some_and(|r| self.is_cursed(r)) {
+            let removed = self.left_ring.take();
+            self.unequip_ring_side_effect(&removed);
+            self.equip_ring_side_effect(&ring);
+            self.left_ring = Some(ring);
+            return removed;
+        }
+
         let removed = self.right_ring.take();
         self.unequip_ring_side_effect(&removed);
         self.equip_ring_side_effect(&ring);
@@ -518,42 +1149,165 @@ impl Character {
         }
     }
 
+    /// Whether the given ring is cursed.
+    pub fn is_cursed(&self, ring: &Ring) -> bool {
+        self.cursed_rings.contains(ring)
+    }
+
+    /// Whether another ring can be equipped, i.e. not both of the worn
+    /// rings are cursed.
+    pub fn can_equip_ring(&self) -> bool {
+        ![&self.left_ring, &self.right_ring]
+            .iter()
+            .all(|ring| ring.as_ref().is_some_and(|r| self.is_cursed(r)))
+    }
+
+    /// The number of cursed rings and equipment pieces being worn.
+    pub fn curses(&self) -> i32 {
+        let rings = [&self.left_ring, &self.right_ring]
+            .iter()
+            .filter(|ring| ring.as_ref().is_some_and(|r| self.is_cursed(r)))
+            .count();
+        let pieces = [
+            &self.sword,
+            &self.shield,
+            &self.helmet,
+            &self.armor,
+            &self.boots,
+        ]
+        .iter()
+        .filter(|piece| piece.as_ref().is_some_and(|p| p.is_cursed()))
+        .count();
+        (rings + pieces) as i32
+    }
+
+    /// Lift the curse of every ring and equipment piece, returning whether
+    /// there was any.
+    pub fn lift_curses(&mut self) -> bool {
+        let mut lifted = !self.cursed_rings.is_empty();
+        self.cursed_rings.clear();
+        // the stronger effect of the worn rings goes away with the curse
+        self.current_hp = min(self.current_hp, self.max_hp());
+        self.current_mp = min(self.current_mp, self.max_mp());
+
+        for piece in self.equipment_slots().iter_mut().filter_map(|p| p.as_mut()) {
+            lifted = lifted || piece.is_cursed();
+            piece.lift_curse();
+        }
+        lifted
+    }
+
     /// Return true if an evade ring is equipped, i.e. no enemies should appear.
     pub fn enemies_evaded(&self) -> bool {
         self.left_ring == Some(Ring::Evade) || self.right_ring == Some(Ring::Evade)
     }
 
+    /// How good the character is at dealing with chest traps: one point for
+    /// a class with the disarm skill and one for wearing the trap ring.
+    pub fn trap_skill(&self) -> i32 {
+        let mut skill = 0;
+        if self.class.skills.contains(&class::Skill::Disarm) {
+            skill += 1;
+        }
+        if self.left_ring == Some(Ring::Trap) || self.right_ring == Some(Ring::Trap) {
+            skill += 1;
+        }
+        skill
+    }
+
+    /// The bonus granted by the pair of rings the character is wearing, if any.
+    pub fn set_bonus(&self) -> Option<SetBonus> {
+        match (&self.left_ring, &self.right_ring) {
+            (Some(left), Some(right)) => SetBonus::of(left, right),
+            _ => None,
+        }
+    }
+
+    /// How many inventory slots the hero has, more the higher their level
+    /// and the more bags they used.
+    pub fn inventory_slots(&self) -> i32 {
+        BASE_SLOTS + self.level / 5 + BAG_SLOTS * self.bags
+    }
+
     /// Return true if a chest ring is equipped, i.e. the character should have double
     /// chance to find a chest.
     pub fn double_chests(&self) -> bool {
         self.left_ring == Some(Ring::Chest) || self.right_ring == Some(Ring::Chest)
     }
 
+    /// Return true if a gold ring is equipped.
+    pub fn gold_ring(&self) -> bool {
+        self.left_ring == Some(Ring::Gold) || self.right_ring == Some(Ring::Gold)
+    }
+
+    /// Return true if a vampire ring is equipped, i.e. attacks drain hp.
+    pub fn vampire_ring(&self) -> bool {
+        self.left_ring == Some(Ring::Vampire) || self.right_ring == Some(Ring::Vampire)
+    }
+
+    /// Return true if a balance ring is equipped, i.e. chaos stones don't
+    /// lower any stat.
+    pub fn balance_ring(&self) -> bool {
+        self.left_ring == Some(Ring::Balance) || self.right_ring == Some(Ring::Balance)
+    }
+
     /// Return the gold that should be rewarded for beating an enemy of the given
-    /// level. Doubled if the gold ring is equipped.
+    /// level. Doubled if the gold ring is equipped, and raised along with
+    /// the stats by the prestige rank.
     pub fn gold_gained(&self, enemy_level: i32) -> i32 {
-        let level = max(1, enemy_level - self.level);
-        let gold = random().gold_gained(level * 50);
-
-        if self.level > enemy_level + 10 {
-            // don't reward cheap victories
-            0
-        } else if self.left_ring == Some(Ring::Gold) || self.right_ring == Some(Ring::Gold) {
+        let gold = self.base_gold(enemy_level);
+        if self.gold_ring() {
             gold * 2
         } else {
             gold
         }
     }
 
+    /// Same as `gold_gained` but regardless of the gold ring.
+    pub fn base_gold(&self, enemy_level: i32) -> i32 {
+        if self.level > enemy_level + 10 {
+            // don't reward cheap victories
+            return 0;
+        }
+        let level = max(1, enemy_level - self.level);
+        let gold = random().gold_gained(level * 50);
+        (gold as f64 * (1.0 + self.prestige_factor())).round() as i32
+    }
+
+    pub fn ring_tier(&self, ring: &Ring) -> i32 {
+        self.ring_tiers.get(ring).copied().unwrap_or(0)
+    }
+
+    /// Raise the upgrade tier of the given ring, applying the increased effect
+    /// if it's equipped.
+    pub fn upgrade_ring(&mut self, ring: &Ring) {
+        let equipped =
+            self.left_ring.as_ref() == Some(ring) || self.right_ring.as_ref() == Some(ring);
+        if equipped {
+            self.unequip_ring_side_effect(&Some(ring.clone()));
+        }
+        *self.ring_tiers.entry(ring.clone()).or_insert(0) += 1;
+        if equipped {
+            self.equip_ring_side_effect(ring);
+        }
+    }
+
+    /// The factor applied to the base stat modified by the ring, increased
+    /// by half its value for each upgrade tier, and doubled if it's cursed.
+    fn ring_factor(&self, ring: &Ring) -> f64 {
+        let curse = if self.is_cursed(ring) { 2.0 } else { 1.0 };
+        ring.factor() * (1.0 + 0.5 * self.ring_tier(ring) as f64) * curse
+    }
+
     /// Apply any side-effects of the ring over the character stats, e.g.
     /// increasing its max hp for an HP ring.
     fn equip_ring_side_effect(&mut self, ring: &Ring) {
-        match ring {
+        match ring.base() {
             Ring::HP => {
-                self.current_hp += (ring.factor() * self.max_hp as f64) as i32;
+                self.current_hp += (self.ring_factor(ring) * self.max_hp as f64) as i32;
             }
             Ring::MP => {
-                self.current_mp += (ring.factor() * self.max_mp as f64) as i32;
+                self.current_mp += (self.ring_factor(ring) * self.max_mp as f64) as i32;
             }
             _ => {}
         }
@@ -561,33 +1315,54 @@ impl Character {
 
     /// Unapply the side-effects of the ring on the character.
     fn unequip_ring_side_effect(&mut self, ring: &Option<Ring>) {
-        match ring {
+        match ring.as_ref().map(|ring| ring.base()) {
             Some(Ring::HP) => {
-                let to_remove = (ring.as_ref().unwrap().factor() * self.max_hp as f64) as i32;
+                let factor = self.ring_factor(ring.as_ref().unwrap());
+                let to_remove = (factor * self.max_hp as f64) as i32;
                 self.current_hp = max(1, self.current_hp - to_remove);
             }
             Some(Ring::MP) => {
-                let to_remove = (ring.as_ref().unwrap().factor() * self.max_mp as f64) as i32;
+                let factor = self.ring_factor(ring.as_ref().unwrap());
+                let to_remove = (factor * self.max_mp as f64) as i32;
                 self.current_mp = max(1, self.current_mp - to_remove);
             }
             _ => {}
         }
     }
 
-    /// If either ring matches the given one, apply the ring effect
-    /// to the given base stat, e.g. for an HP ring increase the base HP.
+    /// If either ring matches the given one, or is an upgrade of it, apply
+    /// the ring effect to the given base stat, e.g. for an HP ring increase
+    /// the base HP. Matching ring pairs, passive skills and the prestige rank
+    /// may increase the stat further.
     fn modify_stat(&self, base: i32, ring: Ring) -> i32 {
-        let mut factor = 1.0;
-        if self.left_ring.as_ref() == Some(&ring) {
-            factor += ring.factor();
+        let mut factor = 1.0 + self.set_bonus().map_or(0.0, |bonus| bonus.factor(&ring));
+        factor += self.prestige_factor();
+        for skill in &self.skills {
+            if self.has_skill(*skill) {
+                factor += skill.factor(&ring);
+            }
         }
-        if self.right_ring.as_ref() == Some(&ring) {
-            factor += ring.factor();
+        for equipped in [&self.left_ring, &self.right_ring]
+            .iter()
+            .copied()
+            .flatten()
+        {
+            if equipped.base() == ring {
+                factor += self.ring_factor(equipped);
+            }
         }
         (base as f64 * factor).round() as i32
     }
 }
 
+/// How many experience points a character of the given level requires to
+/// move to the next one.
+fn xp_for_level(level: i32) -> i32 {
+    let exp = 1.5;
+    let base_xp = 30.0;
+    (base_xp * (level as f64).powf(exp)) as i32
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -604,7 +1379,7 @@ mod tests {
         assert_eq!(hero.class.hp.base(), hero.max_hp);
         assert_eq!(hero.class.strength.base(), hero.strength);
         assert_eq!(hero.class.speed.base(), hero.speed);
-        assert!(hero.status_effect.is_none());
+        assert!(hero.status_effects.is_empty());
     }
 
     #[test]
@@ -662,6 +1437,69 @@ mod tests {
         assert_eq!(40, foe.damage(&hero).0);
     }
 
+    #[test]
+    fn elemental_damage() {
+        let mut hero = new_char();
+        let mut foe = new_char();
+        hero.strength = 10;
+        foe.strength = 10;
+
+        // a fire sword against an ice enemy
+        hero.sword = Some(equipment::Equipment::elemental_sword(1, Element::Fire));
+        let damage = hero.physical_attack();
+        foe.class.element = Some(Element::Ice);
+        assert_eq!(Some(Element::Fire), hero.attack_element());
+        assert_eq!(damage * 2, hero.damage(&foe).0);
+
+        // a fire enemy resists it
+        foe.class.element = Some(Element::Fire);
+        assert_eq!(damage / 2, hero.damage(&foe).0);
+
+        // the enemy attacks with the class element
+        hero.class.element = Some(Element::Ice);
+        assert_eq!(Some(Element::Fire), foe.attack_element());
+        assert_eq!(20, foe.damage(&hero).0);
+    }
+
+    #[test]
+    fn boss_phases() {
+        let mut boss = new_char();
+        boss.max_hp = 100;
+        boss.current_hp = 60;
+        assert!(!boss.maybe_enrage());
+
+        boss.class.category = class::Category::Boss;
+        assert!(!boss.maybe_enrage());
+
+        let speed = boss.speed();
+        boss.current_hp = 50;
+        assert!(boss.maybe_enrage());
+        assert!(boss.speed() > speed);
+        assert!(boss.status_effects.has(StatusEffect::Shield));
+
+        // the second phase starts only once but its effects are renewed
+        boss.status_effects.tick();
+        assert!(!boss.maybe_enrage());
+        assert!(boss.status_effects.has(StatusEffect::Haste));
+    }
+
+    #[test]
+    fn enrage_timer() {
+        let mut boss = new_char();
+        let strength = boss.strength;
+        assert!(!boss.tick_enrage_timer());
+
+        boss.enrage_timer = Some(2);
+        assert!(!boss.tick_enrage_timer());
+        assert_eq!(strength, boss.strength);
+        assert!(boss.tick_enrage_timer());
+        assert_eq!(strength * ENRAGE_STRENGTH, boss.strength);
+
+        // the rage lasts, but it isn't doubled again
+        assert!(!boss.tick_enrage_timer());
+        assert_eq!(strength * ENRAGE_STRENGTH, boss.strength);
+    }
+
     #[test]
     fn test_xp_gained() {
         let hero = new_char();
@@ -714,6 +1552,7 @@ mod tests {
         assert_eq!(1, hero.add_experience(25));
         assert_eq!(2, hero.level);
         assert_eq!(15, hero.xp);
+        assert_eq!(45, hero.total_xp());
 
         // multiple increases at once
         let mut hero = new_char();
@@ -791,24 +1630,48 @@ mod tests {
         hero.apply_status_effects().unwrap_or_default();
         assert_eq!(25, hero.current_hp);
 
-        hero.status_effect = Some(StatusEffect::Burn);
+        hero.status_effects.inflict(StatusEffect::Burn);
         hero.apply_status_effects().unwrap_or_default();
         assert_eq!(24, hero.current_hp);
 
-        hero.status_effect = Some(StatusEffect::Poison);
+        // effects stack
+        hero.status_effects.inflict(StatusEffect::Poison);
         hero.apply_status_effects().unwrap_or_default();
-        assert_eq!(23, hero.current_hp);
+        assert_eq!(22, hero.current_hp);
 
-        hero.status_effect = None;
+        // burn expires, poison stays
         hero.apply_status_effects().unwrap_or_default();
-        assert_eq!(23, hero.current_hp);
+        assert_eq!(20, hero.current_hp);
+        assert!(!hero.status_effects.has(StatusEffect::Burn));
+        hero.apply_status_effects().unwrap_or_default();
+        assert_eq!(19, hero.current_hp);
+
+        hero.status_effects.cure();
+        hero.apply_status_effects().unwrap_or_default();
+        assert_eq!(19, hero.current_hp);
+
+        // stun doesn't deal damage
+        hero.status_effects.inflict(StatusEffect::Stun);
+        hero.apply_status_effects().unwrap_or_default();
+        assert_eq!(19, hero.current_hp);
+        assert!(hero.status_effects.is_empty());
 
-        hero.status_effect = Some(StatusEffect::Burn);
+        hero.status_effects.inflict(StatusEffect::Burn);
         hero.current_hp = 1;
         assert!(hero.apply_status_effects().is_err());
         assert_eq!(0, hero.current_hp);
     }
 
+    #[test]
+    fn poison_weakens_attack() {
+        let mut hero = new_plain_stats_char();
+        assert_eq!(30, hero.magic_attack());
+
+        hero.status_effects.inflict(StatusEffect::Poison);
+        hero.status_effects.inflict(StatusEffect::Poison);
+        assert_eq!(24, hero.magic_attack());
+    }
+
     #[test]
     fn apply_ring_status() {
         let mut hero = new_char();
@@ -859,7 +1722,7 @@ mod tests {
 
         // hp - burn cancel each other
         hero.right_ring = None;
-        hero.status_effect = Some(StatusEffect::Burn);
+        hero.status_effects.inflict(StatusEffect::Burn);
         hero.apply_status_effects().unwrap_or_default();
         assert_eq!(23, hero.current_hp);
         assert_eq!(10, hero.current_mp);
@@ -902,14 +1765,27 @@ mod tests {
         assert!(player.sword.is_some());
 
         // attempt change to different class at level 2
+        // half the stats are kept, the rest comes from the new class growth
         player.level = 2;
         player.xp = 20;
+        player.current_hp = 1;
         assert!(player.change_class("warrior").is_ok());
         assert_eq!("warrior", player.class.name);
         assert_eq!(0, player.xp);
-        assert_eq!(player.max_hp, thief_class.hp.base());
-        assert_eq!(player.strength, thief_class.strength.base());
-        assert_eq!(player.speed, thief_class.speed.base());
+        let blend = |current: i32, stat: &Stat| (current + stat.at(2)) / 2;
+        assert_eq!(
+            player.max_hp,
+            blend(thief_class.hp.base(), &warrior_class.hp)
+        );
+        assert_eq!(
+            player.strength,
+            blend(thief_class.strength.base(), &warrior_class.strength)
+        );
+        assert_eq!(
+            player.speed,
+            blend(thief_class.speed.base(), &warrior_class.speed)
+        );
+        assert_eq!(player.max_hp, player.current_hp);
         assert!(player.sword.is_some());
     }
 
@@ -936,10 +1812,12 @@ mod tests {
         assert_eq!(0, player.max_mp);
         assert_eq!(0, player.current_mp);
 
-        // in level > 1, change to magic class should give base magic instead of zero
+        // in level > 1, change to magic class should give some magic instead of zero
         player.change_class("mage").unwrap_or_default();
-        assert_eq!(base_mp, player.max_mp);
-        assert_eq!(base_mp, player.current_mp);
+        let mp = player.class.mp.as_ref().unwrap().at(3) / 2;
+        assert!(mp > 0);
+        assert_eq!(mp, player.max_mp);
+        assert_eq!(mp, player.current_mp);
     }
 
     #[test]
@@ -991,6 +1869,139 @@ mod tests {
         assert_eq!(((base_strength + sword_strength) / 3, 0), mage.damage(&foe));
     }
 
+    #[test]
+    fn learn_spells() {
+        let mut mage = Character::player();
+        mage.change_class("mage").unwrap_or_default();
+        assert_eq!(vec![Spell::Heal], mage.spells());
+
+        mage.level = 5;
+        assert_eq!(
+            vec![Spell::Heal, Spell::Fireball, Spell::Shield],
+            mage.spells()
+        );
+
+        assert!(!mage.learn_spell(Spell::Fireball));
+        assert!(mage.learn_spell(Spell::Haste));
+        assert!(!mage.learn_spell(Spell::Haste));
+        assert_eq!(4, mage.spells().len());
+    }
+
+    #[test]
+    fn custom_class_abilities() {
+        let mage = Class::player_by_name("mage").unwrap();
+        let class = Class {
+            equipment: vec![Key::Sword, Key::Boots],
+            signature: Some(Spell::Fireball),
+            ..mage.clone()
+        };
+        let mut hero = Character::new(class, 1);
+        assert_eq!(1, hero.sword.as_ref().unwrap().level());
+        assert!(hero.boots.is_some());
+        assert!(hero.shield.is_none());
+        assert_eq!(vec![Spell::Fireball, Spell::Heal], hero.spells());
+
+        hero.max_mp = 40;
+        assert_eq!(8, hero.spell_mp_cost(Spell::Fireball));
+        assert_eq!(10, hero.spell_mp_cost(Spell::Heal));
+
+        // equipment found before a level 1 class change is kept
+        hero.sword = Some(equipment::Equipment::sword(5));
+        hero.change_class("warrior").unwrap_or_default();
+        assert_eq!(5, hero.sword.as_ref().unwrap().level());
+        assert!(hero.boots.is_some());
+        assert_eq!(Vec::<Spell>::new(), hero.spells());
+    }
+
+    #[test]
+    fn throw_items() {
+        let hero = new_char();
+
+        let mut foe = new_char();
+        let (_, result) = hero.throw(Throwable::Bomb, &mut foe);
+        assert!(result.is_ok());
+        assert!(foe.current_hp < foe.max_hp());
+        assert!(foe.status_effects.has(StatusEffect::Burn));
+
+        // holy water stuns the living
+        let mut foe = new_char();
+        let _ = hero.throw(Throwable::HolyWater, &mut foe);
+        assert_eq!(foe.max_hp(), foe.current_hp);
+        assert!(foe.status_effects.has(StatusEffect::Stun));
+
+        // and hurts the undead
+        let zombie = Class::enemy_by_name("zombie").unwrap().clone();
+        let mut foe = Character::new(zombie, 1);
+        let _ = hero.throw(Throwable::HolyWater, &mut foe);
+        assert_eq!(foe.max_hp() - foe.max_hp() / 3, foe.current_hp);
+        assert!(!foe.status_effects.has(StatusEffect::Stun));
+    }
+
+    #[test]
+    fn cast_spells() {
+        let mut mage = Character::player();
+        mage.change_class("mage").unwrap_or_default();
+        let mut foe = new_char();
+
+        // no need to heal at full hp
+        assert!(!mage.should_cast(Spell::Heal));
+        mage.current_hp = 1;
+        assert!(mage.should_cast(Spell::Heal));
+
+        let mp = mage.current_mp;
+        let (xp, result) = mage.cast(Spell::Heal, &mut foe);
+        assert_eq!((0, true), (xp, result.is_ok()));
+        assert_eq!(1 + mage.max_hp() / 2, mage.current_hp);
+        assert_eq!(mp - Spell::Heal.mp_cost(mage.max_mp()), mage.current_mp);
+
+        // not known
+        assert!(!mage.should_cast(Spell::Shield));
+        mage.learn_spell(Spell::Shield);
+        assert!(mage.should_cast(Spell::Shield));
+        let deffense = mage.deffense();
+        let _ = mage.cast(Spell::Shield, &mut foe);
+        assert!(mage.status_effects.has(StatusEffect::Shield));
+        assert!(!mage.should_cast(Spell::Shield));
+        assert_eq!((deffense as f64 * 1.5) as i32, mage.deffense());
+
+        // not enough mp
+        mage.learn_spell(Spell::Fireball);
+        mage.current_mp = 0;
+        assert!(!mage.should_cast(Spell::Fireball));
+        mage.current_mp = mage.max_mp();
+        assert!(mage.should_cast(Spell::Fireball));
+        let hp = foe.current_hp;
+        let _ = mage.cast(Spell::Fireball, &mut foe);
+        assert!(foe.current_hp < hp);
+
+        // only for magic classes
+        let mut warrior = Character::player();
+        warrior.learn_spell(Spell::Fireball);
+        warrior.max_mp = 10;
+        warrior.current_mp = 10;
+        assert!(!warrior.should_cast(Spell::Fireball));
+    }
+
+    #[test]
+    fn test_armor_deffense() {
+        let mut char = new_plain_stats_char();
+        assert_eq!(0, char.deffense());
+
+        char.shield = Some(equipment::Equipment::shield(1));
+        let shield_deffense = char.deffense();
+        assert!(shield_deffense > 0);
+
+        char.helmet = Some(equipment::Equipment::helmet(1));
+        char.armor = Some(equipment::Equipment::armor(1));
+        char.boots = Some(equipment::Equipment::boots(1));
+        let total: i32 = [Key::Helmet, Key::Armor, Key::Boots]
+            .iter()
+            .map(|key| char.equipment(key).unwrap().strength())
+            .sum();
+        assert_eq!(shield_deffense + total, char.deffense());
+        assert!(char.equipment(&Key::Sword).is_none());
+    }
+
     #[test]
     fn test_hp_ring() {
         let mut char = new_plain_stats_char();
@@ -1098,6 +2109,68 @@ mod tests {
         assert_eq!(15, char.speed());
     }
 
+    #[test]
+    fn test_vampire_ring() {
+        let mut char = new_plain_stats_char();
+        let mut enemy = new_plain_stats_char();
+        enemy.max_hp = 1000;
+        enemy.current_hp = 1000;
+        char.equip_ring(Ring::Vampire);
+        char.current_hp = 1;
+        let _ = char.attack(&mut enemy);
+        let damage = enemy.max_hp() - enemy.current_hp;
+        assert!(damage > 0);
+        assert_eq!(1 + max(1, damage * VAMPIRE_DRAIN / 100), char.current_hp);
+    }
+
+    #[test]
+    fn test_ring_tiers() {
+        let mut char = new_plain_stats_char();
+        char.equip_ring(Ring::Speed);
+        assert_eq!(15, char.speed());
+
+        char.upgrade_ring(&Ring::Speed);
+        assert_eq!(18, char.speed());
+        char.upgrade_ring(&Ring::Speed);
+        assert_eq!(20, char.speed());
+
+        // the tier is kept while the ring is unequipped
+        char.unequip_ring(&Key::Ring(Ring::Speed));
+        assert_eq!(10, char.speed());
+        char.equip_ring(Ring::Speed);
+        assert_eq!(20, char.speed());
+
+        let data = serde_json::to_string(&char).unwrap();
+        let char: Character = serde_json::from_str(&data).unwrap();
+        assert_eq!(2, char.ring_tier(&Ring::Speed));
+
+        // upgrading an equipped hp ring also raises the current hp
+        let mut char = new_plain_stats_char();
+        char.equip_ring(Ring::HP);
+        let hp = char.current_hp;
+        char.upgrade_ring(&Ring::HP);
+        assert!(char.current_hp > hp);
+    }
+
+    #[test]
+    fn test_set_bonus() {
+        let mut char = new_plain_stats_char();
+        char.class.mp = None;
+        char.equip_ring(Ring::Attack);
+        assert_eq!(None, char.set_bonus());
+        assert_eq!(15, char.physical_attack());
+
+        char.equip_ring(Ring::Deffense);
+        assert_eq!(Some(SetBonus::Might), char.set_bonus());
+        assert_eq!(18, char.physical_attack());
+        assert_eq!(8, char.deffense());
+
+        // the bonus doesn't apply to unrelated stats
+        char.equip_ring(Ring::Speed);
+        assert_eq!(None, char.set_bonus());
+        assert_eq!(15, char.speed());
+    }
+
     #[test]
     fn test_status_rings() {
         let mut char = new_plain_stats_char();
@@ -1131,6 +2204,14 @@ mod tests {
             char.inflicted_status_effect(&another)
         );
 
+        // can be stacked until maxed
+        another.status_effects.inflict(StatusEffect::Poison);
+        assert!(char.inflicted_status_effect(&another).is_some());
+        another.status_effects.inflict(StatusEffect::Poison);
+        another.status_effects.inflict(StatusEffect::Poison);
+        assert!(char.inflicted_status_effect(&another).is_none());
+        another.status_effects.cure();
+
         another.left_ring = Some(Ring::Protect);
         assert!(char.inflicted_status_effect(&another).is_none());
 
@@ -1139,6 +2220,64 @@ mod tests {
         assert!(char.inflicted_status_effect(&another).is_none());
     }
 
+    #[test]
+    fn test_skills() {
+        let mut player = Character::player();
+        assert_eq!(0, player.skill_points());
+        assert!(player.learn_skill(Skill::Toughness).is_err());
+
+        player.level = 10;
+        assert_eq!(2, player.skill_points());
+        assert!(player.learn_skill(Skill::Pickpocket).is_err());
+        assert!(player.learn_skill(Skill::Bloodlust).is_err());
+        let max_hp = player.max_hp();
+        player.learn_skill(Skill::Toughness).unwrap();
+        assert!(player.learn_skill(Skill::Toughness).is_err());
+        assert_eq!((max_hp as f64 * 1.2).round() as i32, player.max_hp());
+
+        // active skills are only ready when needed
+        player.learn_skill(Skill::Bloodlust).unwrap();
+        player.skills.push(Skill::SecondWind);
+        assert_eq!(None, player.ready_skill());
+        player.current_hp = 1;
+        assert_eq!(Some(Skill::SecondWind), player.ready_skill());
+        player.use_skill(Skill::SecondWind);
+        assert_eq!(1 + player.max_hp() / 3, player.current_hp);
+
+        // skills are refunded when changing class
+        player.change_class("thief").unwrap_or_default();
+        assert!(player.skills.is_empty());
+        assert_eq!(2, player.skill_points());
+    }
+
+    #[test]
+    fn ascend() {
+        let mut hero = new_char();
+        hero.level = 99;
+        assert!(hero.ascend().is_err());
+
+        hero.level = PRESTIGE_LEVEL;
+        hero.sword = Some(equipment::Equipment::sword(50));
+        hero.left_ring = Some(Ring::HP);
+        hero.ascend().unwrap();
+        assert_eq!(1, hero.level);
+        assert_eq!(1, hero.prestige);
+        assert_eq!(Some("ascended"), hero.title());
+        assert_eq!(50, hero.sword.as_ref().unwrap().level());
+        assert_eq!(Some(Ring::HP), hero.left_ring);
+        assert_eq!(hero.max_hp(), hero.current_hp);
+        let gold = new_char().gold_gained(1);
+        assert_eq!((gold as f64 * 1.1).round() as i32, hero.gold_gained(1));
+
+        // each rank raises the stats further
+        let plain = hero.modify_stat(100, Ring::Speed);
+        assert_eq!(110, plain);
+        hero.level = PRESTIGE_LEVEL;
+        hero.ascend().unwrap();
+        assert_eq!(120, hero.modify_stat(100, Ring::Speed));
+        assert_eq!(Some("exalted"), hero.title());
+    }
+
     #[test]
     fn modify_stat() {
         let mut char = new_plain_stats_char();
@@ -1319,6 +2458,75 @@ mod tests {
         assert_eq!(300, player.gold_gained(4));
     }
 
+    #[test]
+    fn trap_skill() {
+        let mut player = new_char();
+        assert_eq!(0, player.trap_skill());
+
+        player.right_ring = Some(Ring::Trap);
+        assert_eq!(1, player.trap_skill());
+
+        let mut thief = Character::new(Class::player_by_name("thief").unwrap().clone(), 1);
+        assert_eq!(1, thief.trap_skill());
+        thief.left_ring = Some(Ring::Trap);
+        assert_eq!(2, thief.trap_skill());
+    }
+
+    #[test]
+    fn forget_stones() {
+        let mut hero = new_char();
+        let (max_hp, strength) = (hero.max_hp, hero.strength);
+        let hp = hero.raise_with_stone("hp");
+        hero.raise_with_stone("hp");
+        hero.raise_with_stone("str");
+        assert!(hp > 0);
+        assert!(hero.spend_stone_point("hp").is_err());
+
+        assert_eq!(3, hero.forget_stones());
+        assert_eq!(max_hp, hero.max_hp);
+        assert_eq!(strength, hero.strength);
+        assert!(hero.current_hp <= hero.max_hp);
+        assert!(hero.stone_raises.is_empty());
+        assert_eq!(0, hero.forget_stones());
+
+        // the class has no mp to raise
+        assert!(hero.spend_stone_point("mp").is_err());
+        assert!(hero.spend_stone_point("luck").is_err());
+        assert_eq!(3, hero.stone_points);
+        for _ in 0..3 {
+            assert!(hero.spend_stone_point("spd").unwrap() > 0);
+        }
+        assert!(hero.speed > new_char().speed);
+        assert_eq!((3, hero.speed - new_char().speed), hero.stone_raises["spd"]);
+        assert!(hero.spend_stone_point("spd").is_err());
+    }
+
+    #[test]
+    fn manual_growth() {
+        let mut hero = new_char();
+        hero.manual_growth = true;
+        let (max_hp, strength, speed) = (hero.max_hp, hero.strength, hero.speed);
+        hero.add_experience(hero.xp_for_next());
+        assert_eq!(2, hero.level);
+        assert_eq!(max_hp, hero.max_hp);
+        // no mp to grow for this class
+        assert_eq!(3, hero.growth_points);
+
+        assert!(hero.spend_growth_point("mp").is_err());
+        for _ in 0..3 {
+            assert_eq!(3, hero.spend_growth_point("str").unwrap());
+        }
+        assert!(hero.spend_growth_point("str").is_err());
+        assert_eq!(strength + 9, hero.strength);
+        assert_eq!(speed, hero.speed);
+        assert_eq!(max_hp, hero.max_hp);
+
+        hero.manual_growth = false;
+        hero.raise_level();
+        assert_eq!(0, hero.growth_points);
+        assert_eq!(speed + 2, hero.speed);
+    }
+
     // HELPERS
 
     fn new_char() -> Character {
@@ -1331,6 +2539,13 @@ mod tests {
                 strength: Stat(10, 3),
                 speed: Stat(10, 2),
                 inflicts: None,
+                drops: None,
+                element: None,
+                spells: Vec::new(),
+                skills: Vec::new(),
+                equipment: Vec::new(),
+                passive: None,
+                signature: None,
             },
             1,
         )
@@ -0,0 +1,149 @@
+use crate::item::ring::Ring;
+use anyhow::{bail, Result};
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
+
+/// Levels it takes to earn each skill point.
+pub const SKILL_POINT_LEVELS: i32 = 5;
+
+/// Extra percentage of the gold gained in battle with the pickpocket skill.
+pub const PICKPOCKET_GOLD: i32 = 25;
+
+/// Percentage of the max mp recovered after a battle won with the mana flow skill.
+pub const MANA_FLOW_MP: i32 = 20;
+
+/// Skills are abilities that player characters unlock with the skill points
+/// earned as they level up. Each player class has its own small tree of
+/// skills, where each one requires the previous to be unlocked first.
+///
+/// Passive skills are always in effect, while active ones are used
+/// automatically, at most once per battle, when the hero is in trouble.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter)]
+#[serde(rename_all = "snake_case")]
+pub enum Skill {
+    Toughness,
+    Bloodlust,
+    SecondWind,
+    Pickpocket,
+    TreasureHunter,
+    SmokeBomb,
+    ArcaneFocus,
+    ManaFlow,
+    ManaSurge,
+    Appraise,
+}
+
+impl Skill {
+    pub fn from(name: &str) -> Result<Self> {
+        let skill = match name.to_lowercase().as_str() {
+            "toughness" => Skill::Toughness,
+            "bloodlust" => Skill::Bloodlust,
+            "second-wind" => Skill::SecondWind,
+            "pickpocket" => Skill::Pickpocket,
+            "treasure-hunter" => Skill::TreasureHunter,
+            "smoke-bomb" => Skill::SmokeBomb,
+            "arcane-focus" => Skill::ArcaneFocus,
+            "mana-flow" => Skill::ManaFlow,
+            "mana-surge" => Skill::ManaSurge,
+            "appraise" => Skill::Appraise,
+            skill => bail!("skill {} not found", skill),
+        };
+        Ok(skill)
+    }
+
+    /// The skill tree of the given class, in unlock order.
+    /// Classes other than the default player ones don't have skills.
+    pub fn tree(class_name: &str) -> Vec<Self> {
+        match class_name {
+            "warrior" => vec![Skill::Toughness, Skill::Bloodlust, Skill::SecondWind],
+            "thief" => vec![
+                Skill::Pickpocket,
+                Skill::TreasureHunter,
+                Skill::SmokeBomb,
+                Skill::Appraise,
+            ],
+            "mage" => vec![Skill::ArcaneFocus, Skill::ManaFlow, Skill::ManaSurge],
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(
+            self,
+            Skill::SecondWind | Skill::SmokeBomb | Skill::ManaSurge
+        )
+    }
+
+    /// How much the skill increases the stats affected by the given ring,
+    /// as a factor of the base stat.
+    pub fn factor(&self, ring: &Ring) -> f64 {
+        match (self, ring) {
+            (Skill::Toughness, Ring::HP) => 0.2,
+            (Skill::Bloodlust, Ring::Attack) => 0.15,
+            (Skill::ArcaneFocus, Ring::Magic) => 0.2,
+            _ => 0.0,
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Skill::Toughness => "increases max hp",
+            Skill::Bloodlust => "increases physical attack",
+            Skill::SecondWind => "restores hp once per battle when close to death",
+            Skill::Pickpocket => "increases the gold gained in battle",
+            Skill::TreasureHunter => "doubles chest finding frequency",
+            Skill::SmokeBomb => "escapes once per battle when close to death",
+            Skill::ArcaneFocus => "increases magical attack",
+            Skill::ManaFlow => "recovers some mp after each battle won",
+            Skill::ManaSurge => "restores mp once per battle when running out of it",
+            Skill::Appraise => "reveals what mimics guard, to fight them or leave",
+        }
+    }
+}
+
+impl fmt::Display for Skill {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Skill::Toughness => "toughness",
+            Skill::Bloodlust => "bloodlust",
+            Skill::SecondWind => "second-wind",
+            Skill::Pickpocket => "pickpocket",
+            Skill::TreasureHunter => "treasure-hunter",
+            Skill::SmokeBomb => "smoke-bomb",
+            Skill::ArcaneFocus => "arcane-focus",
+            Skill::ManaFlow => "mana-flow",
+            Skill::ManaSurge => "mana-surge",
+            Skill::Appraise => "appraise",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn from_into() {
+        for skill in Skill::iter() {
+            assert_eq!(skill, Skill::from(&skill.to_string()).unwrap());
+        }
+        assert!(Skill::from("fly").is_err());
+    }
+
+    #[test]
+    fn trees() {
+        // every skill belongs to exactly one class tree
+        let trees: Vec<Skill> = ["warrior", "thief", "mage"]
+            .iter()
+            .flat_map(|class| Skill::tree(class))
+            .collect();
+        assert_eq!(Skill::iter().count(), trees.len());
+        for skill in Skill::iter() {
+            assert!(trees.contains(&skill));
+        }
+        assert!(Skill::tree("rat").is_empty());
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::{bail, Result};
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use std::cmp::max;
+use strum_macros::EnumIter;
+
+/// Spells can be cast in battle by characters of magic classes, at the
+/// expense of magic points. Classes unlock them as they level up, and
+/// scrolls can teach them to any magic character.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Spell {
+    #[default]
+    Heal,
+    Fireball,
+    Shield,
+    Haste,
+}
+
+impl Spell {
+    pub fn from(name: &str) -> Result<Self> {
+        let spell = match name.to_lowercase().as_str() {
+            "heal" => Spell::Heal,
+            "fireball" => Spell::Fireball,
+            "shield" => Spell::Shield,
+            "haste" => Spell::Haste,
+            spell => bail!("spell {} not found", spell),
+        };
+        Ok(spell)
+    }
+
+    /// How many magic points it takes to cast the spell, as a percentage of
+    /// the caster's max mp.
+    pub fn mp_cost(&self, max_mp: i32) -> i32 {
+        let percentage = match self {
+            Spell::Heal => 25,
+            Spell::Fireball => 40,
+            Spell::Shield | Spell::Haste => 30,
+        };
+        max(1, max_mp * percentage / 100)
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Spell::Heal => "restores half of the max hp",
+            Spell::Fireball => "deals increased fire magic damage",
+            Spell::Shield => "raises defense for a few turns",
+            Spell::Haste => "raises speed for a few turns",
+        }
+    }
+}
+
+impl fmt::Display for Spell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Spell::Heal => "heal",
+            Spell::Fireball => "fireball",
+            Spell::Shield => "shield",
+            Spell::Haste => "haste",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use strum::IntoEnumIterator;
+
+    #[test]
+    fn from_into() {
+        for spell in Spell::iter() {
+            assert_eq!(spell, Spell::from(&spell.to_string()).unwrap());
+        }
+        assert!(Spell::from("meteor").is_err());
+    }
+
+    #[test]
+    fn mp_cost() {
+        assert_eq!(1, Spell::Heal.mp_cost(0));
+        assert_eq!(10, Spell::Fireball.mp_cost(25));
+    }
+}
@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::min;
+
+/// Conditions that can be inflicted on a character by attacks, or by the
+/// character itself with spells, lasting for a limited number of turns.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusEffect {
+    /// Deals damage every turn, briefly.
+    Burn,
+    /// Deals damage every turn and weakens the character's attacks.
+    Poison,
+    /// The character loses its turns.
+    Stun,
+    /// Raises the character defense.
+    Shield,
+    /// Raises the character speed.
+    Haste,
+}
+
+impl StatusEffect {
+    /// How many turns the effect lasts after being inflicted.
+    fn duration(&self) -> u32 {
+        match self {
+            StatusEffect::Burn => 3,
+            StatusEffect::Poison => 10,
+            StatusEffect::Stun => 1,
+            StatusEffect::Shield | StatusEffect::Haste => 5,
+        }
+    }
+
+    /// How many times the effect can be inflicted on top of itself.
+    fn max_stacks(&self) -> u32 {
+        match self {
+            StatusEffect::Burn | StatusEffect::Poison => 3,
+            StatusEffect::Stun | StatusEffect::Shield | StatusEffect::Haste => 1,
+        }
+    }
+
+    /// Whether the effect is an ailment, as opposed to a beneficial one.
+    pub fn is_harmful(&self) -> bool {
+        !matches!(self, StatusEffect::Shield | StatusEffect::Haste)
+    }
+}
+
+/// A status effect currently affecting a character.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Status {
+    pub effect: StatusEffect,
+    pub stacks: u32,
+    pub turns: u32,
+}
+
+/// The set of status effects of a character. Inflicting an effect that's
+/// already present adds a stack to it and resets its duration.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(transparent)]
+pub struct Statuses(Vec<Status>);
+
+impl Statuses {
+    pub fn inflict(&mut self, effect: StatusEffect) {
+        if let Some(status) = self.0.iter_mut().find(|s| s.effect == effect) {
+            status.stacks = min(status.stacks + 1, effect.max_stacks());
+            status.turns = effect.duration();
+        } else {
+            self.0.push(Status {
+                effect,
+                stacks: 1,
+                turns: effect.duration(),
+            });
+        }
+    }
+
+    pub fn has(&self, effect: StatusEffect) -> bool {
+        self.stacks(effect) > 0
+    }
+
+    pub fn stacks(&self, effect: StatusEffect) -> u32 {
+        self.0
+            .iter()
+            .find(|s| s.effect == effect)
+            .map_or(0, |s| s.stacks)
+    }
+
+    /// Whether inflicting the given effect again would make no difference
+    /// other than extending its duration.
+    pub fn is_maxed(&self, effect: StatusEffect) -> bool {
+        self.stacks(effect) >= effect.max_stacks()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Status> {
+        self.0.iter()
+    }
+
+    /// Remove all harmful status effects. Returns true if there was any to remove.
+    pub fn cure(&mut self) -> bool {
+        let before = self.0.len();
+        self.0.retain(|s| !s.effect.is_harmful());
+        self.0.len() < before
+    }
+
+    /// How many units of hp damage the effects deal every turn.
+    pub fn damage_units(&self) -> i32 {
+        (self.stacks(StatusEffect::Burn) + self.stacks(StatusEffect::Poison)) as i32
+    }
+
+    /// The factor to apply to the character attack, 10% less for each
+    /// stack of poison.
+    pub fn attack_factor(&self) -> f64 {
+        1.0 - 0.1 * self.stacks(StatusEffect::Poison) as f64
+    }
+
+    /// The factor to apply to the given base stat, 50% more when the
+    /// effect that boosts it is active.
+    pub fn boost_factor(&self, effect: StatusEffect) -> f64 {
+        if self.has(effect) {
+            1.5
+        } else {
+            1.0
+        }
+    }
+
+    /// Count down one turn of each effect, removing and returning the ones
+    /// that expired.
+    pub fn tick(&mut self) -> Vec<StatusEffect> {
+        let mut expired = Vec::new();
+        for status in &mut self.0 {
+            status.turns = status.turns.saturating_sub(1);
+            if status.turns == 0 {
+                expired.push(status.effect);
+            }
+        }
+        self.0.retain(|s| s.turns > 0);
+        expired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stack_and_expire() {
+        let mut statuses = Statuses::default();
+        statuses.inflict(StatusEffect::Burn);
+        statuses.inflict(StatusEffect::Burn);
+        statuses.inflict(StatusEffect::Stun);
+        statuses.inflict(StatusEffect::Stun);
+        assert_eq!(2, statuses.stacks(StatusEffect::Burn));
+        assert_eq!(1, statuses.stacks(StatusEffect::Stun));
+        assert!(statuses.is_maxed(StatusEffect::Stun));
+        assert_eq!(2, statuses.damage_units());
+
+        assert_eq!(vec![StatusEffect::Stun], statuses.tick());
+        assert!(!statuses.has(StatusEffect::Stun));
+
+        // inflicting again resets the duration
+        statuses.inflict(StatusEffect::Burn);
+        assert_eq!(3, statuses.stacks(StatusEffect::Burn));
+        assert!(statuses.tick().is_empty());
+        assert!(statuses.tick().is_empty());
+        assert_eq!(vec![StatusEffect::Burn], statuses.tick());
+        assert!(statuses.is_empty());
+    }
+
+    #[test]
+    fn cure() {
+        let mut statuses = Statuses::default();
+        assert!(!statuses.cure());
+
+        statuses.inflict(StatusEffect::Poison);
+        statuses.inflict(StatusEffect::Haste);
+        assert_eq!(0.9, statuses.attack_factor());
+        assert_eq!(1.5, statuses.boost_factor(StatusEffect::Haste));
+        assert!(statuses.cure());
+        assert_eq!(1.0, statuses.attack_factor());
+
+        // beneficial effects are kept
+        assert!(statuses.has(StatusEffect::Haste));
+        assert!(!statuses.cure());
+    }
+}
@@ -0,0 +1,145 @@
+use crate::randomizer::random;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const HOURS_PER_DAY: i32 = 24;
+
+/// The weather can change every few hours.
+const WEATHER_HOURS: i32 = 6;
+
+/// Night lasts from this hour until dawn.
+const DUSK: i32 = 20;
+const DAWN: i32 = 6;
+
+/// New heroes set out in the morning.
+const START_HOUR: i32 = 8;
+
+/// Hours that pass with each directory walked and each battle fought.
+pub const STEP_HOURS: i32 = 1;
+pub const BATTLE_HOURS: i32 = 1;
+
+/// The state of the sky, which affects what the hero comes across.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Weather {
+    #[default]
+    Clear,
+    /// Washes gold up to the surface, making gold chests more common.
+    Rain,
+    /// Hides the hero from the enemies.
+    Fog,
+    /// Drives the enemies out of their shelters.
+    Storm,
+}
+
+impl fmt::Display for Weather {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Weather::Clear => "clear",
+            Weather::Rain => "rain",
+            Weather::Fog => "fog",
+            Weather::Storm => "storm",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The world clock, advanced as the hero moves and fights, with the time of
+/// day and the current weather.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Clock {
+    /// Hours passed since the hero set out.
+    hours: i32,
+    pub weather: Weather,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self {
+            hours: START_HOUR,
+            weather: Weather::Clear,
+        }
+    }
+}
+
+impl Clock {
+    /// Let the given hours pass, rolling a new weather each time a weather
+    /// period starts.
+    pub fn advance(&mut self, hours: i32) {
+        for _ in 0..hours {
+            self.hours += 1;
+            if self.hours % WEATHER_HOURS == 0 {
+                self.weather = random().weather();
+            }
+        }
+    }
+
+    /// The hour of the current day, from 0 to 23.
+    pub fn hour(&self) -> i32 {
+        self.hours % HOURS_PER_DAY
+    }
+
+    pub fn is_night(&self) -> bool {
+        let hour = self.hour();
+        !(DAWN..DUSK).contains(&hour)
+    }
+
+    /// Percentage points added to, or taken from, the chance of an enemy
+    /// appearing.
+    pub fn encounter_shift(&self) -> i32 {
+        match self.weather {
+            Weather::Fog => -25,
+            Weather::Storm => 25,
+            Weather::Clear | Weather::Rain => 0,
+        }
+    }
+}
+
+impl fmt::Display for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let period = if self.is_night() { "night" } else { "day" };
+        write!(f, "{:02}:00 {} {}", self.hour(), period, self.weather)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day_and_night() {
+        let mut clock = Clock::default();
+        assert_eq!(8, clock.hour());
+        assert!(!clock.is_night());
+        assert_eq!("08:00 day clear", clock.to_string());
+
+        clock.advance(12);
+        assert_eq!(20, clock.hour());
+        assert!(clock.is_night());
+
+        clock.advance(10);
+        assert_eq!(6, clock.hour());
+        assert!(!clock.is_night());
+    }
+
+    #[test]
+    fn test_weather() {
+        let mut clock = Clock {
+            weather: Weather::Rain,
+            ..Clock::default()
+        };
+        assert_eq!(0, clock.encounter_shift());
+
+        // the weather is rolled again when a new period starts
+        clock.advance(3);
+        assert_eq!(Weather::Rain, clock.weather);
+        clock.advance(1);
+        assert_eq!(Weather::Clear, clock.weather);
+
+        clock.weather = Weather::Fog;
+        assert!(clock.encounter_shift() < 0);
+        clock.weather = Weather::Storm;
+        assert!(clock.encounter_shift() > 0);
+    }
+}
@@ -1,19 +1,48 @@
-use crate::character;
-use crate::character::enemy;
-use crate::game::Game;
-use crate::item;
-use crate::item::key::Key;
-use crate::location::Location;
-use crate::log;
-use anyhow::{anyhow, bail, Result};
-
+use anyhow::{bail, Result};
+use rpg_cli::activity;
+use rpg_cli::arena;
+use rpg_cli::bank;
+use rpg_cli::character;
+use rpg_cli::character::enemy;
+use rpg_cli::character::skill::Skill;
+use rpg_cli::character::spell::Spell;
+use rpg_cli::datafile;
+use rpg_cli::difficulty::Difficulty;
+use rpg_cli::duel;
+use rpg_cli::dungeon::Direction;
+use rpg_cli::encounter;
+use rpg_cli::gamble;
+use rpg_cli::game::{Action, Game, Pickup};
+use rpg_cli::item;
+use rpg_cli::item::auto_use::AutoUse;
+use rpg_cli::item::key::Key;
+#[cfg(feature = "leaderboard")]
+use rpg_cli::leaderboard;
+use rpg_cli::log;
+use rpg_cli::raid;
+use rpg_cli::simulation;
+use rpg_cli::sync::{self, Side, Synced};
+use rpg_cli::title;
+use rpg_cli::trade;
+use rpg_cli::world;
+
+use crate::completions;
+use crate::tui;
 use clap::Clap;
+use std::ops::RangeInclusive;
+use std::path::Path;
 
 #[derive(Clap)]
 pub enum Command {
     /// Display stats for the given items. Defaults to displaying hero stats if no item is specified. [default]
     #[clap(aliases=&["s", "status"], display_order=0)]
-    Stat { items: Vec<String> },
+    Stat {
+        items: Vec<String>,
+
+        /// Display the statistics of the player's history across heroes.
+        #[clap(long, conflicts_with = "items")]
+        history: bool,
+    },
 
     /// Moves the hero to the supplied destination, potentially initiating battles along the way.
     #[clap(name = "cd", display_order = 1)]
@@ -30,6 +59,10 @@ pub enum Command {
         #[clap(long)]
         bribe: bool,
 
+        /// Spell to cast during battles instead of attacking, when useful.
+        #[clap(long)]
+        cast: Option<String>,
+
         /// Move the hero's to a different location without spawning enemies.
         /// Intended for scripts and shell integration.
         #[clap(short, long)]
@@ -38,32 +71,370 @@ pub enum Command {
 
     /// Inspect the directory contents, possibly finding treasure chests and hero tombstones.
     #[clap(name = "ls", display_order = 1)]
-    Inspect,
+    Inspect {
+        /// List the chest contents and choose which ones to pick up.
+        /// The rest is left at the location for later.
+        #[clap(long)]
+        choose: bool,
+    },
+
+    /// Reply to the npc met at the current location, with the number of one
+    /// of the replies listed by `ls`. If none is given, it's asked for.
+    Talk { reply: Option<usize> },
+
+    /// Rests at the current location, restoring the hero's hp and mp. Free at
+    /// home, elsewhere it costs gold and the hero may be ambushed during the night.
+    #[clap(display_order = 1)]
+    Rest,
+
+    /// Bookmarks directories to fast travel to them later, paying gold for
+    /// the distance but skipping the battles along the way.
+    /// If no action is given lists the waypoints.
+    #[clap(display_order = 1)]
+    Waypoint {
+        #[clap(subcommand)]
+        action: Option<WaypointAction>,
+    },
+
+    /// Show the map of the directories explored by the hero, with the
+    /// known tombstones, chests, bosses and waypoints.
+    #[clap(display_order = 1)]
+    Map,
+
+    /// Attempt to disarm the trap of a chest left at the current location
+    /// with `ls --choose`. Failing springs the trap.
+    #[clap(display_order = 1)]
+    Disarm,
 
     /// Buys an item from the shop.
     /// If name is omitted lists the items available for sale.
     #[clap(alias = "b", display_order = 2)]
     Buy { items: Vec<String> },
 
+    /// Buys from and sells to the shop at home.
+    /// If no items are given, lists the ones available for sale today.
+    #[clap(display_order = 2)]
+    Shop {
+        items: Vec<String>,
+
+        /// Sell the given items from the inventory instead of buying them.
+        #[clap(long)]
+        sell: bool,
+    },
+
+    /// Repairs the hero's worn equipment at the shop, restoring its durability.
+    /// The cost depends on the equipment level and how worn it is.
+    #[clap(display_order = 2)]
+    Repair,
+
+    /// Upgrades an equipped stat ring at the shop, spending gold and a level stone.
+    #[clap(display_order = 2)]
+    Upgrade { ring: String },
+
+    /// Stores gold at the bank at home, where it isn't lost when the hero dies.
+    /// If no action is given prints the bank balance.
+    #[clap(display_order = 2)]
+    Bank {
+        #[clap(subcommand)]
+        action: Option<BankAction>,
+    },
+
+    /// Fights endless waves of stronger and stronger enemies at home, with
+    /// no running away, until the hero is knocked out. Each wave beaten earns
+    /// arena tokens, exchanged for exclusive rings and equipment.
+    #[clap(display_order = 2)]
+    Arena {
+        #[clap(subcommand)]
+        action: Option<ArenaAction>,
+    },
+
+    /// Wagers gold on a roll of two dice against the house, at home.
+    /// A higher roll doubles the wager, ties go to the house. How much can
+    /// be wagered per day is capped by the hero level.
+    #[clap(display_order = 2)]
+    Gamble { amount: i32 },
+
+    /// Sends gold and items from home to the game of another local profile,
+    /// which claims them by running trade with no profile. Rings already
+    /// found in the other game are sent back.
+    #[clap(display_order = 2)]
+    Trade {
+        /// The profile to send to.
+        to: Option<String>,
+
+        /// The items to send, repeated to send more than one.
+        #[clap(requires = "to")]
+        items: Vec<String>,
+
+        /// The amount of gold to send.
+        #[clap(long, requires = "to")]
+        gold: Option<i32>,
+    },
+
+    /// Combines materials from the inventory into a new item.
+    /// If name is omitted lists the available recipes.
+    #[clap(display_order = 3)]
+    Craft { name: Option<String> },
+
+    /// Combines three potions or ethers from the inventory into one of the
+    /// next tier: mid-potion, hi-potion, mid-ether or hi-ether.
+    #[clap(display_order = 3)]
+    Brew { item: String },
+
     /// Uses an item from the inventory.
     #[clap(alias = "u", display_order = 3)]
-    Use { items: Vec<String> },
+    Use {
+        items: Vec<String>,
+
+        /// Teleport to this directory when using an escape, instead of home.
+        /// It must have been visited by the hero before.
+        #[clap(long)]
+        to: Option<String>,
+
+        /// Spend the points refunded by a forget-stone on these stats,
+        /// separated by commas, like `hp,str,str`.
+        #[clap(long)]
+        spend: Option<String>,
+
+        /// Use chaos stones without asking for confirmation.
+        #[clap(long, short)]
+        yes: bool,
+    },
+
+    /// Prints the quest journal, with the active and completed quests.
+    #[clap(aliases = &["t", "todo"], display_order = 4)]
+    Quest,
+
+    /// Opens a full-screen interface showing the hero, inventory, map and last
+    /// battle, where commands can be typed as on the command line.
+    #[clap(display_order = 4)]
+    Tui,
+
+    /// Keeps the game loaded and runs the commands sent by the cli over a
+    /// Unix socket, so they don't have to load and save it each time. Runs
+    /// until stopped, e.g. with Ctrl-C.
+    #[clap(display_order = 4)]
+    Daemon,
+
+    /// Prints a compact status line to embed in the shell prompt, without
+    /// changing the game. Nothing is printed if there's no game yet.
+    #[clap(display_order = 4)]
+    Prompt {
+        /// The line to print, with {class}, {level}, {hp}, {max_hp}, {hp_bar},
+        /// {mp}, {max_mp}, {gold}, {location}, {distance}, {status}, {time},
+        /// {title} and {mode} placeholders.
+        #[clap(
+            long,
+            short,
+            default_value = "{class}[{level}]{hp_bar} {gold}g {status}"
+        )]
+        format: String,
+    },
+
+    /// Prints the completion script for the given shell, which also completes
+    /// the item, ring, class and waypoint names from the game.
+    #[clap(display_order = 4)]
+    Completions {
+        #[clap(possible_values = &completions::SHELLS)]
+        shell: String,
+    },
+
+    /// Prints the game names of the given kind, for the completion scripts.
+    #[clap(setting(clap::AppSettings::Hidden))]
+    Complete {
+        #[clap(possible_values = &completions::KINDS)]
+        kind: String,
+    },
+
+    /// Prints the skill tree of the hero class and the skill points left.
+    /// If a name is given, spends a skill point to learn that skill.
+    Skills { name: Option<String> },
+
+    /// Prints the player achievements and the progress towards them.
+    Achievements,
+
+    /// Prints the daily and weekly challenges, with the progress towards
+    /// them and the streak of consecutive days or weeks completed.
+    Challenges,
+
+    /// Prints the raid boss of the week, the directory where it lairs, the
+    /// turns it takes to enrage and the title earned by beating it.
+    Raid,
+
+    /// Manages the cosmetic titles earned from achievements and bosses,
+    /// shown with the hero. If no action is given lists them.
+    Title {
+        #[clap(subcommand)]
+        action: Option<TitleAction>,
+    },
 
-    /// Prints the quest todo list.
-    #[clap(alias = "t", display_order = 4)]
-    Todo,
+    /// Prints the time and splits of the speedrun, to share it.
+    Speedrun,
+
+    /// Prints the enemy classes faced by the player, with kill statistics.
+    Bestiary,
+
+    /// Prints the locations of the tombs left by fallen heroes, with their contents.
+    Tombs,
+
+    /// Prints the turn by turn events of the latest battles again.
+    /// Use the --json option to get them as structured events.
+    Replay {
+        /// How many of the latest battles to replay.
+        #[clap(default_value = "1")]
+        count: usize,
+    },
 
     /// Resets the current game.
     Reset {
         /// Reset data files, losing cross-hero progress.
         #[clap(long)]
         hard: bool,
+
+        /// Start a New Game+, keeping the hero's rings and half of their gold.
+        /// Unlocked by beating the final boss.
+        #[clap(long, conflicts_with = "hard")]
+        new_game_plus: bool,
+
+        /// Start a new game in hardcore mode, where death deletes the game
+        /// instead of leaving a tombstone.
+        #[clap(long, conflicts_with_all = &["hard", "new-game-plus"])]
+        hardcore: bool,
+
+        /// Start a new game in ironman mode, where the game is saved before
+        /// showing the outcome of each command and can't be restored from
+        /// older copies.
+        #[clap(long, conflicts_with_all = &["hard", "new-game-plus"])]
+        ironman: bool,
+
+        /// Start the new game at the given difficulty: easy, normal, hard or
+        /// nightmare. Harder games have stronger enemies and fewer chests.
+        #[clap(long, conflicts_with = "new-game-plus")]
+        difficulty: Option<String>,
+    },
+
+    /// Manage the profiles, each one with its own independent game.
+    /// If no option is given lists the profiles.
+    Profiles {
+        /// Create a new profile with the given name.
+        #[clap(long)]
+        create: Option<String>,
+
+        /// Play the created profile in an abstract world, where locations
+        /// are virtual places instead of directories of the filesystem.
+        #[clap(long = "abstract", requires = "create")]
+        abstract_world: bool,
+
+        /// Time the games of the created profile as speedruns, from the start
+        /// to the final boss, with splits at the milestones on the way.
+        #[clap(long, requires = "create")]
+        speedrun: bool,
+
+        /// Delete the given profile and its game.
+        #[clap(long)]
+        delete: Option<String>,
+
+        /// Use the given profile in the next commands.
+        #[clap(long)]
+        switch: Option<String>,
+    },
+
+    /// Write the game to a file, to back it up or move it to another machine.
+    Export { file: String },
+
+    /// Replace the game with one from a file written by export.
+    Import { file: String },
+
+    /// Fight the hero in a duel against the one of a game exported by
+    /// another player, to raise the duel rating. Nothing is lost in duels.
+    /// If no file is given prints the rating and results so far.
+    Duel { file: Option<String> },
+
+    /// Push the game to the remote set in the config, or pull it from there
+    /// if it was played on another machine since the last sync. When both
+    /// changed, the game saved last is kept.
+    Sync {
+        /// Replace the game at the remote with this one.
+        #[clap(long, conflicts_with = "pull")]
+        push: bool,
+
+        /// Replace this game with the one at the remote.
+        #[clap(long)]
+        pull: bool,
     },
 
-    /// Change the character class.
+    /// Submit a summary of the game to the leaderboard server set in the
+    /// config, or show its rankings.
+    #[cfg(feature = "leaderboard")]
+    Leaderboard {
+        #[clap(subcommand)]
+        action: LeaderboardAction,
+    },
+
+    /// Upgrade the game data to the current save format. This happens
+    /// automatically when loading the game, keeping a backup of the old data.
+    Migrate {
+        /// Report the changes that the upgrade would make, without saving them.
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// Recruit heroes into the party or remove them from it, at home.
+    /// If no option is given lists the party members.
+    Party {
+        /// Create a new hero of the given class and add it to the party.
+        #[clap(long)]
+        add: Option<String>,
+
+        /// Remove the hero of the given class from the party.
+        #[clap(long)]
+        remove: Option<String>,
+    },
+
+    /// Swap the active companion with one resting at home.
+    /// If name is omitted lists the hero's companions.
+    Companion {
+        name: Option<String>,
+
+        /// Release the active companion, keeping the item it held.
+        #[clap(long)]
+        dismiss: bool,
+    },
+
+    /// Explore a virtual dungeon, one room at a time.
+    /// Without arguments, enters a new dungeon or shows the current floor map.
+    Dungeon {
+        /// Direction to move to: n, s, e or w.
+        direction: Option<String>,
+
+        /// Leave the current dungeon.
+        #[clap(long)]
+        exit: bool,
+    },
+
+    /// Change the character class at home. Above level 1 it costs gold and a level stone.
     /// If name is omitted lists the available character classes.
     Class { name: Option<String> },
 
+    /// Assigns the points granted on level up to stats, like `hp str str`,
+    /// when growing manually. If no stat is given shows the points left.
+    Levelup {
+        stats: Vec<String>,
+
+        /// Grant points to assign on each level up, instead of raising the
+        /// stats along the class growth.
+        #[clap(long)]
+        manual: bool,
+
+        /// Raise the stats along the class growth on each level up.
+        #[clap(long, conflicts_with = "manual")]
+        auto: bool,
+    },
+
+    /// Restart the hero at level 1 with a permanent stat bonus and a new title.
+    /// Unlocked at level 100, only at home.
+    Ascend,
+
     /// Prints the hero's current location
     #[clap(name = "pwd")]
     PrintWorkDir,
@@ -77,32 +448,324 @@ pub enum Command {
         /// Attempt to avoid battles by bribing the enemy.
         #[clap(long)]
         bribe: bool,
+
+        /// Spell to cast during the battle instead of attacking, when useful.
+        #[clap(long)]
+        cast: Option<String>,
+
+        /// Choose what to do on each turn instead of fighting automatically.
+        #[clap(long, short, conflicts_with_all = &["run", "bribe", "cast"])]
+        interactive: bool,
+    },
+
+    /// Claim the rewards accrued by passive training.
+    /// The training is opt-in: when enabled, invocations from the shell
+    /// integration (`cd --force`) accrue a small amount of xp.
+    Train {
+        /// Start accruing xp from shell activity.
+        #[clap(long)]
+        on: bool,
+
+        /// Stop accruing xp from shell activity.
+        #[clap(long)]
+        off: bool,
+    },
+
+    /// Change the player preferences saved with the game.
+    Config {
+        #[clap(subcommand)]
+        setting: ConfigSetting,
     },
 
     #[clap(setting(clap::AppSettings::Hidden))]
     Idkfa { level: i32 },
+
+    /// Runs automated battles or chest generations for a new hero and
+    /// prints the aggregate results, to check changes to the drop tables
+    /// and enemy stats.
+    #[clap(setting(clap::AppSettings::Hidden))]
+    Simulate {
+        #[clap(possible_values = &["battles", "chests"])]
+        kind: String,
+
+        /// Level of the simulated hero.
+        #[clap(long, default_value = "1")]
+        level: i32,
+
+        /// Distance from home, or a range of them like 1-10.
+        #[clap(long, default_value = "1")]
+        distance: String,
+
+        /// Level of the equipment worn by the hero, none if not given.
+        #[clap(long)]
+        gear: Option<i32>,
+
+        /// How many battles or chests to run at each distance.
+        #[clap(long, default_value = "1000")]
+        runs: usize,
+    },
+}
+
+#[derive(Clap)]
+pub enum ConfigSetting {
+    /// Sets when items are used automatically during battles, with rules
+    /// like `'potion=hp<30'`, `'ether=mp<20'`, `ether=no-mp`, `remedy=poison`
+    /// or `potion=off`, quoted when they have a `<` so the shell doesn't take
+    /// it as a redirection. If no rule is given lists the current ones.
+    Items {
+        rules: Vec<String>,
+
+        /// Go back to the default rules.
+        #[clap(long, conflicts_with = "rules")]
+        reset: bool,
+    },
+}
+
+#[derive(Clap)]
+pub enum WaypointAction {
+    /// Bookmarks the current directory with the given name, replacing the
+    /// waypoint of that name if there was one.
+    Set { name: String },
+
+    /// Lists the waypoints and the cost of fast traveling to each of them.
+    List,
+
+    /// Fast travels to the given waypoint.
+    Go { name: String },
+}
+
+#[cfg(feature = "leaderboard")]
+#[derive(Clap)]
+pub enum LeaderboardAction {
+    /// Posts the hero class and level, the playtime, deaths and achievements,
+    /// signed with the key set in the config.
+    Submit,
+
+    /// Prints the rankings, best first.
+    Show,
+}
+
+#[derive(Clap)]
+pub enum TitleAction {
+    /// Shows the given earned title with the hero, instead of the prestige one.
+    Set { title: String },
+
+    /// Stops showing an earned title.
+    Clear,
+}
+
+#[derive(Clap)]
+pub enum ArenaAction {
+    /// Lists the rewards exchanged for arena tokens.
+    Rewards,
+
+    /// Exchanges arena tokens for the given reward.
+    Exchange { item: String },
+}
+
+#[derive(Clap)]
+pub enum BankAction {
+    /// Moves gold from the hero to the bank. Deposits all of it if no amount is given.
+    Deposit { amount: Option<i32> },
+
+    /// Moves gold from the bank to the hero. Withdraws all of it if no amount is given.
+    Withdraw { amount: Option<i32> },
+
+    /// Pays a premium, based on the hero level, so that if the hero dies half
+    /// of their gold and items are left at home instead of in their tomb.
+    Insure,
 }
 
 pub fn run(cmd: Option<Command>, game: &mut Game) -> Result<()> {
-    match cmd.unwrap_or(Command::Stat { items: vec![] }) {
-        Command::Stat { items } => stat(game, &items)?,
+    if game.ironman && cmd.as_ref().is_some_and(asks_player) {
+        bail!("Commands that ask the player to choose can't be used in ironman games.");
+    }
+    // the shell prompt and completions run all the time, not only when playing
+    let passive = matches!(
+        cmd,
+        Some(Command::Prompt { .. } | Command::Complete { .. } | Command::Completions { .. })
+    );
+    if !passive {
+        game.track_session(activity::now());
+    }
+    match cmd.unwrap_or(Command::Stat {
+        items: vec![],
+        history: false,
+    }) {
+        Command::Stat { history: true, .. } => log::history(&game.stats),
+        Command::Stat { items, .. } => stat(game, &items)?,
         Command::ChangeDir {
             destination,
             run,
             bribe,
+            cast,
             force,
-        } => change_dir(game, &destination, run, bribe, force)?,
-        Command::Inspect => game.inspect(),
+        } => change_dir(game, &destination, run, bribe, &cast, force)?,
+        Command::Inspect { choose } => inspect(game, choose)?,
+        Command::Talk { reply } => talk(game, reply)?,
+        Command::Rest => rest(game)?,
+        Command::Waypoint { action } => waypoint(game, action)?,
+        Command::Map => log::world_map(&world::map(game)),
+        Command::Disarm => game.disarm_chest()?,
         Command::Class { name } => class(game, &name)?,
-        Command::Battle { run, bribe } => battle(game, run, bribe)?,
-        Command::PrintWorkDir => println!("{}", game.location.path_string()),
-        Command::Reset { .. } => game.reset(),
-        Command::Buy { items } => shop(game, &items)?,
-        Command::Use { items } => use_item(game, &items)?,
-        Command::Todo => {
+        Command::Levelup {
+            stats,
+            manual,
+            auto,
+        } => levelup(game, &stats, manual, auto)?,
+        Command::Ascend => game.ascend()?,
+        Command::Party { add, remove } => party(game, &add, &remove)?,
+        Command::Profiles {
+            create,
+            abstract_world,
+            speedrun,
+            delete,
+            switch,
+        } => profiles(&create, abstract_world, speedrun, &delete, &switch)?,
+        Command::Companion { name, dismiss } => companion(game, &name, dismiss)?,
+        Command::Dungeon { direction, exit } => dungeon(game, &direction, exit)?,
+        Command::Battle {
+            run,
+            bribe,
+            cast,
+            interactive,
+        } => battle(game, run, bribe, &cast, interactive)?,
+        Command::PrintWorkDir => log::work_dir(&game.location),
+        Command::Reset {
+            new_game_plus: true,
+            ..
+        } => game.new_game_plus()?,
+        Command::Reset {
+            hardcore,
+            ironman,
+            difficulty,
+            ..
+        } => {
+            // parse before resetting, so a typo doesn't lose the game
+            let difficulty = difficulty.map(|name| Difficulty::from(&name)).transpose()?;
+            if hardcore {
+                game.reset_hardcore();
+            } else {
+                game.reset();
+            }
+            if ironman {
+                game.ironman = true;
+            }
+            if let Some(difficulty) = difficulty {
+                game.difficulty = difficulty;
+            }
+        }
+        Command::Export { file } => {
+            datafile::export(game, Path::new(&file))?;
+            log::exported(&file);
+        }
+        Command::Import { file } => {
+            *game = datafile::import(Path::new(&file))?;
+            log::status(game);
+        }
+        Command::Duel { file: None } => log::duels(&game.duels),
+        Command::Duel { file: Some(file) } => {
+            let rival = datafile::import(Path::new(&file))?;
+            duel::duel(game, &rival);
+        }
+        Command::Sync { push, pull } => {
+            let force = match (push, pull) {
+                (true, _) => Some(Side::Local),
+                (_, true) => Some(Side::Remote),
+                _ => None,
+            };
+            let synced = sync::sync(game, force)?;
+            log::synced(&synced);
+            if let Synced::Pulled = synced {
+                log::status(game);
+            }
+        }
+        #[cfg(feature = "leaderboard")]
+        Command::Leaderboard { action } => match action {
+            LeaderboardAction::Submit => log::leaderboard_submitted(&leaderboard::submit(game)?),
+            LeaderboardAction::Show => {
+                log::leaderboard(&leaderboard::show()?, leaderboard::player_name())
+            }
+        },
+        // these are run before loading the game, or by main instead
+        Command::Daemon
+        | Command::Migrate { .. }
+        | Command::Prompt { .. }
+        | Command::Completions { .. }
+        | Command::Complete { .. } => {}
+        Command::Buy { items } => shop(game, &items, false)?,
+        Command::Shop { items, sell } => shop(game, &items, sell)?,
+        Command::Repair => item::shop::repair(game)?,
+        Command::Upgrade { ring } => item::shop::upgrade_ring(game, &Key::from(&ring)?)?,
+        Command::Bank { action } => match action {
+            None => bank::balance(game)?,
+            Some(BankAction::Deposit { amount }) => bank::deposit(game, amount)?,
+            Some(BankAction::Withdraw { amount }) => bank::withdraw(game, amount)?,
+            Some(BankAction::Insure) => bank::insure(game)?,
+        },
+        Command::Arena { action } => match action {
+            None => arena::fight(game)?,
+            Some(ArenaAction::Rewards) => {
+                log::arena_rewards(&arena::rewards(game), game.arena_tokens)
+            }
+            Some(ArenaAction::Exchange { item }) => arena::exchange(game, &Key::from(&item)?)?,
+        },
+        Command::Gamble { amount } => gamble::dice(game, amount)?,
+        Command::Trade { to, items, gold } => trade(game, &to, &items, gold)?,
+        Command::Craft { name } => craft(game, &name)?,
+        Command::Brew { item } => item::brew::brew(game, &Key::from(&item)?)?,
+        Command::Use {
+            items,
+            to,
+            spend,
+            yes,
+        } => use_item(game, &items, &to, &spend, yes)?,
+        Command::Quest => {
             log::quest_list(game.quests.list());
         }
+        Command::Achievements => log::achievements(game.achievements.list()),
+        Command::Challenges => log::challenges(game.challenges.list(activity::now())),
+        Command::Raid => {
+            let raid = raid::current(game, activity::now());
+            log::raid(&raid, game.raids.is_beaten(raid.week));
+        }
+        Command::Title { action } => match action {
+            None => log::titles(&title::list(game), game.titles.active()),
+            Some(TitleAction::Set { title }) => title::set(game, &title)?,
+            Some(TitleAction::Clear) => title::clear(game),
+        },
+        Command::Speedrun => {
+            if game.speedrun.is_none() {
+                bail!("Not a speedrun, create a profile for it with `rpg profiles --create <name> --speedrun`.");
+            }
+            log::speedrun(game, activity::now());
+        }
+        Command::Bestiary => log::bestiary(&game.bestiary),
+        Command::Tombs => log::tombs(game.tombs()),
+        Command::Replay { count } => {
+            let battles = datafile::load_battles();
+            let skip = battles.len().saturating_sub(count);
+            log::replay(&battles[skip..]);
+        }
+        Command::Skills { name: None } => log::skills(&game.player),
+        Command::Skills { name: Some(name) } => {
+            let skill = Skill::from(&name)?;
+            game.player.learn_skill(skill)?;
+            log::skill_learned(skill);
+        }
+        Command::Train { on, off } => train(game, on, off),
+        Command::Config {
+            setting: ConfigSetting::Items { rules, reset },
+        } => auto_use(game, &rules, reset)?,
+        Command::Tui => tui::run(game)?,
         Command::Idkfa { level } => debug_command(game, level),
+        Command::Simulate {
+            kind,
+            level,
+            distance,
+            gear,
+            runs,
+        } => simulate(&kind, level, &distance, gear, runs)?,
     };
 
     Ok(())
@@ -110,14 +773,26 @@ pub fn run(cmd: Option<Command>, game: &mut Game) -> Result<()> {
 
 /// Attempt to move the hero to the supplied location, possibly engaging
 /// in combat along the way.
-fn change_dir(game: &mut Game, dest: &str, run: bool, bribe: bool, force: bool) -> Result<()> {
-    let dest = Location::from(dest)?;
+fn change_dir(
+    game: &mut Game,
+    dest: &str,
+    run: bool,
+    bribe: bool,
+    cast: &Option<String>,
+    force: bool,
+) -> Result<()> {
+    let dest = game.location.resolve(dest)?;
+    let cast = parse_spell(game, cast)?;
     let result = if force {
+        // forced moves come from the shell integration, which is what
+        // the passive training keeps track of
+        game.track_activity();
+
         // When change is force, skip enemies along the way
         // but still apply side-effects at destination
         game.visit(dest)
     } else {
-        game.go_to(&dest, run, bribe)
+        game.go_to(&dest, run, bribe, cast)
     };
 
     if let Err(character::Dead) = result {
@@ -128,11 +803,191 @@ fn change_dir(game: &mut Game, dest: &str, run: bool, bribe: bool, force: bool)
     Ok(())
 }
 
+/// Set or fast travel to a waypoint, handling the hero's death if they
+/// succumb to a status effect on arrival. Lists the waypoints otherwise.
+fn waypoint(game: &mut Game, action: Option<WaypointAction>) -> Result<()> {
+    match action {
+        Some(WaypointAction::Set { name }) => {
+            game.waypoints.insert(name, game.location.clone());
+        }
+        Some(WaypointAction::Go { name }) => {
+            let dest = match game.waypoints.get(&name) {
+                Some(dest) => dest.clone(),
+                None => bail!("waypoint {} not found.", name),
+            };
+            return match game.fast_travel(dest) {
+                Ok(true) => Ok(()),
+                Ok(false) => bail!("Not enough gold."),
+                Err(character::Dead) => {
+                    game.reset();
+                    bail!("");
+                }
+            };
+        }
+        Some(WaypointAction::List) | None => {}
+    }
+
+    let waypoints: Vec<_> = game
+        .waypoints
+        .iter()
+        .map(|(name, location)| (name, location, game.fast_travel_cost(location)))
+        .collect();
+    log::waypoints(&waypoints);
+    Ok(())
+}
+
+/// Rest at the current location, handling the hero's death if ambushed.
+fn rest(game: &mut Game) -> Result<()> {
+    match game.rest() {
+        Ok(true) => Ok(()),
+        Ok(false) => bail!("Not enough gold."),
+        Err(character::Dead) => {
+            game.reset();
+            bail!("");
+        }
+    }
+}
+
+/// Look for chests and tombstones at the current location, handling
+/// the hero's death if a mimic chest is found. When the items found don't
+/// fit in the inventory, the player picks which ones to take.
+fn inspect(game: &mut Game, choose: bool) -> Result<()> {
+    let pickup = if choose {
+        Pickup::Choose
+    } else if can_prompt(game) {
+        Pickup::AskIfFull
+    } else {
+        Pickup::All
+    };
+    let asked = match game.inspect(pickup) {
+        Ok(asked) => asked,
+        Err(character::Dead) => {
+            game.reset();
+            bail!("");
+        }
+    };
+
+    if choose || asked {
+        let contents = game.chest_contents();
+        if !contents.is_empty() {
+            let free_slots = game.player.inventory_slots() - game.slots_used();
+            log::chest_contents(&contents, game.chest_trapped(), free_slots.max(0));
+            let indexes = read_selection(contents.len())?;
+            game.take_from_chest(&indexes);
+        }
+    }
+    Ok(())
+}
+
+/// Send gold and items to the given profile, or claim the ones sent to the
+/// profile in use if none is given.
+fn trade(game: &mut Game, to: &Option<String>, items: &[String], gold: Option<i32>) -> Result<()> {
+    match to {
+        Some(to) => {
+            let keys = items
+                .iter()
+                .map(|item| Key::from(item))
+                .collect::<Result<Vec<_>>>()?;
+            trade::send(game, to, &keys, gold.unwrap_or(0))
+        }
+        None => trade::claim(game),
+    }
+}
+
+/// Reply to the npc at the current location with the given option, or the
+/// one entered by the player after listing them. If there's no input, the
+/// npc keeps waiting.
+fn talk(game: &mut Game, reply: Option<usize>) -> Result<()> {
+    let met = match &game.encounter {
+        Some(met) if met.location == game.location => met,
+        _ => bail!("There's no one to talk to here."),
+    };
+    let replies = met.replies();
+    let index = match reply {
+        Some(reply) if reply >= 1 && reply <= replies.len() => reply - 1,
+        Some(reply) => bail!("invalid option {}.", reply),
+        None => {
+            log::encounter(met);
+            log::talk_prompt();
+            match read_selection(replies.len())?.first() {
+                Some(index) => *index,
+                None => return Ok(()),
+            }
+        }
+    };
+    encounter::reply(game, replies[index])
+}
+
+/// Whether the command shows outcomes and waits for the player to choose
+/// before the game is saved, which would let ironman players back out.
+fn asks_player(cmd: &Command) -> bool {
+    matches!(
+        cmd,
+        Command::Battle {
+            interactive: true,
+            ..
+        } | Command::Inspect { choose: true }
+    )
+}
+
+/// Whether the player can be asked to choose in the middle of a command:
+/// they type at a terminal and the output isn't kept by a frontend, like the
+/// daemon or the TUI. Ironman games never ask, as with `asks_player`.
+fn can_prompt(game: &Game) -> bool {
+    use std::io::IsTerminal;
+    !game.ironman && !log::is_captured() && std::io::stdin().is_terminal()
+}
+
+/// Read a list of 1-based option numbers from stdin, separated by spaces or commas,
+/// and return their 0-based indexes. `all` selects every option.
+fn read_selection(total: usize) -> Result<Vec<usize>> {
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    parse_selection(&input, total)
+}
+
+fn parse_selection(input: &str, total: usize) -> Result<Vec<usize>> {
+    let mut indexes = Vec::new();
+    for token in input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty())
+    {
+        if token == "all" {
+            return Ok((0..total).collect());
+        }
+        match token.parse::<usize>() {
+            Ok(n) if n >= 1 && n <= total => indexes.push(n - 1),
+            _ => bail!("invalid option {}.", token),
+        }
+    }
+    Ok(indexes)
+}
+
 /// Potentially run a battle at the current location, independently from
 /// the hero's movement.
-fn battle(game: &mut Game, run: bool, bribe: bool) -> Result<()> {
-    if let Some(mut enemy) = enemy::spawn(&game.location, &game.player) {
-        if let Err(character::Dead) = game.battle(&mut enemy, run, bribe) {
+fn battle(
+    game: &mut Game,
+    run: bool,
+    bribe: bool,
+    cast: &Option<String>,
+    interactive: bool,
+) -> Result<()> {
+    let cast = parse_spell(game, cast)?;
+    let mut enemies = enemy::spawn(
+        &game.location,
+        &game.player,
+        game.new_game_plus,
+        game.difficulty,
+        &game.clock,
+        &game.reputation,
+    );
+    if !enemies.is_empty() {
+        let result = if interactive {
+            game.battle_interactive(&mut enemies, &mut prompt_action)
+        } else {
+            game.battle_group(&mut enemies, run, bribe, cast)
+        };
+        if let Err(character::Dead) = result {
             game.reset();
             bail!("");
         }
@@ -140,31 +995,156 @@ fn battle(game: &mut Game, run: bool, bribe: bool) -> Result<()> {
     Ok(())
 }
 
-/// Set the class for the player character
-fn class(game: &mut Game, class_name: &Option<String>) -> Result<()> {
-    if !game.location.is_home() {
-        bail!("Class change is only allowed at home.")
+/// Prompt the player for the action of its turn, until a valid one is entered.
+/// If there's no more input, just attack.
+fn prompt_action(game: &Game, enemies: &[character::Character]) -> Action {
+    loop {
+        log::battle_prompt(&game.player, enemies);
+        let mut input = String::new();
+        match std::io::stdin().read_line(&mut input) {
+            Ok(0) | Err(_) => return Action::Attack,
+            Ok(_) => match parse_action(game, &input) {
+                Ok(action) => return action,
+                Err(err) => log::error(&err.to_string()),
+            },
+        }
+    }
+}
+
+/// Parse an action such as `attack`, `cast heal`, `use potion` or `flee`,
+/// or their first letter, failing if it can't be done by the hero.
+fn parse_action(game: &Game, input: &str) -> Result<Action> {
+    let mut tokens = input.split_whitespace();
+    let action = tokens.next().unwrap_or_default();
+    let arg = tokens.next();
+    match (action, arg) {
+        ("a", None) | ("attack", None) => Ok(Action::Attack),
+        ("f", None) | ("flee", None) => Ok(Action::Flee),
+        ("c", Some(name)) | ("cast", Some(name)) => {
+            let spell = Spell::from(name)?;
+            if !game.player.can_cast(spell) {
+                bail!("Can't cast {}.", spell);
+            }
+            Ok(Action::Cast(spell))
+        }
+        ("u", Some(name)) | ("use", Some(name)) => {
+            let key = Key::from(name)?;
+            if !game.inventory().contains_key(&key) {
+                bail!("No {} left.", key);
+            }
+            if key == Key::Escape {
+                bail!("Can't use an escape in battle, flee instead.");
+            }
+            Ok(Action::Use(key))
+        }
+        _ => bail!("invalid action {}.", input.trim()),
+    }
+}
+
+/// Parse the name of a spell to cast, failing if the hero doesn't know it.
+fn parse_spell(game: &Game, name: &Option<String>) -> Result<Option<Spell>> {
+    if let Some(name) = name {
+        let spell = Spell::from(name)?;
+        if !game.player.spells().contains(&spell) {
+            bail!("{} spell not known.", spell);
+        }
+        return Ok(Some(spell));
+    }
+    Ok(None)
+}
+
+/// Enter, explore or leave a virtual dungeon, handling the hero's death
+/// while exploring it.
+fn dungeon(game: &mut Game, direction: &Option<String>, exit: bool) -> Result<()> {
+    if exit {
+        return game.exit_dungeon();
+    }
+
+    match (&game.dungeon, direction) {
+        (None, None) => game.enter_dungeon()?,
+        (None, Some(_)) => bail!("Not exploring a dungeon, run `rpg dungeon` to enter one."),
+        (Some(dungeon), None) => log::dungeon(dungeon),
+        (Some(dungeon), Some(direction)) => {
+            let direction = Direction::from(direction)?;
+            if !dungeon.can_move(direction) {
+                bail!("There's a wall there.");
+            }
+            if let Err(character::Dead) = game.explore(direction) {
+                game.reset();
+                bail!("");
+            }
+        }
     }
+    Ok(())
+}
 
+/// Create, delete or switch profiles, or list them if no option is given.
+fn profiles(
+    create: &Option<String>,
+    abstract_world: bool,
+    speedrun: bool,
+    delete: &Option<String>,
+    switch: &Option<String>,
+) -> Result<()> {
+    if let Some(name) = create {
+        datafile::create_profile(name, abstract_world, speedrun)?;
+    }
+    if let Some(name) = delete {
+        datafile::delete_profile(name)?;
+    }
+    if let Some(name) = switch {
+        datafile::switch_profile(name)?;
+    }
+    if create.is_none() && delete.is_none() && switch.is_none() {
+        log::profiles(&datafile::profiles(), &datafile::active_profile());
+    }
+    Ok(())
+}
+
+/// Add or remove party members, or list them if no option is given.
+fn party(game: &mut Game, add: &Option<String>, remove: &Option<String>) -> Result<()> {
+    if let Some(class_name) = add {
+        game.add_party_member(class_name)?;
+    }
+    if let Some(name) = remove {
+        game.remove_party_member(name)?;
+    }
+    if add.is_none() && remove.is_none() {
+        log::party(&game.player, &game.party);
+    }
+    Ok(())
+}
+
+/// Swap or dismiss the active companion, or list them if no option is given.
+fn companion(game: &mut Game, name: &Option<String>, dismiss: bool) -> Result<()> {
+    if dismiss {
+        game.dismiss_companion()
+    } else if let Some(name) = name {
+        game.swap_companion(name)
+    } else {
+        log::companions(&game.companion, &game.companions);
+        Ok(())
+    }
+}
+
+/// Set the class for the player character
+fn class(game: &mut Game, class_name: &Option<String>) -> Result<()> {
     if let Some(class_name) = class_name {
-        let class_name = class_name.to_lowercase();
-        game.player
-            .change_class(&class_name)
-            .map_err(|_| anyhow!("Unknown class name."))
+        game.change_class(&class_name.to_lowercase())
     } else {
         let player_classes: Vec<String> =
             character::class::Class::names(character::class::Category::Player)
                 .iter()
                 .cloned()
                 .collect();
-        println!("Options: {}", player_classes.join(", "));
+        log::class_options(&player_classes);
         Ok(())
     }
 }
 
-/// Buy an item from the shop or list the available items if no item name is provided.
+/// Buy or sell items at the shop or list the available items if no item name is provided.
 /// Shopping is only allowed when the player is at the home directory.
-fn shop(game: &mut Game, items: &[String]) -> Result<()> {
+fn shop(game: &mut Game, items: &[String], sell: bool) -> Result<()> {
     if items.is_empty() {
         item::shop::list(game)
     } else {
@@ -174,7 +1154,11 @@ fn shop(game: &mut Game, items: &[String]) -> Result<()> {
             keys.push(Key::from(item)?);
         }
 
-        item::shop::buy(game, &keys)
+        if sell {
+            item::shop::sell(game, &keys)
+        } else {
+            item::shop::buy(game, &keys)
+        }
     }
 }
 
@@ -186,25 +1170,176 @@ fn stat(game: &mut Game, items: &[String]) -> Result<()> {
         for item_name in items {
             let item_name = Key::from(item_name)?;
             let (display, description) = game.describe(item_name)?;
-            println!("{}: {}", display, description);
+            log::item_description(&display, &description);
         }
         Ok(())
     }
 }
 
+/// Craft an item from the given recipe or list the recipes if no name is provided.
+fn craft(game: &mut Game, name: &Option<String>) -> Result<()> {
+    if let Some(name) = name {
+        item::material::craft(game, &name.to_lowercase())
+    } else {
+        let recipes = item::material::recipes()
+            .iter()
+            .map(|r| (r.name.to_string(), r.materials()))
+            .collect();
+        log::recipes(recipes);
+        Ok(())
+    }
+}
+
 /// Use an item from the inventory or list the inventory contents if no item name is provided.
-fn use_item(game: &mut Game, items: &[String]) -> Result<()> {
+fn use_item(
+    game: &mut Game,
+    items: &[String],
+    to: &Option<String>,
+    spend: &Option<String>,
+    yes: bool,
+) -> Result<()> {
+    if let Some(stats) = spend {
+        return spend_stone_points(game, stats);
+    }
+    if let Some(dest) = to {
+        if items.len() != 1 || Key::from(&items[0])? != Key::Escape {
+            bail!("Only an escape can take the hero to a directory.");
+        }
+        let dest = game.location.resolve(dest)?;
+        return game.escape_to(dest);
+    }
+
     if items.is_empty() {
-        println!("{}", log::format_inventory(game));
+        log::inventory(game);
     } else {
         for item_name in items {
             let item_name = Key::from(item_name)?;
-            game.use_item(item_name)?
+            let has_item = game.inventory().contains_key(&item_name);
+            if item_name == Key::ChaosStone && has_item && !yes && !confirm_chaos(game) {
+                continue;
+            }
+            game.use_item(item_name.clone())?;
+            if item_name == Key::ForgetStone {
+                prompt_stone_points(game);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Ask the player to confirm the use of a chaos stone. If there's no more
+/// input, it's not used.
+fn confirm_chaos(game: &Game) -> bool {
+    log::chaos_prompt(game.player.balance_ring());
+    let mut input = String::new();
+    match std::io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => false,
+        Ok(_) => matches!(
+            input.trim().to_lowercase().as_str(),
+            "y" | "yes" | "s" | "si" | "sí"
+        ),
+    }
+}
+
+/// Prompt the player for the stats to raise with the points refunded by a
+/// forget-stone, until they are spent. If there's no more input, the rest
+/// are kept for later.
+fn prompt_stone_points(game: &mut Game) {
+    while game.player.stone_points > 0 {
+        log::stone_points_prompt(game.player.stone_points);
+        let mut input = String::new();
+        match std::io::stdin().read_line(&mut input) {
+            Ok(0) | Err(_) => return,
+            Ok(_) if input.trim().is_empty() => return,
+            Ok(_) => {
+                if let Err(err) = spend_stone_points(game, &input) {
+                    log::error(&err.to_string());
+                }
+            }
         }
     }
+}
+
+/// Raise the given stats, separated by spaces or commas, with the points
+/// refunded by a forget-stone.
+fn spend_stone_points(game: &mut Game, stats: &str) -> Result<()> {
+    let stats = stats
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|stat| !stat.is_empty());
+    for stat in stats {
+        let increase = game.player.spend_stone_point(stat)?;
+        log::stat_increase(&game.player, stat, increase);
+    }
+    Ok(())
+}
+
+/// Switch how the hero grows on level up, then assign the given stats with
+/// the points granted on level up.
+fn levelup(game: &mut Game, stats: &[String], manual: bool, auto: bool) -> Result<()> {
+    if manual || auto {
+        game.player.manual_growth = manual;
+    }
+    let stats = stats
+        .iter()
+        .flat_map(|stats| stats.split(','))
+        .filter(|stat| !stat.is_empty());
+    for stat in stats {
+        let increase = game.player.spend_growth_point(stat)?;
+        log::stat_increase(&game.player, stat, increase);
+    }
+    log::growth(&game.player);
     Ok(())
 }
 
+/// Toggle the passive training mode or claim its pending rewards.
+fn train(game: &mut Game, on: bool, off: bool) {
+    if on {
+        game.activity.enabled = true;
+    } else if off {
+        game.activity.enabled = false;
+    } else {
+        game.claim_training();
+    }
+}
+
+/// Change the rules for using items automatically during battles, or list
+/// them if none are given.
+fn auto_use(game: &mut Game, rules: &[String], reset: bool) -> Result<()> {
+    if reset {
+        game.auto_use = AutoUse::default();
+    }
+    for rule in rules {
+        game.auto_use.set(rule)?;
+    }
+    log::auto_use(game.auto_use.rules());
+    Ok(())
+}
+
+fn simulate(kind: &str, level: i32, distance: &str, gear: Option<i32>, runs: usize) -> Result<()> {
+    let kind = match kind {
+        "battles" => simulation::Kind::Battles,
+        _ => simulation::Kind::Chests,
+    };
+    if level < 1 {
+        bail!("invalid level {}.", level);
+    }
+    let distances = parse_distances(distance)?;
+    log::simulation(
+        kind,
+        &simulation::simulate(kind, level, gear, distances, runs),
+    );
+    Ok(())
+}
+
+/// Parse a distance from home like `5`, or a range of them like `1-10`.
+fn parse_distances(input: &str) -> Result<RangeInclusive<i32>> {
+    let (from, to) = input.split_once('-').unwrap_or((input, input));
+    match (from.parse::<i32>(), to.parse::<i32>()) {
+        (Ok(from), Ok(to)) if 1 <= from && from <= to => Ok(from..=to),
+        _ => bail!("invalid distance {}.", input),
+    }
+}
+
 fn debug_command(game: &mut Game, level: i32) {
     game.reset();
     game.gold = 5000 * level;
@@ -216,251 +1351,364 @@ fn debug_command(game: &mut Game, level: i32) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rpg_cli::randomizer;
+
+    /// The game logic lives in the library, so randomness needs to be
+    /// turned off explicitly for these tests.
+    fn deterministic(test: impl FnOnce()) {
+        randomizer::with_randomizer(randomizer::TestRandomizer, test)
+    }
 
     #[test]
     fn change_dir_battle() {
-        let mut game = Game::new();
-        let cmd = Command::ChangeDir {
-            destination: "~/..".to_string(),
-            run: false,
-            bribe: false,
-            force: false,
-        };
-
-        // increase level to ensure win
-        for _ in 0..5 {
-            game.player.add_experience(game.player.xp_for_next());
-        }
-
-        let result = run(Some(cmd), &mut game);
-
-        assert!(result.is_ok());
-        assert!(game.player.xp > 0);
-        assert!(game.gold > 0);
+        deterministic(|| {
+            let mut game = Game::new();
+            let cmd = Command::ChangeDir {
+                destination: "~/..".to_string(),
+                run: false,
+                bribe: false,
+                cast: None,
+                force: false,
+            };
+
+            // increase level to ensure win
+            for _ in 0..5 {
+                game.player.add_experience(game.player.xp_for_next());
+            }
+
+            let result = run(Some(cmd), &mut game);
+
+            assert!(result.is_ok());
+            assert!(game.player.xp > 0);
+            assert!(game.gold > 0);
+        });
     }
 
     #[test]
     fn change_dir_dead() {
-        let mut game = Game::new();
-        let cmd = Command::ChangeDir {
-            destination: "~/..".to_string(),
-            run: false,
-            bribe: false,
-            force: false,
-        };
-
-        // reduce stats to ensure loss
-        let weak_class = character::class::Class {
-            hp: character::class::Stat(1, 1),
-            speed: character::class::Stat(1, 1),
-            ..game.player.class
-        };
-        game.player = character::Character::new(weak_class, 1);
-        game.gold = 100;
-        game.player.xp = 100;
-
-        let result = run(Some(cmd), &mut game);
-
-        assert!(result.is_err());
-
-        // game reset
-        assert_eq!(game.player.max_hp(), game.player.current_hp);
-        assert_eq!(0, game.gold);
-        assert_eq!(0, game.player.xp);
-        assert!(!game.tombstones.is_empty());
+        deterministic(|| {
+            let mut game = Game::new();
+            let cmd = Command::ChangeDir {
+                destination: "~/..".to_string(),
+                run: false,
+                bribe: false,
+                cast: None,
+                force: false,
+            };
+
+            // reduce stats to ensure loss
+            let weak_class = character::class::Class {
+                hp: character::class::Stat(1, 1),
+                speed: character::class::Stat(1, 1),
+                ..game.player.class
+            };
+            game.player = character::Character::new(weak_class, 1);
+            game.gold = 100;
+            game.player.xp = 100;
+
+            let result = run(Some(cmd), &mut game);
+
+            assert!(result.is_err());
+
+            // game reset
+            assert_eq!(game.player.max_hp(), game.player.current_hp);
+            assert_eq!(0, game.gold);
+            assert_eq!(0, game.player.xp);
+            assert!(!game.tombstones.is_empty());
+        });
     }
 
     #[test]
     fn status_effect_dead() {
-        let mut game = Game::new();
-
-        // using force prevents battle but effects should apply anyway
-        let cmd = Command::ChangeDir {
-            destination: "~/..".to_string(),
-            run: false,
-            bribe: false,
-            force: true,
-        };
-
-        // reduce stats to ensure loss
-        let weak_class = character::class::Class {
-            hp: character::class::Stat(1, 1),
-            speed: character::class::Stat(1, 1),
-            ..game.player.class
-        };
-        game.player = character::Character::new(weak_class, 1);
-        game.player.status_effect = Some(character::StatusEffect::Burn);
-        game.gold = 100;
-        game.player.xp = 100;
-
-        let result = run(Some(cmd), &mut game);
-
-        assert!(result.is_err());
-
-        // game reset
-        assert_eq!(game.player.max_hp(), game.player.current_hp);
-        assert_eq!(0, game.gold);
-        assert_eq!(0, game.player.xp);
-        assert!(!game.tombstones.is_empty());
+        deterministic(|| {
+            let mut game = Game::new();
+
+            // using force prevents battle but effects should apply anyway
+            let cmd = Command::ChangeDir {
+                destination: "~/..".to_string(),
+                run: false,
+                bribe: false,
+                cast: None,
+                force: true,
+            };
+
+            // reduce stats to ensure loss
+            let weak_class = character::class::Class {
+                hp: character::class::Stat(1, 1),
+                speed: character::class::Stat(1, 1),
+                ..game.player.class
+            };
+            game.player = character::Character::new(weak_class, 1);
+            game.player
+                .status_effects
+                .inflict(character::StatusEffect::Burn);
+            game.gold = 100;
+            game.player.xp = 100;
+
+            let result = run(Some(cmd), &mut game);
+
+            assert!(result.is_err());
+
+            // game reset
+            assert_eq!(game.player.max_hp(), game.player.current_hp);
+            assert_eq!(0, game.gold);
+            assert_eq!(0, game.player.xp);
+            assert!(!game.tombstones.is_empty());
+        });
     }
 
     #[test]
     fn change_dir_home() {
-        let mut game = Game::new();
-
-        assert!(game.location.is_home());
-
-        // force move to a non home location
-        let cmd = Command::ChangeDir {
-            destination: "~/..".to_string(),
-            run: false,
-            bribe: false,
-            force: true,
-        };
-
-        let result = run(Some(cmd), &mut game);
-        assert!(result.is_ok());
-        assert!(!game.location.is_home());
-
-        game.player.current_hp = 1;
-
-        // back home (without forcing)
-        let cmd = Command::ChangeDir {
-            destination: "~".to_string(),
-            run: false,
-            bribe: false,
-            force: false,
-        };
-
-        let result = run(Some(cmd), &mut game);
-        assert!(result.is_ok());
-        assert!(game.location.is_home());
-        assert_eq!(game.player.max_hp(), game.player.current_hp);
+        deterministic(|| {
+            let mut game = Game::new();
+
+            assert!(game.location.is_home());
+
+            // force move to a non home location
+            let cmd = Command::ChangeDir {
+                destination: "~/..".to_string(),
+                run: false,
+                bribe: false,
+                cast: None,
+                force: true,
+            };
+
+            let result = run(Some(cmd), &mut game);
+            assert!(result.is_ok());
+            assert!(!game.location.is_home());
+
+            game.player.current_hp = 1;
+
+            // back home (without forcing)
+            let cmd = Command::ChangeDir {
+                destination: "~".to_string(),
+                run: false,
+                bribe: false,
+                cast: None,
+                force: false,
+            };
+
+            let result = run(Some(cmd), &mut game);
+            assert!(result.is_ok());
+            assert!(game.location.is_home());
+            assert_eq!(game.player.max_hp(), game.player.current_hp);
+        });
     }
 
     #[test]
     fn change_dir_home_force() {
-        let mut game = Game::new();
-
-        assert!(game.location.is_home());
-
-        // force move to a non home location
-        let cmd = Command::ChangeDir {
-            destination: "~/..".to_string(),
-            run: false,
-            bribe: false,
-            force: true,
-        };
-
-        let result = run(Some(cmd), &mut game);
-        assert!(result.is_ok());
-        assert!(!game.location.is_home());
-
-        game.player.current_hp = 1;
-
-        // force back home should restore hp
-        let cmd = Command::ChangeDir {
-            destination: "~".to_string(),
-            run: false,
-            bribe: false,
-            force: true,
-        };
-
-        let result = run(Some(cmd), &mut game);
-        assert!(result.is_ok());
-        assert!(game.location.is_home());
-        assert_eq!(game.player.max_hp(), game.player.current_hp);
+        deterministic(|| {
+            let mut game = Game::new();
+
+            assert!(game.location.is_home());
+
+            // force move to a non home location
+            let cmd = Command::ChangeDir {
+                destination: "~/..".to_string(),
+                run: false,
+                bribe: false,
+                cast: None,
+                force: true,
+            };
+
+            let result = run(Some(cmd), &mut game);
+            assert!(result.is_ok());
+            assert!(!game.location.is_home());
+
+            game.player.current_hp = 1;
+
+            // force back home should restore hp
+            let cmd = Command::ChangeDir {
+                destination: "~".to_string(),
+                run: false,
+                bribe: false,
+                cast: None,
+                force: true,
+            };
+
+            let result = run(Some(cmd), &mut game);
+            assert!(result.is_ok());
+            assert!(game.location.is_home());
+            assert_eq!(game.player.max_hp(), game.player.current_hp);
+        });
     }
 
     #[test]
     fn inspect_tombstone() {
-        // die at non home with some gold
-        let mut game = Game::new();
-        assert!(game.tombstones.is_empty());
-
-        let cmd = Command::ChangeDir {
-            destination: "~/..".to_string(),
-            run: false,
-            bribe: false,
-            force: false,
-        };
-
-        // reduce stats to ensure loss
-        game.player.current_hp = 1;
-
-        game.gold = 100;
-        assert!(run(Some(cmd), &mut game).is_err());
+        deterministic(|| {
+            // die at non home with some gold
+            let mut game = Game::new();
+            assert!(game.tombstones.is_empty());
+
+            let cmd = Command::ChangeDir {
+                destination: "~/..".to_string(),
+                run: false,
+                bribe: false,
+                cast: None,
+                force: false,
+            };
+
+            // reduce stats to ensure loss
+            game.player.current_hp = 1;
+
+            game.gold = 100;
+            assert!(run(Some(cmd), &mut game).is_err());
+
+            assert_eq!(0, game.gold);
+            assert!(!game.tombstones.is_empty());
+
+            // force move to the previous dead location
+            let cmd = Command::ChangeDir {
+                destination: "~/..".to_string(),
+                run: false,
+                bribe: false,
+                cast: None,
+                force: true,
+            };
+            run(Some(cmd), &mut game).unwrap();
+
+            // inspect to pick up lost gold
+            let cmd = Command::Inspect { choose: false };
+            let result = run(Some(cmd), &mut game);
+            assert!(result.is_ok());
+            assert!(game.tombstones.is_empty());
+
+            // includes +200g for visit tombstone quest and +200g for recovering it
+            assert_eq!(500, game.gold);
+        });
+    }
 
-        assert_eq!(0, game.gold);
-        assert!(!game.tombstones.is_empty());
+    #[test]
+    fn selection() {
+        assert_eq!(Vec::<usize>::new(), parse_selection("\n", 3).unwrap());
+        assert_eq!(vec![0, 2], parse_selection("1 3\n", 3).unwrap());
+        assert_eq!(vec![0, 1], parse_selection("1,2", 3).unwrap());
+        assert_eq!(vec![0, 1, 2], parse_selection("all", 3).unwrap());
+        assert!(parse_selection("4", 3).is_err());
+        assert!(parse_selection("potion", 3).is_err());
+    }
 
-        // force move to the previous dead location
-        let cmd = Command::ChangeDir {
-            destination: "~/..".to_string(),
-            run: false,
-            bribe: false,
-            force: true,
-        };
-        run(Some(cmd), &mut game).unwrap();
+    #[test]
+    fn distances() {
+        assert_eq!(5..=5, parse_distances("5").unwrap());
+        assert_eq!(1..=10, parse_distances("1-10").unwrap());
+        assert!(parse_distances("0").is_err());
+        assert!(parse_distances("10-1").is_err());
+        assert!(parse_distances("far").is_err());
+    }
 
-        // inspect to pick up lost gold
-        let cmd = Command::Inspect;
-        let result = run(Some(cmd), &mut game);
-        assert!(result.is_ok());
-        assert!(game.tombstones.is_empty());
+    #[test]
+    fn explore_dungeon() {
+        deterministic(|| {
+            let mut game = Game::new();
+            let explore = |game: &mut Game, direction: Option<&str>, exit: bool| {
+                let cmd = Command::Dungeon {
+                    direction: direction.map(String::from),
+                    exit,
+                };
+                run(Some(cmd), game)
+            };
+
+            assert!(explore(&mut game, Some("e"), false).is_err());
+            assert!(explore(&mut game, None, true).is_err());
+
+            assert!(explore(&mut game, None, false).is_ok());
+            assert!(game.dungeon.is_some());
+            assert!(explore(&mut game, Some("n"), false).is_err());
+            assert!(explore(&mut game, Some("up"), false).is_err());
+
+            assert!(explore(&mut game, None, true).is_ok());
+            assert!(game.dungeon.is_none());
+        });
+    }
 
-        // includes +200g for visit tombstone quest
-        assert_eq!(300, game.gold);
+    #[test]
+    fn actions() {
+        deterministic(|| {
+            let mut game = Game::new();
+            assert_eq!(Action::Attack, parse_action(&game, "a\n").unwrap());
+            assert_eq!(Action::Flee, parse_action(&game, "flee").unwrap());
+            assert!(parse_action(&game, "run").is_err());
+
+            // only items in the inventory can be used
+            assert!(parse_action(&game, "use potion").is_err());
+            game.add_item(Box::new(item::Potion::new(1)));
+            assert_eq!(
+                Action::Use(Key::Potion),
+                parse_action(&game, "u potion").unwrap()
+            );
+            game.add_item(Box::new(item::Escape::new()));
+            assert!(parse_action(&game, "use escape").is_err());
+
+            // only known spells with enough mp can be cast
+            assert!(parse_action(&game, "cast heal").is_err());
+            game.player = character::Character::new(
+                character::class::Class::player_by_name("mage")
+                    .unwrap()
+                    .clone(),
+                1,
+            );
+            assert_eq!(
+                Action::Cast(Spell::Heal),
+                parse_action(&game, "c heal").unwrap()
+            );
+        });
     }
 
     #[test]
     fn buy_use_item() {
-        let mut game = Game::new();
-        assert!(game.inventory().is_empty());
-
-        // not buy if not enough money
-        let cmd = Command::Buy {
-            items: vec![String::from("potion")],
-        };
-        let result = run(Some(cmd), &mut game);
-        assert!(result.is_err());
-        assert!(game.inventory().is_empty());
-
-        // buy potion
-        game.gold = 200;
-        let cmd = Command::Buy {
-            items: vec![String::from("potion")],
-        };
-        let result = run(Some(cmd), &mut game);
-        assert!(result.is_ok());
-        assert!(!game.inventory().is_empty());
-        assert_eq!(0, game.gold);
-
-        // use potion
-        game.player.current_hp -= 1;
-        let cmd = Command::Use {
-            items: vec![String::from("potion")],
-        };
-        let result = run(Some(cmd), &mut game);
-        assert!(result.is_ok());
-        assert!(game.inventory().is_empty());
-        assert_eq!(game.player.max_hp(), game.player.current_hp);
-
-        // not buy if not home
-        let cmd = Command::ChangeDir {
-            destination: "~/..".to_string(),
-            run: false,
-            bribe: false,
-            force: true,
-        };
-        run(Some(cmd), &mut game).unwrap();
-
-        game.gold = 200;
-        let cmd = Command::Buy {
-            items: vec![String::from("potion")],
-        };
-        let result = run(Some(cmd), &mut game);
-        assert!(result.is_err());
-        assert!(game.inventory().is_empty());
+        deterministic(|| {
+            let mut game = Game::new();
+            assert!(game.inventory().is_empty());
+
+            // not buy if not enough money
+            let cmd = Command::Buy {
+                items: vec![String::from("potion")],
+            };
+            let result = run(Some(cmd), &mut game);
+            assert!(result.is_err());
+            assert!(game.inventory().is_empty());
+
+            // buy potion
+            game.gold = 200;
+            let cmd = Command::Buy {
+                items: vec![String::from("potion")],
+            };
+            let result = run(Some(cmd), &mut game);
+            assert!(result.is_ok());
+            assert!(!game.inventory().is_empty());
+            assert_eq!(0, game.gold);
+
+            // use potion
+            game.player.current_hp -= 1;
+            let cmd = Command::Use {
+                items: vec![String::from("potion")],
+                to: None,
+                spend: None,
+                yes: false,
+            };
+            let result = run(Some(cmd), &mut game);
+            assert!(result.is_ok());
+            assert!(game.inventory().is_empty());
+            assert_eq!(game.player.max_hp(), game.player.current_hp);
+
+            // not buy if not home
+            let cmd = Command::ChangeDir {
+                destination: "~/..".to_string(),
+                run: false,
+                bribe: false,
+                cast: None,
+                force: true,
+            };
+            run(Some(cmd), &mut game).unwrap();
+
+            game.gold = 200;
+            let cmd = Command::Buy {
+                items: vec![String::from("potion")],
+            };
+            let result = run(Some(cmd), &mut game);
+            assert!(result.is_err());
+            assert!(game.inventory().is_empty());
+        });
     }
 }
@@ -0,0 +1,389 @@
+use clap::{App, AppSettings, ArgSettings};
+use rpg_cli::character::class::{Category, Class};
+use rpg_cli::game::Game;
+use rpg_cli::item::Item;
+
+/// The shells completion scripts can be printed for.
+pub const SHELLS: [&str; 3] = ["bash", "zsh", "fish"];
+
+/// The kinds of game names that `rpg-cli complete` can list.
+pub const KINDS: [&str; 4] = ["items", "rings", "classes", "waypoints"];
+
+/// A command of the command line, as needed to complete it.
+struct Spec {
+    /// The command name followed by its aliases.
+    names: Vec<String>,
+    about: String,
+    /// What the positional arguments are completed with.
+    values: Option<Values>,
+    options: Vec<Opt>,
+    subcommands: Vec<Spec>,
+}
+
+struct Opt {
+    long: String,
+    about: String,
+    takes_value: bool,
+}
+
+#[derive(Clone, Copy)]
+enum Values {
+    /// Names taken from the game data, listed by `rpg-cli complete`.
+    Game(&'static str),
+    Directories,
+}
+
+/// The arguments completed with more than the command options, by the
+/// command path.
+fn values(path: &[&str]) -> Option<Values> {
+    match path {
+        ["stat"] | ["use"] | ["shop"] => Some(Values::Game("items")),
+        ["upgrade"] => Some(Values::Game("rings")),
+        ["class"] => Some(Values::Game("classes")),
+        ["waypoint", "go"] => Some(Values::Game("waypoints")),
+        ["cd"] => Some(Values::Directories),
+        _ => None,
+    }
+}
+
+/// The completion script for the given shell, built from the command line
+/// definition so it stays in sync with it. Arguments naming things from
+/// the game, like the items to use, are completed by calling back the game
+/// with `rpg-cli complete`, so they always match the current save data.
+pub fn script(app: &App, shell: &str) -> String {
+    let root = spec(app, &[]);
+    match shell {
+        "zsh" => zsh(&root),
+        "fish" => fish(&root),
+        _ => bash(&root),
+    }
+}
+
+/// The names of the given kind found in the game, to complete arguments.
+pub fn names(kind: &str, game: Option<&Game>) -> Vec<String> {
+    let mut names: Vec<String> = match (kind, game) {
+        ("classes", _) => Class::names(Category::Player).into_iter().collect(),
+        ("items", Some(game)) => game.inventory().keys().map(|k| k.to_string()).collect(),
+        ("rings", Some(game)) => [&game.player.left_ring, &game.player.right_ring]
+            .iter()
+            .filter_map(|ring| ring.as_ref().map(|ring| ring.key().to_string()))
+            .collect(),
+        ("waypoints", Some(game)) => game.waypoints.keys().cloned().collect(),
+        _ => Vec::new(),
+    };
+    names.sort();
+    names
+}
+
+fn spec(app: &App, path: &[&str]) -> Spec {
+    let mut names = vec![app.get_name().to_string()];
+    for alias in app.get_all_aliases() {
+        if !names.iter().any(|name| name == alias) {
+            names.push(alias.to_string());
+        }
+    }
+
+    let options = app
+        .get_arguments()
+        .filter(|arg| !arg.is_set(ArgSettings::Hidden))
+        .filter_map(|arg| {
+            let long = arg.get_long()?;
+            Some(Opt {
+                long: long.to_string(),
+                about: summary(arg.get_about()),
+                takes_value: arg.is_set(ArgSettings::TakesValue),
+            })
+        })
+        .filter(|opt| opt.long != "help" && opt.long != "version")
+        .collect();
+
+    let subcommands = app
+        .get_subcommands()
+        .filter(|sub| !sub.is_set(AppSettings::Hidden) && sub.get_name() != "help")
+        .map(|sub| {
+            let mut path = path.to_vec();
+            path.push(sub.get_name());
+            spec(sub, &path)
+        })
+        .collect();
+
+    Spec {
+        names,
+        about: summary(app.get_about()),
+        values: values(path),
+        options,
+        subcommands,
+    }
+}
+
+/// The first sentence of a help text, to describe a completion.
+fn summary(about: Option<&str>) -> String {
+    let about = about.unwrap_or_default();
+    let sentence = about.split(". ").next().unwrap_or_default();
+    sentence.trim().trim_end_matches('.').to_string()
+}
+
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// The commands and their subcommands, with the path of names to each one,
+/// deepest first so that shell case patterns match the nested commands
+/// before their parents.
+fn flatten<'a>(spec: &'a Spec, path: &str, specs: &mut Vec<(String, &'a Spec)>) {
+    for sub in &spec.subcommands {
+        let path = format!("{} {}", path, sub.names[0]);
+        flatten(sub, &path, specs);
+        specs.push((path, sub));
+    }
+}
+
+/// The case patterns matching the given command path, with any aliases of
+/// its last command, for the words typed so far.
+fn patterns(path: &str, spec: &Spec, nested: bool) -> String {
+    let parent = &path[..path.len() - spec.names[0].len()];
+    spec.names
+        .iter()
+        .flat_map(|name| {
+            let exact = format!("\"{}{}\"", parent, name);
+            if nested {
+                vec![exact]
+            } else {
+                vec![exact.clone(), format!("\"{}{} \"*", parent, name)]
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+fn words(spec: &Spec) -> String {
+    let mut words: Vec<String> = spec
+        .subcommands
+        .iter()
+        .flat_map(|sub| sub.names.clone())
+        .collect();
+    words.extend(spec.options.iter().map(|opt| format!("--{}", opt.long)));
+    words.join(" ")
+}
+
+fn bash(root: &Spec) -> String {
+    let mut cases = vec![format!(
+        "        \"\") candidates={} ;;",
+        quote(&words(root))
+    )];
+    let mut specs = Vec::new();
+    flatten(root, "", &mut specs);
+    for (path, spec) in specs {
+        let nested = !spec.subcommands.is_empty();
+        let mut case = format!(
+            "        {})\n            candidates={}",
+            patterns(&path, spec, nested),
+            quote(&words(spec))
+        );
+        match spec.values {
+            Some(Values::Game(kind)) => case.push_str(&format!(
+                "\n            candidates=\"$candidates $(rpg-cli complete {} 2>/dev/null)\"",
+                kind
+            )),
+            Some(Values::Directories) => case.push_str(
+                "\n            [[ \"$cur\" != -* ]] && COMPREPLY=($(compgen -d -- \"$cur\")) && return",
+            ),
+            None => {}
+        }
+        case.push_str(" ;;");
+        cases.push(case);
+    }
+
+    format!(
+        r#"_rpg_cli() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" line="" candidates="" i
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        [[ "${{COMP_WORDS[i]}}" != -* ]] && line="$line ${{COMP_WORDS[i]}}"
+    done
+    case "$line" in
+{}
+    esac
+    COMPREPLY=($(compgen -W "$candidates" -- "$cur"))
+}}
+complete -F _rpg_cli rpg-cli rpg
+"#,
+        cases.join("\n")
+    )
+}
+
+fn zsh(root: &Spec) -> String {
+    let describe = |spec: &Spec| {
+        let commands: Vec<String> = spec
+            .subcommands
+            .iter()
+            .map(|sub| quote(&format!("{}:{}", sub.names[0], sub.about)))
+            .collect();
+        let options: Vec<String> = spec
+            .options
+            .iter()
+            .map(|opt| quote(&format!("--{}:{}", opt.long, opt.about)))
+            .collect();
+        let mut lines = Vec::new();
+        if !commands.is_empty() {
+            lines.push(format!(
+                "commands=({}); _describe command commands",
+                commands.join(" ")
+            ));
+        }
+        if !options.is_empty() {
+            lines.push(format!(
+                "options=({}); _describe option options",
+                options.join(" ")
+            ));
+        }
+        lines
+    };
+
+    let mut cases = vec![format!(
+        "        \"\")\n            {} ;;",
+        describe(root).join("\n            ")
+    )];
+    let mut specs = Vec::new();
+    flatten(root, "", &mut specs);
+    for (path, spec) in specs {
+        let nested = !spec.subcommands.is_empty();
+        let mut lines = describe(spec);
+        match spec.values {
+            Some(Values::Game(kind)) => lines.push(format!(
+                "compadd -- ${{(f)\"$(rpg-cli complete {} 2>/dev/null)\"}}",
+                kind
+            )),
+            Some(Values::Directories) => lines.push(String::from("_directories")),
+            None => {}
+        }
+        if lines.is_empty() {
+            continue;
+        }
+        cases.push(format!(
+            "        {})\n            {} ;;",
+            patterns(&path, spec, nested),
+            lines.join("\n            ")
+        ));
+    }
+
+    format!(
+        r#"#compdef rpg-cli rpg
+_rpg_cli() {{
+    local line="" i
+    local -a commands options
+    for ((i = 2; i < CURRENT; i++)); do
+        [[ "${{words[i]}}" != -* ]] && line="$line ${{words[i]}}"
+    done
+    case "$line" in
+{}
+    esac
+}}
+compdef _rpg_cli rpg-cli rpg
+"#,
+        cases.join("\n")
+    )
+}
+
+fn fish(root: &Spec) -> String {
+    let mut lines = vec![String::from("complete -c rpg-cli -f")];
+    let option = |condition: &str, opt: &Opt| {
+        let mut line = format!("complete -c rpg-cli{} -l {}", condition, opt.long);
+        if opt.takes_value {
+            line.push_str(" -r");
+        }
+        line.push_str(&format!(" -d {}", quote(&opt.about)));
+        line
+    };
+
+    for opt in &root.options {
+        lines.push(option("", opt));
+    }
+    for sub in &root.subcommands {
+        lines.push(format!(
+            "complete -c rpg-cli -n __fish_use_subcommand -a {} -d {}",
+            sub.names[0],
+            quote(&sub.about)
+        ));
+        let seen = format!(" -n '__fish_seen_subcommand_from {}'", sub.names.join(" "));
+        for opt in &sub.options {
+            lines.push(option(&seen, opt));
+        }
+        match sub.values {
+            Some(Values::Game(kind)) => lines.push(format!(
+                "complete -c rpg-cli{} -a '(rpg-cli complete {} 2>/dev/null)'",
+                seen, kind
+            )),
+            Some(Values::Directories) => lines.push(format!(
+                "complete -c rpg-cli{} -a '(__fish_complete_directories)'",
+                seen
+            )),
+            None => {}
+        }
+
+        let names: Vec<&str> = sub
+            .subcommands
+            .iter()
+            .map(|nested| nested.names[0].as_str())
+            .collect();
+        for nested in &sub.subcommands {
+            lines.push(format!(
+                "complete -c rpg-cli -n '__fish_seen_subcommand_from {}; and not __fish_seen_subcommand_from {}' -a {} -d {}",
+                sub.names[0],
+                names.join(" "),
+                nested.names[0],
+                quote(&nested.about)
+            ));
+            if let Some(Values::Game(kind)) = nested.values {
+                lines.push(format!(
+                    "complete -c rpg-cli -n '__fish_seen_subcommand_from {}; and __fish_seen_subcommand_from {}' -a '(rpg-cli complete {} 2>/dev/null)'",
+                    sub.names[0], nested.names[0], kind
+                ));
+            }
+        }
+    }
+    lines.push(String::from("complete -c rpg -w rpg-cli"));
+    format!("{}\n", lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::IntoApp;
+
+    #[test]
+    fn test_scripts() {
+        let app = crate::Opts::into_app();
+
+        let bash = script(&app, "bash");
+        assert!(bash.contains("\"\") candidates='stat s status cd"));
+        assert!(bash.contains("\" use\" | \" use \"* | \" u\" | \" u \"*)"));
+        assert!(bash.contains("$(rpg-cli complete items 2>/dev/null)"));
+        assert!(bash.contains("\" waypoint go\" | \" waypoint go \"*)"));
+        assert!(bash.contains("\" waypoint\")\n            candidates='set list go'"));
+        assert!(!bash.contains("idkfa"));
+
+        let zsh = script(&app, "zsh");
+        assert!(zsh.contains(
+            "'upgrade:Upgrades an equipped stat ring at the shop, spending gold and a level stone'"
+        ));
+        assert!(zsh.contains("compadd -- ${(f)\"$(rpg-cli complete rings 2>/dev/null)\"}"));
+
+        let fish = script(&app, "fish");
+        assert!(fish.contains(
+            "-n '__fish_seen_subcommand_from class' -a '(rpg-cli complete classes 2>/dev/null)'"
+        ));
+        assert!(fish.contains("complete -c rpg-cli -l seed -r"));
+    }
+
+    #[test]
+    fn test_names() {
+        let mut game = Game::new();
+        game.add_item(Box::new(rpg_cli::item::Potion::new(1)));
+        game.add_item(Box::new(rpg_cli::item::Escape::new()));
+
+        assert_eq!(vec!["escape", "potion"], names("items", Some(&game)));
+        assert!(names("rings", Some(&game)).is_empty());
+        assert!(names("classes", None).contains(&String::from("warrior")));
+        assert!(names("items", None).is_empty());
+    }
+}
@@ -0,0 +1,274 @@
+use crate::hook::{Hook, Webhook};
+use crate::location::Pattern;
+use crate::theme::Theme;
+use anyhow::{anyhow, Result};
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+
+/// Player preferences, optionally customized with a config.yaml file
+/// at the rpg data directory.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Config {
+    /// Seed for the random number generator, to make the game deterministic.
+    /// Overridden by the --seed option.
+    pub seed: Option<u64>,
+
+    /// Which enemy to attack first when fighting a group.
+    pub target: Target,
+
+    /// Hero level that unlocks New Game+, besides beating the final boss.
+    pub new_game_plus_level: Option<i32>,
+
+    /// Commands to run and pipes to write to when game events happen.
+    pub hooks: Vec<Hook>,
+
+    /// Web service to notify of notable game events.
+    pub webhook: Option<Webhook>,
+
+    /// Language of the game text, e.g. `es`. Overridden by the RPG_LOCALE
+    /// environment variable.
+    pub locale: Option<String>,
+
+    /// Colors of the game output.
+    pub theme: Theme,
+
+    /// Directories where no enemies nor chests are found, besides the ones
+    /// with an ignore marker file.
+    pub ignore: Vec<Pattern>,
+
+    /// Make enemies stronger and chests richer in directories with many
+    /// files or recent changes.
+    pub directory_scaling: bool,
+
+    /// The enemy classes dwelling in each kind of lair.
+    pub lairs: Lairs,
+
+    /// How the game data is written to disk.
+    pub save_format: SaveFormat,
+
+    /// Where the game data is saved.
+    pub storage: StorageBackend,
+
+    /// Where the `sync` command keeps a copy of the game, to play it on
+    /// several machines.
+    pub sync: Option<SyncRemote>,
+
+    /// Server where the `leaderboard` command submits the hero summary.
+    pub leaderboard: Option<LeaderboardServer>,
+}
+
+/// Strategy to choose the enemy to attack in battles against groups.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    /// The enemy with the least hp left, to reduce the group size sooner.
+    #[default]
+    Weakest,
+    /// The enemy with the highest level.
+    Strongest,
+    /// The enemies in the order they appeared.
+    First,
+}
+
+/// Names of the enemy classes more likely to show up at each kind of lair.
+/// Kinds left out keep their default dwellers, and an empty list leaves
+/// the kind without any.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(default)]
+pub struct Lairs {
+    pub repo: Vec<String>,
+    pub hidden: Vec<String>,
+    pub temp: Vec<String>,
+}
+
+impl Default for Lairs {
+    fn default() -> Self {
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Self {
+            repo: names(&["gremlin", "spider", "slime"]),
+            hidden: names(&["wraith", "sorcerer"]),
+            temp: names(&["zombie", "skeleton", "wraith"]),
+        }
+    }
+}
+
+/// Encoding of the saved game data. Any of them can be loaded, regardless
+/// of the one used to save.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveFormat {
+    /// MessagePack compressed with zstd, the smallest and fastest to load.
+    #[default]
+    Compressed,
+    /// MessagePack without compression.
+    Binary,
+    /// Plain JSON, to read or edit the data by hand.
+    Json,
+}
+
+/// Where the game data is saved, in the rpg data directory.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    /// A directory of files per profile.
+    #[default]
+    Files,
+    /// An SQLite database shared by the profiles, only available when built
+    /// with the `sqlite` feature.
+    Sqlite,
+}
+
+/// Remote copy of the game kept by the `sync` command.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncRemote {
+    /// A git repository, committing the game to the given branch.
+    Git {
+        url: String,
+        #[serde(default = "SyncRemote::default_branch")]
+        branch: String,
+    },
+    /// A bucket of an S3-compatible service, at the given endpoint, e.g.
+    /// `https://s3.amazonaws.com`. The credentials are taken from the
+    /// AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY environment variables.
+    S3 {
+        endpoint: String,
+        bucket: String,
+        #[serde(default = "SyncRemote::default_region")]
+        region: String,
+    },
+}
+
+impl SyncRemote {
+    fn default_branch() -> String {
+        String::from("main")
+    }
+
+    fn default_region() -> String {
+        String::from("us-east-1")
+    }
+}
+
+/// Server of the shared leaderboard, only used when built with the
+/// `leaderboard` feature.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LeaderboardServer {
+    /// Rankings are read from this url, and summaries posted to it.
+    pub url: String,
+    /// The player name shown in the rankings.
+    pub name: String,
+    /// Secret shared with the server, to sign the submitted summaries.
+    pub key: String,
+}
+
+static CONFIG: OnceCell<Config> = OnceCell::new();
+
+impl Config {
+    /// Customize the config based on an input yaml byte array.
+    pub fn load(bytes: &[u8]) -> Result<()> {
+        let config =
+            serde_yaml::from_slice(bytes).map_err(|e| anyhow!("Invalid config file: {}", e))?;
+        CONFIG.set(config).unwrap();
+        Ok(())
+    }
+
+    pub fn get() -> &'static Self {
+        CONFIG.get_or_init(Config::default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> Config {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn parse_seed() {
+        assert!(parse("{}").seed.is_none());
+        assert_eq!(Some(42), parse("seed: 42").seed);
+    }
+
+    #[test]
+    fn parse_target() {
+        assert_eq!(Target::Weakest, parse("{}").target);
+        assert_eq!(Target::Strongest, parse("target: strongest").target);
+    }
+
+    #[test]
+    fn parse_new_game_plus_level() {
+        assert!(parse("{}").new_game_plus_level.is_none());
+        let config = parse("new_game_plus_level: 50");
+        assert_eq!(Some(50), config.new_game_plus_level);
+    }
+
+    #[test]
+    fn parse_hooks() {
+        assert!(parse("{}").hooks.is_empty());
+
+        let config = parse(
+            "hooks:\n  - on: level_up\n    command: echo up\n  - on: death\n    pipe: /tmp/rpg",
+        );
+        assert_eq!(2, config.hooks.len());
+        assert_eq!(Some(String::from("echo up")), config.hooks[0].command);
+        assert_eq!(Some(String::from("/tmp/rpg")), config.hooks[1].pipe);
+        assert!(serde_yaml::from_str::<Config>("hooks:\n  - on: lunch").is_err());
+    }
+
+    #[test]
+    fn parse_locale() {
+        assert!(parse("{}").locale.is_none());
+        assert_eq!(Some(String::from("es")), parse("locale: es").locale);
+    }
+
+    #[test]
+    fn parse_theme() {
+        assert!(parse("{}").theme.is_colored());
+        assert!(!parse("theme:\n  preset: no_color").theme.is_colored());
+    }
+
+    #[test]
+    fn parse_save_format() {
+        assert_eq!(SaveFormat::Compressed, parse("{}").save_format);
+        assert_eq!(SaveFormat::Json, parse("save_format: json").save_format);
+    }
+
+    #[test]
+    fn parse_storage() {
+        assert_eq!(StorageBackend::Files, parse("{}").storage);
+        assert_eq!(StorageBackend::Sqlite, parse("storage: sqlite").storage);
+    }
+
+    #[test]
+    fn parse_sync() {
+        assert!(parse("{}").sync.is_none());
+        assert_eq!(
+            Some(SyncRemote::Git {
+                url: String::from("saves.git"),
+                branch: String::from("main")
+            }),
+            parse("sync:\n  git:\n    url: saves.git").sync
+        );
+    }
+
+    #[test]
+    fn parse_lairs() {
+        assert_eq!(Lairs::default(), parse("{}").lairs);
+
+        let config = parse("lairs:\n  repo: [rat]\n  temp: []");
+        assert_eq!(vec![String::from("rat")], config.lairs.repo);
+        assert_eq!(Lairs::default().hidden, config.lairs.hidden);
+        assert!(config.lairs.temp.is_empty());
+    }
+
+    #[test]
+    fn parse_leaderboard() {
+        assert!(parse("{}").leaderboard.is_none());
+
+        let config = parse("leaderboard:\n  url: http://localhost\n  name: me\n  key: secret");
+        assert_eq!(String::from("me"), config.leaderboard.unwrap().name);
+    }
+}
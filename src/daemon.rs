@@ -0,0 +1,183 @@
+use crate::command::{self, Command};
+use crate::Opts;
+use anyhow::Result;
+use rpg_cli::game::Game;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A command sent to the daemon: the command line arguments, without the
+/// binary name, and the directory it was run from, which relative paths
+/// are resolved against.
+#[derive(Serialize, Deserialize)]
+struct Request {
+    args: Vec<String>,
+    cwd: PathBuf,
+}
+
+/// The output of a command run by the daemon, and its error if it failed.
+/// Commands the daemon doesn't run are answered with `local`, so the client
+/// runs them itself.
+#[derive(Serialize, Deserialize, Default)]
+struct Response {
+    output: Vec<String>,
+    error: Option<String>,
+    local: bool,
+}
+
+/// Run the command with the daemon of the profile, if there's one running,
+/// printing its output. Returns whether the daemon ran it.
+#[cfg(unix)]
+pub fn forward(opts: &Opts) -> Result<bool> {
+    use std::net::Shutdown;
+    use std::os::unix::net::UnixStream;
+
+    if let Some(Command::Daemon) = opts.cmd {
+        return Ok(false);
+    }
+    let stream = match UnixStream::connect(rpg_cli::datafile::socket_file()) {
+        Ok(stream) => stream,
+        Err(_) => return Ok(false),
+    };
+    let request = Request {
+        args: std::env::args_os()
+            .skip(1)
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect(),
+        cwd: std::env::current_dir()?,
+    };
+    serde_json::to_writer(&stream, &request)?;
+    stream.shutdown(Shutdown::Write)?;
+
+    let response: Response = serde_json::from_reader(&stream)?;
+    if response.local {
+        return Ok(false);
+    }
+    for line in response.output {
+        println!("{}", line);
+    }
+    match response.error {
+        Some(error) => Err(anyhow::anyhow!(error)),
+        None => Ok(true),
+    }
+}
+
+#[cfg(not(unix))]
+pub fn forward(_opts: &Opts) -> Result<bool> {
+    Ok(false)
+}
+
+/// Listen for commands at the socket of the profile, running them on the
+/// given game and saving it after each one, until the process is stopped.
+#[cfg(unix)]
+pub fn serve(opts: &Opts, game: &mut Game) -> Result<()> {
+    use rpg_cli::{datafile, log};
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    let socket = datafile::socket_file();
+    if UnixStream::connect(&socket).is_ok() {
+        anyhow::bail!("There's a daemon already running for this profile.");
+    }
+    // left behind by a daemon that was stopped
+    let _ = std::fs::remove_file(&socket);
+    let listener = UnixListener::bind(&socket)?;
+    log::daemon_listening(&socket);
+
+    let mut modified = datafile::modified();
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(_) => continue,
+        };
+        let response = match serde_json::from_reader::<_, Request>(&stream) {
+            Ok(request) => match parse(opts, &request) {
+                Some(request_opts) => execute(request_opts, &request.cwd, game, &mut modified),
+                None => Response {
+                    local: true,
+                    ..Response::default()
+                },
+            },
+            Err(err) => Response {
+                error: Some(err.to_string()),
+                ..Response::default()
+            },
+        };
+        // the client may be gone, e.g. if it was interrupted
+        let _ = serde_json::to_writer(&stream, &response);
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn serve(_opts: &Opts, _game: &mut Game) -> Result<()> {
+    anyhow::bail!("The daemon is only supported on Unix systems.")
+}
+
+/// The options of the request, if the daemon can run its command. It can't
+/// run the ones that read from the terminal or affect other profiles, nor
+/// print with other output options than its own.
+#[cfg_attr(not(unix), allow(dead_code))]
+fn parse(daemon: &Opts, request: &Request) -> Option<Opts> {
+    use clap::Clap;
+
+    let args = std::iter::once("rpg-cli").chain(request.args.iter().map(String::as_str));
+    let opts = Opts::try_parse_from(args).ok()?;
+    let output = (opts.quiet, opts.verbose, opts.plain, opts.json);
+    if output != (daemon.quiet, daemon.verbose, daemon.plain, daemon.json) {
+        return None;
+    }
+    match opts.cmd {
+        Some(Command::Daemon)
+        | Some(Command::Tui)
+        | Some(Command::Completions { .. })
+        | Some(Command::Complete { .. })
+        | Some(Command::Migrate { .. })
+        | Some(Command::Profiles { .. })
+        | Some(Command::Reset { hard: true, .. })
+        | Some(Command::Battle {
+            interactive: true, ..
+        })
+        | Some(Command::Inspect { choose: true }) => None,
+        _ => Some(opts),
+    }
+}
+
+/// Run the command of the request on the game and save it, same as the cli
+/// does, keeping the output to send it back.
+#[cfg(unix)]
+fn execute(
+    opts: Opts,
+    cwd: &std::path::Path,
+    game: &mut Game,
+    modified: &mut Option<std::time::SystemTime>,
+) -> Response {
+    use rpg_cli::{config, datafile, log};
+
+    let seed = opts.seed.or(config::Config::get().seed);
+    log::capture(true);
+    let result = (|| {
+        std::env::set_current_dir(cwd)?;
+        // commands run without the daemon may have changed the game since
+        if datafile::modified() != *modified {
+            *game = datafile::load()?.unwrap_or_else(|| crate::new_game(seed));
+        }
+        if let Some(Command::Prompt { format }) = &opts.cmd {
+            log::prompt(game, format);
+            return Ok(());
+        }
+
+        crate::init_random(game, seed)?;
+        let result = command::run(opts.cmd, game);
+        datafile::save(game)?;
+        datafile::save_battles(log::recorded_battles())?;
+        *modified = datafile::modified();
+        result
+    })();
+
+    let output = log::captured();
+    log::capture(false);
+    Response {
+        output,
+        error: result.err().map(|err| err.to_string()),
+        local: false,
+    }
+}
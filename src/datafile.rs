@@ -1,38 +1,663 @@
 use crate::character::class;
+use crate::config::{Config, SaveFormat, StorageBackend};
 use crate::game;
-use anyhow::{bail, Result};
+use crate::i18n;
+use crate::item::drops::Drops;
+use crate::location;
+use crate::log;
+use crate::plugin;
+use crate::storage::{self, FileStorage, Storage};
+use crate::title;
+use anyhow::{anyhow, bail, Result};
+use once_cell::sync::OnceCell;
+use serde_json::{json, Map, Value};
 use std::{fs, io, path};
 
 struct NotFound;
 
+/// What was found reading a saved game.
+enum Saved {
+    Missing,
+    /// The file couldn't be parsed as a game, e.g. it was cut short by an
+    /// interrupted write.
+    Corrupt,
+    /// The game, with the count of commits it was saved with.
+    Game(Box<game::Game>, u64),
+}
+
+const INVALID_DATA: &str = "Invalid game data file. If it was generated with a previous version please run `reset --hard` to restart.";
+
+/// A change to the save format, applied to the data saved with the previous
+/// version. Returns a description of each change made.
+type Migration = fn(&mut Value) -> Vec<String>;
+
+/// The migrations to apply, in order, to upgrade data from each version of
+/// the save format to the next. Data saved before the format was versioned
+/// is version 0.
+const MIGRATIONS: [Migration; 1] = [add_version];
+
+/// The current version of the save format.
+const VERSION: usize = MIGRATIONS.len();
+
+/// The first bytes of zstd compressed data.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Compression level of saves, low since they're written on every command.
+const ZSTD_LEVEL: i32 = 3;
+
+/// Game fields that aren't needed to show the hero status, mostly the
+/// history of the game, saved apart so they're only loaded when playing.
+const COLD_FIELDS: [&str; 10] = [
+    "tombstones",
+    "chests",
+    "inspected",
+    "visited",
+    "buried_chests",
+    "quests",
+    "achievements",
+    "bestiary",
+    "challenges",
+    "stats",
+];
+
+/// Field of the game data with the hash of its cold data.
+const COLD_HASH: &str = "cold";
+
+/// Names of the entries saved for each profile.
+const DATA: &str = "data";
+const COLD: &str = "cold";
+/// Data set aside when the game was recovered from the backup.
+const CORRUPT: &str = "data.corrupt";
+/// Count of the saves of ironman games, kept apart from the data.
+const COMMITS: &str = "commits";
+/// Turn-by-turn events of the latest battles, for replays.
+const BATTLES: &str = "battles";
+/// Fingerprint of the game as it was when last synced with the remote.
+const SYNCED: &str = "synced";
+
+/// How many of the most recent battles are kept in the battle log.
+const MAX_RECORDED_BATTLES: usize = 50;
+
+/// The profile whose game is loaded and saved by the current command.
+static PROFILE: OnceCell<String> = OnceCell::new();
+
+/// Where the game of the profile is saved.
+static STORAGE: OnceCell<Box<dyn Storage>> = OnceCell::new();
+
+/// The profile stored directly at the rpg data directory, which always exists.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Set the profile of the game to load and save, or the active one
+/// if none is given, saving it to the storage of the config.
+pub fn init_profile(profile: Option<String>) -> Result<()> {
+    let profile = profile.unwrap_or_else(active_profile);
+    if !profiles().contains(&profile) {
+        bail!(
+            "Profile {} not found, create it with `rpg profiles --create {}`.",
+            profile,
+            profile
+        );
+    }
+    location::init_abstract(abstract_file(&profile).exists());
+    PROFILE.set(profile).unwrap();
+    if STORAGE.get().is_none() {
+        init_storage(open_storage()?);
+    }
+    Ok(())
+}
+
+/// Save the game to the given storage instead of the one of the config,
+/// e.g. to keep it in memory. It must be set before the game is loaded.
+pub fn init_storage(storage: Box<dyn Storage>) {
+    if STORAGE.set(storage).is_err() {
+        panic!("storage already initialized");
+    }
+}
+
+fn open_storage() -> Result<Box<dyn Storage>> {
+    open_storage_of(PROFILE.get().map_or(DEFAULT_PROFILE, String::as_str))
+}
+
+/// The storage of the config for the game of the given profile.
+fn open_storage_of(profile: &str) -> Result<Box<dyn Storage>> {
+    match Config::get().storage {
+        StorageBackend::Files => Ok(Box::new(FileStorage::new(dir_of(profile)))),
+        #[cfg(feature = "sqlite")]
+        StorageBackend::Sqlite => {
+            let storage = storage::SqliteStorage::open(&database_file(), profile)?;
+            Ok(Box::new(storage))
+        }
+        #[cfg(not(feature = "sqlite"))]
+        StorageBackend::Sqlite => {
+            bail!(
+                "This rpg-cli was built without SQLite support, change the storage in the config."
+            )
+        }
+    }
+}
+
+/// The storage of the game, the profile directory if none was set.
+fn storage() -> &'static dyn Storage {
+    STORAGE
+        .get_or_init(|| Box::new(FileStorage::new(profile_dir())))
+        .as_ref()
+}
+
+/// The profile whose game is loaded and saved by the current command.
+pub fn current_profile() -> String {
+    PROFILE.get().cloned().unwrap_or_else(active_profile)
+}
+
+/// The profile used when none is passed with the --profile option.
+pub fn active_profile() -> String {
+    fs::read_to_string(profile_file())
+        .map(|name| name.trim().to_string())
+        .unwrap_or_else(|_| DEFAULT_PROFILE.to_string())
+}
+
+/// The names of the existing profiles, the default one first.
+pub fn profiles() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(profiles_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .filter_map(|entry| entry.file_name().into_string().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    names.sort();
+    names.insert(0, DEFAULT_PROFILE.to_string());
+    names
+}
+
+/// Create a new profile, with its own independent game. Abstract profiles
+/// are played in a world of virtual places instead of the filesystem, and
+/// the games of speedrun profiles are timed.
+pub fn create_profile(name: &str, abstract_world: bool, speedrun: bool) -> Result<()> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!("Profile names can only contain letters, numbers, - and _.");
+    }
+    if profiles().iter().any(|profile| profile == name) {
+        bail!("Profile {} already exists.", name);
+    }
+    fs::create_dir_all(profiles_dir().join(name))?;
+    if abstract_world {
+        fs::write(abstract_file(name), "")?;
+    }
+    if speedrun {
+        fs::write(speedrun_file(name), "")?;
+    }
+    Ok(())
+}
+
+/// Delete a profile and its game, wherever the storage of the config keeps
+/// it. The default and active profiles can't be deleted.
+pub fn delete_profile(name: &str) -> Result<()> {
+    if name == DEFAULT_PROFILE {
+        bail!("The default profile can't be deleted.");
+    }
+    if !profiles().iter().any(|profile| profile == name) {
+        bail!("Profile {} not found.", name);
+    }
+    if name == active_profile() || Some(name) == PROFILE.get().map(String::as_str) {
+        bail!("Can't delete the profile in use, switch to another one first.");
+    }
+    open_storage_of(name)?.clear()?;
+    fs::remove_dir_all(profiles_dir().join(name))?;
+    Ok(())
+}
+
+/// Make the given profile the one used by the next commands.
+pub fn switch_profile(name: &str) -> Result<()> {
+    if !profiles().iter().any(|profile| profile == name) {
+        bail!("Profile {} not found.", name);
+    }
+    if name == DEFAULT_PROFILE {
+        if profile_file().exists() {
+            fs::remove_file(profile_file())?;
+        }
+    } else {
+        storage::write_file(&profile_file(), name.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Load the saved game, if any. When the data is missing or corrupt but
+/// the backup of the previous save is fine, the game is recovered from it,
+/// keeping the corrupt data aside.
 pub fn load() -> Result<Option<game::Game>> {
-    match read(data_file()) {
-        Err(NotFound) => Ok(None),
-        Ok(data) => {
-            if let Ok(game) = serde_json::from_slice(&data) {
-                Ok(Some(game))
-            } else {
-                bail!("Invalid game data file. If it was generated with a previous version please run `reset --hard` to restart.");
+    let storage = storage();
+    let (mut game, commits) = match read_game(storage, DATA, true)? {
+        Saved::Game(game, commits) => (game, commits),
+        saved => match read_game(storage, &backup_of(DATA), true)? {
+            Saved::Game(game, commits) => {
+                if let Saved::Corrupt = saved {
+                    storage.rename(DATA, CORRUPT)?;
+                }
+                log::save_recovered();
+                (game, commits)
             }
+            _ if matches!(saved, Saved::Missing) => return Ok(None),
+            _ => bail!("{}", INVALID_DATA),
+        },
+    };
+
+    if game.ironman && !is_latest(commits, saved_commits()) {
+        game.ironman = false;
+        log::ironman_lost();
+    }
+    Ok(Some(*game))
+}
+
+/// Read the game saved in the given entry, upgrading it to the current save
+/// format. Without `cold`, the cold fields are left with their defaults.
+/// Only data saved by a newer version is an error, since it can't be
+/// recovered from a backup either.
+fn read_game(storage: &dyn Storage, name: &str, cold: bool) -> Result<Saved> {
+    let value = match read_data(storage, name, cold) {
+        Err(NotFound) => return Ok(Saved::Missing),
+        Ok(None) => return Ok(Saved::Corrupt),
+        Ok(Some(value)) => value,
+    };
+    let (value, _) = migrate_value(value)?;
+    let commits = value["commits"].as_u64().unwrap_or(0);
+    match serde_json::from_value(value) {
+        Ok(game) => Ok(Saved::Game(Box::new(game), commits)),
+        Err(_) => Ok(Saved::Corrupt),
+    }
+}
+
+/// Read the game data saved in the given entry as is, joined with its cold
+/// data if `cold` is set. Data that can't be decoded, or whose cold data is
+/// missing, is `None`.
+fn read_data(storage: &dyn Storage, name: &str, cold: bool) -> Result<Option<Value>, NotFound> {
+    let mut value = match decode(&storage.read(name).ok_or(NotFound)?) {
+        Some(value @ Value::Object(_)) => value,
+        _ => return Ok(None),
+    };
+    // data saved before the split has no cold part
+    let hash = match value.as_object_mut().unwrap().remove(COLD_HASH) {
+        Some(hash) if cold => hash,
+        _ => return Ok(Some(value)),
+    };
+    match hash.as_str().and_then(|hash| read_cold(storage, hash)) {
+        Some(fields) => {
+            value.as_object_mut().unwrap().extend(fields);
+            Ok(Some(value))
         }
+        None => Ok(None),
+    }
+}
+
+/// The cold data with the given hash. It's looked for in the backup too,
+/// since it's the one that matches the data saved before it.
+fn read_cold(storage: &dyn Storage, hash: &str) -> Option<Map<String, Value>> {
+    vec![COLD.to_string(), backup_of(COLD)]
+        .into_iter()
+        .find_map(|name| {
+            let data = storage.read(&name)?;
+            if hash != format!("{:016x}", fnv(&data)) {
+                return None;
+            }
+            match decode(&data)? {
+                Value::Object(fields) => Some(fields),
+                _ => None,
+            }
+        })
+}
+
+/// Whether data saved with the given count of commits is the latest one,
+/// according to the count kept outside of it. Restored backups have fewer
+/// commits, and a missing count can't be trusted. Data with one commit
+/// more than the count was saved right before an interruption.
+fn is_latest(commits: u64, saved: Option<u64>) -> bool {
+    match saved {
+        Some(saved) => commits == saved || commits == saved + 1,
+        None => commits == 0,
     }
 }
 
+/// Load the game without writing anything, not even when recovering it from
+/// the backup, for commands that shouldn't have side effects. Missing or
+/// invalid data is just reported as no game. The cold data isn't loaded,
+/// so it's fast enough to run on every shell prompt.
+pub fn peek() -> Option<game::Game> {
+    vec![DATA.to_string(), backup_of(DATA)]
+        .into_iter()
+        .find_map(|name| match read_game(storage(), &name, false) {
+            Ok(Saved::Game(game, _)) => Some(*game),
+            _ => None,
+        })
+}
+
+/// Load the game of another profile, as `peek` does, e.g. to check what it
+/// can receive in a trade. A profile without a saved game has none.
+pub fn peek_profile(profile: &str) -> Option<game::Game> {
+    let storage = open_storage_of(profile).ok()?;
+    vec![DATA.to_string(), backup_of(DATA)]
+        .into_iter()
+        .find_map(|name| match read_game(storage.as_ref(), &name, false) {
+            Ok(Saved::Game(game, _)) => Some(*game),
+            _ => None,
+        })
+}
+
+/// Save the game, replacing the data atomically so it's never left half
+/// written, and keeping the previous one as backup. The cold fields are
+/// saved apart, and the data refers to them by hash.
+/// Ironman games also count their saves, to tell when an older copy of the
+/// data is restored.
 pub fn save(game: &game::Game) -> Result<(), io::Error> {
-    let data = serde_json::to_vec(game).unwrap();
-    write(data_file(), data)
+    let mut value = serde_json::to_value(game).unwrap();
+    value["version"] = json!(VERSION);
+    let commits = game.ironman.then(|| saved_commits().unwrap_or(0) + 1);
+    if let Some(commits) = commits {
+        value["commits"] = json!(commits);
+    }
+    let storage = storage();
+    write_game(storage, value)?;
+    if let Some(commits) = commits {
+        storage.write(COMMITS, commits.to_string().as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Write the game data, and its cold fields apart.
+fn write_game(storage: &dyn Storage, mut value: Value) -> Result<(), io::Error> {
+    let fields = value.as_object_mut().unwrap();
+    let cold: Map<String, Value> = COLD_FIELDS
+        .iter()
+        .filter_map(|field| fields.remove_entry(*field))
+        .collect();
+    let cold = encode(&Value::Object(cold))?;
+    fields.insert(
+        String::from(COLD_HASH),
+        json!(format!("{:016x}", fnv(&cold))),
+    );
+    let data = encode(&value)?;
+
+    // the cold data goes first, so the data never refers to missing cold
+    // data. If interrupted, the backups are recovered on load
+    replace(storage, COLD, &cold)?;
+    replace(storage, DATA, &data)
+}
+
+/// Append the given battles to the battle log, dropping the oldest ones
+/// past the maximum kept.
+pub fn save_battles(battles: Vec<Vec<Value>>) -> Result<(), io::Error> {
+    if battles.is_empty() {
+        return Ok(());
+    }
+    let mut log = load_battles();
+    log.extend(battles);
+    let excess = log.len().saturating_sub(MAX_RECORDED_BATTLES);
+    log.drain(..excess);
+    storage().write(BATTLES, &serde_json::to_vec(&log).unwrap())
+}
+
+/// The battles in the battle log, each one as a list of its events, the
+/// most recent last.
+pub fn load_battles() -> Vec<Vec<Value>> {
+    storage()
+        .read(BATTLES)
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .unwrap_or_default()
+}
+
+/// Upgrade the game data to the current save format, returning the
+/// changes made. With `dry_run`, the changes are only reported.
+pub fn migrate(dry_run: bool) -> Result<Vec<String>> {
+    let value = match read_data(storage(), DATA, true) {
+        Err(NotFound) => return Ok(Vec::new()),
+        Ok(None) => bail!("{}", INVALID_DATA),
+        Ok(Some(value)) => value,
+    };
+
+    // saving keeps the original data as backup
+    let (value, changes) = migrate_value(value)?;
+    if !dry_run && !changes.is_empty() {
+        match serde_json::from_value::<game::Game>(value) {
+            Ok(game) => save(&game)?,
+            Err(_) => bail!("{}", INVALID_DATA),
+        }
+    }
+    Ok(changes)
+}
+
+/// When the game data was last written, to tell whether the game changed
+/// since it was loaded.
+pub fn modified() -> Option<std::time::SystemTime> {
+    storage().modified(DATA)
+}
+
+/// Where the daemon of the profile listens for commands.
+pub fn socket_file() -> path::PathBuf {
+    profile_dir().join("daemon.sock")
+}
+
+/// The fingerprint of the game at the last sync, if it was ever synced.
+pub fn synced() -> Option<String> {
+    let checksum = storage().read(SYNCED)?;
+    Some(String::from_utf8_lossy(&checksum).into_owned())
+}
+
+pub fn save_synced(fingerprint: &str) -> Result<(), io::Error> {
+    storage().write(SYNCED, fingerprint.as_bytes())
 }
 
+/// Delete the saved game, and its backups so they're not recovered instead.
 pub fn remove() {
-    let rpg_dir = rpg_dir();
-    if rpg_dir.exists() {
-        fs::remove_file(data_file()).unwrap();
+    let storage = storage();
+    for name in &[backup_of(DATA), COLD.to_string(), backup_of(COLD)] {
+        storage.remove(name).unwrap();
     }
+    storage.remove(DATA).unwrap();
 }
 
-pub fn load_classes() {
+pub fn load_classes() -> Result<()> {
     if let Ok(bytes) = read(classes_file()) {
-        class::Class::load(&bytes)
+        class::Class::load(&bytes)?;
+    }
+    Ok(())
+}
+
+pub fn load_config() -> Result<()> {
+    if let Ok(bytes) = read(config_file()) {
+        Config::load(&bytes)?;
+    }
+    Ok(())
+}
+
+/// Load the `.rhai` scripts at the plugins directory, in name order.
+pub fn load_plugins() -> Result<()> {
+    let entries = match fs::read_dir(plugins_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return Ok(()),
+    };
+    let mut paths: Vec<path::PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+        .collect();
+    paths.sort();
+
+    let mut sources = Vec::new();
+    for path in paths {
+        let name = path.file_stem().unwrap().to_string_lossy().to_string();
+        sources.push((name, fs::read_to_string(&path)?));
+    }
+    plugin::load(&sources)
+}
+
+/// Set the language of the game text from the RPG_LOCALE environment
+/// variable or the config file, using the translation file at the locales
+/// directory if there's one for it.
+pub fn load_locale() -> Result<()> {
+    let locale = match std::env::var("RPG_LOCALE") {
+        Ok(locale) if !locale.is_empty() => locale,
+        _ => match &Config::get().locale {
+            Some(locale) => locale.clone(),
+            None => return Ok(()),
+        },
+    };
+    let user = read(locales_dir().join(format!("{}.yaml", locale))).ok();
+    i18n::load(&locale, user.as_deref())
+}
+
+pub fn load_drops() -> Result<()> {
+    if let Ok(bytes) = read(drops_file()) {
+        Drops::load(&bytes)?;
+    }
+    Ok(())
+}
+
+/// Write the given game to a self-contained file, for backup or to move it
+/// to another machine.
+pub fn export(game: &game::Game, file: &path::Path) -> Result<()> {
+    fs::write(file, serde_json::to_vec_pretty(&exported(game))?)?;
+    Ok(())
+}
+
+/// The given game as written by `export`. A checksum is included to detect
+/// changes made to it. The hero title is included apart too, to be shown
+/// without reading the game.
+pub fn exported(game: &game::Game) -> Value {
+    let mut value = serde_json::to_value(game).unwrap();
+    value["version"] = json!(VERSION);
+    let level = game.player.level;
+    json!({
+        "level": level,
+        "title": title::shown(game),
+        "checksum": checksum(level, &value),
+        "game": value,
+    })
+}
+
+/// Read a game from a file written by `export`.
+pub fn import(file: &path::Path) -> Result<game::Game> {
+    let data = fs::read(file).map_err(|e| anyhow!("Can't read {}: {}", file.display(), e))?;
+    let export: Value = serde_json::from_slice(&data).unwrap_or_default();
+    imported(&export, &file.display().to_string())
+}
+
+/// The game of a value written by `export`, validating that it wasn't
+/// modified and upgrading it to the current save format. Imported games are
+/// never in ironman mode, since they could be older copies of the game.
+/// The source names where the value was read from, for errors.
+pub fn imported(export: &Value, source: &str) -> Result<game::Game> {
+    let (level, checksum_value, value) = match (
+        export["level"].as_i64(),
+        export["checksum"].as_str(),
+        export.get("game"),
+    ) {
+        (Some(level), Some(checksum), Some(game)) => (level as i32, checksum, game),
+        _ => bail!("{} is not an exported game.", source),
+    };
+    if checksum(level, value) != checksum_value {
+        bail!("The exported game was modified or is corrupted.");
+    }
+
+    let (value, _) = migrate_value(value.clone())?;
+    match serde_json::from_value::<game::Game>(value) {
+        Ok(mut game) if game.player.level == level => {
+            game.ironman = false;
+            Ok(game)
+        }
+        _ => bail!("The exported game was modified or is corrupted."),
+    }
+}
+
+/// A FNV-1a hash of the hero level and the game data, as hex. It doesn't
+/// prevent tampering but makes editing the exported file less trivial.
+fn checksum(level: i32, game: &Value) -> String {
+    let data = format!("rpg-cli:{}:{}", level, game);
+    format!("{:016x}", fnv(data.as_bytes()))
+}
+
+/// A hash of the game to tell whether it changed. Unlike the checksum of
+/// exports, it's the same for equal games even if their sets are serialized
+/// in a different order, since arrays are sorted before hashing.
+pub fn fingerprint(game: &game::Game) -> String {
+    let value = canonical(serde_json::to_value(game).unwrap());
+    format!("{:016x}", fnv(value.to_string().as_bytes()))
+}
+
+/// The value with its arrays sorted, recursively.
+fn canonical(value: Value) -> Value {
+    match value {
+        Value::Array(items) => {
+            let mut items: Vec<Value> = items.into_iter().map(canonical).collect();
+            items.sort_by_cached_key(|item| item.to_string());
+            Value::Array(items)
+        }
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(field, value)| (field, canonical(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// The FNV-1a hash of the data.
+fn fnv(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in data {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Apply the migrations the given game data is missing. Returns the
+/// migrated data and the changes made.
+fn migrate_value(mut value: Value) -> Result<(Value, Vec<String>)> {
+    let version = value["version"].as_u64().unwrap_or(0) as usize;
+    if version > VERSION {
+        bail!("The game data was saved by a newer version of rpg-cli, please upgrade it.");
+    }
+
+    let mut changes = Vec::new();
+    for migration in &MIGRATIONS[version..] {
+        changes.extend(migration(&mut value));
+    }
+    value["version"] = json!(VERSION);
+    Ok((value, changes))
+}
+
+/// Version 1: the save format version is recorded with the data.
+fn add_version(_value: &mut Value) -> Vec<String> {
+    vec![String::from("record the save format version")]
+}
+
+/// Serialize the game data in the save format of the config.
+fn encode(value: &Value) -> Result<Vec<u8>, io::Error> {
+    let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+    match Config::get().save_format {
+        SaveFormat::Json => serde_json::to_vec(value).map_err(io::Error::from),
+        SaveFormat::Binary => rmp_serde::to_vec(value).map_err(invalid),
+        SaveFormat::Compressed => {
+            let data = rmp_serde::to_vec(value).map_err(invalid)?;
+            zstd::encode_all(data.as_slice(), ZSTD_LEVEL)
+        }
+    }
+}
+
+/// Parse game data saved in any of the save formats, telling them apart by
+/// their first bytes. JSON data is always an object.
+fn decode(data: &[u8]) -> Option<Value> {
+    if data.starts_with(&ZSTD_MAGIC) {
+        let data = zstd::decode_all(data).ok()?;
+        rmp_serde::from_slice(&data).ok()
+    } else if data.first() == Some(&b'{') {
+        serde_json::from_slice(data).ok()
+    } else {
+        rmp_serde::from_slice(data).ok()
     }
 }
 
@@ -40,12 +665,10 @@ fn read(file: path::PathBuf) -> Result<Vec<u8>, NotFound> {
     fs::read(file).map_err(|_| NotFound)
 }
 
-fn write(file: path::PathBuf, data: Vec<u8>) -> Result<(), io::Error> {
-    let rpg_dir = rpg_dir();
-    if !rpg_dir.exists() {
-        fs::create_dir(&rpg_dir).unwrap();
-    }
-    fs::write(file, &data)
+/// Write the entry, moving the previous contents to its backup.
+fn replace(storage: &dyn Storage, name: &str, data: &[u8]) -> Result<(), io::Error> {
+    storage.rename(name, &backup_of(name))?;
+    storage.write(name, data)
 }
 
 pub fn rpg_dir() -> path::PathBuf {
@@ -59,19 +682,99 @@ pub fn rpg_dir() -> path::PathBuf {
     }
 }
 
-fn data_file() -> path::PathBuf {
-    rpg_dir().join("data")
+/// Where the files of the current profile are kept.
+fn profile_dir() -> path::PathBuf {
+    dir_of(PROFILE.get().map_or(DEFAULT_PROFILE, String::as_str))
+}
+
+/// Where the files of the given profile are kept.
+fn dir_of(profile: &str) -> path::PathBuf {
+    if profile == DEFAULT_PROFILE {
+        rpg_dir()
+    } else {
+        profiles_dir().join(profile)
+    }
+}
+
+/// Copy of the entry as it was before the last save, e.g. before it was
+/// migrated to a new save format.
+fn backup_of(name: &str) -> String {
+    format!("{}.bak", name)
+}
+
+fn saved_commits() -> Option<u64> {
+    let count = storage().read(COMMITS)?;
+    String::from_utf8_lossy(&count).trim().parse().ok()
+}
+
+/// Where the git repository of the sync config is cloned.
+pub fn sync_dir() -> path::PathBuf {
+    rpg_dir().join("sync")
+}
+
+/// The database of the games of all the profiles, with the sqlite storage.
+#[cfg(feature = "sqlite")]
+fn database_file() -> path::PathBuf {
+    rpg_dir().join("rpg.db")
+}
+
+fn profiles_dir() -> path::PathBuf {
+    rpg_dir().join("profiles")
+}
+
+/// Where the gold and items traded to the given profile wait to be claimed.
+pub fn escrow_file(profile: &str) -> path::PathBuf {
+    rpg_dir().join("escrow").join(format!("{}.json", profile))
+}
+
+/// Whether the games of the current profile are speedruns.
+pub fn is_speedrun() -> bool {
+    match PROFILE.get() {
+        Some(profile) if profile != DEFAULT_PROFILE => speedrun_file(profile).exists(),
+        _ => false,
+    }
+}
+
+/// Marks the profiles whose games are speedruns.
+fn speedrun_file(profile: &str) -> path::PathBuf {
+    profiles_dir().join(profile).join("speedrun")
+}
+
+/// Marks the profiles played in the abstract world.
+fn abstract_file(profile: &str) -> path::PathBuf {
+    profiles_dir().join(profile).join("abstract")
+}
+
+fn profile_file() -> path::PathBuf {
+    rpg_dir().join("profile")
 }
 
 fn classes_file() -> path::PathBuf {
     rpg_dir().join("classes.yaml")
 }
 
+fn drops_file() -> path::PathBuf {
+    rpg_dir().join("drops.toml")
+}
+
+fn plugins_dir() -> path::PathBuf {
+    rpg_dir().join("plugins")
+}
+
+fn locales_dir() -> path::PathBuf {
+    rpg_dir().join("locales")
+}
+
+fn config_file() -> path::PathBuf {
+    rpg_dir().join("config.yaml")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::item::key;
     use crate::item::ring;
+    use crate::storage::MemoryStorage;
 
     #[test]
     fn serialize_ring() {
@@ -84,4 +787,164 @@ mod tests {
         let mut game: game::Game = serde_json::from_slice(&data).unwrap();
         assert!(game.use_item(key::Key::Ring(ring::Ring::Void)).is_ok());
     }
+
+    #[test]
+    fn migrations() {
+        // unversioned data is migrated and loads fine
+        let value = serde_json::to_value(game::Game::new()).unwrap();
+        let (value, changes) = migrate_value(value).unwrap();
+        assert_eq!(1, changes.len());
+        assert_eq!(json!(VERSION), value["version"]);
+        assert!(serde_json::from_value::<game::Game>(value.clone()).is_ok());
+
+        // up to date data is left as is
+        let (_, changes) = migrate_value(value.clone()).unwrap();
+        assert!(changes.is_empty());
+
+        // data from the future is not touched
+        let mut future = value;
+        future["version"] = json!(VERSION + 1);
+        assert!(migrate_value(future).is_err());
+    }
+
+    #[test]
+    fn export_import() {
+        let file = std::env::temp_dir().join("rpg-export-test.json");
+        let mut game = game::Game::new();
+        game.gold = 100;
+        game.player.level = 3;
+        game.add_item(Box::new(ring::Ring::Void));
+        game.ironman = true;
+        game.player.prestige = 1;
+        export(&game, &file).unwrap();
+
+        // the hero title can be read without the game
+        let data: Value = serde_json::from_slice(&fs::read(&file).unwrap()).unwrap();
+        assert_eq!("ascended", data["title"]);
+
+        let imported = import(&file).unwrap();
+        assert!(!imported.ironman);
+        assert_eq!(100, imported.gold);
+        assert_eq!(3, imported.player.level);
+        assert!(imported
+            .inventory
+            .contains_key(&key::Key::Ring(ring::Ring::Void)));
+
+        // edited exports are rejected
+        let data = fs::read_to_string(&file).unwrap();
+        fs::write(&file, data.replace("\"gold\": 100", "\"gold\": 99999")).unwrap();
+        assert!(import(&file).is_err());
+
+        fs::write(&file, "{}").unwrap();
+        assert!(import(&file).is_err());
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn fingerprints() {
+        let mut game = game::Game::new();
+        let value = serde_json::to_value(&game).unwrap();
+        let loaded: game::Game = serde_json::from_value(value).unwrap();
+        assert_eq!(fingerprint(&game), fingerprint(&loaded));
+
+        game.gold += 1;
+        assert_ne!(fingerprint(&game), fingerprint(&loaded));
+    }
+
+    #[test]
+    fn ironman_commits() {
+        assert!(is_latest(0, None));
+        assert!(is_latest(5, Some(5)));
+        // interrupted before the count was written
+        assert!(is_latest(6, Some(5)));
+
+        // restored backups, or data with the count removed
+        assert!(!is_latest(3, Some(5)));
+        assert!(!is_latest(5, None));
+        assert!(!is_latest(8, Some(5)));
+    }
+
+    #[test]
+    fn save_formats() {
+        let mut game = game::Game::new();
+        game.add_item(Box::new(ring::Ring::Void));
+        let value = serde_json::to_value(&game).unwrap();
+
+        let json = serde_json::to_vec(&value).unwrap();
+        let binary = rmp_serde::to_vec(&value).unwrap();
+        let compressed = zstd::encode_all(binary.as_slice(), ZSTD_LEVEL).unwrap();
+        assert!(binary.len() < json.len());
+        assert!(compressed.len() < binary.len());
+        for data in &[json, binary, compressed] {
+            let decoded = decode(data).unwrap();
+            assert_eq!(value, decoded);
+            assert!(serde_json::from_value::<game::Game>(decoded).is_ok());
+        }
+
+        let mut data = encode(&value).unwrap();
+        assert!(data.starts_with(&ZSTD_MAGIC));
+        data.truncate(data.len() / 2);
+        assert!(decode(&data).is_none());
+    }
+
+    #[test]
+    fn cold_data() {
+        let storage = MemoryStorage::default();
+        let mut game = game::Game::new();
+        game.gold = 100;
+        game.stats.earn(100);
+        let value = serde_json::to_value(&game).unwrap();
+        write_game(&storage, value.clone()).unwrap();
+        assert!(storage.read(COLD).is_some());
+
+        // the hot part alone has the hero but not the history
+        let hot = read_data(&storage, DATA, false).ok().flatten().unwrap();
+        assert_eq!(json!(100), hot["gold"]);
+        assert!(hot.get("stats").is_none());
+        assert_eq!(Some(value), read_data(&storage, DATA, true).ok().flatten());
+
+        // when interrupted after writing the cold data, the previous
+        // one is in the backup
+        let cold = storage.read(COLD).unwrap();
+        game.gold = 200;
+        game.stats.earn(100);
+        write_game(&storage, serde_json::to_value(&game).unwrap()).unwrap();
+        storage.rename(&backup_of(DATA), DATA).unwrap();
+        let joined = read_data(&storage, DATA, true).ok().flatten().unwrap();
+        assert_eq!(json!(100), joined["gold"]);
+
+        // cold data that doesn't match is as bad as damaged data
+        let backup = backup_of(COLD);
+        storage.write(&backup, &cold[..cold.len() / 2]).unwrap();
+        assert!(matches!(read_data(&storage, DATA, true), Ok(None)));
+    }
+
+    #[test]
+    fn read_damaged_data() {
+        let storage = MemoryStorage::default();
+        let read = |storage| read_game(storage, DATA, true);
+        assert!(matches!(read(&storage), Ok(Saved::Missing)));
+
+        let data = serde_json::to_vec(&game::Game::new()).unwrap();
+        storage.write(DATA, &data).unwrap();
+        assert!(matches!(read(&storage), Ok(Saved::Game(..))));
+
+        // cut short by an interrupted write
+        storage.write(DATA, &data[..data.len() / 2]).unwrap();
+        assert!(matches!(read(&storage), Ok(Saved::Corrupt)));
+        storage.write(DATA, br#"{"gold": "lots"}"#).unwrap();
+        assert!(matches!(read(&storage), Ok(Saved::Corrupt)));
+
+        // newer data can't be recovered from a backup either
+        let newer = json!({ "version": VERSION + 1 }).to_string();
+        storage.write(DATA, newer.as_bytes()).unwrap();
+        assert!(read(&storage).is_err());
+    }
+
+    #[test]
+    fn invalid_profiles() {
+        assert!(create_profile("", false, false).is_err());
+        assert!(create_profile("../data", true, true).is_err());
+        assert!(delete_profile(DEFAULT_PROFILE).is_err());
+    }
 }
@@ -0,0 +1,102 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How challenging the game is, chosen when starting a new game and kept
+/// across heroes. It scales the stats of the enemies, how often chests are
+/// found, and how far ahead of a location the hero can be before chests
+/// stop appearing there.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    Nightmare,
+}
+
+impl Difficulty {
+    pub fn from(name: &str) -> Result<Self> {
+        let difficulty = match name.to_lowercase().as_str() {
+            "easy" => Difficulty::Easy,
+            "normal" => Difficulty::Normal,
+            "hard" => Difficulty::Hard,
+            "nightmare" => Difficulty::Nightmare,
+            other => bail!("difficulty {} not found", other),
+        };
+        Ok(difficulty)
+    }
+
+    /// The stats of the enemies, as a percentage of the regular ones.
+    pub fn enemy_stats(&self) -> i32 {
+        match self {
+            Difficulty::Easy => 80,
+            Difficulty::Normal => 100,
+            Difficulty::Hard => 125,
+            Difficulty::Nightmare => 150,
+        }
+    }
+
+    /// Extra attempts at finding each kind of chest content.
+    pub fn extra_chest_rolls(&self) -> i32 {
+        match self {
+            Difficulty::Easy => 1,
+            _ => 0,
+        }
+    }
+
+    /// Percentage of the chests that would be found but are lost instead.
+    pub fn chests_lost(&self) -> u32 {
+        match self {
+            Difficulty::Easy | Difficulty::Normal => 0,
+            Difficulty::Hard => 25,
+            Difficulty::Nightmare => 50,
+        }
+    }
+
+    /// How many levels above the distance of a location the hero can be
+    /// and still find chests there.
+    pub fn level_gap(&self) -> i32 {
+        match self {
+            Difficulty::Easy => 15,
+            Difficulty::Normal => 10,
+            Difficulty::Hard => 7,
+            Difficulty::Nightmare => 5,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+            Difficulty::Nightmare => "nightmare",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_into() {
+        for difficulty in &[
+            Difficulty::Easy,
+            Difficulty::Normal,
+            Difficulty::Hard,
+            Difficulty::Nightmare,
+        ] {
+            assert_eq!(
+                *difficulty,
+                Difficulty::from(&difficulty.to_string()).unwrap()
+            );
+        }
+        assert!(Difficulty::from("insane").is_err());
+        assert_eq!(Difficulty::Normal, Difficulty::default());
+    }
+}
@@ -0,0 +1,180 @@
+use crate::character::{Character, Dead, StatusEffect};
+use crate::game::Game;
+use crate::log;
+use serde::{Deserialize, Serialize};
+
+/// The rating every hero starts dueling with.
+pub const INITIAL_RATING: i32 = 1000;
+
+/// The most a single duel can change the rating.
+const K_FACTOR: f64 = 32.0;
+
+/// Duels not decided after this many turns end in a draw, e.g. when both
+/// heroes keep healing more than they're hurt.
+const MAX_TURNS: i32 = 1000;
+
+/// How a duel ended for the hero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Won,
+    Lost,
+    Draw,
+}
+
+/// The hero's rating and results in duels against exported heroes.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Record {
+    pub rating: i32,
+    pub won: i32,
+    pub lost: i32,
+    pub drawn: i32,
+}
+
+impl Default for Record {
+    fn default() -> Self {
+        Self {
+            rating: INITIAL_RATING,
+            won: 0,
+            lost: 0,
+            drawn: 0,
+        }
+    }
+}
+
+impl Record {
+    /// Account for a duel against a rival of the given rating, updating the
+    /// rating as in the Elo system: beating a higher rated rival gains more
+    /// than beating a lower rated one. Return the rating change.
+    pub fn score(&mut self, rival_rating: i32, outcome: Outcome) -> i32 {
+        let expected = 1.0 / (1.0 + 10f64.powf((rival_rating - self.rating) as f64 / 400.0));
+        let actual = match outcome {
+            Outcome::Won => {
+                self.won += 1;
+                1.0
+            }
+            Outcome::Lost => {
+                self.lost += 1;
+                0.0
+            }
+            Outcome::Draw => {
+                self.drawn += 1;
+                0.5
+            }
+        };
+        let change = (K_FACTOR * (actual - expected)).round() as i32;
+        self.rating += change;
+        change
+    }
+}
+
+/// Fight the hero of the game against the one of the rival game, as
+/// exported by another player, and update the hero rating with the
+/// outcome. Both heroes start the duel fully restored, and nothing they
+/// do in it is kept.
+pub fn duel(game: &mut Game, rival: &Game) -> Outcome {
+    let mut hero = game.player.clone();
+    let mut rival_hero = rival.player.clone();
+    hero.restore();
+    rival_hero.restore();
+
+    log::duel_start(&hero, &rival_hero, &game.location);
+    let outcome = fight(&mut hero, &mut rival_hero);
+    log::battle_end();
+
+    let change = game.duels.score(rival.duels.rating, outcome);
+    log::duel_end(outcome, change, &game.duels);
+    outcome
+}
+
+/// Run a turn-based combat between both characters, with the turn frequency
+/// given by their speed, as in regular battles. The rings work as usual:
+/// double-beat, counter-attack and revive, at most once per duel.
+fn fight(hero: &mut Character, rival: &mut Character) -> Outcome {
+    let mut fighters = [hero, rival];
+    let mut accums = [0, 0];
+    let mut revived = [false, false];
+
+    for _ in 0..MAX_TURNS {
+        accums[0] += fighters[0].speed();
+        accums[1] += fighters[1].speed();
+        let turn = if accums[0] >= accums[1] { 0 } else { 1 };
+        let other = 1 - turn;
+
+        let [hero, rival] = &mut fighters;
+        let (attacker, receiver) = if turn == 0 {
+            (&mut **hero, &mut **rival)
+        } else {
+            (&mut **rival, &mut **hero)
+        };
+        if attacker.status_effects.has(StatusEffect::Stun) {
+            log::stunned(attacker);
+        } else {
+            let _ = attacker.attack(receiver);
+            attacker.maybe_double_beat(receiver);
+            if receiver.current_hp > 0 {
+                receiver.maybe_counter_attack(attacker);
+            }
+        }
+        if attacker.current_hp > 0 {
+            // status effects may finish off the attacker too
+            let _ = attacker.apply_status_effects();
+        }
+        accums[turn] = -1;
+
+        // check the receiver first, since it's the one more likely to fall
+        for i in [other, turn] {
+            if fighters[i].current_hp > 0 {
+                continue;
+            }
+            match fighters[i].maybe_revive(Err(Dead), revived[i]) {
+                Ok(already_revived) => revived[i] = already_revived,
+                Err(_) => {
+                    log::battle_lost(fighters[i]);
+                    return if i == 0 { Outcome::Lost } else { Outcome::Won };
+                }
+            }
+        }
+    }
+    Outcome::Draw
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::equipment::Equipment;
+
+    #[test]
+    fn ratings() {
+        let mut record = Record::default();
+        assert_eq!(16, record.score(INITIAL_RATING, Outcome::Won));
+        assert_eq!(INITIAL_RATING + 16, record.rating);
+
+        // losing to a lower rated rival costs more than winning gained
+        assert_eq!(-17, record.score(INITIAL_RATING, Outcome::Lost));
+        assert_eq!(0, record.score(record.rating, Outcome::Draw));
+
+        // beating a much better rival is worth much more
+        assert_eq!(29, record.score(record.rating + 400, Outcome::Won));
+        assert_eq!((2, 1, 1), (record.won, record.lost, record.drawn));
+    }
+
+    #[test]
+    fn duel_stronger_wins() {
+        let mut game = Game::new();
+        for _ in 1..10 {
+            game.player.add_experience(game.player.xp_for_next());
+        }
+        game.player.sword = Some(Equipment::sword(10));
+        game.player.current_hp = 1;
+        let rival = Game::new();
+
+        assert_eq!(Outcome::Won, duel(&mut game, &rival));
+        assert_eq!(INITIAL_RATING + 16, game.duels.rating);
+        assert_eq!(1, game.duels.won);
+        // the hero isn't restored by dueling
+        assert_eq!(1, game.player.current_hp);
+
+        assert_eq!(Outcome::Lost, duel(&mut Game::new(), &game));
+    }
+}
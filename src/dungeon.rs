@@ -0,0 +1,209 @@
+use crate::location::Distance;
+use crate::randomizer::choose_weighted;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// How many floors a dungeon has.
+const FLOORS: i32 = 3;
+
+/// A temporary, virtual dungeon that is not backed by real directories.
+/// Each floor is a square grid of rooms, explored one move at a time,
+/// starting at the top left corner. The floor boss waits at the opposite
+/// corner, and beating it leads to the next floor.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Dungeon {
+    floor: i32,
+    size: i32,
+    rooms: Vec<Room>,
+    visited: Vec<bool>,
+    position: (i32, i32),
+}
+
+/// What the hero finds when entering a room. Rooms are emptied once their
+/// contents are faced.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Room {
+    Empty,
+    Enemy,
+    Chest,
+    Trap,
+    Boss,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Direction {
+    pub fn from(name: &str) -> Result<Self> {
+        let direction = match name.to_lowercase().as_str() {
+            "n" | "north" => Direction::North,
+            "s" | "south" => Direction::South,
+            "e" | "east" => Direction::East,
+            "w" | "west" => Direction::West,
+            other => bail!("{} is not a direction, use n, s, e or w.", other),
+        };
+        Ok(direction)
+    }
+
+    fn offset(&self) -> (i32, i32) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::South => (0, 1),
+            Direction::East => (1, 0),
+            Direction::West => (-1, 0),
+        }
+    }
+}
+
+impl Dungeon {
+    pub fn generate() -> Self {
+        let mut dungeon = Self {
+            floor: 0,
+            size: 0,
+            rooms: Vec::new(),
+            visited: Vec::new(),
+            position: (0, 0),
+        };
+        dungeon.next_floor();
+        dungeon
+    }
+
+    /// Move to a freshly generated floor, one room wider than the previous.
+    /// Returns false if the dungeon had no more floors.
+    pub fn next_floor(&mut self) -> bool {
+        if self.floor == FLOORS {
+            return false;
+        }
+        self.floor += 1;
+        self.size = self.floor + 2;
+        self.position = (0, 0);
+
+        let rooms = (self.size * self.size) as usize;
+        let choices = [
+            (Room::Empty, 4),
+            (Room::Enemy, 3),
+            (Room::Chest, 2),
+            (Room::Trap, 1),
+        ];
+        self.rooms = (0..rooms)
+            .map(|_| choose_weighted(&choices, |c| c.1).unwrap().0)
+            .collect();
+        self.rooms[0] = Room::Empty;
+        self.rooms[rooms - 1] = Room::Boss;
+        self.visited = vec![false; rooms];
+        self.visited[0] = true;
+        true
+    }
+
+    pub fn floor(&self) -> i32 {
+        self.floor
+    }
+
+    pub fn floors(&self) -> i32 {
+        FLOORS
+    }
+
+    /// The distance from home equivalent to the current floor, which
+    /// determines the enemies and loot found in it.
+    pub fn distance(&self) -> Distance {
+        Distance::from(10 * self.floor)
+    }
+
+    pub fn can_move(&self, direction: Direction) -> bool {
+        self.index(self.next_position(direction)).is_some()
+    }
+
+    /// Move one room in the given direction, returning its contents and
+    /// leaving it empty. If there's no room there, stay in place.
+    pub fn step(&mut self, direction: Direction) -> Room {
+        let position = self.next_position(direction);
+        if let Some(index) = self.index(position) {
+            self.position = position;
+            self.visited[index] = true;
+            std::mem::replace(&mut self.rooms[index], Room::Empty)
+        } else {
+            Room::Empty
+        }
+    }
+
+    /// One line per row of the current floor: `@` for the hero's position,
+    /// `B` for the boss, `.` for visited rooms and `?` for unexplored ones.
+    pub fn map(&self) -> Vec<String> {
+        (0..self.size)
+            .map(|y| {
+                (0..self.size)
+                    .map(|x| {
+                        let index = (y * self.size + x) as usize;
+                        if (x, y) == self.position {
+                            '@'
+                        } else if self.rooms[index] == Room::Boss {
+                            'B'
+                        } else if self.visited[index] {
+                            '.'
+                        } else {
+                            '?'
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn next_position(&self, direction: Direction) -> (i32, i32) {
+        let (dx, dy) = direction.offset();
+        (self.position.0 + dx, self.position.1 + dy)
+    }
+
+    fn index(&self, (x, y): (i32, i32)) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.size || y >= self.size {
+            None
+        } else {
+            Some((y * self.size + x) as usize)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explore() {
+        let mut dungeon = Dungeon::generate();
+        assert_eq!(1, dungeon.floor());
+        assert_eq!(10, dungeon.distance().len());
+        assert_eq!(vec!["@??", "???", "??B"], dungeon.map());
+
+        assert!(!dungeon.can_move(Direction::North));
+        assert!(!dungeon.can_move(Direction::West));
+        assert!(dungeon.can_move(Direction::East));
+
+        // rooms are emptied after visiting them
+        dungeon.step(Direction::East);
+        assert_eq!(Room::Empty, dungeon.step(Direction::West));
+        dungeon.step(Direction::South);
+        dungeon.step(Direction::South);
+        dungeon.step(Direction::East);
+        assert_eq!(Room::Boss, dungeon.step(Direction::East));
+        assert_eq!(vec!["..?", ".??", "..@"], dungeon.map());
+
+        assert!(dungeon.next_floor());
+        assert_eq!(4, dungeon.map().len());
+        assert!(dungeon.next_floor());
+        assert!(!dungeon.next_floor());
+        assert_eq!(3, dungeon.floor());
+    }
+
+    #[test]
+    fn directions() {
+        assert_eq!(Direction::North, Direction::from("n").unwrap());
+        assert_eq!(Direction::West, Direction::from("West").unwrap());
+        assert!(Direction::from("up").is_err());
+    }
+}
@@ -0,0 +1,376 @@
+use crate::gamble;
+use crate::game::Game;
+use crate::hook;
+use crate::item::chest;
+use crate::item::equipment::Equipment;
+use crate::item::map::TreasureMap;
+use crate::item::ring::Ring;
+use crate::item::shop;
+use crate::item::Item;
+use crate::location::Location;
+use crate::log;
+use crate::quest;
+use crate::randomizer::{choose, random};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// Times the value of an item that a wandering merchant asks for it.
+const MERCHANT_MARKUP: i32 = 2;
+
+/// Gold rewarded for guiding a lost traveler home, per step away from it.
+const GUIDE_REWARD: i32 = 50;
+
+/// Gold wagered with a mysterious stranger, per hero level.
+const STRANGER_WAGER: i32 = 50;
+
+/// Relative weights of the merchant, traveler, stranger and rare merchant.
+const NPC_WEIGHTS: [u32; 4] = [10, 10, 10, 2];
+
+/// Gold asked by a rare merchant for a ring.
+const RARE_RING_PRICE: i32 = 25_000;
+
+/// Gold asked by a rare merchant for a treasure map.
+const RARE_MAP_PRICE: i32 = 3_000;
+
+/// Levels above those found at the distance of the gear sold by a rare
+/// merchant.
+const RARE_GEAR_BONUS: i32 = 10;
+
+/// Times the home shop price that a rare merchant asks for gear.
+const RARE_GEAR_MARKUP: i32 = 3;
+
+/// A non-hostile character met while looking around a location, which
+/// waits there for the hero to answer its offer.
+#[derive(Serialize, Deserialize)]
+pub struct Encounter {
+    pub location: Location,
+    pub npc: Npc,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Npc {
+    /// Sells an item as found in chests, above its value.
+    Merchant { item: Box<dyn Item>, price: i32 },
+    /// Asks to be guided home, adding a quest rewarded once the hero is there.
+    Traveler { reward: i32 },
+    /// Rolls the dice against the hero, as the house does at home.
+    Stranger { wager: i32 },
+    /// Sells goods that the home shop doesn't have, at a steep price, and
+    /// stays until they are sold out or the hero moves on.
+    RareMerchant { stock: Vec<(Ware, i32)> },
+}
+
+/// The goods sold by rare merchants.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum Ware {
+    /// One of the rings still missing from the game, taken out of the ring
+    /// pool once bought.
+    Ring(Ring),
+    Map,
+    /// Gear stronger than found at the distance, worn once bought.
+    Equipment(Equipment),
+}
+
+impl std::fmt::Display for Ware {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Ware::Ring(ring) => write!(f, "{}", ring),
+            Ware::Map => write!(f, "{}", TreasureMap),
+            Ware::Equipment(piece) => write!(f, "{}", piece),
+        }
+    }
+}
+
+/// The answers the hero can give to an npc.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reply {
+    Accept,
+    /// Buy the ware at the given index of a rare merchant's stock.
+    Buy(usize),
+    Leave,
+}
+
+impl Encounter {
+    /// Randomly meet an npc at the current location, with chances that
+    /// depend on the distance from home as those of chests do.
+    pub fn generate(game: &Game) -> Option<Self> {
+        if game.location.is_home() || game.location.is_ignored() {
+            return None;
+        }
+        let distance = game.location.distance_from_home();
+        if !random().npc_encounter(&distance) {
+            return None;
+        }
+
+        let npc = match random().pick_weighted(&NPC_WEIGHTS) {
+            0 => {
+                let item = chest::random_item(game.player.rounded_level());
+                let price = game.reputation.price(item.sell_price() * MERCHANT_MARKUP);
+                Npc::Merchant { item, price }
+            }
+            1 => Npc::Traveler {
+                reward: GUIDE_REWARD * distance.len(),
+            },
+            2 => Npc::Stranger {
+                wager: STRANGER_WAGER * game.player.level,
+            },
+            _ => Npc::RareMerchant {
+                stock: rare_stock(game, distance.len()),
+            },
+        };
+        Some(Self {
+            location: game.location.clone(),
+            npc,
+        })
+    }
+
+    /// The answers to the npc, in the order they are offered.
+    pub fn replies(&self) -> Vec<Reply> {
+        let mut replies = match &self.npc {
+            Npc::RareMerchant { stock } => (0..stock.len()).map(Reply::Buy).collect(),
+            _ => vec![Reply::Accept],
+        };
+        replies.push(Reply::Leave);
+        replies
+    }
+
+    /// Fail if the hero can't take the npc's offer.
+    fn check(&self, game: &Game, reply: Reply) -> Result<()> {
+        match (&self.npc, reply) {
+            (Npc::Merchant { item, price }, Reply::Accept) => {
+                if game.gold < *price {
+                    bail!("Not enough gold.");
+                }
+                if !game.has_room_for(&item.key()) {
+                    bail!("Not enough room in the inventory.");
+                }
+            }
+            (Npc::Stranger { wager }, Reply::Accept) if game.gold < *wager => {
+                bail!("Not enough gold.")
+            }
+            (Npc::Traveler { .. } | Npc::Stranger { .. }, Reply::Accept) => {}
+            (Npc::RareMerchant { stock }, Reply::Buy(index)) if index < stock.len() => {
+                let (ware, price) = &stock[index];
+                if game.gold < *price {
+                    bail!("Not enough gold.");
+                }
+                match ware {
+                    Ware::Ring(ring) if !game.ring_pool.contains(ring) => {
+                        bail!("The {} was already found.", ring)
+                    }
+                    Ware::Map if !game.has_room_for(&TreasureMap.key()) => {
+                        bail!("Not enough room in the inventory.")
+                    }
+                    Ware::Equipment(piece) => {
+                        if let Some(worn) = game.player.equipment(&piece.key()) {
+                            if worn.is_cursed() {
+                                bail!("The cursed {} can't be replaced.", worn);
+                            }
+                            if worn.level() >= piece.level() {
+                                bail!("The {} worn is already as good.", worn);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => bail!("Not a reply to this npc."),
+        }
+        Ok(())
+    }
+}
+
+/// Answer the npc met at the current location. Unless the offer can't be
+/// taken, the npc leaves afterwards.
+pub fn reply(game: &mut Game, reply: Reply) -> Result<()> {
+    let encounter = match game.encounter.take() {
+        Some(encounter) if encounter.location == game.location => encounter,
+        encounter => {
+            game.encounter = encounter;
+            bail!("There's no one to talk to here.");
+        }
+    };
+    if reply == Reply::Leave {
+        log::npc_left();
+        return Ok(());
+    }
+    if let Err(err) = encounter.check(game, reply) {
+        game.encounter = Some(encounter);
+        return Err(err);
+    }
+
+    match encounter.npc {
+        Npc::RareMerchant { mut stock } => {
+            let Reply::Buy(index) = reply else {
+                unreachable!("only purchases are checked for rare merchants")
+            };
+            let (ware, price) = stock.remove(index);
+            game.gold -= price;
+            game.stats.spend(price);
+            log::npc_trade(&ware.to_string(), price);
+            match ware {
+                Ware::Ring(ring) => {
+                    game.ring_pool.remove(&ring);
+                    hook::ring_found(&ring);
+                    game.add_item(Box::new(ring));
+                }
+                Ware::Map => game.add_item(Box::new(TreasureMap)),
+                Ware::Equipment(piece) => game.player.equip(piece),
+            }
+            // the merchant waits for the hero to look at the rest
+            if !stock.is_empty() {
+                game.encounter = Some(Encounter {
+                    location: encounter.location,
+                    npc: Npc::RareMerchant { stock },
+                });
+            }
+        }
+        Npc::Merchant { item, price } => {
+            game.gold -= price;
+            game.stats.spend(price);
+            log::npc_trade(&item.to_string(), price);
+            game.add_item(item);
+        }
+        Npc::Traveler { reward } => {
+            quest::traveler_met(game, reward);
+            log::npc_guided(reward);
+        }
+        Npc::Stranger { wager } => {
+            let roll = random().dice_roll();
+            let stranger_roll = random().dice_roll();
+            let gold = gamble::payout(wager, roll, stranger_roll);
+            game.gold += gold;
+            if gold > 0 {
+                game.stats.earn(gold);
+            } else {
+                game.stats.spend(-gold);
+            }
+            log::gamble(roll, stranger_roll, gold);
+        }
+    }
+    Ok(())
+}
+
+/// The goods of a rare merchant met at the given distance: a ring still
+/// missing from the game, if any, a treasure map and a piece of gear.
+fn rare_stock(game: &Game, distance: i32) -> Vec<(Ware, i32)> {
+    let mut stock = Vec::new();
+
+    // sort the pool since the set iteration order changes between runs,
+    // which would break seeded games
+    let mut rings: Vec<&Ring> = game.ring_pool.iter().collect();
+    rings.sort_by_key(|ring| ring.to_string());
+    if let Some(ring) = choose(&rings) {
+        stock.push((Ware::Ring((*ring).clone()), RARE_RING_PRICE));
+    }
+    stock.push((Ware::Map, RARE_MAP_PRICE));
+
+    let piece = chest::pick_equipment(distance + RARE_GEAR_BONUS);
+    let price = piece.level() * shop::EQUIPMENT_PRICE * RARE_GEAR_MARKUP;
+    stock.push((Ware::Equipment(piece), price));
+
+    stock
+        .into_iter()
+        .map(|(ware, price)| (ware, game.reputation.price(price)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::key::Key;
+    use crate::item::Potion;
+    use crate::location::tests::location_from;
+
+    fn meet(game: &mut Game, npc: Npc) {
+        game.location = location_from("~/a/b");
+        game.encounter = Some(Encounter {
+            location: game.location.clone(),
+            npc,
+        });
+    }
+
+    #[test]
+    fn merchant() {
+        let mut game = Game::new();
+        let item = Box::new(Potion::new(1));
+        meet(&mut game, Npc::Merchant { item, price: 300 });
+
+        // the merchant waits until the hero can pay
+        assert!(reply(&mut game, Reply::Accept).is_err());
+        assert!(game.encounter.is_some());
+
+        game.gold = 500;
+        reply(&mut game, Reply::Accept).unwrap();
+        assert_eq!(200, game.gold);
+        assert_eq!(1, *game.inventory().get(&Key::Potion).unwrap());
+        assert!(game.encounter.is_none());
+        assert!(reply(&mut game, Reply::Accept).is_err());
+    }
+
+    #[test]
+    fn traveler() {
+        let mut game = Game::new();
+        meet(&mut game, Npc::Traveler { reward: 100 });
+        reply(&mut game, Reply::Accept).unwrap();
+        assert!(game.encounter.is_none());
+        assert!(game
+            .quests
+            .list()
+            .contains(&(false, String::from("guide the lost traveler home"))));
+
+        game.visit(Location::home()).unwrap();
+        assert!(game
+            .quests
+            .list()
+            .contains(&(true, String::from("guide the lost traveler home"))));
+        assert_eq!(100, game.gold);
+    }
+
+    #[test]
+    fn rare_merchant() {
+        let mut game = Game::new();
+        game.gold = 100_000;
+        let stock = rare_stock(&game, 5);
+        assert_eq!(3, stock.len());
+        let Ware::Ring(ring) = stock[0].0.clone() else {
+            panic!("a ring is offered first");
+        };
+        meet(&mut game, Npc::RareMerchant { stock });
+        assert_eq!(4, game.encounter.as_ref().unwrap().replies().len());
+        assert!(reply(&mut game, Reply::Accept).is_err());
+
+        // the merchant stays until sold out
+        reply(&mut game, Reply::Buy(0)).unwrap();
+        assert!(!game.ring_pool.contains(&ring));
+        assert!(game.inventory().contains_key(&Key::Ring(ring)));
+        assert_eq!(100_000 - RARE_RING_PRICE, game.gold);
+
+        reply(&mut game, Reply::Buy(1)).unwrap();
+        assert!(game.player.sword.as_ref().unwrap().level() > 5);
+        assert!(reply(&mut game, Reply::Buy(1)).is_err());
+
+        reply(&mut game, Reply::Buy(0)).unwrap();
+        assert!(game.inventory().contains_key(&Key::TreasureMap));
+        assert!(game.encounter.is_none());
+    }
+
+    #[test]
+    fn stranger() {
+        let mut game = Game::new();
+        game.gold = 100;
+        meet(&mut game, Npc::Stranger { wager: 50 });
+
+        // leaving doesn't cost anything, and the stranger is gone
+        reply(&mut game, Reply::Leave).unwrap();
+        assert_eq!(100, game.gold);
+        assert!(game.encounter.is_none());
+
+        // ties go to the stranger
+        meet(&mut game, Npc::Stranger { wager: 50 });
+        reply(&mut game, Reply::Accept).unwrap();
+        assert_eq!(50, game.gold);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::character::class::Class;
+use crate::character::enemy;
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use strum_macros::EnumIter;
+
+/// Reputation lost with a faction for each of its members killed.
+pub const KILL_PENALTY: i32 = 1;
+
+/// Reputation gained with a faction for each of its quests finished.
+pub const QUEST_REPUTATION: i32 = 25;
+
+/// Reputation is kept between these bounds.
+const MIN_REPUTATION: i32 = -100;
+const MAX_REPUTATION: i32 = 100;
+
+/// The enemy classes that belong to the goblins.
+const GOBLINS: &[&str] = &["gremlin", "orc"];
+
+/// Groups of the game world that remember how the hero treated them.
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Faction {
+    Goblins,
+    Undead,
+    /// The shopkeepers, which have no members to fight.
+    Merchants,
+}
+
+impl Faction {
+    /// The faction the given enemy class belongs to, if any.
+    pub fn of(class: &Class) -> Option<Self> {
+        if GOBLINS.contains(&class.name.as_str()) {
+            Some(Faction::Goblins)
+        } else if enemy::is_undead(class) {
+            Some(Faction::Undead)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for Faction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Faction::Goblins => "goblins",
+            Faction::Undead => "undead",
+            Faction::Merchants => "merchants",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How a faction regards the hero, by reputation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Standing {
+    Hostile,
+    Neutral,
+    /// Members don't attack the hero at random, merchants give discounts.
+    Friendly,
+    /// Like friendly, with bigger discounts.
+    Honored,
+}
+
+impl Standing {
+    fn of(reputation: i32) -> Self {
+        match reputation {
+            r if r >= 50 => Standing::Honored,
+            r if r >= 25 => Standing::Friendly,
+            r if r <= -25 => Standing::Hostile,
+            _ => Standing::Neutral,
+        }
+    }
+}
+
+impl fmt::Display for Standing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Standing::Hostile => "hostile",
+            Standing::Neutral => "neutral",
+            Standing::Friendly => "friendly",
+            Standing::Honored => "honored",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The hero's reputation with each faction, kept across heroes.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct Reputation {
+    /// Reputation by faction, missing for the ones never dealt with.
+    factions: BTreeMap<Faction, i32>,
+}
+
+impl Reputation {
+    pub fn get(&self, faction: Faction) -> i32 {
+        self.factions.get(&faction).copied().unwrap_or(0)
+    }
+
+    pub fn standing(&self, faction: Faction) -> Standing {
+        Standing::of(self.get(faction))
+    }
+
+    /// Change the reputation with the faction by the given amount, within
+    /// bounds. Return whether its standing changed.
+    pub fn change(&mut self, faction: Faction, amount: i32) -> bool {
+        let before = self.standing(faction);
+        let reputation = self.factions.entry(faction).or_default();
+        *reputation = (*reputation + amount).clamp(MIN_REPUTATION, MAX_REPUTATION);
+        before != self.standing(faction)
+    }
+
+    /// Account for the kill of an enemy of the given class. Return its
+    /// faction if the standing with it changed.
+    pub fn kill(&mut self, class: &Class) -> Option<Faction> {
+        let faction = Faction::of(class)?;
+        self.change(faction, -KILL_PENALTY).then_some(faction)
+    }
+
+    /// Whether enemies of the given class leave the hero alone instead of
+    /// attacking, because their faction is friendly.
+    pub fn spares(&self, class: &Class) -> bool {
+        Faction::of(class).is_some_and(|faction| self.standing(faction) >= Standing::Friendly)
+    }
+
+    /// The given shop price, discounted by the standing with the merchants.
+    pub fn price(&self, cost: i32) -> i32 {
+        match self.standing(Faction::Merchants) {
+            Standing::Honored => cost * 80 / 100,
+            Standing::Friendly => cost * 90 / 100,
+            _ => cost,
+        }
+    }
+
+    /// The factions dealt with so far, with their reputation and standing.
+    pub fn list(&self) -> Vec<(Faction, i32, Standing)> {
+        self.factions
+            .iter()
+            .map(|(faction, reputation)| (*faction, *reputation, Standing::of(*reputation)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standings() {
+        let mut reputation = Reputation::default();
+        let zombie = Class::enemy_by_name("zombie").unwrap();
+        let rat = Class::enemy_by_name("rat").unwrap();
+        assert_eq!(Some(Faction::Undead), Faction::of(zombie));
+        assert_eq!(None, Faction::of(rat));
+        assert_eq!(Standing::Neutral, reputation.standing(Faction::Undead));
+        assert!(reputation.list().is_empty());
+
+        assert!(!reputation.change(Faction::Undead, 24));
+        assert!(!reputation.spares(zombie));
+        assert!(reputation.change(Faction::Undead, 1));
+        assert!(reputation.spares(zombie));
+        assert!(!reputation.spares(rat));
+
+        // killing members lowers it back
+        assert_eq!(Some(Faction::Undead), reputation.kill(zombie));
+        assert_eq!(None, reputation.kill(rat));
+        assert_eq!(24, reputation.get(Faction::Undead));
+
+        reputation.change(Faction::Goblins, -1000);
+        assert_eq!(MIN_REPUTATION, reputation.get(Faction::Goblins));
+        assert_eq!(Standing::Hostile, reputation.standing(Faction::Goblins));
+    }
+
+    #[test]
+    fn merchant_discounts() {
+        let mut reputation = Reputation::default();
+        assert_eq!(500, reputation.price(500));
+        reputation.change(Faction::Merchants, QUEST_REPUTATION);
+        assert_eq!(450, reputation.price(500));
+        reputation.change(Faction::Merchants, QUEST_REPUTATION);
+        assert_eq!(400, reputation.price(500));
+    }
+}
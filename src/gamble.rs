@@ -0,0 +1,133 @@
+use crate::activity;
+use crate::game::Game;
+use crate::log;
+use crate::randomizer::random;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// Gold that can be wagered every real-time day, per hero level. The cap
+/// keeps seeded games, where the rolls are known in advance, from being
+/// an endless source of gold.
+const DAILY_WAGER_PER_LEVEL: i32 = 100;
+
+/// Gold wagered at the dice table, kept across heroes so a new one doesn't
+/// lift the daily cap.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct Gambling {
+    /// Real-time day, counted since the unix epoch, of the last wager.
+    day: u64,
+
+    /// Gold wagered during that day.
+    wagered: i32,
+}
+
+impl Gambling {
+    /// The gold a hero of the given level can still wager on the given day.
+    pub fn allowance(&self, today: u64, level: i32) -> i32 {
+        let wagered = if self.day == today { self.wagered } else { 0 };
+        std::cmp::max(0, DAILY_WAGER_PER_LEVEL * level - wagered)
+    }
+
+    fn wager(&mut self, today: u64, amount: i32) {
+        if self.day != today {
+            self.day = today;
+            self.wagered = 0;
+        }
+        self.wagered += amount;
+    }
+}
+
+/// Wager the given amount of gold on a roll of two dice against the house.
+/// A higher roll wins as much gold as wagered, otherwise it's lost.
+pub fn dice(game: &mut Game, amount: i32) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Gambling is only allowed at home.");
+    }
+    if amount <= 0 {
+        bail!("Nothing to wager.");
+    }
+    if amount > game.gold {
+        bail!("Not enough gold.");
+    }
+    let today = activity::now() / activity::SECONDS_PER_DAY;
+    let allowance = game.gambling.allowance(today, game.player.level);
+    if allowance == 0 {
+        bail!("The house takes no more wagers today.");
+    }
+    if amount > allowance {
+        bail!("The house takes at most {}g more today.", allowance);
+    }
+
+    game.gambling.wager(today, amount);
+    let roll = random().dice_roll();
+    let house_roll = random().dice_roll();
+    let gold = payout(amount, roll, house_roll);
+    game.gold += gold;
+    if gold > 0 {
+        game.stats.earn(gold);
+    } else {
+        game.stats.spend(-gold);
+    }
+    log::gamble(roll, house_roll, gold);
+    Ok(())
+}
+
+/// The gold won, or lost if negative, by a wager given the hero and house
+/// rolls. The house wins ties, which gives it the edge.
+pub fn payout(amount: i32, roll: i32, house_roll: i32) -> i32 {
+    if roll > house_roll {
+        amount
+    } else {
+        -amount
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowance() {
+        let mut gambling = Gambling::default();
+        assert_eq!(DAILY_WAGER_PER_LEVEL, gambling.allowance(100, 1));
+        assert_eq!(5 * DAILY_WAGER_PER_LEVEL, gambling.allowance(100, 5));
+
+        gambling.wager(100, DAILY_WAGER_PER_LEVEL);
+        assert_eq!(0, gambling.allowance(100, 1));
+        assert_eq!(DAILY_WAGER_PER_LEVEL, gambling.allowance(100, 2));
+
+        // the cap is lifted the next day
+        assert_eq!(DAILY_WAGER_PER_LEVEL, gambling.allowance(101, 1));
+        gambling.wager(101, 10);
+        assert_eq!(DAILY_WAGER_PER_LEVEL - 10, gambling.allowance(101, 1));
+
+        assert_eq!(10, payout(10, 8, 7));
+        assert_eq!(-10, payout(10, 7, 7));
+        assert_eq!(-10, payout(10, 2, 12));
+    }
+
+    #[test]
+    fn test_dice() {
+        let mut game = Game::new();
+        game.gold = 500;
+        assert!(dice(&mut game, 0).is_err());
+        assert!(dice(&mut game, 600).is_err());
+        assert!(dice(&mut game, DAILY_WAGER_PER_LEVEL + 1).is_err());
+
+        // the test rolls are a tie, won by the house
+        dice(&mut game, 60).unwrap();
+        assert_eq!(440, game.gold);
+        assert_eq!(60, game.stats.gold_spent);
+        assert!(dice(&mut game, 50).is_err());
+
+        // the cap is kept across heroes
+        game.reset();
+        game.gold = 500;
+        assert!(dice(&mut game, 50).is_err());
+        dice(&mut game, 40).unwrap();
+
+        game.location = crate::location::Location::from("/").unwrap();
+        assert!(dice(&mut game, 10).is_err());
+    }
+}
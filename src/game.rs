@@ -1,21 +1,108 @@
 extern crate dirs;
 
+use crate::achievement;
+use crate::achievement::Achievements;
+use crate::activity;
+use crate::activity::Activity;
+use crate::bank::Bank;
+use crate::bestiary::Bestiary;
+use crate::challenge;
+use crate::challenge::Challenges;
 use crate::character;
+use crate::character::class::{Category, Class};
+use crate::character::companion::Companion;
 use crate::character::enemy;
-use crate::character::Character;
+use crate::character::skill::{self, Skill};
+use crate::character::spell::Spell;
+use crate::character::{Character, StatusEffect};
+use crate::clock::{self, Clock};
+use crate::config::{Config, Target};
+use crate::difficulty::Difficulty;
+use crate::duel;
+use crate::dungeon::{Direction, Dungeon, Room};
+use crate::encounter::Encounter;
+use crate::faction::Reputation;
+use crate::gamble::Gambling;
+use crate::hook;
+use crate::item::auto_use::AutoUse;
 use crate::item::chest::Chest;
+use crate::item::drops::Drops;
+use crate::item::equipment;
 use crate::item::key::Key;
 use crate::item::ring::Ring;
-use crate::item::Item;
-use crate::location::Location;
+use crate::item::{Item, Potion};
+use crate::location::{Distance, Location};
 use crate::log;
+use crate::plugin;
 use crate::quest;
 use crate::quest::QuestList;
+use crate::raid;
 use crate::randomizer::random;
-use crate::randomizer::Randomizer;
+use crate::rested::Rested;
+use crate::speedrun;
+use crate::speedrun::Speedrun;
+use crate::stats::Stats;
+use crate::title::{self, Titles};
 use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// The options of the player on each turn of an interactive battle.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Attack,
+    Cast(Spell),
+    Use(Key),
+    Flee,
+}
+
+/// A function that decides the player action on each turn of an interactive
+/// battle, given the current game and enemies state.
+pub type ChooseAction<'a> = dyn FnMut(&Game, &[Character]) -> Action + 'a;
+
+/// How the chests and tombstones found at a location are picked up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pickup {
+    /// Take everything that fits in the inventory, leaving the rest of the
+    /// items at the location.
+    All,
+    /// Leave the chests at the location, so their contents can be
+    /// selectively taken.
+    Choose,
+    /// Take everything when it fits in the inventory, or else leave all the
+    /// items at the location for the player to choose which to take.
+    AskIfFull,
+}
+
+/// How many heroes can be in the party, including the leader.
+const MAX_PARTY_SIZE: usize = 3;
+
+/// Percentage of the hero's gold lost after each battle won for every
+/// cursed ring or piece of equipment worn.
+const CURSE_GOLD_DRAIN: i32 = 5;
+
+/// Gold it costs to change class above level 1, for each hero level.
+const CLASS_CHANGE_COST: i32 = 100;
+
+/// Gold it costs to rest for each directory away from home.
+const REST_COST: i32 = 20;
+
+/// Gold it costs to fast travel to a waypoint for each directory away from it.
+const FAST_TRAVEL_COST: i32 = 50;
+
+/// Percentage of the gold and items of an insured hero that is left at home
+/// when they die, instead of in their tomb.
+const INSURANCE_COVERAGE: i32 = 50;
+
+/// Extra battle xp percentage for each enemy class killed for the first time.
+const FIRST_KILL_XP: i32 = 20;
+
+/// How many items of the same kind fit in a single inventory slot.
+const STACK_SIZE: usize = 10;
+
+/// Percentage of the hero's gold, carried and stored at the bank, kept when
+/// starting a New Game+.
+const NEW_GAME_PLUS_GOLD: i32 = 50;
 
 /// Carries all the game state that is saved between commands and exposes
 /// the high-level interface for gameplay: moving across directories and
@@ -24,9 +111,27 @@ use std::collections::{HashMap, HashSet};
 #[serde(default)]
 pub struct Game {
     pub player: Character,
+
+    /// Heroes that travel and fight with the player, sharing its gold and
+    /// inventory but leveling independently.
+    pub party: Vec<Character>,
+
     pub location: Location,
     pub gold: i32,
 
+    /// Earned by beating arena waves, exchanged for exclusive rings and
+    /// equipment.
+    pub arena_tokens: i32,
+
+    /// Gold stored at home, kept across heroes.
+    pub bank: Bank,
+
+    /// Gold wagered at home, kept across heroes to enforce the daily cap.
+    pub gambling: Gambling,
+
+    /// Whether the hero paid the insurance that covers their next death.
+    pub insured: bool,
+
     /// Items currently carried and unequipped
     pub inventory: HashMap<Key, Vec<Box<dyn Item>>>,
 
@@ -34,15 +139,119 @@ pub struct Game {
     /// can't be found again.
     inspected: HashSet<Location>,
 
+    /// Locations visited by the hero, which can be reached again with
+    /// an escape.
+    visited: HashSet<Location>,
+
     /// Chests left at the location where the player dies.
     pub tombstones: HashMap<String, Chest>,
 
+    /// Chests found but not completely picked up, by location.
+    pub chests: HashMap<String, Chest>,
+
+    /// Locations revealed by treasure maps, where a buried chest is dug up
+    /// the next time the hero gets there.
+    pub buried_chests: HashSet<Location>,
+
+    /// The npc met at the current location, waiting for the hero's reply.
+    pub encounter: Option<Encounter>,
+
     /// There's one instance of each type of ring in the game.
     /// This set starts with all rings and they are moved to the inventory as
     /// they are found in chests.
     pub ring_pool: HashSet<Ring>,
 
     pub quests: QuestList,
+
+    /// Long-term goals of the player, kept across heroes and games.
+    pub achievements: Achievements,
+
+    /// Enemy classes faced by the player, kept across heroes and games.
+    pub bestiary: Bestiary,
+
+    /// Standing of the hero with each faction, kept across heroes.
+    pub reputation: Reputation,
+
+    /// Rating and results of the hero in duels against exported heroes.
+    pub duels: duel::Record,
+
+    /// Progress towards the daily and weekly challenges, kept across heroes
+    /// and games so streaks aren't lost.
+    pub challenges: Challenges,
+
+    /// The last week a raid boss was beaten, kept across heroes.
+    pub raids: raid::Record,
+
+    /// Cosmetic titles earned and the one shown with the hero, kept across
+    /// heroes.
+    pub titles: Titles,
+
+    /// Statistics of the player's history, kept across heroes and games.
+    pub stats: Stats,
+
+    /// In-game days passed, counted as the times the hero came back home
+    /// to rest. The shop stock rotates every day.
+    pub day: i32,
+
+    /// Time of day and weather, advanced as the hero walks and fights.
+    pub clock: Clock,
+
+    /// Seed of the random number generator, if the game is deterministic.
+    pub seed: Option<u64>,
+
+    /// Commands run since the game was seeded, so each one gets a
+    /// different random sequence.
+    seeded_commands: u64,
+
+    /// Shell activity accounting for the opt-in passive training mode.
+    pub activity: Activity,
+
+    /// Real-world time the hero spent resting at home between sessions,
+    /// rewarded with extra xp in the next battles.
+    pub rested: Rested,
+
+    /// The tamed creature currently fighting alongside the hero.
+    pub companion: Option<Companion>,
+
+    /// Tamed creatures resting at home, which can be swapped with the
+    /// active companion.
+    pub companions: Vec<Companion>,
+
+    /// Distances from home at which the guarding boss was already beaten.
+    pub bosses_beaten: HashSet<i32>,
+
+    /// The virtual dungeon the hero is exploring, if any.
+    pub dungeon: Option<Dungeon>,
+
+    /// Whether the final boss was beaten, which unlocks New Game+.
+    pub final_boss_beaten: bool,
+
+    /// How many times New Game+ was started. Each one raises the tier of
+    /// the enemies found away from home.
+    pub new_game_plus: i32,
+
+    /// In hardcore mode, death deletes the game instead of leaving a
+    /// tombstone, so nothing is preserved for the next hero.
+    pub hardcore: bool,
+
+    /// In ironman mode, the game is saved before the outcome of each command
+    /// is shown and can't be restored from older copies, so bad luck can't
+    /// be undone. Kept across heroes.
+    pub ironman: bool,
+
+    /// Chosen when starting a new game, kept across heroes.
+    pub difficulty: Difficulty,
+
+    /// When to use items automatically during battles, a player
+    /// preference kept across heroes.
+    pub auto_use: AutoUse,
+
+    /// Directories bookmarked by the player to fast travel to, by name.
+    /// Kept across heroes.
+    pub waypoints: BTreeMap<String, Location>,
+
+    /// The timer of games played as speedruns, kept across heroes.
+    pub speedrun: Option<Speedrun>,
 }
 
 impl Game {
@@ -50,34 +259,100 @@ impl Game {
         let quests = QuestList::new();
 
         // There's one instance of each ring exiting per game.
-        // The diamond ring is the only one that's found in the shop
-        // instead of chests
-        let mut ring_pool = Ring::set();
-        ring_pool.remove(&Ring::Diamond);
+        // The diamond ring is found in the shop and the arena rings are
+        // exchanged for tokens, instead of found in chests
+        let ring_pool = Ring::found_set();
 
         Self {
             location: Location::home(),
             player: Character::player(),
+            party: Vec::new(),
             gold: 0,
+            arena_tokens: 0,
+            bank: Bank::default(),
+            gambling: Gambling::default(),
+            insured: false,
             inventory: HashMap::new(),
             tombstones: HashMap::new(),
+            chests: HashMap::new(),
+            buried_chests: HashSet::new(),
+            encounter: None,
             inspected: HashSet::new(),
+            visited: HashSet::new(),
             quests,
+            achievements: Achievements::default(),
+            bestiary: Bestiary::default(),
+            reputation: Reputation::default(),
+            duels: duel::Record::default(),
+            challenges: Challenges::default(),
+            raids: raid::Record::default(),
+            titles: Titles::default(),
+            stats: Stats::default(),
             ring_pool,
+            day: 0,
+            clock: Clock::default(),
+            seed: None,
+            seeded_commands: 0,
+            activity: Activity::default(),
+            rested: Rested::default(),
+            companion: None,
+            companions: Vec::new(),
+            bosses_beaten: HashSet::new(),
+            dungeon: None,
+            final_boss_beaten: false,
+            new_game_plus: 0,
+            hardcore: false,
+            ironman: false,
+            difficulty: Difficulty::default(),
+            auto_use: AutoUse::default(),
+            waypoints: BTreeMap::new(),
+            speedrun: None,
         }
     }
 
     /// Remove the game data and reset this reference.
-    /// Progress is preserved across games.
+    /// Progress is preserved across games, unless in hardcore mode.
     pub fn reset(&mut self) {
+        if self.hardcore {
+            self.reset_hardcore();
+            return;
+        }
+
         let mut new_game = Self::new();
         // preserve tombstones and quests across hero's lifes
         std::mem::swap(&mut new_game.tombstones, &mut self.tombstones);
+        std::mem::swap(&mut new_game.chests, &mut self.chests);
+        std::mem::swap(&mut new_game.bank, &mut self.bank);
+        std::mem::swap(&mut new_game.gambling, &mut self.gambling);
+        std::mem::swap(&mut new_game.buried_chests, &mut self.buried_chests);
         std::mem::swap(&mut new_game.quests, &mut self.quests);
+        std::mem::swap(&mut new_game.reputation, &mut self.reputation);
+        std::mem::swap(&mut new_game.achievements, &mut self.achievements);
+        std::mem::swap(&mut new_game.bestiary, &mut self.bestiary);
+        std::mem::swap(&mut new_game.challenges, &mut self.challenges);
+        std::mem::swap(&mut new_game.raids, &mut self.raids);
+        std::mem::swap(&mut new_game.titles, &mut self.titles);
+        std::mem::swap(&mut new_game.speedrun, &mut self.speedrun);
+        std::mem::swap(&mut new_game.stats, &mut self.stats);
+        std::mem::swap(&mut new_game.rested, &mut self.rested);
         std::mem::swap(&mut new_game.ring_pool, &mut self.ring_pool);
-
-        // remember last selected class
-        new_game.player = character::Character::new(self.player.class.clone(), 1);
+        // companions wait for the next hero at home
+        std::mem::swap(&mut new_game.companion, &mut self.companion);
+        std::mem::swap(&mut new_game.companions, &mut self.companions);
+
+        // remember last selected class and the prestige rank
+        new_game.player = character::Character::new(self.player.class.clone(), 1)
+            .with_prestige(self.player.prestige);
+        new_game.player.manual_growth = self.player.manual_growth;
+
+        // the passive training is a player preference, but its rewards belong to the hero
+        new_game.activity.enabled = self.activity.enabled;
+        new_game.seed = self.seed;
+        new_game.difficulty = self.difficulty;
+        new_game.ironman = self.ironman;
+        new_game.new_game_plus = self.new_game_plus;
+        std::mem::swap(&mut new_game.auto_use, &mut self.auto_use);
+        std::mem::swap(&mut new_game.waypoints, &mut self.waypoints);
 
         // replace the current, finished game with the new one
         *self = new_game;
@@ -85,6 +360,109 @@ impl Game {
         quest::game_reset(self);
     }
 
+    /// Replace this game with a new one in hardcore mode, with no progress
+    /// carried over other than the hero class, its prestige rank, the New
+    /// Game+ tier and the player records: achievements, bestiary and stats.
+    pub fn reset_hardcore(&mut self) {
+        let mut new_game = Self::new();
+        new_game.player = character::Character::new(self.player.class.clone(), 1)
+            .with_prestige(self.player.prestige);
+        new_game.player.manual_growth = self.player.manual_growth;
+        new_game.hardcore = true;
+        new_game.ironman = self.ironman;
+        new_game.difficulty = self.difficulty;
+        new_game.new_game_plus = self.new_game_plus;
+        std::mem::swap(&mut new_game.auto_use, &mut self.auto_use);
+        std::mem::swap(&mut new_game.waypoints, &mut self.waypoints);
+        std::mem::swap(&mut new_game.gambling, &mut self.gambling);
+        std::mem::swap(&mut new_game.achievements, &mut self.achievements);
+        std::mem::swap(&mut new_game.bestiary, &mut self.bestiary);
+        std::mem::swap(&mut new_game.challenges, &mut self.challenges);
+        std::mem::swap(&mut new_game.raids, &mut self.raids);
+        std::mem::swap(&mut new_game.titles, &mut self.titles);
+        std::mem::swap(&mut new_game.speedrun, &mut self.speedrun);
+        std::mem::swap(&mut new_game.stats, &mut self.stats);
+        *self = new_game;
+    }
+
+    /// Whether a New Game+ can be started, after beating the final boss or
+    /// reaching the level set in the config.
+    pub fn new_game_plus_unlocked(&self) -> bool {
+        let level = Config::get().new_game_plus_level;
+        self.final_boss_beaten || level.is_some_and(|level| self.player.level >= level)
+    }
+
+    /// Restart the world with a new level 1 hero that keeps the rings and
+    /// part of the gold of the current one. Enemies are stronger than in the
+    /// previous game, and a second pool of upgraded rings can be found.
+    pub fn new_game_plus(&mut self) -> Result<()> {
+        if !self.new_game_plus_unlocked() {
+            bail!("New Game+ is unlocked by beating the final boss.");
+        }
+
+        let mut rings = Vec::new();
+        rings.extend(self.player.left_ring.take());
+        rings.extend(self.player.right_ring.take());
+        for key in self.inventory.keys() {
+            if let Key::Ring(ring) = key {
+                rings.push(ring.clone());
+            }
+        }
+
+        let mut new_game = Self::new();
+        new_game.player = character::Character::new(self.player.class.clone(), 1)
+            .with_prestige(self.player.prestige);
+        new_game.player.manual_growth = self.player.manual_growth;
+        new_game.gold = self.gold * NEW_GAME_PLUS_GOLD / 100;
+        new_game.bank = self.bank.clone();
+        new_game.bank.balance = self.bank.balance * NEW_GAME_PLUS_GOLD / 100;
+        std::mem::swap(&mut new_game.gambling, &mut self.gambling);
+        new_game.new_game_plus = self.new_game_plus + 1;
+        new_game.ring_pool.extend(Ring::upgraded_set());
+        for ring in &rings {
+            new_game.ring_pool.remove(ring);
+        }
+        new_game.activity.enabled = self.activity.enabled;
+        new_game.seed = self.seed;
+        new_game.hardcore = self.hardcore;
+        new_game.ironman = self.ironman;
+        new_game.difficulty = self.difficulty;
+        std::mem::swap(&mut new_game.auto_use, &mut self.auto_use);
+        std::mem::swap(&mut new_game.waypoints, &mut self.waypoints);
+        std::mem::swap(&mut new_game.achievements, &mut self.achievements);
+        std::mem::swap(&mut new_game.bestiary, &mut self.bestiary);
+        std::mem::swap(&mut new_game.challenges, &mut self.challenges);
+        std::mem::swap(&mut new_game.raids, &mut self.raids);
+        std::mem::swap(&mut new_game.titles, &mut self.titles);
+        std::mem::swap(&mut new_game.speedrun, &mut self.speedrun);
+        std::mem::swap(&mut new_game.stats, &mut self.stats);
+        *self = new_game;
+
+        for ring in rings {
+            self.add_item(Box::new(ring));
+        }
+        log::new_game_plus(self);
+        Ok(())
+    }
+
+    /// Make the game deterministic by seeding its random number generator.
+    /// Changing the seed restarts the random sequence.
+    pub fn set_seed(&mut self, seed: u64) {
+        if self.seed != Some(seed) {
+            self.seed = Some(seed);
+            self.seeded_commands = 0;
+        }
+    }
+
+    /// If the game is seeded, return the (seed, command number) pair to
+    /// initialize the random number generator of the current command.
+    pub fn command_seed(&mut self) -> Option<(u64, u64)> {
+        let seed = self.seed?;
+        let command = self.seeded_commands;
+        self.seeded_commands += 1;
+        Some((seed, command))
+    }
+
     /// Move the hero's location towards the given destination, one directory
     /// at a time, with some chance of enemies appearing on each one.
     pub fn go_to(
@@ -92,15 +470,43 @@ impl Game {
         dest: &Location,
         run: bool,
         bribe: bool,
+        cast: Option<Spell>,
     ) -> Result<(), character::Dead> {
         while self.location != *dest {
             self.visit(self.location.go_to(dest))?;
 
-            if !self.location.is_home() {
-                if let Some(mut enemy) = enemy::spawn(&self.location, &self.player) {
-                    if self.battle(&mut enemy, run, bribe)? {
+            if !self.location.is_home() && !self.location.is_ignored() {
+                let beaten = &self.bosses_beaten;
+                let boss = enemy::spawn_boss(&self.location, &self.player, beaten, self.difficulty);
+                if let Some(mut boss) = boss {
+                    // beaten bosses don't appear again
+                    if self.battle(&mut boss, run, bribe, cast)? {
+                        let distance = self.location.distance_from_home().len();
+                        self.bosses_beaten.insert(distance);
+                        return Ok(());
+                    }
+                    continue;
+                }
+
+                // the raid boss of the week lairs at a single directory
+                if let Some(mut boss) = raid::spawn(self, activity::now()) {
+                    if self.battle(&mut boss, run, bribe, cast)? {
+                        raid::beaten(self, activity::now());
                         return Ok(());
                     }
+                    continue;
+                }
+
+                let mut enemies = enemy::spawn(
+                    &self.location,
+                    &self.player,
+                    self.new_game_plus,
+                    self.difficulty,
+                    &self.clock,
+                    &self.reputation,
+                );
+                if !enemies.is_empty() && self.battle_group(&mut enemies, run, bribe, cast)? {
+                    return Ok(());
                 }
             }
         }
@@ -109,20 +515,30 @@ impl Game {
 
     /// Set the hero's location to the one given, and apply related side effects.
     pub fn visit(&mut self, location: Location) -> Result<(), character::Dead> {
+        let arrived_home = location.is_home() && !self.location.is_home();
+        if arrived_home {
+            self.day += 1;
+        }
+        // npcs don't follow the hero around
+        if self.location != location {
+            self.encounter = None;
+        }
         self.location = location;
+        self.stats.step(&self.location);
+        self.clock.advance(clock::STEP_HOURS);
+        self.visited.insert(self.location.clone());
         if self.location.is_home() {
-            let (recovered_hp, recovered_mp, healed) = self.player.restore();
-            log::heal(
-                &self.player,
-                &self.location,
-                recovered_hp,
-                recovered_mp,
-                healed,
-            );
+            self.restore();
+        }
+        if arrived_home {
+            quest::home_reached(self);
         }
 
         // In location is home, already healed of negative status
-        let result = self.player.apply_status_effects();
+        let result = self
+            .player
+            .apply_status_effects()
+            .or_else(|dead| self.revive(dead));
 
         if let Err(character::Dead) = result {
             // drops tombstone
@@ -131,33 +547,583 @@ impl Game {
         result
     }
 
+    /// Locations visited by the current hero.
+    pub fn visited(&self) -> &HashSet<Location> {
+        &self.visited
+    }
+
+    /// Gold it costs to rest at the current location: free at home, and
+    /// pricier the farther from it.
+    pub fn rest_cost(&self) -> i32 {
+        REST_COST * self.location.distance_from_home().len()
+    }
+
+    /// Rest at the current location, paying its cost to restore the hp and mp
+    /// of the party, with some chance of being ambushed during the night away
+    /// from home. Return whether the hero could afford to rest.
+    pub fn rest(&mut self) -> Result<bool, character::Dead> {
+        let cost = self.rest_cost();
+        if self.gold < cost {
+            return Ok(false);
+        }
+        if cost > 0 {
+            self.gold -= cost;
+            self.stats.spend(cost);
+            log::rest(cost);
+        }
+        self.restore();
+
+        if !self.location.is_home()
+            && !self.location.is_ignored()
+            && !self.player.enemies_evaded()
+            && random().night_ambush()
+        {
+            log::ambush();
+            let distance = self.location.distance_from_home();
+            let mut enemies = enemy::spawn_group(
+                &self.location,
+                &self.player,
+                &distance,
+                self.new_game_plus,
+                self.difficulty,
+                &self.clock,
+                &self.reputation,
+            );
+            if !enemies.is_empty() {
+                self.battle_group(&mut enemies, false, false, None)?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Gold it costs to fast travel from the current location to the given
+    /// one, the farther the pricier.
+    pub fn fast_travel_cost(&self, dest: &Location) -> i32 {
+        FAST_TRAVEL_COST * self.location.distance_from(dest).len()
+    }
+
+    /// Pay to move the hero straight to the given location, skipping the
+    /// battles along the way. Return whether the hero could afford the trip.
+    pub fn fast_travel(&mut self, dest: Location) -> Result<bool, character::Dead> {
+        let cost = self.fast_travel_cost(&dest);
+        if self.gold < cost {
+            return Ok(false);
+        }
+        self.gold -= cost;
+        self.stats.spend(cost);
+        log::fast_travel(cost, &dest);
+        self.visit(dest)?;
+        Ok(true)
+    }
+
+    /// Restore the hp and mp of the hero, party and companion, and heal
+    /// their status ailments.
+    fn restore(&mut self) {
+        let (recovered_hp, recovered_mp, healed) = self.player.restore();
+        for member in &mut self.party {
+            member.restore();
+        }
+        if let Some(companion) = &mut self.companion {
+            companion.character.restore();
+        }
+        log::heal(
+            &self.player,
+            &self.location,
+            recovered_hp,
+            recovered_mp,
+            healed,
+        );
+    }
+
     /// Look for chests and tombstones at the current location.
     /// Remembers previously visited locations for consistency.
-    pub fn inspect(&mut self) {
+    /// Returns whether items were left at the location for the player to
+    /// choose from, or Err(Dead) if the hero dies fighting a mimic or a
+    /// chest guard.
+    pub fn inspect(&mut self, pickup: Pickup) -> Result<bool, character::Dead> {
+        let mut asked = false;
         if let Some(mut chest) = self.tombstones.remove(&self.location.to_string()) {
+            asked |= self.set_aside_items(&mut chest, pickup);
             let (items, gold) = chest.pick_up(self);
             log::tombstone(&items, gold);
             quest::tombstone(self);
+            achievement::tomb_raided(self);
+            self.leave_behind(chest);
+        }
+
+        // chests looked for by quests are placed once the hero gets there
+        let location = self.location.to_string();
+        if self.quests.chest_at(&self.location) && !self.chests.contains_key(&location) {
+            let chest = Chest::cursed(self);
+            self.chests.insert(location, chest);
+        }
+
+        if self.buried_chests.remove(&self.location) {
+            let chest = Chest::buried(self);
+            self.keep_chest(chest);
+        }
+
+        if let Some(chest) = self.chests.remove(&self.location.to_string()) {
+            asked |= self.open_chest(chest, pickup)?;
         }
 
         if !self.inspected.contains(&self.location) {
             self.inspected.insert(self.location.clone());
-            if let Some(mut chest) = Chest::generate(self) {
-                let (items, gold) = chest.pick_up(self);
-                log::chest(&items, gold);
-                quest::chest(self);
+            if let Some(chest) = Chest::generate(self) {
+                asked |= self.open_chest(chest, pickup)?;
+            }
+            if let Some(encounter) = Encounter::generate(self) {
+                self.encounter = Some(encounter);
             }
         }
+        if let Some(encounter) = &self.encounter {
+            log::encounter(encounter);
+        }
+        Ok(asked)
+    }
+
+    /// Pick up the contents of a chest found at the current location.
+    /// If the chest is a mimic or guarded, the enemy needs to be beaten first,
+    /// or the chest stays at the location.
+    /// Returns whether its items were left for the player to choose from.
+    fn open_chest(&mut self, mut chest: Chest, pickup: Pickup) -> Result<bool, character::Dead> {
+        // appraised mimics are left in place, so the hero can decide
+        // whether to come back and fight for the chest
+        if self.player.has_skill(Skill::Appraise) && chest.appraise() {
+            log::appraisal(&chest.contents(), chest.is_trapped());
+            self.keep_chest(chest);
+            return Ok(false);
+        }
+
+        // neither mimics nor guards can be avoided, and the chest stays at
+        // the location until they are beaten
+        let guard = chest.mimic(&self.location);
+        if let Some(mut guard) = guard.or_else(|| chest.guard(&self.location)) {
+            match self.battle(&mut guard, false, false, None) {
+                Ok(true) => chest.unguard(),
+                result => {
+                    // the chest waits for the hero, or the next one, to come back
+                    if result.is_ok() {
+                        log::chest_guarded(&guard);
+                    }
+                    self.keep_chest(chest);
+                    return result.map(|_| false);
+                }
+            }
+        }
+
+        let mut asked = pickup == Pickup::Choose;
+        if asked {
+            self.keep_chest(chest);
+        } else {
+            chest.spring_trap(self);
+            asked = self.set_aside_items(&mut chest, pickup);
+            let jackpot = chest.is_jackpot();
+            let (items, gold) = chest.pick_up(self);
+            log::chest(&items, gold);
+            if jackpot {
+                log::jackpot(gold);
+            }
+            self.stats.earn(gold);
+            quest::chest(self);
+            achievement::chest_opened(self);
+            challenge::chest_opened(self);
+            self.leave_behind(chest);
+        }
+
+        // rarely, a creature hiding in the chest befriends the hero
+        if random().companion_chest() {
+            let level = std::cmp::max(1, self.player.level / 2);
+            let creature = Character::new(Class::random(Category::Common).clone(), level);
+            if let Some(companion) = Companion::tame(&creature) {
+                self.add_companion(companion);
+            }
+        }
+        Ok(asked)
+    }
+
+    /// When asking the player and the items of the chest don't all fit in
+    /// the inventory, leave them at the location for the player to choose
+    /// which to take, instead of the ones that happen to not fit.
+    /// Returns whether they were left.
+    fn set_aside_items(&mut self, chest: &mut Chest, pickup: Pickup) -> bool {
+        if pickup != Pickup::AskIfFull || chest.fits(self) {
+            return false;
+        }
+        self.keep_chest(chest.take_items());
+        true
+    }
+
+    /// Generate a new dungeon and start exploring it.
+    pub fn enter_dungeon(&mut self) -> Result<()> {
+        if self.dungeon.is_some() {
+            bail!("Already exploring a dungeon.");
+        }
+        let dungeon = Dungeon::generate();
+        log::dungeon(&dungeon);
+        self.dungeon = Some(dungeon);
+        Ok(())
+    }
+
+    /// Leave the current dungeon, which is lost with its remaining contents.
+    pub fn exit_dungeon(&mut self) -> Result<()> {
+        if self.dungeon.take().is_none() {
+            bail!("Not exploring a dungeon.");
+        }
+        log::dungeon_exit(false);
+        Ok(())
+    }
+
+    /// Move the hero one room in the given direction inside the current
+    /// dungeon and face whatever is found there. Enemies and loot are the
+    /// ones found at the distance from home equivalent to the floor depth.
+    /// Beating the floor boss leads to the next floor, and beating the
+    /// last one completes the dungeon.
+    pub fn explore(&mut self, direction: Direction) -> Result<(), character::Dead> {
+        let (room, distance, floor) = match &mut self.dungeon {
+            Some(dungeon) => (dungeon.step(direction), dungeon.distance(), dungeon.floor()),
+            None => return Ok(()),
+        };
+
+        match room {
+            Room::Empty => {}
+            Room::Enemy => {
+                let mut enemies = enemy::spawn_group(
+                    &self.location,
+                    &self.player,
+                    &distance,
+                    self.new_game_plus,
+                    self.difficulty,
+                    &self.clock,
+                    &self.reputation,
+                );
+                if !enemies.is_empty() {
+                    self.battle_group_at(&mut enemies, false, false, None, &distance)?;
+                }
+            }
+            Room::Chest => {
+                if let Some(chest) = Chest::generate_at(self, &distance) {
+                    self.open_chest(chest, Pickup::All)?;
+                }
+            }
+            Room::Trap => {
+                let damage = std::cmp::max(1, self.player.max_hp() / 5);
+                let result = self.player.update_hp(-damage);
+                self.player.status_effects.inflict(StatusEffect::Poison);
+                log::trap(&self.player, damage);
+                if let Err(dead) = result {
+                    if self.revive(dead).is_err() {
+                        self.battle_lost();
+                        return Err(character::Dead);
+                    }
+                }
+            }
+            Room::Boss => {
+                if let Some(class) = Class::boss(distance.len()) {
+                    let mut boss = Character::new(class.clone(), distance.len());
+                    log::enemy_appears(&boss, &self.location);
+                    let boss = std::slice::from_mut(&mut boss);
+                    self.battle_group_at(boss, false, false, None, &distance)?;
+                }
+
+                let finished = !self.dungeon.as_mut().is_some_and(|d| d.next_floor());
+                if finished {
+                    self.dungeon = None;
+                    log::dungeon_exit(true);
+                    return Ok(());
+                }
+                log::dungeon_floor(floor + 1);
+            }
+        }
+
+        if let Some(dungeon) = &self.dungeon {
+            log::dungeon(dungeon);
+        }
+        Ok(())
+    }
+
+    /// Create a new level 1 hero of the given class and add it to the party.
+    pub fn add_party_member(&mut self, class_name: &str) -> Result<()> {
+        if !self.location.is_home() {
+            bail!("Party members can only be recruited at home.");
+        }
+        if self.party.len() + 1 >= MAX_PARTY_SIZE {
+            bail!("The party is full.");
+        }
+        if let Some(class) = Class::player_by_name(class_name) {
+            let member = Character::new(class.clone(), 1);
+            log::party_joined(&member);
+            self.party.push(member);
+            Ok(())
+        } else {
+            bail!("Unknown player class {}.", class_name);
+        }
+    }
+
+    /// Remove the first party member of the given class name.
+    pub fn remove_party_member(&mut self, name: &str) -> Result<()> {
+        if !self.location.is_home() {
+            bail!("Party members can only leave at home.");
+        }
+        if let Some(index) = self.party.iter().position(|m| m.name() == name) {
+            let member = self.party.remove(index);
+            log::party_left(&member);
+            Ok(())
+        } else {
+            bail!("No {} in the party.", name);
+        }
+    }
+
+    /// Make the given companion the active one if there's none, otherwise
+    /// send it to rest at home.
+    pub fn add_companion(&mut self, companion: Companion) {
+        log::companion_joined(&companion);
+        if self.companion.is_none() {
+            self.companion = Some(companion);
+        } else {
+            self.companions.push(companion);
+        }
+    }
+
+    /// Replace the active companion with the one by the given name resting
+    /// at home.
+    pub fn swap_companion(&mut self, name: &str) -> Result<()> {
+        if !self.location.is_home() {
+            bail!("Companions can only be swapped at home.");
+        }
+        if let Some(index) = self.companions.iter().position(|c| c.name() == name) {
+            let mut companion = self.companions.remove(index);
+            companion.character.restore();
+            if let Some(previous) = self.companion.replace(companion) {
+                self.companions.push(previous);
+            }
+            Ok(())
+        } else {
+            bail!("No {} companion resting at home.", name);
+        }
+    }
+
+    /// Release the active companion, putting the item it held back in the
+    /// inventory.
+    pub fn dismiss_companion(&mut self) -> Result<()> {
+        if !self.location.is_home() {
+            bail!("Companions can only be dismissed at home.");
+        }
+        if let Some(mut companion) = self.companion.take() {
+            if let Some(item) = companion.item.take() {
+                self.add_item(item);
+            }
+            log::companion_dismissed(&companion);
+            Ok(())
+        } else {
+            bail!("No active companion.");
+        }
+    }
+
+    /// List the contents of the chest left at the current location, if any.
+    pub fn chest_contents(&self) -> Vec<String> {
+        self.chests
+            .get(&self.location.to_string())
+            .map_or(Vec::new(), |chest| chest.contents())
+    }
+
+    /// Whether the chest left at the current location, if any, is trapped.
+    pub fn chest_trapped(&self) -> bool {
+        self.chests
+            .get(&self.location.to_string())
+            .is_some_and(|chest| chest.is_trapped())
+    }
+
+    /// Attempt to disarm the trap of the chest left at the current location.
+    /// If the attempt fails, the trap is sprung on the hero.
+    pub fn disarm_chest(&mut self) -> Result<()> {
+        let location = self.location.to_string();
+        let mut chest = match self.chests.remove(&location) {
+            Some(chest) => chest,
+            None => bail!("No chest here."),
+        };
+        let trapped = chest.is_trapped();
+        chest.disarm(self);
+        self.chests.insert(location, chest);
+
+        if !trapped {
+            bail!("The chest is not trapped.");
+        }
+        Ok(())
+    }
+
+    /// Pick up the elements at the given indexes of the chest left at the
+    /// current location, leaving the rest for later. If the chest is
+    /// trapped, the trap is sprung first.
+    pub fn take_from_chest(&mut self, indexes: &[usize]) {
+        let location = self.location.to_string();
+        if let Some(mut chest) = self.chests.remove(&location) {
+            chest.spring_trap(self);
+            let mut taken = chest.take(indexes);
+            let jackpot = taken.is_jackpot();
+            let (items, gold) = taken.pick_up(self);
+            log::chest(&items, gold);
+            if jackpot {
+                log::jackpot(gold);
+            }
+            self.stats.earn(gold);
+            quest::chest(self);
+            achievement::chest_opened(self);
+            challenge::chest_opened(self);
+
+            if !chest.is_empty() {
+                self.chests.insert(location, chest);
+            }
+            self.leave_behind(taken);
+        }
+    }
+
+    /// Leave the items of a chest that didn't fit in the inventory in a
+    /// chest at the current location, to be picked up later.
+    pub fn leave_behind(&mut self, chest: Chest) {
+        if let Some(left) = chest.only_items() {
+            log::inventory_full(&left.contents());
+            self.keep_chest(left);
+        }
+    }
+
+    /// Leave the chest at the current location, along with any other
+    /// already there.
+    fn keep_chest(&mut self, mut chest: Chest) {
+        let location = self.location.to_string();
+        if let Some(previous) = self.chests.remove(&location) {
+            chest.extend(previous);
+        }
+        self.chests.insert(location, chest);
+    }
+
+    /// Inventory slots taken by the carried items. Each slot holds a stack
+    /// of items of the same kind, and rings take no space.
+    pub fn slots_used(&self) -> i32 {
+        let slots: usize = self
+            .inventory
+            .iter()
+            .filter(|(key, _)| !matches!(key, Key::Ring(_)))
+            .map(|(_, items)| items.len().div_ceil(STACK_SIZE))
+            .sum();
+        slots as i32
+    }
+
+    /// Whether all the items of the given kinds fit in the inventory at once.
+    pub fn has_room_for_all(&self, keys: &[Key]) -> bool {
+        let mut added: HashMap<&Key, usize> = HashMap::new();
+        for key in keys.iter().filter(|key| !matches!(key, Key::Ring(_))) {
+            *added.entry(key).or_insert(0) += 1;
+        }
+        let slots: usize = added
+            .iter()
+            .map(|(key, added)| {
+                let count = self.inventory.get(key).map_or(0, Vec::len);
+                (count + added).div_ceil(STACK_SIZE) - count.div_ceil(STACK_SIZE)
+            })
+            .sum();
+        self.slots_used() + slots as i32 <= self.player.inventory_slots()
+    }
+
+    /// Whether one more item of the given kind fits in the inventory,
+    /// either in a stack that isn't full or in a free slot.
+    pub fn has_room_for(&self, key: &Key) -> bool {
+        if let Key::Ring(_) = key {
+            return true;
+        }
+        let count = self.inventory.get(key).map_or(0, Vec::len);
+        !count.is_multiple_of(STACK_SIZE) || self.slots_used() < self.player.inventory_slots()
+    }
+
+    /// Account for an invocation from the shell integration, which may accrue
+    /// xp if the passive training mode is enabled.
+    pub fn track_activity(&mut self) {
+        // a small fraction of what's needed for the next level
+        let xp = std::cmp::max(1, self.player.xp_for_next() / 100);
+        self.activity.tick(activity::now(), xp);
+    }
+
+    /// Account for a command run at the given time, adding to the playtime.
+    /// It may earn rested battles if the hero was left at home since the
+    /// previous one.
+    pub fn track_session(&mut self, now: u64) {
+        self.stats.play(now);
+        let earned = self.rested.track(now, self.location.is_home());
+        if earned > 0 {
+            log::rested(earned, self.rested.battles);
+        }
+    }
+
+    /// Collect the xp and items accrued by the hero while training.
+    pub fn claim_training(&mut self) {
+        let (xp, potions) = self.activity.claim();
+        let levels_up = self.player.add_experience(xp);
+
+        let mut items = HashMap::new();
+        if potions > 0 {
+            for _ in 0..potions {
+                self.add_item(Box::new(Potion::new(self.player.rounded_level())));
+            }
+            items.insert(Key::Potion, potions);
+        }
+
+        log::training(&self.player, xp, levels_up, &items);
+        if levels_up > 0 {
+            quest::level_up(self, levels_up);
+            challenge::level_up(self, levels_up);
+            speedrun::level_up(self);
+            hook::level_up(self);
+            plugin::level_up(self);
+        }
     }
 
     pub fn add_item(&mut self, item: Box<dyn Item>) {
         let key = item.key();
         let entry = self.inventory.entry(item.key()).or_insert_with(Vec::new);
         entry.push(item);
+        if let Key::Ring(ring) = &key {
+            achievement::ring_found(self, ring);
+        }
         quest::item_added(self, key);
     }
 
     pub fn use_item(&mut self, name: Key) -> Result<()> {
+        if let Key::Material(_) = name {
+            bail!("{} can only be used for crafting.", name);
+        }
+        if name == Key::PhoenixDown {
+            bail!("{} is used when the hero dies.", name);
+        }
+        if let Key::Throwable(_) = name {
+            bail!("{} can only be thrown in battle.", name);
+        }
+        if name == Key::ForgetStone && self.player.stone_raises.is_empty() {
+            bail!("No stone was used yet, there is nothing to forget.");
+        }
+        if name == Key::Bag && self.player.bags >= character::MAX_BAGS {
+            bail!("The hero can't carry more bags.");
+        }
+        if let Key::Scroll(spell) = name {
+            if !self.player.class.is_magic() {
+                bail!("Only magic classes can learn spells.");
+            }
+            if self.player.spells().contains(&spell) {
+                bail!("{} spell already known.", spell);
+            }
+        }
+
+        // cursed rings can't be unequipped, nor replaced by a new one
+        if let Key::Ring(ring) = &name {
+            let equipped = self.player.left_ring.as_ref() == Some(ring)
+                || self.player.right_ring.as_ref() == Some(ring);
+            if equipped && self.player.is_cursed(ring) {
+                bail!("{} is cursed, use a remedy to remove it.", name);
+            }
+            if self.inventory.contains_key(&name) && !self.player.can_equip_ring() {
+                bail!("The equipped rings are cursed, use a remedy to remove them.");
+            }
+        }
+
         // get all items of that type and use one
         // if there are no remaining, drop the type from the inventory
         if let Some(mut items) = self.inventory.remove(&name) {
@@ -184,6 +1150,81 @@ impl Game {
         }
     }
 
+    /// Use an escape to teleport to the given location instead of home.
+    /// Only the locations already visited by the hero can be reached.
+    pub fn escape_to(&mut self, dest: Location) -> Result<()> {
+        if !dest.is_home() && !self.visited.contains(&dest) {
+            bail!("{} wasn't visited by the hero.", dest);
+        }
+        if self.remove_item(&Key::Escape).is_none() {
+            bail!("item not found.");
+        }
+        quest::item_used(self, Key::Escape);
+        self.visit(dest).unwrap_or_default();
+        Ok(())
+    }
+
+    /// Remove one item of the given kind from the inventory, if there's any.
+    pub fn remove_item(&mut self, key: &Key) -> Option<Box<dyn Item>> {
+        let items = self.inventory.get_mut(key)?;
+        let item = items.pop();
+        if items.is_empty() {
+            self.inventory.remove(key);
+        }
+        item
+    }
+
+    /// Change the hero class, only at home. Above level 1 this respecs the
+    /// hero, which costs gold and a level stone.
+    pub fn change_class(&mut self, name: &str) -> Result<()> {
+        if !self.location.is_home() {
+            bail!("Class change is only allowed at home.")
+        }
+        if Class::player_by_name(name).is_none() {
+            bail!("Unknown class name.");
+        }
+
+        let cost = CLASS_CHANGE_COST * self.player.level;
+        let respec = self.player.level > 1 && name != self.player.class.name;
+        if respec {
+            if !self.inventory.contains_key(&Key::LevelStone) {
+                bail!("A {} is needed to change class.", Key::LevelStone);
+            }
+            if self.gold < cost {
+                bail!("Not enough gold.");
+            }
+            self.gold -= cost;
+            self.stats.spend(cost);
+            self.remove_item(&Key::LevelStone);
+            log::respec(cost);
+        }
+        self.player.change_class(name).unwrap_or_default();
+        Ok(())
+    }
+
+    /// Restart the hero at level 1 with the next prestige rank, see
+    /// `Character::ascend`.
+    pub fn ascend(&mut self) -> Result<()> {
+        if !self.location.is_home() {
+            bail!("Ascension is only allowed at home.")
+        }
+        self.player.ascend()?;
+        log::ascend(&self.player);
+        Ok(())
+    }
+
+    /// The locations of the tombs left by fallen heroes, sorted, paired with
+    /// the contents of each one.
+    pub fn tombs(&self) -> Vec<(&String, Vec<String>)> {
+        let mut tombs: Vec<(&String, Vec<String>)> = self
+            .tombstones
+            .iter()
+            .map(|(location, tomb)| (location, tomb.contents()))
+            .collect();
+        tombs.sort_by_key(|(location, _)| *location);
+        tombs
+    }
+
     pub fn inventory(&self) -> HashMap<&Key, usize> {
         self.inventory
             .iter()
@@ -193,18 +1234,14 @@ impl Game {
 
     pub fn describe(&self, key: Key) -> Result<(String, String)> {
         let (display, description) = match key {
-            Key::Sword if self.player.sword.is_some() => self
-                .player
-                .sword
-                .as_ref()
-                .map(|s| (s.to_string(), s.describe()))
-                .unwrap(),
-            Key::Shield if self.player.shield.is_some() => self
-                .player
-                .shield
-                .as_ref()
-                .map(|s| (s.to_string(), s.describe()))
-                .unwrap(),
+            Key::Sword | Key::Shield | Key::Helmet | Key::Armor | Key::Boots
+                if self.player.equipment(&key).is_some() =>
+            {
+                self.player
+                    .equipment(&key)
+                    .map(|s| (s.to_string(), s.describe()))
+                    .unwrap()
+            }
             Key::Ring(ref ring) if self.player.left_ring.as_ref() == Some(ring) => {
                 (ring.to_string(), ring.describe())
             }
@@ -221,7 +1258,13 @@ impl Game {
             }
         };
 
-        Ok((display, description))
+        match key {
+            Key::Ring(ring) if self.player.is_cursed(&ring) => Ok((
+                format!("cursed {}", display),
+                format!("{}, {}", description, equipment::CURSE_DESCRIPTION),
+            )),
+            _ => Ok((display, description)),
+        }
     }
 
     /// Attempt to bribe or run away according to the given options,
@@ -233,146 +1276,607 @@ impl Game {
         enemy: &mut Character,
         run: bool,
         bribe: bool,
+        cast: Option<Spell>,
+    ) -> Result<bool, character::Dead> {
+        self.battle_group(std::slice::from_mut(enemy), run, bribe, cast)
+    }
+
+    /// Same as `battle` but against a group of enemies at once. Bribing
+    /// costs as much as bribing each enemy, and running away depends on
+    /// the strongest and fastest of them.
+    pub fn battle_group(
+        &mut self,
+        enemies: &mut [Character],
+        run: bool,
+        bribe: bool,
+        cast: Option<Spell>,
+    ) -> Result<bool, character::Dead> {
+        let distance = self.location.distance_from_home();
+        self.battle_group_at(enemies, run, bribe, cast, &distance)
+    }
+
+    /// Same as `battle_group` but with the loot dropped by the enemies as
+    /// found at the given distance from home, e.g. the depth of a dungeon
+    /// floor, instead of the current location's.
+    fn battle_group_at(
+        &mut self,
+        enemies: &mut [Character],
+        run: bool,
+        bribe: bool,
+        cast: Option<Spell>,
+        distance: &Distance,
     ) -> Result<bool, character::Dead> {
+        for enemy in enemies.iter() {
+            self.bestiary.encounter(enemy);
+        }
+        log::battle_start(&self.player, enemies, &self.location);
+
         // don't attempt bribe and run in the same turn
         if bribe {
-            let bribe_cost = self.player.gold_gained(enemy.level) / 2;
+            let bribe_cost: i32 = enemies
+                .iter()
+                .map(|enemy| self.player.gold_gained(enemy.level) / 2)
+                .sum();
             if self.gold >= bribe_cost && random().bribe_succeeds() {
                 self.gold -= bribe_cost;
+                self.stats.spend(bribe_cost);
                 log::bribe(&self.player, bribe_cost);
+                log::battle_end();
+                for enemy in enemies.iter() {
+                    quest::enemy_bribed(self, enemy);
+                }
                 return Ok(false);
             };
             log::bribe(&self.player, 0);
-        } else if run {
-            let success = random().run_away_succeeds(
-                self.player.level,
-                enemy.level,
-                self.player.speed(),
-                enemy.speed(),
-            );
-            log::run_away(&self.player, success);
-            if success {
-                return Ok(false);
-            }
+        } else if run && self.run_away(enemies) {
+            log::battle_end();
+            return Ok(false);
         }
 
-        if let Ok(xp) = self.run_battle(enemy) {
-            self.battle_won(enemy, xp);
-            Ok(true)
-        } else {
-            self.battle_lost();
-            Err(character::Dead)
+        self.fight(enemies, cast, None, distance)
+    }
+
+    /// Start a battle against the given enemies where, instead of fighting
+    /// automatically, the player action for each turn is decided by the
+    /// given function, based on the current game and enemies state.
+    /// Return Ok(true) if the enemies were beaten, Ok(false) if the player
+    /// fled, Err<Dead> if the character dies.
+    pub fn battle_interactive(
+        &mut self,
+        enemies: &mut [Character],
+        choose: &mut ChooseAction,
+    ) -> Result<bool, character::Dead> {
+        for enemy in enemies.iter() {
+            self.bestiary.encounter(enemy);
+        }
+        log::battle_start(&self.player, enemies, &self.location);
+        let distance = self.location.distance_from_home();
+        self.fight(enemies, None, Some(choose), &distance)
+    }
+
+    /// Fight a wave of arena enemies. Arena battles are to the knockout: a
+    /// beaten hero is carried out with 1 hp instead of dying, and keeps
+    /// everything. Return whether the wave was beaten.
+    pub fn arena_battle(&mut self, enemies: &mut [Character]) -> bool {
+        for enemy in enemies.iter() {
+            self.bestiary.encounter(enemy);
         }
+        log::battle_start(&self.player, enemies, &self.location);
+        self.clock.advance(clock::BATTLE_HOURS);
+        plugin::battle_start(self, enemies);
+        let won = match self.run_battle(enemies, None, None, true) {
+            Ok(Some(xp)) => {
+                let distance = self.location.distance_from_home();
+                self.battle_won(enemies, xp, &distance);
+                true
+            }
+            Ok(None) => false,
+            Err(character::Dead) => {
+                self.player.status_effects.cure();
+                self.player.current_hp = 1;
+                log::knocked_out(&self.player);
+                false
+            }
+        };
+        self.stats.battle(won);
+        log::battle_end();
+        won
     }
 
-    /// Runs a turn-based combat between the game's player and the given enemy.
+    /// Attempt to run away from the given enemies, returning whether it
+    /// succeeded.
+    fn run_away(&self, enemies: &[Character]) -> bool {
+        let success = random().run_away_succeeds(
+            self.player.level,
+            enemies.iter().map(|e| e.level).max().unwrap_or(0),
+            self.player.speed(),
+            enemies.iter().map(|e| e.speed()).max().unwrap_or(0),
+        );
+        log::run_away(&self.player, success);
+        success
+    }
+
+    /// Run the battle and handle its outcome, with the loot as found at the
+    /// given distance from home.
+    fn fight(
+        &mut self,
+        enemies: &mut [Character],
+        cast: Option<Spell>,
+        choose: Option<&mut ChooseAction>,
+        distance: &Distance,
+    ) -> Result<bool, character::Dead> {
+        self.clock.advance(clock::BATTLE_HOURS);
+        plugin::battle_start(self, enemies);
+        let result = match self.run_battle(enemies, cast, choose, false) {
+            Ok(Some(xp)) => {
+                self.stats.battle(true);
+                self.battle_won(enemies, xp, distance);
+                Ok(true)
+            }
+            Ok(None) => {
+                self.stats.battle(false);
+                Ok(false)
+            }
+            Err(character::Dead) => {
+                self.stats.battle(false);
+                self.battle_lost();
+                Err(character::Dead)
+            }
+        };
+        log::battle_end();
+        result
+    }
+
+    /// Runs a turn-based combat between the game's player and the given enemies.
     /// The frequency of the turns is determined by the speed stat of each
     /// character.
     ///
     /// Some special abilities are enabled by the player's equipped rings:
     /// Double-beat, counter-attack and revive.
     ///
-    /// Returns Ok(xp gained) if the player wins, or Err(()) if it loses.
-    fn run_battle(&mut self, enemy: &mut Character) -> Result<i32, character::Dead> {
+    /// Party members get their own turns, interleaved with the player's and
+    /// the enemies' by speed, and gain experience from their own attacks.
+    /// The battle is lost only if the player dies: knocked out members
+    /// stop fighting until they rest at home.
+    ///
+    /// When fighting a group, the enemy to attack is chosen according to
+    /// the configured target strategy.
+    ///
+    /// If a spell to cast is given, the player uses its turns to cast it
+    /// instead of attacking whenever it's useful and there's enough mp.
+    /// If a choose function is given, it decides the player actions instead.
+    ///
+    /// Battles to the knockout don't use phoenix downs to revive the player,
+    /// since being beaten in them isn't dying.
+    ///
+    /// Returns Ok(Some(xp gained)) if the player wins, Ok(None) if it flees,
+    /// or Err(Dead) if it loses.
+    fn run_battle(
+        &mut self,
+        enemies: &mut [Character],
+        cast: Option<Spell>,
+        mut choose: Option<&mut ChooseAction>,
+        knockout: bool,
+    ) -> Result<Option<i32>, character::Dead> {
         // Player's using the revive ring can come back to life at most once per battle
         let mut already_revived = false;
 
+        // and active skills are used at most once per battle too
+        let mut skill_used = false;
+
         // These accumulators get increased based on the character's speed:
         // the faster will get more frequent turns.
-        let (mut pl_accum, mut en_accum) = (0, 0);
+        let mut pl_accum = 0;
+        let mut en_accums = vec![0; enemies.len()];
+        let mut party_accums = vec![0; self.party.len()];
         let mut xp = 0;
 
-        while enemy.current_hp > 0 {
+        while let Some(target) = pick_target(enemies) {
             pl_accum += self.player.speed();
-            en_accum += enemy.speed();
+            for (accum, enemy) in en_accums.iter_mut().zip(enemies.iter()) {
+                if enemy.current_hp > 0 {
+                    *accum += enemy.speed();
+                }
+            }
+            for (accum, member) in party_accums.iter_mut().zip(&self.party) {
+                if member.current_hp > 0 {
+                    *accum += member.speed();
+                }
+            }
+
+            // the enemy standing closest to its turn
+            let (enemy_turn, en_accum) = en_accums
+                .iter()
+                .copied()
+                .enumerate()
+                .filter(|(i, _)| enemies[*i].current_hp > 0)
+                .max_by_key(|(_, accum)| *accum)
+                .unwrap();
+
+            // the fastest party member gets the turn if it's ahead of both
+            // the player and the enemies
+            let member_turn = party_accums
+                .iter()
+                .enumerate()
+                .filter(|(i, accum)| self.party[*i].current_hp > 0 && **accum > pl_accum)
+                .max_by_key(|(_, accum)| **accum)
+                .filter(|(_, accum)| **accum >= en_accum)
+                .map(|(i, _)| i);
+
+            if let Some(i) = member_turn {
+                let member = &mut self.party[i];
+                if member.status_effects.has(StatusEffect::Stun) {
+                    log::stunned(member);
+                } else {
+                    let (member_xp, _) = member.attack(&mut enemies[target]);
+                    member.add_experience(member_xp);
+                }
+                member.apply_status_effects().unwrap_or_default();
 
-            if pl_accum >= en_accum {
+                party_accums[i] = -1;
+            } else if pl_accum >= en_accum {
                 // In some urgent circumstances, it's preferable to use the turn to
                 // recover mp or hp than attacking
-                if !self.autopotion(enemy) && !self.autoether(enemy) {
+                if self.player.status_effects.has(StatusEffect::Stun) {
+                    log::stunned(&self.player);
+                } else if let Some(choose) = choose.as_mut() {
+                    match choose(self, enemies) {
+                        Action::Attack => {
+                            let enemy = &mut enemies[target];
+                            let (new_xp, _) = self.player.attack(enemy);
+                            xp += new_xp;
+                            self.player.maybe_double_beat(enemy);
+                        }
+                        Action::Cast(spell) => {
+                            let (new_xp, _) = self.player.cast(spell, &mut enemies[target]);
+                            xp += new_xp;
+                        }
+                        Action::Use(key) => match self.use_in_battle(key, enemies, target) {
+                            Ok(new_xp) => xp += new_xp,
+                            Err(err) => log::error(&err.to_string()),
+                        },
+                        Action::Flee => {
+                            if self.run_away(enemies) {
+                                return Ok(None);
+                            }
+                        }
+                    }
+                } else if let Some(skill) = self.player.ready_skill().filter(|_| !skill_used) {
+                    skill_used = true;
+                    self.player.use_skill(skill);
+                    if skill == Skill::SmokeBomb {
+                        return Ok(None);
+                    }
+                } else if let Some(spell) = cast.filter(|s| self.player.should_cast(*s)) {
+                    let (new_xp, _) = self.player.cast(spell, &mut enemies[target]);
+                    xp += new_xp;
+                } else if let Some(new_xp) = self.auto_use_item(enemies, target) {
+                    xp += new_xp;
+                } else {
+                    let enemy = &mut enemies[target];
                     let (new_xp, _) = self.player.attack(enemy);
                     xp += new_xp;
 
                     self.player.maybe_double_beat(enemy);
                 }
 
+                // the companion fights on the hero's turn, gaining its own xp
+                if let Some(companion) = &mut self.companion {
+                    if let Some(target) = pick_target(enemies) {
+                        if !companion.is_knocked_out() {
+                            let (companion_xp, _) =
+                                companion.character.attack(&mut enemies[target]);
+                            companion.character.add_experience(companion_xp);
+                        }
+                    }
+                }
+
                 // Status effects are applied after each turn. The player may die
                 // during its own turn because of status ailment damage
                 let died = self.player.apply_status_effects();
-                already_revived = self.player.maybe_revive(died, already_revived)?;
+                already_revived = self
+                    .player
+                    .maybe_revive(died, already_revived)
+                    .or_else(|dead| self.battle_revive(dead, knockout).map(|_| already_revived))?;
+
+                pl_accum = -1;
+            } else {
+                let enemy = &mut enemies[enemy_turn];
+                if enemy.maybe_enrage() {
+                    log::enraged(enemy);
+                }
+                if enemy.tick_enrage_timer() {
+                    log::raid_enraged(enemy);
+                }
+
+                if enemy.status_effects.has(StatusEffect::Stun) {
+                    log::stunned(enemy);
+                } else if let Some(companion) = self
+                    .companion
+                    .as_mut()
+                    .filter(|c| !c.is_knocked_out() && random().enemy_targets_companion())
+                {
+                    let _ = enemy.attack(&mut companion.character);
+                } else if let Some(member) = self.enemy_target() {
+                    let _ = enemy.attack(member);
+                } else {
+                    let (_, died) = enemy.attack(&mut self.player);
+                    already_revived =
+                        self.player
+                            .maybe_revive(died, already_revived)
+                            .or_else(|dead| {
+                                self.battle_revive(dead, knockout).map(|_| already_revived)
+                            })?;
+
+                    self.player.maybe_counter_attack(enemy);
+                }
+
+                enemy.apply_status_effects().unwrap_or_default();
+
+                en_accums[enemy_turn] = -1;
+            }
+        }
+
+        Ok(Some(xp))
+    }
+
+    fn battle_won(&mut self, enemies: &[Character], xp: i32, distance: &Distance) {
+        // fighting a group is harder than fighting its enemies one at a time
+        let xp = xp * (100 + 25 * (enemies.len() as i32 - 1)) / 100;
+
+        // learning about a new kind of enemy is rewarded with extra xp
+        let first_kills: Vec<String> = enemies
+            .iter()
+            .filter(|enemy| self.bestiary.kill(enemy))
+            .map(|enemy| enemy.name())
+            .collect();
+        let xp = xp * (100 + FIRST_KILL_XP * first_kills.len() as i32) / 100;
+
+        // time spent resting at home between sessions is rewarded too
+        let xp = match self.rested.bonus() {
+            Some(bonus) => xp * (100 + bonus) / 100,
+            None => xp,
+        };
+        let mut gold: i32 = enemies
+            .iter()
+            .map(|enemy| {
+                let loot = Drops::get().loot(&enemy.class.name);
+                let gold = loot.gold(self.player.gold_gained(enemy.level));
+                if enemy.gilded {
+                    gold * 2
+                } else {
+                    gold
+                }
+            })
+            .sum();
+        if self.player.has_skill(Skill::Pickpocket) {
+            gold = gold * (100 + skill::PICKPOCKET_GOLD) / 100;
+        }
+        self.gold += gold;
+        if self.player.has_skill(Skill::ManaFlow) {
+            self.player
+                .update_mp(self.player.max_mp() * skill::MANA_FLOW_MP / 100);
+        }
+        let levels_up = self.player.add_experience(xp);
 
-                pl_accum = -1;
-            } else {
-                let (_, died) = enemy.attack(&mut self.player);
-                already_revived = self.player.maybe_revive(died, already_revived)?;
+        // killing the members of a faction makes it less friendly
+        for enemy in enemies {
+            if let Some(faction) = self.reputation.kill(&enemy.class) {
+                log::standing(faction, self.reputation.standing(faction));
+            }
+        }
 
-                self.player.maybe_counter_attack(enemy);
+        // each enemy may drop its own loot, bosses always drop valuable loot
+        let mut loot = Chest::default();
+        for enemy in enemies {
+            if enemy.is_boss() {
+                loot.extend(Chest::boss_loot(self, distance));
+            } else if let Some(chest) = Chest::battle_loot(self, enemy, distance) {
+                for key in chest.keys() {
+                    self.bestiary.drop_seen(enemy, key);
+                }
+                loot.extend(chest);
+            }
+        }
+        for enemy in enemies {
+            if let Some(material) = &enemy.class.drops {
+                if Drops::get().loot(&enemy.class.name).material_dropped() {
+                    self.bestiary.drop_seen(enemy, material.key());
+                    loot.add_item(Box::new(material.clone()));
+                }
+            }
+        }
+        let (reward_items, loot_gold) = loot.pick_up(self);
+        let gold = gold + loot_gold;
+        self.stats.earn(gold);
 
-                enemy.apply_status_effects().unwrap_or_default();
+        // cursed items feed on the hero's gold
+        let drained = self.gold * CURSE_GOLD_DRAIN * self.player.curses() / 100;
+        self.gold -= drained;
 
-                en_accum = -1;
+        log::battle_won(self, xp, levels_up, gold, &reward_items);
+        if drained > 0 {
+            log::curse_drain(drained);
+        }
+        self.leave_behind(loot);
+        for name in &first_kills {
+            log::bestiary_entry(name);
+        }
+        for enemy in enemies {
+            if enemy.name() == "gorthaur" {
+                self.final_boss_beaten = true;
+                speedrun::final_boss_beaten(self);
+                title::final_boss_beaten(self);
             }
+            if enemy.is_boss() {
+                achievement::boss_beaten(self);
+                hook::boss_kill(self, enemy);
+                speedrun::boss_beaten(self, enemy);
+            }
+            achievement::enemy_killed(self, &enemy.class);
+            quest::battle_won(self, enemy, 0);
+        }
+        challenge::battle_won(self);
+        if levels_up > 0 {
+            quest::level_up(self, levels_up);
+            challenge::level_up(self, levels_up);
+            speedrun::level_up(self);
+            hook::level_up(self);
+            plugin::level_up(self);
         }
+        plugin::battle_won(self, enemies, xp, gold);
 
-        Ok(xp)
+        // at most one enemy of the group can be tamed
+        if let Some(companion) = enemies
+            .iter()
+            .filter(|_| random().tame_enemy())
+            .find_map(Companion::tame)
+        {
+            self.add_companion(companion);
+        }
     }
 
-    fn battle_won(&mut self, enemy: &Character, xp: i32) {
-        let gold = self.player.gold_gained(enemy.level);
-        self.gold += gold;
-        let levels_up = self.player.add_experience(xp);
-
-        let reward_items =
-            Chest::battle_loot(self).map_or(HashMap::new(), |mut chest| chest.pick_up(self).0);
+    /// Bring the hero back from death with half their hp, and cured of
+    /// status effects, by using up a phoenix down if they carry one.
+    /// Otherwise the hero stays dead.
+    fn revive(&mut self, dead: character::Dead) -> Result<(), character::Dead> {
+        if self.remove_item(&Key::PhoenixDown).is_none() {
+            return Err(dead);
+        }
+        let healed = self.player.status_effects.cure();
+        let restored = std::cmp::max(1, self.player.max_hp() / 2);
+        self.player.current_hp = restored;
+        log::heal_item(&self.player, "phoenix-down", restored, 0, healed);
+        quest::item_used(self, Key::PhoenixDown);
+        Ok(())
+    }
 
-        log::battle_won(self, xp, levels_up, gold, &reward_items);
-        quest::battle_won(self, enemy, levels_up);
+    /// Revive the player with a phoenix down when dying in battle, unless
+    /// it's to the knockout.
+    fn battle_revive(
+        &mut self,
+        dead: character::Dead,
+        knockout: bool,
+    ) -> Result<(), character::Dead> {
+        if knockout {
+            return Err(dead);
+        }
+        self.revive(dead)
     }
 
     fn battle_lost(&mut self) {
+        log::battle_lost(&self.player);
+        let penniless = self.gold == 0;
+
         // Drop hero items in the location. If there was a previous tombstone
-        // merge the contents of both chests
-        let mut tombstone = Chest::drop(self);
-        let location = self.location.to_string();
-        if let Some(previous) = self.tombstones.remove(&location) {
-            tombstone.extend(previous);
+        // merge the contents of both chests. Hardcore heroes leave nothing behind
+        if !self.hardcore {
+            let mut tombstone = Chest::drop(self);
+
+            // insured heroes leave part of it at home for the next one,
+            // otherwise recovering the tomb becomes a quest
+            if self.insured {
+                let payout = tombstone.split(INSURANCE_COVERAGE);
+                log::insurance_payout(&payout.contents());
+                let home = Location::home().to_string();
+                let mut chest = self.chests.remove(&home).unwrap_or_default();
+                chest.extend(payout);
+                self.chests.insert(home, chest);
+            } else {
+                quest::tomb_left(self);
+            }
+
+            let location = self.location.to_string();
+            if let Some(previous) = self.tombstones.remove(&location) {
+                tombstone.extend(previous);
+            }
+            self.tombstones.insert(location, tombstone);
         }
-        self.tombstones.insert(location, tombstone);
 
-        log::battle_lost(&self.player);
+        self.stats.death(&self.location);
+        achievement::died(self, penniless);
+        hook::death(self);
     }
 
-    /// If the player is low on hp and has a potion available use it
-    /// instead of attacking in the current turn.
-    fn autopotion(&mut self, enemy: &Character) -> bool {
-        if self.player.current_hp > self.player.max_hp() / 3 {
-            return false;
+    /// Randomly pick one of the party members still standing as the target
+    /// of the enemy attack, or None if it should attack the player.
+    fn enemy_target(&mut self) -> Option<&mut Character> {
+        let mut standing: Vec<&mut Character> =
+            self.party.iter_mut().filter(|m| m.current_hp > 0).collect();
+        let target = random().enemy_target(standing.len());
+        if target == 0 {
+            None
+        } else {
+            Some(standing.swap_remove(target - 1))
+        }
+    }
+
+    /// If the state of the player triggers an auto-use rule, e.g. being low
+    /// on hp, and the item is available, use it instead of attacking the
+    /// target in the current turn. Returns the xp gained if an item was used.
+    fn auto_use_item(&mut self, enemies: &mut [Character], target: usize) -> Option<i32> {
+        let triggered: Vec<Key> = self
+            .auto_use
+            .triggered(&self.player)
+            .into_iter()
+            .filter(|key| self.inventory.contains_key(key))
+            .collect();
+        if triggered.is_empty() {
+            return None;
         }
 
         // If there's a good chance of winning the battle on the next attack,
-        // don't use the potion.
-        let (potential_damage, _) = self.player.damage(enemy);
-        if potential_damage >= enemy.current_hp {
-            return false;
+        // don't use the item.
+        let (potential_damage, _) = self.player.damage(&enemies[target]);
+        if potential_damage >= enemies[target].current_hp {
+            return None;
         }
 
-        self.use_item(Key::Potion).is_ok()
+        triggered
+            .into_iter()
+            .find_map(|key| self.use_in_battle(key, enemies, target).ok())
     }
 
-    fn autoether(&mut self, enemy: &Character) -> bool {
-        if !self.player.class.is_magic() || self.player.can_magic_attack() {
-            return false;
+    /// Use an item during a battle. Throwable items are thrown at the
+    /// target, or at every enemy standing if they hit all. Escapes can't be
+    /// used, since they would take the hero home in the middle of the
+    /// battle. Returns the xp gained with the item.
+    fn use_in_battle(&mut self, key: Key, enemies: &mut [Character], target: usize) -> Result<i32> {
+        let throwable = match key {
+            Key::Throwable(throwable) => throwable,
+            Key::Escape => bail!("Can't use an escape in battle, flee instead."),
+            _ => return self.use_item(key).map(|_| 0),
+        };
+        if self.remove_item(&key).is_none() {
+            bail!("item not found.");
         }
+        log::throw(&self.player, throwable);
+        quest::item_used(self, key);
 
-        // If there's a good chance of winning the battle on the next attack,
-        // don't use the ether.
-        let (potential_damage, _) = self.player.damage(enemy);
-        if potential_damage >= enemy.current_hp {
-            return false;
+        let mut xp = 0;
+        for (i, enemy) in enemies.iter_mut().enumerate() {
+            if enemy.current_hp > 0 && (i == target || throwable.hits_all()) {
+                let (new_xp, _) = self.player.throw(throwable, enemy);
+                xp += new_xp;
+            }
         }
+        Ok(xp)
+    }
+}
 
-        self.use_item(Key::Ether).is_ok()
+/// Choose the enemy still standing to attack next, according to the
+/// configured strategy. Returns None if all enemies were beaten.
+fn pick_target(enemies: &[Character]) -> Option<usize> {
+    let standing = enemies
+        .iter()
+        .enumerate()
+        .filter(|(_, enemy)| enemy.current_hp > 0);
+    match Config::get().target {
+        Target::Weakest => standing.min_by_key(|(_, enemy)| enemy.current_hp),
+        Target::Strongest => standing.max_by_key(|(_, enemy)| enemy.level),
+        Target::First => standing.min_by_key(|(i, _)| *i),
     }
+    .map(|(i, _)| i)
 }
 
 impl Default for Game {
@@ -386,6 +1890,8 @@ mod tests {
     use super::*;
     use crate::character::class;
     use crate::item;
+    use crate::item::scroll::Scroll;
+    use crate::item::throwable::Throwable;
 
     #[test]
     fn test_inventory() {
@@ -420,6 +1926,25 @@ mod tests {
         assert!(game.use_item(Key::Potion).is_err());
     }
 
+    #[test]
+    fn use_scroll() {
+        let mut game = Game::new();
+        game.add_item(Box::new(Scroll(Spell::Haste)));
+
+        // warriors can't learn spells
+        assert!(game.use_item(Key::Scroll(Spell::Haste)).is_err());
+        assert_eq!(1, game.inventory().len());
+
+        game.player.change_class("mage").unwrap_or_default();
+        assert!(game.use_item(Key::Scroll(Spell::Haste)).is_ok());
+        assert!(game.player.spells().contains(&Spell::Haste));
+        assert!(game.inventory().is_empty());
+
+        // already known
+        game.add_item(Box::new(Scroll(Spell::Haste)));
+        assert!(game.use_item(Key::Scroll(Spell::Haste)).is_err());
+    }
+
     #[test]
     fn test_ring_equip() {
         let mut game = Game::new();
@@ -486,7 +2011,8 @@ mod tests {
 
     #[test]
     fn battle_won() {
-        let enemy_base = class::Class::random(class::Category::Common);
+        // an enemy without a loot table, which gains the usual gold
+        let enemy_base = class::Class::enemy_by_name("wolf").unwrap();
         let enemy_class = class::Class {
             speed: class::Stat(1, 1),
             hp: class::Stat(16, 1),
@@ -509,11 +2035,12 @@ mod tests {
         // player - 5 hp
         // enemy - 10hp (but has 3 remaining)
 
-        let result = game.battle(&mut enemy, false, false);
+        let result = game.battle(&mut enemy, false, false, None);
         assert!(result.is_ok());
         assert_eq!(15, game.player.current_hp);
         assert_eq!(1, game.player.level);
-        assert_eq!(16, game.player.xp);
+        // extra 20% xp for the first kill of the class
+        assert_eq!(19, game.player.xp);
         // extra 100g for the completed quest
         assert_eq!(150, game.gold);
 
@@ -521,20 +2048,725 @@ mod tests {
 
         // same turns, added xp increases level
 
-        let result = game.battle(&mut enemy, false, false);
+        let result = game.battle(&mut enemy, false, false, None);
         assert!(result.is_ok());
         assert_eq!(2, game.player.level);
-        assert_eq!(2, game.player.xp);
+        assert_eq!(5, game.player.xp);
         // extra 100g for level up quest
         assert_eq!(300, game.gold);
     }
 
+    #[test]
+    fn difficulty_kept() {
+        let mut game = Game::new();
+        game.difficulty = Difficulty::Nightmare;
+        game.reset();
+        assert_eq!(Difficulty::Nightmare, game.difficulty);
+        game.reset_hardcore();
+        assert_eq!(Difficulty::Nightmare, game.difficulty);
+    }
+
+    #[test]
+    fn fast_travel() {
+        let mut game = Game::new();
+        game.quests = QuestList::default();
+        let dest = Location::from("~/..").unwrap();
+        game.waypoints.insert(String::from("up"), dest.clone());
+        assert_eq!(FAST_TRAVEL_COST, game.fast_travel_cost(&dest));
+
+        assert!(!game.fast_travel(dest.clone()).unwrap());
+        assert_eq!(Location::home(), game.location);
+
+        game.gold = FAST_TRAVEL_COST + 10;
+        assert!(game.fast_travel(dest.clone()).unwrap());
+        assert_eq!(dest, game.location);
+        assert_eq!(10, game.gold);
+        assert_eq!(FAST_TRAVEL_COST, game.stats.gold_spent);
+
+        // waypoints are kept across heroes
+        game.reset();
+        assert_eq!(Some(&dest), game.waypoints.get("up"));
+    }
+
+    #[test]
+    fn escape_to_visited() {
+        let mut game = Game::new();
+        game.quests = QuestList::default();
+        let root = Location::from("/").unwrap();
+        game.add_item(Box::new(item::Escape::new()));
+        assert!(game.escape_to(root.clone()).is_err());
+
+        game.visit(root.clone()).unwrap();
+        game.visit(Location::home()).unwrap();
+        game.escape_to(root.clone()).unwrap();
+        assert_eq!(root, game.location);
+        assert!(game.inventory().is_empty());
+        assert!(game.escape_to(Location::home()).is_err());
+    }
+
+    #[test]
+    fn auto_use_rules() {
+        let mut game = Game::new();
+        game.quests = QuestList::default();
+        let mut enemies: Vec<Character> = (0..2)
+            .map(|_| {
+                let class = class::Class::random(class::Category::Common).clone();
+                let mut enemy = character::Character::new(class, 1);
+                enemy.current_hp = 1000;
+                enemy
+            })
+            .collect();
+
+        game.add_item(Box::new(item::Remedy::new()));
+        game.player.status_effects.inflict(StatusEffect::Poison);
+        assert!(game.auto_use_item(&mut enemies, 0).is_none());
+
+        game.auto_use.set("remedy=poison").unwrap();
+        assert!(game.auto_use_item(&mut enemies, 0).is_some());
+        assert!(!game.player.status_effects.has(StatusEffect::Poison));
+        assert!(game.inventory().is_empty());
+
+        // bombs are thrown at every enemy
+        game.add_item(Box::new(Throwable::Bomb));
+        game.auto_use.set("bomb=hp<50").unwrap();
+        game.player.current_hp = 1;
+        assert!(game.auto_use_item(&mut enemies, 0).is_some());
+        assert!(game.inventory().is_empty());
+        for enemy in &enemies {
+            assert!(enemy.current_hp < 1000);
+            assert!(enemy.status_effects.has(StatusEffect::Burn));
+        }
+        assert!(game.use_item(Key::Throwable(Throwable::Bomb)).is_err());
+
+        // the rules are kept across heroes
+        game.reset();
+        assert_eq!(4, game.auto_use.rules().len());
+    }
+
+    #[test]
+    fn gilded_enemy() {
+        let mut game = Game::new();
+        game.quests = QuestList::default();
+        game.location = Location::from("/").unwrap();
+        let player_class = class::Class {
+            hp: class::Stat(100, 1),
+            strength: class::Stat(100, 1),
+            speed: class::Stat(100, 1),
+            ..game.player.class.clone()
+        };
+        game.player = character::Character::new(player_class, 1);
+        let enemy_class = class::Class {
+            hp: class::Stat(10, 1),
+            strength: class::Stat(2, 1),
+            speed: class::Stat(1, 1),
+            ..class::Class::enemy_by_name("wolf").unwrap().clone()
+        };
+        let mut enemy = character::Character::new(enemy_class, 1).gild();
+        assert_eq!(15, enemy.max_hp());
+
+        let gold = game.player.gold_gained(enemy.level);
+        game.battle(&mut enemy, false, false, None).unwrap();
+        assert_eq!(gold * 2, game.gold);
+        assert!(!game.inventory().is_empty());
+    }
+
+    #[test]
+    fn party_members() {
+        let mut game = Game::new();
+        assert!(game.add_party_member("necromancer").is_err());
+        assert!(game.add_party_member("mage").is_ok());
+        assert!(game.add_party_member("thief").is_ok());
+        assert!(game.add_party_member("warrior").is_err());
+        assert_eq!(2, game.party.len());
+
+        assert!(game.remove_party_member("warrior").is_err());
+        assert!(game.remove_party_member("mage").is_ok());
+        assert_eq!("thief", game.party[0].name());
+
+        game.location = Location::from("~/..").unwrap();
+        assert!(game.add_party_member("mage").is_err());
+        assert!(game.remove_party_member("thief").is_err());
+    }
+
+    #[test]
+    fn party_battle() {
+        let enemy_class = class::Class {
+            speed: class::Stat(1, 1),
+            hp: class::Stat(100, 1),
+            strength: class::Stat(1, 1),
+            ..class::Class::random(class::Category::Common).clone()
+        };
+        let mut enemy = character::Character::new(enemy_class, 1);
+
+        let mut game = Game::new();
+        game.add_party_member("thief").unwrap();
+        assert!(game.battle(&mut enemy, false, false, None).unwrap());
+
+        // the member leveled independently from its own attacks
+        let member = &game.party[0];
+        assert!(member.xp > 0 || member.level > 1);
+        assert!(game.player.xp > 0 || game.player.level > 1);
+
+        // members are restored at home
+        game.party[0].current_hp = 1;
+        game.location = Location::from("~/..").unwrap();
+        game.visit(Location::home()).unwrap();
+        assert_eq!(game.party[0].max_hp(), game.party[0].current_hp);
+    }
+
+    #[test]
+    fn group_battle() {
+        let enemy_class = class::Class {
+            speed: class::Stat(1, 1),
+            hp: class::Stat(20, 1),
+            strength: class::Stat(1, 1),
+            ..class::Class::enemy_by_name("wolf").unwrap().clone()
+        };
+        let mut enemies = vec![
+            character::Character::new(enemy_class.clone(), 1),
+            character::Character::new(enemy_class, 1),
+        ];
+
+        // the weakest enemy is attacked first
+        enemies[1].current_hp = 5;
+        assert_eq!(Some(1), pick_target(&enemies));
+
+        let mut game = Game::new();
+        game.quests = QuestList::default();
+        assert!(game.battle_group(&mut enemies, false, false, None).unwrap());
+        assert!(enemies.iter().all(|enemy| enemy.current_hp == 0));
+        assert_eq!(None, pick_target(&enemies));
+
+        // gold is rewarded for each enemy
+        assert_eq!(2 * game.player.gold_gained(1), game.gold);
+    }
+
+    #[test]
+    fn interactive_battle() {
+        let enemy_class = class::Class {
+            speed: class::Stat(1, 1),
+            hp: class::Stat(30, 1),
+            strength: class::Stat(1, 1),
+            ..class::Class::random(class::Category::Common).clone()
+        };
+        let mut enemy = character::Character::new(enemy_class, 1);
+
+        let mut game = Game::new();
+        game.add_item(Box::new(Potion::new(1)));
+        game.player.current_hp -= 5;
+
+        // use the potion on the first turn, then attack
+        let mut potions = Vec::new();
+        let mut choose = |game: &Game, _: &[Character]| {
+            let has_potion = game.inventory().contains_key(&Key::Potion);
+            potions.push(has_potion);
+            if has_potion {
+                Action::Use(Key::Potion)
+            } else {
+                Action::Attack
+            }
+        };
+        assert!(game
+            .battle_interactive(std::slice::from_mut(&mut enemy), &mut choose)
+            .unwrap());
+        assert_eq!(0, enemy.current_hp);
+        assert!(potions[0]);
+        assert!(!potions[1]);
+
+        // escapes don't take the hero home in the middle of the battle
+        let location = Location::from("/").unwrap();
+        game.location = location.clone();
+        game.player.current_hp -= 5;
+        game.add_item(Box::new(item::Escape::new()));
+        let mut enemy = character::Character::new(enemy.class.clone(), 1);
+        let mut turns = 0;
+        let mut choose = |_: &Game, _: &[Character]| {
+            turns += 1;
+            if turns == 1 {
+                Action::Use(Key::Escape)
+            } else {
+                Action::Attack
+            }
+        };
+        assert!(game
+            .battle_interactive(std::slice::from_mut(&mut enemy), &mut choose)
+            .unwrap());
+        assert_eq!(location, game.location);
+        assert!(game.inventory().contains_key(&Key::Escape));
+    }
+
+    #[test]
+    fn new_game_plus() {
+        let mut game = Game::new();
+        game.gold = 1000;
+        game.player.level = 10;
+        game.player.left_ring = Some(Ring::Speed);
+        game.add_item(Box::new(Ring::Gold));
+        assert!(!game.new_game_plus_unlocked());
+        assert!(game.new_game_plus().is_err());
+        assert_eq!(0, game.new_game_plus);
+
+        game.final_boss_beaten = true;
+        game.new_game_plus().unwrap();
+        assert_eq!(1, game.new_game_plus);
+        assert_eq!(1, game.player.level);
+        assert_eq!(500, game.gold);
+        assert!(!game.final_boss_beaten);
+
+        // rings are kept, and the upgraded ones can be found
+        assert!(game.player.left_ring.is_none());
+        assert!(game.inventory.contains_key(&Key::Ring(Ring::Speed)));
+        assert!(game.inventory.contains_key(&Key::Ring(Ring::Gold)));
+        assert!(!game.ring_pool.contains(&Ring::Speed));
+        assert!(game.ring_pool.contains(&Ring::Attack));
+        assert!(game.ring_pool.contains(&Ring::SpeedPlus));
+    }
+
+    #[test]
+    fn new_game_plus_kept() {
+        let mut game = Game::new();
+        game.final_boss_beaten = true;
+        game.new_game_plus().unwrap();
+
+        // dying doesn't bring the enemies back to the first tier
+        game.reset();
+        assert_eq!(1, game.new_game_plus);
+        assert!(game.ring_pool.contains(&Ring::SpeedPlus));
+        game.reset_hardcore();
+        assert_eq!(1, game.new_game_plus);
+    }
+
+    #[test]
+    fn boss_battle() {
+        let mut game = Game::new();
+        let home = Location::home().path_string();
+        game.location =
+            crate::location::tests::location_from(&format!("{}/1/2/3/4/5/6/7/8/9", home));
+        let guarded =
+            crate::location::tests::location_from(&format!("{}/1/2/3/4/5/6/7/8/9/10", home));
+
+        for _ in 0..50 {
+            game.player.add_experience(game.player.xp_for_next());
+        }
+        game.go_to(&guarded, false, false, None).unwrap();
+
+        // the boss loot is always picked up
+        assert_eq!(guarded, game.location);
+        assert!(game.bosses_beaten.contains(&10));
+        assert!(game.gold > 0);
+        assert!(
+            game.player.sword.is_some()
+                || game.player.shield.is_some()
+                || game.player.helmet.is_some()
+                || game.player.armor.is_some()
+                || game.player.boots.is_some()
+        );
+        assert!(enemy::spawn_boss(
+            &game.location,
+            &game.player,
+            &game.bosses_beaten,
+            game.difficulty
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn companion_battle() {
+        let enemy_class = class::Class {
+            speed: class::Stat(1, 1),
+            hp: class::Stat(40, 1),
+            strength: class::Stat(1, 1),
+            ..class::Class::random(class::Category::Common).clone()
+        };
+        let mut enemy = character::Character::new(enemy_class.clone(), 1);
+
+        let mut game = Game::new();
+        let companion = Companion::tame(&enemy).unwrap();
+        game.add_companion(companion);
+        assert!(game.battle(&mut enemy, false, false, None).unwrap());
+
+        // the companion got its own xp
+        let companion = game.companion.as_ref().unwrap();
+        assert!(companion.character.xp > 0 || companion.character.level > 1);
+    }
+
+    #[test]
+    fn swap_companions() {
+        let rat =
+            character::Character::new(class::Class::random(class::Category::Common).clone(), 1);
+        let mut game = Game::new();
+        assert!(game.dismiss_companion().is_err());
+
+        let mut first = Companion::tame(&rat).unwrap();
+        first.character.class.name = String::from("first");
+        first.item = Some(Box::new(item::Potion::new(1)));
+        let mut second = Companion::tame(&rat).unwrap();
+        second.character.class.name = String::from("second");
+        game.add_companion(first);
+        game.add_companion(second);
+        assert_eq!("first", game.companion.as_ref().unwrap().name());
+        assert_eq!(1, game.companions.len());
+
+        assert!(game.swap_companion("third").is_err());
+        assert!(game.swap_companion("second").is_ok());
+        assert_eq!("second", game.companion.as_ref().unwrap().name());
+        assert_eq!("first", game.companions[0].name());
+
+        // only at home
+        game.location = Location::from("~/..").unwrap();
+        assert!(game.swap_companion("first").is_err());
+        assert!(game.dismiss_companion().is_err());
+        game.location = Location::home();
+
+        assert!(game.swap_companion("first").is_ok());
+        assert!(game.dismiss_companion().is_ok());
+        assert!(game.companion.is_none());
+        assert_eq!(1, *game.inventory().get(&Key::Potion).unwrap());
+    }
+
+    #[test]
+    fn drop_companion_item() {
+        let rat =
+            character::Character::new(class::Class::random(class::Category::Common).clone(), 1);
+        let mut game = Game::new();
+        let mut companion = Companion::tame(&rat).unwrap();
+        companion.item = Some(Box::new(item::Potion::new(1)));
+        game.add_companion(companion);
+
+        let mut chest = Chest::drop(&mut game);
+        assert!(game.companion.as_ref().unwrap().item.is_none());
+
+        let (items, _) = chest.pick_up(&mut game);
+        assert_eq!(1, *items.get(&Key::Potion).unwrap());
+    }
+
+    #[test]
+    fn seeded_commands() {
+        let mut game = Game::new();
+        assert!(game.command_seed().is_none());
+
+        game.set_seed(42);
+        assert_eq!(Some((42, 0)), game.command_seed());
+        assert_eq!(Some((42, 1)), game.command_seed());
+
+        // same seed doesn't restart the sequence
+        game.set_seed(42);
+        assert_eq!(Some((42, 2)), game.command_seed());
+
+        game.set_seed(7);
+        assert_eq!(Some((7, 0)), game.command_seed());
+
+        game.reset();
+        assert_eq!(Some((7, 0)), game.command_seed());
+    }
+
+    #[test]
+    fn count_days() {
+        let mut game = Game::new();
+        assert_eq!(0, game.day);
+
+        game.visit(Location::from("~/..").unwrap()).unwrap();
+        game.visit(Location::home()).unwrap();
+        assert_eq!(1, game.day);
+
+        // staying home doesn't count
+        game.visit(Location::home()).unwrap();
+        assert_eq!(1, game.day);
+    }
+
+    #[test]
+    fn passive_training() {
+        let mut game = Game::new();
+
+        // opt-in only
+        game.track_activity();
+        game.claim_training();
+        assert_eq!(0, game.player.xp);
+
+        game.activity.enabled = true;
+        game.track_activity();
+        game.claim_training();
+        assert_eq!(1, game.player.xp);
+        assert_eq!(0, game.activity.pending_xp);
+
+        game.reset();
+        assert!(game.activity.enabled);
+    }
+
     #[test]
     fn battle_lost() {
         let mut game = Game::new();
         let enemy_class = class::Class::random(class::Category::Common);
         let mut enemy = character::Character::new(enemy_class.clone(), 10);
-        let result = game.battle(&mut enemy, false, false);
+        let result = game.battle(&mut enemy, false, false, None);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn hardcore_battle_lost() {
+        let mut game = Game::new();
+        game.reset_hardcore();
+        game.quests = QuestList::default();
+        assert!(game.hardcore);
+
+        game.gold = 100;
+        game.add_item(Box::new(Potion::new(1)));
+        let enemy_class = class::Class::random(class::Category::Common);
+        let mut enemy = character::Character::new(enemy_class.clone(), 10);
+        assert!(game.battle(&mut enemy, false, false, None).is_err());
+
+        // no tombstone is left behind
+        assert!(game.tombstones.is_empty());
+        assert_eq!(100, game.gold);
+
+        // and the next game starts from scratch, still hardcore
+        game.tombstones
+            .insert(game.location.to_string(), Chest::drop(&mut Game::new()));
+        game.reset();
+        assert!(game.hardcore);
+        assert!(game.tombstones.is_empty());
+        assert_eq!(0, game.gold);
+        assert!(game.inventory.is_empty());
+    }
+
+    #[test]
+    fn phoenix_down() {
+        let mut game = Game::new();
+        assert!(game.revive(character::Dead).is_err());
+
+        game.add_item(Box::new(item::PhoenixDown::new()));
+        assert!(game.use_item(Key::PhoenixDown).is_err());
+        game.player.current_hp = 0;
+        assert!(game.revive(character::Dead).is_ok());
+        assert_eq!(game.player.max_hp() / 2, game.player.current_hp);
+        assert!(game.inventory().is_empty());
+
+        // used up in battle before the hero dies for good
+        game.add_item(Box::new(item::PhoenixDown::new()));
+        let enemy_class = class::Class::random(class::Category::Common);
+        let mut enemy = character::Character::new(enemy_class.clone(), 10);
+        assert!(game.battle(&mut enemy, false, false, None).is_err());
+        let tombstone = &game.tombstones[&game.location.to_string()];
+        assert!(!tombstone.contents().contains(&String::from("phoenix-down")));
+    }
+
+    #[test]
+    fn insured_battle_lost() {
+        let mut game = Game::new();
+        game.quests = QuestList::default();
+        game.location = Location::from("/").unwrap();
+        game.insured = true;
+        game.gold = 100;
+        game.add_item(Box::new(item::map::TreasureMap));
+        game.add_item(Box::new(item::map::TreasureMap));
+        let enemy_class = class::Class::random(class::Category::Common);
+        let mut enemy = character::Character::new(enemy_class.clone(), 10);
+        assert!(game.battle(&mut enemy, false, false, None).is_err());
+
+        // half is left at home and no recovery quest is needed
+        assert_eq!(0, game.quests.list().len());
+        let home = game.chests.get_mut(&Location::home().to_string()).unwrap();
+        let (items, gold) = home.pick_up(&mut Game::new());
+        assert_eq!(50, gold);
+        assert_eq!(Some(&1), items.get(&Key::TreasureMap));
+        let tomb = game.tombstones.get_mut(&game.location.to_string()).unwrap();
+        let (items, gold) = tomb.pick_up(&mut Game::new());
+        assert_eq!(50, gold);
+        assert_eq!(Some(&1), items.get(&Key::TreasureMap));
+
+        // uninsured heroes need to go back to their tomb
+        game.reset();
+        game.gold = 100;
+        assert!(game.battle(&mut enemy, false, false, None).is_err());
+        assert_eq!(1, game.quests.list().len());
+    }
+
+    #[test]
+    fn multiple_tombs() {
+        let mut game = Game::new();
+        game.quests = QuestList::default();
+        let enemy_class = class::Class::random(class::Category::Common);
+        let mut enemy = character::Character::new(enemy_class.clone(), 10);
+
+        // each death leaves a tomb where it happened, merged with the
+        // previous one only if at the same location
+        for (path, gold) in &[("/", 100), ("/tmp", 20), ("/", 5)] {
+            game.reset();
+            game.location = Location::from(path).unwrap();
+            game.gold = *gold;
+            assert!(game.battle(&mut enemy, false, false, None).is_err());
+        }
+        game.reset();
+
+        let tombs = game.tombs();
+        assert_eq!(2, tombs.len());
+        assert_eq!("/", tombs[0].0);
+        assert!(tombs[0].1.contains(&String::from("105g")));
+        assert!(tombs[1].1.contains(&String::from("20g")));
+    }
+
+    #[test]
+    fn keep_prestige() {
+        let mut game = Game::new();
+        game.player.prestige = 2;
+        game.reset();
+        assert_eq!(2, game.player.prestige);
+        assert_eq!(game.player.max_hp(), game.player.current_hp);
+
+        game.reset_hardcore();
+        assert_eq!(2, game.player.prestige);
+
+        game.final_boss_beaten = true;
+        game.new_game_plus().unwrap();
+        assert_eq!(2, game.player.prestige);
+    }
+
+    #[test]
+    fn keep_manual_growth() {
+        let mut game = Game::new();
+        game.player.manual_growth = true;
+        game.reset();
+        assert!(game.player.manual_growth);
+
+        game.reset_hardcore();
+        assert!(game.player.manual_growth);
+
+        game.final_boss_beaten = true;
+        game.new_game_plus().unwrap();
+        assert!(game.player.manual_growth);
+    }
+
+    #[test]
+    fn rest() {
+        let mut game = Game::new();
+        game.player.current_hp = 1;
+        assert!(game.rest().unwrap());
+        assert_eq!(game.player.max_hp(), game.player.current_hp);
+
+        // away from home it costs gold
+        game.location = Location::from("/").unwrap();
+        let cost = game.rest_cost();
+        assert!(cost > 0);
+        game.player.current_hp = 1;
+        game.gold = cost - 1;
+        assert!(!game.rest().unwrap());
+        assert_eq!(1, game.player.current_hp);
+
+        game.gold = cost;
+        assert!(game.rest().unwrap());
+        assert_eq!(0, game.gold);
+        assert_eq!(game.player.max_hp(), game.player.current_hp);
+    }
+
+    #[test]
+    fn change_class() {
+        let mut game = Game::new();
+        assert!(game.change_class("choripan").is_err());
+
+        // free at level 1
+        game.change_class("thief").unwrap();
+        assert_eq!("thief", game.player.class.name);
+
+        game.player.raise_level();
+        assert!(game.change_class("warrior").is_err());
+        game.add_item(Box::new(item::stone::Level));
+        assert!(game.change_class("warrior").is_err());
+
+        game.gold = 2 * CLASS_CHANGE_COST + 10;
+        game.change_class("warrior").unwrap();
+        assert_eq!("warrior", game.player.class.name);
+        assert_eq!(10, game.gold);
+        assert!(game.inventory().is_empty());
+
+        // no cost to keep the same class
+        game.change_class("warrior").unwrap();
+        assert_eq!(10, game.gold);
+
+        game.location = Location::from("/").unwrap();
+        assert!(game.change_class("thief").is_err());
+    }
+
+    #[test]
+    fn stone_items() {
+        let mut game = Game::new();
+        game.add_item(Box::new(item::stone::Forget));
+        assert!(game.use_item(Key::ForgetStone).is_err());
+        game.add_item(Box::new(item::stone::Power));
+        game.use_item(Key::PowerStone).unwrap();
+        game.use_item(Key::ForgetStone).unwrap();
+        assert_eq!(1, game.player.stone_points);
+
+        // the test randomizer boosts the hp and lowers the next stat
+        let (max_hp, attack) = (game.player.max_hp(), game.player.physical_attack());
+        let hp_growth = game.player.class.hp.1;
+        game.add_item(Box::new(item::stone::Chaos));
+        game.use_item(Key::ChaosStone).unwrap();
+        assert_eq!(max_hp + 3 * hp_growth, game.player.max_hp());
+        assert!(game.player.physical_attack() < attack);
+
+        let attack = game.player.physical_attack();
+        game.player.left_ring = Some(Ring::Balance);
+        game.add_item(Box::new(item::stone::Chaos));
+        game.use_item(Key::ChaosStone).unwrap();
+        assert_eq!(max_hp + 6 * hp_growth, game.player.max_hp());
+        assert_eq!(attack, game.player.physical_attack());
+    }
+
+    #[test]
+    fn cursed_items() {
+        let mut game = Game::new();
+        game.quests = QuestList::default();
+        game.player = character::Character::new(game.player.class.clone(), 20);
+        let mut sword = item::equipment::Equipment::sword(20);
+        sword.curse();
+        game.player.sword = Some(sword);
+        game.player.cursed_rings.insert(Ring::Attack);
+        game.add_item(Box::new(Ring::Attack));
+        game.use_item(Key::Ring(Ring::Attack)).unwrap();
+        assert_eq!(2, game.player.curses());
+
+        // cursed rings can't be unequipped or pushed out by other rings
+        assert!(game.use_item(Key::Ring(Ring::Attack)).is_err());
+        game.player.equip_ring(Ring::Void);
+        game.player.equip_ring(Ring::Speed);
+        assert_eq!(Some(Ring::Attack), game.player.right_ring);
+
+        // cursed items drain gold after battles
+        game.gold = 1000;
+        let mut enemy =
+            character::Character::new(class::Class::random(class::Category::Common).clone(), 1);
+        assert!(game.battle(&mut enemy, false, false, None).unwrap());
+        assert_eq!(1000 - 2 * 50, game.gold);
+
+        game.add_item(Box::new(item::Remedy::new()));
+        game.use_item(Key::Remedy).unwrap();
+        assert_eq!(0, game.player.curses());
+        assert!(!game.player.sword.as_ref().unwrap().is_cursed());
+        assert!(game.use_item(Key::Ring(Ring::Attack)).is_ok());
+    }
+
+    #[test]
+    fn buried_chest() {
+        let mut game = Game::new();
+        game.quests = QuestList::default();
+
+        game.add_item(Box::new(item::map::TreasureMap));
+        game.use_item(Key::TreasureMap).unwrap();
+        assert_eq!(1, game.buried_chests.len());
+
+        // dig up the chest at the current location instead
+        game.buried_chests.clear();
+        game.buried_chests.insert(game.location.clone());
+        let rings = game.ring_pool.len();
+        game.inspect(Pickup::All).unwrap();
+        assert!(game.buried_chests.is_empty());
+        assert!(game.gold > 0);
+        assert_eq!(4, game.inventory().values().sum::<usize>());
+        assert_eq!(rings - 1, game.ring_pool.len());
+
+        // it's only found once
+        let gold = game.gold;
+        game.inspect(Pickup::All).unwrap();
+        assert_eq!(gold, game.gold);
+    }
 }
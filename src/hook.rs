@@ -0,0 +1,346 @@
+use crate::achievement::Achievement;
+use crate::character::Character;
+use crate::config::Config;
+use crate::game::Game;
+use crate::item::ring::Ring;
+use crate::log;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::io::Write;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// How long to wait for a reader to open a named pipe before giving up
+/// on the event, so the game doesn't hang when nobody is listening.
+const PIPE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Set while the events are not the player's own, so no hook runs for them.
+static MUTED: AtomicBool = AtomicBool::new(false);
+
+/// Run the given function without firing any hook or webhook.
+pub fn muted<T>(f: impl FnOnce() -> T) -> T {
+    let muted = MUTED.swap(true, Ordering::Relaxed);
+    let result = f();
+    MUTED.store(muted, Ordering::Relaxed);
+    result
+}
+
+/// Game events that hooks can be set to run on.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    LevelUp,
+    Death,
+    BossKill,
+    RingFound,
+    Achievement,
+}
+
+impl Event {
+    fn name(&self) -> &'static str {
+        match self {
+            Event::LevelUp => "level_up",
+            Event::Death => "death",
+            Event::BossKill => "boss_kill",
+            Event::RingFound => "ring_found",
+            Event::Achievement => "achievement",
+        }
+    }
+}
+
+/// A user action to run when a game event happens, set in the config file:
+///
+/// ```yaml
+/// hooks:
+///   - on: level_up
+///     command: notify-send "rpg" "$RPG_EVENT"
+///   - on: boss_kill
+///     pipe: /tmp/rpg-events
+/// ```
+///
+/// Commands run in the shell, with the event name and its JSON line in the
+/// `RPG_EVENT` and `RPG_EVENT_DATA` environment variables. Pipes get the
+/// JSON line written to them, and can also be regular files.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Hook {
+    pub on: Event,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub pipe: Option<String>,
+}
+
+impl Hook {
+    /// Run the hook actions for an event with the given JSON line. Failing
+    /// hooks are reported but don't interrupt the game.
+    fn run(&self, event: Event, line: &str) {
+        if let Some(command) = &self.command {
+            if let Err(error) = spawn(command, event, line) {
+                log::hook_failed("command", &error.to_string());
+            }
+        }
+        if let Some(pipe) = &self.pipe {
+            if let Err(error) = write_pipe(pipe, line) {
+                log::hook_failed("pipe", &error.to_string());
+            }
+        }
+    }
+}
+
+/// Notifications posted to a web service, e.g. a Discord channel, when
+/// notable events happen. Set in the config file:
+///
+/// ```yaml
+/// webhook:
+///   url: https://discord.com/api/webhooks/...
+///   format: discord
+/// ```
+///
+/// By default the event JSON line is posted on deaths, boss kills, rings
+/// found and achievements unlocked.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(default)]
+    pub format: Format,
+    #[serde(default = "Webhook::default_events")]
+    pub events: Vec<Event>,
+}
+
+/// The body of the webhook requests.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// The event JSON line, as written to pipes.
+    #[default]
+    Json,
+    /// A Discord message describing the event.
+    Discord,
+}
+
+impl Webhook {
+    fn default_events() -> Vec<Event> {
+        vec![
+            Event::Death,
+            Event::BossKill,
+            Event::RingFound,
+            Event::Achievement,
+        ]
+    }
+
+    /// Post the event with curl, in the background so the game isn't slowed
+    /// down by the request. Errors, e.g. when offline or if curl is missing,
+    /// are ignored.
+    fn post(&self, event: Event, data: &Value) {
+        let body = match self.format {
+            Format::Json => data.to_string(),
+            Format::Discord => json!({ "content": message(event, data) }).to_string(),
+        };
+        let child = Command::new("curl")
+            .args(["--silent", "--max-time", "10", "--request", "POST"])
+            .args(["--header", "Content-Type: application/json"])
+            .args(["--data-binary", &body, &self.url])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        if let Ok(child) = child {
+            reap(child);
+        }
+    }
+}
+
+/// A human readable description of the event.
+fn message(event: Event, data: &Value) -> String {
+    let text = |key: &str| data[key].as_str().unwrap_or_default().to_string();
+    match event {
+        Event::LevelUp => format!("{} reached level {}", text("class"), data["level"]),
+        Event::Death => format!(
+            "{}[{}] died at {}",
+            text("class"),
+            data["level"],
+            text("location")
+        ),
+        Event::BossKill => format!(
+            "{}[{}] was beaten at {}",
+            text("enemy"),
+            data["level"],
+            text("location")
+        ),
+        Event::RingFound => format!("{} found", text("ring")),
+        Event::Achievement => format!("achievement unlocked: {}", text("achievement")),
+    }
+}
+
+/// Start the command without waiting for it, so slow notifications don't
+/// hold the game back. Its output is discarded to keep the game's clean.
+fn spawn(command: &str, event: Event, line: &str) -> std::io::Result<()> {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let child = Command::new(shell)
+        .args([flag, command])
+        .env("RPG_EVENT", event.name())
+        .env("RPG_EVENT_DATA", line)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    reap(child);
+    Ok(())
+}
+
+/// Wait for the child process in a separate thread, so it doesn't linger
+/// as a zombie after exiting while the game keeps running, e.g. in the
+/// daemon. The thread is abandoned if the game exits first.
+fn reap(mut child: Child) {
+    std::thread::spawn(move || {
+        let _ = child.wait();
+    });
+}
+
+/// Append the line to the given pipe. Opening a named pipe blocks until
+/// there's a reader, so it's done in a separate thread that's abandoned if
+/// it takes too long.
+fn write_pipe(path: &str, line: &str) -> std::io::Result<()> {
+    let (sender, receiver) = mpsc::channel();
+    let path = path.to_string();
+    let line = format!("{}\n", line);
+    std::thread::spawn(move || {
+        let result = std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(line.as_bytes()));
+        let _ = sender.send(result);
+    });
+    match receiver.recv_timeout(PIPE_TIMEOUT) {
+        Ok(result) => result,
+        Err(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            "no reader on the pipe",
+        )),
+    }
+}
+
+/// Run the configured hooks for the event, passing the given data along
+/// with the event name.
+fn fire(event: Event, mut data: Value) {
+    if MUTED.load(Ordering::Relaxed) {
+        return;
+    }
+    let config = Config::get();
+    let hooks: Vec<&Hook> = config
+        .hooks
+        .iter()
+        .filter(|hook| hook.on == event)
+        .collect();
+    let webhook = config
+        .webhook
+        .as_ref()
+        .filter(|webhook| webhook.events.contains(&event));
+    if hooks.is_empty() && webhook.is_none() {
+        return;
+    }
+
+    data["event"] = json!(event.name());
+    let line = data.to_string();
+    for hook in hooks {
+        hook.run(event, &line);
+    }
+    if let Some(webhook) = webhook {
+        webhook.post(event, &data);
+    }
+}
+
+// EVENT TRIGGERING FUNCTIONS
+
+pub fn level_up(game: &Game) {
+    fire(
+        Event::LevelUp,
+        json!({"class": game.player.name(), "level": game.player.level}),
+    );
+}
+
+pub fn death(game: &Game) {
+    fire(
+        Event::Death,
+        json!({
+            "class": game.player.name(),
+            "level": game.player.level,
+            "location": game.location.to_string(),
+        }),
+    );
+}
+
+pub fn boss_kill(game: &Game, boss: &Character) {
+    fire(
+        Event::BossKill,
+        json!({
+            "enemy": boss.name(),
+            "level": boss.level,
+            "location": game.location.to_string(),
+        }),
+    );
+}
+
+pub fn ring_found(ring: &Ring) {
+    fire(Event::RingFound, json!({ "ring": ring.to_string() }));
+}
+
+pub fn achievement(achievement: &Achievement) {
+    fire(
+        Event::Achievement,
+        json!({ "achievement": achievement.description() }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_pipe() {
+        let path = std::env::temp_dir().join("rpg-hook-events");
+        let _ = std::fs::remove_file(&path);
+        let hook = Hook {
+            on: Event::Death,
+            command: None,
+            pipe: Some(path.to_string_lossy().to_string()),
+        };
+
+        hook.run(Event::Death, r#"{"event":"death"}"#);
+        hook.run(Event::Death, r#"{"event":"death"}"#);
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert_eq!("{\"event\":\"death\"}\n{\"event\":\"death\"}\n", written);
+    }
+
+    #[test]
+    fn test_webhook_message() {
+        let data = json!({"class": "warrior", "level": 5, "location": "~/dev"});
+        assert_eq!("warrior[5] died at ~/dev", message(Event::Death, &data));
+
+        let data = json!({"enemy": "dragon", "level": 10, "location": "~/a"});
+        assert_eq!(
+            "dragon[10] was beaten at ~/a",
+            message(Event::BossKill, &data)
+        );
+
+        let data = json!({ "achievement": "beat a boss" });
+        assert_eq!(
+            "achievement unlocked: beat a boss",
+            message(Event::Achievement, &data)
+        );
+
+        let webhook: Webhook = serde_yaml::from_str("url: http://localhost").unwrap();
+        assert_eq!(Format::Json, webhook.format);
+        assert!(webhook.events.contains(&Event::RingFound));
+        assert!(!webhook.events.contains(&Event::LevelUp));
+    }
+}
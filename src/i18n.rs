@@ -0,0 +1,207 @@
+use anyhow::{anyhow, bail, Result};
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The languages shipped with the game, by locale code.
+const BUILTIN: [(&str, &str); 2] = [
+    ("en", include_str!("i18n/en.yaml")),
+    ("es", include_str!("i18n/es.yaml")),
+];
+
+static CATALOG: OnceCell<Catalog> = OnceCell::new();
+
+/// The game text in some language, loaded from a yaml file:
+///
+/// ```yaml
+/// messages:
+///   rested: "{gold} rested"
+/// names:
+///   potion: potion
+/// ```
+///
+/// Messages are looked up by id and can have `{placeholders}` for the
+/// values shown in them. Names are the translations of the item, class,
+/// spell and status effect names. Error messages aren't in the catalog and
+/// are always shown in English.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Catalog {
+    messages: HashMap<String, String>,
+    names: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// The catalog of the given locale, with the English text for anything
+    /// missing in it. A user translation file, if any, is laid over the
+    /// built-in one, or can add a language of its own.
+    pub fn new(locale: &str, user: Option<&[u8]>) -> Result<Self> {
+        let builtin = BUILTIN.iter().find(|(code, _)| *code == language(locale));
+        if builtin.is_none() && user.is_none() {
+            bail!("Unknown locale {}", locale);
+        }
+
+        let mut catalog = Self::parse(BUILTIN[0].1.as_bytes())?;
+        if let Some((_, source)) = builtin {
+            catalog.extend(Self::parse(source.as_bytes())?);
+        }
+        if let Some(bytes) = user {
+            catalog.extend(Self::parse(bytes)?);
+        }
+        Ok(catalog)
+    }
+
+    fn parse(bytes: &[u8]) -> Result<Self> {
+        serde_yaml::from_slice(bytes).map_err(|e| anyhow!("Invalid translation file: {}", e))
+    }
+
+    fn extend(&mut self, other: Self) {
+        self.messages.extend(other.messages);
+        self.names.extend(other.names);
+    }
+
+    fn message(&self, id: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+        let text = self.messages.get(id).map_or(id, String::as_str);
+        fill(text, |placeholder| {
+            args.iter()
+                .find(|(key, _)| *key == placeholder)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    fn name(&self, text: &str) -> String {
+        text.split(' ')
+            .map(|word| {
+                let end = word.find(['[', '(']).unwrap_or(word.len());
+                match self.names.get(&word[..end]) {
+                    Some(name) => format!("{}{}", name, &word[end..]),
+                    None => word.to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn original(&self, name: &str) -> Option<&str> {
+        self.names
+            .iter()
+            .find(|(original, translated)| translated.as_str() == name && original.as_str() != name)
+            .map(|(original, _)| original.as_str())
+    }
+}
+
+/// The language part of locale codes like `es_AR.UTF-8`.
+fn language(locale: &str) -> &str {
+    locale.split(['_', '-', '.']).next().unwrap_or(locale)
+}
+
+/// Set the language of the game text. Without it, the text is in English.
+pub fn load(locale: &str, user: Option<&[u8]>) -> Result<()> {
+    let _ = CATALOG.set(Catalog::new(locale, user)?);
+    Ok(())
+}
+
+fn catalog() -> &'static Catalog {
+    CATALOG.get_or_init(|| Catalog::new("en", None).unwrap())
+}
+
+/// The message of the given id in the current language, with its
+/// placeholders replaced by the given values.
+pub fn t(id: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    catalog().message(id, args)
+}
+
+/// Translate the item, class, spell or status names in the given text,
+/// e.g. `cursed sword[5]`. Other words are left as is.
+pub fn name(text: &str) -> String {
+    catalog().name(text)
+}
+
+/// The English name of a translated one, so items can be referred to by
+/// the name they are shown with.
+pub fn original(name: &str) -> Option<String> {
+    catalog().original(name).map(String::from)
+}
+
+/// Replace the `{placeholders}` in the given text with the values for
+/// them. Placeholders without a value are left as is.
+pub fn fill(text: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut filled = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let placeholder = rest
+            .find('}')
+            .and_then(|end| value(&rest[1..end]).map(|value| (end, value)));
+        match placeholder {
+            Some((end, value)) => {
+                filled.push_str(&value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message() {
+        let catalog = Catalog::new("en", None).unwrap();
+        assert_eq!("10g rested", catalog.message("rested", &[("gold", &"10g")]));
+        assert_eq!("{gold} rested", catalog.message("rested", &[]));
+        assert_eq!("not a message", catalog.message("not a message", &[]));
+
+        let catalog = Catalog::new("es_AR.UTF-8", None).unwrap();
+        assert_eq!(
+            "10g descansado",
+            catalog.message("rested", &[("gold", &"10g")])
+        );
+        assert!(Catalog::new("xx", None).is_err());
+    }
+
+    #[test]
+    fn test_builtin_catalogs() {
+        let english = Catalog::parse(BUILTIN[0].1.as_bytes()).unwrap();
+        for (code, source) in BUILTIN.iter() {
+            let catalog = Catalog::parse(source.as_bytes()).unwrap();
+            for id in catalog.messages.keys() {
+                assert!(english.messages.contains_key(id), "{}: {}", code, id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_name() {
+        let catalog = Catalog::new("es", None).unwrap();
+        assert_eq!("poción", catalog.name("potion"));
+        assert_eq!("maldita espada[5]", catalog.name("cursed sword[5]"));
+        assert_eq!("guerrero", catalog.name("warrior"));
+        assert_eq!("~/dev", catalog.name("~/dev"));
+        assert_eq!(Some("potion"), catalog.original("poción"));
+        assert_eq!(None, catalog.original("potion"));
+    }
+
+    #[test]
+    fn test_user_catalog() {
+        let user = b"messages:\n  rested: \"{gold} zzz\"\nnames:\n  potion: pocion";
+        let catalog = Catalog::new("es", Some(user)).unwrap();
+        assert_eq!("5g zzz", catalog.message("rested", &[("gold", &"5g")]));
+        assert_eq!("pocion", catalog.name("potion"));
+        assert_eq!("éter", catalog.name("ether"));
+
+        // user files can add new languages
+        let catalog = Catalog::new("xx", Some(user)).unwrap();
+        assert_eq!("dodged!", catalog.message("dodged", &[]));
+        assert!(Catalog::new("xx", Some(b"nouns: {}")).is_err());
+    }
+}
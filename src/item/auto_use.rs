@@ -0,0 +1,177 @@
+use super::key::Key;
+use crate::character::{Character, StatusEffect};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Condition that makes the hero use an item on their own during battles.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    /// The hp is below the given percentage of the max hp.
+    Hp(i32),
+    /// The mp is below the given percentage of the max mp.
+    Mp(i32),
+    /// The mp isn't enough for a magic attack.
+    NoMp,
+    /// The hero suffers the given status effect.
+    Status(StatusEffect),
+}
+
+impl Trigger {
+    /// Parse a trigger like `hp<30`, `mp<20`, `no-mp` or `poison`.
+    pub fn from(name: &str) -> Result<Self> {
+        let name = name.to_lowercase();
+        let percent = |value: &str| match value.parse::<i32>() {
+            Ok(percent) if percent > 0 && percent <= 100 => Ok(percent),
+            _ => bail!("{} is not a valid percentage", value),
+        };
+        let trigger = if let Some(value) = name.strip_prefix("hp<") {
+            Trigger::Hp(percent(value)?)
+        } else if let Some(value) = name.strip_prefix("mp<") {
+            Trigger::Mp(percent(value)?)
+        } else {
+            match name.as_str() {
+                "no-mp" => Trigger::NoMp,
+                "burn" => Trigger::Status(StatusEffect::Burn),
+                "poison" => Trigger::Status(StatusEffect::Poison),
+                other => bail!("condition {} not found", other),
+            }
+        };
+        Ok(trigger)
+    }
+
+    fn is_met(&self, player: &Character) -> bool {
+        match self {
+            Trigger::Hp(percent) => player.current_hp * 100 < player.max_hp() * percent,
+            Trigger::Mp(percent) => {
+                player.max_mp() > 0 && player.current_mp * 100 < player.max_mp() * percent
+            }
+            Trigger::NoMp => player.class.is_magic() && !player.can_magic_attack(),
+            Trigger::Status(status) => player.status_effects.has(*status),
+        }
+    }
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::Hp(percent) => write!(f, "hp<{}", percent),
+            Trigger::Mp(percent) => write!(f, "mp<{}", percent),
+            Trigger::NoMp => write!(f, "no-mp"),
+            Trigger::Status(StatusEffect::Burn) => write!(f, "burn"),
+            Trigger::Status(StatusEffect::Poison) => write!(f, "poison"),
+            Trigger::Status(status) => write!(f, "{:?}", status),
+        }
+    }
+}
+
+/// The rules for using items automatically during battles, set by the
+/// player and kept across heroes. They're checked in order, and at most
+/// one item is used per turn.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct AutoUse {
+    rules: Vec<(Key, Trigger)>,
+}
+
+impl Default for AutoUse {
+    /// Drink a potion when low on hp, and an ether when out of mp.
+    fn default() -> Self {
+        Self {
+            rules: vec![(Key::Potion, Trigger::Hp(34)), (Key::Ether, Trigger::NoMp)],
+        }
+    }
+}
+
+impl AutoUse {
+    pub fn rules(&self) -> &[(Key, Trigger)] {
+        &self.rules
+    }
+
+    /// Set a rule from an `<item>=<condition>` string, replacing the
+    /// current one for that item. The `off` condition removes the rule.
+    pub fn set(&mut self, rule: &str) -> Result<()> {
+        let (item, condition) = match rule.split_once('=') {
+            Some(parts) => parts,
+            None => bail!("{} is not an <item>=<condition> rule", rule),
+        };
+        let key = Key::from(item)?;
+        if !matches!(
+            key,
+            Key::Potion
+                | Key::MidPotion
+                | Key::HiPotion
+                | Key::Ether
+                | Key::MidEther
+                | Key::HiEther
+                | Key::Elixir
+                | Key::Remedy
+                | Key::Throwable(_)
+        ) {
+            bail!("{} can't be used automatically", key);
+        }
+
+        if condition == "off" {
+            self.rules.retain(|(item, _)| *item != key);
+            return Ok(());
+        }
+        let trigger = Trigger::from(condition)?;
+        match self.rules.iter_mut().find(|(item, _)| *item == key) {
+            Some(rule) => rule.1 = trigger,
+            None => self.rules.push((key, trigger)),
+        }
+        Ok(())
+    }
+
+    /// The items whose rule is triggered by the state of the player, in
+    /// the order they should be tried.
+    pub fn triggered(&self, player: &Character) -> Vec<Key> {
+        self.rules
+            .iter()
+            .filter(|(_, trigger)| trigger.is_met(player))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rules() {
+        for name in &["hp<30", "mp<5", "no-mp", "burn", "poison"] {
+            assert_eq!(*name, Trigger::from(name).unwrap().to_string());
+        }
+        assert!(Trigger::from("hp<0").is_err());
+        assert!(Trigger::from("hp<101").is_err());
+        assert!(Trigger::from("stun").is_err());
+
+        let mut auto_use = AutoUse::default();
+        assert!(auto_use.set("potion").is_err());
+        assert!(auto_use.set("escape=hp<10").is_err());
+        assert!(auto_use.set("nectar=hp<10").is_err());
+
+        auto_use.set("remedy=poison").unwrap();
+        auto_use.set("potion=hp<50").unwrap();
+        auto_use.set("ether=off").unwrap();
+        let expected = vec![
+            (Key::Potion, Trigger::Hp(50)),
+            (Key::Remedy, Trigger::Status(StatusEffect::Poison)),
+        ];
+        assert_eq!(expected, auto_use.rules());
+    }
+
+    #[test]
+    fn triggered_rules() {
+        let mut auto_use = AutoUse::default();
+        auto_use.set("remedy=poison").unwrap();
+        let mut player = Character::player();
+        assert!(auto_use.triggered(&player).is_empty());
+
+        player.current_hp = player.max_hp() / 4;
+        player.status_effects.inflict(StatusEffect::Poison);
+        assert_eq!(vec![Key::Potion, Key::Remedy], auto_use.triggered(&player));
+    }
+}
@@ -0,0 +1,79 @@
+use super::key::Key;
+use super::{Ether, Item, Potion, Tier};
+use crate::game::Game;
+use crate::log;
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+
+/// How many items of a tier are brewed into one of the tier above.
+pub const BREW_COST: usize = 3;
+
+/// Consume three potions or ethers of the tier below the given one to brew
+/// a new one, of the hero's level.
+pub fn brew(game: &mut Game, key: &Key) -> Result<()> {
+    let level = game.player.rounded_level();
+    let (ingredient, brewed): (Key, Box<dyn Item>) = match key {
+        Key::MidPotion => (Key::Potion, Box::new(Potion::tiered(level, Tier::Mid))),
+        Key::HiPotion => (Key::MidPotion, Box::new(Potion::tiered(level, Tier::Hi))),
+        Key::MidEther => (Key::Ether, Box::new(Ether::tiered(level, Tier::Mid))),
+        Key::HiEther => (Key::MidEther, Box::new(Ether::tiered(level, Tier::Hi))),
+        _ => bail!("{} can't be brewed.", key),
+    };
+    if game.inventory().get(&ingredient).copied().unwrap_or(0) < BREW_COST {
+        bail!("Brewing a {} takes {} {}s.", key, BREW_COST, ingredient);
+    }
+
+    for _ in 0..BREW_COST {
+        game.remove_item(&ingredient);
+    }
+    let brewed_name = brewed.to_string();
+    game.add_item(brewed);
+
+    let consumed = HashMap::from([(ingredient, BREW_COST as i32)]);
+    log::craft(&consumed, &brewed_name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn brew_tiers() {
+        let mut game = Game::new();
+        for _ in 0..2 {
+            game.add_item(Box::new(Potion::new(1)));
+        }
+        assert!(brew(&mut game, &Key::MidPotion).is_err());
+        assert!(brew(&mut game, &Key::Potion).is_err());
+        assert_eq!(2, *game.inventory().get(&Key::Potion).unwrap());
+
+        game.add_item(Box::new(Potion::new(1)));
+        assert!(brew(&mut game, &Key::MidPotion).is_ok());
+        assert!(!game.inventory().contains_key(&Key::Potion));
+        assert_eq!(1, *game.inventory().get(&Key::MidPotion).unwrap());
+
+        // the top tier is brewed from the middle one
+        assert!(brew(&mut game, &Key::HiPotion).is_err());
+        for _ in 0..2 {
+            game.add_item(Box::new(Potion::tiered(1, Tier::Mid)));
+        }
+        assert!(brew(&mut game, &Key::HiPotion).is_ok());
+        assert_eq!(1, game.inventory().len());
+        assert_eq!(1, *game.inventory().get(&Key::HiPotion).unwrap());
+    }
+
+    #[test]
+    fn tiers_restore_more() {
+        let mut game = Game::new();
+        let max_hp = game.player.max_hp();
+        game.player.current_hp = 1;
+        Potion::new(1).apply(&mut game);
+        let low = game.player.current_hp - 1;
+
+        game.player.current_hp = 1;
+        Potion::tiered(1, Tier::Mid).apply(&mut game);
+        assert_eq!(std::cmp::min(max_hp, 1 + 2 * low), game.player.current_hp);
+        assert_eq!("mid-potion[1]", Potion::tiered(1, Tier::Mid).to_string());
+    }
+}
@@ -1,15 +1,29 @@
+use super::drops::Drops;
 use super::equipment::Equipment;
 use super::key::Key;
+use super::map::TreasureMap;
 use super::ring;
-use super::stone;
-use super::{Escape, Ether, Item, Potion, Remedy};
+use super::Item;
+use crate::character::enemy;
+use crate::character::skill::Skill;
+use crate::character::{Character, StatusEffect};
+use crate::clock::Weather;
 use crate::game;
-use crate::randomizer::random;
-use crate::randomizer::Randomizer;
-use rand::prelude::{IteratorRandom, SliceRandom};
+use crate::hook;
+use crate::location;
+use crate::log;
+use crate::plugin;
+use crate::randomizer::{choose, choose_weighted, random};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// How many times a beaten enemy may drop an item.
+const LOOT_ITEM_ROLLS: usize = 3;
+
+/// Directories farther from home that chests are generated as if found, for
+/// each prestige rank of the hero, so they keep up with stronger enemies.
+const PRESTIGE_DISTANCE: i32 = 5;
+
 /// A chest is a bag of items that can be picked up by the hero.
 /// It can randomly appear at a location upon inspection, or dropped
 /// by the hero when they die.
@@ -18,12 +32,107 @@ pub struct Chest {
     items: Vec<Box<dyn Item>>,
     sword: Option<Equipment>,
     shield: Option<Equipment>,
+    #[serde(default)]
+    helmet: Option<Equipment>,
+    #[serde(default)]
+    armor: Option<Equipment>,
+    #[serde(default)]
+    boots: Option<Equipment>,
     gold: i32,
+
+    /// Mimic chests are monsters that need to be beaten before picking up
+    /// their contents.
+    #[serde(default)]
+    mimic: bool,
+
+    /// Guarded chests can only be picked up after beating the enemy
+    /// protecting them, and stay at their location otherwise.
+    #[serde(default)]
+    guarded: bool,
+
+    /// Trapped chests spring their trap on the hero when picked up,
+    /// unless it's avoided or disarmed first.
+    #[serde(default)]
+    trap: Option<Trap>,
+
+    /// Whether the gold is a jackpot, celebrated when picked up.
+    #[serde(default)]
+    jackpot: bool,
+
+    /// Whether the hero already knows what the mimic guards.
+    #[serde(default)]
+    appraised: bool,
+}
+
+/// The harm a chest trap does to the hero when sprung.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Trap {
+    /// Deals a fifth of the max hp as damage, without killing the hero.
+    Damage,
+    /// Steals a tenth of the hero's gold.
+    Gold,
+    Poison,
+}
+
+impl Trap {
+    fn random() -> Self {
+        *choose(&[Trap::Damage, Trap::Gold, Trap::Poison]).unwrap()
+    }
+
+    fn spring(&self, game: &mut game::Game) {
+        let effect = match self {
+            Trap::Damage => {
+                let player = &mut game.player;
+                let damage = std::cmp::min(player.max_hp() / 5, player.current_hp - 1);
+                let damage = std::cmp::max(0, damage);
+                player.current_hp -= damage;
+                format!("-{}hp", damage)
+            }
+            Trap::Gold => {
+                let lost = game.gold / 10;
+                game.gold -= lost;
+                format!("-{}g", lost)
+            }
+            Trap::Poison => {
+                game.player.status_effects.inflict(StatusEffect::Poison);
+                String::from("poisoned")
+            }
+        };
+        log::chest_trap(&game.player, &effect);
+    }
 }
 
 impl Chest {
-    /// Randomly generate a chest at the current location.
+    /// Randomly generate a chest at the current location, unless chests are
+    /// disabled there. Dangerous directories have chests as found farther
+    /// from home.
     pub fn generate(game: &mut game::Game) -> Option<Self> {
+        if game.location.is_ignored() {
+            return None;
+        }
+        let distance = location::Distance::from(found_distance(game));
+        Self::generate_at(game, &distance)
+    }
+
+    /// Generate a bonus chest, as it would be found the given distance
+    /// farther from home than the current location. Unlike the random ones,
+    /// it always has gold and a piece of equipment, and it's never a mimic
+    /// nor trapped.
+    pub fn generate_boosted(game: &mut game::Game, boost: i32) -> Self {
+        let distance = location::Distance::from(found_distance(game) + boost);
+        let mut chest = Self::generate_at(game, &distance).unwrap_or_default();
+        chest.mimic = false;
+        chest.trap = None;
+        let gold = game.player.gold_gained(game.player.level + distance.len());
+        chest.gold = std::cmp::max(chest.gold, gold);
+        chest.put(random_equipment(distance.len()));
+        chest
+    }
+
+    /// Randomly generate a chest as it would be found at the given distance
+    /// from home, regardless of the current location.
+    pub fn generate_at(game: &mut game::Game, distance: &location::Distance) -> Option<Self> {
         // if the evade ring is equipped, don't generate chests
         // otherwise player can go arbitrarily deep and break the game
         // by finding all treasure contents
@@ -31,10 +140,8 @@ impl Chest {
             return None;
         }
 
-        let distance = &game.location.distance_from_home();
-
-        // don't reward cheap victories
-        if game.player.level > distance.len() + 10 {
+        let (overleveled, kept) = scarcity(game, distance);
+        if random().chest_lost(100 - kept) {
             return None;
         }
 
@@ -43,26 +150,48 @@ impl Chest {
         // chest at the end
         let mut gold_chest = random().gold_chest(distance);
         let mut equipment_chest = random().equipment_chest(distance);
-        let mut ring_chest = random().ring_chest(distance);
+        let mut ring_chest = overleveled <= 0 && random().ring_chest(distance);
         let mut item_chest_attempts = 3;
 
-        // If the chest ring is equipped, double the likelyhood of finding a chest
-        if game.player.double_chests() {
+        // If the chest ring is equipped, double the likelyhood of finding a chest,
+        // or triple it when worn along with the gold ring.
+        // The treasure hunter skill adds to that
+        let mut extra_rolls = if game.player.set_bonus() == Some(ring::SetBonus::Treasure) {
+            2
+        } else if game.player.double_chests() {
+            1
+        } else {
+            0
+        };
+        if game.player.has_skill(Skill::TreasureHunter) {
+            extra_rolls += 1;
+        }
+        extra_rolls += game.difficulty.extra_chest_rolls();
+
+        // rain washes gold up to the surface
+        if game.clock.weather == Weather::Rain {
+            gold_chest = gold_chest || random().gold_chest(distance);
+        }
+        for _ in 0..extra_rolls {
             gold_chest = gold_chest || random().gold_chest(distance);
             equipment_chest = equipment_chest || random().equipment_chest(distance);
-            ring_chest = ring_chest || random().ring_chest(distance);
-            item_chest_attempts *= 2;
+            ring_chest = ring_chest || (overleveled <= 0 && random().ring_chest(distance));
         }
+        item_chest_attempts *= 1 + extra_rolls;
 
         let mut chest = Self::default();
 
         if gold_chest {
-            chest.gold = game.player.gold_gained(game.player.level + distance.len());
+            // the gold ring makes the bigger payouts likelier
+            let gold_ring = game.player.gold_ring();
+            let tiers = &Drops::get().chests.gold_tiers;
+            let tier = choose_weighted(tiers, |tier| tier.weight(gold_ring)).unwrap();
+            let gold = game.player.base_gold(game.player.level + distance.len());
+            chest.gold = gold * tier.payout as i32 / 100 * kept as i32 / 100;
+            chest.jackpot = tier.jackpot && chest.gold > 0;
         }
         if equipment_chest {
-            let (sword, shield) = random_equipment(distance.len());
-            chest.sword = sword;
-            chest.shield = shield;
+            chest.put(random_equipment(distance.len()));
         }
 
         if ring_chest {
@@ -88,21 +217,132 @@ impl Chest {
             }
         }
 
+        let plugin_chest = plugin::chest_found(game, distance.len(), &mut chest);
+
         // Return None instead of an empty chest if none was found
-        if gold_chest || equipment_chest || item_chest || ring_chest {
+        if gold_chest || equipment_chest || item_chest || ring_chest || plugin_chest {
+            chest.mimic = random().mimic_chest();
+            chest.guarded = !chest.mimic && random().guarded_chest();
+            if random().trapped_chest() {
+                chest.trap = Some(Trap::random());
+            }
             Some(chest)
         } else {
             None
         }
     }
 
-    pub fn battle_loot(game: &mut game::Game) -> Option<Self> {
-        // reuse item % from chests, but don't add extra gold
-        // kind of hacky but does for now
-        Self::generate(game).map(|mut c| {
-            c.gold = 0;
-            c
-        })
+    /// Generate the loot dropped by a beaten enemy, if any, following the
+    /// loot table of its class, as found at the given distance from home.
+    /// Gilded enemies always drop a piece of equipment and an item.
+    pub fn battle_loot(
+        game: &mut game::Game,
+        enemy: &Character,
+        distance: &location::Distance,
+    ) -> Option<Self> {
+        let loot = Drops::get().loot(&enemy.class.name);
+        let level = game.player.rounded_level();
+        let mut chest = Self::default();
+
+        // enemies drop less where chests are scarce
+        let (overleveled, kept) = scarcity(game, distance);
+        if !game.location.is_ignored() && !random().chest_lost(100 - kept) {
+            if loot.equipment_dropped(distance) {
+                chest.put(random_equipment(distance.len()));
+            }
+            if overleveled <= 0 && loot.ring_dropped(distance) {
+                if let Some(ring) = random_ring(game) {
+                    chest.items.push(Box::new(ring));
+                }
+            }
+            for _ in 0..LOOT_ITEM_ROLLS {
+                if loot.item_dropped(distance) {
+                    chest.items.push(pick_item(loot.items(level)));
+                }
+            }
+        }
+        if random().treasure_map_drop() {
+            chest.items.push(Box::new(TreasureMap));
+        }
+        if enemy.gilded {
+            chest.put(random_equipment(distance.len()));
+            chest.items.push(pick_item(loot.items(level)));
+        }
+        if chest.is_empty() {
+            None
+        } else {
+            Some(chest)
+        }
+    }
+
+    /// Generate the chest that rewards completing a quest: the reward gold,
+    /// plus a random item for the bigger rewards.
+    pub fn reward(game: &game::Game, gold: i32) -> Self {
+        let mut chest = Self {
+            gold,
+            ..Self::default()
+        };
+        if gold >= 1000 {
+            chest.items.push(random_item(game.player.rounded_level()));
+        }
+        chest
+    }
+
+    /// Generate the chest a quest expects to be found at the current location.
+    /// Cursed chests are always mimics.
+    pub fn cursed(game: &game::Game) -> Self {
+        let distance = game.location.distance_from_home().len();
+        let mut chest = Self {
+            gold: game.player.gold_gained(game.player.level + distance),
+            mimic: true,
+            ..Self::default()
+        };
+        chest.put(random_equipment(distance));
+        chest
+    }
+
+    /// Generate the chest dug up at a location revealed by a treasure map:
+    /// triple gold, a piece of equipment above the location level, a few items
+    /// and a ring, if there are any left.
+    pub fn buried(game: &mut game::Game) -> Self {
+        let distance = game.location.distance_from_home().len();
+        let mut chest = Self {
+            gold: 3 * game.player.gold_gained(game.player.level + distance),
+            ..Self::default()
+        };
+        chest.put(random_equipment(distance + 5));
+        for _ in 0..3 {
+            chest.items.push(random_item(game.player.rounded_level()));
+        }
+        if let Some(ring) = random_ring(game) {
+            chest.items.push(Box::new(ring));
+        }
+        chest
+    }
+
+    /// Generate the loot of a beaten boss at the given distance from home:
+    /// plenty of gold, a high level piece of equipment, a couple of items,
+    /// one of the rare boss items, a treasure map and a ring, if there are
+    /// any left.
+    pub fn boss_loot(game: &mut game::Game, distance: &location::Distance) -> Self {
+        let distance = distance.len();
+        let mut chest = Self {
+            gold: 2 * game.player.gold_gained(game.player.level + distance),
+            ..Self::default()
+        };
+        chest.put(random_equipment(distance + 10));
+        for _ in 0..2 {
+            chest.items.push(random_item(game.player.rounded_level()));
+        }
+        let rare = Drops::get().boss_items(game.player.rounded_level());
+        if !rare.is_empty() {
+            chest.items.push(pick_item(rare));
+        }
+        chest.items.push(Box::new(TreasureMap));
+        if let Some(ring) = random_ring(game) {
+            chest.items.push(Box::new(ring));
+        }
+        chest
     }
 
     /// Remove the gold, items and equipment from a hero and return them as a new chest.
@@ -111,6 +351,9 @@ impl Chest {
         let mut items: Vec<Box<dyn Item>> = items.into_values().flatten().collect();
         let sword = game.player.sword.take();
         let shield = game.player.shield.take();
+        let helmet = game.player.helmet.take();
+        let armor = game.player.armor.take();
+        let boots = game.player.boots.take();
 
         // equipped rings should be dropped as items
         if let Some(ring) = game.player.left_ring.take() {
@@ -119,6 +362,10 @@ impl Chest {
         if let Some(ring) = game.player.right_ring.take() {
             items.push(Box::new(ring));
         }
+        // so is the item held by the active companion
+        if let Some(item) = game.companion.as_mut().and_then(|c| c.item.take()) {
+            items.push(item);
+        }
         let gold = game.gold;
 
         game.gold = 0;
@@ -127,40 +374,289 @@ impl Chest {
             items,
             sword,
             shield,
+            helmet,
+            armor,
+            boots,
             gold,
+            mimic: false,
+            guarded: false,
+            trap: None,
+            jackpot: false,
+            appraised: false,
+        }
+    }
+
+    /// The equipment slots of the chest, in the same order as the character's.
+    fn equipment_slots(&mut self) -> [&mut Option<Equipment>; 5] {
+        [
+            &mut self.sword,
+            &mut self.shield,
+            &mut self.helmet,
+            &mut self.armor,
+            &mut self.boots,
+        ]
+    }
+
+    /// The equipment pieces contained in the chest.
+    fn equipment(&self) -> Vec<&Equipment> {
+        [
+            &self.sword,
+            &self.shield,
+            &self.helmet,
+            &self.armor,
+            &self.boots,
+        ]
+        .iter()
+        .filter_map(|piece| piece.as_ref())
+        .collect()
+    }
+
+    /// Put the given piece in its equipment slot.
+    fn put(&mut self, equipment: Equipment) {
+        let slot = match equipment.key() {
+            Key::Sword => &mut self.sword,
+            Key::Shield => &mut self.shield,
+            Key::Helmet => &mut self.helmet,
+            Key::Armor => &mut self.armor,
+            Key::Boots => &mut self.boots,
+            key => unreachable!("{} is not an equipment piece", key),
+        };
+        slot.replace(equipment);
+    }
+
+    /// If this is a mimic chest, return the enemy that needs to be beaten
+    /// to pick it up, stronger the more valuable the chest contents.
+    /// The chest stays a mimic until `unguard` is called.
+    pub fn mimic(&self, location: &location::Location) -> Option<Character> {
+        self.mimic
+            .then(|| enemy::mimic(location, self.guard_level(location)))
+    }
+
+    /// If this is a guarded chest, return the enemy that needs to be beaten
+    /// to pick it up, stronger the more valuable the chest contents.
+    /// The chest stays guarded until `unguard` is called.
+    pub fn guard(&self, location: &location::Location) -> Option<Character> {
+        self.guarded
+            .then(|| enemy::guard(location, self.guard_level(location)))
+    }
+
+    /// Stop guarding the chest, once its mimic or guard was beaten.
+    pub fn unguard(&mut self) {
+        self.mimic = false;
+        self.guarded = false;
+    }
+
+    /// The level of the enemy protecting the chest at the given location,
+    /// based on the value of its contents.
+    fn guard_level(&self, location: &location::Location) -> i32 {
+        let equipment = self.equipment().iter().map(|e| e.level()).max();
+        let equipment = equipment.unwrap_or(0);
+        let value = equipment / 5 + self.items.len() as i32 + self.gold / 500;
+        let level = location.distance_from_home().len() + value;
+        std::cmp::max(1, level)
+    }
+
+    /// Whether the chest is guarded by a mimic the hero didn't appraise
+    /// yet. Either way, it counts as appraised afterwards.
+    pub fn appraise(&mut self) -> bool {
+        self.mimic && !std::mem::replace(&mut self.appraised, true)
+    }
+
+    pub fn is_trapped(&self) -> bool {
+        self.trap.is_some()
+    }
+
+    /// If the chest is trapped, spring the trap on the hero, unless they
+    /// notice it in time. Either way, the chest stops being trapped.
+    pub fn spring_trap(&mut self, game: &mut game::Game) {
+        if let Some(trap) = self.trap.take() {
+            if random().trap_avoided(game.player.trap_skill()) {
+                log::trap_disarmed(&game.player);
+            } else {
+                trap.spring(game);
+            }
         }
     }
 
+    /// Attempt to disarm the chest trap, springing it on failure.
+    /// Return whether the trap was disarmed.
+    pub fn disarm(&mut self, game: &mut game::Game) -> bool {
+        if let Some(trap) = self.trap.take() {
+            if random().disarm_succeeds(game.player.trap_skill()) {
+                log::trap_disarmed(&game.player);
+                return true;
+            }
+            trap.spring(game);
+        }
+        false
+    }
+
     /// Add the items of this chest to the current game/hero
     /// Return a picked up (item counts, gold) tuple.
     pub fn pick_up(&mut self, game: &mut game::Game) -> (HashMap<Key, i32>, i32) {
         let mut item_counts = HashMap::new();
 
-        // the equipment is picked up only if it's better than the current one
-        if maybe_upgrade(&mut game.player.sword, &mut self.sword) {
-            item_counts.insert(Key::Sword, 1);
-        }
-        if maybe_upgrade(&mut game.player.shield, &mut self.shield) {
-            item_counts.insert(Key::Shield, 1);
+        // the equipment is picked up only if it's better than the current one,
+        // and cursed pieces can't be replaced
+        let mut slots = game.player.equipment_slots();
+        for (current, other) in slots.iter_mut().zip(self.equipment_slots().iter_mut()) {
+            if current.as_ref().is_some_and(|piece| piece.is_cursed()) {
+                continue;
+            }
+            let key = other.as_ref().map(|e| e.key());
+            if maybe_upgrade(current, other) {
+                item_counts.insert(key.unwrap(), 1);
+            }
         }
 
-        // items and gold are always picked up
-        for item in self.items.drain(..) {
-            *item_counts.entry(item.key()).or_insert(0) += 1;
-            game.add_item(item);
+        // items are picked up while they fit in the inventory, the rest
+        // is left in the chest. Gold is always picked up
+        for item in std::mem::take(&mut self.items) {
+            if game.has_room_for(&item.key()) {
+                *item_counts.entry(item.key()).or_insert(0) += 1;
+                game.add_item(item);
+            } else {
+                self.items.push(item);
+            }
         }
 
         game.gold += self.gold;
-        (item_counts, self.gold)
+        (item_counts, std::mem::take(&mut self.gold))
+    }
+
+    /// Whether all the items of this chest fit in the inventory at once.
+    pub fn fits(&self, game: &game::Game) -> bool {
+        let keys: Vec<Key> = self.items.iter().map(|item| item.key()).collect();
+        game.has_room_for_all(&keys)
+    }
+
+    /// Move the items of this chest to a new one, leaving the gold and the
+    /// equipment.
+    pub fn take_items(&mut self) -> Self {
+        Self {
+            items: std::mem::take(&mut self.items),
+            ..Self::default()
+        }
+    }
+
+    /// A chest with just the items of this one, if it has any, e.g. the
+    /// ones that didn't fit in the inventory.
+    pub fn only_items(self) -> Option<Self> {
+        if self.items.is_empty() {
+            return None;
+        }
+        Some(Self {
+            items: self.items,
+            ..Self::default()
+        })
+    }
+
+    pub fn add_item(&mut self, item: Box<dyn Item>) {
+        self.items.push(item);
+    }
+
+    pub fn gold(&self) -> i32 {
+        self.gold
+    }
+
+    /// Whether the gold of the chest is a jackpot.
+    pub fn is_jackpot(&self) -> bool {
+        self.jackpot
+    }
+
+    pub fn add_gold(&mut self, gold: i32) {
+        self.gold += gold;
+    }
+
+    /// The keys of the items and equipment pieces in the chest, repeated
+    /// for each copy.
+    pub fn keys(&self) -> Vec<Key> {
+        let mut keys: Vec<Key> = self.items.iter().map(|item| item.key()).collect();
+        keys.extend(self.equipment().iter().map(|piece| piece.key()));
+        keys
+    }
+
+    /// Describe each element of the chest contents, in the order expected
+    /// by `take` to select a subset of them.
+    pub fn contents(&self) -> Vec<String> {
+        let mut contents: Vec<String> = self.items.iter().map(|i| i.to_string()).collect();
+        for piece in self.equipment() {
+            contents.push(piece.to_string());
+        }
+        if self.gold > 0 {
+            contents.push(format!("{}g", self.gold));
+        }
+        contents
+    }
+
+    /// Move the elements at the given indexes of the `contents` list
+    /// to a new chest, leaving the rest in this one.
+    pub fn take(&mut self, indexes: &[usize]) -> Self {
+        let mut taken = Self::default();
+
+        let mut index = 0;
+        for item in std::mem::take(&mut self.items) {
+            if indexes.contains(&index) {
+                taken.items.push(item);
+            } else {
+                self.items.push(item);
+            }
+            index += 1;
+        }
+
+        for slot in self.equipment_slots().iter_mut() {
+            if slot.is_some() {
+                if indexes.contains(&index) {
+                    taken.put(slot.take().unwrap());
+                }
+                index += 1;
+            }
+        }
+        if self.gold > 0 && indexes.contains(&index) {
+            taken.gold = std::mem::take(&mut self.gold);
+            taken.jackpot = std::mem::take(&mut self.jackpot);
+        }
+        taken
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+            && self.sword.is_none()
+            && self.shield.is_none()
+            && self.helmet.is_none()
+            && self.armor.is_none()
+            && self.boots.is_none()
+            && self.gold == 0
+    }
+
+    /// Move the given percentage of the gold and items of this chest to a
+    /// new one. Equipment is kept in this chest.
+    pub fn split(&mut self, percent: i32) -> Self {
+        let gold = self.gold * percent / 100;
+        self.gold -= gold;
+        let count = self.items.len() * percent as usize / 100;
+        let items = self.items.split_off(self.items.len() - count);
+        Self {
+            gold,
+            items,
+            ..Self::default()
+        }
     }
 
     /// Add the elements of `other` to this chest
     pub fn extend(&mut self, mut other: Self) {
         // keep the best of each equipment
-        maybe_upgrade(&mut self.sword, &mut other.sword);
-        maybe_upgrade(&mut self.shield, &mut other.shield);
+        let mut pieces = other.equipment_slots();
+        for (current, piece) in self.equipment_slots().iter_mut().zip(pieces.iter_mut()) {
+            maybe_upgrade(current, piece);
+        }
         self.items.extend(other.items.drain(..));
         self.gold += other.gold;
+        self.trap = self.trap.or(other.trap);
+        self.jackpot = self.jackpot || other.jackpot;
+        self.mimic = self.mimic || other.mimic;
+        self.guarded = self.guarded || other.guarded;
+        self.appraised = self.appraised || other.appraised;
     }
 }
 
@@ -176,57 +672,72 @@ fn maybe_upgrade(current: &mut Option<Equipment>, other: &mut Option<Equipment>)
     false
 }
 
-fn random_equipment(distance: i32) -> (Option<Equipment>, Option<Equipment>) {
-    let mut rng = rand::thread_rng();
+/// How far from home the chests found at the current location are
+/// generated as if found: farther in dangerous directories and for heroes
+/// of higher prestige ranks.
+fn found_distance(game: &game::Game) -> i32 {
+    game.location.distance_from_home().len()
+        + game.location.danger()
+        + game.player.prestige * PRESTIGE_DISTANCE
+}
 
-    let level = std::cmp::max(1, (distance / 5) * 5);
+/// How many levels the hero is above the gap tolerated by the difficulty at
+/// the given distance, and the percentage of chests still found there.
+/// Don't reward cheap victories: past that gap chests get scarcer, and
+/// harder games have fewer of them.
+fn scarcity(game: &game::Game, distance: &location::Distance) -> (i32, u32) {
+    let overleveled = game.player.level - distance.len() - game.difficulty.level_gap();
+    let kept = Drops::get().chests.overleveled.kept(overleveled);
+    (
+        overleveled,
+        kept * (100 - game.difficulty.chests_lost()) / 100,
+    )
+}
+
+/// Return a weighted random piece of equipment, rarely cursed.
+fn random_equipment(distance: i32) -> Equipment {
+    let mut piece = pick_equipment(distance);
+    if random().cursed_item() {
+        piece.curse();
+    }
+    piece
+}
 
-    vec![
-        (100, (Some(Equipment::sword(level)), None)),
-        (80, (None, Some(Equipment::shield(level)))),
-        (30, (Some(Equipment::sword(level + 5)), None)),
-        (20, (None, Some(Equipment::shield(level + 5)))),
-        (1, (Some(Equipment::sword(100)), None)),
-    ]
-    .choose_weighted_mut(&mut rng, |c| c.0)
-    .unwrap()
-    .to_owned()
-    .1
+/// Return a weighted random piece of equipment, as found at the given
+/// distance, never cursed.
+pub fn pick_equipment(distance: i32) -> Equipment {
+    let level = std::cmp::max(1, (distance / 5) * 5);
+    let choices = Drops::get().equipment(level);
+    choose_weighted(&choices, |c| c.0).unwrap().1.to_owned()
 }
 
 /// Return a weigthed random item.
-fn random_item(level: i32) -> Box<dyn Item> {
-    let mut choices: Vec<(i32, Box<dyn Item>)> = vec![
-        (150, Box::new(Potion::new(level))),
-        (10, Box::new(Remedy::new())),
-        (10, Box::new(Escape::new())),
-        (50, Box::new(Ether::new(level))),
-        (5, Box::new(stone::Health)),
-        (5, Box::new(stone::Magic)),
-        (5, Box::new(stone::Power)),
-        (5, Box::new(stone::Speed)),
-        (1, Box::new(stone::Level)),
-    ];
-
-    // make a separate vec with enumerated weights, then remove from the item vec
-    // with the resulting index
-    let indexed_weights: Vec<_> = choices.iter().map(|(w, _)| w).enumerate().collect();
-
-    let mut rng = rand::thread_rng();
-    let index = indexed_weights
-        .choose_weighted(&mut rng, |c| c.1)
-        .unwrap()
-        .0;
+pub fn random_item(level: i32) -> Box<dyn Item> {
+    pick_item(Drops::get().items(level))
+}
+
+/// Return one of the given items, picked by weight.
+fn pick_item(mut choices: Vec<(u32, Box<dyn Item>)>) -> Box<dyn Item> {
+    // pick the index from the weights, then remove the item from the vec
+    let weights: Vec<u32> = choices.iter().map(|(w, _)| *w).collect();
+    let index = random().pick_weighted(&weights);
     choices.remove(index).1
 }
 
-fn random_ring(game: &mut game::Game) -> Option<ring::Ring> {
-    let mut rng = rand::thread_rng();
-    if let Some(ring) = game.ring_pool.iter().choose(&mut rng).cloned() {
-        game.ring_pool.take(&ring)
-    } else {
-        None
+pub fn random_ring(game: &mut game::Game) -> Option<ring::Ring> {
+    // sort the pool since the set iteration order changes between runs,
+    // which would break seeded games
+    let mut rings: Vec<&ring::Ring> = game.ring_pool.iter().collect();
+    rings.sort_by_key(|ring| ring.to_string());
+
+    let ring = choose(&rings).cloned().cloned()?;
+
+    // only the rings that modify a stat can be cursed into a stronger version
+    if ring.is_upgradable() && random().cursed_item() {
+        game.player.cursed_rings.insert(ring.clone());
     }
+    hook::ring_found(&ring);
+    game.ring_pool.take(&ring)
 }
 
 impl Default for Chest {
@@ -235,7 +746,15 @@ impl Default for Chest {
             gold: 0,
             sword: None,
             shield: None,
+            helmet: None,
+            armor: None,
+            boots: None,
             items: Vec::new(),
+            mimic: false,
+            guarded: false,
+            trap: None,
+            jackpot: false,
+            appraised: false,
         }
     }
 }
@@ -244,7 +763,8 @@ impl Default for Chest {
 mod tests {
     use super::super::equipment::Equipment;
     use super::*;
-    use super::{Escape, Potion};
+    use crate::character::class::Class;
+    use crate::item::{Escape, Potion};
 
     #[test]
     fn test_empty_drop_pickup() {
@@ -272,6 +792,7 @@ mod tests {
         game.add_item(Box::new(Potion::new(1)));
         game.player.sword = Some(Equipment::sword(1));
         game.player.shield = Some(Equipment::shield(1));
+        game.player.shield.as_mut().unwrap().wear();
         game.gold = 100;
 
         let mut tomb = Chest::drop(&mut game);
@@ -288,6 +809,34 @@ mod tests {
         assert!(game.player.sword.is_some());
         assert!(game.player.shield.is_some());
         assert_eq!(2, *game.inventory().get(&Key::Potion).unwrap());
+
+        // the equipment wear is preserved
+        assert_eq!(1, game.player.shield.as_ref().unwrap().repair_cost());
+    }
+
+    #[test]
+    fn test_armor_drop_pickup() {
+        let mut game = game::Game::new();
+        game.player.helmet = Some(Equipment::helmet(5));
+        game.player.armor = Some(Equipment::armor(1));
+        game.player.boots = Some(Equipment::boots(1));
+
+        let mut tomb = Chest::drop(&mut game);
+        assert!(game.player.helmet.is_none());
+        assert!(game.player.armor.is_none());
+        assert!(game.player.boots.is_none());
+        assert_eq!(vec!["helmet[5]", "armor[1]", "boots[1]"], tomb.contents());
+
+        let mut game = game::Game::new();
+        game.player.helmet = Some(Equipment::helmet(10));
+        let (item_counts, _) = tomb.pick_up(&mut game);
+
+        // the helmet was a downgrade, kept the current one
+        assert_eq!(10, game.player.helmet.as_ref().unwrap().level());
+        assert!(!item_counts.contains_key(&Key::Helmet));
+        assert_eq!(1, game.player.armor.as_ref().unwrap().level());
+        assert_eq!(1, game.player.boots.as_ref().unwrap().level());
+        assert_eq!(Some(&1), item_counts.get(&Key::Armor));
     }
 
     #[test]
@@ -321,6 +870,23 @@ mod tests {
         assert_eq!(3, *game.inventory().get(&Key::Potion).unwrap());
     }
 
+    #[test]
+    fn test_cursed_pickup() {
+        let mut game = game::Game::new();
+        let mut cursed = Equipment::sword(1);
+        cursed.curse();
+        game.player.sword = Some(cursed);
+
+        let mut chest = Chest::default();
+        chest.put(Equipment::sword(10));
+        chest.put(Equipment::shield(10));
+        chest.pick_up(&mut game);
+
+        // the cursed sword can't be replaced
+        assert_eq!(1, game.player.sword.as_ref().unwrap().level());
+        assert_eq!(10, game.player.shield.as_ref().unwrap().level());
+    }
+
     #[test]
     fn test_merge() {
         let items: Vec<Box<dyn Item>> = vec![Box::new(Potion::new(1)), Box::new(Potion::new(1))];
@@ -329,6 +895,7 @@ mod tests {
             sword: Some(Equipment::sword(1)),
             shield: Some(Equipment::shield(10)),
             gold: 100,
+            ..Chest::default()
         };
 
         let items: Vec<Box<dyn Item>> = vec![Box::new(Potion::new(1)), Box::new(Escape::new())];
@@ -337,10 +904,14 @@ mod tests {
             sword: Some(Equipment::sword(10)),
             shield: Some(Equipment::shield(1)),
             gold: 100,
+            mimic: true,
+            ..Chest::default()
         };
 
         chest1.extend(chest2);
         assert_eq!(200, chest1.gold);
+        // the mimic isn't lost when merging chests
+        assert!(chest1.mimic);
         assert_eq!(10, chest1.sword.as_ref().unwrap().level());
         assert_eq!(10, chest1.shield.as_ref().unwrap().level());
         let item_keys = chest1.items.iter().map(|i| i.key()).collect::<Vec<_>>();
@@ -350,6 +921,156 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_mimic() {
+        let location = crate::location::tests::location_from("~/1/2/3");
+        let chest = Chest {
+            items: vec![Box::new(Potion::new(1)), Box::new(Potion::new(1))],
+            ..Chest::default()
+        };
+        assert!(chest.mimic(&location).is_none());
+
+        let mut chest = Chest {
+            mimic: true,
+            ..chest
+        };
+        let enemy = chest.mimic(&location).unwrap();
+        assert_eq!("mimic", enemy.name());
+        let base_level = enemy.level;
+
+        // only fought until beaten
+        assert!(chest.mimic(&location).is_some());
+        chest.unguard();
+        assert!(chest.mimic(&location).is_none());
+
+        // more valuable contents, stronger mimic
+        let chest = Chest {
+            sword: Some(Equipment::sword(10)),
+            gold: 1000,
+            mimic: true,
+            ..chest
+        };
+        assert_eq!(base_level + 4, chest.mimic(&location).unwrap().level);
+    }
+
+    #[test]
+    fn test_guarded() {
+        let mut game = game::Game::new();
+        let location = game.location.to_string();
+        let chest = Chest {
+            items: vec![Box::new(Potion::new(1))],
+            guarded: true,
+            ..Chest::default()
+        };
+        game.chests.insert(location.clone(), chest);
+
+        // losing to the guard leaves the chest in place, for the next hero
+        game.player.current_hp = 1;
+        assert!(game.inspect(game::Pickup::All).is_err());
+        game.reset();
+        assert!(game.chests[&location].guarded);
+        assert!(game.inventory().is_empty());
+
+        // beating it, the chest is picked up
+        game.player.sword = Some(Equipment::sword(100));
+        game.player.restore();
+        game.inspect(game::Pickup::All).unwrap();
+        assert!(!game.chests.contains_key(&location));
+        assert_eq!(Some(&1), game.inventory().get(&Key::Potion));
+    }
+
+    #[test]
+    fn test_appraise() {
+        let mut game = game::Game::new();
+        assert!(game.player.change_class("thief").is_ok());
+        game.player.skills.push(Skill::Appraise);
+        let location = game.location.to_string();
+        let chest = Chest {
+            items: vec![Box::new(Potion::new(1))],
+            mimic: true,
+            ..Chest::default()
+        };
+        game.chests.insert(location.clone(), chest);
+
+        // the mimic is left in place, unfought
+        game.inspect(game::Pickup::All).unwrap();
+        assert!(game.chests[&location].mimic);
+        assert!(game.inventory().is_empty());
+
+        // looking again means fighting for it
+        game.player.current_hp = 1;
+        assert!(game.inspect(game::Pickup::All).is_err());
+    }
+
+    #[test]
+    fn test_take_contents() {
+        let mut chest = Chest {
+            items: vec![Box::new(Potion::new(1)), Box::new(Escape::new())],
+            shield: Some(Equipment::shield(5)),
+            gold: 100,
+            ..Chest::default()
+        };
+        assert_eq!(
+            vec!["potion[1]", "escape", "shield[5]", "100g"],
+            chest.contents()
+        );
+
+        let taken = chest.take(&[1, 3]);
+        assert_eq!(vec!["escape", "100g"], taken.contents());
+        assert_eq!(vec!["potion[1]", "shield[5]"], chest.contents());
+
+        let taken = chest.take(&[0, 1]);
+        assert_eq!(vec!["potion[1]", "shield[5]"], taken.contents());
+        assert!(chest.is_empty());
+    }
+
+    #[test]
+    fn test_trap() {
+        let mut game = game::Game::new();
+        game.gold = 100;
+        let mut chest = Chest {
+            gold: 50,
+            trap: Some(Trap::Gold),
+            ..Chest::default()
+        };
+        chest.spring_trap(&mut game);
+        assert!(!chest.is_trapped());
+        assert_eq!(90, game.gold);
+
+        // sprung only once
+        chest.spring_trap(&mut game);
+        assert_eq!(90, game.gold);
+
+        // damage traps never kill the hero
+        game.player.current_hp = 1;
+        let mut chest = Chest {
+            trap: Some(Trap::Damage),
+            ..Chest::default()
+        };
+        chest.spring_trap(&mut game);
+        assert_eq!(1, game.player.current_hp);
+
+        // without trap skill, disarming fails and springs the trap
+        let mut chest = Chest {
+            trap: Some(Trap::Poison),
+            ..Chest::default()
+        };
+        assert!(!chest.disarm(&mut game));
+        assert!(!chest.is_trapped());
+        assert!(game.player.status_effects.has(StatusEffect::Poison));
+
+        // the trap ring helps disarming
+        game.player.status_effects = Default::default();
+        game.player.left_ring = Some(ring::Ring::Trap);
+        let mut chest = Chest {
+            trap: Some(Trap::Poison),
+            ..Chest::default()
+        };
+        assert!(chest.disarm(&mut game));
+        assert!(!chest.is_trapped());
+        assert!(!game.player.status_effects.has(StatusEffect::Poison));
+    }
+
     #[test]
     fn test_take_random_ring() {
         let mut game = game::Game::new();
@@ -389,4 +1110,177 @@ mod tests {
         assert!(game.inventory.contains_key(&Key::Ring(ring::Ring::Speed)));
         assert!(game.inventory.contains_key(&Key::Ring(ring::Ring::Magic)));
     }
+
+    #[test]
+    fn test_inventory_full() {
+        let mut game = game::Game::new();
+        game.quests = crate::quest::QuestList::default();
+        let slots = game.player.inventory_slots();
+        for _ in 0..slots * 10 - 1 {
+            game.add_item(Box::new(Potion::new(1)));
+        }
+        assert_eq!(slots, game.slots_used());
+        assert!(game.has_room_for(&Key::Potion));
+        assert!(!game.has_room_for(&Key::Escape));
+        assert!(game.has_room_for(&Key::Ring(ring::Ring::Void)));
+
+        // what doesn't fit is left at the location for later
+        let chest = Chest {
+            items: vec![Box::new(Potion::new(1)), Box::new(Escape::new())],
+            sword: Some(Equipment::sword(1)),
+            gold: 10,
+            ..Chest::default()
+        };
+        let location = game.location.to_string();
+        game.chests.insert(location.clone(), chest);
+        game.inspect(game::Pickup::All).unwrap();
+        assert_eq!(10, game.gold);
+        assert!(game.player.sword.is_some());
+        assert_eq!(vec!["escape"], game.chests[&location].contents());
+
+        // bags make room for more
+        game.add_item(Box::new(super::super::Bag::new()));
+        game.use_item(Key::Bag).unwrap();
+        assert_eq!(
+            slots + crate::character::BAG_SLOTS,
+            game.player.inventory_slots()
+        );
+        game.inspect(game::Pickup::All).unwrap();
+        assert_eq!(10, game.gold);
+        assert_eq!(Some(&1), game.inventory().get(&Key::Escape));
+        assert!(!game.chests.contains_key(&location));
+
+        game.player.bags = crate::character::MAX_BAGS;
+        game.add_item(Box::new(super::super::Bag::new()));
+        assert!(game.use_item(Key::Bag).is_err());
+    }
+
+    #[test]
+    fn test_inventory_full_asked() {
+        let mut game = game::Game::new();
+        game.quests = crate::quest::QuestList::default();
+        for _ in 0..game.player.inventory_slots() * 10 - 1 {
+            game.add_item(Box::new(Potion::new(1)));
+        }
+        assert!(game.has_room_for_all(&[Key::Potion, Key::Ring(ring::Ring::Void)]));
+        assert!(!game.has_room_for_all(&[Key::Potion, Key::Potion]));
+
+        // when not everything fits, all the items are left for the player to choose
+        let chest = Chest {
+            items: vec![Box::new(Potion::new(1)), Box::new(Escape::new())],
+            sword: Some(Equipment::sword(1)),
+            gold: 10,
+            ..Chest::default()
+        };
+        let location = game.location.to_string();
+        game.chests.insert(location.clone(), chest);
+        let potions = game.inventory()[&Key::Potion];
+        assert!(game.inspect(game::Pickup::AskIfFull).unwrap());
+        assert_eq!(10, game.gold);
+        assert!(game.player.sword.is_some());
+        assert_eq!(potions, game.inventory()[&Key::Potion]);
+        assert_eq!(vec!["potion[1]", "escape"], game.chest_contents());
+
+        game.take_from_chest(&[0]);
+        assert_eq!(potions + 1, game.inventory()[&Key::Potion]);
+        assert_eq!(vec!["escape"], game.chest_contents());
+
+        // when everything fits, nothing is asked
+        game.inventory.remove(&Key::Potion);
+        assert!(!game.inspect(game::Pickup::AskIfFull).unwrap());
+        assert_eq!(Some(&1), game.inventory().get(&Key::Escape));
+        assert!(game.chest_contents().is_empty());
+    }
+
+    #[test]
+    fn test_battle_loot() {
+        let mut game = game::Game::new();
+        let wolf = Class::enemy_by_name("wolf").unwrap().clone();
+        let sorcerer = Class::enemy_by_name("sorcerer").unwrap().clone();
+
+        let home = location::Distance::from(0);
+        let enemy = Character::new(wolf, 1);
+        assert!(Chest::battle_loot(&mut game, &enemy, &home).is_none());
+
+        // gilded enemies always drop equipment and one of their class items
+        let enemy = Character::new(sorcerer.clone(), 1).gild();
+        let loot = Chest::battle_loot(&mut game, &enemy, &home).unwrap();
+        assert!(loot.keys().contains(&Key::Ether));
+        assert!(loot.keys().contains(&Key::Sword));
+
+        // strong heroes only get loot from enemies found deep enough, like
+        // the ones of the dungeon floors
+        let drops = |distance| {
+            (0..50)
+                .filter(|seed| {
+                    crate::randomizer::with_seed(*seed, || {
+                        let mut game = game::Game::new();
+                        game.player.level = 30;
+                        let enemy = Character::new(sorcerer.clone(), 30);
+                        let distance = location::Distance::from(distance);
+                        Chest::battle_loot(&mut game, &enemy, &distance).is_some()
+                    })
+                })
+                .count()
+        };
+        assert!(drops(30) > drops(0));
+    }
+
+    #[test]
+    fn test_gold_tiers() {
+        // the gold ring makes jackpots likelier instead of doubling the gold
+        let jackpots = |gold_ring: bool| {
+            crate::randomizer::with_seed(5, || {
+                let mut game = game::Game::new();
+                if gold_ring {
+                    game.player.left_ring = Some(ring::Ring::Gold);
+                }
+                (0..2000)
+                    .filter_map(|_| Chest::generate_at(&mut game, &location::Distance::from(10)))
+                    .filter(|chest| chest.is_jackpot())
+                    .count()
+            })
+        };
+        let (without, with) = (jackpots(false), jackpots(true));
+        assert!(without > 0);
+        assert!(with > without);
+
+        // the jackpot goes along with the gold
+        let mut chest = Chest {
+            items: vec![Box::new(Potion::new(1))],
+            gold: 1000,
+            jackpot: true,
+            ..Chest::default()
+        };
+        assert!(!chest.take(&[0]).is_jackpot());
+        assert!(chest.take(&[0]).is_jackpot());
+        assert!(!chest.is_jackpot());
+    }
+
+    #[test]
+    fn test_generate_seeded() {
+        let generate = |seed| {
+            crate::randomizer::with_seed(seed, || {
+                let mut game = game::Game::new();
+                Chest::generate_at(&mut game, &location::Distance::from(4))
+                    .map(|chest| chest.contents())
+            })
+        };
+
+        let chests: Vec<_> = (0..100).map(generate).collect();
+        assert_eq!(chests[7], generate(7));
+        assert!(chests.iter().any(|chest| chest.is_none()));
+        assert!(chests.iter().any(|chest| chest.is_some()));
+        for contents in chests.iter().flatten() {
+            assert!(!contents.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_prestige_distance() {
+        let mut game = game::Game::new();
+        let distance = found_distance(&game);
+        game.player.prestige = 2;
+        assert_eq!(distance + 2 * PRESTIGE_DISTANCE, found_distance(&game));
+    }
 }
@@ -0,0 +1,42 @@
+use super::{key, Item};
+use crate::game;
+use crate::plugin;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// An item registered by a plugin, which handles its use.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Custom {
+    name: String,
+}
+
+impl Custom {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Custom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[typetag::serde]
+impl Item for Custom {
+    fn apply(&mut self, game: &mut game::Game) {
+        plugin::use_item(game, &self.name);
+    }
+
+    fn key(&self) -> key::Key {
+        key::Key::Custom(self.name.clone())
+    }
+
+    fn describe(&self) -> String {
+        plugin::item(&self.name).map_or(String::from("unknown item"), |item| {
+            item.description.clone()
+        })
+    }
+}
@@ -0,0 +1,597 @@
+use super::custom::Custom;
+use super::equipment::Equipment;
+use super::key::Key;
+use super::scroll::Scroll;
+use super::{stone, Bag, Elixir, Escape, Ether, Item, PhoenixDown, Potion, Remedy, Tier};
+use crate::character::element::Element;
+use crate::location::Distance;
+use crate::randomizer::random;
+use anyhow::{anyhow, bail, Result};
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// The weights and chances that determine what's found in chests,
+/// optionally customized with a drops.toml file at the rpg data directory.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Drops {
+    /// Relative weights of the items found in chests, by item name.
+    /// Sorted by name so seeded games pick the same items.
+    items: BTreeMap<String, u32>,
+
+    /// Relative weights of the rare items that bosses drop, one with each
+    /// boss loot. Other enemies never drop them, but chests may hold them.
+    #[serde(default)]
+    boss_items: BTreeMap<String, u32>,
+
+    /// Relative weights of the equipment pieces found in chests.
+    equipment: Vec<EquipmentDrop>,
+
+    pub chests: ChestOdds,
+
+    /// What beaten enemies drop, by class name. Classes without a table
+    /// drop as if they were chests.
+    #[serde(default)]
+    loot: BTreeMap<String, Loot>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct EquipmentDrop {
+    kind: Key,
+    weight: u32,
+
+    /// Levels above the base level of the location.
+    #[serde(default)]
+    bonus: i32,
+
+    /// Fixed level of the piece, regardless of the location.
+    level: Option<i32>,
+
+    /// Element of the piece, only for swords.
+    element: Option<Element>,
+}
+
+/// The chances of each kind of content being found in a chest.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ChestOdds {
+    pub gold: DistanceOdds,
+    pub equipment: DistanceOdds,
+    pub ring: DistanceOdds,
+    pub item: DistanceOdds,
+
+    /// The chances of meeting a wandering npc while looking for chests.
+    #[serde(default = "default_npc")]
+    pub npc: DistanceOdds,
+
+    pub mimic: Ratio,
+    #[serde(default = "default_guard")]
+    pub guard: Ratio,
+    pub trap: Ratio,
+
+    #[serde(default)]
+    pub overleveled: Overleveled,
+
+    /// The payouts of gold chests, from the most common to the jackpot.
+    #[serde(rename = "gold_tier", default = "default_gold_tiers")]
+    pub gold_tiers: Vec<GoldTier>,
+}
+
+/// A payout of gold chests.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct GoldTier {
+    /// Relative weight of the tier.
+    weight: u32,
+
+    /// Relative weight of the tier with the gold ring equipped.
+    ring_weight: u32,
+
+    /// The gold paid, as a percentage of the usual for the distance.
+    pub payout: u32,
+
+    /// Whether finding the tier is celebrated.
+    #[serde(default)]
+    pub jackpot: bool,
+}
+
+impl GoldTier {
+    pub fn weight(&self, gold_ring: bool) -> u32 {
+        if gold_ring {
+            self.ring_weight
+        } else {
+            self.weight
+        }
+    }
+}
+
+/// The chance of guarded chests in older drop files without it, same as the
+/// default.
+fn default_guard() -> Ratio {
+    Ratio(1, 15)
+}
+
+/// The npc chances of older drop files without them, same as the defaults.
+fn default_npc() -> DistanceOdds {
+    DistanceOdds {
+        near: Ratio(1, 20),
+        mid: Ratio(1, 15),
+        far: Ratio(1, 10),
+    }
+}
+
+/// The tiers of older drop files without them, same as the defaults.
+fn default_gold_tiers() -> Vec<GoldTier> {
+    let tier = |weight, ring_weight, payout, jackpot| GoldTier {
+        weight,
+        ring_weight,
+        payout,
+        jackpot,
+    };
+    vec![
+        tier(80, 50, 60, false),
+        tier(18, 40, 200, false),
+        tier(2, 10, 1000, true),
+    ]
+}
+
+/// How chests get scarcer once the hero is more levels above the distance
+/// from home than the difficulty tolerates.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Overleveled {
+    /// Percentage of the chances lost with each level above the gap.
+    decay: u32,
+
+    /// Lowest percentage of the chances kept, however big the gap.
+    min: u32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DistanceOdds {
+    near: Ratio,
+    mid: Ratio,
+    far: Ratio,
+}
+
+/// The loot table of an enemy class. The chances and items not given are
+/// those of chests at the distance from home.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Loot {
+    item: Option<Ratio>,
+    equipment: Option<Ratio>,
+    ring: Option<Ratio>,
+
+    /// Chance of dropping the material of the class, if it has one.
+    material: Option<Ratio>,
+
+    /// Relative weights of the items dropped, by item name.
+    #[serde(default)]
+    items: BTreeMap<String, u32>,
+
+    /// Range of the gold gained, as percentages of the usual for the level.
+    #[serde(default = "usual_gold")]
+    gold: (u32, u32),
+}
+
+fn usual_gold() -> (u32, u32) {
+    (100, 100)
+}
+
+/// The table of the classes that drop as chests do.
+static CHEST_LOOT: Loot = Loot {
+    item: None,
+    equipment: None,
+    ring: None,
+    material: None,
+    items: BTreeMap::new(),
+    gold: (100, 100),
+};
+
+/// A chance expressed as a (numerator, denominator) pair.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ratio(pub u32, pub u32);
+
+static DROPS: OnceCell<Drops> = OnceCell::new();
+
+impl Drops {
+    /// Customize the drop tables based on an input toml byte array.
+    pub fn load(bytes: &[u8]) -> Result<()> {
+        let drops = from_bytes(bytes).map_err(|e| anyhow!("Invalid drops file: {}", e))?;
+        DROPS.set(drops).unwrap();
+        Ok(())
+    }
+
+    pub fn get() -> &'static Self {
+        DROPS.get_or_init(|| from_bytes(include_bytes!("drops.toml")).unwrap())
+    }
+
+    /// The items that can be found in chests, of the given level,
+    /// paired with their weights.
+    pub fn items(&self, level: i32) -> Vec<(u32, Box<dyn Item>)> {
+        self.items
+            .iter()
+            .filter_map(|(name, weight)| item(name, level).map(|item| (*weight, item)))
+            .collect()
+    }
+
+    /// The rare items dropped by bosses, of the given level, paired with
+    /// their weights.
+    pub fn boss_items(&self, level: i32) -> Vec<(u32, Box<dyn Item>)> {
+        self.boss_items
+            .iter()
+            .filter_map(|(name, weight)| item(name, level).map(|item| (*weight, item)))
+            .collect()
+    }
+
+    /// The equipment pieces that can be found in chests, based on the given
+    /// level, paired with their weights.
+    pub fn equipment(&self, level: i32) -> Vec<(u32, Equipment)> {
+        self.equipment
+            .iter()
+            .map(|drop| (drop.weight, drop.equipment(level)))
+            .collect()
+    }
+
+    /// The loot table of the given enemy class.
+    pub fn loot(&self, class: &str) -> &Loot {
+        self.loot.get(class).unwrap_or(&CHEST_LOOT)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for name in self.items.keys() {
+            if item(name, 1).is_none() {
+                bail!("{} can't be found in chests", name);
+            }
+        }
+        if self.items.values().all(|weight| *weight == 0) {
+            bail!("at least one item needs a positive weight");
+        }
+        for name in self.boss_items.keys() {
+            if item(name, 1).is_none() {
+                bail!("{} can't be dropped by bosses", name);
+            }
+        }
+        if !self.boss_items.is_empty() && self.boss_items.values().all(|weight| *weight == 0) {
+            bail!("at least one boss item needs a positive weight");
+        }
+
+        for (class, loot) in &self.loot {
+            for name in loot.items.keys() {
+                if item(name, 1).is_none() {
+                    bail!("{} can't be dropped by {}", name, class);
+                }
+                if self.boss_items.contains_key(name) {
+                    bail!("{} is only dropped by bosses", name);
+                }
+            }
+            if !loot.items.is_empty() && loot.items.values().all(|weight| *weight == 0) {
+                bail!(
+                    "at least one item dropped by {} needs a positive weight",
+                    class
+                );
+            }
+            if loot.gold.0 > loot.gold.1 {
+                bail!("the gold range of {} is reversed", class);
+            }
+        }
+
+        for drop in &self.equipment {
+            match drop.kind {
+                Key::Sword => {}
+                Key::Shield | Key::Helmet | Key::Armor | Key::Boots => {
+                    if drop.element.is_some() {
+                        bail!("only swords can have an element");
+                    }
+                }
+                _ => bail!("{} is not a piece of equipment", drop.kind),
+            }
+        }
+        if self.equipment.iter().all(|drop| drop.weight == 0) {
+            bail!("at least one piece of equipment needs a positive weight");
+        }
+
+        let chests = &self.chests;
+        let odds = [
+            &chests.gold,
+            &chests.equipment,
+            &chests.ring,
+            &chests.item,
+            &chests.npc,
+        ];
+        let loot_odds = self
+            .loot
+            .values()
+            .flat_map(|loot| [loot.item, loot.equipment, loot.ring, loot.material])
+            .flatten();
+        for ratio in odds
+            .iter()
+            .flat_map(|odds| vec![odds.near, odds.mid, odds.far])
+            .chain(vec![chests.mimic, chests.guard, chests.trap])
+            .chain(loot_odds)
+        {
+            if ratio.1 == 0 || ratio.0 > ratio.1 {
+                bail!("{}/{} is not a valid chance", ratio.0, ratio.1);
+            }
+        }
+        for gold_ring in [false, true] {
+            if chests
+                .gold_tiers
+                .iter()
+                .all(|tier| tier.weight(gold_ring) == 0)
+            {
+                bail!("at least one gold tier needs a positive weight");
+            }
+        }
+
+        let overleveled = &chests.overleveled;
+        if overleveled.decay > 100 || overleveled.min > 100 {
+            bail!("overleveled chest chances must be percentages");
+        }
+        Ok(())
+    }
+}
+
+impl Loot {
+    /// Whether an item is dropped, on one of the rolls for items.
+    pub fn item_dropped(&self, distance: &Distance) -> bool {
+        match self.item {
+            Some(chance) => random().loot_dropped(chance),
+            None => random().item_chest(distance),
+        }
+    }
+
+    pub fn equipment_dropped(&self, distance: &Distance) -> bool {
+        match self.equipment {
+            Some(chance) => random().loot_dropped(chance),
+            None => random().equipment_chest(distance),
+        }
+    }
+
+    pub fn ring_dropped(&self, distance: &Distance) -> bool {
+        match self.ring {
+            Some(chance) => random().loot_dropped(chance),
+            None => random().ring_chest(distance),
+        }
+    }
+
+    pub fn material_dropped(&self) -> bool {
+        match self.material {
+            Some(chance) => random().loot_dropped(chance),
+            None => random().material_drop(),
+        }
+    }
+
+    /// The items that can be dropped, of the given level, paired with
+    /// their weights.
+    pub fn items(&self, level: i32) -> Vec<(u32, Box<dyn Item>)> {
+        if self.items.is_empty() {
+            // other enemies don't drop the rare items of bosses
+            let drops = Drops::get();
+            return drops
+                .items(level)
+                .into_iter()
+                .filter(|(_, item)| !drops.boss_items.contains_key(&item.key().to_string()))
+                .collect();
+        }
+        self.items
+            .iter()
+            .filter_map(|(name, weight)| item(name, level).map(|item| (*weight, item)))
+            .collect()
+    }
+
+    /// The given gold scaled by a random percentage within the gold range.
+    pub fn gold(&self, gold: i32) -> i32 {
+        let (min, max) = self.gold;
+        let percent = min + random().pick((max - min + 1) as usize) as u32;
+        gold * percent as i32 / 100
+    }
+}
+
+impl EquipmentDrop {
+    fn equipment(&self, base_level: i32) -> Equipment {
+        let level = self.level.unwrap_or(base_level + self.bonus);
+        match (&self.kind, self.element) {
+            (Key::Sword, Some(element)) => Equipment::elemental_sword(level, element),
+            (Key::Sword, None) => Equipment::sword(level),
+            (Key::Shield, _) => Equipment::shield(level),
+            (Key::Helmet, _) => Equipment::helmet(level),
+            (Key::Armor, _) => Equipment::armor(level),
+            _ => Equipment::boots(level),
+        }
+    }
+}
+
+impl Overleveled {
+    /// The percentage of chests still found by a hero the given number of
+    /// levels above the tolerated gap.
+    pub fn kept(&self, levels: i32) -> u32 {
+        let mut kept = 100;
+        for _ in 0..levels {
+            kept = kept * (100 - self.decay) / 100;
+            if kept <= self.min {
+                return self.min;
+            }
+        }
+        kept
+    }
+}
+
+impl Default for Overleveled {
+    fn default() -> Self {
+        Self { decay: 20, min: 5 }
+    }
+}
+
+impl DistanceOdds {
+    pub fn at(&self, distance: &Distance) -> Ratio {
+        match distance {
+            Distance::Near(_) => self.near,
+            Distance::Mid(_) => self.mid,
+            Distance::Far(_) => self.far,
+        }
+    }
+}
+
+fn from_bytes(bytes: &[u8]) -> Result<Drops> {
+    let drops: Drops = toml::from_slice(bytes)?;
+    drops.validate()?;
+    Ok(drops)
+}
+
+/// The item of the given name and level, if it's one that can be found in chests.
+pub fn item(name: &str, level: i32) -> Option<Box<dyn Item>> {
+    let item: Box<dyn Item> = match Key::from(name).ok()? {
+        Key::Potion => Box::new(Potion::new(level)),
+        Key::Escape => Box::new(Escape::new()),
+        Key::Remedy => Box::new(Remedy::new()),
+        Key::Bag => Box::new(Bag::new()),
+        Key::Ether => Box::new(Ether::new(level)),
+        Key::MidPotion => Box::new(Potion::tiered(level, Tier::Mid)),
+        Key::HiPotion => Box::new(Potion::tiered(level, Tier::Hi)),
+        Key::MidEther => Box::new(Ether::tiered(level, Tier::Mid)),
+        Key::HiEther => Box::new(Ether::tiered(level, Tier::Hi)),
+        Key::Elixir => Box::new(Elixir::new()),
+        Key::PhoenixDown => Box::new(PhoenixDown::new()),
+        Key::HealthStone => Box::new(stone::Health),
+        Key::MagicStone => Box::new(stone::Magic),
+        Key::PowerStone => Box::new(stone::Power),
+        Key::SpeedStone => Box::new(stone::Speed),
+        Key::LevelStone => Box::new(stone::Level),
+        Key::ForgetStone => Box::new(stone::Forget),
+        Key::ChaosStone => Box::new(stone::Chaos),
+        Key::Material(material) => Box::new(material),
+        Key::Throwable(throwable) => Box::new(throwable),
+        Key::Scroll(spell) => Box::new(Scroll(spell)),
+        Key::Custom(name) => Box::new(Custom::new(&name)),
+        _ => return None,
+    };
+    Some(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_drops() {
+        let drops = Drops::get();
+        assert_eq!(28, drops.items(1).len());
+        assert_eq!(2, drops.boss_items(1).len());
+        assert_eq!(14, drops.equipment(5).len());
+        assert_eq!(Ratio(6, 30), drops.chests.gold.at(&Distance::from(1)));
+        assert_eq!(Ratio(0, 30), drops.chests.ring.at(&Distance::from(1)));
+        assert_eq!(Ratio(1, 20), drops.chests.mimic);
+        assert_eq!(Ratio(1, 15), drops.chests.guard);
+        assert_eq!(100, drops.chests.overleveled.kept(0));
+        assert_eq!(80, drops.chests.overleveled.kept(1));
+        assert_eq!(64, drops.chests.overleveled.kept(2));
+        assert_eq!(5, drops.chests.overleveled.kept(20));
+        assert_eq!(3, drops.chests.gold_tiers.len());
+        assert!(drops.chests.gold_tiers[2].jackpot);
+
+        let levels: Vec<i32> = drops
+            .equipment(5)
+            .iter()
+            .map(|(_, piece)| piece.level())
+            .collect();
+        assert!(levels.contains(&5));
+        assert!(levels.contains(&10));
+        assert!(levels.contains(&100));
+
+        // classes without a loot table drop the chest items other than the
+        // boss ones, and the usual gold
+        assert_eq!(26, drops.loot("wolf").items(1).len());
+        assert_eq!(100, drops.loot("wolf").gold(100));
+        assert_eq!(4, drops.loot("dragon").items(1).len());
+        // the test randomizer gives the lowest gold in the range
+        assert_eq!(40, drops.loot("rat").gold(100));
+        assert_eq!(150, drops.loot("dragon").gold(100));
+    }
+
+    #[test]
+    fn invalid_drops() {
+        let defaults = include_str!("drops.toml");
+        assert!(from_bytes(defaults.as_bytes()).is_ok());
+
+        let unknown = defaults.replace("potion = 150", "nectar = 150");
+        assert!(from_bytes(unknown.as_bytes()).is_err());
+
+        let ring = defaults.replace("potion = 150", "void-rng = 150");
+        assert!(from_bytes(ring.as_bytes()).is_err());
+
+        let element = defaults.replace(
+            "kind = \"shield\"\nweight = 80",
+            "kind = \"shield\"\nweight = 80\nelement = \"fire\"",
+        );
+        assert!(from_bytes(element.as_bytes()).is_err());
+
+        let ratio = defaults.replace("mimic = [1, 20]", "mimic = [21, 20]");
+        assert!(from_bytes(ratio.as_bytes()).is_err());
+
+        let missing = defaults.replace("trap = [1, 10]", "");
+        assert!(from_bytes(missing.as_bytes()).is_err());
+
+        let decay = defaults.replace("decay = 20", "decay = 120");
+        assert!(from_bytes(decay.as_bytes()).is_err());
+
+        // older drop files without the overleveled section use the defaults
+        let start = defaults.find("[chests.overleveled]").unwrap();
+        let end = start + defaults[start..].find("\n\n").unwrap();
+        let old = format!("{}{}", &defaults[..start], &defaults[end..]);
+        assert_eq!(
+            80,
+            from_bytes(old.as_bytes())
+                .unwrap()
+                .chests
+                .overleveled
+                .kept(1)
+        );
+
+        let loot = defaults.replace("potion = 20,", "nectar = 20,");
+        assert!(from_bytes(loot.as_bytes()).is_err());
+
+        // boss items can't be in the tables of other enemies
+        let loot = defaults.replace("potion = 20,", "phoenix-down = 20,");
+        assert!(from_bytes(loot.as_bytes()).is_err());
+
+        let boss = defaults.replace("elixir = 3", "nectar = 3");
+        assert!(from_bytes(boss.as_bytes()).is_err());
+
+        let loot = defaults.replace("[1, 5]\ngold = [150, 300]", "[6, 5]\ngold = [150, 300]");
+        assert!(from_bytes(loot.as_bytes()).is_err());
+
+        let loot = defaults.replace("gold = [150, 300]", "gold = [300, 150]");
+        assert!(from_bytes(loot.as_bytes()).is_err());
+
+        // older drop files without gold tiers use the defaults
+        let start = defaults.find("[[chests.gold_tier]]").unwrap();
+        let end = defaults.find("[chests.overleveled]").unwrap();
+        let old = format!("{}{}", &defaults[..start], &defaults[end..]);
+        let old = from_bytes(old.as_bytes()).unwrap();
+        let tiers: Vec<u32> = old.chests.gold_tiers.iter().map(|t| t.payout).collect();
+        assert_eq!(vec![60, 200, 1000], tiers);
+
+        let tiers = defaults.replace(
+            "weight = 80\nring_weight = 50",
+            "weight = 0\nring_weight = 0",
+        );
+        let tiers = tiers.replace(
+            "weight = 18\nring_weight = 40",
+            "weight = 0\nring_weight = 40",
+        );
+        let tiers = tiers.replace(
+            "weight = 2\nring_weight = 10",
+            "weight = 0\nring_weight = 10",
+        );
+        assert!(from_bytes(tiers.as_bytes()).is_err());
+
+        let cutoff = defaults.replace("min = 5", "min = 0");
+        let cutoff = from_bytes(cutoff.as_bytes()).unwrap();
+        assert_eq!(0, cutoff.chests.overleveled.kept(30));
+    }
+}
@@ -2,20 +2,59 @@ use core::fmt;
 
 use super::key::Key;
 use crate::character::class::Class;
+use crate::character::element::Element;
 use serde::{Deserialize, Serialize};
 
+/// Amount of battle turns a piece of equipment can be used before breaking.
+const MAX_DURABILITY: i32 = 100;
+
 /// Equipment piece with a strength contribution based on
-/// a level. Used to generically represent swords and shields.
+/// a level. Used to generically represent swords, shields and armor pieces.
+/// The third element is the durability, which wears down with use; broken
+/// equipment doesn't contribute any strength until it's repaired.
+/// The fourth is an optional element, which swords add to physical attacks.
+/// The fifth tells if the piece is cursed: stronger, but draining gold after
+/// battles and stuck to the hero until the curse is lifted.
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct Equipment(Key, i32);
+pub struct Equipment(
+    Key,
+    i32,
+    #[serde(default = "max_durability")] i32,
+    #[serde(default)] Option<Element>,
+    #[serde(default)] bool,
+);
+
+/// Description of the drawbacks of cursed equipment and rings.
+pub const CURSE_DESCRIPTION: &str =
+    "cursed: drains gold after battles and can't be removed without a remedy";
+
+fn max_durability() -> i32 {
+    MAX_DURABILITY
+}
 
 impl Equipment {
     pub fn sword(level: i32) -> Self {
-        Self(Key::Sword, level)
+        Self(Key::Sword, level, MAX_DURABILITY, None, false)
     }
 
     pub fn shield(level: i32) -> Self {
-        Self(Key::Shield, level)
+        Self(Key::Shield, level, MAX_DURABILITY, None, false)
+    }
+
+    pub fn helmet(level: i32) -> Self {
+        Self(Key::Helmet, level, MAX_DURABILITY, None, false)
+    }
+
+    pub fn armor(level: i32) -> Self {
+        Self(Key::Armor, level, MAX_DURABILITY, None, false)
+    }
+
+    pub fn boots(level: i32) -> Self {
+        Self(Key::Boots, level, MAX_DURABILITY, None, false)
+    }
+
+    pub fn elemental_sword(level: i32, element: Element) -> Self {
+        Self(Key::Sword, level, MAX_DURABILITY, Some(element), false)
     }
 
     pub fn level(&self) -> i32 {
@@ -26,20 +65,89 @@ impl Equipment {
         self.0.clone()
     }
 
+    pub fn durability(&self) -> i32 {
+        self.2
+    }
+
+    pub fn element(&self) -> Option<Element> {
+        self.3
+    }
+
+    pub fn is_cursed(&self) -> bool {
+        self.4
+    }
+
+    pub fn curse(&mut self) {
+        self.4 = true;
+    }
+
+    pub fn lift_curse(&mut self) {
+        self.4 = false;
+    }
+
+    pub fn is_broken(&self) -> bool {
+        self.2 == 0
+    }
+
+    /// Reduce the durability after being used in a battle turn.
+    /// Return true if the piece broke as a result.
+    pub fn wear(&mut self) -> bool {
+        if self.is_broken() {
+            return false;
+        }
+        self.2 -= 1;
+        self.is_broken()
+    }
+
+    /// Gold needed to fully restore the durability, proportional to the
+    /// piece level and how worn it is.
+    pub fn repair_cost(&self) -> i32 {
+        self.level() * (MAX_DURABILITY - self.2)
+    }
+
+    pub fn repair(&mut self) {
+        self.2 = MAX_DURABILITY;
+    }
+
+    /// Raise the level of the piece by one, e.g. as a result of crafting.
+    pub fn upgrade(&mut self) {
+        self.1 += 1;
+    }
+
     /// How many strength points get added to the player when
     /// the item is equipped.
     pub fn strength(&self) -> i32 {
+        if self.is_broken() {
+            return 0;
+        }
+
         // get the base strength of the hero at this level
         let player_strength = Class::player_first().strength.at(self.level());
 
         // calculate the added strength as a function of the player strength
-        (player_strength as f64 * 0.5).round() as i32
+        // the smaller armor pieces contribute less than swords and shields
+        let factor = match self.key() {
+            Key::Helmet | Key::Boots => 0.2,
+            Key::Armor => 0.3,
+            _ => 0.5,
+        };
+        // cursed pieces are half as strong again
+        let factor = if self.is_cursed() {
+            factor * 1.5
+        } else {
+            factor
+        };
+        (player_strength as f64 * factor).round() as i32
     }
 
     /// Return true if the other weapon either is None or has lower level than this one.
+    /// At the same level, an elemental piece is an upgrade from a plain one.
     pub fn is_upgrade_from(&self, maybe_other: &Option<Self>) -> bool {
         if let Some(equip) = maybe_other {
             self.level() > equip.level()
+                || (self.level() == equip.level()
+                    && self.element().is_some()
+                    && equip.element().is_none())
         } else {
             true
         }
@@ -51,12 +159,101 @@ impl Equipment {
         } else {
             "defense"
         };
-        format!("increases {} by {}", stat, self.strength())
+        let element = self
+            .element()
+            .map_or(String::new(), |e| format!(", deals {} damage", e));
+        let description = if self.is_broken() {
+            format!("broken, repair it to increase {} again", stat)
+        } else {
+            format!(
+                "increases {} by {}{} (durability {}/{})",
+                stat,
+                self.strength(),
+                element,
+                self.durability(),
+                MAX_DURABILITY
+            )
+        };
+        if self.is_cursed() {
+            format!("{}, {}", description, CURSE_DESCRIPTION)
+        } else {
+            description
+        }
     }
 }
 
 impl fmt::Display for Equipment {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_cursed() {
+            write!(f, "cursed ")?;
+        }
+        if let Some(element) = self.element() {
+            write!(f, "{} ", element)?;
+        }
         write!(f, "{}[{}]", self.key(), self.level())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_durability() {
+        let mut sword = Equipment::sword(5);
+        let strength = sword.strength();
+        assert!(strength > 0);
+        assert_eq!(0, sword.repair_cost());
+
+        assert!(!sword.wear());
+        assert_eq!(MAX_DURABILITY - 1, sword.durability());
+        assert_eq!(5, sword.repair_cost());
+
+        for _ in 1..MAX_DURABILITY - 1 {
+            assert!(!sword.wear());
+        }
+        assert!(sword.wear());
+        assert!(sword.is_broken());
+        assert_eq!(0, sword.strength());
+
+        // already broken
+        assert!(!sword.wear());
+        assert_eq!(5 * MAX_DURABILITY, sword.repair_cost());
+
+        sword.repair();
+        assert_eq!(strength, sword.strength());
+    }
+
+    #[test]
+    fn test_elemental() {
+        let plain = Equipment::sword(5);
+        let fire = Equipment::elemental_sword(5, Element::Fire);
+        assert_eq!("fire sword[5]", fire.to_string());
+        assert_eq!(Some(Element::Fire), fire.element());
+
+        assert!(fire.is_upgrade_from(&Some(plain.clone())));
+        assert!(!plain.is_upgrade_from(&Some(fire.clone())));
+        assert!(Equipment::sword(10).is_upgrade_from(&Some(fire)));
+    }
+
+    #[test]
+    fn test_cursed() {
+        let mut sword = Equipment::sword(10);
+        let strength = sword.strength();
+        sword.curse();
+        assert!(sword.is_cursed());
+        assert!(sword.strength() > strength);
+        assert_eq!("cursed sword[10]", sword.to_string());
+
+        sword.lift_curse();
+        assert_eq!(strength, sword.strength());
+        assert_eq!("sword[10]", sword.to_string());
+    }
+
+    #[test]
+    fn test_missing_durability() {
+        let sword: Equipment = serde_json::from_str(r#"["sword", 5]"#).unwrap();
+        assert_eq!(MAX_DURABILITY, sword.durability());
+        assert!(sword.element().is_none());
+    }
+}
@@ -1,4 +1,9 @@
+use super::material::Material;
 use super::ring::Ring;
+use super::throwable::Throwable;
+use crate::character::spell::Spell;
+use crate::i18n;
+use crate::plugin;
 use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
 use std::convert::From;
@@ -9,33 +14,62 @@ use strum_macros::EnumIter;
 #[serde(try_from = "String", into = "String")]
 pub enum Key {
     Potion,
+    MidPotion,
+    HiPotion,
     Escape,
     Remedy,
     Ether,
+    MidEther,
+    HiEther,
+    Elixir,
+    PhoenixDown,
     HealthStone,
     MagicStone,
     PowerStone,
     SpeedStone,
     LevelStone,
+    ForgetStone,
+    ChaosStone,
     Sword,
     Shield,
+    Helmet,
+    Armor,
+    Boots,
     Ring(Ring),
+    Material(Material),
+    Throwable(Throwable),
+    Scroll(Spell),
+    TreasureMap,
+    Bag,
+    /// An item registered by a plugin.
+    Custom(String),
 }
 
 impl Key {
     pub fn from(name: &str) -> Result<Self> {
         let key = match name.to_lowercase().as_str() {
             "potion" | "p" => Key::Potion,
+            "mid-potion" | "mid-p" => Key::MidPotion,
+            "hi-potion" | "hi-p" => Key::HiPotion,
             "ether" | "e" => Key::Ether,
+            "mid-ether" | "mid-e" => Key::MidEther,
+            "hi-ether" | "hi-e" => Key::HiEther,
+            "elixir" | "el" => Key::Elixir,
+            "phoenix-down" | "down" => Key::PhoenixDown,
             "remedy" | "r" => Key::Remedy,
             "escape" | "es" => Key::Escape,
             "sword" | "sw" => Key::Sword,
             "shield" | "sh" => Key::Shield,
+            "helmet" | "hm" => Key::Helmet,
+            "armor" | "ar" => Key::Armor,
+            "boots" | "bt" => Key::Boots,
             "hp-stone" | "hp" => Key::HealthStone,
             "mp-stone" | "mp" => Key::MagicStone,
             "str-stone" | "str" | "strength" => Key::PowerStone,
             "spd-stone" | "spd" | "speed" => Key::SpeedStone,
             "lvl-stone" | "level" | "lv" | "lvl" => Key::LevelStone,
+            "forget-stone" | "forget" => Key::ForgetStone,
+            "chaos-stone" | "chaos" => Key::ChaosStone,
             "void-rng" | "void" => Key::Ring(Ring::Void),
             "att-rng" | "att-ring" | "att" | "attack" | "attack-ring" | "attack-rng" => {
                 Key::Ring(Ring::Attack)
@@ -59,27 +93,72 @@ impl Key {
             "revive-rng" | "revive" | "revive-ring" => Key::Ring(Ring::Revive),
             "chest-rng" | "chest" | "chest-ring" => Key::Ring(Ring::Chest),
             "gold-rng" | "gold" | "gold-ring" => Key::Ring(Ring::Gold),
+            "trap-rng" | "trap" | "trap-ring" => Key::Ring(Ring::Trap),
+            "balance-rng" | "balance" | "balance-ring" => Key::Ring(Ring::Balance),
             "diamond-rng" | "diamond" | "diamond-ring" => Key::Ring(Ring::Diamond),
-            key => bail!("item {} not found", key),
+            "vampire-rng" | "vampire-ring" => Key::Ring(Ring::Vampire),
+            "att-rng+" | "att-ring+" => Key::Ring(Ring::AttackPlus),
+            "def-rng+" | "def-ring+" => Key::Ring(Ring::DeffensePlus),
+            "spd-rng+" | "spd-ring+" => Key::Ring(Ring::SpeedPlus),
+            "mag-rng+" | "mag-ring+" => Key::Ring(Ring::MagicPlus),
+            "mp-rng+" | "mp-ring+" => Key::Ring(Ring::MPPlus),
+            "hp-rng+" | "hp-ring+" => Key::Ring(Ring::HPPlus),
+            "fang" | "fangs" => Key::Material(Material::Fang),
+            "ore" | "ores" => Key::Material(Material::Ore),
+            "herb" | "herbs" => Key::Material(Material::Herb),
+            "bomb" | "bombs" => Key::Throwable(Throwable::Bomb),
+            "shuriken" => Key::Throwable(Throwable::Shuriken),
+            "holy-water" | "holy" => Key::Throwable(Throwable::HolyWater),
+            "heal-scroll" | "heal-scr" => Key::Scroll(Spell::Heal),
+            "fireball-scroll" | "fireball-scr" => Key::Scroll(Spell::Fireball),
+            "shield-scroll" | "shield-scr" => Key::Scroll(Spell::Shield),
+            "haste-scroll" | "haste-scr" => Key::Scroll(Spell::Haste),
+            "map" | "treasure-map" => Key::TreasureMap,
+            "bag" | "bags" => Key::Bag,
+            key if plugin::item(key).is_some() => Key::Custom(key.to_string()),
+            key => match i18n::original(key) {
+                Some(original) => return Key::from(&original),
+                None => bail!("item {} not found", key),
+            },
         };
         Ok(key)
     }
+
+    /// Rings, scrolls, level, forget and chaos stones are rare finds,
+    /// highlighted in the game output.
+    pub fn is_rare(&self) -> bool {
+        matches!(
+            self,
+            Key::Ring(_) | Key::Scroll(_) | Key::LevelStone | Key::ForgetStone | Key::ChaosStone
+        )
+    }
 }
 
 impl fmt::Display for Key {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let name = match self {
             Key::Potion => "potion",
+            Key::MidPotion => "mid-potion",
+            Key::HiPotion => "hi-potion",
             Key::Escape => "escape",
             Key::Remedy => "remedy",
             Key::Ether => "ether",
+            Key::MidEther => "mid-ether",
+            Key::HiEther => "hi-ether",
+            Key::Elixir => "elixir",
+            Key::PhoenixDown => "phoenix-down",
             Key::HealthStone => "hp-stone",
             Key::MagicStone => "mp-stone",
             Key::PowerStone => "str-stone",
             Key::SpeedStone => "spd-stone",
             Key::LevelStone => "lvl-stone",
+            Key::ForgetStone => "forget-stone",
+            Key::ChaosStone => "chaos-stone",
             Key::Sword => "sword",
             Key::Shield => "shield",
+            Key::Helmet => "helmet",
+            Key::Armor => "armor",
+            Key::Boots => "boots",
             Key::Ring(Ring::Void) => "void-rng",
             Key::Ring(Ring::Attack) => "att-rng",
             Key::Ring(Ring::Deffense) => "def-rng",
@@ -99,7 +178,29 @@ impl fmt::Display for Key {
             Key::Ring(Ring::Revive) => "revive-rng",
             Key::Ring(Ring::Chest) => "chest-rng",
             Key::Ring(Ring::Gold) => "gold-rng",
+            Key::Ring(Ring::Trap) => "trap-rng",
+            Key::Ring(Ring::Balance) => "balance-rng",
             Key::Ring(Ring::Diamond) => "diamond-rng",
+            Key::Ring(Ring::Vampire) => "vampire-rng",
+            Key::Ring(Ring::AttackPlus) => "att-rng+",
+            Key::Ring(Ring::DeffensePlus) => "def-rng+",
+            Key::Ring(Ring::SpeedPlus) => "spd-rng+",
+            Key::Ring(Ring::MagicPlus) => "mag-rng+",
+            Key::Ring(Ring::MPPlus) => "mp-rng+",
+            Key::Ring(Ring::HPPlus) => "hp-rng+",
+            Key::Material(Material::Fang) => "fang",
+            Key::Material(Material::Ore) => "ore",
+            Key::Material(Material::Herb) => "herb",
+            Key::Throwable(Throwable::Bomb) => "bomb",
+            Key::Throwable(Throwable::Shuriken) => "shuriken",
+            Key::Throwable(Throwable::HolyWater) => "holy-water",
+            Key::Scroll(Spell::Heal) => "heal-scroll",
+            Key::Scroll(Spell::Fireball) => "fireball-scroll",
+            Key::Scroll(Spell::Shield) => "shield-scroll",
+            Key::Scroll(Spell::Haste) => "haste-scroll",
+            Key::TreasureMap => "map",
+            Key::Bag => "bag",
+            Key::Custom(name) => name,
         };
 
         write!(f, "{}", name)
@@ -110,7 +211,8 @@ impl fmt::Display for Key {
 // allow Key variants to be used as keys in JSON objects for serialization
 impl From<String> for Key {
     fn from(key: String) -> Self {
-        Key::from(&key).unwrap()
+        // items of plugins that are no longer loaded are kept as they were
+        Key::from(&key).unwrap_or(Key::Custom(key))
     }
 }
 
@@ -143,6 +245,30 @@ mod tests {
                     let parsed = Key::from(String::from(ring_key.clone()).as_str()).unwrap();
                     assert_eq!(ring_key, parsed);
                 }
+            } else if let Key::Throwable(_) = key {
+                for throwable in Throwable::iter() {
+                    let throwable_key = Key::Throwable(throwable);
+                    let parsed = Key::from(String::from(throwable_key.clone()).as_str()).unwrap();
+                    assert_eq!(throwable_key, parsed);
+                }
+            } else if let Key::Material(_) = key {
+                for material in Material::iter() {
+                    let material_key = Key::Material(material);
+                    let parsed = Key::from(String::from(material_key.clone()).as_str()).unwrap();
+                    assert_eq!(material_key, parsed);
+                }
+            } else if let Key::Scroll(_) = key {
+                for spell in Spell::iter() {
+                    let scroll_key = Key::Scroll(spell);
+                    let parsed = Key::from(String::from(scroll_key.clone()).as_str()).unwrap();
+                    assert_eq!(scroll_key, parsed);
+                }
+            } else if let Key::Custom(_) = key {
+                // plugin items are only found by name if their plugin is
+                // loaded, but they are always deserialized
+                let custom = Key::Custom(String::from("apple"));
+                let parsed: Key = String::from(custom.clone()).into();
+                assert_eq!(custom, parsed);
             } else {
                 let parsed = Key::from(String::from(key.clone()).as_str()).unwrap();
                 assert_eq!(key, parsed);
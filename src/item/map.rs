@@ -0,0 +1,55 @@
+use super::{key, Item};
+use crate::game;
+use crate::location::Location;
+use crate::log;
+use crate::randomizer::{choose, random};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How many directories below home a buried chest can be.
+const MIN_DEPTH: i32 = 3;
+const MAX_DEPTH: i32 = 8;
+
+/// A map that reveals the location of a buried chest when used.
+/// The chest is dug up by the hero the next time they visit that directory.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TreasureMap;
+
+impl TreasureMap {
+    /// Pick the burial location by walking down random directories from home,
+    /// stopping early if there are no more directories to go into.
+    fn burial_location() -> Location {
+        let depth = MIN_DEPTH + random().pick((MAX_DEPTH - MIN_DEPTH + 1) as usize) as i32;
+        let mut location = Location::home();
+        for _ in 0..depth {
+            match choose(&location.subdirectories()) {
+                Some(dir) => location = dir.clone(),
+                None => break,
+            }
+        }
+        location
+    }
+}
+
+#[typetag::serde]
+impl Item for TreasureMap {
+    fn apply(&mut self, game: &mut game::Game) {
+        let location = Self::burial_location();
+        log::treasure_map(&location);
+        game.buried_chests.insert(location);
+    }
+
+    fn key(&self) -> key::Key {
+        key::Key::TreasureMap
+    }
+
+    fn describe(&self) -> String {
+        String::from("reveals where a chest is buried")
+    }
+}
+
+impl fmt::Display for TreasureMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "map")
+    }
+}
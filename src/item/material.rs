@@ -0,0 +1,258 @@
+use super::key::Key;
+use super::{chest, Ether, Item, Potion, Remedy};
+use crate::game::Game;
+use crate::log;
+use anyhow::{bail, Result};
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use strum_macros::EnumIter;
+
+/// Materials are dropped by enemies and found in chests. They have no effect
+/// on their own but can be combined into other items by crafting.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Hash, EnumIter, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Material {
+    #[default]
+    Fang,
+    Ore,
+    Herb,
+}
+
+impl fmt::Display for Material {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.key())
+    }
+}
+
+#[typetag::serde]
+impl Item for Material {
+    /// Materials can't be used directly, put it back in the inventory.
+    fn apply(&mut self, game: &mut Game) {
+        game.add_item(Box::new(self.clone()));
+    }
+
+    fn key(&self) -> Key {
+        Key::Material(self.clone())
+    }
+
+    fn describe(&self) -> String {
+        String::from("crafting material")
+    }
+
+    fn sell_price(&self) -> i32 {
+        50
+    }
+}
+
+/// What a recipe produces when crafted.
+enum Product {
+    Potion,
+    Ether,
+    Remedy,
+    /// Raise the level of the equipped piece of the given type.
+    Upgrade(Key),
+    /// One of the rings that haven't been found yet.
+    Ring,
+}
+
+/// A combination of materials that can be crafted into a new item.
+pub struct Recipe {
+    pub name: &'static str,
+    materials: &'static [(Material, usize)],
+    product: Product,
+}
+
+impl Recipe {
+    /// Describe the materials needed to craft this recipe, e.g. herbx2 fangx1
+    pub fn materials(&self) -> String {
+        self.materials
+            .iter()
+            .map(|(material, count)| format!("{}x{}", material, count))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+const RECIPES: &[Recipe] = &[
+    Recipe {
+        name: "potion",
+        materials: &[(Material::Herb, 2)],
+        product: Product::Potion,
+    },
+    Recipe {
+        name: "ether",
+        materials: &[(Material::Herb, 2), (Material::Fang, 1)],
+        product: Product::Ether,
+    },
+    Recipe {
+        name: "remedy",
+        materials: &[(Material::Herb, 1), (Material::Fang, 1)],
+        product: Product::Remedy,
+    },
+    Recipe {
+        name: "sword",
+        materials: &[(Material::Ore, 3), (Material::Fang, 1)],
+        product: Product::Upgrade(Key::Sword),
+    },
+    Recipe {
+        name: "shield",
+        materials: &[(Material::Ore, 3)],
+        product: Product::Upgrade(Key::Shield),
+    },
+    Recipe {
+        name: "helmet",
+        materials: &[(Material::Ore, 2)],
+        product: Product::Upgrade(Key::Helmet),
+    },
+    Recipe {
+        name: "armor",
+        materials: &[(Material::Ore, 3), (Material::Herb, 1)],
+        product: Product::Upgrade(Key::Armor),
+    },
+    Recipe {
+        name: "boots",
+        materials: &[(Material::Ore, 2), (Material::Fang, 1)],
+        product: Product::Upgrade(Key::Boots),
+    },
+    Recipe {
+        name: "ring",
+        materials: &[(Material::Fang, 5), (Material::Ore, 5), (Material::Herb, 5)],
+        product: Product::Ring,
+    },
+];
+
+pub fn recipes() -> &'static [Recipe] {
+    RECIPES
+}
+
+/// Consume the materials of the recipe with the given name to craft a new item.
+/// The materials are only consumed if the item could be crafted.
+pub fn craft(game: &mut Game, name: &str) -> Result<()> {
+    let recipe = if let Some(recipe) = RECIPES.iter().find(|r| r.name == name) {
+        recipe
+    } else {
+        bail!("recipe {} not found.", name);
+    };
+
+    let inventory = game.inventory();
+    for (material, count) in recipe.materials {
+        let key = Key::Material(material.clone());
+        if inventory.get(&key).copied().unwrap_or(0) < *count {
+            bail!("Not enough materials.");
+        }
+    }
+
+    let level = game.player.rounded_level();
+    let crafted = match &recipe.product {
+        Product::Potion => add(game, Box::new(Potion::new(level))),
+        Product::Ether => add(game, Box::new(Ether::new(level))),
+        Product::Remedy => add(game, Box::new(Remedy::new())),
+        Product::Upgrade(key) => {
+            let piece = game
+                .player
+                .equipment_slots()
+                .iter_mut()
+                .filter_map(|p| p.as_mut())
+                .find(|p| p.key() == *key)
+                .map(|piece| {
+                    piece.upgrade();
+                    piece.to_string()
+                });
+            if let Some(piece) = piece {
+                piece
+            } else {
+                bail!("No {} equipped.", key);
+            }
+        }
+        Product::Ring => {
+            if let Some(ring) = chest::random_ring(game) {
+                add(game, Box::new(ring))
+            } else {
+                bail!("There are no rings left to craft.");
+            }
+        }
+    };
+
+    let mut consumed = HashMap::new();
+    for (material, count) in recipe.materials {
+        let key = Key::Material(material.clone());
+        if let Some(items) = game.inventory.get_mut(&key) {
+            items.truncate(items.len() - count);
+            if items.is_empty() {
+                game.inventory.remove(&key);
+            }
+        }
+        consumed.insert(key, *count as i32);
+    }
+
+    log::craft(&consumed, &crafted);
+    Ok(())
+}
+
+fn add(game: &mut Game, item: Box<dyn Item>) -> String {
+    let crafted = item.to_string();
+    game.add_item(item);
+    crafted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::equipment::Equipment;
+    use super::*;
+
+    #[test]
+    fn craft_potion() {
+        let mut game = Game::new();
+        game.add_item(Box::new(Material::Herb));
+        assert!(craft(&mut game, "potion").is_err());
+        assert!(craft(&mut game, "elixir").is_err());
+
+        game.add_item(Box::new(Material::Herb));
+        game.add_item(Box::new(Material::Herb));
+        assert!(craft(&mut game, "potion").is_ok());
+        assert_eq!(1, *game.inventory().get(&Key::Potion).unwrap());
+        assert_eq!(
+            1,
+            *game
+                .inventory()
+                .get(&Key::Material(Material::Herb))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn craft_upgrade() {
+        let mut game = Game::new();
+        for _ in 0..3 {
+            game.add_item(Box::new(Material::Ore));
+        }
+
+        // nothing to upgrade, materials are kept
+        assert!(craft(&mut game, "shield").is_err());
+        assert_eq!(
+            3,
+            *game.inventory().get(&Key::Material(Material::Ore)).unwrap()
+        );
+
+        game.player.shield = Some(Equipment::shield(5));
+        assert!(craft(&mut game, "shield").is_ok());
+        assert_eq!(6, game.player.shield.as_ref().unwrap().level());
+        assert!(game.inventory().is_empty());
+    }
+
+    #[test]
+    fn craft_ring() {
+        let mut game = Game::new();
+        for _ in 0..5 {
+            game.add_item(Box::new(Material::Fang));
+            game.add_item(Box::new(Material::Ore));
+            game.add_item(Box::new(Material::Herb));
+        }
+        let rings = game.ring_pool.len();
+
+        assert!(craft(&mut game, "ring").is_ok());
+        assert_eq!(rings - 1, game.ring_pool.len());
+        assert_eq!(1, game.inventory().len());
+    }
+}
@@ -1,63 +1,128 @@
 use core::fmt;
 
 use crate::character::class as character;
+use crate::character::class::Passive;
+use crate::character::BAG_SLOTS;
 use crate::game;
 use crate::location;
 use crate::log;
 use serde::{Deserialize, Serialize};
 
+pub mod auto_use;
+pub mod brew;
 pub mod chest;
+pub mod custom;
+pub mod drops;
 pub mod equipment;
 pub mod key;
+pub mod map;
+pub mod material;
 pub mod ring;
+pub mod scroll;
 pub mod shop;
 pub mod stone;
+pub mod throwable;
 
 #[typetag::serde(tag = "type")]
 pub trait Item: fmt::Display {
     fn apply(&mut self, game: &mut game::Game);
     fn key(&self) -> key::Key;
     fn describe(&self) -> String;
+
+    /// Gold paid by the shop when buying the item back.
+    /// Zero if the shop doesn't buy this kind of item.
+    fn sell_price(&self) -> i32 {
+        0
+    }
+}
+
+/// How strong a potion or ether is. Each tier above the lowest can be
+/// brewed from three items of the tier below it.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Tier {
+    #[default]
+    Low,
+    Mid,
+    Hi,
+}
+
+impl Tier {
+    /// How many times the amount of the lowest tier is restored.
+    fn strength(&self) -> i32 {
+        match self {
+            Tier::Low => 1,
+            Tier::Mid => 2,
+            Tier::Hi => 4,
+        }
+    }
+
+    /// How many items of the lowest tier this one is brewed from.
+    fn worth(&self) -> i32 {
+        match self {
+            Tier::Low => 1,
+            Tier::Mid => 3,
+            Tier::Hi => 9,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Potion {
     level: i32,
+    #[serde(default)]
+    tier: Tier,
 }
 
 impl Potion {
     pub fn new(level: i32) -> Self {
-        Self { level }
+        Self::tiered(level, Tier::Low)
+    }
+
+    pub fn tiered(level: i32, tier: Tier) -> Self {
+        Self { level, tier }
     }
 
     fn restores(&self) -> i32 {
-        character::Class::player_first().hp.at(self.level) / 2
+        character::Class::player_first().hp.at(self.level) / 2 * self.tier.strength()
     }
 }
 
 impl fmt::Display for Potion {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "potion[{}]", self.level)
+        write!(f, "{}[{}]", self.key(), self.level)
     }
 }
 
 #[typetag::serde]
 impl Item for Potion {
     fn apply(&mut self, game: &mut game::Game) {
-        let recovered = game.player.update_hp(self.restores()).unwrap();
-        log::heal_item(&game.player, "potion", recovered, 0, false);
+        let mut to_restore = self.restores();
+        if game.player.class.has_passive(Passive::DoublePotion) {
+            to_restore *= 2;
+        }
+        let recovered = game.player.update_hp(to_restore).unwrap();
+        log::heal_item(&game.player, &self.key().to_string(), recovered, 0, false);
     }
 
     fn key(&self) -> key::Key {
-        key::Key::Potion
+        match self.tier {
+            Tier::Low => key::Key::Potion,
+            Tier::Mid => key::Key::MidPotion,
+            Tier::Hi => key::Key::HiPotion,
+        }
     }
 
     fn describe(&self) -> String {
         format!("restores {}hp", self.restores())
     }
+
+    fn sell_price(&self) -> i32 {
+        self.level * 100 * self.tier.worth()
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Escape {}
 
 impl Escape {
@@ -77,7 +142,11 @@ impl Item for Escape {
     }
 
     fn describe(&self) -> String {
-        String::from("transports the player safely back home")
+        String::from("transports the player safely back home, or to a visited directory")
+    }
+
+    fn sell_price(&self) -> i32 {
+        500
     }
 }
 
@@ -87,7 +156,7 @@ impl fmt::Display for Escape {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Remedy {}
 
 impl Remedy {
@@ -99,8 +168,11 @@ impl Remedy {
 #[typetag::serde]
 impl Item for Remedy {
     fn apply(&mut self, game: &mut game::Game) {
-        let healed = game.player.status_effect.take().is_some();
+        let healed = game.player.status_effects.cure();
         log::heal_item(&game.player, "remedy", 0, 0, healed);
+        if game.player.lift_curses() {
+            log::curses_lifted(&game.player);
+        }
     }
 
     fn key(&self) -> key::Key {
@@ -108,7 +180,11 @@ impl Item for Remedy {
     }
 
     fn describe(&self) -> String {
-        String::from("removes status ailments")
+        String::from("removes status ailments and lifts curses")
+    }
+
+    fn sell_price(&self) -> i32 {
+        200
     }
 }
 
@@ -118,42 +194,175 @@ impl fmt::Display for Remedy {
     }
 }
 
+/// A rare potion that fully restores the hero's hp and mp.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Elixir {}
+
+impl Elixir {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[typetag::serde]
+impl Item for Elixir {
+    fn apply(&mut self, game: &mut game::Game) {
+        let recovered_hp = game.player.update_hp(game.player.max_hp()).unwrap();
+        let recovered_mp = game.player.update_mp(game.player.max_mp());
+        log::heal_item(&game.player, "elixir", recovered_hp, recovered_mp, false);
+    }
+
+    fn key(&self) -> key::Key {
+        key::Key::Elixir
+    }
+
+    fn describe(&self) -> String {
+        String::from("restores all hp and mp")
+    }
+
+    fn sell_price(&self) -> i32 {
+        1500
+    }
+}
+
+impl fmt::Display for Elixir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "elixir")
+    }
+}
+
+/// A rare item that brings the hero back from death, used up
+/// automatically instead of dying.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PhoenixDown {}
+
+impl PhoenixDown {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[typetag::serde]
+impl Item for PhoenixDown {
+    /// Phoenix downs can't be used directly, put it back in the inventory.
+    fn apply(&mut self, game: &mut game::Game) {
+        game.add_item(Box::new(self.clone()));
+    }
+
+    fn key(&self) -> key::Key {
+        key::Key::PhoenixDown
+    }
+
+    fn describe(&self) -> String {
+        String::from("brings the hero back from death with half their hp")
+    }
+
+    fn sell_price(&self) -> i32 {
+        2500
+    }
+}
+
+impl fmt::Display for PhoenixDown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "phoenix-down")
+    }
+}
+
+/// A bag that permanently adds inventory slots to the hero when used.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Bag {}
+
+impl Bag {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[typetag::serde]
+impl Item for Bag {
+    fn apply(&mut self, game: &mut game::Game) {
+        game.player.bags += 1;
+        log::bag(game.player.inventory_slots());
+    }
+
+    fn key(&self) -> key::Key {
+        key::Key::Bag
+    }
+
+    fn describe(&self) -> String {
+        format!("adds {} inventory slots", BAG_SLOTS)
+    }
+}
+
+impl fmt::Display for Bag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bag")
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Ether {
     level: i32,
+    #[serde(default)]
+    tier: Tier,
 }
 
 impl Ether {
     pub fn new(level: i32) -> Self {
-        Self { level }
+        Self::tiered(level, Tier::Low)
+    }
+
+    pub fn tiered(level: i32, tier: Tier) -> Self {
+        Self { level, tier }
     }
 }
 
 impl fmt::Display for Ether {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "ether[{}]", self.level)
+        write!(f, "{}[{}]", self.key(), self.level)
     }
 }
 
 #[typetag::serde]
 impl Item for Ether {
     fn apply(&mut self, game: &mut game::Game) {
-        let to_restore = game
+        let mut to_restore = game
             .player
             .class
             .mp
             .as_ref()
-            .map_or(0, |mp| mp.at(self.level));
+            .map_or(0, |mp| mp.at(self.level))
+            * self.tier.strength();
+        if game.player.class.has_passive(Passive::DoubleEther) {
+            to_restore *= 2;
+        }
         let recovered_mp = game.player.update_mp(to_restore);
 
-        log::heal_item(&game.player, "ether", 0, recovered_mp, false);
+        log::heal_item(
+            &game.player,
+            &self.key().to_string(),
+            0,
+            recovered_mp,
+            false,
+        );
     }
 
     fn key(&self) -> key::Key {
-        key::Key::Ether
+        match self.tier {
+            Tier::Low => key::Key::Ether,
+            Tier::Mid => key::Key::MidEther,
+            Tier::Hi => key::Key::HiEther,
+        }
     }
 
     fn describe(&self) -> String {
-        format!("restores level {} amount mp", self.level)
+        match self.tier.strength() {
+            1 => format!("restores level {} amount mp", self.level),
+            strength => format!("restores {}x level {} amount mp", strength, self.level),
+        }
+    }
+
+    fn sell_price(&self) -> i32 {
+        self.level * 125 * self.tier.worth()
     }
 }
@@ -1,5 +1,6 @@
 use super::{key, Item};
 use crate::game;
+use crate::log;
 use core::fmt;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
@@ -29,12 +30,144 @@ pub enum Ring {
     Revive,
     Chest,
     Gold,
+    Trap,
+    Balance,
     Diamond,
+    /// Only obtained with arena tokens.
+    Vampire,
+
+    // upgraded rings, only found after starting a New Game+
+    AttackPlus,
+    DeffensePlus,
+    SpeedPlus,
+    MagicPlus,
+    MPPlus,
+    HPPlus,
+}
+
+/// How many times a stat ring can be upgraded at the shop.
+pub const MAX_TIER: i32 = 2;
+
+/// Extra passive effects granted when wearing both rings of a matching pair.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetBonus {
+    /// Increases physical attack and defense.
+    Might,
+    /// Increases magical attack.
+    Arcane,
+    /// Increases speed.
+    Swift,
+    /// Triples chest finding frequency.
+    Treasure,
+}
+
+/// The ring pairs that grant a set bonus, in any order. Upgraded rings
+/// count as their base ring.
+const SET_BONUSES: [(Ring, Ring, SetBonus); 4] = [
+    (Ring::Attack, Ring::Deffense, SetBonus::Might),
+    (Ring::Magic, Ring::MP, SetBonus::Arcane),
+    (Ring::Speed, Ring::Evade, SetBonus::Swift),
+    (Ring::Chest, Ring::Gold, SetBonus::Treasure),
+];
+
+impl SetBonus {
+    /// The bonus granted by wearing the given pair of rings, if any.
+    pub fn of(left: &Ring, right: &Ring) -> Option<Self> {
+        let (left, right) = (left.base(), right.base());
+        SET_BONUSES
+            .iter()
+            .find(|(a, b, _)| (*a == left && *b == right) || (*a == right && *b == left))
+            .map(|(_, _, bonus)| *bonus)
+    }
+
+    /// The extra factor applied to the base stat modified by the given ring,
+    /// on top of the ring's own factor.
+    pub fn factor(&self, ring: &Ring) -> f64 {
+        match (self, ring) {
+            (SetBonus::Might, Ring::Attack) => 0.25,
+            (SetBonus::Might, Ring::Deffense) => 0.25,
+            (SetBonus::Arcane, Ring::Magic) => 0.25,
+            (SetBonus::Swift, Ring::Speed) => 0.25,
+            _ => 0.0,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let str = match self {
+            SetBonus::Might => "increases physical attack and defense",
+            SetBonus::Arcane => "increases magical attack",
+            SetBonus::Swift => "increases speed",
+            SetBonus::Treasure => "triples chest finding frequency",
+        };
+        str.to_string()
+    }
+}
+
+impl fmt::Display for SetBonus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SetBonus::Might => "might",
+            SetBonus::Arcane => "arcane",
+            SetBonus::Swift => "swift",
+            SetBonus::Treasure => "treasure",
+        };
+        write!(f, "{}", name)
+    }
 }
 
 impl Ring {
+    /// The rings of a regular game.
     pub fn set() -> HashSet<Ring> {
-        Ring::iter().collect()
+        Ring::iter().filter(|ring| !ring.is_upgraded()).collect()
+    }
+
+    /// The rings of a regular game that are found in chests, and so can be
+    /// collected, leaving out the bought ones.
+    pub fn found_set() -> HashSet<Ring> {
+        Ring::set()
+            .into_iter()
+            .filter(|ring| !ring.is_bought())
+            .collect()
+    }
+
+    /// The second pool of rings, with upgraded effects, that's added to
+    /// the game after starting a New Game+.
+    pub fn upgraded_set() -> HashSet<Ring> {
+        Ring::iter().filter(|ring| ring.is_upgraded()).collect()
+    }
+
+    /// Whether the ring is bought instead of found in chests, so it's never
+    /// in the ring pool: the diamond ring at the shop and the arena rings.
+    pub fn is_bought(&self) -> bool {
+        matches!(self, Ring::Diamond | Ring::Vampire)
+    }
+
+    pub fn is_upgraded(&self) -> bool {
+        *self != self.base()
+    }
+
+    /// The regular ring this one is an upgrade of, or itself if it's not
+    /// an upgraded ring.
+    pub fn base(&self) -> Ring {
+        match self {
+            Ring::AttackPlus => Ring::Attack,
+            Ring::DeffensePlus => Ring::Deffense,
+            Ring::SpeedPlus => Ring::Speed,
+            Ring::MagicPlus => Ring::Magic,
+            Ring::MPPlus => Ring::MP,
+            Ring::HPPlus => Ring::HP,
+            ring => ring.clone(),
+        }
+    }
+
+    /// Whether the ring can be upgraded at the shop, i.e. it modifies a stat.
+    pub fn is_upgradable(&self) -> bool {
+        self.factor() > 0.0
+    }
+
+    /// Gold needed to upgrade a ring to the given tier, along with a level stone.
+    pub fn upgrade_cost(tier: i32) -> i32 {
+        1000 * tier
     }
 
     /// For stat modifying stats, return the factor that should be
@@ -47,6 +180,12 @@ impl Ring {
             Ring::Magic => 0.5,
             Ring::MP => 0.5,
             Ring::HP => 0.5,
+            Ring::AttackPlus => 1.0,
+            Ring::DeffensePlus => 1.0,
+            Ring::SpeedPlus => 1.0,
+            Ring::MagicPlus => 1.0,
+            Ring::MPPlus => 1.0,
+            Ring::HPPlus => 1.0,
             _ => 0.0,
         }
     }
@@ -66,6 +205,9 @@ impl Item for Ring {
         if let Some(removed) = game.player.equip_ring(self.clone()) {
             game.add_item(Box::new(removed));
         }
+        if let Some(bonus) = game.player.set_bonus() {
+            log::set_bonus(&bonus);
+        }
     }
 
     fn key(&self) -> key::Key {
@@ -92,9 +234,53 @@ impl Item for Ring {
             Ring::Counter => "counter-attack when an attack is received",
             Ring::Revive => "come back from dead during battle",
             Ring::Chest => "doubles chest finding frequency",
-            Ring::Gold => "doubles gold gained in battles and chests",
+            Ring::Gold => "doubles battle gold, makes chest jackpots likelier",
+            Ring::Trap => "helps avoiding and disarming chest traps",
+            Ring::Balance => "prevents chaos stones from lowering stats",
             Ring::Diamond => "looks expensive",
+            Ring::Vampire => "recovers hp with each hit",
+            Ring::AttackPlus => "greatly increases physical attack",
+            Ring::DeffensePlus => "greatly increases defense",
+            Ring::SpeedPlus => "greatly increases speed",
+            Ring::MagicPlus => "greatly increases magical attack",
+            Ring::MPPlus => "greatly increases max mp",
+            Ring::HPPlus => "greatly increases max hp",
         };
         str.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgraded_rings() {
+        assert!(!Ring::set().contains(&Ring::AttackPlus));
+        assert!(Ring::set().contains(&Ring::Vampire));
+        assert!(!Ring::found_set().contains(&Ring::Vampire));
+        assert!(!Ring::found_set().contains(&Ring::Diamond));
+        assert!(Ring::upgraded_set().contains(&Ring::AttackPlus));
+        assert_eq!(Ring::Attack, Ring::AttackPlus.base());
+        assert!(!Ring::Attack.is_upgraded());
+        assert!(Ring::AttackPlus.factor() > Ring::Attack.factor());
+    }
+
+    #[test]
+    fn set_bonuses() {
+        assert_eq!(
+            Some(SetBonus::Swift),
+            SetBonus::of(&Ring::Speed, &Ring::Evade)
+        );
+        assert_eq!(
+            Some(SetBonus::Swift),
+            SetBonus::of(&Ring::Evade, &Ring::Speed)
+        );
+        assert_eq!(
+            Some(SetBonus::Might),
+            SetBonus::of(&Ring::DeffensePlus, &Ring::Attack)
+        );
+        assert_eq!(None, SetBonus::of(&Ring::Speed, &Ring::Gold));
+        assert_eq!(None, SetBonus::of(&Ring::Speed, &Ring::SpeedPlus));
+    }
+}
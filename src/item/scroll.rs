@@ -0,0 +1,38 @@
+use super::key::Key;
+use super::Item;
+use crate::character::spell::Spell;
+use crate::game::Game;
+use crate::log;
+use core::fmt;
+use serde::{Deserialize, Serialize};
+
+/// Scrolls teach a spell to the hero, even if it's not one its class would
+/// learn. Only characters of magic classes can use them.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Scroll(pub Spell);
+
+impl fmt::Display for Scroll {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.key())
+    }
+}
+
+#[typetag::serde]
+impl Item for Scroll {
+    fn apply(&mut self, game: &mut Game) {
+        game.player.learn_spell(self.0);
+        log::learn_spell(&game.player, self.0);
+    }
+
+    fn key(&self) -> Key {
+        Key::Scroll(self.0)
+    }
+
+    fn describe(&self) -> String {
+        format!("teaches the {} spell, which {}", self.0, self.0.describe())
+    }
+
+    fn sell_price(&self) -> i32 {
+        250
+    }
+}
@@ -2,9 +2,9 @@ use std::fmt::Display;
 
 use super::equipment::Equipment;
 use super::key::Key;
-use super::ring::Ring;
+use super::ring::{self, Ring};
 use super::Item;
-use crate::character::Character;
+use crate::character;
 use crate::game::Game;
 use crate::log;
 use crate::quest;
@@ -17,9 +17,9 @@ pub fn list(game: &Game) -> Result<()> {
         bail!("Shop is only allowed at home.");
     }
 
-    let items = available_items(&game.player)
+    let items = available_items(game)
         .iter()
-        .map(|s| (s.cost(), s.to_string()))
+        .map(|s| (game.reputation.price(s.cost()), s.to_string()))
         .collect();
     log::shop_list(game, items);
     Ok(())
@@ -41,18 +41,23 @@ pub fn buy(game: &mut Game, item_keys: &[Key]) -> Result<()> {
     // Buy one at a time and break on first error
     for key in item_keys {
         // get list every time to prevent e.g. buying the sword twice
-        let item = available_items(&game.player)
+        let item = available_items(game)
             .into_iter()
             .find(|s| s.to_key() == *key);
 
         if let Some(item) = item {
-            let item_cost = item.cost();
+            let item_cost = game.reputation.price(item.cost());
 
             if game.gold < item_cost {
                 error = "Not enough gold.".to_string();
                 break;
             }
+            if item.to_inventory() && !game.has_room_for(key) {
+                error = "Not enough room in the inventory.".to_string();
+                break;
+            }
             game.gold -= item_cost;
+            game.stats.spend(item_cost);
             item.add_to(game);
 
             total_cost += item_cost;
@@ -72,19 +77,169 @@ pub fn buy(game: &mut Game, item_keys: &[Key]) -> Result<()> {
     Ok(())
 }
 
-/// Build a list of items currently available at the shop
-fn available_items(player: &Character) -> Vec<Box<dyn Shoppable>> {
-    let mut items = Vec::<Box<dyn Shoppable>>::new();
-    let level = player.rounded_level();
+/// Restore the durability of the hero's worn equipment, paying for as many
+/// pieces as possible. Will bail if there's not enough gold for all of them,
+/// only after reporting what was repaired.
+pub fn repair(game: &mut Game) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Shop is only allowed at home.");
+    }
+
+    let mut gold = game.gold;
+    let mut repaired = Vec::new();
+    let mut error = String::from("");
+    for piece in game
+        .player
+        .equipment_slots()
+        .iter_mut()
+        .filter_map(|p| p.as_mut())
+    {
+        let cost = piece.repair_cost();
+        if cost == 0 {
+            continue;
+        }
+        if gold < cost {
+            error = "Not enough gold.".to_string();
+            break;
+        }
+        gold -= cost;
+        piece.repair();
+        repaired.push(piece.to_string());
+    }
+
+    log::repair(game.gold - gold, &repaired);
+    game.stats.spend(game.gold - gold);
+    game.gold = gold;
+    if !error.is_empty() {
+        bail!(error);
+    }
+    Ok(())
+}
+
+/// Upgrade the effect of an equipped stat ring to its next tier, paying gold
+/// and consuming a level stone.
+pub fn upgrade_ring(game: &mut Game, key: &Key) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Shop is only allowed at home.");
+    }
+
+    let ring = if let Key::Ring(ring) = key {
+        ring
+    } else {
+        bail!("{} is not a ring.", key);
+    };
+    let player = &game.player;
+    if player.left_ring.as_ref() != Some(ring) && player.right_ring.as_ref() != Some(ring) {
+        bail!("{} is not equipped.", key);
+    }
+    if !ring.is_upgradable() {
+        bail!("{} can't be upgraded.", key);
+    }
+    let tier = player.ring_tier(ring) + 1;
+    if tier > ring::MAX_TIER {
+        bail!("{} is already fully upgraded.", key);
+    }
+    if !game.inventory.contains_key(&Key::LevelStone) {
+        bail!("A {} is needed to upgrade a ring.", Key::LevelStone);
+    }
+    let cost = Ring::upgrade_cost(tier);
+    if game.gold < cost {
+        bail!("Not enough gold.");
+    }
+
+    game.gold -= cost;
+    game.stats.spend(cost);
+    game.remove_item(&Key::LevelStone);
+    game.player.upgrade_ring(ring);
+    log::ring_upgrade(ring, tier, cost);
+    Ok(())
+}
+
+/// Sell as much as possible from the given item list, buying them back from
+/// the inventory at half their price.
+/// Will stop selling if there's an error (the item is not in the inventory or
+/// the shop doesn't buy it), but will keep the gold earned so far.
+/// Will bail on error only after reporting what was sold.
+pub fn sell(game: &mut Game, item_keys: &[Key]) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Shop is only allowed at home.");
+    }
+
+    let mut item_counts = HashMap::new();
+    let mut total_price = 0;
+    let mut error = String::from("");
 
-    let sword = Equipment::sword(level);
-    if sword.is_upgrade_from(&player.sword) {
-        items.push(Box::new(sword));
+    for key in item_keys {
+        let price = game
+            .inventory
+            .get(key)
+            .and_then(|items| items.last())
+            .map(|item| item.sell_price());
+
+        match price {
+            None => {
+                error = format!("{} not in inventory.", key);
+                break;
+            }
+            Some(0) => {
+                error = format!("{} can't be sold.", key);
+                break;
+            }
+            Some(price) => {
+                let items = game.inventory.get_mut(key).unwrap();
+                items.pop();
+                if items.is_empty() {
+                    game.inventory.remove(key);
+                }
+                game.gold += price;
+                game.stats.earn(price);
+                total_price += price;
+                *item_counts.entry(key.clone()).or_insert(0) += 1;
+            }
+        }
     }
 
-    let shield = Equipment::shield(level);
-    if shield.is_upgrade_from(&player.shield) {
-        items.push(Box::new(shield));
+    log::shop_sell(total_price, &item_counts);
+    if !error.is_empty() {
+        bail!(error);
+    }
+    Ok(())
+}
+
+/// Gold asked per level of the equipment sold at the shop.
+pub const EQUIPMENT_PRICE: i32 = 500;
+
+/// How many of the rotating stock items are available each day.
+const ROTATING_STOCK: usize = 4;
+
+/// Build a list of items currently available at the shop.
+/// Consumables are always in stock, while the equipment and escapes offered
+/// rotate each in-game day.
+fn available_items(game: &Game) -> Vec<Box<dyn Shoppable>> {
+    let player = &game.player;
+    let mut items = Vec::<Box<dyn Shoppable>>::new();
+    let level = player.rounded_level();
+
+    let mut rotating: Vec<Box<dyn Shoppable>> = vec![
+        Box::new(Equipment::sword(level)),
+        Box::new(Equipment::shield(level)),
+        Box::new(Equipment::helmet(level)),
+        Box::new(Equipment::armor(level)),
+        Box::new(Equipment::boots(level)),
+        Box::new(super::Escape::new()),
+    ];
+    let start = game.day as usize % rotating.len();
+    rotating.rotate_left(start);
+    rotating.truncate(ROTATING_STOCK);
+    for item in rotating {
+        // don't offer equipment that wouldn't be an upgrade or can't be replaced
+        let key = item.to_key();
+        if let Some(equipped) = player.equipment(&key) {
+            if equipped.level() >= level || equipped.is_cursed() {
+                continue;
+            }
+        }
+        items.push(item);
     }
 
     let potion = super::Potion::new(level);
@@ -96,8 +251,9 @@ fn available_items(player: &Character) -> Vec<Box<dyn Shoppable>> {
     let remedy = super::Remedy::new();
     items.push(Box::new(remedy));
 
-    let escape = super::Escape::new();
-    items.push(Box::new(escape));
+    if player.bags < character::MAX_BAGS {
+        items.push(Box::new(super::Bag::new()));
+    }
 
     if player.level >= 25 {
         items.push(Box::new(Ring::Diamond));
@@ -110,24 +266,30 @@ trait Shoppable: Display {
     fn cost(&self) -> i32;
     fn add_to(&self, game: &mut Game);
     fn to_key(&self) -> Key;
+
+    /// Whether the item goes to the inventory once bought, instead of
+    /// being equipped right away.
+    fn to_inventory(&self) -> bool {
+        true
+    }
 }
 
 impl Shoppable for Equipment {
     fn cost(&self) -> i32 {
-        self.level() * 500
+        self.level() * EQUIPMENT_PRICE
     }
 
     fn add_to(&self, game: &mut Game) {
-        match self.key() {
-            Key::Sword => game.player.sword = Some(self.clone()),
-            Key::Shield => game.player.shield = Some(self.clone()),
-            _ => {}
-        }
+        game.player.equip(self.clone());
     }
 
     fn to_key(&self) -> Key {
         self.key()
     }
+
+    fn to_inventory(&self) -> bool {
+        false
+    }
 }
 
 impl Shoppable for super::Potion {
@@ -186,6 +348,20 @@ impl Shoppable for super::Ether {
     }
 }
 
+impl Shoppable for super::Bag {
+    fn cost(&self) -> i32 {
+        2000
+    }
+
+    fn add_to(&self, game: &mut Game) {
+        game.add_item(Box::new(self.clone()));
+    }
+
+    fn to_key(&self) -> Key {
+        self.key()
+    }
+}
+
 impl Shoppable for Ring {
     fn cost(&self) -> i32 {
         50_000
@@ -202,9 +378,20 @@ impl Shoppable for Ring {
 
 #[cfg(test)]
 mod tests {
-    use super::super::Potion;
+    use super::super::{stone, Potion};
     use super::*;
 
+    #[test]
+    fn buy_discounted() {
+        let mut game = Game::new();
+        game.gold = 1000;
+        game.reputation
+            .change(crate::faction::Faction::Merchants, 50);
+
+        buy(&mut game, &[Key::Potion]).unwrap();
+        assert_eq!(840, game.gold);
+    }
+
     #[test]
     fn buy_one() {
         let potion = Potion::new(1);
@@ -265,4 +452,110 @@ mod tests {
         assert_eq!(1, *game.inventory().get(&Key::Potion).unwrap());
         assert!(game.player.shield.is_some());
     }
+
+    #[test]
+    fn rotating_stock() {
+        let mut game = Game::new();
+        let keys = |game: &Game| {
+            available_items(game)
+                .iter()
+                .map(|i| i.to_key())
+                .collect::<Vec<_>>()
+        };
+
+        let today = keys(&game);
+        assert!(today.contains(&Key::Sword));
+        assert!(!today.contains(&Key::Escape));
+        assert!(today.contains(&Key::Potion));
+        assert_eq!(today, keys(&game));
+
+        game.day = 2;
+        let later = keys(&game);
+        assert!(!later.contains(&Key::Sword));
+        assert!(later.contains(&Key::Escape));
+        assert!(later.contains(&Key::Potion));
+    }
+
+    #[test]
+    fn sell_items() {
+        let mut game = Game::new();
+        game.add_item(Box::new(Potion::new(1)));
+        game.add_item(Box::new(Potion::new(1)));
+        game.add_item(Box::new(super::super::stone::Health));
+
+        let result = sell(&mut game, &[Key::Potion, Key::HealthStone, Key::Potion]);
+        assert!(result.is_err());
+        assert_eq!(100, game.gold);
+        assert_eq!(1, *game.inventory().get(&Key::Potion).unwrap());
+        assert_eq!(1, *game.inventory().get(&Key::HealthStone).unwrap());
+
+        let result = sell(&mut game, &[Key::Potion, Key::Potion]);
+        assert!(result.is_err());
+        assert_eq!(200, game.gold);
+        assert!(!game.inventory().contains_key(&Key::Potion));
+    }
+
+    #[test]
+    fn repair_equipment() {
+        let mut game = Game::new();
+        let mut sword = Equipment::sword(5);
+        let mut shield = Equipment::shield(5);
+        for _ in 0..10 {
+            sword.wear();
+            shield.wear();
+        }
+        game.player.sword = Some(sword);
+        game.player.shield = Some(shield);
+        game.player.boots = Some(Equipment::boots(5));
+        game.gold = 60;
+
+        // can only afford the sword
+        let result = repair(&mut game);
+        assert!(result.is_err());
+        assert_eq!(10, game.gold);
+        assert_eq!(0, game.player.sword.as_ref().unwrap().repair_cost());
+        assert_eq!(50, game.player.shield.as_ref().unwrap().repair_cost());
+
+        game.gold = 50;
+        assert!(repair(&mut game).is_ok());
+        assert_eq!(0, game.gold);
+        assert_eq!(0, game.player.shield.as_ref().unwrap().repair_cost());
+    }
+
+    #[test]
+    fn upgrade_equipped_ring() {
+        let mut game = Game::new();
+        let key = Key::Ring(Ring::Speed);
+        game.gold = 2500;
+        game.add_item(Box::new(stone::Level));
+
+        // needs to be equipped
+        assert!(upgrade_ring(&mut game, &key).is_err());
+        game.player.equip_ring(Ring::Speed);
+        assert!(upgrade_ring(&mut game, &Key::Ring(Ring::Gold)).is_err());
+
+        assert!(upgrade_ring(&mut game, &key).is_ok());
+        assert_eq!(1, game.player.ring_tier(&Ring::Speed));
+        assert_eq!(1500, game.gold);
+        assert!(!game.inventory.contains_key(&Key::LevelStone));
+
+        // needs another stone
+        assert!(upgrade_ring(&mut game, &key).is_err());
+        game.add_item(Box::new(stone::Level));
+        game.add_item(Box::new(stone::Level));
+        game.gold = 1999;
+        assert!(upgrade_ring(&mut game, &key).is_err());
+        game.gold = 2000;
+        assert!(upgrade_ring(&mut game, &key).is_ok());
+        assert_eq!(2, game.player.ring_tier(&Ring::Speed));
+
+        // can't go past the max tier
+        game.gold = 5000;
+        assert!(upgrade_ring(&mut game, &key).is_err());
+        assert_eq!(5000, game.gold);
+
+        // only stat rings are upgraded
+        game.player.equip_ring(Ring::Evade);
+        assert!(upgrade_ring(&mut game, &Key::Ring(Ring::Evade)).is_err());
+    }
 }
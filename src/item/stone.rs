@@ -1,7 +1,12 @@
 use super::{key, Item};
+use crate::challenge;
 use crate::game;
+use crate::hook;
 use crate::log;
+use crate::plugin;
 use crate::quest;
+use crate::randomizer::choose;
+use crate::speedrun;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -19,10 +24,23 @@ pub struct Speed;
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Level;
 
+/// How many times a chaos stone raises the stat it boosts.
+const CHAOS_RAISES: i32 = 3;
+
+/// Greatly raises a random stat, but lowers another one unless a balance
+/// ring is equipped.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Chaos;
+
+/// Takes back the stat raises of the stones used so far, refunding them as
+/// points to spend again on any stat.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Forget;
+
 #[typetag::serde]
 impl Item for Health {
     fn apply(&mut self, game: &mut game::Game) {
-        let inc = game.player.raise_hp();
+        let inc = game.player.raise_with_stone("hp");
         log(game, "hp", inc);
     }
 
@@ -38,7 +56,7 @@ impl Item for Health {
 #[typetag::serde]
 impl Item for Magic {
     fn apply(&mut self, game: &mut game::Game) {
-        let inc = game.player.raise_mp();
+        let inc = game.player.raise_with_stone("mp");
         log(game, "mp", inc);
     }
 
@@ -54,7 +72,7 @@ impl Item for Magic {
 #[typetag::serde]
 impl Item for Power {
     fn apply(&mut self, game: &mut game::Game) {
-        let inc = game.player.raise_strength();
+        let inc = game.player.raise_with_stone("str");
         log(game, "str", inc);
     }
 
@@ -70,7 +88,7 @@ impl Item for Power {
 #[typetag::serde]
 impl Item for Speed {
     fn apply(&mut self, game: &mut game::Game) {
-        let inc = game.player.raise_speed();
+        let inc = game.player.raise_with_stone("spd");
         log(game, "spd", inc);
     }
 
@@ -89,6 +107,10 @@ impl Item for Level {
         game.player.raise_level();
         log(game, "level", 1);
         quest::level_up(game, 1);
+        challenge::level_up(game, 1);
+        speedrun::level_up(game);
+        hook::level_up(game);
+        plugin::level_up(game);
     }
 
     fn key(&self) -> key::Key {
@@ -100,6 +122,51 @@ impl Item for Level {
     }
 }
 
+#[typetag::serde]
+impl Item for Forget {
+    fn apply(&mut self, game: &mut game::Game) {
+        let refunded = game.player.forget_stones();
+        log::stones_forgotten(refunded);
+    }
+
+    fn key(&self) -> key::Key {
+        key::Key::ForgetStone
+    }
+
+    fn describe(&self) -> String {
+        String::from("refunds the stats raised by stones")
+    }
+}
+
+#[typetag::serde]
+impl Item for Chaos {
+    fn apply(&mut self, game: &mut game::Game) {
+        let stats = game.player.stone_stats();
+        let boosted = *choose(&stats).unwrap();
+        let increase = (0..CHAOS_RAISES)
+            .map(|_| game.player.raise_stat(boosted))
+            .sum();
+        log(game, boosted, increase);
+
+        if game.player.balance_ring() {
+            log::chaos_balanced();
+            return;
+        }
+        let others: Vec<&str> = stats.into_iter().filter(|s| *s != boosted).collect();
+        let lowered = *choose(&others).unwrap();
+        let decrease = game.player.lower_stat(lowered);
+        log::stat_decrease(&game.player, lowered, decrease);
+    }
+
+    fn key(&self) -> key::Key {
+        key::Key::ChaosStone
+    }
+
+    fn describe(&self) -> String {
+        String::from("greatly raises a random stat, lowers another")
+    }
+}
+
 fn log(game: &mut game::Game, stat: &'static str, increase: i32) {
     log::stat_increase(&game.player, stat, increase);
 }
@@ -134,3 +201,15 @@ impl std::fmt::Display for Level {
         write!(f, "{}", self.key())
     }
 }
+
+impl std::fmt::Display for Chaos {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.key())
+    }
+}
+
+impl std::fmt::Display for Forget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.key())
+    }
+}
@@ -0,0 +1,62 @@
+use super::key::Key;
+use super::Item;
+use crate::game::Game;
+use core::fmt;
+use serde::{Deserialize, Serialize};
+use strum_macros::EnumIter;
+
+/// Items thrown at the enemies during battles, instead of attacking.
+/// They have no effect outside of battles.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, EnumIter, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Throwable {
+    /// Deals fire damage to every enemy and burns them.
+    #[default]
+    Bomb,
+    /// Deals damage to the target regardless of its defense.
+    Shuriken,
+    /// Hurts an undead target badly, and stuns any other.
+    HolyWater,
+}
+
+impl Throwable {
+    /// Whether the item hits every enemy standing, not only the target.
+    pub fn hits_all(&self) -> bool {
+        *self == Throwable::Bomb
+    }
+}
+
+impl fmt::Display for Throwable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.key())
+    }
+}
+
+#[typetag::serde]
+impl Item for Throwable {
+    /// Throwables can only be used in battle, put it back in the inventory.
+    fn apply(&mut self, game: &mut Game) {
+        game.add_item(Box::new(*self));
+    }
+
+    fn key(&self) -> Key {
+        Key::Throwable(*self)
+    }
+
+    fn describe(&self) -> String {
+        let description = match self {
+            Throwable::Bomb => "thrown in battle, burns every enemy",
+            Throwable::Shuriken => "thrown in battle, hits the enemy through its defense",
+            Throwable::HolyWater => "thrown in battle, hurts the undead and stuns the rest",
+        };
+        String::from(description)
+    }
+
+    fn sell_price(&self) -> i32 {
+        match self {
+            Throwable::Bomb => 300,
+            Throwable::Shuriken => 150,
+            Throwable::HolyWater => 250,
+        }
+    }
+}
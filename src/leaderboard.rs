@@ -0,0 +1,128 @@
+use crate::config::{Config, LeaderboardServer};
+use crate::game::Game;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Seconds to wait for the leaderboard server before giving up.
+const TIMEOUT_SECONDS: u64 = 10;
+
+/// The header with the signature of the submitted summary.
+const SIGNATURE_HEADER: &str = "X-Rpg-Signature";
+
+/// What's shared of a game in the leaderboard.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    pub name: String,
+    pub class: String,
+    pub level: i32,
+    /// Seconds spent playing.
+    pub playtime: u64,
+    pub deaths: i32,
+    /// Achievements unlocked.
+    pub achievements: usize,
+}
+
+impl Summary {
+    /// The summary of the game, under the given player name.
+    pub fn of(game: &Game, name: &str) -> Self {
+        let achievements = game
+            .achievements
+            .list()
+            .into_iter()
+            .filter(|(_, progress, goal)| progress == goal)
+            .count();
+        Self {
+            name: name.to_string(),
+            class: game.player.name(),
+            level: game.player.level,
+            playtime: game.stats.playtime,
+            deaths: game.stats.total_deaths(),
+            achievements,
+        }
+    }
+}
+
+/// Post the summary of the game to the leaderboard server set in the
+/// config, signed with its key so the server can tell it comes from a
+/// known player. Return the summary submitted.
+pub fn submit(game: &Game) -> Result<Summary> {
+    let server = server()?;
+    let summary = Summary::of(game, &server.name);
+    let body = serde_json::to_string(&summary)?;
+    agent()
+        .post(&server.url)
+        .set("Content-Type", "application/json")
+        .set(SIGNATURE_HEADER, &sign(&body, &server.key))
+        .send_string(&body)
+        .map_err(|err| anyhow!("Can't submit to the leaderboard: {}", err))?;
+    Ok(summary)
+}
+
+/// The rankings of the leaderboard server set in the config, best first.
+pub fn show() -> Result<Vec<Summary>> {
+    let server = server()?;
+    agent()
+        .get(&server.url)
+        .call()
+        .map_err(|err| anyhow!("Can't read the leaderboard: {}", err))?
+        .into_json()
+        .map_err(|err| anyhow!("Invalid leaderboard rankings: {}", err))
+}
+
+/// The player name, to tell their entries apart in the rankings.
+pub fn player_name() -> Option<&'static str> {
+    Config::get()
+        .leaderboard
+        .as_ref()
+        .map(|server| server.name.as_str())
+}
+
+fn server() -> Result<&'static LeaderboardServer> {
+    Config::get()
+        .leaderboard
+        .as_ref()
+        .ok_or_else(|| anyhow!("No leaderboard server is set in the config."))
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(TIMEOUT_SECONDS))
+        .build()
+}
+
+/// The HMAC-SHA256 of the data with the given key, as hex.
+fn sign(data: &str, key: &str) -> String {
+    hmac_sha256::HMAC::mac(data, key)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::Location;
+
+    #[test]
+    fn summary() {
+        let mut game = Game::new();
+        game.stats.playtime = 120;
+        game.stats.death(&Location::home());
+        let summary = Summary::of(&game, "me");
+        assert_eq!("me", summary.name);
+        assert_eq!(1, summary.level);
+        assert_eq!(120, summary.playtime);
+        assert_eq!(1, summary.deaths);
+        assert_eq!(0, summary.achievements);
+    }
+
+    #[test]
+    fn signature() {
+        // the test vector of RFC 4231
+        assert_eq!(
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            sign("what do ya want for nothing?", "Jefe")
+        );
+    }
+}
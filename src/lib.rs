@@ -0,0 +1,114 @@
+//! The rpg-cli game engine, exposed as a library so it can be embedded in
+//! other frontends besides the command line.
+//!
+//! The [`game::Game`] struct carries all the game state and exposes the
+//! high-level gameplay interface: moving across directories, engaging in
+//! battles, finding chests and using items. The [`datafile`] module handles
+//! loading and saving that state to the rpg data directory.
+//!
+//! Without the default `native` feature, the filesystem layer is left out so
+//! the engine can be built for `wasm32-unknown-unknown` with the `wasm`
+//! feature. Frontends there play in the abstract world, see
+//! [`location::init_abstract`], and keep the game state themselves.
+//!
+//! ```no_run
+//! use rpg_cli::game::{Game, Pickup};
+//! use rpg_cli::location::Location;
+//!
+//! let mut game = rpg_cli::datafile::load()?.unwrap_or_else(Game::new);
+//! let dest = Location::from("~/projects")?;
+//!
+//! if game.go_to(&dest, false, false, None).is_err() {
+//!     // the hero died on the way, start over
+//!     game.reset();
+//! }
+//! if game.inspect(Pickup::All).is_err() {
+//!     game.reset();
+//! }
+//!
+//! rpg_cli::datafile::save(&game)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Game events are printed to stdout by the [`log`] module, which can be
+//! configured to emit JSON lines for easier integration.
+
+/// Long-term goals tracked across heroes and games.
+pub mod achievement;
+/// Opt-in passive training from shell activity.
+pub mod activity;
+/// Waves of enemies fought at home for tokens exchanged for exclusive items.
+pub mod arena;
+/// Gold stored at home, safe from death.
+pub mod bank;
+/// Record of the enemy classes faced by the player.
+pub mod bestiary;
+/// Daily and weekly challenges with bonus rewards.
+pub mod challenge;
+/// Player and enemy characters, their classes and battle mechanics.
+pub mod character;
+/// The world clock, with the time of day and the weather.
+pub mod clock;
+/// User preferences loaded from the config file.
+pub mod config;
+/// Persistence of the game state and customization files.
+#[cfg(feature = "native")]
+pub mod datafile;
+/// Difficulty levels of the game.
+pub mod difficulty;
+/// Simulated battles against the heroes of exported games.
+pub mod duel;
+/// Virtual dungeons explored room by room.
+pub mod dungeon;
+/// Non-hostile characters met away from home, with offers for the hero.
+pub mod encounter;
+/// Factions of the game world and the hero's reputation with them.
+pub mod faction;
+/// Dice games at home to wager gold on.
+pub mod gamble;
+/// The game state and high-level gameplay interface.
+pub mod game;
+/// User commands run on game events.
+pub mod hook;
+/// Translations of the game text.
+pub mod i18n;
+/// Items, equipment, chests and the shop.
+pub mod item;
+/// The shared leaderboard where players submit a summary of their game.
+#[cfg(feature = "leaderboard")]
+pub mod leaderboard;
+/// Filesystem locations as game places.
+pub mod location;
+/// Output of the game events.
+pub mod log;
+/// Rhai scripts that add content and react to game events.
+pub mod plugin;
+/// Quests and their rewards.
+pub mod quest;
+/// Weekly raid bosses, lairing at a directory picked anew each week.
+pub mod raid;
+/// Randomness of the game elements, deterministic in tests and seeded games.
+pub mod randomizer;
+/// Extra xp earned by resting at home between play sessions.
+pub mod rested;
+/// Automated battles and chests to check the game balance.
+pub mod simulation;
+/// Timed runs from the start of the game to the final boss.
+pub mod speedrun;
+/// Statistics of the player's history.
+pub mod stats;
+/// Backends where the saved games are kept.
+#[cfg(feature = "native")]
+pub mod storage;
+/// Copies of the game kept at a remote, to play it on several machines.
+#[cfg(feature = "native")]
+pub mod sync;
+/// Colors of the game output.
+pub mod theme;
+/// Cosmetic titles earned by the player, shown with the hero.
+pub mod title;
+/// Gold and items traded between the games of local profiles.
+#[cfg(feature = "native")]
+pub mod trade;
+/// The world map of the directories explored by the hero.
+pub mod world;
@@ -1,7 +1,37 @@
-use crate::datafile::rpg_dir;
+use crate::activity::SECONDS_PER_DAY;
+use crate::config::Config;
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 use std::path;
 
+/// File that disables encounters and chests in the directory it's placed
+/// and all its subdirectories.
+const IGNORE_FILE: &str = ".rpg-ignore";
+
+/// Set for profiles played in an abstract world, where locations are virtual
+/// places instead of directories of the filesystem.
+static ABSTRACT: OnceCell<bool> = OnceCell::new();
+
+/// Files in a directory from which it adds a level to its enemies and
+/// chests, and another one for each ten times as many.
+const BUSY_DIR_FILES: usize = 50;
+
+/// Most levels a directory can add to its enemies and chests.
+const MAX_DANGER: i32 = 5;
+
+/// The places below every location of the abstract world.
+const ABSTRACT_PLACES: [&str; 6] = ["caves", "forest", "hills", "ruins", "swamp", "tower"];
+
+/// Set whether locations are virtual places instead of real directories.
+pub fn init_abstract(enabled: bool) {
+    let _ = ABSTRACT.set(enabled);
+}
+
+fn is_abstract() -> bool {
+    *ABSTRACT.get().unwrap_or(&false)
+}
+
 #[derive(Serialize, Deserialize, Debug, Eq, Clone)]
 pub struct Location {
     path: path::PathBuf,
@@ -10,19 +40,34 @@ pub struct Location {
 impl Location {
     /// Build a location from the given path string.
     /// The path is validated to exist and converted to it's canonical form.
+    /// In the abstract world any path is valid, and relative ones start
+    /// at home.
     pub fn from(path: &str) -> Result<Self, std::io::Error> {
+        Self::home().resolve(path)
+    }
+
+    /// The location of the given path as typed by the player at this
+    /// location. Relative paths start at the shell working directory, or
+    /// at this location in the abstract world, where the hero doesn't
+    /// follow the shell around.
+    pub fn resolve(&self, path: &str) -> Result<Self, std::io::Error> {
         // if input doesn't come from shell, we want to interpret ~ as home ourselves
         let mut path = patch_oldpwd(path);
         if path.starts_with('~') {
             // TODO figure out these string lossy stuff
-            let home_str = dirs::home_dir().unwrap().to_string_lossy().to_string();
+            let home_str = home_dir().to_string_lossy().to_string();
             path = path.replacen("~", &home_str, 1)
         }
 
         let path = path::Path::new(&path);
+        if is_abstract() {
+            return Ok(Self {
+                path: virtual_path(&self.path, path),
+            });
+        }
         // this is a replacement to std::fs::canonicalize()
         // that circumvents windows quirks with paths
-        let path = dunce::canonicalize(&path)?;
+        let path = dunce::canonicalize(path)?;
         Ok(Self { path })
     }
 
@@ -31,47 +76,335 @@ impl Location {
     }
 
     pub fn home() -> Self {
-        Self {
-            path: dirs::home_dir().unwrap(),
+        Self { path: home_dir() }
+    }
+
+    /// A directory the given number of levels below home, which doesn't
+    /// need to exist, to play out what would happen that far from it.
+    pub fn below_home(depth: i32) -> Self {
+        let mut path = home_dir();
+        for _ in 0..depth {
+            path.push("somewhere");
         }
+        Self { path }
     }
 
     pub fn is_home(&self) -> bool {
-        self.path == dirs::home_dir().unwrap()
+        self.path == home_dir()
     }
 
+    #[cfg(feature = "native")]
     pub fn is_rpg_dir(&self) -> bool {
-        self.path == rpg_dir()
+        self.path == crate::datafile::rpg_dir()
+    }
+
+    #[cfg(not(feature = "native"))]
+    pub fn is_rpg_dir(&self) -> bool {
+        false
     }
 
     /// Return a new location that it's one dir closer to the given destination.
+    /// When the destination is on another drive, the hero goes up to the
+    /// root of this one and then jumps to the root of the other.
     pub fn go_to(&self, dest: &Self) -> Self {
-        let next = if dest.path.starts_with(&self.path) {
-            let self_len = self.path.components().count();
-            dest.path.components().take(self_len + 1).collect()
+        let (from, to) = (Parts::of(&self.path), Parts::of(&dest.path));
+        let root_len = dest.path.components().count() - to.names.len();
+        let next = if from.root == to.root && to.names.starts_with(&from.names) {
+            let depth = root_len + from.names.len() + 1;
+            dest.path.components().take(depth).collect()
+        } else if let Some(parent) = self.path.parent() {
+            parent.to_path_buf()
         } else {
-            self.path.parent().unwrap().to_path_buf()
+            dest.path.components().take(root_len).collect()
         };
         Self { path: next }
     }
 
-    fn distance_from(&self, other: &Self) -> Distance {
-        let mut current = self.path.as_path();
-        let dest = other.path.as_path();
-
-        let mut distance = 0;
-        while !dest.starts_with(&current) {
-            current = current.parent().unwrap();
-            distance += 1;
-        }
-        let dest = dest.strip_prefix(current).unwrap();
-        let len = distance + dest.components().count() as i32;
-        Distance::from(len)
+    pub fn distance_from(&self, other: &Self) -> Distance {
+        let steps = Parts::of(&self.path).steps_to(&Parts::of(&other.path));
+        Distance::from(steps as i32)
     }
 
     pub fn distance_from_home(&self) -> Distance {
         self.distance_from(&Location::home())
     }
+
+    /// Whether this location is a subdirectory, at any depth, of the given one.
+    pub fn is_below(&self, other: &Self) -> bool {
+        self.path != other.path && self.path.starts_with(&other.path)
+    }
+
+    /// The path of this location relative to the given ancestor, e.g. `b/c`
+    /// for `/a/b/c` relative to `/a`.
+    pub fn relative_to(&self, ancestor: &Self) -> String {
+        match self.path.strip_prefix(&ancestor.path) {
+            Ok(path) => path.to_string_lossy().to_string(),
+            Err(_) => self.to_string(),
+        }
+    }
+
+    /// Whether this is a hidden directory, e.g. `~/.config`.
+    pub fn is_hidden(&self) -> bool {
+        self.path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+    }
+
+    /// Whether this directory belongs to a git repository, not counting
+    /// one at or above home.
+    pub fn is_repo(&self) -> bool {
+        if is_abstract() {
+            return false;
+        }
+        let home = home_dir();
+        self.path
+            .ancestors()
+            .take_while(|dir| !home.starts_with(dir))
+            .any(|dir| dir.join(".git").exists())
+    }
+
+    /// Whether this is the system directory for temporary files or one of
+    /// its subdirectories.
+    pub fn is_temp(&self) -> bool {
+        if is_abstract() {
+            return false;
+        }
+        let temp = std::env::temp_dir();
+        let temp = dunce::canonicalize(&temp).unwrap_or(temp);
+        self.path.starts_with(temp)
+    }
+
+    /// Extra levels for the enemies and chests found here, by the size and
+    /// recent activity of the directory, when enabled in the config.
+    pub fn danger(&self) -> i32 {
+        if is_abstract() || !Config::get().directory_scaling {
+            return 0;
+        }
+        self.danger_with(&FsMetadata, crate::activity::now())
+    }
+
+    /// Extra levels for the enemies and chests found here at the given
+    /// time, by the directory stats of the given source.
+    pub fn danger_with(&self, metadata: &dyn Metadata, now: u64) -> i32 {
+        metadata
+            .dir_stats(&self.path)
+            .map_or(0, |stats| stats.danger(now))
+    }
+
+    /// Whether enemies and chests are disabled at this location, by an
+    /// ignore marker file in it or above it, or by a config pattern
+    /// matching it or any of its parents.
+    pub fn is_ignored(&self) -> bool {
+        let patterns = &Config::get().ignore;
+        self.path.ancestors().any(|dir| {
+            patterns.iter().any(|pattern| pattern.matches(dir))
+                || (!is_abstract() && dir.join(IGNORE_FILE).exists())
+        })
+    }
+
+    /// The visible directories right below this location, sorted by path so
+    /// seeded games pick the same ones. The rpg data directory is excluded.
+    pub fn subdirectories(&self) -> Vec<Self> {
+        if is_abstract() {
+            return self.virtual_subdirectories();
+        }
+        let entries = match std::fs::read_dir(&self.path) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+        let mut dirs: Vec<Self> = entries
+            .filter_map(Result::ok)
+            .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+            .map(|entry| Self { path: entry.path() })
+            .filter(|location| location.path.is_dir() && !location.is_rpg_dir())
+            .collect();
+        dirs.sort_by(|a, b| a.path.cmp(&b.path));
+        dirs
+    }
+}
+
+/// What's known of a directory to tell how dangerous it is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DirStats {
+    /// How many entries it has.
+    pub files: usize,
+    /// Unix timestamp of its most recently modified entry, if any.
+    pub modified: Option<u64>,
+}
+
+impl DirStats {
+    /// Extra levels for the enemies and chests of the directory at the given
+    /// time: one for each tenfold of files starting at `BUSY_DIR_FILES`, one
+    /// for changes in the last week and another one for changes in the last
+    /// day.
+    pub fn danger(&self, now: u64) -> i32 {
+        let mut danger = 0;
+        let mut threshold = BUSY_DIR_FILES;
+        while self.files >= threshold {
+            danger += 1;
+            threshold *= 10;
+        }
+        if let Some(modified) = self.modified {
+            let age = now.saturating_sub(modified);
+            if age < 7 * SECONDS_PER_DAY {
+                danger += 1;
+            }
+            if age < SECONDS_PER_DAY {
+                danger += 1;
+            }
+        }
+        danger.min(MAX_DANGER)
+    }
+}
+
+/// Source of the stats of directories, so their danger can be worked out
+/// without looking at the filesystem.
+pub trait Metadata {
+    fn dir_stats(&self, path: &path::Path) -> Option<DirStats>;
+}
+
+/// The stats of directories as found in the filesystem.
+pub struct FsMetadata;
+
+impl Metadata for FsMetadata {
+    fn dir_stats(&self, path: &path::Path) -> Option<DirStats> {
+        let mut stats = DirStats::default();
+        for entry in std::fs::read_dir(path).ok()?.filter_map(Result::ok) {
+            stats.files += 1;
+            let modified = entry
+                .metadata()
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|elapsed| elapsed.as_secs());
+            stats.modified = stats.modified.max(modified);
+        }
+        Some(stats)
+    }
+}
+
+/// A glob pattern for the directories where the game is disabled, e.g.
+/// `**/node_modules` or `~/mnt/*`. A leading `~` stands for home.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(try_from = "String")]
+pub struct Pattern(glob::Pattern);
+
+impl Pattern {
+    fn matches(&self, path: &path::Path) -> bool {
+        let options = glob::MatchOptions {
+            require_literal_separator: true,
+            ..glob::MatchOptions::new()
+        };
+        self.0.matches_path_with(path, options)
+    }
+}
+
+impl TryFrom<String> for Pattern {
+    type Error = String;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        let pattern = match pattern.strip_prefix('~') {
+            Some(rest) => format!("{}{}", home_dir().to_string_lossy(), rest),
+            None => pattern,
+        };
+        glob::Pattern::new(&pattern)
+            .map(Pattern)
+            .map_err(|e| format!("invalid pattern {}: {}", pattern, e))
+    }
+}
+
+/// The home directory, with any symlink or junction in it resolved, the same
+/// as the paths of locations. Where there's none, e.g. in the browser, home
+/// is the root.
+fn home_dir() -> path::PathBuf {
+    let home = dirs::home_dir().unwrap_or_else(|| path::PathBuf::from("/"));
+    dunce::canonicalize(&home).unwrap_or(home)
+}
+
+/// A path split in the parts used to measure distances: the root, which
+/// tells apart Windows drives and network shares, and the names of the
+/// directories below it.
+#[derive(Debug, PartialEq)]
+struct Parts {
+    root: String,
+    names: Vec<std::ffi::OsString>,
+}
+
+impl Parts {
+    fn of(path: &path::Path) -> Self {
+        let mut root = String::new();
+        let mut names = Vec::new();
+        for component in path.components() {
+            match component {
+                path::Component::Prefix(prefix) => root.push_str(&prefix_root(prefix.kind())),
+                path::Component::RootDir => root.push(path::MAIN_SEPARATOR),
+                path::Component::Normal(name) => names.push(name.to_os_string()),
+                _ => {}
+            }
+        }
+        Self { root, names }
+    }
+
+    /// How many directories apart two paths are, going up to their closest
+    /// common ancestor and down from it. Paths on different roots have none,
+    /// so going from one root to the other counts as a single step.
+    fn steps_to(&self, other: &Self) -> usize {
+        if self.root != other.root {
+            return self.names.len() + 1 + other.names.len();
+        }
+        let common = self
+            .names
+            .iter()
+            .zip(other.names.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        self.names.len() + other.names.len() - 2 * common
+    }
+}
+
+/// The root of a Windows path prefix, written the same for all the ways to
+/// refer to a drive or share, e.g. `c:` and `\\?\C:` are both `C:`.
+fn prefix_root(prefix: path::Prefix) -> String {
+    match prefix {
+        path::Prefix::Disk(letter) | path::Prefix::VerbatimDisk(letter) => {
+            format!("{}:", letter.to_ascii_uppercase() as char)
+        }
+        path::Prefix::UNC(server, share) | path::Prefix::VerbatimUNC(server, share) => format!(
+            r"\\{}\{}",
+            server.to_string_lossy(),
+            share.to_string_lossy()
+        )
+        .to_lowercase(),
+        path::Prefix::Verbatim(name) => format!(r"\\?\{}", name.to_string_lossy()),
+        path::Prefix::DeviceNS(name) => format!(r"\\.\{}", name.to_string_lossy()),
+    }
+}
+
+impl Location {
+    fn virtual_subdirectories(&self) -> Vec<Self> {
+        ABSTRACT_PLACES
+            .iter()
+            .map(|place| Self {
+                path: self.path.join(place),
+            })
+            .collect()
+    }
+}
+
+/// The given path, relative to the base one if it isn't absolute, with the
+/// `.` and `..` components solved without looking at the filesystem.
+fn virtual_path(base: &path::Path, path: &path::Path) -> path::PathBuf {
+    let mut resolved = path::PathBuf::new();
+    for component in base.join(path).components() {
+        match component {
+            path::Component::CurDir => {}
+            path::Component::ParentDir => {
+                resolved.pop();
+            }
+            component => resolved.push(component),
+        }
+    }
+    resolved
 }
 
 /// To match the `cd` behavior, when the path '-' is passed try to
@@ -103,7 +436,7 @@ impl std::hash::Hash for Location {
 
 impl std::fmt::Display for Location {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let home = dirs::home_dir().unwrap().to_string_lossy().to_string();
+        let home = home_dir().to_string_lossy().to_string();
         let mut loc = self.path.to_string_lossy().replace(&home, "~");
         if loc == "~" {
             loc = "home".to_string();
@@ -136,6 +469,11 @@ impl Distance {
             Distance::Far(s) => *s,
         }
     }
+
+    /// Whether there's no distance at all, i.e. at home.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 #[cfg(test)]
@@ -193,6 +531,7 @@ pub mod tests {
         assert_eq!(distance("/Users/facundo/other", "/Users/facundo/").len(), 1);
         assert_eq!(distance("/Users/facundo/other", "/").len(), 3);
         assert_eq!(distance("/", "/Users/facundo/other").len(), 3);
+        assert_eq!(Location::below_home(3).distance_from_home().len(), 3);
         assert_eq!(
             distance("/Users/rusty/cage", "/Users/facundo/other").len(),
             4
@@ -202,6 +541,187 @@ pub mod tests {
             4
         );
         assert_eq!(Location::home().distance_from_home().len(), 0);
+        assert!(Location::home().distance_from_home().is_empty());
+        assert!(!distance("/", "/Users").is_empty());
+    }
+
+    #[test]
+    fn test_windows_roots() {
+        use std::ffi::OsStr;
+        use std::path::Prefix;
+
+        assert_eq!("C:", prefix_root(Prefix::Disk(b'c')));
+        assert_eq!("C:", prefix_root(Prefix::VerbatimDisk(b'C')));
+        let share = Prefix::UNC(OsStr::new("Server"), OsStr::new("Games"));
+        let verbatim_share = Prefix::VerbatimUNC(OsStr::new("server"), OsStr::new("games"));
+        assert_eq!(prefix_root(share), prefix_root(verbatim_share));
+        assert_ne!(prefix_root(share), prefix_root(Prefix::Disk(b'C')));
+
+        let parts = |root: &str, names: &[&str]| Parts {
+            root: root.to_string(),
+            names: names.iter().map(|name| name.into()).collect(),
+        };
+        let home = parts(r"C:\", &["Users", "facundo"]);
+        let steps = |to: &Parts| home.steps_to(to);
+        assert_eq!(0, steps(&parts(r"C:\", &["Users", "facundo"])));
+        assert_eq!(2, steps(&parts(r"C:\", &["Users", "rusty"])));
+        assert_eq!(2, steps(&parts(r"C:\", &[])));
+        // up to the drive root, over to the other one and down
+        assert_eq!(3, steps(&parts(r"D:\", &[])));
+        assert_eq!(5, steps(&parts(r"D:\", &["Users", "facundo"])));
+        assert_eq!(4, steps(&parts(r"\\server\games\", &["saves"])));
+        assert_eq!(4, parts(r"D:\", &["dev"]).steps_to(&home));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_walk_across_drives() {
+        let dest = location_from(r"D:\games");
+        let source = location_from(r"C:\Users").go_to(&dest);
+        assert_eq!(location_from(r"C:\"), source);
+        let source = source.go_to(&dest);
+        assert_eq!(location_from(r"D:\"), source);
+        assert_eq!(dest, source.go_to(&dest));
+        assert_eq!(
+            location_from(r"c:\Users")
+                .distance_from(&location_from(r"\\?\C:\Users\dev"))
+                .len(),
+            1
+        );
+        assert_eq!(location_from(r"C:\Users").distance_from(&dest).len(), 3);
+    }
+
+    #[test]
+    fn test_ignore_pattern() {
+        let pattern = |text: &str| Pattern::try_from(text.to_string()).unwrap();
+        let path = |text| path::Path::new(text);
+
+        let modules = pattern("**/node_modules");
+        assert!(modules.matches(path("/Users/facundo/app/node_modules")));
+        assert!(!modules.matches(path("/Users/facundo/app")));
+
+        let mounts = pattern("/mnt/*");
+        assert!(mounts.matches(path("/mnt/share")));
+        assert!(!mounts.matches(path("/mnt/share/dir")));
+        assert!(!mounts.matches(path("/mnt")));
+
+        let build = pattern("~/build");
+        assert!(build.matches(&home_dir().join("build")));
+        assert!(Pattern::try_from(String::from("[build")).is_err());
+    }
+
+    #[test]
+    fn test_ignore_file() {
+        let dir = std::env::temp_dir().join("rpg-ignore-test");
+        let child = dir.join("target").join("debug");
+        std::fs::create_dir_all(&child).unwrap();
+        let location = Location {
+            path: child.clone(),
+        };
+        assert!(!location.is_ignored());
+
+        std::fs::write(dir.join("target").join(IGNORE_FILE), "").unwrap();
+        assert!(location.is_ignored());
+        assert!(!Location { path: dir.clone() }.is_ignored());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_danger() {
+        struct Fake(Option<DirStats>);
+        impl Metadata for Fake {
+            fn dir_stats(&self, _path: &path::Path) -> Option<DirStats> {
+                self.0
+            }
+        }
+
+        let now = 100 * SECONDS_PER_DAY;
+        let stats = |files, modified| {
+            Fake(Some(DirStats {
+                files,
+                modified: Some(modified),
+            }))
+        };
+        let location = location_from("/Users/facundo/dev");
+        assert_eq!(0, location.danger_with(&Fake(None), now));
+        assert_eq!(0, location.danger_with(&stats(10, 0), now));
+        assert_eq!(1, location.danger_with(&stats(BUSY_DIR_FILES, 0), now));
+        assert_eq!(2, location.danger_with(&stats(600, 0), now));
+
+        // recent changes make it more dangerous
+        assert_eq!(
+            1,
+            location.danger_with(&stats(10, now - 2 * SECONDS_PER_DAY), now)
+        );
+        assert_eq!(2, location.danger_with(&stats(10, now - 60), now));
+        assert_eq!(
+            MAX_DANGER,
+            location.danger_with(&stats(1_000_000, now), now)
+        );
+    }
+
+    #[test]
+    fn test_fs_metadata() {
+        let dir = std::env::temp_dir().join("rpg-metadata-test");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("file"), "").unwrap();
+
+        let stats = FsMetadata.dir_stats(&dir).unwrap();
+        assert_eq!(2, stats.files);
+        assert!(stats.modified.is_some());
+        assert!(FsMetadata.dir_stats(&dir.join("missing")).is_none());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_relative() {
+        let parent = location_from("/Users/facundo");
+        let child = location_from("/Users/facundo/dev/rpg");
+        assert!(child.is_below(&parent));
+        assert!(!parent.is_below(&child));
+        assert!(!parent.is_below(&parent));
+        assert!(!location_from("/Users/facundo2").is_below(&parent));
+        assert_eq!("dev/rpg", child.relative_to(&parent));
+    }
+
+    #[test]
+    fn test_subdirectories() {
+        let root = std::env::temp_dir().join("rpg-subdirectories");
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in &["b", "a/nested", ".hidden"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        std::fs::write(root.join("file"), "").unwrap();
+
+        let root = Location::from(&root.to_string_lossy()).unwrap();
+        let names: Vec<String> = root
+            .subdirectories()
+            .iter()
+            .map(|dir| dir.path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(vec!["a", "b"], names);
+        assert!(location_from("/missing/dir").subdirectories().is_empty());
+    }
+
+    #[test]
+    fn test_virtual_path() {
+        let base = path::Path::new("/home/hero/forest");
+        let resolve = |path| virtual_path(base, path::Path::new(path));
+        assert_eq!(path::Path::new("/home/hero/forest/caves"), resolve("caves"));
+        assert_eq!(path::Path::new("/home/hero/ruins"), resolve("../ruins/."));
+        assert_eq!(path::Path::new("/tower"), resolve("/tower"));
+        assert_eq!(path::Path::new("/"), resolve("../../../../.."));
+
+        let children = location_from("/home/hero").virtual_subdirectories();
+        assert_eq!(ABSTRACT_PLACES.len(), children.len());
+        assert_eq!(location_from("/home/hero/caves"), children[0]);
+        assert_eq!(
+            1,
+            children[0]
+                .distance_from(&location_from("/home/hero"))
+                .len()
+        );
     }
 
     /// test-only equivalent for Location::from, specifically to bypass
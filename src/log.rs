@@ -1,37 +1,245 @@
+use crate::achievement::Achievement;
+use crate::activity;
+use crate::arena::Reward;
+use crate::bestiary::Bestiary;
+use crate::character::companion::Companion;
+use crate::character::skill::Skill;
+use crate::character::spell::Spell;
 use crate::character::AttackType;
 use crate::character::{Character, StatusEffect};
+use crate::difficulty::Difficulty;
+use crate::duel;
+use crate::dungeon::Dungeon;
+use crate::encounter::{Encounter, Npc, Reply};
+use crate::faction::{Faction, Standing};
 use crate::game::Game;
+use crate::i18n::{self, t};
+use crate::item::auto_use::Trigger;
 use crate::item::key::Key;
+use crate::item::ring::{Ring, SetBonus};
+use crate::item::throwable::Throwable;
+#[cfg(feature = "leaderboard")]
+use crate::leaderboard;
 use crate::location::Location;
+use crate::raid::Raid;
+use crate::rested;
+use crate::simulation::{Kind, Outcome};
+use crate::speedrun::Split;
+use crate::stats::Stats;
+use crate::theme::{paint, Role};
+use crate::title;
+use crate::world::{self, Site};
 use colored::*;
 use once_cell::sync::OnceCell;
+use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Mutex;
 
 // This are initialized based on input args and then act as constants
 // this prevents having to pass around the flags or lazily parsing the opts
 static QUIET: OnceCell<bool> = OnceCell::new();
+static VERBOSE: OnceCell<bool> = OnceCell::new();
 static PLAIN: OnceCell<bool> = OnceCell::new();
+static JSON: OnceCell<bool> = OnceCell::new();
 
 /// Set the global output preferences
-pub fn init(quiet: bool, plain: bool) {
+pub fn init(quiet: bool, verbose: bool, plain: bool, json: bool) {
     QUIET.set(quiet).unwrap();
+    VERBOSE.set(verbose).unwrap();
     PLAIN.set(plain).unwrap();
+    JSON.set(json).unwrap();
 }
 
+/// Lines of output kept instead of printed, for frontends that show the game
+/// events themselves, like the TUI. None when printing to stdout.
+static CAPTURED: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// All the output goes through `emit`, so it can be captured.
+macro_rules! println {
+    () => {
+        emit(String::new())
+    };
+    ($($arg:tt)*) => {
+        emit(format!($($arg)*))
+    };
+}
+
+/// Start or stop keeping the output lines instead of printing them.
+pub fn capture(enabled: bool) {
+    *CAPTURED.lock().unwrap() = if enabled { Some(Vec::new()) } else { None };
+}
+
+/// Whether the output lines are kept instead of printed.
+pub fn is_captured() -> bool {
+    CAPTURED.lock().unwrap().is_some()
+}
+
+/// Take the output lines kept since the last call.
+pub fn captured() -> Vec<String> {
+    CAPTURED
+        .lock()
+        .unwrap()
+        .as_mut()
+        .map_or(Vec::new(), std::mem::take)
+}
+
+fn emit(text: String) {
+    match CAPTURED.lock().unwrap().as_mut() {
+        Some(lines) => lines.extend(text.split('\n').map(String::from)),
+        None => std::println!("{}", text),
+    }
+}
+
+/// Run the given function without printing its output, leaving the lines
+/// captured and the battles recorded so far untouched.
+pub fn silenced<T>(f: impl FnOnce() -> T) -> T {
+    let captured = CAPTURED.lock().unwrap().replace(Vec::new());
+    let battles = std::mem::take(&mut *BATTLES.lock().unwrap());
+    let recording = *RECORDING.lock().unwrap();
+    let result = f();
+    *CAPTURED.lock().unwrap() = captured;
+    *BATTLES.lock().unwrap() = battles;
+    *RECORDING.lock().unwrap() = recording;
+    result
+}
+
+/// Events of the battles fought by the current command, recorded for later
+/// replay. The battle in progress, if any, is the last one.
+static BATTLES: Mutex<Vec<Vec<Value>>> = Mutex::new(Vec::new());
+static RECORDING: Mutex<bool> = Mutex::new(false);
+
 fn quiet() -> bool {
     *QUIET.get().unwrap_or(&false)
 }
 
+/// When set, battles are shown in full detail: the stats of the enemies,
+/// who attacks on each turn and the exact hp left.
+fn verbose() -> bool {
+    *VERBOSE.get().unwrap_or(&false)
+}
+
+/// Whether to show the events of each battle turn, besides its outcome.
+/// Only the outcome is shown in quiet mode.
+fn show_turn() -> bool {
+    !quiet() || !*RECORDING.lock().unwrap()
+}
+
 fn plain() -> bool {
     *PLAIN.get().unwrap_or(&false)
 }
 
+/// When set, every event is printed as a line of structured JSON
+/// instead of the human-readable output.
+fn json() -> bool {
+    *JSON.get().unwrap_or(&false)
+}
+
+/// Start recording the events of a battle, to be replayed later.
+pub fn battle_start(player: &Character, enemies: &[Character], location: &Location) {
+    let start = json!({
+        "event": "battle",
+        "player": json_character(player),
+        "enemies": enemies.iter().map(json_character).collect::<Vec<Value>>(),
+        "location": location.to_string(),
+    });
+    BATTLES.lock().unwrap().push(vec![start]);
+    *RECORDING.lock().unwrap() = true;
+}
+
+pub fn battle_end() {
+    *RECORDING.lock().unwrap() = false;
+}
+
+/// Take the battles recorded so far, each one as a list of events.
+pub fn recorded_battles() -> Vec<Vec<Value>> {
+    battle_end();
+    std::mem::take(&mut *BATTLES.lock().unwrap())
+}
+
+/// The human-readable lines of a recorded battle.
+pub fn battle_lines(battle: &[Value]) -> Vec<String> {
+    battle.iter().filter_map(format_replayed).collect()
+}
+
+/// Print the events of the given recorded battles again.
+pub fn replay(battles: &[Vec<Value>]) {
+    for battle in battles {
+        for event in battle {
+            if json() {
+                println!("{}", event);
+            } else if let Some(line) = format_replayed(event) {
+                println!("{}", line);
+            }
+        }
+    }
+}
+
 pub fn enemy_appears(enemy: &Character, location: &Location) {
+    if json() {
+        return json_event(
+            "enemy_appears",
+            json!({"enemy": json_character(enemy), "location": location.to_string()}),
+        );
+    }
     log(enemy, location, "");
+    if verbose() {
+        let element = enemy.class.element.map_or(String::new(), |element| {
+            format!(" {}", i18n::name(&element.to_string()))
+        });
+        let details = format!(
+            "    hp:{} att:{} mag:{} def:{} spd:{}{}",
+            enemy.max_hp(),
+            enemy.physical_attack(),
+            enemy.magic_attack(),
+            enemy.deffense(),
+            enemy.speed(),
+            element
+        );
+        println!("{}", details.dimmed());
+    }
 }
 
-pub fn attack(character: &Character, attack: &AttackType, damage: i32, mp_cost: i32) {
-    if !quiet() {
+pub fn attack(
+    attacker: &Character,
+    character: &Character,
+    attack: &AttackType,
+    damage: i32,
+    mp_cost: i32,
+) {
+    let (critical, missed, status_effect) = match attack {
+        AttackType::Regular => (false, false, None),
+        AttackType::Critical => (true, false, None),
+        AttackType::Effect(status) => (false, false, Some(status_effect_params(*status).0)),
+        AttackType::Miss => (false, true, None),
+    };
+    let data = json!({
+        "attacker": json_character(attacker),
+        "receiver": json_character(character),
+        "damage": damage,
+        "mp_cost": mp_cost,
+        "critical": critical,
+        "missed": missed,
+        "status_effect": status_effect,
+    });
+    if battle_event("attack", data) {
+        return;
+    }
+    if verbose() {
+        let details = t(
+            "attacked_by",
+            &[
+                ("hp", &character.current_hp),
+                ("max_hp", &character.max_hp()),
+                (
+                    "attacker",
+                    &format!("{}[{}]", i18n::name(&attacker.name()), attacker.level),
+                ),
+            ],
+        );
+        let text = format_attack(character, attack, damage, mp_cost);
+        battle_log(character, &format!("{} {}", text, details.dimmed()));
+    } else if !quiet() {
         battle_log(
             character,
             &format_attack(character, attack, damage, mp_cost),
@@ -40,19 +248,122 @@ pub fn attack(character: &Character, attack: &AttackType, damage: i32, mp_cost:
 }
 
 pub fn status_effect(character: &Character, hp: i32, mp: i32) {
-    if hp != 0 || mp != 0 {
-        let emoji = character
-            .status_effect
-            .map_or("", |s| status_effect_params(s).1);
-
+    if hp == 0 && mp == 0 {
+        return;
+    }
+    let data = json!({"character": json_character(character), "hp": hp, "mp": mp});
+    if !battle_event("status_effect", data) && show_turn() {
         battle_log(
             character,
-            &format_stat_change(character, hp, mp, false, emoji),
+            &format_stat_change(character, hp, mp, false, &format_status_emojis(character)),
+        );
+    }
+}
+
+pub fn status_expired(character: &Character, status_effect: StatusEffect) {
+    let data = json!({
+        "character": json_character(character),
+        "status_effect": status_effect_params(status_effect).0,
+    });
+    if battle_event("status_expired", data) {
+        return;
+    }
+    if !quiet() {
+        let (name, emoji) = status_effect_params(status_effect);
+        let text = t(
+            "wore_off",
+            &[("emoji", &emoji), ("status", &i18n::name(name))],
+        );
+        battle_log(character, &text);
+    }
+}
+
+pub fn cast(caster: &Character, spell: Spell, mp_cost: i32) {
+    let data = json!({"caster": json_character(caster), "spell": spell, "mp_cost": mp_cost});
+    if battle_event("cast", data) {
+        return;
+    }
+    if !quiet() {
+        let text = format!(
+            "\u{2728} -{}mp {}!",
+            mp_cost,
+            i18n::name(&spell.to_string())
+        );
+        battle_log(caster, &paint(&text, Role::Mp).to_string());
+    }
+}
+
+/// Show the hero throwing an item at the enemies.
+pub fn throw(thrower: &Character, item: Throwable) {
+    let data = json!({"thrower": json_character(thrower), "item": item.to_string()});
+    if battle_event("throw", data) {
+        return;
+    }
+    if !quiet() {
+        let text = format!("\u{1F4A5} {}!", i18n::name(&item.to_string()));
+        battle_log(thrower, &text);
+    }
+}
+
+pub fn learn_spell(player: &Character, spell: Spell) {
+    if json() {
+        return json_event(
+            "learn_spell",
+            json!({"player": json_character(player), "spell": spell}),
         );
     }
+    let text = t(
+        "spell_learned",
+        &[("spell", &i18n::name(&spell.to_string()))],
+    );
+    battle_log(player, &paint(&text, Role::Mp).to_string());
+}
+
+pub fn stunned(character: &Character) {
+    if battle_event("stunned", json!({"character": json_character(character)})) {
+        return;
+    }
+    if !quiet() {
+        let (_, emoji) = status_effect_params(StatusEffect::Stun);
+        battle_log(character, &t("stunned", &[("emoji", &emoji)]));
+    }
+}
+
+pub fn enraged(character: &Character) {
+    if battle_event("enraged", json!({"character": json_character(character)})) {
+        return;
+    }
+    if !quiet() {
+        let text = paint(&t("enraged", &[]), Role::Damage).bold();
+        battle_log(character, &text.to_string());
+    }
+}
+
+/// Tell that the enrage timer of a raid boss ran out.
+pub fn raid_enraged(character: &Character) {
+    if battle_event(
+        "raid_enraged",
+        json!({"character": json_character(character)}),
+    ) {
+        return;
+    }
+    if !quiet() {
+        let text = paint(&t("raid_enraged", &[]), Role::Damage).bold();
+        battle_log(character, &text.to_string());
+    }
 }
 
 pub fn battle_won(game: &Game, xp: i32, levels_up: i32, gold: i32, items: &HashMap<Key, i32>) {
+    let data = json!({
+        "player": json_character(&game.player),
+        "xp": xp,
+        "levels_up": levels_up,
+        "gold": gold,
+        "items": items,
+    });
+    if battle_event("battle_won", data) {
+        return;
+    }
     battle_log(
         &game.player,
         &format!(
@@ -65,134 +376,1815 @@ pub fn battle_won(game: &Game, xp: i32, levels_up: i32, gold: i32, items: &HashM
     short_status(game);
 }
 
-pub fn battle_lost(player: &Character) {
-    battle_log(player, "\u{1F480}");
+/// Report the rested battles earned by the time spent at home.
+pub fn rested(earned: i32, battles: i32) {
+    if json() {
+        return json_event(
+            "rested",
+            json!({ "earned": earned, "battles": battles, "bonus": rested::RESTED_XP }),
+        );
+    }
+    println!(
+        "   \u{1F6CC} {}",
+        t(
+            "rested_xp",
+            &[("battles", &battles), ("bonus", &rested::RESTED_XP)]
+        )
+    );
+}
+
+/// Report an enemy that left the hero alone because its faction is friendly.
+pub fn enemy_spared(name: &str) {
+    if json() {
+        return json_event("enemy_spared", json!({ "enemy": name }));
+    }
+    println!(
+        "   \u{1F91D} {}",
+        t("enemy_spared", &[("enemy", &i18n::name(name))])
+    );
+}
+
+/// Report a change in the standing of the hero with a faction.
+pub fn standing(faction: Faction, standing: Standing) {
+    if json() {
+        return json_event(
+            "standing",
+            json!({ "faction": faction, "standing": standing.to_string() }),
+        );
+    }
+    let text = t(
+        "standing_changed",
+        &[
+            ("faction", &i18n::name(&faction.to_string())),
+            ("standing", &i18n::name(&standing.to_string())),
+        ],
+    );
+    let role = if standing >= Standing::Friendly {
+        Role::Success
+    } else {
+        Role::Damage
+    };
+    println!("   {}", paint(&text, role));
+}
+
+pub fn training(player: &Character, xp: i32, levels_up: i32, items: &HashMap<Key, i32>) {
+    if json() {
+        return json_event(
+            "training",
+            json!({
+                "player": json_character(player),
+                "xp": xp,
+                "levels_up": levels_up,
+                "items": items,
+            }),
+        );
+    }
+    if xp > 0 || !items.is_empty() {
+        battle_log(
+            player,
+            &format!(
+                "{}{}{}{}",
+                format!("+{}xp", xp).bold(),
+                level_up(levels_up),
+                format_ls("", items, 0),
+                t("trained", &[])
+            ),
+        );
+    }
+}
+
+pub fn equipment_broken(character: &Character, piece: &str) {
+    let data = json!({"character": json_character(character), "equipment": piece});
+    if battle_event("equipment_broken", data) {
+        return;
+    }
+    if !quiet() {
+        battle_log(
+            character,
+            &paint(&t("broke", &[("piece", &i18n::name(piece))]), Role::Damage).to_string(),
+        );
+    }
+}
+
+pub fn repair(cost: i32, pieces: &[String]) {
+    if json() {
+        return json_event("repair", json!({"gold": -cost, "equipment": pieces}));
+    }
+    if !pieces.is_empty() {
+        let pieces = i18n::name(&pieces.join(" "));
+        let text = t(
+            "repaired",
+            &[("gold", &format_gold_signed(-cost)), ("pieces", &pieces)],
+        );
+        println!("  {}", text);
+    }
+}
+
+/// Show the gold moved to (negative) or from (positive) the bank,
+/// and the resulting balance.
+pub fn bank(gold: i32, balance: i32) {
+    if json() {
+        return json_event("bank", json!({"gold": gold, "balance": balance}));
+    }
+    let action = match gold {
+        0 => String::new(),
+        g if g < 0 => format!("{}, ", t("deposited", &[("gold", &format_gold_signed(g))])),
+        g => format!("{}, ", t("withdrawn", &[("gold", &format_gold_signed(g))])),
+    };
+    let balance = t("in_the_bank", &[("gold", &format_gold(balance))]);
+    println!("  {}{}", action, balance);
+}
+
+/// Show the gold and items sent to the game of another profile.
+pub fn trade_sent(profile: &str, items: &HashMap<Key, i32>, gold: i32) {
+    if json() {
+        return json_event(
+            "trade_sent",
+            json!({"profile": profile, "items": items, "gold": -gold}),
+        );
+    }
+    let mut sent = Vec::new();
+    if gold != 0 {
+        sent.push(format_gold_signed(-gold).to_string());
+    }
+    for (key, count) in items {
+        sent.push(format!("-{}x{}", i18n::name(&key.to_string()), count));
+    }
+    let text = t("trade_sent", &[("profile", &profile)]);
+    println!("  {} {}", sent.join(" "), text);
+}
+
+/// Show the gold and items received from the game of another profile, and
+/// the rings sent back because they were already found.
+pub fn trade_received(profile: &str, items: &HashMap<Key, i32>, gold: i32, refused: &[Ring]) {
+    if json() {
+        return json_event(
+            "trade_received",
+            json!({"profile": profile, "items": items, "gold": gold, "refused": refused}),
+        );
+    }
+    let text = t("trade_received", &[("profile", &profile)]);
+    println!("  {}{}", format_ls("\u{1F4E6}", items, gold), text);
+    for ring in refused {
+        let ring = i18n::name(&Key::Ring(ring.clone()).to_string());
+        let text = t("ring_refused", &[("ring", &ring), ("profile", &profile)]);
+        println!("    {}", text);
+    }
+}
+
+pub fn bank_interest(gold: i32) {
+    if json() {
+        return json_event("bank_interest", json!({ "gold": gold }));
+    }
+    println!(
+        "  {}",
+        t("interest", &[("gold", &format_gold_signed(gold))])
+    );
+}
+
+/// Show the hero and house dice rolls, and the gold won or lost.
+pub fn gamble(roll: i32, house_roll: i32, gold: i32) {
+    if json() {
+        return json_event(
+            "gamble",
+            json!({"roll": roll, "house_roll": house_roll, "gold": gold}),
+        );
+    }
+    println!(
+        "  \u{1F3B2} {} vs {} {}",
+        roll,
+        house_roll,
+        format_gold_signed(gold)
+    );
+}
+
+pub fn rest(cost: i32) {
+    if json() {
+        return json_event("rest", json!({ "gold": -cost }));
+    }
+    println!("  {}", t("rested", &[("gold", &format_gold_signed(-cost))]));
+}
+
+pub fn fast_travel(cost: i32, dest: &Location) {
+    if json() {
+        return json_event(
+            "fast_travel",
+            json!({ "gold": -cost, "location": dest.to_string() }),
+        );
+    }
+    let text = t(
+        "traveled",
+        &[("gold", &format_gold_signed(-cost)), ("location", dest)],
+    );
+    println!("  {}", text);
+}
+
+/// List the waypoints by name, with their location and the cost of fast
+/// traveling there from the current location.
+pub fn waypoints(waypoints: &[(&String, &Location, i32)]) {
+    if json() {
+        let waypoints: Vec<Value> = waypoints
+            .iter()
+            .map(|(name, location, cost)| {
+                json!({"name": name, "location": location.to_string(), "gold": cost})
+            })
+            .collect();
+        return json_event("waypoints", json!({ "waypoints": waypoints }));
+    }
+    for (name, location, cost) in waypoints {
+        println!(
+            "  {:<10}{:<30}{}",
+            name,
+            location.to_string(),
+            format_gold(*cost)
+        );
+    }
+}
+
+/// Show the world map as a tree of the known directories.
+pub fn world_map(sites: &[Site]) {
+    if json() {
+        let sites: Vec<Value> = sites.iter().map(site_json).collect();
+        return json_event("map", json!({ "sites": sites }));
+    }
+    for line in world::render(sites) {
+        println!("  {}", line);
+    }
+}
+
+fn site_json(site: &Site) -> Value {
+    let marks: Vec<String> = site.marks.iter().map(|mark| mark.to_string()).collect();
+    let children: Vec<Value> = site.children.iter().map(site_json).collect();
+    json!({
+        "location": site.location.to_string(),
+        "distance": site.distance,
+        "marks": marks,
+        "children": children,
+    })
+}
+
+pub fn ambush() {
+    if json() {
+        return json_event("ambush", json!({}));
+    }
+    println!("   {}", t("ambushed", &[]));
+}
+
+pub fn insured(premium: i32) {
+    if json() {
+        return json_event("insured", json!({ "gold": -premium }));
+    }
+    println!(
+        "  {}",
+        t("insured", &[("gold", &format_gold_signed(-premium))])
+    );
+}
+
+pub fn ring_upgrade(ring: &Ring, tier: i32, cost: i32) {
+    if json() {
+        return json_event(
+            "ring_upgrade",
+            json!({"ring": ring.to_string(), "tier": tier, "gold": -cost}),
+        );
+    }
+    let ring = format!("{}(+{})", i18n::name(&ring.to_string()), tier);
+    let text = t(
+        "upgraded",
+        &[("gold", &format_gold_signed(-cost)), ("ring", &ring)],
+    );
+    println!("  {}", text);
+}
+
+pub fn craft(materials: &HashMap<Key, i32>, crafted: &str) {
+    if json() {
+        return json_event("craft", json!({"materials": materials, "crafted": crafted}));
+    }
+    let materials = materials
+        .iter()
+        .map(|(key, count)| format!("-{}x{}", key, count))
+        .collect::<Vec<_>>();
+    println!("  {} +{}", materials.join(" "), crafted);
+}
+
+pub fn recipes(recipes: Vec<(String, String)>) {
+    if json() {
+        let recipes: Vec<Value> = recipes
+            .iter()
+            .map(|(name, materials)| json!({"name": name, "materials": materials}))
+            .collect();
+        return json_event("recipes", json!({ "recipes": recipes }));
+    }
+    for (name, materials) in recipes {
+        println!("    {:<10}  {}", name, materials);
+    }
+}
+
+/// Show the start of a New Game+ and the gold carried over to it.
+pub fn new_game_plus(game: &Game) {
+    if json() {
+        return json_event(
+            "new_game_plus",
+            json!({"new_game_plus": game.new_game_plus, "gold": game.gold}),
+        );
+    }
+    if !quiet() {
+        let title = t("new_game_plus", &[("count", &game.new_game_plus)]);
+        println!("  {} {}", title.bold(), format_gold(game.gold));
+    }
+}
+
+pub fn ascend(player: &Character) {
+    if json() {
+        return json_event(
+            "ascend",
+            json!({"prestige": player.prestige, "title": player.title()}),
+        );
+    }
+    if !quiet() {
+        let title = format!("{} {}", "\u{2726}", player.title().unwrap_or_default());
+        let prestige = t("prestige", &[("prestige", &player.prestige)]);
+        println!("  {} {}", paint(&title, Role::Title).bold(), prestige);
+    }
+}
+
+pub fn party_joined(member: &Character) {
+    if json() {
+        return json_event("party_joined", json_character(member));
+    }
+    battle_log(
+        member,
+        &paint(&t("party_joined", &[]), Role::Xp).to_string(),
+    );
+}
+
+pub fn party_left(member: &Character) {
+    if json() {
+        return json_event("party_left", json_character(member));
+    }
+    battle_log(member, &t("party_left", &[]));
+}
+
+pub fn companion_joined(companion: &Companion) {
+    if json() {
+        return json_event("companion_joined", json_companion(companion));
+    }
+    battle_log(
+        &companion.character,
+        &paint(&t("companion_joined", &[]), Role::Xp).to_string(),
+    );
+}
+
+pub fn companion_dismissed(companion: &Companion) {
+    if json() {
+        return json_event("companion_dismissed", json_companion(companion));
+    }
+    battle_log(&companion.character, &t("companion_dismissed", &[]));
+}
+
+/// List the party leader followed by the rest of the members.
+pub fn party(player: &Character, members: &[Character]) {
+    if json() {
+        let members: Vec<Value> = members.iter().map(json_character).collect();
+        return json_event(
+            "party",
+            json!({"leader": json_character(player), "members": members}),
+        );
+    }
+    for member in std::iter::once(player).chain(members) {
+        battle_log(
+            member,
+            &format!("{}/{}hp", member.current_hp, member.max_hp()),
+        );
+    }
+}
+
+/// List the active companion followed by the ones resting at home.
+pub fn companions(active: &Option<Companion>, resting: &[Companion]) {
+    if json() {
+        let resting: Vec<Value> = resting.iter().map(json_companion).collect();
+        return json_event(
+            "companions",
+            json!({"active": active.as_ref().map(json_companion), "resting": resting}),
+        );
+    }
+    for (companion, status) in active
+        .iter()
+        .map(|c| (c, t("active", &[])))
+        .chain(resting.iter().map(|c| (c, t("resting", &[]))))
+    {
+        let item = companion.item.as_ref().map_or(String::new(), |i| {
+            format!(
+                " {}",
+                t("holding", &[("item", &i18n::name(&i.to_string()))])
+            )
+        });
+        println!(
+            "    {}[{}] {}/{}hp {}{}",
+            i18n::name(&companion.name()),
+            companion.character.level,
+            companion.character.current_hp,
+            companion.character.max_hp(),
+            status,
+            item
+        );
+    }
+}
+
+/// Start recording a duel between the hero and the rival, and show both.
+pub fn duel_start(hero: &Character, rival: &Character, location: &Location) {
+    battle_start(hero, std::slice::from_ref(rival), location);
+    if json() {
+        return json_event(
+            "duel_start",
+            json!({"hero": json_character(hero), "rival": json_character(rival)}),
+        );
+    }
+    log(hero, location, "");
+    log(rival, location, &t("duel_rival", &[]));
+}
+
+/// Show how the duel ended and the resulting rating.
+pub fn duel_end(outcome: duel::Outcome, change: i32, record: &duel::Record) {
+    let key = match outcome {
+        duel::Outcome::Won => "duel_won",
+        duel::Outcome::Lost => "duel_lost",
+        duel::Outcome::Draw => "duel_draw",
+    };
+    if json() {
+        return json_event(
+            "duel_end",
+            json!({"outcome": key.trim_start_matches("duel_"), "change": change, "rating": record.rating}),
+        );
+    }
+    let rating = format!("{} ({:+})", record.rating, change);
+    println!("  {}", t(key, &[("rating", &rating)]));
+}
+
+pub fn duels(record: &duel::Record) {
+    if json() {
+        return json_event(
+            "duels",
+            json!({"rating": record.rating, "won": record.won, "lost": record.lost, "drawn": record.drawn}),
+        );
+    }
+    let text = t(
+        "duel_record",
+        &[
+            ("rating", &record.rating),
+            ("won", &record.won),
+            ("lost", &record.lost),
+            ("drawn", &record.drawn),
+        ],
+    );
+    println!("  {}", text);
+}
+
+/// The hero was beaten in the arena, where it's not deadly.
+pub fn knocked_out(player: &Character) {
+    if battle_event("knocked_out", json!({"player": json_character(player)})) {
+        return;
+    }
+    battle_log(player, &t("knocked_out", &[]));
+}
+
+pub fn arena_wave(wave: i32) {
+    if json() {
+        return json_event("arena_wave", json!({ "wave": wave }));
+    }
+    println!(
+        "{}",
+        paint(&t("arena_wave", &[("wave", &wave)]), Role::Title)
+    );
+}
+
+/// Show the waves beaten in an arena run and the tokens earned.
+pub fn arena_end(waves: i32, tokens: i32, record: bool) {
+    if json() {
+        return json_event(
+            "arena_end",
+            json!({"waves": waves, "tokens": tokens, "record": record}),
+        );
+    }
+    let mut text = t("arena_end", &[("waves", &waves), ("tokens", &tokens)]);
+    if record {
+        text = format!("{} {}", text, t("arena_record", &[]));
+    }
+    println!("  {}", text);
+}
+
+/// List the arena rewards with their price in tokens.
+pub fn arena_rewards(rewards: &[(Reward, i32)], tokens: i32) {
+    if json() {
+        let rewards: Vec<Value> = rewards
+            .iter()
+            .map(|(reward, price)| json!({"item": reward.to_string(), "tokens": price}))
+            .collect();
+        return json_event(
+            "arena_rewards",
+            json!({"rewards": rewards, "tokens": tokens}),
+        );
+    }
+    for (reward, price) in rewards {
+        let name = i18n::name(&reward.to_string());
+        let price = t("arena_tokens", &[("tokens", price)]);
+        println!("    {:<10}  {}", name, price);
+    }
+    let tokens = t("arena_tokens", &[("tokens", &tokens)]);
+    println!("\n    {}", t("funds", &[("gold", &tokens)]));
+}
+
+pub fn arena_exchange(item: &str, price: i32) {
+    if json() {
+        return json_event("arena_exchange", json!({ "item": item, "tokens": -price }));
+    }
+    let price = t("arena_tokens", &[("tokens", &-price)]);
+    println!("  +{} {}", i18n::name(item), price);
+}
+
+pub fn battle_lost(player: &Character) {
+    if battle_event("battle_lost", json!({"player": json_character(player)})) {
+        return;
+    }
+    battle_log(player, "\u{1F480}");
+}
+
+/// Report the effects of a plugin event handler.
+pub fn plugin(name: &str, message: Option<&str>, items: &HashMap<Key, i32>, gold: i32) {
+    if message.is_none() && items.is_empty() && gold == 0 {
+        return;
+    }
+    if json() {
+        return json_event(
+            "plugin",
+            json!({"plugin": name, "message": message, "items": items, "gold": gold}),
+        );
+    }
+    let message = message.map_or(String::new(), |message| format!(" {}", message));
+    let effects = format_ls("", items, gold);
+    let line = format!("  [{}]{} {}", name, message, effects.trim());
+    println!("{}", line.trim_end());
+}
+
+pub fn chest(items: &HashMap<Key, i32>, gold: i32) {
+    if json() {
+        return json_event("chest", json!({"items": items, "gold": gold}));
+    }
+    println!("{}", format_ls("\u{1F4E6}", items, gold));
+}
+
+/// Show what a chest guarded by a mimic holds, before fighting for it.
+pub fn appraisal(contents: &[String], trapped: bool) {
+    if json() {
+        return json_event(
+            "appraisal",
+            json!({"contents": contents, "trapped": trapped}),
+        );
+    }
+    let contents = contents.join(", ");
+    let key = if trapped {
+        "appraisal_trapped"
+    } else {
+        "appraisal"
+    };
+    println!("   \u{1F50D} {}", t(key, &[("contents", &contents)]));
+}
+
+/// Tell that a chest stays at the location, still protected by the guard
+/// the hero didn't beat.
+pub fn chest_guarded(guard: &Character) {
+    if json() {
+        return json_event("chest_guarded", json!({"guard": guard.name()}));
+    }
+    println!(
+        "   \u{1F6E1} {}",
+        t("chest_guarded", &[("guard", &i18n::name(&guard.name()))])
+    );
+}
+
+/// Report the stones refunded by a stone of forgetting.
+pub fn stones_forgotten(points: i32) {
+    if json() {
+        return json_event("stones_forgotten", json!({ "points": points }));
+    }
+    println!(
+        "   \u{1F4AB} {}",
+        t("stones_forgotten", &[("points", &points.to_string())])
+    );
+}
+
+/// Celebrate a chest that had a jackpot of gold.
+pub fn jackpot(gold: i32) {
+    if json() {
+        return json_event("jackpot", json!({ "gold": gold }));
+    }
+    let text = t("jackpot", &[("gold", &format_gold(gold))]);
+    println!("   \u{1F4B0} {} \u{1F4B0}", text);
+}
+
+/// Show the map of the current dungeon floor.
+pub fn dungeon(dungeon: &Dungeon) {
+    if json() {
+        return json_event(
+            "dungeon",
+            json!({"floor": dungeon.floor(), "floors": dungeon.floors(), "map": dungeon.map()}),
+        );
+    }
+    if !quiet() {
+        let floor = t(
+            "floor",
+            &[("floor", &dungeon.floor()), ("floors", &dungeon.floors())],
+        );
+        println!("  {}", floor);
+        for row in dungeon.map() {
+            let row: Vec<String> = row.chars().map(String::from).collect();
+            println!("  {}", row.join(" "));
+        }
+    }
+}
+
+pub fn dungeon_floor(floor: i32) {
+    if json() {
+        return json_event("dungeon_floor", json!({ "floor": floor }));
+    }
+    println!("  \u{1FA9C} {}", t("down_to_floor", &[("floor", &floor)]));
+}
+
+pub fn dungeon_exit(completed: bool) {
+    if json() {
+        return json_event("dungeon_exit", json!({ "completed": completed }));
+    }
+    if completed {
+        println!(
+            "  {}",
+            paint(&t("dungeon_cleared", &[]), Role::Success).bold()
+        );
+    } else {
+        println!("  {}", t("dungeon_left", &[]));
+    }
+}
+
+pub fn trap(player: &Character, damage: i32) {
+    if json() {
+        return json_event(
+            "trap",
+            json!({"player": json_character(player), "damage": damage}),
+        );
+    }
+    battle_log(player, &t("trap", &[("damage", &damage)]));
+}
+
+/// Show the effect of a chest trap sprung on the hero.
+pub fn chest_trap(player: &Character, effect: &str) {
+    if json() {
+        return json_event(
+            "chest_trap",
+            json!({"player": json_character(player), "effect": effect}),
+        );
+    }
+    battle_log(player, &t("chest_trap", &[("effect", &effect)]));
+}
+
+pub fn trap_disarmed(player: &Character) {
+    if json() {
+        return json_event("trap_disarmed", json!({"player": json_character(player)}));
+    }
+    battle_log(player, &t("trap_disarmed", &[]));
+}
+
+pub fn curses_lifted(player: &Character) {
+    if json() {
+        return json_event("curses_lifted", json!({"player": json_character(player)}));
+    }
+    battle_log(player, &t("curses_lifted", &[]));
+}
+
+pub fn treasure_map(location: &Location) {
+    if json() {
+        return json_event("treasure_map", json!({ "location": location.to_string() }));
+    }
+    let location = location.to_string().bold();
+    println!(
+        "    \u{1F5FA}  {}",
+        t("buried_chest", &[("location", &location)])
+    );
+}
+
+/// Show the inventory slots of the hero after using a bag.
+pub fn bag(slots: i32) {
+    if json() {
+        return json_event("bag", json!({ "slots": slots }));
+    }
+    println!("  {}", t("inventory_slots", &[("slots", &slots)]));
+}
+
+/// Report the chest items that didn't fit in the inventory and were left
+/// at the location.
+pub fn inventory_full(items: &[String]) {
+    if json() {
+        return json_event("inventory_full", json!({ "items": items }));
+    }
+    let items = i18n::name(&items.join(" "));
+    println!("  {}", t("inventory_full", &[("items", &items)]));
+}
+
+pub fn curse_drain(gold: i32) {
+    if json() {
+        return json_event("curse_drain", json!({ "gold": -gold }));
+    }
+    if !quiet() {
+        let gold = format_gold_signed(-gold);
+        println!("    {}", t("drained_by_curses", &[("gold", &gold)]));
+    }
+}
+
+/// Show the characters in an interactive battle and prompt for the
+/// player action on its turn.
+pub fn battle_prompt(player: &Character, enemies: &[Character]) {
+    if json() {
+        let enemies: Vec<Value> = enemies.iter().map(json_character).collect();
+        return json_event(
+            "battle_prompt",
+            json!({"player": json_character(player), "enemies": enemies}),
+        );
+    }
+    for enemy in enemies.iter().filter(|e| e.current_hp > 0) {
+        battle_log(enemy, "");
+    }
+    battle_log(player, "");
+    print!("{}", t("battle_prompt", &[]));
+    std::io::stdout().flush().unwrap_or_default();
+}
+
+/// Prompt for the stats to raise with the points refunded by a forget-stone.
+pub fn stone_points_prompt(points: i32) {
+    if json() {
+        return json_event("stone_points_prompt", json!({ "points": points }));
+    }
+    print!(
+        "{}",
+        t("stone_points_prompt", &[("points", &points.to_string())])
+    );
+    std::io::stdout().flush().unwrap_or_default();
+}
+
+/// List the contents of a chest and prompt for the ones to pick up, along
+/// with the free inventory slots to fit them in.
+pub fn chest_contents(contents: &[String], trapped: bool, free_slots: i32) {
+    if json() {
+        return json_event(
+            "chest_contents",
+            json!({ "contents": contents, "trapped": trapped, "free_slots": free_slots }),
+        );
+    }
+    if trapped {
+        println!("\u{1F4E6} {}", paint(&t("trapped", &[]), Role::Damage));
+    } else {
+        println!("\u{1F4E6}");
+    }
+    for (index, content) in contents.iter().enumerate() {
+        println!("  {:>2}. {}", index + 1, i18n::name(content));
+    }
+    print!("{}", t("pick_up", &[("slots", &free_slots)]));
+    std::io::stdout().flush().unwrap_or_default();
+}
+
+/// Show what the npc met at the location says, and the replies to it.
+pub fn encounter(encounter: &Encounter) {
+    let (npc, offer, accept) = match &encounter.npc {
+        Npc::Merchant { item, price } => {
+            let item = i18n::name(&item.to_string());
+            let text = t(
+                "npc_merchant",
+                &[("item", &item), ("price", &format_gold(*price))],
+            );
+            ("merchant", text, t("reply_buy", &[("item", &item)]))
+        }
+        Npc::Traveler { reward } => (
+            "traveler",
+            t("npc_traveler", &[("gold", &format_gold(*reward))]),
+            t("reply_guide", &[]),
+        ),
+        Npc::Stranger { wager } => (
+            "stranger",
+            t("npc_stranger", &[("gold", &format_gold(*wager))]),
+            t("reply_roll", &[]),
+        ),
+        Npc::RareMerchant { .. } => ("rare_merchant", t("npc_rare_merchant", &[]), String::new()),
+    };
+    let replies: Vec<String> = encounter
+        .replies()
+        .iter()
+        .map(|reply| match (reply, &encounter.npc) {
+            (Reply::Buy(index), Npc::RareMerchant { stock }) => {
+                let (ware, price) = &stock[*index];
+                t(
+                    "reply_buy_for",
+                    &[
+                        ("item", &i18n::name(&ware.to_string())),
+                        ("price", &format_gold(*price)),
+                    ],
+                )
+            }
+            (Reply::Leave, _) => t("reply_leave", &[]),
+            _ => accept.clone(),
+        })
+        .collect();
+    if json() {
+        return json_event(
+            "encounter",
+            json!({ "npc": npc, "offer": offer, "replies": replies }),
+        );
+    }
+    println!("\u{1F5E3}  {}", offer);
+    for (index, reply) in replies.iter().enumerate() {
+        println!("  {:>2}. {}", index + 1, reply);
+    }
+}
+
+pub fn talk_prompt() {
+    if json() {
+        return;
+    }
+    print!("{}", t("talk_prompt", &[]));
+    std::io::stdout().flush().unwrap_or_default();
+}
+
+pub fn npc_left() {
+    if json() {
+        return json_event("npc_left", json!({}));
+    }
+    println!("  {}", t("npc_left", &[]));
+}
+
+pub fn npc_trade(item: &str, price: i32) {
+    if json() {
+        return json_event("npc_trade", json!({ "item": item, "gold": -price }));
+    }
+    println!("  +{} {}", i18n::name(item), format_gold_signed(-price));
+}
+
+pub fn npc_guided(reward: i32) {
+    if json() {
+        return json_event("npc_guided", json!({ "reward": reward }));
+    }
+    println!("  {}", t("npc_guided", &[("gold", &format_gold(reward))]));
+}
+
+pub fn tombstone(items: &HashMap<Key, i32>, gold: i32) {
+    if json() {
+        return json_event("tombstone", json!({"items": items, "gold": gold}));
+    }
+    println!("{}", format_ls("\u{1FAA6} ", items, gold));
+}
+
+pub fn insurance_payout(contents: &[String]) {
+    if json() {
+        return json_event("insurance_payout", json!({ "contents": contents }));
+    }
+    if !contents.is_empty() {
+        let items = i18n::name(&contents.join(" "));
+        println!("    {}", t("insurance_payout", &[("items", &items)]));
+    }
+}
+
+pub fn bribe(player: &Character, amount: i32) {
+    let data = json!({"player": json_character(player), "success": amount > 0, "gold": -amount});
+    if battle_event("bribe", data) {
+        return;
+    }
+    if amount > 0 {
+        let suffix = t("bribed", &[("gold", &format_gold_signed(-amount))]);
+        battle_log(player, &suffix);
+    } else {
+        battle_log(player, &t("cant_bribe", &[]));
+    }
+}
+
+pub fn run_away(player: &Character, success: bool) {
+    let data = json!({"player": json_character(player), "success": success});
+    if battle_event("run_away", data) {
+        return;
+    }
+    if success {
+        battle_log(player, &t("fled", &[]));
+    } else {
+        battle_log(player, &t("cant_run", &[]));
+    }
+}
+
+pub fn heal_item(
+    player: &Character,
+    item: &str,
+    recovered_hp: i32,
+    recovered_mp: i32,
+    healed: bool,
+) {
+    if recovered_hp <= 0 && recovered_mp <= 0 && !healed {
+        return;
+    }
+    let data = json!({
+        "player": json_character(player),
+        "item": item,
+        "hp": recovered_hp,
+        "mp": recovered_mp,
+        "healed": healed,
+    });
+    if !battle_event("heal_item", data) && show_turn() {
+        let role = if recovered_mp > 0 { Role::Mp } else { Role::Hp };
+        battle_log(
+            player,
+            &format_stat_change(
+                player,
+                recovered_hp,
+                recovered_mp,
+                healed,
+                &paint(&i18n::name(item), role).to_string(),
+            ),
+        );
+    }
+}
+
+pub fn heal(
+    player: &Character,
+    location: &Location,
+    recovered_hp: i32,
+    recovered_mp: i32,
+    healed: bool,
+) {
+    if json() {
+        if recovered_hp > 0 || recovered_mp > 0 || healed {
+            json_event(
+                "heal",
+                json!({
+                    "player": json_character(player),
+                    "location": location.to_string(),
+                    "hp": recovered_hp,
+                    "mp": recovered_mp,
+                    "healed": healed,
+                }),
+            );
+        }
+        return;
+    }
+    if recovered_hp > 0 || recovered_mp > 0 || healed {
+        log(
+            player,
+            location,
+            &format_stat_change(player, recovered_hp, recovered_mp, healed, ""),
+        );
+    }
+}
+
+pub fn respec(cost: i32) {
+    if json() {
+        return json_event(
+            "respec",
+            json!({ "gold": -cost, "items": [Key::LevelStone] }),
+        );
+    }
+    let item = i18n::name(&Key::LevelStone.to_string());
+    let text = t(
+        "class_changed",
+        &[("gold", &format_gold_signed(-cost)), ("item", &item)],
+    );
+    println!("  {}", text);
+}
+
+pub fn change_class(player: &Character, lost_xp: i32) {
+    if json() {
+        return json_event(
+            "change_class",
+            json!({"player": json_character(player), "lost_xp": lost_xp}),
+        );
+    }
+    if lost_xp > 0 {
+        let text = paint(&format!("-{}xp", lost_xp), Role::Damage);
+        battle_log(player, &text.to_string());
+    }
+}
+
+pub fn stat_increase(player: &Character, stat: &str, increase: i32) {
+    if json() {
+        return json_event(
+            "stat_increase",
+            json!({"player": json_character(player), "stat": stat, "increase": increase}),
+        );
+    }
+    let suffix = if stat == "level" {
+        level_up(increase)
+    } else {
+        paint(&format!("+{}{}", increase, stat), Role::Xp).to_string()
+    };
+    battle_log(player, &suffix);
+}
+
+pub fn stat_decrease(player: &Character, stat: &str, decrease: i32) {
+    if json() {
+        return json_event(
+            "stat_decrease",
+            json!({"player": json_character(player), "stat": stat, "decrease": decrease}),
+        );
+    }
+    let suffix = paint(&format!("-{}{}", decrease, stat), Role::HpLost).to_string();
+    battle_log(player, &suffix);
+}
+
+/// Show how the hero grows on level up and the points left to assign.
+pub fn growth(player: &Character) {
+    if json() {
+        return json_event(
+            "growth",
+            json!({"manual": player.manual_growth, "points": player.growth_points}),
+        );
+    }
+    let key = if player.manual_growth {
+        "growth_manual"
+    } else {
+        "growth_auto"
+    };
+    println!("   {}", t(key, &[]));
+    if player.growth_points > 0 {
+        println!("   {}", format_growth_points(player));
+    }
+}
+
+/// Report a chaos stone that lowered no stat thanks to the balance ring.
+pub fn chaos_balanced() {
+    if json() {
+        return json_event("chaos_balanced", json!({}));
+    }
+    println!("   \u{2696} {}", t("chaos_balanced", &[]));
+}
+
+/// Ask for confirmation before using a chaos stone.
+pub fn chaos_prompt(balanced: bool) {
+    if json() {
+        return json_event("chaos_prompt", json!({ "balanced": balanced }));
+    }
+    let key = if balanced {
+        "chaos_prompt_balanced"
+    } else {
+        "chaos_prompt"
+    };
+    print!("{}", t(key, &[]));
+    std::io::stdout().flush().unwrap_or_default();
+}
+
+/// Print the hero status according to options
+pub fn status(game: &Game) {
+    if json() {
+        json_event("status", json_status(game));
+    } else if plain() {
+        plain_status(game);
+    } else if quiet() {
+        short_status(game);
+    } else {
+        long_status(game)
+    }
+}
+
+/// Print a single line status for shell prompts, with the placeholders of
+/// the format replaced by the hero details. No colors are used, since
+/// prompts need escapes around them.
+pub fn prompt(game: &Game, format: &str) {
+    println!("{}", format_prompt(game, format));
+}
+
+pub fn shop_list(game: &Game, items: Vec<(i32, String)>) {
+    if json() {
+        let items: Vec<Value> = items
+            .iter()
+            .map(|(cost, item)| json!({"item": item, "cost": cost}))
+            .collect();
+        return json_event("shop_list", json!({"items": items, "funds": game.gold}));
+    }
+    for (cost, item) in items {
+        println!("    {:<10}  {}", item, format_gold(cost));
+    }
+
+    println!("\n    {}", t("funds", &[("gold", &format_gold(game.gold))]));
+}
+
+pub fn shop_buy(cost: i32, items: &HashMap<Key, i32>) {
+    if json() {
+        return json_event("shop_buy", json!({"gold": -cost, "items": items}));
+    }
+    if !items.is_empty() {
+        println!("  {}", format_ls("", items, -cost));
+    }
+}
+
+pub fn shop_sell(price: i32, items: &HashMap<Key, i32>) {
+    if json() {
+        return json_event("shop_sell", json!({"gold": price, "items": items}));
+    }
+    if !items.is_empty() {
+        let items = items
+            .iter()
+            .map(|(key, count)| format!("-{}x{}", key, count))
+            .collect::<Vec<_>>();
+        println!("  {} {}", format_gold_signed(price), items.join(" "));
+    }
+}
+
+/// Print the quest journal, active quests first.
+pub fn quest_list(quests: Vec<(bool, String)>) {
+    if json() {
+        let quests: Vec<Value> = quests
+            .iter()
+            .map(|(completed, quest)| json!({"quest": quest, "completed": completed}))
+            .collect();
+        return json_event("quest_list", json!({ "quests": quests }));
+    }
+    for (_, quest) in quests.iter().filter(|(completed, _)| !completed) {
+        println!("  {} {}", "□".dimmed(), quest);
+    }
+    for (_, quest) in quests.iter().filter(|(completed, _)| *completed) {
+        println!("  {} {}", paint("✔", Role::Success), quest.dimmed());
+    }
+}
+
+pub fn set_bonus(bonus: &SetBonus) {
+    if json() {
+        return json_event(
+            "set_bonus",
+            json!({"bonus": bonus.to_string(), "description": bonus.describe()}),
+        );
+    }
+    if !quiet() {
+        let text = t(
+            "set_bonus",
+            &[
+                ("bonus", &bonus.to_string().bold()),
+                ("description", &bonus.describe()),
+            ],
+        );
+        println!("   {}", text);
+    }
+}
+
+/// Print the statistics of the player's history.
+pub fn history(stats: &Stats) {
+    if json() {
+        return json_event(
+            "history",
+            json!({
+                "battles": stats.battles,
+                "win_rate": stats.win_rate(),
+                "gold_earned": stats.gold_earned,
+                "gold_spent": stats.gold_spent,
+                "steps": stats.steps,
+                "deepest": stats.deepest,
+                "deaths": stats.deaths,
+                "best_wave": stats.best_wave,
+                "playtime": stats.playtime,
+            }),
+        );
+    }
+    let battles = t(
+        "battles",
+        &[("battles", &stats.battles), ("rate", &stats.win_rate())],
+    );
+    println!("  {}", battles);
+    let gold = t(
+        "gold_history",
+        &[
+            ("earned", &format_gold(stats.gold_earned)),
+            ("spent", &format_gold(stats.gold_spent)),
+        ],
+    );
+    println!("  {}", gold);
+    println!("  {}", t("steps", &[("steps", &stats.steps)]));
+    println!("  {}", t("deepest", &[("location", &stats.deepest)]));
+    println!("  {}", t("best_wave", &[("wave", &stats.best_wave)]));
+    let playtime = format_duration(stats.playtime);
+    println!("  {}", t("playtime", &[("time", &playtime)]));
+    let deaths = stats.deaths();
+    if !deaths.is_empty() {
+        println!("  {}", t("deaths", &[]));
+        for (location, count) in deaths {
+            println!("    {:>3} {}", count, location);
+        }
+    }
+}
+
+#[cfg(feature = "leaderboard")]
+pub fn leaderboard_submitted(summary: &leaderboard::Summary) {
+    if json() {
+        return json_event("leaderboard_submitted", json!({ "summary": summary }));
+    }
+    println!(
+        "  {}",
+        t("leaderboard_submitted", &[("name", &summary.name)])
+    );
+}
+
+/// Print the leaderboard rankings, highlighting the entries of the player.
+#[cfg(feature = "leaderboard")]
+pub fn leaderboard(rankings: &[leaderboard::Summary], player: Option<&str>) {
+    if json() {
+        return json_event("leaderboard", json!({ "rankings": rankings }));
+    }
+    for (rank, summary) in rankings.iter().enumerate() {
+        let line = format!(
+            "{:>3}. {:<16} {}[{}] {} {}",
+            rank + 1,
+            summary.name,
+            i18n::name(&summary.class),
+            summary.level,
+            format_duration(summary.playtime),
+            t(
+                "leaderboard_entry",
+                &[
+                    ("deaths", &summary.deaths),
+                    ("achievements", &summary.achievements),
+                ],
+            )
+        );
+        if player == Some(summary.name.as_str()) {
+            println!("  {}", line.bold());
+        } else {
+            println!("  {}", line);
+        }
+    }
+}
+
+/// Print the enemy classes faced by the player, with their kill statistics.
+pub fn bestiary(bestiary: &Bestiary) {
+    if json() {
+        let entries: Vec<Value> = bestiary
+            .list()
+            .iter()
+            .map(|(name, entry)| {
+                json!({
+                    "class": name,
+                    "encountered": entry.encountered,
+                    "killed": entry.killed,
+                    "max_level": entry.max_level,
+                    "drops": entry.drops,
+                })
+            })
+            .collect();
+        return json_event("bestiary", json!({ "bestiary": entries }));
+    }
+    for (name, entry) in bestiary.list() {
+        let drops: Vec<String> = entry
+            .drops
+            .iter()
+            .map(|key| i18n::name(&key.to_string()))
+            .collect();
+        println!(
+            "  {:<12} {} {:>3}/{:<3} max {}  {}",
+            i18n::name(name),
+            t("killed", &[]),
+            entry.killed,
+            entry.encountered,
+            format!("lv{}", entry.max_level).bold(),
+            drops.join(", ").dimmed()
+        );
+    }
+}
+
+/// Print the tombs left by fallen heroes, with their contents.
+pub fn tombs(tombs: Vec<(&String, Vec<String>)>) {
+    if json() {
+        let tombs: Vec<Value> = tombs
+            .iter()
+            .map(|(location, contents)| json!({"location": location, "contents": contents}))
+            .collect();
+        return json_event("tombs", json!({ "tombs": tombs }));
+    }
+    for (location, contents) in tombs {
+        println!(
+            "  \u{1FAA6} {}  {}",
+            location.bold(),
+            contents.join(" ").dimmed()
+        );
+    }
+}
+
+/// Print the skill tree of the player class, marking the learned skills.
+pub fn skills(player: &Character) {
+    let tree = Skill::tree(&player.class.name);
+    if json() {
+        let skills: Vec<Value> = tree
+            .iter()
+            .map(|skill| {
+                json!({
+                    "skill": skill.to_string(),
+                    "active": skill.is_active(),
+                    "description": skill.describe(),
+                    "learned": player.has_skill(*skill),
+                })
+            })
+            .collect();
+        return json_event(
+            "skills",
+            json!({"skills": skills, "points": player.skill_points()}),
+        );
+    }
+    for skill in tree {
+        let kind = if skill.is_active() {
+            t("active_skill", &[])
+        } else {
+            t("passive_skill", &[])
+        };
+        let line = format!("{} ({}): {}", skill, kind, skill.describe());
+        if player.has_skill(skill) {
+            println!("  {} {}", paint("✔", Role::Success), line.dimmed());
+        } else {
+            println!("  {} {}", "□".dimmed(), line);
+        }
+    }
+    let points = t("skill_points", &[("points", &player.skill_points())]);
+    println!("\n    {}", points);
+}
+
+pub fn skill_learned(skill: Skill) {
+    if json() {
+        return json_event("skill_learned", json!({ "skill": skill.to_string() }));
+    }
+    let skill = skill.to_string().bold();
+    println!("  {}", t("skill_learned", &[("skill", &skill)]));
+}
+
+pub fn skill_used(character: &Character, skill: Skill, hp: i32, mp: i32) {
+    let data = json!({"character": json_character(character), "skill": skill.to_string(), "hp": hp, "mp": mp});
+    if battle_event("skill_used", data) || !show_turn() {
+        return;
+    }
+    battle_log(
+        character,
+        &format_stat_change(character, hp, mp, false, &skill.to_string()),
+    );
+}
+
+pub fn bestiary_entry(name: &str) {
+    if json() {
+        return json_event("bestiary_entry", json!({ "class": name }));
+    }
+    if !quiet() {
+        let name = i18n::name(name);
+        println!("   \u{1F4D6} {}", t("bestiary_entry", &[("class", &name)]));
+    }
+}
+
+/// Print every achievement with the player's progress towards it.
+pub fn achievements(achievements: Vec<(Achievement, i32, i32)>) {
+    if json() {
+        let achievements: Vec<Value> = achievements
+            .iter()
+            .map(|(achievement, progress, goal)| {
+                json!({"achievement": achievement.description(), "progress": progress, "goal": goal})
+            })
+            .collect();
+        return json_event("achievements", json!({ "achievements": achievements }));
+    }
+    for (achievement, progress, goal) in achievements {
+        let line = format!("{} {}/{}", achievement.description(), progress, goal);
+        if progress == goal {
+            println!("  {} {}", paint("✔", Role::Success), line.dimmed());
+        } else {
+            println!("  {} {}", "□".dimmed(), line);
+        }
+    }
+}
+
+/// Print the current challenges with their progress and streaks.
+pub fn challenges(challenges: Vec<(String, i32, i32, i32)>) {
+    if json() {
+        let challenges: Vec<Value> = challenges
+            .iter()
+            .map(|(challenge, progress, goal, streak)| {
+                json!({"challenge": challenge, "progress": progress, "goal": goal, "streak": streak})
+            })
+            .collect();
+        return json_event("challenges", json!({ "challenges": challenges }));
+    }
+    for (challenge, progress, goal, streak) in challenges {
+        let mut line = format!("{} {}/{}", challenge, progress, goal);
+        if streak > 0 {
+            line.push_str(&format!(" {}", t("streak", &[("streak", &streak)])));
+        }
+        if progress == goal {
+            println!("  {} {}", paint("✔", Role::Success), line.dimmed());
+        } else {
+            println!("  {} {}", "□".dimmed(), line);
+        }
+    }
+}
+
+pub fn challenge_completed(challenge: &str, streak: i32, items: &HashMap<Key, i32>, gold: i32) {
+    if json() {
+        return json_event(
+            "challenge_completed",
+            json!({"challenge": challenge, "streak": streak, "reward": gold, "items": items}),
+        );
+    }
+    if !quiet() {
+        let mut reward = format_gold_signed(gold).to_string();
+        for (key, count) in items {
+            reward.push_str(&format!(" +{}x{}", i18n::name(&key.to_string()), count));
+        }
+        let text = t(
+            "challenge_completed",
+            &[("reward", &reward), ("challenge", &challenge)],
+        );
+        let streak = t("streak", &[("streak", &streak)]);
+        println!("   {} {}", text, streak.dimmed());
+    }
+}
+
+/// Show the raid of the week and whether its boss was beaten.
+pub fn raid(raid: &Raid, beaten: bool) {
+    if json() {
+        return json_event(
+            "raid",
+            json!({
+                "boss": raid.boss.name,
+                "lair": raid.lair.to_string(),
+                "enrage_turns": raid.enrage_turns,
+                "title": raid.title(),
+                "beaten": beaten,
+            }),
+        );
+    }
+    let text = t(
+        "raid",
+        &[
+            ("boss", &i18n::name(&raid.boss.name)),
+            ("lair", &raid.lair.to_string()),
+            ("turns", &raid.enrage_turns),
+            ("title", &raid.title()),
+        ],
+    );
+    if beaten {
+        println!("  {} {}", paint("✔", Role::Success), text.dimmed());
+    } else {
+        println!("  {} {}", "□".dimmed(), text);
+    }
+}
+
+pub fn raid_beaten(items: &HashMap<Key, i32>, gold: i32) {
+    if json() {
+        return json_event("raid_beaten", json!({"reward": gold, "items": items}));
+    }
+    if !quiet() {
+        let mut reward = format_gold_signed(gold).to_string();
+        for (key, count) in items {
+            reward.push_str(&format!(" +{}x{}", i18n::name(&key.to_string()), count));
+        }
+        println!("   {}", t("raid_beaten", &[("reward", &reward)]));
+    }
+}
+
+/// List the titles earned, marking the one shown with the hero.
+pub fn titles(titles: &[String], active: Option<&str>) {
+    if json() {
+        return json_event("titles", json!({"titles": titles, "active": active}));
+    }
+    if titles.is_empty() {
+        println!("  {}", t("no_titles", &[]).dimmed());
+    }
+    for title in titles {
+        if Some(title.as_str()) == active {
+            let title = paint(&format!("\u{2726}{}", title), Role::Title).bold();
+            println!("  {}", title);
+        } else {
+            println!("   {}", title);
+        }
+    }
+}
+
+pub fn title_earned(title: &str) {
+    if json() {
+        return json_event("title_earned", json!({ "title": title }));
+    }
+    if !quiet() {
+        let title = paint(&format!("\u{2726}{}", title), Role::Title).bold();
+        println!("   {}", t("title_earned", &[("title", &title)]));
+    }
+}
+
+pub fn title_set(title: Option<&str>) {
+    if json() {
+        return json_event("title_set", json!({ "title": title }));
+    }
+    match title {
+        Some(title) => {
+            let title = paint(&format!("\u{2726}{}", title), Role::Title);
+            println!("  {}", t("title_set", &[("title", &title)]));
+        }
+        None => println!("  {}", t("title_cleared", &[])),
+    }
+}
+
+/// Warn that the saved game was replaced by an older copy, which ends the
+/// ironman run.
+pub fn ironman_lost() {
+    if json() {
+        return json_event("ironman_lost", json!({}));
+    }
+    println!("{}", paint(&t("ironman_lost", &[]), Role::Damage));
 }
 
-pub fn chest(items: &HashMap<Key, i32>, gold: i32) {
-    println!("{}", format_ls("\u{1F4E6}", items, gold));
+/// Tell where the daemon listens for commands.
+pub fn daemon_listening(socket: &std::path::Path) {
+    let socket = socket.display().to_string();
+    if json() {
+        return json_event("daemon_listening", json!({ "socket": socket }));
+    }
+    println!("{}", t("daemon_listening", &[("socket", &socket)]));
 }
 
-pub fn tombstone(items: &HashMap<Key, i32>, gold: i32) {
-    println!("{}", format_ls("\u{1FAA6} ", items, gold));
+/// Warn that the saved game couldn't be read and the backup of the previous
+/// save was loaded instead.
+pub fn save_recovered() {
+    if json() {
+        return json_event("save_recovered", json!({}));
+    }
+    println!("{}", paint(&t("save_recovered", &[]), Role::Damage));
 }
 
-pub fn bribe(player: &Character, amount: i32) {
-    if amount > 0 {
-        let suffix = format!("bribed {}", format_gold_signed(-amount));
-        battle_log(player, &suffix);
+/// Print the speedrun of the game, with its time and splits, as a summary
+/// to share.
+pub fn speedrun(game: &Game, now: u64) {
+    let run = match &game.speedrun {
+        Some(run) => run,
+        None => return,
+    };
+    if json() {
+        let splits: Vec<Value> = run
+            .splits()
+            .iter()
+            .map(|split| json!({"split": split.name, "time": split.time}))
+            .collect();
+        return json_event(
+            "speedrun",
+            json!({
+                "class": game.player.name(),
+                "level": game.player.level,
+                "difficulty": game.difficulty,
+                "hardcore": game.hardcore,
+                "time": run.elapsed(now),
+                "finished": run.is_finished(),
+                "splits": splits,
+            }),
+        );
+    }
+    let status = if run.is_finished() {
+        paint(&t("speedrun_finished", &[]), Role::Success)
     } else {
-        battle_log(player, "can't bribe!");
+        t("speedrun_running", &[]).dimmed()
+    };
+    println!(
+        "  {}[{}]{} {} {}",
+        i18n::name(&game.player.name()),
+        game.player.level,
+        format_mode(game),
+        format_duration(run.elapsed(now)),
+        status
+    );
+    let width = run.splits().iter().map(|s| s.name.len()).max().unwrap_or(0);
+    for split in run.splits() {
+        println!(
+            "    {:width$} {}",
+            split.name,
+            format_duration(split.time),
+            width = width
+        );
     }
 }
 
-pub fn run_away(player: &Character, success: bool) {
-    if success {
-        battle_log(player, "fled!");
-    } else {
-        battle_log(player, "can't run!");
+pub fn speedrun_split(split: &Split) {
+    if json() {
+        return json_event(
+            "speedrun_split",
+            json!({"split": split.name, "time": split.time}),
+        );
+    }
+    if !quiet() {
+        println!(
+            "   \u{23F1} {} {}",
+            split.name,
+            format_duration(split.time).dimmed()
+        );
     }
 }
 
-pub fn heal_item(
-    player: &Character,
-    item: &str,
-    recovered_hp: i32,
-    recovered_mp: i32,
-    healed: bool,
-) {
-    let color = if recovered_mp > 0 { "purple" } else { "green" };
-
-    if recovered_hp > 0 || recovered_mp > 0 || healed {
-        battle_log(
-            player,
-            &format_stat_change(
-                player,
-                recovered_hp,
-                recovered_mp,
-                healed,
-                &item.color(color).to_string(),
-            ),
+pub fn achievement_unlocked(achievement: &Achievement) {
+    if json() {
+        return json_event(
+            "achievement_unlocked",
+            json!({"achievement": achievement.description()}),
+        );
+    }
+    if !quiet() {
+        let text = t(
+            "achievement_unlocked",
+            &[("achievement", &achievement.description())],
         );
+        println!("   \u{1F3C6} {}", text);
     }
 }
 
-pub fn heal(
-    player: &Character,
-    location: &Location,
-    recovered_hp: i32,
-    recovered_mp: i32,
-    healed: bool,
-) {
-    if recovered_hp > 0 || recovered_mp > 0 || healed {
-        log(
-            player,
-            location,
-            &format_stat_change(player, recovered_hp, recovered_mp, healed, ""),
+pub fn quest_done(items: &HashMap<Key, i32>, gold: i32) {
+    if json() {
+        return json_event("quest_done", json!({ "reward": gold, "items": items }));
+    }
+    if !quiet() {
+        let mut reward = format_gold_signed(gold).to_string();
+        for (key, count) in items {
+            reward.push_str(&format!(" +{}x{}", i18n::name(&key.to_string()), count));
+        }
+        println!("   {}", t("quest_completed", &[("reward", &reward)]));
+    }
+}
+
+pub fn item_description(item: &str, description: &str) {
+    if json() {
+        return json_event(
+            "item_description",
+            json!({"item": item, "description": description}),
         );
     }
+    println!("{}: {}", item, description);
 }
 
-pub fn change_class(player: &Character, lost_xp: i32) {
-    if lost_xp > 0 {
-        battle_log(player, &format!("-{}xp", lost_xp).bright_red().to_string());
+pub fn inventory(game: &Game) {
+    if json() {
+        return json_event("inventory", json!({ "inventory": game.inventory() }));
     }
+    println!("{}", format_inventory(game));
 }
 
-pub fn stat_increase(player: &Character, stat: &str, increase: i32) {
-    let suffix = if stat == "level" {
-        level_up(increase)
-    } else {
-        format!("+{}{}", increase, stat).cyan().to_string()
-    };
-    battle_log(player, &suffix);
+pub fn work_dir(location: &Location) {
+    if json() {
+        return json_event("work_dir", json!({ "path": location.path_string() }));
+    }
+    println!("{}", location.path_string());
 }
 
-/// Print the hero status according to options
-pub fn status(game: &Game) {
-    if plain() {
-        plain_status(game);
-    } else if quiet() {
-        short_status(game);
-    } else {
-        long_status(game)
+pub fn exported(file: &str) {
+    if json() {
+        return json_event("exported", json!({ "file": file }));
     }
+    println!("{}", t("exported", &[("file", &file)]));
 }
 
-pub fn shop_list(game: &Game, items: Vec<(i32, String)>) {
-    for (cost, item) in items {
-        println!("    {:<10}  {}", item, format_gold(cost));
+/// Tell what syncing the game did. On conflicts, the gold and items of both
+/// games are listed where they differ, so the player knows what was lost.
+#[cfg(feature = "native")]
+pub fn synced(synced: &crate::sync::Synced) {
+    use crate::sync::{Side, Synced};
+
+    let (event, conflict) = match synced {
+        Synced::UpToDate => ("sync_up_to_date", None),
+        Synced::Pushed => ("sync_pushed", None),
+        Synced::Pulled => ("sync_pulled", None),
+        Synced::Conflict(conflict) if conflict.kept == Side::Local => {
+            ("sync_kept_local", Some(conflict))
+        }
+        Synced::Conflict(conflict) => ("sync_kept_remote", Some(conflict)),
+    };
+    if json() {
+        let mut data = json!({ "outcome": event.trim_start_matches("sync_") });
+        if let Some(conflict) = conflict {
+            data["gold"] = json!({"local": conflict.gold.0, "remote": conflict.gold.1});
+            data["items"] = conflict
+                .items
+                .iter()
+                .map(
+                    |(item, local, remote)| json!({"item": item, "local": local, "remote": remote}),
+                )
+                .collect();
+        }
+        return json_event("synced", data);
+    }
+    let conflict = match conflict {
+        Some(conflict) => conflict,
+        None => return println!("{}", t(event, &[])),
+    };
+    println!("{}", paint(&t(event, &[]), Role::Damage));
+    let (local, remote) = (format_gold(conflict.gold.0), format_gold(conflict.gold.1));
+    let gold = t("sync_differs", &[("local", &local), ("remote", &remote)]);
+    println!("  {:<8}{}", "", gold);
+    for (item, local, remote) in &conflict.items {
+        let counts = t("sync_differs", &[("local", local), ("remote", remote)]);
+        println!("  {:<8}{}", i18n::name(item), counts);
     }
+}
 
-    println!("\n    funds: {}", format_gold(game.gold));
+/// Show the changes made, or that would be made, to upgrade the game data.
+pub fn migration(changes: &[String], dry_run: bool) {
+    if json() {
+        return json_event(
+            "migration",
+            json!({ "changes": changes, "dry_run": dry_run }),
+        );
+    }
+    if changes.is_empty() {
+        println!("{}", t("up_to_date", &[]));
+        return;
+    }
+    for change in changes {
+        println!("  - {}", change);
+    }
+    if dry_run {
+        println!("{}", t("dry_run", &[]).dimmed());
+    }
 }
 
-pub fn shop_buy(cost: i32, items: &HashMap<Key, i32>) {
-    if !items.is_empty() {
-        println!("  {}", format_ls("", items, -cost));
+/// List the rules to use items automatically during battles.
+pub fn auto_use(rules: &[(Key, Trigger)]) {
+    if json() {
+        let rules: Vec<Value> = rules
+            .iter()
+            .map(|(key, trigger)| json!({"item": key, "condition": trigger.to_string()}))
+            .collect();
+        return json_event("auto_use", json!({ "rules": rules }));
+    }
+    for (key, trigger) in rules {
+        println!("  {:<8}{}", key.to_string(), trigger);
     }
 }
 
-pub fn quest_list(quests: Vec<(bool, String)>) {
-    for (completed, quest) in quests {
-        if completed {
-            println!("  {} {}", "✔".green(), quest.dimmed());
+/// List the profiles, marking the active one.
+pub fn profiles(profiles: &[String], active: &str) {
+    if json() {
+        return json_event(
+            "profiles",
+            json!({ "profiles": profiles, "active": active }),
+        );
+    }
+    for profile in profiles {
+        if profile == active {
+            println!("  {} {}", paint("*", Role::Success), profile.bold());
         } else {
-            println!("  {} {}", "□".dimmed(), quest);
+            println!("    {}", profile);
         }
     }
 }
 
-pub fn quest_done(reward: i32) {
-    if !quiet() {
-        println!("   {} quest completed!", format_gold_signed(reward));
+pub fn class_options(classes: &[String]) {
+    if json() {
+        return json_event("class_options", json!({ "classes": classes }));
+    }
+    // not translated, since these are typed to choose the class
+    println!(
+        "{}",
+        t("class_options", &[("classes", &classes.join(", "))])
+    );
+}
+
+/// Print the error that finished the command, if any.
+/// Report a hook that couldn't be run, which doesn't interrupt the game.
+pub fn hook_failed(hook: &str, error: &str) {
+    if json() {
+        return json_event("hook_failed", json!({ "hook": hook, "error": error }));
+    }
+    println!(
+        "{}",
+        paint(
+            &t("hook_failed", &[("hook", &hook), ("error", &error)]),
+            Role::Damage
+        )
+    );
+}
+
+/// Report a plugin handler that failed, which doesn't interrupt the game.
+pub fn plugin_failed(plugin: &str, handler: &str, error: &str) {
+    if json() {
+        return json_event(
+            "plugin_failed",
+            json!({ "plugin": plugin, "handler": handler, "error": error }),
+        );
+    }
+    let text = t(
+        "plugin_failed",
+        &[
+            ("plugin", &plugin),
+            ("handler", &handler),
+            ("error", &error),
+        ],
+    );
+    println!("{}", paint(&text, Role::Damage));
+}
+
+pub fn error(message: &str) {
+    if json() {
+        return json_event("error", json!({ "message": message }));
+    }
+    // don't print a new line if error message is empty
+    if !message.is_empty() {
+        println!("{}", message);
     }
 }
 
 fn level_up(levels_up: i32) -> String {
     if levels_up > 0 {
         let plus = (0..levels_up).map(|_| "+").collect::<String>();
-        format!(" {}level", plus).cyan().to_string()
+        paint(&format!(" {}level", plus), Role::Xp).to_string()
     } else {
         "".to_string()
     }
@@ -202,7 +2194,13 @@ fn long_status(game: &Game) {
     let player = &game.player;
     let location = &game.location;
 
-    println!("{}@{}", format_character(player), location);
+    println!(
+        "{}@{}{}{}",
+        format_character(player),
+        location,
+        format_mode(game),
+        format_title(game)
+    );
     println!(
         "    hp:{} {}/{}",
         hp_display(player, 10),
@@ -228,8 +2226,88 @@ fn long_status(game: &Game) {
         player.xp,
         player.xp_for_next()
     );
-    if let Some(status) = player.status_effect {
-        println!("    status: {}", format_status_effect(status).bright_red());
+    if player.growth_points > 0 {
+        println!("    {}", format_growth_points(player));
+    }
+    if game.rested.battles > 0 {
+        let text = t(
+            "rested_status",
+            &[
+                ("battles", &game.rested.battles),
+                ("bonus", &rested::RESTED_XP),
+            ],
+        );
+        println!("    {}", paint(&text, Role::Xp));
+    }
+    let reputation: Vec<String> = game
+        .reputation
+        .list()
+        .into_iter()
+        .filter(|(_, reputation, _)| *reputation != 0)
+        .map(|(faction, reputation, standing)| {
+            format!(
+                "{} {}({})",
+                i18n::name(&faction.to_string()),
+                i18n::name(&standing.to_string()),
+                reputation
+            )
+        })
+        .collect();
+    if !reputation.is_empty() {
+        println!(
+            "    {}",
+            t("reputation", &[("factions", &reputation.join(", "))])
+        );
+    }
+    if !player.status_effects.is_empty() {
+        let statuses: Vec<String> = player
+            .status_effects
+            .iter()
+            .map(|status| {
+                let (name, emoji) = status_effect_params(status.effect);
+                t(
+                    "turns",
+                    &[
+                        ("emoji", &emoji),
+                        ("status", &i18n::name(name)),
+                        ("stacks", &status.stacks),
+                        ("turns", &status.turns),
+                    ],
+                )
+            })
+            .collect();
+        let statuses = paint(&statuses.join(" "), Role::Damage);
+        println!("    {}", t("status", &[("effects", &statuses)]));
+    }
+    if !game.party.is_empty() {
+        println!("    {}", t("party", &[]));
+        for member in &game.party {
+            println!(
+                "    {}{}{}{} {}/{}hp",
+                format_character(member),
+                hp_display(member, 4),
+                mp_display(member, 4),
+                xp_display(member, 4),
+                member.current_hp,
+                member.max_hp()
+            );
+        }
+    }
+    if let Some(companion) = &game.companion {
+        let companion = format!(
+            "{}[{}] {}/{}hp",
+            i18n::name(&companion.name()),
+            companion.character.level,
+            companion.character.current_hp,
+            companion.character.max_hp()
+        );
+        println!("    {}", t("companion", &[("companion", &companion)]));
+    }
+    let spells = player.spells();
+    if !spells.is_empty() {
+        let spells: Vec<String> = spells.iter().map(|s| s.to_string()).collect();
+        let spells = paint(&i18n::name(&spells.join(" ")), Role::Mp);
+        println!("    {}", t("spells", &[("spells", &spells)]));
     }
     println!(
         "    att:{}   mag:{}   def:{}   spd:{}",
@@ -239,34 +2317,78 @@ fn long_status(game: &Game) {
         player.speed()
     );
     println!("    {}", format_equipment(player));
-    println!("    {}", format_inventory(game));
+    let slots = t(
+        "slots",
+        &[
+            ("used", &game.slots_used()),
+            ("max", &game.player.inventory_slots()),
+        ],
+    );
+    println!("    {} {}", format_inventory(game), slots);
     println!("    {}", format_gold(game.gold));
+    println!("    {}", game.clock.to_string().dimmed());
+    if let Some(run) = &game.speedrun {
+        let time = format_duration(run.elapsed(activity::now()));
+        println!("    {}", t("speedrun", &[("time", &time)]).dimmed());
+    }
+}
+
+fn format_growth_points(player: &Character) -> String {
+    let text = t(
+        "growth_points",
+        &[("points", &player.growth_points.to_string())],
+    );
+    paint(&text, Role::Xp).to_string()
 }
 
 fn short_status(game: &Game) {
     let player = &game.player;
+    let suffix = format!(
+        "{}{}{}",
+        format_status_emojis(player),
+        format_mode(game),
+        format_title(game)
+    );
+    log(player, &game.location, suffix.trim_start());
+}
 
-    let suffix = if let Some(status) = player.status_effect {
-        let (_, emoji) = status_effect_params(status);
-        emoji
-    } else {
-        ""
-    };
-    log(player, &game.location, suffix);
+/// The names of the modes of the game other than the defaults: the
+/// difficulty if not normal, hardcore and ironman.
+fn game_mode(game: &Game) -> Vec<String> {
+    let mut mode = Vec::new();
+    if game.difficulty != Difficulty::Normal {
+        mode.push(game.difficulty.to_string());
+    }
+    if game.hardcore {
+        mode.push(String::from("hardcore"));
+    }
+    if game.ironman {
+        mode.push(String::from("ironman"));
+    }
+    mode
 }
 
 fn plain_status(game: &Game) {
     let player = &game.player;
 
-    let status_effect = if let Some(status) = player.status_effect {
-        let (name, _) = status_effect_params(status);
-        format!("status:{}\t", name)
-    } else {
+    let status_effect = if player.status_effects.is_empty() {
         String::new()
+    } else {
+        let names: Vec<&str> = player
+            .status_effects
+            .iter()
+            .map(|status| status_effect_params(status.effect).0)
+            .collect();
+        format!("status:{}\t", names.join(","))
     };
+    let mode: String = game_mode(game)
+        .iter()
+        .map(|mode| format!("{}\t", mode))
+        .collect();
+    let title = title::shown(game).map_or(String::new(), |title| format!("{}\t", title));
 
     println!(
-        "{}[{}]\t@{}\thp:{}/{}\tmp:{}/{}\txp:{}/{}\tatt:{}\tmag:{}\tdef:{}\tspd:{}\t{}{}\t{}\tg:{}",
+        "{}[{}]\t@{}\thp:{}/{}\tmp:{}/{}\txp:{}/{}\tatt:{}\tmag:{}\tdef:{}\tspd:{}\t{}{}{}{}\t{}\tg:{}",
         player.name(),
         player.level,
         game.location,
@@ -280,6 +2402,8 @@ fn plain_status(game: &Game) {
         player.physical_attack(),
         player.deffense(),
         player.speed(),
+        mode,
+        title,
         status_effect,
         format_equipment(player),
         format_inventory(game),
@@ -294,7 +2418,8 @@ fn format_ls(emoji: &str, items: &HashMap<Key, i32>, gold: i32) -> String {
         string.push_str(&format!("{} ", format_gold_signed(gold)));
     }
     for (key, count) in items {
-        string.push_str(&format!("+{}x{} ", key, count));
+        let item = format!("+{}x{}", i18n::name(&key.to_string()), count);
+        string.push_str(&format!("{} ", highlight(item, key.is_rare(), false)));
     }
     string
 }
@@ -325,34 +2450,160 @@ fn battle_log(character: &Character, suffix: &str) {
 }
 
 fn format_character(character: &Character) -> String {
-    let name = format!("{:>8}", character.name());
+    // gilded enemies are marked with a star
+    let class = i18n::name(&character.name());
+    let name = if character.gilded {
+        format!("{:>8}", format!("\u{2605}{}", class))
+    } else {
+        format!("{:>8}", class)
+    };
     let name = if character.name() == "shadow" {
         name.dimmed()
+    } else if character.gilded {
+        paint(&name, Role::Gilded).bold()
     } else if character.is_player() {
         name.bold()
     } else {
-        name.yellow().bold()
+        paint(&name, Role::Enemy).bold()
     };
     format!("{}[{}]", name, character.level)
 }
 
+/// A simplified human-readable version of a recorded battle event, based on
+/// the character data saved with it.
+fn format_replayed(event: &Value) -> Option<String> {
+    let character = |data: &Value| {
+        let name = i18n::name(data["name"].as_str().unwrap_or_default());
+        let name = format!("{:>8}", name);
+        let name = if data["xp"].is_null() {
+            paint(&name, Role::Enemy).bold()
+        } else {
+            name.bold()
+        };
+        format!(
+            "{}[{}] {}/{}hp",
+            name, data["level"], data["hp"], data["max_hp"]
+        )
+    };
+    let number = |key: &str| event[key].as_i64().unwrap_or_default();
+    let text = |key: &str| event[key].as_str().unwrap_or_default().to_string();
+
+    let line = match event["event"].as_str()? {
+        "battle" => {
+            let enemies: Vec<String> = event["enemies"]
+                .as_array()?
+                .iter()
+                .map(|enemy| {
+                    let name = i18n::name(enemy["name"].as_str().unwrap_or_default());
+                    format!("{}[{}]", name, enemy["level"])
+                })
+                .collect();
+            let title = t(
+                "battle_at",
+                &[
+                    ("location", &text("location")),
+                    ("enemies", &enemies.join(" ")),
+                ],
+            );
+            format!("  {}", title.bold())
+        }
+        "attack" => {
+            let result = if event["missed"] == json!(true) {
+                t("dodged", &[])
+            } else if event["critical"] == json!(true) {
+                format!("-{}hp {}", number("damage"), t("critical", &[]))
+            } else if let Some(status) = event["status_effect"].as_str() {
+                format!("-{}hp {}", number("damage"), i18n::name(status))
+            } else {
+                format!("-{}hp", number("damage"))
+            };
+            format!("{} {}", character(&event["receiver"]), result)
+        }
+        "cast" => format!(
+            "{} -{}mp {}!",
+            character(&event["caster"]),
+            number("mp_cost"),
+            i18n::name(&text("spell"))
+        ),
+        "status_effect" | "skill_used" | "heal_item" => {
+            let who = if event["player"].is_null() {
+                &event["character"]
+            } else {
+                &event["player"]
+            };
+            let source = i18n::name(&[text("skill"), text("item")].concat());
+            format!(
+                "{} {:+}hp {:+}mp {}",
+                character(who),
+                number("hp"),
+                number("mp"),
+                source
+            )
+        }
+        "status_expired" => {
+            let status = i18n::name(&text("status_effect"));
+            let text = t("wore_off", &[("emoji", &""), ("status", &status)]);
+            format!("{} {}", character(&event["character"]), text.trim())
+        }
+        "stunned" => {
+            let text = t("stunned", &[("emoji", &"")]);
+            format!("{} {}", character(&event["character"]), text.trim())
+        }
+        "enraged" => format!("{} {}", character(&event["character"]), t("enraged", &[])),
+        "equipment_broken" => {
+            let piece = i18n::name(&text("equipment"));
+            let text = t("broke", &[("piece", &piece)]);
+            format!("{} {}", character(&event["character"]), text)
+        }
+        "bribe" if event["success"] == json!(true) => {
+            let gold = format!("{}g", -number("gold"));
+            let text = t("bribed", &[("gold", &gold)]);
+            format!("{} {}", character(&event["player"]), text)
+        }
+        "bribe" => format!("{} {}", character(&event["player"]), t("cant_bribe", &[])),
+        "run_away" if event["success"] == json!(true) => {
+            format!("{} {}", character(&event["player"]), t("fled", &[]))
+        }
+        "run_away" => format!("{} {}", character(&event["player"]), t("cant_run", &[])),
+        "battle_won" => format!(
+            "{} +{}xp +{}g",
+            character(&event["player"]),
+            number("xp"),
+            number("gold")
+        ),
+        "battle_lost" => format!("{} \u{1F480}", character(&event["player"])),
+        _ => return None,
+    };
+    Some(line)
+}
+
 fn format_equipment(character: &Character) -> String {
     let mut fragments = Vec::new();
 
     if let Some(sword) = &character.sword {
-        fragments.push(sword.to_string());
-    }
-
-    if let Some(shield) = &character.shield {
-        fragments.push(shield.to_string());
+        let name = i18n::name(&sword.to_string());
+        fragments.push(highlight(name, false, sword.is_cursed()));
     }
 
-    if let Some(ring) = &character.left_ring {
-        fragments.push(ring.to_string());
+    for piece in [
+        &character.shield,
+        &character.helmet,
+        &character.armor,
+        &character.boots,
+    ]
+    .iter()
+    .filter_map(|p| p.as_ref())
+    {
+        let name = i18n::name(&piece.to_string());
+        fragments.push(highlight(name, false, piece.is_cursed()));
     }
 
-    if let Some(ring) = &character.right_ring {
-        fragments.push(ring.to_string());
+    for ring in [&character.left_ring, &character.right_ring]
+        .iter()
+        .filter_map(|r| r.as_ref())
+    {
+        let name = i18n::name(&format_ring(character, ring));
+        fragments.push(highlight(name, true, character.is_cursed(ring)));
     }
 
     format!("equip:{{{}}}", fragments.join(","))
@@ -362,29 +2613,51 @@ pub fn format_inventory(game: &Game) -> String {
     let mut items = game
         .inventory()
         .iter()
-        .map(|(k, v)| format!("{}x{}", k, v))
-        .collect::<Vec<String>>();
+        .map(|(k, v)| match k {
+            Key::Ring(ring) if game.player.is_cursed(ring) => {
+                let name = format!(
+                    "{} {}x{}",
+                    i18n::name("cursed"),
+                    i18n::name(&k.to_string()),
+                    v
+                );
+                (name, k.is_rare(), true)
+            }
+            _ => (
+                format!("{}x{}", i18n::name(&k.to_string()), v),
+                k.is_rare(),
+                false,
+            ),
+        })
+        .collect::<Vec<_>>();
 
+    // sorted before highlighting, so colors don't change the order
     items.sort();
+    let items: Vec<String> = items
+        .into_iter()
+        .map(|(name, rare, cursed)| highlight(name, rare, cursed))
+        .collect();
     format!("item:{{{}}}", items.join(","))
 }
 
 fn format_attack(receiver: &Character, attack: &AttackType, damage: i32, mp_cost: i32) -> String {
     let magic_effect = if mp_cost > 0 {
-        format!("\u{2728} -{}mp ", mp_cost).purple().to_string()
+        paint(&format!("\u{2728} -{}mp ", mp_cost), Role::Mp).to_string()
     } else {
         String::from("")
     };
 
     match attack {
         AttackType::Regular => format_hp_change(receiver, -damage, &magic_effect),
-        AttackType::Critical => {
-            format_hp_change(receiver, -damage, &format!("{}critical!", magic_effect))
-        }
+        AttackType::Critical => format_hp_change(
+            receiver,
+            -damage,
+            &format!("{}{}", magic_effect, t("critical", &[])),
+        ),
         AttackType::Effect(status_effect) => {
             format_hp_change(receiver, -damage, &format_status_effect(*status_effect))
         }
-        AttackType::Miss => format!("{}dodged!", magic_effect),
+        AttackType::Miss => format!("{}{}", magic_effect, t("dodged", &[])),
     }
 }
 
@@ -395,9 +2668,13 @@ fn format_stat_change(
     healed: bool,
     suffix: &str,
 ) -> String {
-    let mut healed_text = String::new();
+    let mut hp_text = String::new();
     let mut mp_text = String::new();
+    let mut healed_text = String::new();
 
+    if hp != 0 {
+        hp_text = format!("{} ", format_hp_change(receiver, hp, ""));
+    }
     if mp != 0 {
         mp_text = format!("{:+}mp ", mp);
     }
@@ -407,51 +2684,75 @@ fn format_stat_change(
 
     format!(
         "{}{}{}{}",
-        &format_hp_change(receiver, hp, ""),
-        mp_text.purple(),
-        healed_text.green(),
+        hp_text,
+        paint(&mp_text, Role::Mp),
+        paint(&healed_text, Role::Hp),
         suffix
     )
 }
 
 fn format_hp_change(receiver: &Character, amount: i32, suffix: &str) -> String {
     if amount != 0 {
-        let color = if receiver.is_player() {
-            if amount < 0 {
-                "bright red"
-            } else {
-                "green"
-            }
+        let text = format!("{:+}hp {}", amount, suffix);
+        let text = text.trim_end();
+        if !receiver.is_player() {
+            text.white().to_string()
+        } else if amount < 0 {
+            paint(text, Role::Damage).to_string()
         } else {
-            "white"
-        };
-        format!("{:+}hp {}", amount, suffix)
-            .color(color)
-            .to_string()
+            paint(text, Role::Hp).to_string()
+        }
     } else {
         String::from("")
     }
 }
 
+/// Color rare and cursed items, except in the plain output meant for
+/// scripts.
+fn highlight(text: String, rare: bool, cursed: bool) -> String {
+    if plain() {
+        text
+    } else if cursed {
+        paint(&text, Role::Cursed).to_string()
+    } else if rare {
+        paint(&text, Role::Rare).to_string()
+    } else {
+        text
+    }
+}
+
 fn format_status_effect(status_effect: StatusEffect) -> String {
     let (name, emoji) = status_effect_params(status_effect);
-    format!("{} {}!", emoji, name)
+    format!("{} {}!", emoji, i18n::name(name))
 }
 
 fn status_effect_params(status_effect: StatusEffect) -> (&'static str, &'static str) {
     match status_effect {
         StatusEffect::Burn => ("burn", "\u{1F525}"),
         StatusEffect::Poison => ("poison", "\u{2620}\u{FE0F} "),
+        StatusEffect::Stun => ("stun", "\u{1F4AB}"),
+        StatusEffect::Shield => ("shield", "\u{1F6E1}\u{FE0F} "),
+        StatusEffect::Haste => ("haste", "\u{1F4A8}"),
     }
 }
 
+/// The emojis of all the status effects of the character, e.g. to use as
+/// a log suffix.
+fn format_status_emojis(character: &Character) -> String {
+    character
+        .status_effects
+        .iter()
+        .map(|status| status_effect_params(status.effect).1)
+        .collect()
+}
+
 fn hp_display(character: &Character, slots: i32) -> String {
     bar_display(
         slots,
         character.current_hp,
         character.max_hp(),
-        "green",
-        "red",
+        Role::Hp,
+        Role::HpLost,
     )
 }
 
@@ -462,13 +2763,7 @@ fn mp_display(character: &Character, slots: i32) -> String {
         0
     };
 
-    bar_display(
-        slots,
-        current_mp,
-        character.max_mp(),
-        "purple",
-        "bright black",
-    )
+    bar_display(slots, current_mp, character.max_mp(), Role::Mp, Role::Empty)
 }
 
 fn xp_display(character: &Character, slots: i32) -> String {
@@ -477,8 +2772,8 @@ fn xp_display(character: &Character, slots: i32) -> String {
             slots,
             character.xp,
             character.xp_for_next(),
-            "cyan",
-            "bright black",
+            Role::Xp,
+            Role::Empty,
         )
     } else {
         // enemies don't have experience
@@ -490,18 +2785,14 @@ fn bar_display(
     slots: i32,
     current: i32,
     total: i32,
-    current_color: &str,
-    missing_color: &str,
+    current_role: Role,
+    missing_role: Role,
 ) -> String {
     let (filled, rest) = bar_slots(slots, total, current);
-    let current = (0..filled)
-        .map(|_| "x")
-        .collect::<String>()
-        .color(current_color);
-    let missing = (0..rest)
-        .map(|_| "-")
-        .collect::<String>()
-        .color(missing_color);
+    let current = (0..filled).map(|_| "x").collect::<String>();
+    let missing = (0..rest).map(|_| "-").collect::<String>();
+    let current = paint(&current, current_role);
+    let missing = paint(&missing, missing_role);
     format!("[{}{}]", current, missing)
 }
 
@@ -510,18 +2801,336 @@ fn bar_slots(slots: i32, total: i32, current: i32) -> (i32, i32) {
     (units, slots - units)
 }
 
+// JSON
+
+fn json_event(event: &str, mut data: Value) {
+    data["event"] = json!(event);
+    println!("{}", data);
+}
+
+/// Record the event if a battle is in progress, and print it in JSON mode.
+/// Returns whether it was printed, so the human-readable output is skipped.
+fn battle_event(event: &str, mut data: Value) -> bool {
+    data["event"] = json!(event);
+    if *RECORDING.lock().unwrap() {
+        if let Some(battle) = BATTLES.lock().unwrap().last_mut() {
+            battle.push(data.clone());
+        }
+    }
+    if json() {
+        println!("{}", data);
+    }
+    json()
+}
+
+fn json_character(character: &Character) -> Value {
+    let mut value = json!({
+        "name": character.name(),
+        "level": character.level,
+        "hp": character.current_hp,
+        "max_hp": character.max_hp(),
+        "mp": character.current_mp,
+        "max_mp": character.max_mp(),
+        "status_effects": character.status_effects,
+    });
+    if character.gilded {
+        value["gilded"] = json!(true);
+    }
+    if character.is_player() {
+        value["xp"] = json!(character.xp);
+        value["xp_for_next"] = json!(character.xp_for_next());
+    }
+    value
+}
+
+fn json_companion(companion: &Companion) -> Value {
+    let mut value = json_character(&companion.character);
+    value["xp"] = json!(companion.character.xp);
+    value["item"] = json!(companion.item.as_ref().map(|i| i.to_string()));
+    value
+}
+
+fn json_status(game: &Game) -> Value {
+    let player = &game.player;
+    let equipment: Vec<String> = [
+        &player.sword,
+        &player.shield,
+        &player.helmet,
+        &player.armor,
+        &player.boots,
+    ]
+    .iter()
+    .filter_map(|p| p.as_ref().map(|p| p.to_string()))
+    .chain(player.left_ring.iter().map(|r| format_ring(player, r)))
+    .chain(player.right_ring.iter().map(|r| format_ring(player, r)))
+    .collect();
+
+    json!({
+        "player": json_character(player),
+        "location": game.location.to_string(),
+        "attack": player.physical_attack(),
+        "magic": player.magic_attack(),
+        "defense": player.deffense(),
+        "speed": player.speed(),
+        "growth_points": player.growth_points,
+        "rested_battles": game.rested.battles,
+        "reputation": game.reputation.list().into_iter().map(|(faction, reputation, standing)| {
+            json!({ "faction": faction, "reputation": reputation, "standing": standing.to_string() })
+        }).collect::<Vec<Value>>(),
+        "equipment": equipment,
+        "spells": player.spells(),
+        "party": game.party.iter().map(json_character).collect::<Vec<Value>>(),
+        "companion": game.companion.as_ref().map(json_companion),
+        "inventory": game.inventory(),
+        "slots": game.slots_used(),
+        "max_slots": game.player.inventory_slots(),
+        "gold": game.gold,
+        "hardcore": game.hardcore,
+        "ironman": game.ironman,
+        "difficulty": game.difficulty,
+        "prestige": player.prestige,
+        "title": title::shown(game),
+        "clock": {
+            "hour": game.clock.hour(),
+            "night": game.clock.is_night(),
+            "weather": game.clock.weather,
+        },
+        "speedrun": game.speedrun.as_ref().map(|run| json!({
+            "time": run.elapsed(activity::now()),
+            "finished": run.is_finished(),
+        })),
+    })
+}
+
+/// Mark hardcore games, which are lost for good when the hero dies, and
+/// games not played at the normal difficulty.
+fn format_mode(game: &Game) -> String {
+    let mut mode = String::new();
+    if game.difficulty != Difficulty::Normal {
+        let difficulty = paint(&game.difficulty.to_string(), Role::Damage);
+        mode.push_str(&format!(" {}", difficulty));
+    }
+    if game.hardcore {
+        mode.push_str(&format!(" {}", paint("\u{2620}", Role::Damage)));
+    }
+    if game.ironman {
+        mode.push_str(&format!(" {}", paint("ironman", Role::Damage)));
+    }
+    mode
+}
+
+/// The title shown with the hero, if any.
+fn format_title(game: &Game) -> String {
+    match title::shown(game) {
+        Some(title) => format!(" {}", paint(&format!("\u{2726}{}", title), Role::Title)),
+        None => String::new(),
+    }
+}
+
+/// The ring name, marked if cursed and followed by its upgrade tier if it
+/// was upgraded.
+fn format_ring(character: &Character, ring: &Ring) -> String {
+    let name = if character.is_cursed(ring) {
+        format!("cursed {}", ring)
+    } else {
+        ring.to_string()
+    };
+    match character.ring_tier(ring) {
+        0 => name,
+        tier => format!("{}(+{})", name, tier),
+    }
+}
+
+fn format_prompt(game: &Game, format: &str) -> String {
+    // unknown placeholders are printed as is
+    let line = i18n::fill(format, |placeholder| prompt_value(game, placeholder));
+    line.trim_end().to_string()
+}
+
+fn prompt_value(game: &Game, placeholder: &str) -> Option<String> {
+    let player = &game.player;
+    let value = match placeholder {
+        "class" => player.name(),
+        "level" => player.level.to_string(),
+        "hp" => player.current_hp.to_string(),
+        "max_hp" => player.max_hp().to_string(),
+        "hp_bar" => {
+            let (filled, rest) = bar_slots(4, player.max_hp(), player.current_hp);
+            format!(
+                "[{}{}]",
+                "x".repeat(filled as usize),
+                "-".repeat(rest as usize)
+            )
+        }
+        "mp" => player.current_mp.to_string(),
+        "max_mp" => player.max_mp().to_string(),
+        "gold" => game.gold.to_string(),
+        "location" => game.location.to_string(),
+        "distance" => game.location.distance_from_home().len().to_string(),
+        "status" => player
+            .status_effects
+            .iter()
+            .map(|status| status_effect_params(status.effect).0)
+            .collect::<Vec<_>>()
+            .join(","),
+        "time" => game.clock.to_string(),
+        "title" => title::shown(game).unwrap_or_default().to_string(),
+        "mode" => game_mode(game).join(","),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Seconds as `h:mm:ss`.
+fn format_duration(seconds: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Print the results of a simulation, one line per distance with the share
+/// of battles won or chests found and what was obtained on average per run.
+pub fn simulation(kind: Kind, outcomes: &[Outcome]) {
+    let name = match kind {
+        Kind::Battles => "battles",
+        Kind::Chests => "chests",
+    };
+    if json() {
+        let outcomes: Vec<Value> = outcomes
+            .iter()
+            .map(|outcome| {
+                json!({
+                    "distance": outcome.distance,
+                    "runs": outcome.runs,
+                    "successes": outcome.successes,
+                    "gold": outcome.gold,
+                    "xp": outcome.xp,
+                    "items": outcome.items,
+                })
+            })
+            .collect();
+        return json_event("simulation", json!({"kind": name, "outcomes": outcomes}));
+    }
+    let success = t(&format!("simulation_{}", name), &[]);
+    for outcome in outcomes {
+        let runs = std::cmp::max(1, outcome.runs) as f64;
+        let rate = format!("{:.1}%", 100.0 * outcome.successes as f64 / runs);
+        let gold = format!("{:>8}", format!("{:.1}g", outcome.gold as f64 / runs));
+        let mut line = format!(
+            "  {} {:<4} {} {:>6} {}",
+            t("distance", &[]),
+            outcome.distance,
+            success,
+            rate,
+            paint(&gold, Role::Gold)
+        );
+        if kind == Kind::Battles {
+            line += &format!(" {:>8}", format!("{:.1}xp", outcome.xp as f64 / runs));
+        }
+        let items: Vec<String> = outcome
+            .items
+            .iter()
+            .map(|(key, count)| format!("{} {:.3}", i18n::name(key), *count as f64 / runs))
+            .collect();
+        println!("{}  {}", line, items.join(", ").dimmed());
+    }
+}
+
 fn format_gold(gold: i32) -> ColoredString {
-    format!("{}g", gold).yellow()
+    paint(&format!("{}g", gold), Role::Gold)
 }
 
 fn format_gold_signed(gold: i32) -> ColoredString {
-    format!("{:+}g", gold).yellow()
+    paint(&format!("{:+}g", gold), Role::Gold)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_json_status() {
+        let mut game = Game::new();
+        game.gold = 100;
+        game.player.sword = Some(crate::item::equipment::Equipment::sword(1));
+        game.add_item(Box::new(crate::item::Potion::new(1)));
+
+        let status = json_status(&game);
+        assert_eq!(1, status["player"]["level"]);
+        assert_eq!(game.player.current_hp, status["player"]["hp"]);
+        assert_eq!(0, status["player"]["xp"]);
+        assert_eq!(game.location.to_string(), status["location"]);
+        assert_eq!(json!(["sword[1]"]), status["equipment"]);
+        assert_eq!(1, status["inventory"]["potion"]);
+        assert_eq!(100, status["gold"]);
+
+        // enemies don't have experience
+        let class = crate::character::class::Class::enemy_by_name("zombie").unwrap();
+        let enemy = Character::new(class.clone(), 1);
+        let enemy_json = json_character(&enemy);
+        assert_eq!("zombie", enemy_json["name"]);
+        assert!(enemy_json.get("xp").is_none());
+        assert!(enemy_json.get("xp_for_next").is_none());
+    }
+
+    #[test]
+    fn test_format_replayed() {
+        let game = Game::new();
+        let enemy = Character::new(game.player.class.clone(), 3);
+        let start = json!({
+            "event": "battle",
+            "enemies": [json_character(&enemy)],
+            "location": "~/dev",
+        });
+        let line = format_replayed(&start).unwrap();
+        assert!(line.contains("battle at ~/dev"));
+        assert!(line.contains("warrior[3]"));
+
+        let attack = json!({
+            "event": "attack",
+            "receiver": json_character(&enemy),
+            "damage": 12,
+            "critical": true,
+        });
+        let line = format_replayed(&attack).unwrap();
+        assert!(line.contains("[3]"));
+        assert!(line.ends_with("-12hp critical!"));
+
+        let won = json!({"event": "battle_won", "player": json_character(&game.player), "xp": 20, "gold": 50});
+        assert!(format_replayed(&won).unwrap().ends_with("+20xp +50g"));
+
+        assert!(format_replayed(&json!({"event": "unknown"})).is_none());
+    }
+
+    #[test]
+    fn test_format_prompt() {
+        let mut game = Game::new();
+        game.gold = 120;
+        game.player.current_hp = 1;
+        let line = format_prompt(&game, "{class}[{level}]{hp_bar} {gold}g {status}");
+        assert_eq!("warrior[1][x---] 120g", line);
+
+        game.player.status_effects.inflict(StatusEffect::Poison);
+        let line = format_prompt(&game, "{hp}/{max_hp} {status} {unknown} {gold");
+        let expected = format!("1/{} poison {{unknown}} {{gold", game.player.max_hp());
+        assert_eq!(expected, line);
+
+        assert_eq!("warrior", format_prompt(&game, "{class} {title}"));
+        game.player.prestige = 1;
+        assert_eq!("warrior ascended", format_prompt(&game, "{class} {title}"));
+
+        assert_eq!("warrior", format_prompt(&game, "{class} {mode}"));
+        game.hardcore = true;
+        game.ironman = true;
+        assert_eq!(
+            "warrior hardcore,ironman",
+            format_prompt(&game, "{class} {mode}")
+        );
+    }
+
     #[test]
     fn test_bar_slots() {
         // simple case 1:1 between points and slots
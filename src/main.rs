@@ -1,17 +1,14 @@
-use game::Game;
+use rpg_cli::game::Game;
+use rpg_cli::speedrun::Speedrun;
+use rpg_cli::{activity, config, datafile, log, randomizer};
 
-mod character;
 mod command;
-mod datafile;
-mod game;
-mod item;
-mod location;
-mod log;
-mod quest;
-mod randomizer;
+mod completions;
+mod daemon;
+mod tui;
 
 use anyhow::Result;
-use clap::{crate_version, AppSettings, Clap};
+use clap::{crate_version, AppSettings, Clap, IntoApp};
 
 /// Your filesystem as a dungeon!
 #[derive(Clap)]
@@ -21,21 +18,35 @@ struct Opts {
     #[clap(subcommand)]
     cmd: Option<command::Command>,
 
-    /// Print succinct output when possible.
+    /// Print succinct output when possible, e.g. only the outcome of battles.
     #[clap(long, short, global = true)]
     quiet: bool,
 
+    /// Print detailed output, e.g. the enemy stats and every attack of battles.
+    #[clap(long, short, global = true, conflicts_with = "quiet")]
+    verbose: bool,
+
     /// Print machine-readable output when possible.
     #[clap(long, global = true)]
     plain: bool,
+
+    /// Print every event as a line of JSON, for integration with other programs.
+    #[clap(long, global = true)]
+    json: bool,
+
+    /// Seed the random number generator, so the same commands produce the same results.
+    /// The seed is saved with the game.
+    #[clap(long, global = true)]
+    seed: Option<u64>,
+
+    /// Load and save the game of the given profile instead of the active one.
+    #[clap(long, global = true)]
+    profile: Option<String>,
 }
 
 fn main() {
     if let Err(err) = run_game() {
-        // don't print a new line if error message is empty
-        if !err.to_string().is_empty() {
-            println!("{}", err);
-        };
+        log::error(&err.to_string());
 
         std::process::exit(1);
     }
@@ -45,17 +56,76 @@ fn main() {
 /// Inner errors are bubbled up.
 fn run_game() -> Result<()> {
     let opts: Opts = Opts::parse();
-    log::init(opts.quiet, opts.plain);
-    datafile::load_classes();
+    log::init(opts.quiet, opts.verbose, opts.plain, opts.json);
+
+    if let Some(command::Command::Completions { shell }) = &opts.cmd {
+        print!("{}", completions::script(&Opts::into_app(), shell));
+        return Ok(());
+    }
+
+    // the config chooses where the game is saved
+    datafile::load_config()?;
+    datafile::init_profile(opts.profile.clone())?;
+
+    // a running daemon already has the game loaded, so it's faster for it
+    // to run the command
+    if daemon::forward(&opts)? {
+        return Ok(());
+    }
+
+    // the prompt runs on every shell prompt, so it skips loading anything
+    // it doesn't need and never saves
+    if let Some(command::Command::Prompt { format }) = &opts.cmd {
+        if let Some(game) = datafile::peek() {
+            log::prompt(&game, format);
+        }
+        return Ok(());
+    }
+    if let Some(command::Command::Complete { kind }) = &opts.cmd {
+        datafile::load_classes()?;
+        for name in completions::names(kind, datafile::peek().as_ref()) {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
+
+    datafile::load_plugins()?;
+    datafile::load_classes()?;
+    if !config::Config::get().theme.is_colored() {
+        colored::control::set_override(false);
+    }
+    // the plain and json output is meant for scripts, so it's kept in English
+    if !opts.plain && !opts.json {
+        datafile::load_locale()?;
+    }
+    datafile::load_drops()?;
 
     // reset --hard is a special case, it needs to work when we
     // fail to deserialize the game data -- e.g. on backward
     // incompatible changes
-    if let Some(command::Command::Reset { hard: true }) = opts.cmd {
+    if let Some(command::Command::Reset { hard: true, .. }) = opts.cmd {
         datafile::remove();
     }
 
-    let mut game = datafile::load()?.unwrap_or_else(Game::new);
+    // migrations also need to work on files that can't be loaded yet,
+    // and the dry run shouldn't save the migrated game
+    if let Some(command::Command::Migrate { dry_run }) = opts.cmd {
+        let changes = datafile::migrate(dry_run)?;
+        log::migration(&changes, dry_run);
+        return Ok(());
+    }
+
+    let seed = opts.seed.or(config::Config::get().seed);
+    let mut game = datafile::load()?.unwrap_or_else(|| new_game(seed));
+    if let Some(command::Command::Daemon) = opts.cmd {
+        return daemon::serve(&opts, &mut game);
+    }
+    init_random(&mut game, seed)?;
+
+    // ironman games are saved before showing what happened, so quitting
+    // once the outcome is known doesn't undo it
+    let ironman = game.ironman;
+    log::capture(ironman);
 
     let result = command::run(opts.cmd, &mut game);
 
@@ -63,6 +133,38 @@ fn run_game() -> Result<()> {
     // E.g. if the player dies it's an error / exit code 1
     // and that needs to be reflected in the game state.
     datafile::save(&game).unwrap();
+    datafile::save_battles(log::recorded_battles()).unwrap();
 
+    if ironman {
+        for line in log::captured() {
+            println!("{}", line);
+        }
+        log::capture(false);
+    }
     result
 }
+
+fn new_game(seed: Option<u64>) -> Game {
+    // seed before creating the hero, so its stats are reproducible too
+    randomizer::init(seed.map(|seed| (seed, 0)));
+    let mut game = Game::new();
+    if datafile::is_speedrun() {
+        game.speedrun = Some(Speedrun::new(activity::now()));
+    }
+    game
+}
+
+/// Initialize the random number generator for the next command, with the
+/// given seed if any.
+fn init_random(game: &mut Game, seed: Option<u64>) -> Result<()> {
+    if let Some(seed) = seed {
+        // changing the seed restarts the random sequence, which would allow
+        // replaying the same outcomes in ironman games
+        if game.ironman && game.seed != Some(seed) {
+            anyhow::bail!("The seed of ironman games can't be changed.");
+        }
+        game.set_seed(seed);
+    }
+    randomizer::init(game.command_seed());
+    Ok(())
+}
@@ -0,0 +1,362 @@
+use crate::character::class::{Category, Class};
+use crate::character::Character;
+use crate::game::Game;
+use crate::item::chest::Chest;
+use crate::item::drops;
+use crate::item::key::Key;
+use crate::item::Item;
+use crate::log;
+use anyhow::{bail, Result};
+use once_cell::sync::OnceCell;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+/// Upper bound of the operations a plugin can run per call, so a runaway
+/// script can't hang the game.
+const MAX_OPERATIONS: u64 = 100_000;
+
+static PLUGINS: OnceCell<Plugins> = OnceCell::new();
+
+/// Rhai scripts loaded from the plugins directory at startup.
+///
+/// When loaded, a script can register custom content:
+///
+/// * `register_enemy(#{...})` adds an enemy class, with the same fields as
+///   the classes file.
+/// * `register_item(name, description)` adds an item that can be found in
+///   chests and used like the built-in ones.
+///
+/// And it can define handlers for game events, which receive an event map
+/// with a `hero` snapshot and return a map of effects, or nothing:
+///
+/// * `on_battle_start(event)`, with the `enemies` about to be fought.
+/// * `on_battle_won(event)`, with the beaten `enemies` and the `xp` and
+///   `gold` earned.
+/// * `on_level_up(event)`.
+/// * `on_chest(event)`, with the `distance` of the chest found and its
+///   `gold` and `items`. The effects are added to the chest instead of
+///   given to the hero.
+/// * `on_use(event)`, with the custom `item` used by the hero.
+///
+/// The effects are `gold` to give or take, `hp` and `mp` to restore or
+/// drain, the names of the `items` to give and a `message` to show.
+/// Handlers never kill the hero, nor take more gold than it has.
+pub struct Plugins {
+    engine: Engine,
+    scripts: Vec<(String, AST)>,
+    items: Vec<CustomItem>,
+    enemies: Vec<Class>,
+}
+
+/// An item registered by a plugin.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomItem {
+    pub name: String,
+    pub description: String,
+}
+
+/// Changes to the game requested by a plugin event handler.
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Effects {
+    pub gold: i32,
+    pub hp: i32,
+    pub mp: i32,
+    pub items: Vec<String>,
+    pub message: Option<String>,
+}
+
+impl Plugins {
+    /// Compile and run the given (name, source) scripts, collecting the
+    /// content they register.
+    pub fn new(sources: &[(String, String)]) -> Result<Self> {
+        let items = Arc::new(Mutex::new(Vec::new()));
+        let enemies = Arc::new(Mutex::new(Vec::new()));
+
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let registered = items.clone();
+        engine.register_fn(
+            "register_item",
+            move |name: &str, description: &str| -> Result<(), Box<EvalAltResult>> {
+                let name = name.to_lowercase();
+                if Key::from(&name).is_ok() {
+                    return Err(format!("item {} already exists", name).into());
+                }
+                registered.lock().unwrap().push(CustomItem {
+                    name,
+                    description: description.to_string(),
+                });
+                Ok(())
+            },
+        );
+        let registered = enemies.clone();
+        engine.register_fn(
+            "register_enemy",
+            move |class: Map| -> Result<(), Box<EvalAltResult>> {
+                let class: Class = rhai::serde::from_dynamic(&class.into())?;
+                if matches!(class.category, Category::Player | Category::Boss) {
+                    return Err(
+                        format!("class {}: only enemies can be registered", class.name).into(),
+                    );
+                }
+                registered.lock().unwrap().push(class);
+                Ok(())
+            },
+        );
+
+        let mut scripts = Vec::new();
+        for (name, source) in sources {
+            let run = engine
+                .compile(source)
+                .map_err(|e| e.to_string())
+                .and_then(|ast| engine.run_ast(&ast).map(|_| ast).map_err(|e| e.to_string()));
+            match run {
+                Ok(ast) => scripts.push((name.clone(), ast)),
+                Err(error) => bail!("Invalid plugin {}: {}", name, error),
+            }
+        }
+
+        let items = items.lock().unwrap().clone();
+        let enemies = enemies.lock().unwrap().clone();
+        Ok(Self {
+            engine,
+            scripts,
+            items,
+            enemies,
+        })
+    }
+
+    /// Run the given handler of every plugin defining it, returning the
+    /// effects requested by each one, by plugin name. Failing handlers are
+    /// reported but don't interrupt the game.
+    fn call(&self, handler: &str, event: &Value) -> Vec<(&str, Effects)> {
+        let mut results = Vec::new();
+        for (name, ast) in &self.scripts {
+            let defined = ast
+                .iter_functions()
+                .any(|function| function.name == handler && function.params.len() == 1);
+            if !defined {
+                continue;
+            }
+            match self.run(ast, handler, event) {
+                Ok(effects) => results.push((name.as_str(), effects)),
+                Err(error) => log::plugin_failed(name, handler, &error.to_string()),
+            }
+        }
+        results
+    }
+
+    fn run(&self, ast: &AST, handler: &str, event: &Value) -> Result<Effects, Box<EvalAltResult>> {
+        let event = rhai::serde::to_dynamic(event)?;
+        let options = CallFnOptions::new().eval_ast(false);
+        let result: Dynamic =
+            self.engine
+                .call_fn_with_options(options, &mut Scope::new(), ast, handler, (event,))?;
+        if result.is_unit() {
+            return Ok(Effects::default());
+        }
+        rhai::serde::from_dynamic(&result)
+    }
+}
+
+/// Load the plugins, before the classes are so the enemies they register
+/// are included.
+pub fn load(sources: &[(String, String)]) -> Result<()> {
+    if !sources.is_empty() {
+        let _ = PLUGINS.set(Plugins::new(sources)?);
+    }
+    Ok(())
+}
+
+/// The enemy classes registered by the plugins.
+pub fn enemies() -> Vec<Class> {
+    PLUGINS
+        .get()
+        .map_or(Vec::new(), |plugins| plugins.enemies.clone())
+}
+
+/// The custom item of the given name, if a plugin registered it.
+pub fn item(name: &str) -> Option<&'static CustomItem> {
+    PLUGINS.get()?.items.iter().find(|item| item.name == name)
+}
+
+fn call(handler: &str, event: Value) -> Vec<(&'static str, Effects)> {
+    match PLUGINS.get() {
+        Some(plugins) => plugins.call(handler, &event),
+        None => Vec::new(),
+    }
+}
+
+/// The event map passed to handlers, with the given fields and a snapshot
+/// of the hero.
+fn event(game: &Game, mut fields: Value) -> Value {
+    let player = &game.player;
+    fields["hero"] = json!({
+        "class": player.name(),
+        "level": player.level,
+        "hp": player.current_hp,
+        "max_hp": player.max_hp(),
+        "mp": player.current_mp,
+        "max_mp": player.max_mp(),
+        "gold": game.gold,
+        "location": game.location.to_string(),
+        "distance": game.location.distance_from_home().len(),
+        "hour": game.clock.hour(),
+        "weather": game.clock.weather,
+    });
+    fields
+}
+
+fn json_enemies(enemies: &[Character]) -> Value {
+    let enemies: Vec<Value> = enemies
+        .iter()
+        .map(|enemy| json!({"name": enemy.name(), "level": enemy.level}))
+        .collect();
+    json!(enemies)
+}
+
+/// Give the effects to the hero.
+fn apply(game: &mut Game, plugin: &str, effects: Effects) {
+    let gold = std::cmp::max(effects.gold, -game.gold);
+    game.gold += gold;
+    if gold > 0 {
+        game.stats.earn(gold);
+    }
+
+    let player = &mut game.player;
+    player.current_hp = (player.current_hp + effects.hp).clamp(1, player.max_hp());
+    player.current_mp = (player.current_mp + effects.mp).clamp(0, player.max_mp());
+
+    let mut chest = Chest::default();
+    for item in build_items(&effects.items, game.player.rounded_level()) {
+        chest.add_item(item);
+    }
+    let (items, _) = chest.pick_up(game);
+
+    log::plugin(plugin, effects.message.as_deref(), &items, gold);
+    game.leave_behind(chest);
+}
+
+/// The items of the given names, skipping unknown ones.
+pub fn build_items(names: &[String], level: i32) -> Vec<Box<dyn Item>> {
+    names
+        .iter()
+        .filter_map(|name| drops::item(name, level))
+        .collect()
+}
+
+// EVENT TRIGGERING FUNCTIONS
+
+pub fn battle_start(game: &mut Game, enemies: &[Character]) {
+    let event = event(game, json!({ "enemies": json_enemies(enemies) }));
+    for (plugin, effects) in call("on_battle_start", event) {
+        apply(game, plugin, effects);
+    }
+}
+
+pub fn battle_won(game: &mut Game, enemies: &[Character], xp: i32, gold: i32) {
+    let fields = json!({"enemies": json_enemies(enemies), "xp": xp, "gold": gold});
+    let event = event(game, fields);
+    for (plugin, effects) in call("on_battle_won", event) {
+        apply(game, plugin, effects);
+    }
+}
+
+pub fn level_up(game: &mut Game) {
+    let event = event(game, json!({}));
+    for (plugin, effects) in call("on_level_up", event) {
+        apply(game, plugin, effects);
+    }
+}
+
+/// Let the plugins add gold and items to a chest found at the given
+/// distance. Return whether they added anything.
+pub fn chest_found(game: &Game, distance: i32, chest: &mut Chest) -> bool {
+    let fields = json!({"distance": distance, "gold": chest.gold(), "items": chest.contents()});
+    let mut added = false;
+    for (_, effects) in call("on_chest", event(game, fields)) {
+        chest.add_gold(std::cmp::max(0, effects.gold));
+        for item in build_items(&effects.items, game.player.rounded_level()) {
+            chest.add_item(item);
+            added = true;
+        }
+        added = added || effects.gold > 0;
+    }
+    added
+}
+
+pub fn use_item(game: &mut Game, item: &str) {
+    let event = event(game, json!({ "item": item }));
+    for (plugin, effects) in call("on_use", event) {
+        apply(game, plugin, effects);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compile(source: &str) -> Result<Plugins> {
+        Plugins::new(&[(String::from("test"), source.to_string())])
+    }
+
+    #[test]
+    fn test_register() {
+        let source = r#"
+            register_item("apple", "restores a bit of hp");
+            register_enemy(#{
+                name: "goblin",
+                hp: [20, 5],
+                strength: [8, 2],
+                speed: [6, 1],
+                category: "common",
+            });
+        "#;
+        let plugins = compile(source).unwrap();
+        let apple = CustomItem {
+            name: String::from("apple"),
+            description: String::from("restores a bit of hp"),
+        };
+        assert_eq!(vec![apple], plugins.items);
+        assert_eq!("goblin", plugins.enemies[0].name);
+        assert!(plugins.enemies[0].mp.is_none());
+
+        assert!(compile(r#"register_item("potion", "already exists");"#).is_err());
+        assert!(compile(r#"register_enemy(#{ name: "broken" });"#).is_err());
+        assert!(compile("let x = ;").is_err());
+    }
+
+    #[test]
+    fn test_handlers() {
+        let source = r#"
+            fn on_level_up(event) {
+                #{ gold: event.hero.level * 10, items: ["potion"], message: "well done" }
+            }
+            fn on_chest(event) {}
+            fn on_battle_won(event) { #{ lives: 3 } }
+            fn on_use(event) { loop {} }
+        "#;
+        let plugins = compile(source).unwrap();
+        let event = json!({"hero": {"level": 3}});
+
+        let effects = Effects {
+            gold: 30,
+            items: vec![String::from("potion")],
+            message: Some(String::from("well done")),
+            ..Effects::default()
+        };
+        assert_eq!(vec![("test", effects)], plugins.call("on_level_up", &event));
+        assert_eq!(
+            vec![("test", Effects::default())],
+            plugins.call("on_chest", &event)
+        );
+
+        // undefined handlers, unknown effects and endless loops are skipped
+        assert!(plugins.call("on_battle_start", &event).is_empty());
+        assert!(plugins.call("on_battle_won", &event).is_empty());
+        assert!(plugins.call("on_use", &event).is_empty());
+    }
+}
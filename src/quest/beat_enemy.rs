@@ -87,3 +87,37 @@ impl Quest for BeatEnemyDistance {
         false
     }
 }
+
+pub fn count(class: &str, total: usize) -> Box<dyn Quest> {
+    Box::new(BeatEnemyCount {
+        class: class.to_string(),
+        total,
+        beaten: 0,
+    })
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BeatEnemyCount {
+    class: String,
+    total: usize,
+    beaten: usize,
+}
+
+#[typetag::serde]
+impl Quest for BeatEnemyCount {
+    fn description(&self) -> String {
+        format!(
+            "beat {} {}s {}/{}",
+            self.total, self.class, self.beaten, self.total
+        )
+    }
+
+    fn handle(&mut self, event: &Event) -> bool {
+        if let Event::BattleWon { enemy, .. } = event {
+            if enemy.name() == self.class {
+                self.beaten += 1;
+            }
+        }
+        self.beaten >= self.total
+    }
+}
@@ -0,0 +1,83 @@
+use super::beat_enemy;
+use super::{Event, Quest};
+use crate::location::Location;
+use serde::{Deserialize, Serialize};
+
+/// The chain of quests to beat a few orcs and then find the cursed chest
+/// they were guarding at the given location.
+pub fn cursed_chest(location: Location) -> Box<dyn Quest> {
+    Box::new(QuestChain {
+        description: String::from("the cursed chest"),
+        steps: vec![
+            beat_enemy::count("orc", 5),
+            Box::new(FindCursedChest { location }),
+        ],
+        current: 0,
+    })
+}
+
+/// A quest made of multiple steps that need to be completed in order.
+/// Events are only passed to the current step.
+#[derive(Serialize, Deserialize)]
+pub struct QuestChain {
+    description: String,
+    steps: Vec<Box<dyn Quest>>,
+    current: usize,
+}
+
+#[typetag::serde]
+impl Quest for QuestChain {
+    fn description(&self) -> String {
+        if let Some(step) = self.steps.get(self.current) {
+            format!(
+                "{} ({}/{}): {}",
+                self.description,
+                self.current + 1,
+                self.steps.len(),
+                step.description()
+            )
+        } else {
+            self.description.to_string()
+        }
+    }
+
+    fn handle(&mut self, event: &Event) -> bool {
+        if let Some(step) = self.steps.get_mut(self.current) {
+            if step.handle(event) {
+                self.current += 1;
+            }
+        }
+        self.current >= self.steps.len()
+    }
+
+    fn chest_at(&self, location: &Location) -> bool {
+        self.steps
+            .get(self.current)
+            .is_some_and(|step| step.chest_at(location))
+    }
+}
+
+/// Find a chest that only appears at a specific location while this
+/// quest is active. The chest is a mimic that needs to be beaten first.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FindCursedChest {
+    location: Location,
+}
+
+#[typetag::serde]
+impl Quest for FindCursedChest {
+    fn description(&self) -> String {
+        format!("find the cursed chest at {}", self.location)
+    }
+
+    fn handle(&mut self, event: &Event) -> bool {
+        if let Event::ChestFound { location } = event {
+            return *location == self.location;
+        }
+        false
+    }
+
+    fn chest_at(&self, location: &Location) -> bool {
+        *location == self.location
+    }
+}
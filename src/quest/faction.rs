@@ -0,0 +1,83 @@
+use super::{Event, Quest};
+use crate::faction::Faction;
+use serde::{Deserialize, Serialize};
+
+/// Items to buy at the shop to befriend the merchants.
+const TRADE_ITEMS: usize = 10;
+
+/// Enemies to bribe to befriend their faction.
+const BRIBES: usize = 3;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Trade {
+    remaining: usize,
+}
+
+impl Trade {
+    pub fn new() -> Self {
+        Self {
+            remaining: TRADE_ITEMS,
+        }
+    }
+}
+
+#[typetag::serde]
+impl Quest for Trade {
+    fn description(&self) -> String {
+        let bought = TRADE_ITEMS - self.remaining;
+        format!(
+            "buy {} items at the shop {}/{}",
+            TRADE_ITEMS, bought, TRADE_ITEMS
+        )
+    }
+
+    fn handle(&mut self, event: &Event) -> bool {
+        if let Event::ItemBought { .. } = event {
+            self.remaining -= 1;
+        }
+        self.remaining == 0
+    }
+
+    fn faction(&self) -> Option<Faction> {
+        Some(Faction::Merchants)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Bribe {
+    faction: Faction,
+    remaining: usize,
+}
+
+impl Bribe {
+    pub fn new(faction: Faction) -> Self {
+        Self {
+            faction,
+            remaining: BRIBES,
+        }
+    }
+}
+
+#[typetag::serde]
+impl Quest for Bribe {
+    fn description(&self) -> String {
+        let bribed = BRIBES - self.remaining;
+        format!(
+            "bribe {} {} instead of fighting {}/{}",
+            BRIBES, self.faction, bribed, BRIBES
+        )
+    }
+
+    fn handle(&mut self, event: &Event) -> bool {
+        if let Event::EnemyBribed { enemy } = event {
+            if Faction::of(&enemy.class) == Some(self.faction) {
+                self.remaining -= 1;
+            }
+        }
+        self.remaining == 0
+    }
+
+    fn faction(&self) -> Option<Faction> {
+        Some(self.faction)
+    }
+}
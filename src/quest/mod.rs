@@ -1,6 +1,8 @@
 use crate::character::class;
 use crate::character::Character;
+use crate::faction::{Faction, QUEST_REPUTATION};
 use crate::game;
+use crate::item::chest::Chest;
 use crate::item::key::Key;
 use crate::location::Location;
 use crate::log;
@@ -8,8 +10,12 @@ use core::fmt;
 use serde::{Deserialize, Serialize};
 
 mod beat_enemy;
+mod chain;
+mod faction;
 mod level;
 mod ring;
+mod tomb;
+mod traveler;
 mod tutorial;
 
 /// A task that is assigned to the player when certain conditions are met.
@@ -22,6 +28,22 @@ pub trait Quest {
     /// Update the quest progress based on the given event and
     /// return whether the quest was finished.
     fn handle(&mut self, event: &Event) -> bool;
+
+    /// Whether the quest expects a chest to be found at the given location,
+    /// in which case it will be placed there for the hero to pick up.
+    fn chest_at(&self, _location: &Location) -> bool {
+        false
+    }
+
+    /// Whether the quest expects the tomb at the given location to be visited.
+    fn tomb_at(&self, _location: &Location) -> bool {
+        false
+    }
+
+    /// The faction that gains reputation when the quest is finished, if any.
+    fn faction(&self) -> Option<Faction> {
+        None
+    }
 }
 
 impl fmt::Display for dyn Quest {
@@ -30,8 +52,9 @@ impl fmt::Display for dyn Quest {
     }
 }
 
-/// Keeps a TODO list of quests for the game.
-/// Each quest is unlocked at a certain level and has completion reward.
+/// Keeps a journal of quests for the game.
+/// Each quest is unlocked at a certain level and has completion reward,
+/// delivered as a chest with the reward gold.
 #[derive(Serialize, Deserialize, Default)]
 pub struct QuestList {
     quests: Vec<(Status, i32, Box<dyn Quest>)>,
@@ -49,6 +72,9 @@ enum Status {
     Completed,
 }
 
+/// Gold rewarded for recovering the tomb of a fallen hero.
+const RECOVERY_REWARD: i32 = 200;
+
 // EVENT TRIGGERING FUNCTIONS
 
 pub fn battle_won(game: &mut game::Game, enemy: &Character, levels_up: i32) {
@@ -88,12 +114,49 @@ pub fn item_added(game: &mut game::Game, item: Key) {
     handle(game, Event::ItemAdded { item });
 }
 
+pub fn enemy_bribed(game: &mut game::Game, enemy: &Character) {
+    handle(game, Event::EnemyBribed { enemy });
+}
+
 pub fn chest(game: &mut game::Game) {
-    handle(game, Event::ChestFound);
+    handle(
+        game,
+        Event::ChestFound {
+            location: game.location.clone(),
+        },
+    );
 }
 
 pub fn tombstone(game: &mut game::Game) {
-    handle(game, Event::TombtsoneFound);
+    handle(
+        game,
+        Event::TombtsoneFound {
+            location: game.location.clone(),
+        },
+    );
+}
+
+/// Add a quest to recover the tomb the hero left at the current location,
+/// unless there's one already.
+pub fn tomb_left(game: &mut game::Game) {
+    let location = game.location.clone();
+    let quests = &mut game.quests;
+    if !quests.tomb_at(&location) {
+        quests
+            .quests
+            .push((Status::Unlocked, RECOVERY_REWARD, tomb::recover(location)));
+    }
+}
+
+/// Add a quest to guide a lost traveler home, for the given reward.
+pub fn traveler_met(game: &mut game::Game, reward: i32) {
+    game.quests
+        .quests
+        .push((Status::Unlocked, reward, Box::new(traveler::GuideHome)));
+}
+
+pub fn home_reached(game: &mut game::Game) {
+    handle(game, Event::HomeReached);
 }
 
 pub fn game_reset(game: &mut game::Game) {
@@ -103,7 +166,18 @@ pub fn game_reset(game: &mut game::Game) {
 fn handle(game: &mut game::Game, event: Event) {
     // it would be preferable to have quests decoupled from the game struct
     // but that makes event handling much more complicated
-    game.gold += game.quests.handle(&event);
+    for (reward, faction) in game.quests.handle(&event) {
+        let mut chest = Chest::reward(game, reward);
+        let (items, gold) = chest.pick_up(game);
+        log::quest_done(&items, gold);
+        game.leave_behind(chest);
+
+        if let Some(faction) = faction {
+            if game.reputation.change(faction, QUEST_REPUTATION) {
+                log::standing(faction, game.reputation.standing(faction));
+            }
+        }
+    }
 }
 
 pub enum Event<'a> {
@@ -125,8 +199,16 @@ pub enum Event<'a> {
     ItemAdded {
         item: Key,
     },
-    ChestFound,
-    TombtsoneFound,
+    EnemyBribed {
+        enemy: &'a Character,
+    },
+    ChestFound {
+        location: Location,
+    },
+    TombtsoneFound {
+        location: Location,
+    },
+    HomeReached,
     GameReset,
 }
 
@@ -158,6 +240,8 @@ impl QuestList {
             1000,
             beat_enemy::of_class(class::Category::Common, "beat all common creatures"),
         ));
+        self.quests
+            .push((Status::Locked(2), 500, Box::new(faction::Trade::new())));
 
         self.quests
             .push((Status::Locked(5), 200, Box::new(tutorial::VisitTomb)));
@@ -175,6 +259,15 @@ impl QuestList {
         ));
         self.quests
             .push((Status::Locked(5), 1000, beat_enemy::at_distance(10)));
+        self.quests.push((
+            Status::Locked(5),
+            1000,
+            Box::new(faction::Bribe::new(Faction::Goblins)),
+        ));
+        if let Ok(location) = Location::from(&std::env::temp_dir().to_string_lossy()) {
+            self.quests
+                .push((Status::Locked(5), 2000, chain::cursed_chest(location)));
+        }
 
         self.quests.push((
             Status::Locked(10),
@@ -187,6 +280,11 @@ impl QuestList {
             10000,
             Box::new(level::ReachLevel::new(50)),
         ));
+        self.quests.push((
+            Status::Locked(10),
+            2000,
+            Box::new(faction::Bribe::new(Faction::Undead)),
+        ));
 
         for name in class::Class::names(class::Category::Player) {
             self.quests.push((
@@ -216,11 +314,12 @@ impl QuestList {
     }
 
     /// Pass the event to each of the quests, moving the completed ones to DONE.
-    /// The total gold reward is returned.
-    fn handle(&mut self, event: &Event) -> i32 {
+    /// The gold rewards of the completed quests are returned, along with
+    /// the faction each one befriends.
+    fn handle(&mut self, event: &Event) -> Vec<(i32, Option<Faction>)> {
         self.unlock_quests(event);
 
-        let mut total_reward = 0;
+        let mut rewards = Vec::new();
 
         for (status, reward, quest) in &mut self.quests {
             if let Status::Completed = status {
@@ -229,13 +328,26 @@ impl QuestList {
 
             let is_done = quest.handle(event);
             if is_done {
-                total_reward += *reward;
-                log::quest_done(*reward);
+                rewards.push((*reward, quest.faction()));
                 *status = Status::Completed
             }
         }
 
-        total_reward
+        rewards
+    }
+
+    /// Whether an unlocked quest expects a chest at the given location.
+    pub fn chest_at(&self, location: &Location) -> bool {
+        self.quests.iter().any(|(status, _, quest)| {
+            matches!(status, Status::Unlocked) && quest.chest_at(location)
+        })
+    }
+
+    /// Whether an unlocked quest expects the tomb at the given location to be visited.
+    fn tomb_at(&self, location: &Location) -> bool {
+        self.quests
+            .iter()
+            .any(|(status, _, quest)| matches!(status, Status::Unlocked) && quest.tomb_at(location))
     }
 
     /// If the event is a level up, unlock quests for that level.
@@ -300,7 +412,7 @@ mod tests {
         });
         assert_eq!(1, count_status(&quests, Status::Unlocked));
         assert_eq!(1, count_status(&quests, Status::Completed));
-        assert_eq!(10, reward);
+        assert_eq!(vec![(10, None)], reward);
 
         let reward = quests.handle(&Event::LevelUp {
             count: 2,
@@ -309,7 +421,7 @@ mod tests {
         });
         assert_eq!(1, count_status(&quests, Status::Unlocked));
         assert_eq!(3, count_status(&quests, Status::Completed));
-        assert_eq!(50, reward);
+        assert_eq!(vec![(20, None), (30, None)], reward);
     }
 
     #[test]
@@ -387,7 +499,7 @@ mod tests {
         let mut game = game::Game::new();
         game.quests.quests = vec![(Status::Unlocked, 1, Box::new(ring::FindAllRings::new()))];
 
-        for ring in item::ring::Ring::set() {
+        for ring in item::ring::Ring::found_set() {
             game.add_item(Box::new(ring));
         }
 
@@ -409,7 +521,16 @@ mod tests {
         // ruling ring required to spawn the enemy
         game.player.left_ring = Some(item::ring::Ring::Ruling);
 
-        let mut enemy = enemy::spawn(&game.location, &game.player).unwrap();
+        let mut enemy = enemy::spawn(
+            &game.location,
+            &game.player,
+            0,
+            game.difficulty,
+            &game.clock,
+            &game.reputation,
+        )
+        .pop()
+        .unwrap();
 
         // increase many levels to force the player's victory
         for _ in 0..200 {
@@ -417,9 +538,86 @@ mod tests {
         }
         enemy.current_hp = 10;
 
-        game.battle(&mut enemy, false, false).unwrap();
+        game.battle(&mut enemy, false, false, None).unwrap();
+
+        assert_eq!(Status::Completed, game.quests.quests[0].0);
+    }
+
+    #[test]
+    fn cursed_chest_chain() {
+        let mut game = game::Game::new();
+        let tmp = location_from("/tmp");
+        game.quests.quests = vec![(Status::Unlocked, 1000, chain::cursed_chest(tmp.clone()))];
+
+        // the chest isn't there until the orcs are beaten
+        assert!(!game.quests.chest_at(&tmp));
+        let mut orc_class = class::Class::player_first().clone();
+        orc_class.name = String::from("orc");
+        let orc = Character::new(orc_class, 1);
+        let rat = Character::player();
+        battle_won(&mut game, &rat, 0);
+        for _ in 0..5 {
+            battle_won(&mut game, &orc, 0);
+        }
+        assert!(game.quests.chest_at(&tmp));
+        assert!(!game.quests.chest_at(&location_from("~")));
+        assert_eq!(Status::Unlocked, game.quests.quests[0].0);
+
+        // the reward is picked up as a chest
+        game.location = tmp.clone();
+        chest(&mut game);
+        assert_eq!(Status::Completed, game.quests.quests[0].0);
+        assert!(!game.quests.chest_at(&tmp));
+        assert_eq!(1000, game.gold);
+        assert_eq!(1, game.inventory().values().sum::<usize>());
+    }
+
+    #[test]
+    fn recover_tomb() {
+        let mut game = game::Game::new();
+        game.quests = QuestList::default();
+        let tmp = location_from("/tmp");
+        game.location = tmp.clone();
+
+        // dying twice at the same place adds a single quest
+        tomb_left(&mut game);
+        tomb_left(&mut game);
+        assert_eq!(1, game.quests.quests.len());
+        assert!(game.quests.tomb_at(&tmp));
+
+        game.location = location_from("/other");
+        tombstone(&mut game);
+        assert_eq!(Status::Unlocked, game.quests.quests[0].0);
+
+        game.location = tmp.clone();
+        tombstone(&mut game);
+        assert_eq!(Status::Completed, game.quests.quests[0].0);
+        assert_eq!(RECOVERY_REWARD, game.gold);
+        assert!(!game.quests.tomb_at(&tmp));
+    }
+
+    #[test]
+    fn faction_quests() {
+        let mut game = game::Game::new();
+        game.quests.quests = vec![(
+            Status::Unlocked,
+            100,
+            Box::new(faction::Bribe::new(Faction::Goblins)),
+        )];
+
+        let orc = Character::new(class::Class::enemy_by_name("orc").unwrap().clone(), 1);
+        let rat = Character::new(class::Class::enemy_by_name("rat").unwrap().clone(), 1);
+        enemy_bribed(&mut game, &rat);
+        for _ in 0..2 {
+            enemy_bribed(&mut game, &orc);
+        }
+        assert_eq!(Status::Unlocked, game.quests.quests[0].0);
+        assert_eq!(0, game.reputation.get(Faction::Goblins));
 
+        enemy_bribed(&mut game, &orc);
         assert_eq!(Status::Completed, game.quests.quests[0].0);
+        assert_eq!(QUEST_REPUTATION, game.reputation.get(Faction::Goblins));
+        assert!(game.reputation.spares(&orc.class));
     }
 
     fn count_status(quests: &QuestList, status: Status) -> usize {
@@ -30,7 +30,7 @@ pub struct FindAllRings {
 impl FindAllRings {
     pub fn new() -> Self {
         Self {
-            to_find: Ring::set(),
+            to_find: Ring::found_set(),
         }
     }
 }
@@ -38,8 +38,10 @@ impl FindAllRings {
 #[typetag::serde]
 impl Quest for FindAllRings {
     fn description(&self) -> String {
-        let total = Ring::set().len();
-        let already_found = total - self.to_find.len();
+        // quests saved before bought rings were left out may still have them
+        let total = Ring::found_set().len();
+        let to_find = self.to_find.iter().filter(|ring| !ring.is_bought());
+        let already_found = total - to_find.count();
         format!("find all rings {}/{}", already_found, total)
     }
 
@@ -50,6 +52,7 @@ impl Quest for FindAllRings {
         {
             self.to_find.remove(ring);
         }
+        self.to_find.retain(|ring| !ring.is_bought());
         self.to_find.is_empty()
     }
 }
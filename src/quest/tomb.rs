@@ -0,0 +1,32 @@
+use super::{Event, Quest};
+use crate::location::Location;
+use serde::{Deserialize, Serialize};
+
+/// Go back to the location where an uninsured hero died, to pick up the
+/// gold and items left in their tomb.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecoverTomb {
+    location: Location,
+}
+
+pub fn recover(location: Location) -> Box<dyn Quest> {
+    Box::new(RecoverTomb { location })
+}
+
+#[typetag::serde]
+impl Quest for RecoverTomb {
+    fn description(&self) -> String {
+        format!("recover the tomb at {}", self.location)
+    }
+
+    fn handle(&mut self, event: &Event) -> bool {
+        if let Event::TombtsoneFound { location } = event {
+            return *location == self.location;
+        }
+        false
+    }
+
+    fn tomb_at(&self, location: &Location) -> bool {
+        self.location == *location
+    }
+}
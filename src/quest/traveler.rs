@@ -0,0 +1,17 @@
+use super::{Event, Quest};
+use serde::{Deserialize, Serialize};
+
+/// Take a lost traveler met away from home back there.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GuideHome;
+
+#[typetag::serde]
+impl Quest for GuideHome {
+    fn description(&self) -> String {
+        "guide the lost traveler home".to_string()
+    }
+
+    fn handle(&mut self, event: &Event) -> bool {
+        matches!(event, Event::HomeReached)
+    }
+}
@@ -60,7 +60,7 @@ impl Quest for FindChest {
     }
 
     fn handle(&mut self, event: &Event) -> bool {
-        matches!(event, Event::ChestFound)
+        matches!(event, Event::ChestFound { .. })
     }
 }
 
@@ -74,6 +74,6 @@ impl Quest for VisitTomb {
     }
 
     fn handle(&mut self, event: &Event) -> bool {
-        matches!(event, Event::TombtsoneFound)
+        matches!(event, Event::TombtsoneFound { .. })
     }
 }
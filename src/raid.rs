@@ -0,0 +1,240 @@
+use crate::challenge::Period;
+use crate::character::class::Class;
+use crate::character::Character;
+use crate::game::Game;
+use crate::item::chest::Chest;
+use crate::location::Location;
+use crate::log;
+use crate::randomizer::{choose, random, with_seed};
+use crate::title;
+use serde::{Deserialize, Serialize};
+
+/// Levels below the hero's of raid bosses.
+const RAID_LEVELS: i32 = 10;
+
+/// Percentages of the hp and strength of raid bosses relative to regular
+/// ones: they take long to beat but hit softer, until they enrage.
+const RAID_HP: i32 = 300;
+const RAID_STRENGTH: i32 = 50;
+
+/// How many directories below home the lair of a raid boss can be.
+const MIN_DEPTH: i32 = 2;
+const MAX_DEPTH: i32 = 6;
+
+/// Turns before the raid boss flies into a rage, one per real-time week
+/// in turn.
+const ENRAGE_TURNS: [i32; 3] = [20, 15, 12];
+
+/// Reward chests dropped by raid bosses, on top of the boss loot.
+const RAID_CHESTS: i32 = 2;
+
+/// How much farther from home than the lair the reward chests are
+/// generated as if found.
+const CHEST_BOOST: i32 = 25;
+
+/// The raid of a real-time week: which boss lairs where, and how long it
+/// takes to enrage. It's the same for every player on the same week.
+#[derive(Debug, Clone)]
+pub struct Raid {
+    pub week: u64,
+    pub boss: &'static Class,
+    pub lair: Location,
+    pub enrage_turns: i32,
+}
+
+impl Raid {
+    /// The raid of the given week, counted since the unix epoch. The bosses
+    /// take turns, and the lair is found by walking down the directories
+    /// below home, picked at random with the week as seed.
+    pub fn of_week(week: u64) -> Self {
+        Self::with_lair(week, lair(week))
+    }
+
+    fn with_lair(week: u64, lair: Location) -> Self {
+        let bosses = Class::bosses();
+        Self {
+            week,
+            boss: &bosses[week as usize % bosses.len()],
+            lair,
+            enrage_turns: ENRAGE_TURNS[week as usize % ENRAGE_TURNS.len()],
+        }
+    }
+
+    /// The cosmetic title earned by beating the raid boss.
+    pub fn title(&self) -> String {
+        let mut name = self.boss.name.chars();
+        let initial = name.next().map(|c| c.to_uppercase().to_string());
+        format!("{}{}bane", initial.unwrap_or_default(), name.as_str())
+    }
+
+    /// The boss as it appears to fight the given hero, with its enrage
+    /// timer set.
+    pub fn spawn_boss(&self, game: &Game) -> Character {
+        let level = std::cmp::max(1, game.player.level - RAID_LEVELS);
+        let mut boss = Character::new(self.boss.clone(), level)
+            .scaled(game.difficulty.enemy_stats())
+            .reshaped(RAID_HP, RAID_STRENGTH)
+            .with_prestige(game.player.prestige);
+        boss.enrage_timer = Some(self.enrage_turns);
+        boss
+    }
+}
+
+/// The raid bosses beaten, kept across heroes.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct Record {
+    /// The last week a raid boss was beaten.
+    beaten: Option<u64>,
+
+    /// The lair of the raid boss of the week it was found for, so the
+    /// directories aren't walked again on every step.
+    lair: Option<(u64, Location)>,
+}
+
+impl Record {
+    /// Whether the raid boss of the given week was already beaten.
+    pub fn is_beaten(&self, week: u64) -> bool {
+        self.beaten == Some(week)
+    }
+
+    /// The lair of the raid boss of the given week, found the first time
+    /// it's needed in the week.
+    fn lair(&mut self, week: u64) -> Location {
+        match &self.lair {
+            Some((found, lair)) if *found == week => lair.clone(),
+            _ => {
+                let lair = lair(week);
+                self.lair = Some((week, lair.clone()));
+                lair
+            }
+        }
+    }
+}
+
+/// The raid of the week of the given time.
+pub fn current(game: &mut Game, now: u64) -> Raid {
+    let week = Period::Weekly.at(now);
+    Raid::with_lair(week, game.raids.lair(week))
+}
+
+/// Spawn the raid boss of the week of the given time, if the hero is at its
+/// lair and didn't beat it yet this week.
+pub fn spawn(game: &mut Game, now: u64) -> Option<Character> {
+    let week = Period::Weekly.at(now);
+    let distance = game.location.distance_from_home().len();
+    if game.raids.is_beaten(week) || game.location.is_home() || distance > MAX_DEPTH {
+        return None;
+    }
+
+    let raid = current(game, now);
+    if game.location != raid.lair {
+        return None;
+    }
+    let boss = raid.spawn_boss(game);
+    log::enemy_appears(&boss, &game.location);
+    Some(boss)
+}
+
+/// Reward beating the raid boss of the week of the given time with
+/// high-tier chests, and its title the first time.
+pub fn beaten(game: &mut Game, now: u64) {
+    let raid = current(game, now);
+    game.raids.beaten = Some(raid.week);
+
+    let mut chest = Chest::default();
+    for _ in 0..RAID_CHESTS {
+        chest.extend(Chest::generate_boosted(game, CHEST_BOOST));
+    }
+    let (items, gold) = chest.pick_up(game);
+    game.stats.earn(gold);
+    log::raid_beaten(&items, gold);
+    game.leave_behind(chest);
+    title::earn(game, &raid.title());
+}
+
+/// Walk down from home a number of directories, stopping early if there
+/// are no more to go into. Ignored directories are skipped, since enemies
+/// don't appear there. The same seed leads to the same directory.
+fn lair(seed: u64) -> Location {
+    with_seed(seed, || {
+        let depth = MIN_DEPTH + random().pick((MAX_DEPTH - MIN_DEPTH + 1) as usize) as i32;
+        let mut location = Location::home();
+        for _ in 0..depth {
+            let dirs: Vec<Location> = location
+                .subdirectories()
+                .into_iter()
+                .filter(|dir| !dir.is_ignored())
+                .collect();
+            match choose(&dirs) {
+                Some(dir) => location = dir.clone(),
+                None => break,
+            }
+        }
+        location
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weekly_raids() {
+        let raid = Raid::of_week(100);
+        assert_eq!(raid.lair, Raid::of_week(100).lair);
+        assert_ne!(raid.boss.name, Raid::of_week(101).boss.name);
+        assert_eq!("cerberus", raid.boss.name);
+        assert_eq!("Cerberusbane", raid.title());
+        assert!(raid.lair.distance_from_home().len() <= MAX_DEPTH);
+        assert!(!raid.lair.is_ignored());
+
+        let mut game = Game::new();
+        let boss = raid.spawn_boss(&game);
+        assert!(boss.is_boss());
+        assert_eq!(1, boss.level);
+        let regular = Character::new(raid.boss.clone(), 1);
+        assert_eq!(regular.max_hp() * RAID_HP / 100, boss.max_hp());
+        assert_eq!(boss.max_hp(), boss.current_hp);
+        assert_eq!(Some(raid.enrage_turns), boss.enrage_timer);
+
+        // it only lairs away from home
+        assert!(spawn(&mut game, 100 * 7 * 24 * 60 * 60).is_none());
+    }
+
+    #[test]
+    fn lair_found_once_a_week() {
+        let mut game = Game::new();
+        let now = 100 * 7 * 24 * 60 * 60;
+        let raid = current(&mut game, now);
+        assert_eq!(Raid::of_week(100).lair, raid.lair);
+        assert_eq!(Some((100, raid.lair.clone())), game.raids.lair);
+
+        // the kept lair is used for the rest of the week
+        let kept = Location::from("/").unwrap();
+        game.raids.lair = Some((100, kept.clone()));
+        assert_eq!(kept, current(&mut game, now).lair);
+
+        // and found again the next one
+        assert_ne!(kept, current(&mut game, now + 7 * 24 * 60 * 60).lair);
+        assert_eq!(Some((101, Raid::of_week(101).lair)), game.raids.lair);
+    }
+
+    #[test]
+    fn raid_rewards() {
+        let mut game = Game::new();
+        let now = 100 * 7 * 24 * 60 * 60;
+        let week = Period::Weekly.at(now);
+        let title = current(&mut game, now).title();
+
+        beaten(&mut game, now);
+        assert!(game.raids.is_beaten(week));
+        assert!(!game.raids.is_beaten(week + 1));
+        assert_eq!(vec![title.clone()], title::list(&game));
+        assert!(game.gold > 0);
+
+        // the title is earned only once
+        beaten(&mut game, now);
+        assert_eq!(1, title::list(&game).len());
+    }
+}
@@ -1,9 +1,77 @@
 #![allow(dead_code)]
 
 use crate::character::StatusEffect;
+use crate::clock::Weather;
+use crate::item::drops::{Drops, Ratio};
 use crate::location;
-use rand::Rng;
+use once_cell::sync::OnceCell;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::cell::{Cell, RefCell};
 use std::cmp::max;
+use std::ops::Deref;
+use std::rc::Rc;
+use std::sync::{Mutex, MutexGuard};
+
+/// The random number generator shared by all the game elements that need randomness.
+/// It's seeded from entropy unless the game is seeded, to make it deterministic.
+static RNG: OnceCell<Mutex<StdRng>> = OnceCell::new();
+
+/// Set up the random number generator for the current command.
+/// When the game is seeded, a (seed, command number) pair is expected, so
+/// each command gets a different but reproducible sequence of random values.
+pub fn init(seed: Option<(u64, u64)>) {
+    *rng() = new_rng(seed);
+}
+
+fn new_rng(seed: Option<(u64, u64)>) -> StdRng {
+    match seed {
+        Some((seed, command)) => {
+            let mut bytes = [0; 32];
+            bytes[..8].copy_from_slice(&seed.to_le_bytes());
+            bytes[8..16].copy_from_slice(&command.to_le_bytes());
+            StdRng::from_seed(bytes)
+        }
+        None => StdRng::from_entropy(),
+    }
+}
+
+thread_local! {
+    /// The generator used instead of the shared one by the current thread
+    /// while running `with_seed`. It's leaked once per thread so handles to
+    /// it can be given out like the shared one.
+    static SEEDED: &'static Mutex<StdRng> = Box::leak(Box::new(Mutex::new(StdRng::seed_from_u64(0))));
+    static SEEDED_ACTIVE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Get a handle to the shared random number generator.
+/// The handle should be dropped before calling other functions that may need it.
+pub fn rng() -> MutexGuard<'static, StdRng> {
+    if SEEDED_ACTIVE.with(Cell::get) {
+        return SEEDED.with(|seeded| seeded.lock().unwrap());
+    }
+    RNG.get_or_init(|| Mutex::new(StdRng::from_entropy()))
+        .lock()
+        .unwrap()
+}
+
+/// Run the given function with the default randomizer, even in tests, fed
+/// by a generator of the current thread seeded with the given number. The
+/// same seed always gives the same rolls, so properties of the game can be
+/// checked over many seeds and any failing case reproduced.
+pub fn with_seed<T>(seed: u64, f: impl FnOnce() -> T) -> T {
+    let previous = SEEDED.with(|seeded| {
+        let mut rng = seeded.lock().unwrap();
+        std::mem::replace(&mut *rng, StdRng::seed_from_u64(seed))
+    });
+    let active = SEEDED_ACTIVE.with(|active| active.replace(true));
+    let result = with_randomizer(DefaultRandomizer, f);
+    SEEDED_ACTIVE.with(|cell| cell.set(active));
+    SEEDED.with(|seeded| *seeded.lock().unwrap() = previous);
+    result
+}
 
 /// This trait exposes functions to deal with any element of the game that
 /// needs to incorporate randomness.
@@ -44,25 +112,155 @@ pub trait Randomizer {
     fn equipment_chest(&self, distance: &location::Distance) -> bool;
     fn ring_chest(&self, distance: &location::Distance) -> bool;
     fn item_chest(&self, distance: &location::Distance) -> bool;
+    fn mimic_chest(&self) -> bool;
+    fn guarded_chest(&self) -> bool;
+    fn trapped_chest(&self) -> bool;
+
+    /// Whether a wandering npc is met while looking for chests.
+    fn npc_encounter(&self, distance: &location::Distance) -> bool;
+
+    /// Whether a found chest is lost, given the percentage of chests lost
+    /// at the game difficulty.
+    fn chest_lost(&self, percent: u32) -> bool;
+
+    /// Whether the hero notices a chest trap before springing it,
+    /// more likely the higher their trap skill.
+    fn trap_avoided(&self, skill: i32) -> bool;
+
+    /// Whether an attempt to disarm a chest trap succeeds,
+    /// more likely the higher the hero's trap skill.
+    fn disarm_succeeds(&self, skill: i32) -> bool;
+
+    /// Whether a piece of equipment or ring found in a chest is cursed.
+    fn cursed_item(&self) -> bool;
+    fn material_drop(&self) -> bool;
+
+    /// Whether a treasure map is dropped by a beaten enemy.
+    fn treasure_map_drop(&self) -> bool;
+
+    /// Whether a beaten enemy drops something with the given chance,
+    /// as set by the loot table of its class.
+    fn loot_dropped(&self, chance: Ratio) -> bool;
+
+    /// Whether enemies attack the hero resting away from home.
+    fn night_ambush(&self) -> bool;
+
+    /// Whether an enemy spawned at a lair is one of its dwellers.
+    fn lair_enemy(&self) -> bool;
+
+    /// Whether a spawned enemy is a rare gilded variant.
+    fn gilded_enemy(&self) -> bool;
+
+    fn tame_enemy(&self) -> bool;
+    fn companion_chest(&self) -> bool;
+    fn enemy_targets_companion(&self) -> bool;
+
+    /// Index of the target of an enemy attack, where 0 is the player and
+    /// the rest the given number of party members.
+    fn enemy_target(&self, party_size: usize) -> usize;
+
+    /// How many enemies appear together at the given distance.
+    fn enemy_group_size(&self, distance: &location::Distance) -> usize;
+
+    /// The sum of a roll of two six-sided dice.
+    fn dice_roll(&self) -> i32;
+
+    /// The weather for the next few hours of the world clock.
+    fn weather(&self) -> Weather;
+
+    /// Whether the weather flips the outcome of an encounter roll, with the
+    /// given percent chance.
+    fn weather_encounter(&self, percent: u32) -> bool;
+
+    /// Whether a special enemy, like the hero's shadow at home, shows up
+    /// instead of a regular one where it can.
+    fn special_enemy(&self) -> bool;
+
+    /// Index of an element picked from a list of the given length,
+    /// which is never empty.
+    fn pick(&self, len: usize) -> usize;
+
+    /// Index of an element picked from a list with the given weights, each
+    /// one as likely as its weight. At least one of them is positive.
+    fn pick_weighted(&self, weights: &[u32]) -> usize;
+}
+
+thread_local! {
+    /// The randomizer set by `with_randomizer` for the current thread.
+    static INJECTED: RefCell<Option<Rc<dyn Randomizer>>> = const { RefCell::new(None) };
+}
+
+/// Run the given function with its game elements driven by the given
+/// randomizer, instead of the default or test one. It only applies to the
+/// current thread, so tests running in parallel can each use their own,
+/// e.g. to check properties of the game over many different rolls.
+pub fn with_randomizer<T>(randomizer: impl Randomizer + 'static, f: impl FnOnce() -> T) -> T {
+    let randomizer: Rc<dyn Randomizer> = Rc::new(randomizer);
+    let previous = INJECTED.with(|injected| injected.replace(Some(randomizer)));
+    let result = f();
+    INJECTED.with(|injected| injected.replace(previous));
+    result
+}
+
+/// The randomizer in use, as returned by `random`.
+pub enum Current {
+    Builtin(&'static dyn Randomizer),
+    Injected(Rc<dyn Randomizer>),
+}
+
+impl Deref for Current {
+    type Target = dyn Randomizer;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Current::Builtin(randomizer) => *randomizer,
+            Current::Injected(randomizer) => randomizer.as_ref(),
+        }
+    }
 }
 
-#[cfg(not(test))]
 /// Get the randomizer instance. This function provides indirection
-/// so randomness can be turned off during tests to make them deterministic
-pub fn random() -> DefaultRandomizer {
-    DefaultRandomizer {}
+/// so randomness can be turned off during tests to make them deterministic,
+/// or replaced altogether with `with_randomizer`.
+pub fn random() -> Current {
+    if let Some(randomizer) = INJECTED.with(|injected| injected.borrow().clone()) {
+        Current::Injected(randomizer)
+    } else if cfg!(test) {
+        Current::Builtin(&TestRandomizer)
+    } else {
+        Current::Builtin(&DefaultRandomizer)
+    }
 }
 
-#[cfg(test)]
-pub fn random() -> TestRandomizer {
-    TestRandomizer {}
+/// Pick an element of the slice with the current randomizer.
+pub fn choose<T>(items: &[T]) -> Option<&T> {
+    if items.is_empty() {
+        return None;
+    }
+    items.get(random().pick(items.len()))
+}
+
+/// Pick an element of the slice with the current randomizer, each one as
+/// likely as the weight given to it.
+pub fn choose_weighted<T>(items: &[T], weight: impl Fn(&T) -> u32) -> Option<&T> {
+    let weights: Vec<u32> = items.iter().map(weight).collect();
+    if !weights.iter().any(|weight| *weight > 0) {
+        return None;
+    }
+    items.get(random().pick_weighted(&weights))
 }
 
 pub struct DefaultRandomizer;
 
+/// Randomly decide whether something with the given chance happens.
+fn chance(ratio: Ratio) -> bool {
+    let mut rng = rng();
+    rng.gen_ratio(ratio.0, ratio.1)
+}
+
 impl Randomizer for DefaultRandomizer {
     fn should_enemy_appear(&self, distance: &location::Distance) -> bool {
-        let mut rng = rand::thread_rng();
+        let mut rng = rng();
 
         match distance {
             location::Distance::Near(_) => rng.gen_ratio(1, 3),
@@ -72,7 +270,7 @@ impl Randomizer for DefaultRandomizer {
     }
 
     fn bribe_succeeds(&self) -> bool {
-        let mut rng = rand::thread_rng();
+        let mut rng = rng();
         rng.gen_ratio(1, 2)
     }
 
@@ -87,12 +285,12 @@ impl Randomizer for DefaultRandomizer {
 
         let speed_contrib = if player_speed > enemy_speed { 2 } else { 0 };
 
-        let mut rng = rand::thread_rng();
+        let mut rng = rng();
         rng.gen_ratio(1 + level_contrib + speed_contrib, 5)
     }
 
     fn enemy_level(&self, level: i32) -> i32 {
-        let mut rng = rand::thread_rng();
+        let mut rng = rng();
         max(1, level + rng.gen_range(-4..5))
     }
 
@@ -100,7 +298,7 @@ impl Randomizer for DefaultRandomizer {
     fn damage(&self, value: i32) -> i32 {
         let value = value as f64;
 
-        let mut rng = rand::thread_rng();
+        let mut rng = rng();
         let min_val = (value * 0.8).floor() as i32;
         let max_val = (value * 1.2).ceil() as i32;
         max(1, rng.gen_range(min_val..=max_val))
@@ -110,25 +308,25 @@ impl Randomizer for DefaultRandomizer {
         if receiver_speed > attacker_speed {
             let ratio = receiver_speed / attacker_speed;
             let ratio = max(1, 5 - ratio) as u32;
-            let mut rng = rand::thread_rng();
+            let mut rng = rng();
             return rng.gen_ratio(1, ratio);
         }
         false
     }
 
     fn is_critical(&self) -> bool {
-        let mut rng = rand::thread_rng();
+        let mut rng = rng();
         rng.gen_ratio(1, 20)
     }
 
     fn counter_attack(&self) -> bool {
-        let mut rng = rand::thread_rng();
+        let mut rng = rng();
         rng.gen_ratio(1, 2)
     }
 
     fn inflicted(&self, status: Option<(StatusEffect, u32)>) -> Option<StatusEffect> {
         if let Some((status, ratio)) = status {
-            let mut rng = rand::thread_rng();
+            let mut rng = rng();
             if rng.gen_ratio(1, ratio) {
                 return Some(status);
             }
@@ -137,7 +335,7 @@ impl Randomizer for DefaultRandomizer {
     }
 
     fn gold_gained(&self, base: i32) -> i32 {
-        let mut rng = rand::thread_rng();
+        let mut rng = rng();
         let min = (base as f64 * 0.6) as i32;
         let max = (base as f64 * 1.3) as i32;
         rng.gen_range(min..=max)
@@ -147,54 +345,164 @@ impl Randomizer for DefaultRandomizer {
         let min_value = max(1, increase / 2);
         let max_value = 3 * increase / 2;
 
-        let mut rng = rand::thread_rng();
+        let mut rng = rng();
         rng.gen_range(min_value..=max_value)
     }
 
     fn range(&self, max: i32) -> i32 {
-        let mut rng = rand::thread_rng();
+        let mut rng = rng();
         rng.gen_range(0..max)
     }
 
     fn gold_chest(&self, distance: &location::Distance) -> bool {
-        let mut rng = rand::thread_rng();
-
-        match distance {
-            location::Distance::Near(_) => rng.gen_ratio(6, 30),
-            location::Distance::Mid(_) => rng.gen_ratio(7, 30),
-            location::Distance::Far(_) => rng.gen_ratio(4, 30),
-        }
+        chance(Drops::get().chests.gold.at(distance))
     }
 
     fn equipment_chest(&self, distance: &location::Distance) -> bool {
-        let mut rng = rand::thread_rng();
-
-        match distance {
-            location::Distance::Near(_) => rng.gen_ratio(1, 30),
-            location::Distance::Mid(_) => rng.gen_ratio(3, 30),
-            location::Distance::Far(_) => rng.gen_ratio(5, 30),
-        }
+        chance(Drops::get().chests.equipment.at(distance))
     }
 
     fn ring_chest(&self, distance: &location::Distance) -> bool {
-        let mut rng = rand::thread_rng();
-
-        match distance {
-            location::Distance::Near(_) => false,
-            location::Distance::Mid(_) => rng.gen_ratio(3, 30),
-            location::Distance::Far(_) => rng.gen_ratio(5, 30),
-        }
+        chance(Drops::get().chests.ring.at(distance))
     }
 
     fn item_chest(&self, distance: &location::Distance) -> bool {
-        let mut rng = rand::thread_rng();
+        chance(Drops::get().chests.item.at(distance))
+    }
 
-        match distance {
-            location::Distance::Near(_) => rng.gen_ratio(1, 50),
-            location::Distance::Mid(_) => rng.gen_ratio(5, 50),
-            location::Distance::Far(_) => rng.gen_ratio(10, 50),
+    fn mimic_chest(&self) -> bool {
+        chance(Drops::get().chests.mimic)
+    }
+
+    fn guarded_chest(&self) -> bool {
+        chance(Drops::get().chests.guard)
+    }
+
+    fn trapped_chest(&self) -> bool {
+        chance(Drops::get().chests.trap)
+    }
+
+    fn npc_encounter(&self, distance: &location::Distance) -> bool {
+        chance(Drops::get().chests.npc.at(distance))
+    }
+
+    fn chest_lost(&self, percent: u32) -> bool {
+        let mut rng = rng();
+        rng.gen_ratio(percent, 100)
+    }
+
+    fn trap_avoided(&self, skill: i32) -> bool {
+        let mut rng = rng();
+        rng.gen_ratio(skill.clamp(0, 3) as u32, 4)
+    }
+
+    fn disarm_succeeds(&self, skill: i32) -> bool {
+        let mut rng = rng();
+        rng.gen_ratio(1 + skill.clamp(0, 2) as u32, 4)
+    }
+
+    fn cursed_item(&self) -> bool {
+        let mut rng = rng();
+        rng.gen_ratio(1, 25)
+    }
+
+    fn material_drop(&self) -> bool {
+        let mut rng = rng();
+        rng.gen_ratio(1, 3)
+    }
+
+    fn treasure_map_drop(&self) -> bool {
+        let mut rng = rng();
+        rng.gen_ratio(1, 40)
+    }
+
+    fn loot_dropped(&self, ratio: Ratio) -> bool {
+        chance(ratio)
+    }
+
+    fn night_ambush(&self) -> bool {
+        let mut rng = rng();
+        rng.gen_ratio(1, 4)
+    }
+
+    fn lair_enemy(&self) -> bool {
+        let mut rng = rng();
+        rng.gen_ratio(1, 2)
+    }
+
+    fn gilded_enemy(&self) -> bool {
+        let mut rng = rng();
+        rng.gen_ratio(1, 200)
+    }
+
+    fn tame_enemy(&self) -> bool {
+        let mut rng = rng();
+        rng.gen_ratio(1, 20)
+    }
+
+    fn companion_chest(&self) -> bool {
+        let mut rng = rng();
+        rng.gen_ratio(1, 30)
+    }
+
+    fn enemy_targets_companion(&self) -> bool {
+        let mut rng = rng();
+        rng.gen_ratio(1, 3)
+    }
+
+    fn enemy_target(&self, party_size: usize) -> usize {
+        let mut rng = rng();
+        rng.gen_range(0..=party_size)
+    }
+
+    fn enemy_group_size(&self, distance: &location::Distance) -> usize {
+        // groups only roam far away from home
+        if let location::Distance::Far(_) = distance {
+            let mut rng = rng();
+            rng.gen_range(1..=3)
+        } else {
+            1
         }
     }
+
+    fn dice_roll(&self) -> i32 {
+        let mut rng = rng();
+        rng.gen_range(1..=6) + rng.gen_range(1..=6)
+    }
+
+    fn weather(&self) -> Weather {
+        let weights = [
+            (Weather::Clear, 10),
+            (Weather::Rain, 4),
+            (Weather::Fog, 3),
+            (Weather::Storm, 3),
+        ];
+        let mut rng = rng();
+        weights
+            .choose_weighted(&mut *rng, |(_, weight)| *weight)
+            .unwrap()
+            .0
+    }
+
+    fn weather_encounter(&self, percent: u32) -> bool {
+        let mut rng = rng();
+        rng.gen_ratio(percent, 100)
+    }
+
+    fn special_enemy(&self) -> bool {
+        let mut rng = rng();
+        rng.gen_ratio(1, 10)
+    }
+
+    fn pick(&self, len: usize) -> usize {
+        let mut rng = rng();
+        rng.gen_range(0..len)
+    }
+
+    fn pick_weighted(&self, weights: &[u32]) -> usize {
+        let mut rng = rng();
+        WeightedIndex::new(weights).unwrap().sample(&mut *rng)
+    }
 }
 
 /// The test randomizer just exposes the same functions as the default one
@@ -271,12 +579,160 @@ impl Randomizer for TestRandomizer {
     fn ring_chest(&self, _distance: &location::Distance) -> bool {
         false
     }
+
+    fn mimic_chest(&self) -> bool {
+        false
+    }
+
+    fn guarded_chest(&self) -> bool {
+        false
+    }
+
+    fn trapped_chest(&self) -> bool {
+        false
+    }
+
+    fn npc_encounter(&self, _distance: &location::Distance) -> bool {
+        false
+    }
+
+    fn chest_lost(&self, _percent: u32) -> bool {
+        false
+    }
+
+    fn trap_avoided(&self, _skill: i32) -> bool {
+        false
+    }
+
+    fn disarm_succeeds(&self, skill: i32) -> bool {
+        skill > 0
+    }
+
+    fn cursed_item(&self) -> bool {
+        false
+    }
+
+    fn material_drop(&self) -> bool {
+        true
+    }
+
+    fn treasure_map_drop(&self) -> bool {
+        false
+    }
+
+    fn loot_dropped(&self, _chance: Ratio) -> bool {
+        false
+    }
+
+    fn night_ambush(&self) -> bool {
+        false
+    }
+
+    fn lair_enemy(&self) -> bool {
+        false
+    }
+
+    fn gilded_enemy(&self) -> bool {
+        false
+    }
+
+    fn tame_enemy(&self) -> bool {
+        false
+    }
+
+    fn companion_chest(&self) -> bool {
+        false
+    }
+
+    fn enemy_targets_companion(&self) -> bool {
+        false
+    }
+
+    fn enemy_target(&self, _party_size: usize) -> usize {
+        0
+    }
+
+    fn enemy_group_size(&self, _distance: &location::Distance) -> usize {
+        1
+    }
+
+    fn dice_roll(&self) -> i32 {
+        7
+    }
+
+    fn weather(&self) -> Weather {
+        Weather::Clear
+    }
+
+    fn weather_encounter(&self, _percent: u32) -> bool {
+        false
+    }
+
+    fn special_enemy(&self) -> bool {
+        false
+    }
+
+    fn pick(&self, _len: usize) -> usize {
+        0
+    }
+
+    /// The most likely element, the first one if tied.
+    fn pick_weighted(&self, weights: &[u32]) -> usize {
+        let heaviest = weights.iter().max().unwrap_or(&0);
+        weights
+            .iter()
+            .position(|weight| weight == heaviest)
+            .unwrap_or(0)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_seeded_rng() {
+        let rolls = |seed| {
+            let mut rng = new_rng(seed);
+            (0..10)
+                .map(|_| rng.gen_range(0..1000))
+                .collect::<Vec<i32>>()
+        };
+
+        assert_eq!(rolls(Some((42, 0))), rolls(Some((42, 0))));
+        assert_ne!(rolls(Some((42, 0))), rolls(Some((42, 1))));
+        assert_ne!(rolls(Some((42, 0))), rolls(Some((43, 0))));
+    }
+
+    #[test]
+    fn test_seeded() {
+        let rolls = |seed| with_seed(seed, || (0..20).map(|_| random().dice_roll()).collect());
+        let first: Vec<i32> = rolls(1);
+        assert_eq!(first, rolls(1));
+        assert_ne!(first, rolls(2));
+        assert!(first.iter().any(|roll| *roll != 7));
+
+        // the test randomizer is back afterwards
+        assert_eq!(7, random().dice_roll());
+    }
+
+    #[test]
+    fn test_choose() {
+        // the test randomizer picks the first element, or the most likely one
+        assert_eq!(Some(&1), choose(&[1, 2, 3]));
+        assert_eq!(None, choose::<i32>(&[]));
+        let weighted = [(1, 1), (2, 5), (3, 5)];
+        assert_eq!(Some(&(2, 5)), choose_weighted(&weighted, |c| c.1));
+        assert_eq!(None, choose_weighted(&[(1, 0)], |c| c.1));
+
+        with_seed(3, || {
+            for _ in 0..100 {
+                let choice = choose_weighted(&[(1, 0), (2, 1)], |c| c.1);
+                assert_eq!(Some(&(2, 1)), choice);
+            }
+        });
+    }
+
     #[test]
     fn test_increase_stat() {
         let rand = DefaultRandomizer {};
@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+
+/// Seconds away from the game that earn one rested battle.
+const REST_PERIOD: u64 = 3600;
+
+/// Maximum amount of rested battles that can be accumulated.
+pub const MAX_RESTED_BATTLES: i32 = 10;
+
+/// Extra xp percentage earned in each rested battle.
+pub const RESTED_XP: i32 = 50;
+
+/// Accounting of the real-world time the hero spends resting at home between
+/// play sessions, which is rewarded with extra xp in the next few battles.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Rested {
+    /// Timestamp of the last command run, 0 if none was yet.
+    last_played: u64,
+
+    /// Whether the hero was at home when the last command was run.
+    at_home: bool,
+
+    /// Battles left with extra xp.
+    pub battles: i32,
+}
+
+impl Rested {
+    /// Track a command run at the given time, with the hero at home or not.
+    /// If the hero was left at home since the previous one, a battle is
+    /// rested for each period of time passed. Return the rested battles
+    /// earned.
+    pub fn track(&mut self, now: u64, at_home: bool) -> i32 {
+        let mut earned = 0;
+        if self.at_home && self.last_played > 0 && now > self.last_played {
+            let periods = ((now - self.last_played) / REST_PERIOD) as i32;
+            earned = periods.min(MAX_RESTED_BATTLES - self.battles).max(0);
+            self.battles += earned;
+        }
+        self.last_played = now;
+        self.at_home = at_home;
+        earned
+    }
+
+    /// Use up a rested battle, if any is left, returning its extra xp
+    /// percentage.
+    pub fn bonus(&mut self) -> Option<i32> {
+        if self.battles <= 0 {
+            return None;
+        }
+        self.battles -= 1;
+        Some(RESTED_XP)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rest_at_home() {
+        let mut rested = Rested::default();
+        // the first command has nothing to compare to
+        assert_eq!(0, rested.track(100_000, true));
+
+        assert_eq!(0, rested.track(100_000 + REST_PERIOD - 1, true));
+        assert_eq!(2, rested.track(100_000 + 3 * REST_PERIOD, false));
+        assert_eq!(Some(RESTED_XP), rested.bonus());
+        assert_eq!(Some(RESTED_XP), rested.bonus());
+        assert_eq!(None, rested.bonus());
+
+        // time away from home doesn't count
+        assert_eq!(0, rested.track(100_000 + 10 * REST_PERIOD, true));
+
+        // and the accumulated battles are capped
+        assert_eq!(
+            MAX_RESTED_BATTLES,
+            rested.track(100_000 + 100 * REST_PERIOD, true)
+        );
+        assert_eq!(0, rested.track(100_000 + 200 * REST_PERIOD, true));
+        assert_eq!(MAX_RESTED_BATTLES, rested.battles);
+    }
+}
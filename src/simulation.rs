@@ -0,0 +1,151 @@
+use crate::character::enemy;
+use crate::game::Game;
+use crate::hook;
+use crate::item::chest::Chest;
+use crate::item::equipment::Equipment;
+use crate::location::{Distance, Location};
+use crate::log;
+use std::collections::BTreeMap;
+use std::ops::RangeInclusive;
+
+/// What to play out on each run of a simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A battle against the enemies that appear at the distance.
+    Battles,
+    /// A chest as found at the distance.
+    Chests,
+}
+
+/// The aggregate results of the runs at one distance from home.
+#[derive(Debug, Default, Clone)]
+pub struct Outcome {
+    pub distance: i32,
+    pub runs: usize,
+    /// Battles won or chests found.
+    pub successes: usize,
+    pub gold: i64,
+    pub xp: i64,
+    /// How many of each item were obtained, by key.
+    pub items: BTreeMap<String, usize>,
+}
+
+/// Play the given number of runs at each of the distances, each one with a
+/// new hero of the given level, optionally wearing equipment of the given
+/// level. Nothing is printed or saved, and no hooks are fired, but plugins
+/// still take part so their content is reflected in the results.
+pub fn simulate(
+    kind: Kind,
+    level: i32,
+    gear: Option<i32>,
+    distances: RangeInclusive<i32>,
+    runs: usize,
+) -> Vec<Outcome> {
+    distances
+        .map(|distance| {
+            let mut outcome = Outcome {
+                distance,
+                runs,
+                ..Outcome::default()
+            };
+            for _ in 0..runs {
+                let mut game = hero(level, gear, distance);
+                hook::muted(|| {
+                    log::silenced(|| match kind {
+                        Kind::Battles => battle(&mut game, distance, &mut outcome),
+                        Kind::Chests => chest(&mut game, distance, &mut outcome),
+                    })
+                });
+            }
+            outcome
+        })
+        .collect()
+}
+
+/// A new game with the hero raised to the given level, at the given
+/// distance from home.
+fn hero(level: i32, gear: Option<i32>, distance: i32) -> Game {
+    let mut game = Game::new();
+    game.location = Location::below_home(distance);
+    for _ in 1..level {
+        game.player.add_experience(game.player.xp_for_next());
+    }
+    if let Some(gear) = gear {
+        game.player.sword = Some(Equipment::sword(gear));
+        game.player.shield = Some(Equipment::shield(gear));
+        game.player.helmet = Some(Equipment::helmet(gear));
+        game.player.armor = Some(Equipment::armor(gear));
+        game.player.boots = Some(Equipment::boots(gear));
+    }
+    game.player.restore();
+    game
+}
+
+fn battle(game: &mut Game, distance: i32, outcome: &mut Outcome) {
+    let xp = game.player.total_xp();
+    let gold = game.gold;
+    let items = inventory(game);
+
+    let mut enemies = enemy::spawn_group(
+        &game.location,
+        &game.player,
+        &Distance::from(distance),
+        game.new_game_plus,
+        game.difficulty,
+        &game.clock,
+        &game.reputation,
+    );
+    if let Ok(true) = game.battle_group(&mut enemies, false, false, None) {
+        outcome.successes += 1;
+        outcome.gold += (game.gold - gold) as i64;
+        outcome.xp += (game.player.total_xp() - xp) as i64;
+        for (key, count) in inventory(game) {
+            let gained = count.saturating_sub(items.get(&key).copied().unwrap_or(0));
+            if gained > 0 {
+                *outcome.items.entry(key).or_default() += gained;
+            }
+        }
+    }
+}
+
+fn chest(game: &mut Game, distance: i32, outcome: &mut Outcome) {
+    if let Some(chest) = Chest::generate_at(game, &Distance::from(distance)) {
+        outcome.successes += 1;
+        outcome.gold += chest.gold() as i64;
+        for key in chest.keys() {
+            *outcome.items.entry(key.to_string()).or_default() += 1;
+        }
+    }
+}
+
+fn inventory(game: &Game) -> BTreeMap<String, usize> {
+    game.inventory()
+        .into_iter()
+        .map(|(key, count)| (key.to_string(), count))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::randomizer;
+
+    #[test]
+    fn outcomes() {
+        let outcomes = simulate(Kind::Battles, 3, Some(10), 1..=3, 5);
+        assert_eq!(3, outcomes.len());
+        for (outcome, distance) in outcomes.iter().zip(1..) {
+            assert_eq!(distance, outcome.distance);
+            assert_eq!(5, outcome.runs);
+            // a well equipped hero wins every battle near home
+            assert_eq!(5, outcome.successes);
+            assert!(outcome.gold > 0);
+            assert!(outcome.xp > 0);
+        }
+
+        let outcomes = randomizer::with_seed(1, || simulate(Kind::Chests, 1, None, 5..=5, 50));
+        assert_eq!(1, outcomes.len());
+        assert!(outcomes[0].successes > 0);
+        assert!(outcomes[0].successes < 50);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::activity;
+use crate::character::Character;
+use crate::game::Game;
+use crate::log;
+use serde::{Deserialize, Serialize};
+
+/// Hero levels recorded as splits when first reached.
+const LEVEL_SPLITS: [i32; 5] = [10, 25, 50, 75, 100];
+
+/// The wall-clock timer of a speedrun, from the start of the game to the
+/// final boss, with the time each milestone was reached.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct Speedrun {
+    /// Unix timestamp of the start of the run.
+    start: u64,
+
+    /// The milestones reached, in order.
+    splits: Vec<Split>,
+
+    /// Seconds the whole run took, once the final boss is beaten.
+    finish: Option<u64>,
+}
+
+/// A milestone of the run, with the seconds it took to reach it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Split {
+    pub name: String,
+    pub time: u64,
+}
+
+impl Speedrun {
+    pub fn new(start: u64) -> Self {
+        Self {
+            start,
+            ..Self::default()
+        }
+    }
+
+    /// Seconds since the start of the run, or the final time if it's over.
+    pub fn elapsed(&self, now: u64) -> u64 {
+        self.finish
+            .unwrap_or_else(|| now.saturating_sub(self.start))
+    }
+
+    pub fn splits(&self) -> &[Split] {
+        &self.splits
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finish.is_some()
+    }
+
+    /// Record the milestone, unless it was already reached or the run is
+    /// over. Return the new split, if any.
+    fn split(&mut self, name: String, now: u64) -> Option<Split> {
+        if self.is_finished() || self.splits.iter().any(|split| split.name == name) {
+            return None;
+        }
+        let split = Split {
+            name,
+            time: self.elapsed(now),
+        };
+        self.splits.push(split.clone());
+        Some(split)
+    }
+}
+
+// EVENT TRIGGERING FUNCTIONS
+
+pub fn level_up(game: &mut Game) {
+    let level = game.player.level;
+    for milestone in LEVEL_SPLITS.iter().filter(|milestone| **milestone <= level) {
+        split(game, format!("level {}", milestone));
+    }
+}
+
+pub fn boss_beaten(game: &mut Game, boss: &Character) {
+    let distance = game.location.distance_from_home().len();
+    split(game, format!("{} at {}", boss.name(), distance));
+}
+
+/// Stop the timer when the final boss is beaten, and show the summary
+/// of the run.
+pub fn final_boss_beaten(game: &mut Game) {
+    // the levels gained in the last battle count before the end
+    level_up(game);
+    split(game, String::from("gorthaur"));
+    let now = activity::now();
+    match &mut game.speedrun {
+        Some(run) if !run.is_finished() => run.finish = Some(run.elapsed(now)),
+        _ => return,
+    }
+    log::speedrun(game, now);
+}
+
+fn split(game: &mut Game, name: String) {
+    if let Some(run) = &mut game.speedrun {
+        if let Some(split) = run.split(name, activity::now()) {
+            log::speedrun_split(&split);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splits() {
+        let mut run = Speedrun::new(1000);
+        assert_eq!(500, run.elapsed(1500));
+
+        let split = run.split(String::from("level 10"), 1200).unwrap();
+        assert_eq!(200, split.time);
+        assert!(run.split(String::from("level 10"), 1300).is_none());
+        run.split(String::from("level 25"), 1300);
+        assert_eq!(2, run.splits().len());
+
+        run.finish = Some(400);
+        assert_eq!(400, run.elapsed(5000));
+        assert!(run.split(String::from("level 50"), 5000).is_none());
+    }
+
+    #[test]
+    fn test_game_splits() {
+        let mut game = Game::new();
+        game.player.level = 30;
+        level_up(&mut game);
+        assert!(game.speedrun.is_none());
+
+        game.speedrun = Some(Speedrun::new(activity::now()));
+        level_up(&mut game);
+        let names: Vec<&str> = game
+            .speedrun
+            .as_ref()
+            .unwrap()
+            .splits()
+            .iter()
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(vec!["level 10", "level 25"], names);
+
+        final_boss_beaten(&mut game);
+        assert!(game.speedrun.as_ref().unwrap().is_finished());
+    }
+}
@@ -0,0 +1,158 @@
+use crate::location::Location;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Time between commands longer than this, in seconds, is considered away
+/// from the game and isn't counted as playtime.
+const IDLE_SECONDS: u64 = 600;
+
+/// Statistics of the player's history, aggregated across heroes and games.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct Stats {
+    /// Battles fought, not counting the ones avoided by running away or
+    /// bribing the enemy before the first turn.
+    pub battles: i32,
+    pub battles_won: i32,
+
+    /// Gold from beaten enemies, opened chests and sold items.
+    pub gold_earned: i32,
+
+    /// Gold paid at the shop and to bribe enemies.
+    pub gold_spent: i32,
+
+    /// Directories walked by the heroes.
+    pub steps: i32,
+
+    /// Farthest distance from home ever reached.
+    pub deepest: i32,
+
+    /// Hero deaths by location.
+    pub deaths: BTreeMap<String, i32>,
+
+    /// Most arena waves beaten in a row.
+    pub best_wave: i32,
+
+    /// Seconds spent playing, measured between commands.
+    pub playtime: u64,
+
+    /// Timestamp of the last command run, 0 if none was yet.
+    last_command: u64,
+}
+
+impl Stats {
+    pub fn battle(&mut self, won: bool) {
+        self.battles += 1;
+        if won {
+            self.battles_won += 1;
+        }
+    }
+
+    pub fn earn(&mut self, gold: i32) {
+        self.gold_earned += gold;
+    }
+
+    pub fn spend(&mut self, gold: i32) {
+        self.gold_spent += gold;
+    }
+
+    pub fn step(&mut self, location: &Location) {
+        self.steps += 1;
+        self.deepest = std::cmp::max(self.deepest, location.distance_from_home().len());
+    }
+
+    pub fn death(&mut self, location: &Location) {
+        *self.deaths.entry(location.to_string()).or_insert(0) += 1;
+    }
+
+    /// Track a command run at the given time, adding the time since the
+    /// previous one to the playtime unless the player was away.
+    pub fn play(&mut self, now: u64) {
+        if self.last_command > 0 && now > self.last_command {
+            let elapsed = now - self.last_command;
+            if elapsed <= IDLE_SECONDS {
+                self.playtime += elapsed;
+            }
+        }
+        self.last_command = now;
+    }
+
+    /// Account for a run of arena waves, returning whether it's a new record.
+    pub fn arena(&mut self, waves: i32) -> bool {
+        if waves > self.best_wave {
+            self.best_wave = waves;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Total hero deaths.
+    pub fn total_deaths(&self) -> i32 {
+        self.deaths.values().sum()
+    }
+
+    /// Percentage of the fought battles that were won.
+    pub fn win_rate(&self) -> i32 {
+        if self.battles == 0 {
+            0
+        } else {
+            self.battles_won * 100 / self.battles
+        }
+    }
+
+    /// Locations where heroes died, those with most deaths first.
+    pub fn deaths(&self) -> Vec<(&String, i32)> {
+        let mut deaths: Vec<(&String, i32)> = self
+            .deaths
+            .iter()
+            .map(|(location, count)| (location, *count))
+            .collect();
+        deaths.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        deaths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::location::tests::location_from;
+
+    #[test]
+    fn test_history() {
+        let mut stats = Stats::default();
+        assert_eq!(0, stats.win_rate());
+
+        stats.battle(true);
+        stats.battle(true);
+        stats.battle(false);
+        assert_eq!(66, stats.win_rate());
+
+        let home = Location::home().path_string();
+        let near = location_from(&format!("{}/a", home));
+        let far = location_from(&format!("{}/a/b/c", home));
+        stats.step(&far);
+        stats.step(&near);
+        assert_eq!(2, stats.steps);
+        assert_eq!(3, stats.deepest);
+
+        stats.death(&near);
+        stats.death(&far);
+        stats.death(&far);
+        let far = far.to_string();
+        assert_eq!(vec![(&far, 2), (&near.to_string(), 1)], stats.deaths());
+        assert_eq!(3, stats.total_deaths());
+
+        assert!(stats.arena(3));
+        assert!(!stats.arena(2));
+        assert_eq!(3, stats.best_wave);
+
+        stats.play(1000);
+        stats.play(1100);
+        assert_eq!(100, stats.playtime);
+        // time away from the game isn't counted
+        stats.play(1100 + IDLE_SECONDS + 1);
+        stats.play(1100 + IDLE_SECONDS + 61);
+        assert_eq!(160, stats.playtime);
+    }
+}
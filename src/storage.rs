@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use std::{fs, path};
+
+/// Where the game data of a profile is saved, as named entries such as the
+/// data and its backup. Writes are atomic: when interrupted, the entry is
+/// left with either the old or the new contents, never a mix.
+pub trait Storage: Send + Sync {
+    /// The contents of the entry, if it exists.
+    fn read(&self, name: &str) -> Option<Vec<u8>>;
+
+    /// Replace the contents of the entry, creating it if missing.
+    fn write(&self, name: &str, data: &[u8]) -> io::Result<()>;
+
+    /// Move the entry to another name, replacing the entry there. Nothing
+    /// is done if it doesn't exist.
+    fn rename(&self, from: &str, to: &str) -> io::Result<()>;
+
+    /// Delete the entry, if it exists.
+    fn remove(&self, name: &str) -> io::Result<()>;
+
+    /// When the entry was last written.
+    fn modified(&self, name: &str) -> Option<SystemTime>;
+
+    /// Delete every entry, e.g. when its profile is deleted.
+    fn clear(&self) -> io::Result<()>;
+}
+
+/// Entries saved as files of a directory, the default.
+pub struct FileStorage {
+    dir: path::PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(dir: path::PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self, name: &str) -> Option<Vec<u8>> {
+        fs::read(self.dir.join(name)).ok()
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        write_file(&self.dir.join(name), data)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        let from = self.dir.join(from);
+        if from.exists() {
+            fs::rename(from, self.dir.join(to))?;
+        }
+        Ok(())
+    }
+
+    fn remove(&self, name: &str) -> io::Result<()> {
+        let file = self.dir.join(name);
+        if file.exists() {
+            fs::remove_file(file)?;
+        }
+        Ok(())
+    }
+
+    fn modified(&self, name: &str) -> Option<SystemTime> {
+        fs::metadata(self.dir.join(name))
+            .and_then(|meta| meta.modified())
+            .ok()
+    }
+
+    fn clear(&self) -> io::Result<()> {
+        if !self.dir.exists() {
+            return Ok(());
+        }
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.is_file() {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Replace the file atomically: the data is written to a temporary file,
+/// flushed to disk and then renamed over the target.
+pub(crate) fn write_file(file: &path::Path, data: &[u8]) -> io::Result<()> {
+    let mut name = file.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let partial = file.with_file_name(name);
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut out = fs::File::create(&partial)?;
+    out.write_all(data)?;
+    out.sync_all()?;
+    fs::rename(partial, file)
+}
+
+/// Entries kept in memory and lost when dropped, for tests and frontends
+/// that persist the game on their own.
+#[derive(Default)]
+pub struct MemoryStorage {
+    entries: Mutex<HashMap<String, (Vec<u8>, SystemTime)>>,
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self, name: &str) -> Option<Vec<u8>> {
+        let entries = self.entries.lock().unwrap();
+        entries.get(name).map(|(data, _)| data.clone())
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(name.to_string(), (data.to_vec(), SystemTime::now()));
+        Ok(())
+    }
+
+    fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.remove(from) {
+            entries.insert(to.to_string(), entry);
+        }
+        Ok(())
+    }
+
+    fn remove(&self, name: &str) -> io::Result<()> {
+        self.entries.lock().unwrap().remove(name);
+        Ok(())
+    }
+
+    fn modified(&self, name: &str) -> Option<SystemTime> {
+        let entries = self.entries.lock().unwrap();
+        entries.get(name).map(|(_, modified)| *modified)
+    }
+
+    fn clear(&self) -> io::Result<()> {
+        self.entries.lock().unwrap().clear();
+        Ok(())
+    }
+}
+
+/// Entries saved as rows of an SQLite database shared by all the profiles.
+#[cfg(feature = "sqlite")]
+pub struct SqliteStorage {
+    connection: Mutex<rusqlite::Connection>,
+    profile: String,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStorage {
+    /// Open the database at the given file, creating it if missing, to
+    /// save the entries of the given profile.
+    pub fn open(file: &path::Path, profile: &str) -> io::Result<Self> {
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let connection = rusqlite::Connection::open(file).map_err(sql_error)?;
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS entries (
+                    profile TEXT NOT NULL,
+                    name TEXT NOT NULL,
+                    data BLOB NOT NULL,
+                    modified INTEGER NOT NULL,
+                    PRIMARY KEY (profile, name)
+                )",
+            )
+            .map_err(sql_error)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            profile: profile.to_string(),
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Storage for SqliteStorage {
+    fn read(&self, name: &str) -> Option<Vec<u8>> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .query_row(
+                "SELECT data FROM entries WHERE profile = ?1 AND name = ?2",
+                rusqlite::params![self.profile, name],
+                |row| row.get(0),
+            )
+            .ok()
+    }
+
+    fn write(&self, name: &str, data: &[u8]) -> io::Result<()> {
+        let modified = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as i64);
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "INSERT OR REPLACE INTO entries VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![self.profile, name, data, modified],
+            )
+            .map_err(sql_error)?;
+        Ok(())
+    }
+
+    fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction().map_err(sql_error)?;
+        let params = rusqlite::params![self.profile, from, to];
+        transaction
+            .execute(
+                "DELETE FROM entries WHERE profile = ?1 AND name = ?3
+                 AND EXISTS (SELECT 1 FROM entries WHERE profile = ?1 AND name = ?2)",
+                params,
+            )
+            .map_err(sql_error)?;
+        transaction
+            .execute(
+                "UPDATE entries SET name = ?3 WHERE profile = ?1 AND name = ?2",
+                params,
+            )
+            .map_err(sql_error)?;
+        transaction.commit().map_err(sql_error)
+    }
+
+    fn remove(&self, name: &str) -> io::Result<()> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "DELETE FROM entries WHERE profile = ?1 AND name = ?2",
+                rusqlite::params![self.profile, name],
+            )
+            .map_err(sql_error)?;
+        Ok(())
+    }
+
+    fn modified(&self, name: &str) -> Option<SystemTime> {
+        let connection = self.connection.lock().unwrap();
+        let nanos: i64 = connection
+            .query_row(
+                "SELECT modified FROM entries WHERE profile = ?1 AND name = ?2",
+                rusqlite::params![self.profile, name],
+                |row| row.get(0),
+            )
+            .ok()?;
+        Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_nanos(nanos as u64))
+    }
+
+    fn clear(&self) -> io::Result<()> {
+        let connection = self.connection.lock().unwrap();
+        connection
+            .execute(
+                "DELETE FROM entries WHERE profile = ?1",
+                rusqlite::params![self.profile],
+            )
+            .map_err(sql_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn sql_error(err: rusqlite::Error) -> io::Error {
+    io::Error::other(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The behavior every storage has to share.
+    fn check(storage: &dyn Storage) {
+        assert!(storage.read("data").is_none());
+        assert!(storage.modified("data").is_none());
+        storage.write("data", b"first").unwrap();
+        assert_eq!(Some(b"first".to_vec()), storage.read("data"));
+        assert!(storage.modified("data").is_some());
+
+        storage.write("data", b"second").unwrap();
+        storage.write("data.bak", b"old").unwrap();
+        storage.rename("data", "data.bak").unwrap();
+        assert!(storage.read("data").is_none());
+        assert_eq!(Some(b"second".to_vec()), storage.read("data.bak"));
+
+        // renaming a missing entry keeps the target
+        storage.rename("data", "data.bak").unwrap();
+        assert_eq!(Some(b"second".to_vec()), storage.read("data.bak"));
+
+        storage.remove("data.bak").unwrap();
+        storage.remove("data.bak").unwrap();
+        assert!(storage.read("data.bak").is_none());
+
+        storage.write("data", b"first").unwrap();
+        storage.write("data.bak", b"old").unwrap();
+        storage.clear().unwrap();
+        assert!(storage.read("data").is_none());
+        assert!(storage.read("data.bak").is_none());
+    }
+
+    #[test]
+    fn storages() {
+        check(&MemoryStorage::default());
+
+        let dir = std::env::temp_dir().join("rpg-storage-test");
+        let _ = fs::remove_dir_all(&dir);
+        check(&FileStorage::new(dir.clone()));
+
+        #[cfg(feature = "sqlite")]
+        {
+            let db = dir.join("rpg.db");
+            check(&SqliteStorage::open(&db, "default").unwrap());
+            // each profile has its own entries
+            SqliteStorage::open(&db, "other")
+                .unwrap()
+                .write("data", b"other")
+                .unwrap();
+            assert!(SqliteStorage::open(&db, "default")
+                .unwrap()
+                .read("data")
+                .is_none());
+
+            // clearing the entries of a deleted profile keeps the others'
+            let deleted = SqliteStorage::open(&db, "deleted").unwrap();
+            deleted.write("data", b"deleted").unwrap();
+            deleted.clear().unwrap();
+            assert!(SqliteStorage::open(&db, "deleted")
+                .unwrap()
+                .read("data")
+                .is_none());
+            assert!(SqliteStorage::open(&db, "other")
+                .unwrap()
+                .read("data")
+                .is_some());
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,443 @@
+use crate::config::{Config, SyncRemote};
+use crate::datafile;
+use crate::game::Game;
+use anyhow::{anyhow, bail, Result};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, path};
+
+/// A side of the sync, the local game or the copy at the remote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Local,
+    Remote,
+}
+
+/// What syncing the game did.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Synced {
+    UpToDate,
+    Pushed,
+    Pulled,
+    /// The game changed both locally and at the remote since the last sync,
+    /// and the one saved last was kept.
+    Conflict(Conflict),
+}
+
+/// What the games in conflict differed in, as the gold and item counts of
+/// both, so the player knows what was lost.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Conflict {
+    pub kept: Side,
+    /// The local and the remote gold.
+    pub gold: (i32, i32),
+    /// The items whose count differs, with the local and the remote count.
+    pub items: Vec<(String, usize, usize)>,
+}
+
+/// Where the copy of the game is kept, as files named after the profiles.
+trait Remote {
+    fn fetch(&self, name: &str) -> Result<Option<Vec<u8>>>;
+    fn push(&self, name: &str, data: &[u8]) -> Result<()>;
+}
+
+/// Bring the game and its copy at the remote of the config up to date,
+/// pushing or pulling whichever changed since the last sync. With `force`,
+/// the given side replaces the other one regardless.
+pub fn sync(game: &mut Game, force: Option<Side>) -> Result<Synced> {
+    // pulling an older copy of the game would undo bad luck
+    if game.ironman {
+        bail!("Ironman games can't be synced.");
+    }
+    let remote = remote()?;
+    let name = format!("{}.json", datafile::current_profile());
+    let source = format!("{} at the remote", name);
+    let local = datafile::fingerprint(game);
+    // a forced push replaces the remote game even if it can't be read
+    let theirs = match force {
+        Some(Side::Local) => None,
+        _ => match remote.fetch(&name)? {
+            Some(data) => {
+                let value: Value = serde_json::from_slice(&data).unwrap_or_default();
+                let saved_at = value["saved_at"].as_u64().unwrap_or(0);
+                Some((datafile::imported(&value, &source)?, saved_at))
+            }
+            None => None,
+        },
+    };
+
+    let (side, conflict) = match (force, &theirs) {
+        (Some(Side::Remote), None) => bail!("There's no game at the remote to pull."),
+        (Some(side), _) => (side, false),
+        (None, None) => (Side::Local, false),
+        (None, Some((theirs, saved_at))) => {
+            let modified = datafile::modified();
+            // a game that was never saved has nothing to lose
+            let base = match modified {
+                Some(_) => datafile::synced(),
+                None => Some(local.clone()),
+            };
+            let saved = (modified.map_or(0, seconds), *saved_at);
+            match decide(base, local.clone(), datafile::fingerprint(theirs), saved) {
+                Some(decision) => decision,
+                None => {
+                    datafile::save_synced(&local)?;
+                    return Ok(Synced::UpToDate);
+                }
+            }
+        }
+    };
+
+    let report = match &theirs {
+        Some((theirs, _)) if conflict => Some(report(side, game, theirs)),
+        _ => None,
+    };
+    match side {
+        Side::Local => {
+            let mut data = datafile::exported(game);
+            data["saved_at"] = json!(seconds(SystemTime::now()));
+            remote.push(&name, &serde_json::to_vec_pretty(&data)?)?;
+        }
+        // checked above that there's a remote game when pulling
+        Side::Remote => *game = theirs.unwrap().0,
+    }
+    datafile::save_synced(&datafile::fingerprint(game))?;
+    let synced = match (report, side) {
+        (Some(report), _) => Synced::Conflict(report),
+        (None, Side::Local) => Synced::Pushed,
+        (None, Side::Remote) => Synced::Pulled,
+    };
+    Ok(synced)
+}
+
+/// The remote of the sync config.
+fn remote() -> Result<Box<dyn Remote>> {
+    match Config::get().sync.clone() {
+        Some(SyncRemote::Git { url, branch }) => Ok(Box::new(Git {
+            url,
+            branch,
+            dir: datafile::sync_dir(),
+        })),
+        Some(SyncRemote::S3 {
+            endpoint,
+            bucket,
+            region,
+        }) => Ok(Box::new(S3 {
+            endpoint,
+            bucket,
+            region,
+        })),
+        None => bail!("There's no remote to sync with, set one in the sync section of the config."),
+    }
+}
+
+/// The side that should replace the other one, given the fingerprints of the
+/// game at the last sync and of both games, and when each one was saved.
+/// When both changed, the one saved last wins and it's reported as a
+/// conflict. Games that are the same need no sync.
+fn decide(
+    base: Option<String>,
+    local: String,
+    remote: String,
+    (local_saved, remote_saved): (u64, u64),
+) -> Option<(Side, bool)> {
+    if local == remote {
+        return None;
+    }
+    let decision = if base.as_ref() == Some(&local) {
+        (Side::Remote, false)
+    } else if base.as_ref() == Some(&remote) {
+        (Side::Local, false)
+    } else if remote_saved > local_saved {
+        (Side::Remote, true)
+    } else {
+        (Side::Local, true)
+    };
+    Some(decision)
+}
+
+/// The differences in gold and items between the local and the remote game.
+fn report(kept: Side, local: &Game, remote: &Game) -> Conflict {
+    let mut counts: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for (key, items) in &local.inventory {
+        counts.entry(key.to_string()).or_default().0 = items.len();
+    }
+    for (key, items) in &remote.inventory {
+        counts.entry(key.to_string()).or_default().1 = items.len();
+    }
+    Conflict {
+        kept,
+        gold: (local.gold, remote.gold),
+        items: counts
+            .into_iter()
+            .filter(|(_, (local, remote))| local != remote)
+            .map(|(name, (local, remote))| (name, local, remote))
+            .collect(),
+    }
+}
+
+fn seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs())
+}
+
+/// A git repository, cloned at the given directory. The game is committed
+/// to the branch and pushed right away, so the clone is just a cache.
+struct Git {
+    url: String,
+    branch: String,
+    dir: path::PathBuf,
+}
+
+impl Git {
+    fn git(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.dir)
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .map_err(|err| anyhow!("Can't run git: {}", err))?;
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            bail!("git failed to sync the game: {}", error.trim());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Bring the clone up to date with the branch at the remote, if it
+    /// exists yet.
+    fn update(&self) -> Result<()> {
+        if self.dir.join(".git").exists() {
+            self.git(&["remote", "set-url", "origin", &self.url])?;
+        } else {
+            fs::create_dir_all(&self.dir)?;
+            self.git(&["init", "--quiet"])?;
+            self.git(&["remote", "add", "origin", &self.url])?;
+        }
+        self.git(&["fetch", "--quiet", "origin"])?;
+        let branch = format!("refs/remotes/origin/{}", self.branch);
+        if self
+            .git(&["rev-parse", "--quiet", "--verify", &branch])
+            .is_ok()
+        {
+            self.git(&[
+                "checkout",
+                "--quiet",
+                "--force",
+                "-B",
+                &self.branch,
+                &branch,
+            ])?;
+        }
+        Ok(())
+    }
+}
+
+impl Remote for Git {
+    fn fetch(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        self.update()?;
+        Ok(fs::read(self.dir.join(name)).ok())
+    }
+
+    fn push(&self, name: &str, data: &[u8]) -> Result<()> {
+        self.update()?;
+        fs::write(self.dir.join(name), data)?;
+        self.git(&["add", name])?;
+        if !self.git(&["status", "--porcelain"])?.is_empty() {
+            let message = format!("Sync {}", name);
+            self.git(&[
+                "-c",
+                "user.name=rpg-cli",
+                "-c",
+                "user.email=rpg-cli@localhost",
+                "commit",
+                "--quiet",
+                "--message",
+                &message,
+            ])?;
+        }
+        let branch = format!("HEAD:refs/heads/{}", self.branch);
+        self.git(&["push", "--quiet", "origin", &branch])?;
+        Ok(())
+    }
+}
+
+/// A bucket of an S3-compatible service, requested with curl, which signs
+/// the requests with the credentials of the environment.
+struct S3 {
+    endpoint: String,
+    bucket: String,
+    region: String,
+}
+
+impl S3 {
+    /// Request the object with the given name, uploading the given file if
+    /// any. Returns the status code and the body of the response.
+    fn request(&self, name: &str, upload: Option<&path::Path>) -> Result<(u32, Vec<u8>)> {
+        let (key, secret) = match (
+            std::env::var("AWS_ACCESS_KEY_ID"),
+            std::env::var("AWS_SECRET_ACCESS_KEY"),
+        ) {
+            (Ok(key), Ok(secret)) => (key, secret),
+            _ => bail!("Set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY to sync with S3."),
+        };
+        let url = format!(
+            "{}/{}/{}",
+            self.endpoint.trim_end_matches('/'),
+            self.bucket,
+            name
+        );
+        let mut command = Command::new("curl");
+        command
+            .args(["--silent", "--show-error", "--max-time", "30"])
+            .args(["--aws-sigv4", &format!("aws:amz:{}:s3", self.region)])
+            // the credentials are passed through stdin, so they don't show
+            // in the process list
+            .args(["--config", "-"])
+            .args(["--write-out", "\n%{http_code}"]);
+        if let Some(file) = upload {
+            command.arg("--upload-file").arg(file);
+        }
+        let mut child = command
+            .arg(&url)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| anyhow!("Can't run curl: {}", err))?;
+        let config = format!("user = \"{}:{}\"\n", quote(&key), quote(&secret));
+        child.stdin.take().unwrap().write_all(config.as_bytes())?;
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            bail!("curl failed to sync the game: {}", error.trim());
+        }
+        let body = output.stdout;
+        let split = body.iter().rposition(|byte| *byte == b'\n').unwrap_or(0);
+        let status = String::from_utf8_lossy(&body[split..]).trim().parse()?;
+        Ok((status, body[..split].to_vec()))
+    }
+}
+
+impl Remote for S3 {
+    fn fetch(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        match self.request(name, None)? {
+            (200, body) => Ok(Some(body)),
+            (404, _) => Ok(None),
+            (status, body) => bail!(
+                "The remote answered {}: {}",
+                status,
+                String::from_utf8_lossy(&body).trim()
+            ),
+        }
+    }
+
+    fn push(&self, name: &str, data: &[u8]) -> Result<()> {
+        let file = std::env::temp_dir().join(format!("rpg-sync-{}", std::process::id()));
+        fs::write(&file, data)?;
+        let response = self.request(name, Some(&file));
+        fs::remove_file(&file)?;
+        match response? {
+            (200, _) => Ok(()),
+            (status, body) => bail!(
+                "The remote answered {}: {}",
+                status,
+                String::from_utf8_lossy(&body).trim()
+            ),
+        }
+    }
+}
+
+/// Escape the value for a curl config file.
+fn quote(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::key::Key;
+    use crate::item::ring::Ring;
+    use crate::item::Potion;
+
+    #[test]
+    fn decisions() {
+        let (base, a, b) = (String::from("base"), String::from("a"), String::from("b"));
+        assert_eq!(
+            None,
+            decide(Some(base.clone()), a.clone(), a.clone(), (0, 0))
+        );
+
+        // only one side changed since the last sync
+        let decision = decide(Some(a.clone()), a.clone(), b.clone(), (5, 1));
+        assert_eq!(Some((Side::Remote, false)), decision);
+        let decision = decide(Some(b.clone()), a.clone(), b.clone(), (1, 5));
+        assert_eq!(Some((Side::Local, false)), decision);
+
+        // both changed, or never synced before: the last saved wins
+        let decision = decide(Some(base), a.clone(), b.clone(), (1, 5));
+        assert_eq!(Some((Side::Remote, true)), decision);
+        assert_eq!(Some((Side::Local, true)), decide(None, a, b, (5, 1)));
+    }
+
+    #[test]
+    fn conflict_report() {
+        let mut local = Game::new();
+        local.gold = 100;
+        local.add_item(Box::new(Potion::new(1)));
+        local.add_item(Box::new(Ring::Void));
+        let mut remote = Game::new();
+        remote.gold = 50;
+        remote.add_item(Box::new(Potion::new(1)));
+        remote.add_item(Box::new(Potion::new(1)));
+
+        let conflict = report(Side::Local, &local, &remote);
+        assert_eq!((100, 50), conflict.gold);
+        assert_eq!(
+            vec![
+                (Key::Potion.to_string(), 1, 2),
+                (Key::Ring(Ring::Void).to_string(), 1, 0)
+            ],
+            conflict.items
+        );
+    }
+
+    #[test]
+    fn git_remote() {
+        let dir = std::env::temp_dir().join("rpg-sync-test");
+        let _ = fs::remove_dir_all(&dir);
+        let origin = dir.join("origin.git");
+        fs::create_dir_all(&origin).unwrap();
+        let status = Command::new("git")
+            .args(["init", "--quiet", "--bare"])
+            .arg(&origin)
+            .status();
+        // git is needed to sync, but not to build
+        if !status.is_ok_and(|status| status.success()) {
+            return;
+        }
+        let clone = |name: &str| Git {
+            url: origin.display().to_string(),
+            branch: String::from("main"),
+            dir: dir.join(name),
+        };
+
+        let (here, there) = (clone("here"), clone("there"));
+        assert_eq!(None, here.fetch("default.json").unwrap());
+        here.push("default.json", b"first").unwrap();
+        let data = there.fetch("default.json").unwrap();
+        assert_eq!(Some(b"first".to_vec()), data);
+
+        there.push("default.json", b"second").unwrap();
+        // pushing the same again is fine
+        there.push("default.json", b"second").unwrap();
+        let data = here.fetch("default.json").unwrap();
+        assert_eq!(Some(b"second".to_vec()), data);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
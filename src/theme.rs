@@ -0,0 +1,182 @@
+use crate::config::Config;
+use colored::{Color, ColoredString, Colorize};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+/// What a color is used for in the game output.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// The hp bar and healing.
+    Hp,
+    /// The missing part of the hp bar.
+    HpLost,
+    /// Damage taken by the hero and other bad news.
+    Damage,
+    /// The mp bar, spells and magic.
+    Mp,
+    /// The xp bar, level ups and stat increases.
+    Xp,
+    /// The missing part of the mp and xp bars.
+    Empty,
+    Gold,
+    /// Enemy names.
+    Enemy,
+    /// Names of gilded enemies.
+    Gilded,
+    /// Completed quests and achievements, and other good news.
+    Success,
+    /// The titles of ascended heroes.
+    Title,
+    /// Rare items, like rings and scrolls.
+    Rare,
+    /// Cursed items.
+    Cursed,
+}
+
+/// The base colors of a theme.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Preset {
+    #[default]
+    Default,
+    /// Avoids telling things apart by red and green, using blue and yellow
+    /// instead. Only the basic terminal colors are used, since rgb ones
+    /// aren't supported everywhere.
+    Colorblind,
+    /// No colors nor text styles at all.
+    NoColor,
+}
+
+/// A color set in the config file: a name like `bright red`, an rgb hex
+/// code like `#e69f00`, or `none` to leave the text uncolored.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(try_from = "String")]
+pub struct Paint(Option<Color>);
+
+impl TryFrom<String> for Paint {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        if name == "none" {
+            return Ok(Paint(None));
+        }
+        if let Some(hex) = name.strip_prefix('#') {
+            let rgb = u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6);
+            return match rgb {
+                Some(rgb) => Ok(Paint(Some(Color::TrueColor {
+                    r: (rgb >> 16) as u8,
+                    g: (rgb >> 8) as u8,
+                    b: rgb as u8,
+                }))),
+                None => Err(format!("invalid color {}", name)),
+            };
+        }
+        name.parse()
+            .map(|color| Paint(Some(color)))
+            .map_err(|_| format!("invalid color {}", name))
+    }
+}
+
+/// The colors of the game output, set in the config file with a preset and
+/// optionally the color of some roles:
+///
+/// ```yaml
+/// theme:
+///   preset: colorblind
+///   gold: bright yellow
+/// ```
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Theme {
+    pub preset: Preset,
+    #[serde(flatten)]
+    colors: HashMap<Role, Paint>,
+}
+
+impl Theme {
+    /// The color of the given role, if the text should be colored.
+    pub fn color(&self, role: Role) -> Option<Color> {
+        if self.preset == Preset::NoColor {
+            return None;
+        }
+        match self.colors.get(&role) {
+            Some(Paint(color)) => *color,
+            None => Some(self.preset_color(role)),
+        }
+    }
+
+    pub fn is_colored(&self) -> bool {
+        self.preset != Preset::NoColor
+    }
+
+    fn preset_color(&self, role: Role) -> Color {
+        match (self.preset, role) {
+            (Preset::Colorblind, Role::Hp | Role::Success) => Color::BrightBlue,
+            (Preset::Colorblind, Role::HpLost) => Color::Yellow,
+            (Preset::Colorblind, Role::Damage) => Color::BrightYellow,
+            (Preset::Colorblind, Role::Mp) => Color::BrightMagenta,
+            (Preset::Colorblind, Role::Title | Role::Cursed) => Color::Magenta,
+            (Preset::Colorblind, Role::Gilded) => Color::BrightWhite,
+            (Preset::Colorblind, Role::Rare) => Color::BrightCyan,
+            (_, Role::Hp | Role::Success) => Color::Green,
+            (_, Role::HpLost | Role::Cursed) => Color::Red,
+            (_, Role::Damage) => Color::BrightRed,
+            (_, Role::Mp) => Color::Magenta,
+            (_, Role::Xp) => Color::Cyan,
+            (_, Role::Empty) => Color::BrightBlack,
+            (_, Role::Gold | Role::Enemy) => Color::Yellow,
+            (_, Role::Gilded) => Color::BrightYellow,
+            (_, Role::Title) => Color::Magenta,
+            (_, Role::Rare) => Color::BrightBlue,
+        }
+    }
+}
+
+/// The color of the role in the configured theme.
+pub fn color(role: Role) -> Option<Color> {
+    Config::get().theme.color(role)
+}
+
+/// Color the text as set for the role in the configured theme.
+pub fn paint(text: &str, role: Role) -> ColoredString {
+    match color(role) {
+        Some(color) => text.color(color),
+        None => text.normal(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme() {
+        let theme = Theme::default();
+        assert_eq!(Some(Color::Green), theme.color(Role::Hp));
+        assert_eq!(Some(Color::BrightBlack), theme.color(Role::Empty));
+
+        let yaml = "preset: colorblind\ngold: bright yellow\nenemy: '#ff8000'\nrare: none";
+        let theme: Theme = serde_yaml::from_str(yaml).unwrap();
+        assert_ne!(Some(Color::Green), theme.color(Role::Hp));
+        assert_ne!(theme.color(Role::Hp), theme.color(Role::Damage));
+        assert_eq!(Some(Color::BrightYellow), theme.color(Role::Gold));
+        let orange = Color::TrueColor {
+            r: 255,
+            g: 128,
+            b: 0,
+        };
+        assert_eq!(Some(orange), theme.color(Role::Enemy));
+        assert_eq!(None, theme.color(Role::Rare));
+
+        let theme: Theme = serde_yaml::from_str("preset: no_color\ngold: red").unwrap();
+        assert!(!theme.is_colored());
+        assert_eq!(None, theme.color(Role::Gold));
+
+        assert!(serde_yaml::from_str::<Theme>("gold: golden").is_err());
+        assert!(serde_yaml::from_str::<Theme>("gold: '#ff'").is_err());
+        assert!(serde_yaml::from_str::<Theme>("sparkles: red").is_err());
+        assert!(serde_yaml::from_str::<Theme>("preset: neon").is_err());
+    }
+}
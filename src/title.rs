@@ -0,0 +1,117 @@
+use crate::game::Game;
+use crate::log;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// The title earned by beating the final boss.
+const FINAL_BOSS_TITLE: &str = "Gorthaur's Bane";
+
+/// Cosmetic titles earned by beating bosses, and the one chosen to be shown
+/// with the hero. Unlocked achievements give titles too, but they aren't
+/// kept here since the achievements already tell them.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+#[serde(default)]
+pub struct Titles {
+    /// Titles earned from bosses, in the order they were.
+    earned: Vec<String>,
+
+    /// The title shown with the hero, if any.
+    active: Option<String>,
+}
+
+impl Titles {
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+}
+
+/// All the titles earned by the player: the ones of unlocked achievements
+/// first, then the ones of bosses.
+pub fn list(game: &Game) -> Vec<String> {
+    let mut titles: Vec<String> = game
+        .achievements
+        .unlocked()
+        .iter()
+        .map(|achievement| achievement.title().to_string())
+        .collect();
+    titles.extend(game.titles.earned.iter().cloned());
+    titles
+}
+
+/// The title shown with the hero: the one chosen by the player, or else
+/// the one given by its prestige rank, if any.
+pub fn shown(game: &Game) -> Option<&str> {
+    game.titles.active().or(game.player.title())
+}
+
+/// Show the earned title of the given name with the hero. Names are matched
+/// regardless of case.
+pub fn set(game: &mut Game, name: &str) -> Result<()> {
+    let title = list(game)
+        .into_iter()
+        .find(|title| title.eq_ignore_ascii_case(name))
+        .ok_or_else(|| anyhow!("The {} title wasn't earned yet.", name))?;
+    log::title_set(Some(&title));
+    game.titles.active = Some(title);
+    Ok(())
+}
+
+/// Stop showing a chosen title, which brings back the prestige one, if any.
+pub fn clear(game: &mut Game) {
+    game.titles.active = None;
+    log::title_set(None);
+}
+
+/// Grant the given title, unless it was already earned.
+pub fn earn(game: &mut Game, title: &str) {
+    if !list(game).iter().any(|earned| earned == title) {
+        game.titles.earned.push(title.to_string());
+        log::title_earned(title);
+    }
+}
+
+// EVENT TRIGGERING FUNCTIONS
+
+pub fn final_boss_beaten(game: &mut Game) {
+    earn(game, FINAL_BOSS_TITLE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::achievement;
+
+    #[test]
+    fn earned_titles() {
+        let mut game = Game::new();
+        assert!(list(&game).is_empty());
+        assert!(set(&mut game, "boss hunter").is_err());
+
+        achievement::boss_beaten(&mut game);
+        final_boss_beaten(&mut game);
+        final_boss_beaten(&mut game);
+        assert_eq!(vec!["Boss Hunter", FINAL_BOSS_TITLE], list(&game));
+
+        set(&mut game, "boss hunter").unwrap();
+        assert_eq!(Some("Boss Hunter"), shown(&game));
+
+        // titles are kept across heroes
+        game.reset();
+        assert_eq!(2, list(&game).len());
+        assert_eq!(Some("Boss Hunter"), shown(&game));
+    }
+
+    #[test]
+    fn prestige_title() {
+        let mut game = Game::new();
+        assert_eq!(None, shown(&game));
+        game.player.prestige = 1;
+        assert_eq!(Some("ascended"), shown(&game));
+
+        final_boss_beaten(&mut game);
+        set(&mut game, FINAL_BOSS_TITLE).unwrap();
+        assert_eq!(Some(FINAL_BOSS_TITLE), shown(&game));
+        clear(&mut game);
+        assert_eq!(Some("ascended"), shown(&game));
+    }
+}
@@ -0,0 +1,270 @@
+use crate::datafile;
+use crate::game::Game;
+use crate::item::key::Key;
+use crate::item::ring::Ring;
+use crate::item::Item;
+use crate::log;
+use crate::storage;
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path;
+
+/// Gold and items sent from the game of one profile to another's, kept in
+/// the escrow file of the receiving profile until claimed there.
+#[derive(Serialize, Deserialize)]
+pub struct Package {
+    pub from: String,
+    pub gold: i32,
+    pub items: Vec<Box<dyn Item>>,
+}
+
+/// What a game got out of a package.
+#[derive(Debug, Default, PartialEq)]
+pub struct Unpacked {
+    pub gold: i32,
+    pub items: HashMap<Key, i32>,
+    /// Rings that already exist in the receiving game, which go back to
+    /// the ring pool of the sending one.
+    pub refused: Vec<Ring>,
+}
+
+/// Send the given gold and items from the hero to the game of another
+/// profile, through its escrow file. Rings are only sent if the receiving
+/// game hasn't found them yet.
+pub fn send(game: &mut Game, to: &str, keys: &[Key], gold: i32) -> Result<()> {
+    open(game)?;
+    let from = datafile::current_profile();
+    if to == from {
+        bail!("Can't trade with the profile in use.");
+    }
+    if !datafile::profiles().iter().any(|profile| profile == to) {
+        bail!("Profile {} not found.", to);
+    }
+    let mut escrow = load(to)?;
+    let receiver = datafile::peek_profile(to).unwrap_or_default();
+    for key in keys {
+        if let Key::Ring(ring) = key {
+            if !accepts(&receiver, ring) {
+                bail!("The {} was already found in the game of {}.", ring, to);
+            }
+        }
+    }
+
+    let package = pack(game, &from, keys, gold)?;
+    let items = package
+        .items
+        .iter()
+        .fold(HashMap::new(), |mut items, item| {
+            *items.entry(item.key()).or_insert(0) += 1;
+            items
+        });
+    escrow.push(package);
+    if let Err(err) = save(to, &escrow) {
+        // put back what couldn't be sent
+        unpack(game, escrow.pop().unwrap());
+        return Err(err);
+    }
+    log::trade_sent(to, &items, gold);
+    Ok(())
+}
+
+/// Receive the gold and items traded to the profile in use.
+pub fn claim(game: &mut Game) -> Result<()> {
+    open(game)?;
+    let profile = datafile::current_profile();
+    let escrow = load(&profile)?;
+    if escrow.is_empty() {
+        bail!("Nothing was traded to this profile.");
+    }
+    // remove the escrow before unpacking, so nothing is received twice
+    save(&profile, &[])?;
+    for package in escrow {
+        let from = package.from.clone();
+        let unpacked = unpack(game, package);
+        log::trade_received(&from, &unpacked.items, unpacked.gold, &unpacked.refused);
+    }
+    Ok(())
+}
+
+fn open(game: &Game) -> Result<()> {
+    if !game.location.is_home() {
+        bail!("Trading is only allowed at home.");
+    }
+    Ok(())
+}
+
+/// Take the given gold and items out of the game into a package. Traded
+/// rings go back to the ring pool, since they no longer exist in the game.
+pub fn pack(game: &mut Game, from: &str, keys: &[Key], gold: i32) -> Result<Package> {
+    if gold < 0 || (gold == 0 && keys.is_empty()) {
+        bail!("Nothing to trade.");
+    }
+    if gold > game.gold {
+        bail!("Not enough gold.");
+    }
+    let mut needed: HashMap<&Key, usize> = HashMap::new();
+    for key in keys {
+        *needed.entry(key).or_default() += 1;
+    }
+    let inventory = game.inventory();
+    for (key, count) in needed {
+        if inventory.get(key).copied().unwrap_or(0) < count {
+            bail!("Not enough {} in the inventory.", key);
+        }
+    }
+
+    game.gold -= gold;
+    let items: Vec<Box<dyn Item>> = keys
+        .iter()
+        .filter_map(|key| game.remove_item(key))
+        .collect();
+    for item in &items {
+        if let Key::Ring(ring) = item.key() {
+            // bought rings aren't found in chests
+            if !ring.is_bought() {
+                game.ring_pool.insert(ring);
+            }
+        }
+    }
+    Ok(Package {
+        from: from.to_string(),
+        gold,
+        items,
+    })
+}
+
+/// Add the package contents to the game, except the rings that already
+/// exist in it, which are refused.
+pub fn unpack(game: &mut Game, package: Package) -> Unpacked {
+    let mut unpacked = Unpacked {
+        gold: package.gold,
+        ..Unpacked::default()
+    };
+    game.gold += package.gold;
+    for item in package.items {
+        let key = item.key();
+        if let Key::Ring(ring) = &key {
+            if !accepts(game, ring) {
+                unpacked.refused.push(ring.clone());
+                continue;
+            }
+            game.ring_pool.remove(ring);
+        }
+        *unpacked.items.entry(key).or_insert(0) += 1;
+        game.add_item(item);
+    }
+    unpacked
+}
+
+/// Whether the game can receive the given ring, which is the case if it
+/// wasn't found there yet.
+fn accepts(game: &Game, ring: &Ring) -> bool {
+    ring.is_bought() || game.ring_pool.contains(ring)
+}
+
+/// The packages waiting in the escrow file of the given profile.
+fn load(profile: &str) -> Result<Vec<Package>> {
+    read(&datafile::escrow_file(profile))
+}
+
+/// The packages in the given escrow file, if it exists. A file that can't
+/// be parsed is an error instead of no packages, so they aren't lost when
+/// the next ones are saved over it.
+fn read(file: &path::Path) -> Result<Vec<Package>> {
+    if !file.exists() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_slice(&fs::read(file)?)
+        .map_err(|err| anyhow!("Invalid escrow file {}: {}", file.display(), err))
+}
+
+fn save(profile: &str, escrow: &[Package]) -> Result<()> {
+    let file = datafile::escrow_file(profile);
+    if escrow.is_empty() {
+        if file.exists() {
+            fs::remove_file(file)?;
+        }
+    } else {
+        storage::write_file(&file, &serde_json::to_vec(escrow)?)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::Potion;
+
+    #[test]
+    fn trade_items() {
+        let mut sender = Game::new();
+        sender.gold = 100;
+        sender.add_item(Box::new(Potion::new(1)));
+        sender.add_item(Box::new(Potion::new(1)));
+        sender.ring_pool.remove(&Ring::Void);
+        sender.add_item(Box::new(Ring::Void));
+
+        assert!(pack(&mut sender, "a", &[], 0).is_err());
+        assert!(pack(&mut sender, "a", &[], 200).is_err());
+        assert!(pack(
+            &mut sender,
+            "a",
+            &[Key::Potion, Key::Potion, Key::Potion],
+            0
+        )
+        .is_err());
+        assert_eq!(100, sender.gold);
+
+        let keys = [Key::Potion, Key::Ring(Ring::Void)];
+        let package = pack(&mut sender, "a", &keys, 60).unwrap();
+        assert_eq!(40, sender.gold);
+        assert_eq!(1, *sender.inventory().get(&Key::Potion).unwrap());
+        assert!(!sender.inventory().contains_key(&Key::Ring(Ring::Void)));
+        // the ring can be found again by the sender
+        assert!(sender.ring_pool.contains(&Ring::Void));
+
+        let mut receiver = Game::new();
+        let unpacked = unpack(&mut receiver, package);
+        assert_eq!(60, receiver.gold);
+        assert_eq!(
+            HashMap::from([(Key::Potion, 1), (Key::Ring(Ring::Void), 1)]),
+            unpacked.items
+        );
+        assert!(unpacked.refused.is_empty());
+        assert!(!receiver.ring_pool.contains(&Ring::Void));
+    }
+
+    #[test]
+    fn refuse_found_rings() {
+        let mut sender = Game::new();
+        sender.ring_pool.remove(&Ring::Void);
+        sender.add_item(Box::new(Ring::Void));
+        let package = pack(&mut sender, "a", &[Key::Ring(Ring::Void)], 0).unwrap();
+
+        // the receiver already found its own
+        let mut receiver = Game::new();
+        receiver.ring_pool.remove(&Ring::Void);
+        let unpacked = unpack(&mut receiver, package);
+        assert_eq!(vec![Ring::Void], unpacked.refused);
+        assert!(receiver.inventory().is_empty());
+        assert!(!receiver.ring_pool.contains(&Ring::Void));
+    }
+
+    #[test]
+    fn invalid_escrow() {
+        let file = std::env::temp_dir().join(format!("rpg-escrow-{}", std::process::id()));
+        assert!(read(&file).unwrap().is_empty());
+
+        fs::write(&file, "not an escrow").unwrap();
+        assert!(read(&file).is_err());
+
+        let mut sender = Game::new();
+        sender.gold = 10;
+        let package = pack(&mut sender, "a", &[], 10).unwrap();
+        fs::write(&file, serde_json::to_vec(&[package]).unwrap()).unwrap();
+        assert_eq!(10, read(&file).unwrap()[0].gold);
+        fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,368 @@
+use crate::command::{self, Command};
+use anyhow::{anyhow, bail, Result};
+use clap::{AppSettings, Clap};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, LineGauge, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use rpg_cli::game::Game;
+use rpg_cli::item::key::Key;
+use rpg_cli::theme::{self, Role};
+use rpg_cli::{datafile, i18n, log, randomizer, world};
+
+const HELP: &str = "Type a command, e.g. `cd ~/dev` or `use potion`, and press enter. \
+                    Esc quits, page up and down scroll this log.";
+
+/// Lines scrolled by page up and down.
+const SCROLL_LINES: usize = 5;
+
+/// A command typed at the TUI, parsed as on the command line.
+#[derive(Clap)]
+#[clap(setting = AppSettings::NoBinaryName)]
+struct Input {
+    #[clap(subcommand)]
+    cmd: Command,
+}
+
+/// The state of the interface, besides the game itself.
+#[derive(Default)]
+struct Tui {
+    input: String,
+    /// The output of the commands run so far.
+    output: Vec<String>,
+    /// Lines of output scrolled back from the latest one.
+    scroll: usize,
+    /// The events of the last battle fought.
+    battle: Vec<String>,
+}
+
+/// Run the full-screen interface, which shows the hero, inventory, map and
+/// last battle, and takes commands typed as on the command line. The game
+/// is saved after each command, same as when running them from the shell.
+pub fn run(game: &mut Game) -> Result<()> {
+    // the game output is shown in the log pane instead of printed
+    log::capture(true);
+    colored::control::set_override(false);
+
+    let mut tui = Tui::default();
+    tui.output.push(String::from(HELP));
+    let mut terminal = ratatui::init();
+    let result = tui.run(&mut terminal, game);
+    ratatui::restore();
+
+    log::capture(false);
+    result
+}
+
+impl Tui {
+    fn run(&mut self, terminal: &mut DefaultTerminal, game: &mut Game) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame, game))?;
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            match key.code {
+                KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(())
+                }
+                KeyCode::Char(c) => self.input.push(c),
+                KeyCode::Backspace => {
+                    self.input.pop();
+                }
+                KeyCode::PageUp => {
+                    self.scroll = std::cmp::min(self.scroll + SCROLL_LINES, self.output.len())
+                }
+                KeyCode::PageDown => self.scroll = self.scroll.saturating_sub(SCROLL_LINES),
+                KeyCode::Enter => {
+                    let line = std::mem::take(&mut self.input);
+                    match line.trim() {
+                        "quit" | "exit" => return Ok(()),
+                        "" => {}
+                        line => self.execute(game, line)?,
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Run the command line on the game and save it, keeping the output and
+    /// the last battle fought to show them.
+    fn execute(&mut self, game: &mut Game, line: &str) -> Result<()> {
+        self.output.push(format!("> {}", line));
+        let result = parse(line).and_then(|cmd| {
+            randomizer::init(game.command_seed());
+            command::run(Some(cmd), game)
+        });
+        if let Err(err) = result {
+            log::error(&err.to_string());
+        }
+
+        datafile::save(game)?;
+        let battles = log::recorded_battles();
+        if let Some(battle) = battles.last() {
+            self.battle = log::battle_lines(battle);
+        }
+        datafile::save_battles(battles)?;
+
+        self.output.extend(log::captured());
+        self.scroll = 0;
+        Ok(())
+    }
+
+    fn draw(&self, frame: &mut Frame, game: &Game) {
+        let [main, input] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(3)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)])
+                .areas(main);
+        let [stats, inventory] =
+            Layout::vertical([Constraint::Length(11), Constraint::Min(0)]).areas(left);
+        let [map, bottom] =
+            Layout::vertical([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(right);
+        let [battle, output] =
+            Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .areas(bottom);
+
+        draw_stats(frame, stats, game);
+        frame.render_widget(pane("inventory", inventory_lines(game)), inventory);
+        frame.render_widget(pane("map", world::render(&world::map(game))), map);
+        frame.render_widget(pane("battle", self.battle.clone()), battle);
+        frame.render_widget(pane("log", self.visible_output(output.height)), output);
+
+        let prompt = format!("> {}", self.input);
+        let cursor = (input.x + 1 + prompt.chars().count() as u16, input.y + 1);
+        frame.render_widget(pane("command", vec![prompt]), input);
+        frame.set_cursor_position(cursor);
+    }
+
+    /// The output lines that fit in a pane of the given height, taking the
+    /// scroll into account.
+    fn visible_output(&self, height: u16) -> Vec<String> {
+        let end = self.output.len() - std::cmp::min(self.scroll, self.output.len());
+        let start = end.saturating_sub(height.saturating_sub(2) as usize);
+        self.output[start..end].to_vec()
+    }
+}
+
+/// Parse a command line typed at the TUI. The commands that read from the
+/// terminal, or that only make sense from the shell, aren't available.
+fn parse(line: &str) -> Result<Command> {
+    let input = Input::try_parse_from(line.split_whitespace())
+        .map_err(|err| anyhow!(err.to_string().trim_end().to_string()))?;
+    match input.cmd {
+        Command::Tui
+        | Command::Daemon
+        | Command::Prompt { .. }
+        | Command::Completions { .. }
+        | Command::Complete { .. }
+        | Command::Migrate { .. }
+        | Command::Battle {
+            interactive: true, ..
+        }
+        | Command::Inspect { choose: true }
+        | Command::Talk { reply: None } => {
+            bail!("{} can't be run from the tui.", line.trim())
+        }
+        // the refunded stone points are prompted for after using it
+        Command::Use { ref items, .. }
+            if items
+                .iter()
+                .any(|item| Key::from(item).ok() == Some(Key::ForgetStone)) =>
+        {
+            bail!(
+                "{} can't be run from the tui, use --spend instead.",
+                line.trim()
+            )
+        }
+        Command::Use {
+            ref items,
+            yes: false,
+            ..
+        } if items
+            .iter()
+            .any(|item| Key::from(item).ok() == Some(Key::ChaosStone)) =>
+        {
+            bail!("{} needs confirmation, run it with --yes.", line.trim())
+        }
+        cmd => Ok(cmd),
+    }
+}
+
+fn draw_stats(frame: &mut Frame, area: Rect, game: &Game) {
+    let player = &game.player;
+    let block = Block::bordered().title("hero");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let [name, hp, mp, xp, details] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Length(1),
+        Constraint::Min(0),
+    ])
+    .areas(inner);
+
+    let mut title = format!("{}[{}]", i18n::name(&player.name()), player.level);
+    if let Some(rank) = player.title() {
+        title.push_str(&format!(" {}", rank));
+    }
+    for status in player.status_effects.iter() {
+        title.push_str(&format!(" {:?}", status.effect).to_lowercase());
+    }
+    frame.render_widget(Paragraph::new(title), name);
+    frame.render_widget(
+        gauge("hp", player.current_hp, player.max_hp(), Role::Hp),
+        hp,
+    );
+    frame.render_widget(
+        gauge("mp", player.current_mp, player.max_mp(), Role::Mp),
+        mp,
+    );
+    frame.render_widget(gauge("xp", player.xp, player.xp_for_next(), Role::Xp), xp);
+
+    let lines = vec![
+        format!(
+            "att:{} mag:{} def:{} spd:{}",
+            player.physical_attack(),
+            player.magic_attack(),
+            player.deffense(),
+            player.speed()
+        ),
+        format!("gold: {}g", game.gold),
+        format!("at {}", game.location),
+        game.clock.to_string(),
+    ];
+    let lines: Vec<Line> = lines.into_iter().map(Line::from).collect();
+    frame.render_widget(Paragraph::new(lines), details);
+}
+
+fn gauge(label: &str, current: i32, total: i32, role: Role) -> LineGauge<'static> {
+    let ratio = if total > 0 {
+        (current as f64 / total as f64).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    LineGauge::default()
+        .label(format!("{} {}/{}", label, current, total))
+        .ratio(ratio)
+        .filled_style(Style::default().fg(color(role)))
+}
+
+/// The terminal color of the role in the configured theme.
+fn color(role: Role) -> Color {
+    use colored::Color as Theme;
+    match theme::color(role) {
+        None => Color::Reset,
+        Some(Theme::Black) => Color::Black,
+        Some(Theme::Red) => Color::Red,
+        Some(Theme::Green) => Color::Green,
+        Some(Theme::Yellow) => Color::Yellow,
+        Some(Theme::Blue) => Color::Blue,
+        Some(Theme::Magenta) => Color::Magenta,
+        Some(Theme::Cyan) => Color::Cyan,
+        Some(Theme::White) => Color::Gray,
+        Some(Theme::BrightBlack) => Color::DarkGray,
+        Some(Theme::BrightRed) => Color::LightRed,
+        Some(Theme::BrightGreen) => Color::LightGreen,
+        Some(Theme::BrightYellow) => Color::LightYellow,
+        Some(Theme::BrightBlue) => Color::LightBlue,
+        Some(Theme::BrightMagenta) => Color::LightMagenta,
+        Some(Theme::BrightCyan) => Color::LightCyan,
+        Some(Theme::BrightWhite) => Color::White,
+        Some(Theme::TrueColor { r, g, b }) => Color::Rgb(r, g, b),
+    }
+}
+
+/// The equipment of the hero followed by the inventory items.
+fn inventory_lines(game: &Game) -> Vec<String> {
+    let player = &game.player;
+    let mut lines: Vec<String> = [
+        &player.sword,
+        &player.shield,
+        &player.helmet,
+        &player.armor,
+        &player.boots,
+    ]
+    .iter()
+    .filter_map(|piece| piece.as_ref().map(|piece| i18n::name(&piece.to_string())))
+    .collect();
+    for ring in [&player.left_ring, &player.right_ring]
+        .iter()
+        .filter_map(|ring| ring.as_ref())
+    {
+        lines.push(i18n::name(&ring.to_string()));
+    }
+
+    let mut items: Vec<String> = game
+        .inventory()
+        .iter()
+        .map(|(key, count)| format!("{}x{}", i18n::name(&key.to_string()), count))
+        .collect();
+    items.sort();
+    if !lines.is_empty() && !items.is_empty() {
+        lines.push(String::new());
+    }
+    lines.extend(items);
+    lines
+}
+
+fn pane(title: &str, lines: Vec<String>) -> Paragraph<'static> {
+    let lines: Vec<Line> = lines.into_iter().map(Line::from).collect();
+    Paragraph::new(lines).block(Block::bordered().title(title.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn test_draw() {
+        let mut game = Game::new();
+        game.add_item(Box::new(rpg_cli::item::Potion::new(1)));
+        let tui = Tui {
+            output: vec![String::from("> ls"), String::from("found a chest")],
+            ..Tui::default()
+        };
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|frame| tui.draw(frame, &game)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+
+        assert!(screen.contains("warrior[1]"));
+        assert!(screen.contains("potionx1"));
+        assert!(screen.contains("home [0] @"));
+        assert!(screen.contains("found a chest"));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert!(matches!(parse("use potion"), Ok(Command::Use { .. })));
+        assert!(matches!(
+            parse("cd -f ~/dev"),
+            Ok(Command::ChangeDir { .. })
+        ));
+        assert!(parse("battle --interactive").is_err());
+        assert!(parse("use forget-stone").is_err());
+        assert!(parse("use --spend hp,str").is_ok());
+        assert!(parse("use chaos").is_err());
+        assert!(parse("use chaos --yes").is_ok());
+        assert!(parse("talk").is_err());
+        assert!(parse("talk 1").is_ok());
+        assert!(parse("tui").is_err());
+        assert!(parse("fly away").is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::character::class::Class;
+use crate::game::Game;
+use crate::location::Location;
+use std::fmt;
+
+/// A directory known to the player, as shown in the world map. Its children
+/// are the known directories below it, each named by the path relative to
+/// this one, so unexplored directories in between are collapsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Site {
+    pub location: Location,
+    pub name: String,
+    pub distance: i32,
+    pub marks: Vec<Mark>,
+    pub children: Vec<Site>,
+}
+
+/// Something of note about a site.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mark {
+    /// The hero is currently there.
+    Hero,
+    /// A waypoint with the given name.
+    Waypoint(String),
+    /// The tombstone of a dead hero, waiting to be picked up.
+    Tomb,
+    /// A chest that wasn't completely picked up.
+    Chest,
+    /// The boss guarding the distance, and whether it was beaten by the hero.
+    Boss(String, bool),
+}
+
+impl fmt::Display for Mark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mark::Hero => write!(f, "@"),
+            Mark::Waypoint(name) => write!(f, "waypoint:{}", name),
+            Mark::Tomb => write!(f, "tomb"),
+            Mark::Chest => write!(f, "chest"),
+            Mark::Boss(name, false) => write!(f, "boss:{}", name),
+            Mark::Boss(name, true) => write!(f, "boss:{}(beaten)", name),
+        }
+    }
+}
+
+/// Build the world map from the directories visited by the hero, plus home
+/// and the locations of the waypoints, tombstones and chests known to the
+/// player. Directories outside of home end up in separate trees.
+pub fn map(game: &Game) -> Vec<Site> {
+    let mut locations: Vec<Location> = game.visited().iter().cloned().collect();
+    locations.push(Location::home());
+    locations.push(game.location.clone());
+    locations.extend(game.waypoints.values().cloned());
+    let keys = game.tombstones.keys().chain(game.chests.keys());
+    locations.extend(keys.filter_map(|key| known(key)));
+
+    // ancestors sort before their descendants, so parents are placed first
+    locations.sort_by_key(|location| location.path_string());
+    locations.dedup();
+
+    let mut sites = Vec::new();
+    for location in locations {
+        let site = site(game, location);
+        insert(&mut sites, None, site);
+    }
+    sites
+}
+
+/// Render the world map as an ASCII tree, one line per site.
+pub fn render(sites: &[Site]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for site in sites {
+        lines.push(describe(site));
+        render_children(&site.children, "", &mut lines);
+    }
+    lines
+}
+
+fn render_children(sites: &[Site], prefix: &str, lines: &mut Vec<String>) {
+    for (index, site) in sites.iter().enumerate() {
+        let last = index == sites.len() - 1;
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        lines.push(format!("{}{}{}", prefix, branch, describe(site)));
+        render_children(&site.children, &format!("{}{}", prefix, indent), lines);
+    }
+}
+
+fn describe(site: &Site) -> String {
+    let mut line = format!("{} [{}]", site.name, site.distance);
+    for mark in &site.marks {
+        line.push_str(&format!(" {}", mark));
+    }
+    line
+}
+
+fn site(game: &Game, location: Location) -> Site {
+    let distance = location.distance_from_home().len();
+    let key = location.to_string();
+    let mut marks = Vec::new();
+    if location == game.location {
+        marks.push(Mark::Hero);
+    }
+    for (name, waypoint) in &game.waypoints {
+        if *waypoint == location {
+            marks.push(Mark::Waypoint(name.clone()));
+        }
+    }
+    if game.tombstones.contains_key(&key) {
+        marks.push(Mark::Tomb);
+    }
+    if game.chests.contains_key(&key) {
+        marks.push(Mark::Chest);
+    }
+    if let Some(boss) = Class::boss(distance) {
+        if game.bestiary.seen(&boss.name) {
+            let beaten = game.bosses_beaten.contains(&distance);
+            marks.push(Mark::Boss(boss.name.clone(), beaten));
+        }
+    }
+
+    Site {
+        name: key,
+        location,
+        distance,
+        marks,
+        children: Vec::new(),
+    }
+}
+
+/// Place the site below the deepest one containing its location, naming it
+/// after the path relative to that parent.
+fn insert(sites: &mut Vec<Site>, parent: Option<&Location>, mut site: Site) {
+    match sites
+        .iter_mut()
+        .find(|other| site.location.is_below(&other.location))
+    {
+        Some(other) => {
+            let location = other.location.clone();
+            insert(&mut other.children, Some(&location), site)
+        }
+        None => {
+            // home keeps its name so it's easy to spot
+            if let Some(parent) = parent.filter(|_| !site.location.is_home()) {
+                site.name = site.location.relative_to(parent);
+            }
+            sites.push(site);
+        }
+    }
+}
+
+/// The location of a tombstone or chest, which are kept by location name.
+/// Directories that no longer exist are left out.
+fn known(key: &str) -> Option<Location> {
+    if key == "home" {
+        Some(Location::home())
+    } else {
+        Location::from(key).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::chest::Chest;
+    use crate::location::tests::location_from;
+
+    #[test]
+    fn test_map() {
+        let mut game = Game::new();
+        let home = Location::home().path_string();
+        let at = |path: &str| location_from(&format!("{}/{}", home, path));
+
+        game.visit(at("a")).unwrap();
+        game.visit(at("a/b/c")).unwrap();
+        game.visit(at("d")).unwrap();
+        game.waypoints.insert(String::from("deep"), at("a/b/c"));
+        game.chests.insert(at("a").to_string(), Chest::default());
+
+        let lines = render(&map(&game));
+        let expected = vec![
+            "home [0]".to_string(),
+            "├── a [1] chest".to_string(),
+            "│   └── b/c [3] waypoint:deep".to_string(),
+            "└── d [1] @".to_string(),
+        ];
+        assert_eq!(expected, lines);
+    }
+}